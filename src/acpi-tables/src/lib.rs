@@ -10,14 +10,22 @@ pub mod aml;
 pub mod dsdt;
 pub mod fadt;
 pub mod madt;
+pub mod mcfg;
 pub mod rsdp;
+pub mod slit;
+pub mod srat;
+pub mod wdat;
 pub mod xsdt;
 
 pub use aml::Aml;
 pub use dsdt::Dsdt;
 pub use fadt::Fadt;
 pub use madt::Madt;
+pub use mcfg::Mcfg;
 pub use rsdp::Rsdp;
+pub use slit::Slit;
+pub use srat::Srat;
+pub use wdat::Wdat;
 pub use xsdt::Xsdt;
 use zerocopy::little_endian::{U32, U64};
 use zerocopy::{Immutable, IntoBytes};
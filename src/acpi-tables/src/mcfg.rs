@@ -0,0 +1,107 @@
+// Copyright 2025 Amazon.com, Inc. or its affiliates. All Rights Reserved.
+//
+// SPDX-License-Identifier: Apache-2.0
+
+use std::mem::size_of;
+
+use vm_memory::{Bytes, GuestAddress, GuestMemory};
+use zerocopy::little_endian::{U16, U32, U64};
+use zerocopy::{Immutable, IntoBytes};
+
+use crate::{Result, Sdt, SdtHeader, checksum};
+
+// clippy doesn't understand that we actually "use" the fields of this struct when we serialize
+// them as bytes in guest memory, so here we just ignore dead code to avoid having to name
+// everything with an underscore prefix
+#[allow(dead_code)]
+#[repr(C, packed)]
+#[derive(Clone, Copy, Debug, Default, IntoBytes, Immutable)]
+struct ConfigurationSpaceAllocation {
+    base_address: U64,
+    pci_segment_group: U16,
+    start_bus: u8,
+    end_bus: u8,
+    reserved: U32,
+}
+
+/// PCI Express Memory Mapped Configuration Space Base Address Description Table (MCFG)
+///
+/// This table describes the location of the ECAM regions through which the configuration space
+/// of PCI Express functions is accessed. It is defined by the PCI Firmware Specification and
+/// referenced from the ACPI specification:
+/// https://uefi.org/specs/ACPI/6.5/05_ACPI_Software_Programming_Model.html#description-header-signatures-for-tables-defined-by-acpi
+#[allow(dead_code)]
+#[repr(C, packed)]
+#[derive(Clone, Copy, Debug, IntoBytes, Immutable)]
+pub struct Mcfg {
+    header: SdtHeader,
+    reserved: [u8; 8],
+    allocation: ConfigurationSpaceAllocation,
+}
+
+impl Mcfg {
+    /// Creates an MCFG describing the ECAM region at `base_address` for the buses `start_bus` to
+    /// `end_bus` of PCI segment 0.
+    pub fn new(
+        oem_id: [u8; 6],
+        oem_table_id: [u8; 8],
+        oem_revision: u32,
+        base_address: u64,
+        start_bus: u8,
+        end_bus: u8,
+    ) -> Self {
+        let header = SdtHeader::new(
+            *b"MCFG",
+            // It is ok to unwrap, the table is 60 bytes long.
+            size_of::<Mcfg>().try_into().unwrap(),
+            1,
+            oem_id,
+            oem_table_id,
+            oem_revision,
+        );
+
+        let mut mcfg = Mcfg {
+            header,
+            reserved: [0; 8],
+            allocation: ConfigurationSpaceAllocation {
+                base_address: U64::new(base_address),
+                pci_segment_group: U16::ZERO,
+                start_bus,
+                end_bus,
+                reserved: U32::ZERO,
+            },
+        };
+
+        mcfg.header.checksum = checksum(&[mcfg.as_bytes()]);
+        mcfg
+    }
+}
+
+impl Sdt for Mcfg {
+    fn len(&self) -> usize {
+        self.as_bytes().len()
+    }
+
+    fn write_to_guest<M: GuestMemory>(&mut self, mem: &M, address: GuestAddress) -> Result<()> {
+        mem.write_slice(self.as_bytes(), address)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use zerocopy::IntoBytes;
+
+    use super::Mcfg;
+    use crate::checksum;
+
+    #[test]
+    fn test_mcfg() {
+        let mcfg = Mcfg::new(*b"FCVMMC", *b"FCMVMCFG", 1, 0xfff0_0000, 0, 0);
+        let bytes = mcfg.as_bytes();
+        assert_eq!(bytes.len(), 60);
+        assert_eq!(&bytes[0..4], b"MCFG");
+        assert_eq!(&bytes[44..52], &0xfff0_0000u64.to_le_bytes());
+        assert_eq!(checksum(&[bytes]), 0);
+    }
+}
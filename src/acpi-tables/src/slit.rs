@@ -0,0 +1,100 @@
+// Copyright 2025 Amazon.com, Inc. or its affiliates. All Rights Reserved.
+//
+// SPDX-License-Identifier: Apache-2.0
+
+use std::mem::size_of;
+
+use vm_memory::{Address, Bytes, GuestAddress, GuestMemory};
+use zerocopy::little_endian::U64;
+use zerocopy::{Immutable, IntoBytes};
+
+use crate::{AcpiError, Result, Sdt, SdtHeader, checksum};
+
+// clippy doesn't understand that we actually "use" the fields of this struct when we serialize
+// them as bytes in guest memory, so here we just ignore dead code to avoid having to name
+// everything with an underscore prefix
+#[allow(dead_code)]
+#[repr(C, packed)]
+#[derive(Debug, IntoBytes, Immutable)]
+struct SlitHeader {
+    sdt: SdtHeader,
+    number_of_localities: U64,
+}
+
+/// System Locality Information Table (SLIT)
+///
+/// This table provides the relative distances between the proximity domains, i.e. NUMA nodes,
+/// described by the SRAT. More information about this table can be found in the ACPI
+/// specification:
+/// https://uefi.org/specs/ACPI/6.5/05_ACPI_Software_Programming_Model.html#system-locality-information-table-slit
+#[derive(Debug)]
+pub struct Slit {
+    header: SlitHeader,
+    distances: Vec<u8>,
+}
+
+impl Slit {
+    /// Creates a SLIT for `number_of_localities` proximity domains, where `distances` is the
+    /// row-major matrix of the distances between them.
+    pub fn new(
+        oem_id: [u8; 6],
+        oem_table_id: [u8; 8],
+        oem_revision: u32,
+        number_of_localities: u64,
+        distances: Vec<u8>,
+    ) -> Self {
+        let length = size_of::<SlitHeader>() + distances.len();
+        let sdt_header = SdtHeader::new(
+            *b"SLIT",
+            // It is ok to unwrap, there are at most a few dozen localities.
+            length.try_into().unwrap(),
+            1,
+            oem_id,
+            oem_table_id,
+            oem_revision,
+        );
+
+        let mut header = SlitHeader {
+            sdt: sdt_header,
+            number_of_localities: U64::new(number_of_localities),
+        };
+
+        header.sdt.checksum = checksum(&[header.as_bytes(), distances.as_bytes()]);
+
+        Slit { header, distances }
+    }
+}
+
+impl Sdt for Slit {
+    fn len(&self) -> usize {
+        self.header.sdt.length.get().try_into().unwrap()
+    }
+
+    fn write_to_guest<M: GuestMemory>(&mut self, mem: &M, address: GuestAddress) -> Result<()> {
+        mem.write_slice(self.header.as_bytes(), address)?;
+        let address = address
+            .checked_add(size_of::<SlitHeader>() as u64)
+            .ok_or(AcpiError::InvalidGuestAddress)?;
+        mem.write_slice(self.distances.as_bytes(), address)?;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_slit() {
+        let slit = Slit::new(*b"FCVMSL", *b"FCVMSLIT", 1, 2, vec![10, 20, 20, 10]);
+        assert_eq!(slit.len(), 44 + 4);
+
+        let bytes = [slit.header.as_bytes(), slit.distances.as_bytes()].concat();
+        assert_eq!(bytes.len(), slit.len());
+        assert_eq!(&bytes[0..4], b"SLIT");
+        assert_eq!(&bytes[36..44], &2u64.to_le_bytes());
+        assert_eq!(&bytes[44..48], &[10, 20, 20, 10]);
+        assert_eq!(checksum(&[&bytes]), 0);
+    }
+}
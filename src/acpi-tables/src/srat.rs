@@ -0,0 +1,182 @@
+// Copyright 2025 Amazon.com, Inc. or its affiliates. All Rights Reserved.
+//
+// SPDX-License-Identifier: Apache-2.0
+
+use std::mem::size_of;
+
+use vm_memory::{Address, Bytes, GuestAddress, GuestMemory};
+use zerocopy::little_endian::{U16, U32, U64};
+use zerocopy::{Immutable, IntoBytes};
+
+use crate::{AcpiError, Result, Sdt, SdtHeader, checksum};
+
+const SRAT_AFFINITY_ENABLED_FLAG: u32 = 0;
+
+// clippy doesn't understand that we actually "use" the fields of this struct when we serialize
+// them as bytes in guest memory, so here we just ignore dead code to avoid having to name
+// everything with an underscore prefix
+#[allow(dead_code)]
+#[repr(C, packed)]
+#[derive(Copy, Clone, Debug, Default, IntoBytes, Immutable)]
+pub struct ProcessorLocalApicAffinity {
+    r#type: u8,
+    length: u8,
+    proximity_domain_low: u8,
+    apic_id: u8,
+    flags: U32,
+    local_sapic_eid: u8,
+    proximity_domain_high: [u8; 3],
+    clock_domain: U32,
+}
+
+impl ProcessorLocalApicAffinity {
+    pub fn new(proximity_domain: u32, apic_id: u8) -> Self {
+        let [low, high @ ..] = proximity_domain.to_le_bytes();
+        Self {
+            r#type: 0,
+            length: 16,
+            proximity_domain_low: low,
+            apic_id,
+            flags: U32::new(1u32 << SRAT_AFFINITY_ENABLED_FLAG),
+            local_sapic_eid: 0,
+            proximity_domain_high: high,
+            clock_domain: U32::ZERO,
+        }
+    }
+}
+
+// clippy doesn't understand that we actually "use" the fields of this struct when we serialize
+// them as bytes in guest memory, so here we just ignore dead code to avoid having to name
+// everything with an underscore prefix
+#[allow(dead_code)]
+#[repr(C, packed)]
+#[derive(Copy, Clone, Debug, Default, IntoBytes, Immutable)]
+pub struct MemoryAffinity {
+    r#type: u8,
+    length: u8,
+    proximity_domain: U32,
+    reserved0: U16,
+    base_address: U64,
+    range_length: U64,
+    reserved1: U32,
+    flags: U32,
+    reserved2: U64,
+}
+
+impl MemoryAffinity {
+    pub fn new(proximity_domain: u32, base_address: u64, range_length: u64) -> Self {
+        Self {
+            r#type: 1,
+            length: 40,
+            proximity_domain: U32::new(proximity_domain),
+            reserved0: U16::ZERO,
+            base_address: U64::new(base_address),
+            range_length: U64::new(range_length),
+            reserved1: U32::ZERO,
+            flags: U32::new(1u32 << SRAT_AFFINITY_ENABLED_FLAG),
+            reserved2: U64::ZERO,
+        }
+    }
+}
+
+// clippy doesn't understand that we actually "use" the fields of this struct when we serialize
+// them as bytes in guest memory, so here we just ignore dead code to avoid having to name
+// everything with an underscore prefix
+#[allow(dead_code)]
+#[repr(C, packed)]
+#[derive(Debug, IntoBytes, Immutable)]
+struct SratHeader {
+    sdt: SdtHeader,
+    reserved0: U32,
+    reserved1: U64,
+}
+
+/// System Resource Affinity Table (SRAT)
+///
+/// This table associates processors and memory ranges with proximity domains, i.e. NUMA nodes.
+/// More information about this table can be found in the ACPI specification:
+/// https://uefi.org/specs/ACPI/6.5/05_ACPI_Software_Programming_Model.html#system-resource-affinity-table-srat
+#[derive(Debug)]
+pub struct Srat {
+    header: SratHeader,
+    affinity_structures: Vec<u8>,
+}
+
+impl Srat {
+    pub fn new(
+        oem_id: [u8; 6],
+        oem_table_id: [u8; 8],
+        oem_revision: u32,
+        affinity_structures: Vec<u8>,
+    ) -> Self {
+        let length = size_of::<SratHeader>() + affinity_structures.len();
+        let sdt_header = SdtHeader::new(
+            *b"SRAT",
+            // It is ok to unwrap, there is one affinity structure per vCPU and NUMA memory range.
+            length.try_into().unwrap(),
+            3,
+            oem_id,
+            oem_table_id,
+            oem_revision,
+        );
+
+        let mut header = SratHeader {
+            sdt: sdt_header,
+            // Reserved to be 1 for backward compatibility.
+            reserved0: U32::new(1),
+            reserved1: U64::ZERO,
+        };
+
+        header.sdt.checksum = checksum(&[header.as_bytes(), affinity_structures.as_bytes()]);
+
+        Srat {
+            header,
+            affinity_structures,
+        }
+    }
+}
+
+impl Sdt for Srat {
+    fn len(&self) -> usize {
+        self.header.sdt.length.get().try_into().unwrap()
+    }
+
+    fn write_to_guest<M: GuestMemory>(&mut self, mem: &M, address: GuestAddress) -> Result<()> {
+        mem.write_slice(self.header.as_bytes(), address)?;
+        let address = address
+            .checked_add(size_of::<SratHeader>() as u64)
+            .ok_or(AcpiError::InvalidGuestAddress)?;
+        mem.write_slice(self.affinity_structures.as_bytes(), address)?;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_srat() {
+        let mut affinity_structures = Vec::new();
+        affinity_structures.extend(ProcessorLocalApicAffinity::new(0x0102_0304, 2).as_bytes());
+        affinity_structures.extend(MemoryAffinity::new(1, 0x1_0000_0000, 0x4000_0000).as_bytes());
+        let srat = Srat::new(*b"FCVMSR", *b"FCVMSRAT", 1, affinity_structures);
+        assert_eq!(srat.len(), 48 + 16 + 40);
+
+        let bytes = [srat.header.as_bytes(), srat.affinity_structures.as_bytes()].concat();
+        assert_eq!(bytes.len(), srat.len());
+        assert_eq!(&bytes[0..4], b"SRAT");
+        assert_eq!(&bytes[36..40], &1u32.to_le_bytes());
+        // Processor affinity, with the proximity domain split around the APIC ID.
+        assert_eq!(&bytes[48..52], &[0, 16, 0x04, 2]);
+        assert_eq!(&bytes[57..60], &[0x03, 0x02, 0x01]);
+        // Memory affinity
+        assert_eq!(&bytes[64..66], &[1, 40]);
+        assert_eq!(&bytes[66..70], &1u32.to_le_bytes());
+        assert_eq!(&bytes[72..80], &0x1_0000_0000u64.to_le_bytes());
+        assert_eq!(&bytes[80..88], &0x4000_0000u64.to_le_bytes());
+        assert_eq!(&bytes[92..96], &1u32.to_le_bytes());
+        assert_eq!(checksum(&[&bytes]), 0);
+    }
+}
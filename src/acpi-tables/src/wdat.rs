@@ -0,0 +1,228 @@
+// Copyright 2025 Amazon.com, Inc. or its affiliates. All Rights Reserved.
+//
+// SPDX-License-Identifier: Apache-2.0
+
+use std::mem::size_of;
+
+use vm_memory::{Address, Bytes, GuestAddress, GuestMemory};
+use zerocopy::little_endian::{U16, U32};
+use zerocopy::{Immutable, IntoBytes};
+
+use crate::{AcpiError, GenericAddressStructure, Result, Sdt, SdtHeader, checksum};
+
+// Watchdog actions and instructions, as defined by the Hardware Watchdog Timers Design
+// Specification.
+
+/// Restarts the countdown of the watchdog.
+pub const WDAT_ACTION_RESET: u8 = 0x1;
+/// Returns the remaining countdown of the watchdog.
+pub const WDAT_ACTION_QUERY_CURRENT_COUNTDOWN_PERIOD: u8 = 0x4;
+/// Returns the countdown the watchdog is restarted with.
+pub const WDAT_ACTION_QUERY_COUNTDOWN_PERIOD: u8 = 0x5;
+/// Sets the countdown the watchdog is restarted with.
+pub const WDAT_ACTION_SET_COUNTDOWN_PERIOD: u8 = 0x6;
+/// Returns whether the watchdog is running.
+pub const WDAT_ACTION_QUERY_RUNNING_STATE: u8 = 0x8;
+/// Starts the watchdog.
+pub const WDAT_ACTION_SET_RUNNING_STATE: u8 = 0x9;
+/// Returns whether the watchdog is stopped.
+pub const WDAT_ACTION_QUERY_STOPPED_STATE: u8 = 0xA;
+/// Stops the watchdog.
+pub const WDAT_ACTION_SET_STOPPED_STATE: u8 = 0xB;
+
+/// Reads a register, and compares the masked value with the value of the instruction.
+pub const WDAT_INSTRUCTION_READ_VALUE: u8 = 0x0;
+/// Reads the masked value of a register.
+pub const WDAT_INSTRUCTION_READ_COUNTDOWN: u8 = 0x1;
+/// Writes the masked value of the instruction to a register.
+pub const WDAT_INSTRUCTION_WRITE_VALUE: u8 = 0x2;
+/// Writes the masked parameter of the action to a register.
+pub const WDAT_INSTRUCTION_WRITE_COUNTDOWN: u8 = 0x3;
+/// Flag preserving the bits of the register outside of the mask when writing it.
+pub const WDAT_INSTRUCTION_PRESERVE_REGISTER: u8 = 0x80;
+
+/// Flag for an enabled watchdog.
+pub const WDAT_F_ENABLED: u8 = 0x1;
+
+// The watchdog is not a PCI device
+const WDAT_NO_PCI: u8 = 0xff;
+
+/// An instruction entry of the WDAT, describing how to perform (a step of) a watchdog action
+/// through a register of the watchdog.
+// clippy doesn't understand that we actually "use" the fields of this struct when we serialize
+// them as bytes in guest memory, so here we just ignore dead code to avoid having to name
+// everything with an underscore prefix
+#[allow(dead_code)]
+#[repr(C, packed)]
+#[derive(Clone, Copy, Debug, Default, IntoBytes, Immutable)]
+pub struct WatchdogInstruction {
+    action: u8,
+    instruction_flags: u8,
+    reserved: U16,
+    register_region: GenericAddressStructure,
+    value: U32,
+    mask: U32,
+}
+
+impl WatchdogInstruction {
+    pub fn new(
+        action: u8,
+        instruction_flags: u8,
+        register_region: GenericAddressStructure,
+        value: u32,
+        mask: u32,
+    ) -> Self {
+        Self {
+            action,
+            instruction_flags,
+            reserved: U16::ZERO,
+            register_region,
+            value: U32::new(value),
+            mask: U32::new(mask),
+        }
+    }
+}
+
+// clippy doesn't understand that we actually "use" the fields of this struct when we serialize
+// them as bytes in guest memory, so here we just ignore dead code to avoid having to name
+// everything with an underscore prefix
+#[allow(dead_code)]
+#[repr(C, packed)]
+#[derive(Clone, Copy, Debug, IntoBytes, Immutable)]
+struct WdatHeader {
+    sdt: SdtHeader,
+    watchdog_header_length: U32,
+    pci_segment: U16,
+    pci_bus: u8,
+    pci_device: u8,
+    pci_function: u8,
+    reserved0: [u8; 3],
+    timer_period: U32,
+    max_count: U32,
+    min_count: U32,
+    flags: u8,
+    reserved1: [u8; 3],
+    number_of_entries: U32,
+}
+
+/// Watchdog Action Table (WDAT)
+///
+/// This table describes a hardware watchdog timer, as a list of instructions performing each of
+/// the watchdog actions through the registers of the watchdog. It is defined by the Hardware
+/// Watchdog Timers Design Specification and referenced from the ACPI specification:
+/// https://uefi.org/specs/ACPI/6.5/05_ACPI_Software_Programming_Model.html#description-header-signatures-for-tables-defined-by-acpi
+#[derive(Debug)]
+pub struct Wdat {
+    header: WdatHeader,
+    instructions: Vec<WatchdogInstruction>,
+}
+
+impl Wdat {
+    /// Creates a WDAT for an enabled watchdog, whose countdown is expressed in units of
+    /// `timer_period` milliseconds and ranges between `min_count` and `max_count`.
+    pub fn new(
+        oem_id: [u8; 6],
+        oem_table_id: [u8; 8],
+        oem_revision: u32,
+        timer_period: u32,
+        min_count: u32,
+        max_count: u32,
+        instructions: Vec<WatchdogInstruction>,
+    ) -> Self {
+        let length =
+            size_of::<WdatHeader>() + instructions.len() * size_of::<WatchdogInstruction>();
+        let sdt_header = SdtHeader::new(
+            *b"WDAT",
+            // It is ok to unwrap, there are only a handful of instructions per watchdog action.
+            length.try_into().unwrap(),
+            1,
+            oem_id,
+            oem_table_id,
+            oem_revision,
+        );
+
+        let mut header = WdatHeader {
+            sdt: sdt_header,
+            // The watchdog header is everything after the SDT header, up to the instructions.
+            watchdog_header_length: U32::new(
+                (size_of::<WdatHeader>() - size_of::<SdtHeader>())
+                    .try_into()
+                    .unwrap(),
+            ),
+            pci_segment: U16::new(u16::from(WDAT_NO_PCI)),
+            pci_bus: WDAT_NO_PCI,
+            pci_device: WDAT_NO_PCI,
+            pci_function: WDAT_NO_PCI,
+            reserved0: [0; 3],
+            timer_period: U32::new(timer_period),
+            max_count: U32::new(max_count),
+            min_count: U32::new(min_count),
+            flags: WDAT_F_ENABLED,
+            reserved1: [0; 3],
+            number_of_entries: U32::new(instructions.len().try_into().unwrap()),
+        };
+
+        header.sdt.checksum = checksum(&[header.as_bytes(), instructions.as_bytes()]);
+
+        Wdat {
+            header,
+            instructions,
+        }
+    }
+}
+
+impl Sdt for Wdat {
+    fn len(&self) -> usize {
+        self.header.sdt.length.get().try_into().unwrap()
+    }
+
+    fn write_to_guest<M: GuestMemory>(&mut self, mem: &M, address: GuestAddress) -> Result<()> {
+        mem.write_slice(self.header.as_bytes(), address)?;
+        let address = address
+            .checked_add(size_of::<WdatHeader>() as u64)
+            .ok_or(AcpiError::InvalidGuestAddress)?;
+        mem.write_slice(self.instructions.as_bytes(), address)?;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_wdat() {
+        let register = GenericAddressStructure::new(1, 32, 0, 3, 0x530);
+        let instructions = vec![
+            WatchdogInstruction::new(
+                WDAT_ACTION_RESET,
+                WDAT_INSTRUCTION_WRITE_VALUE,
+                register,
+                1,
+                1,
+            ),
+            WatchdogInstruction::new(
+                WDAT_ACTION_QUERY_RUNNING_STATE,
+                WDAT_INSTRUCTION_READ_VALUE,
+                register,
+                1,
+                1,
+            ),
+        ];
+        let wdat = Wdat::new(*b"FCVMWD", *b"FCVMWDAT", 1, 1000, 1, 3600, instructions);
+        assert_eq!(wdat.len(), 68 + 2 * 20);
+
+        let bytes = [wdat.header.as_bytes(), wdat.instructions.as_bytes()].concat();
+        assert_eq!(bytes.len(), wdat.len());
+        assert_eq!(&bytes[0..4], b"WDAT");
+        // Watchdog header length
+        assert_eq!(&bytes[36..40], &32u32.to_le_bytes());
+        // Number of instructions
+        assert_eq!(&bytes[64..68], &2u32.to_le_bytes());
+        // Register of the second instruction
+        assert_eq!(bytes[88], WDAT_ACTION_QUERY_RUNNING_STATE);
+        assert_eq!(&bytes[96..104], &0x530u64.to_le_bytes());
+        assert_eq!(checksum(&[&bytes]), 0);
+    }
+}
@@ -2,6 +2,7 @@
 // SPDX-License-Identifier: Apache-2.0
 
 use serde::Serialize;
+use vmm::cpu_config::templates::CustomCpuTemplate;
 
 use crate::fingerprint::{Fingerprint, FingerprintField};
 
@@ -20,6 +21,17 @@ struct Diff<'a, T: Serialize> {
     curr: &'a T,
 }
 
+#[derive(Serialize)]
+struct GuestCpuConfigDiff<'a> {
+    name: String,
+    prev: &'a CustomCpuTemplate,
+    curr: &'a CustomCpuTemplate,
+    // Fields of the ID registers that differ, to tell apart the CPU features that changed.
+    #[cfg(target_arch = "aarch64")]
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    id_register_fields: Vec<crate::utils::aarch64::IdRegFieldDiff>,
+}
+
 pub fn compare(
     prev: Fingerprint,
     curr: Fingerprint,
@@ -68,10 +80,15 @@ pub fn compare(
                         // This `strip()` call always succeed since the number of inputs is two.
                         let cpu_configs = crate::template::strip::strip(cpu_configs).unwrap();
 
-                        let diff = Diff {
+                        let diff = GuestCpuConfigDiff {
                             name: format!("{filter:#?}"),
                             prev: &cpu_configs[0],
                             curr: &cpu_configs[1],
+                            #[cfg(target_arch = "aarch64")]
+                            id_register_fields: crate::utils::aarch64::id_reg_field_diffs(
+                                &prev.guest_cpu_config,
+                                &curr.guest_cpu_config,
+                            ),
                         };
                         Some(serde_json::to_string_pretty(&diff))
                     } else {
@@ -95,7 +112,6 @@ pub fn compare(
 #[cfg(test)]
 mod tests {
     use clap::ValueEnum;
-    use vmm::cpu_config::templates::CustomCpuTemplate;
 
     use super::*;
 
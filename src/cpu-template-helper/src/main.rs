@@ -26,6 +26,8 @@ enum HelperError {
     /// {0}
     Utils(#[from] utils::UtilsError),
     /// {0}
+    TemplateBaseline(#[from] template::baseline::BaselineError),
+    /// {0}
     TemplateDump(#[from] template::dump::DumpError),
     /// {0}
     TemplateStrip(#[from] template::strip::StripError),
@@ -63,6 +65,10 @@ enum TemplateOperation {
         /// Path of output file.
         #[arg(short, long, value_name = "PATH", default_value = "cpu_config.json")]
         output: PathBuf,
+        /// List the CPUID bits of the known CPU features by name.
+        #[cfg(target_arch = "x86_64")]
+        #[arg(long)]
+        symbolic: bool,
     },
     /// Strip entries shared between multiple CPU template files.
     Strip {
@@ -73,6 +79,21 @@ enum TemplateOperation {
         #[arg(short, long, default_value = "_stripped")]
         suffix: String,
     },
+    /// Generate a CPU template valid on all the hosts of the given fingerprint or CPU template
+    /// dump files, masking the features not available on all of them.
+    Baseline {
+        /// List of paths of input fingerprint or CPU configuration files.
+        #[arg(short, long, value_name = "PATH", num_args = 2..)]
+        paths: Vec<PathBuf>,
+        /// Path of output file.
+        #[arg(
+            short,
+            long,
+            value_name = "PATH",
+            default_value = "baseline_template.json"
+        )]
+        output: PathBuf,
+    },
     /// Verify that the given CPU template file is applied as intended.
     Verify {
         /// Path of firecracker config file.
@@ -125,6 +146,8 @@ fn run(cli: Cli) -> Result<(), HelperError> {
                 config,
                 template,
                 output,
+                #[cfg(target_arch = "x86_64")]
+                symbolic,
             } => {
                 let config = config.map(read_to_string).transpose()?;
                 let template = template
@@ -135,6 +158,14 @@ fn run(cli: Cli) -> Result<(), HelperError> {
 
                 let cpu_config = template::dump::dump(vmm)?;
 
+                #[cfg(target_arch = "x86_64")]
+                let cpu_config_json = match symbolic {
+                    true => serde_json::to_string_pretty(
+                        &template::dump::SymbolicCpuTemplate::from(cpu_config),
+                    )?,
+                    false => serde_json::to_string_pretty(&cpu_config)?,
+                };
+                #[cfg(target_arch = "aarch64")]
                 let cpu_config_json = serde_json::to_string_pretty(&cpu_config)?;
                 write(output, cpu_config_json)?;
             }
@@ -152,6 +183,20 @@ fn run(cli: Cli) -> Result<(), HelperError> {
                     write(path, template_json)?;
                 }
             }
+            TemplateOperation::Baseline { paths, output } => {
+                let configs = paths
+                    .iter()
+                    .map(template::baseline::load_input)
+                    .collect::<Result<Vec<_>, _>>()?;
+
+                let (template, report) = template::baseline::baseline(configs)?;
+
+                for entry in report {
+                    println!("{entry}");
+                }
+                let template_json = serde_json::to_string_pretty(&template)?;
+                write(output, template_json)?;
+            }
             TemplateOperation::Verify { config, template } => {
                 let config = config.map(read_to_string).transpose()?;
                 let template = template
@@ -312,6 +357,32 @@ mod tests {
         run(cli).unwrap();
     }
 
+    #[cfg(target_arch = "x86_64")]
+    #[test]
+    fn test_template_dump_command_symbolic() {
+        let output_file = TempFile::new().unwrap();
+        let args = vec![
+            "cpu-template-helper",
+            "template",
+            "dump",
+            "--output",
+            output_file.as_path().to_str().unwrap(),
+            "--symbolic",
+        ];
+        let cli = Cli::parse_from(args);
+
+        run(cli).unwrap();
+
+        // The symbolic form is a valid CPU template, folding back to the raw form.
+        let template = utils::load_cpu_template(&output_file.as_path().to_path_buf()).unwrap();
+        let (vmm, _) = utils::build_microvm_from_config(None, None).unwrap();
+        let expected = template::dump::dump(vmm).unwrap();
+        assert_eq!(
+            utils::x86_64::CpuidModifierMap::from(template.cpuid_modifiers),
+            utils::x86_64::CpuidModifierMap::from(expected.cpuid_modifiers)
+        );
+    }
+
     #[test]
     fn test_template_strip_command() {
         let files = [generate_sample_template(), generate_sample_template()];
@@ -327,6 +398,32 @@ mod tests {
         run(cli).unwrap();
     }
 
+    #[test]
+    fn test_template_baseline_command() {
+        let files = [generate_sample_template(), generate_sample_fingerprint()];
+        let output_file = TempFile::new().unwrap();
+
+        let mut args = vec![
+            "cpu-template-helper",
+            "template",
+            "baseline",
+            "--output",
+            output_file.as_path().to_str().unwrap(),
+            "-p",
+        ];
+        let paths = files
+            .iter()
+            .map(|file| file.as_path().to_str().unwrap())
+            .collect::<Vec<_>>();
+        args.extend(paths);
+        let cli = Cli::parse_from(args);
+
+        run(cli).unwrap();
+        // The inputs are identical, so there is nothing to mask.
+        let template = utils::load_cpu_template(&output_file.as_path().to_path_buf()).unwrap();
+        assert_eq!(template, Default::default());
+    }
+
     #[test]
     fn test_template_verify_command() {
         let template_file = generate_sample_template();
@@ -0,0 +1,114 @@
+// Copyright 2025 Amazon.com, Inc. or its affiliates. All Rights Reserved.
+// SPDX-License-Identifier: Apache-2.0
+
+use vmm::arch::aarch64::regs::{ID_AA64DFR0_EL1, ID_AA64MMFR0_EL1, ID_AA64PFR0_EL1};
+use vmm::cpu_config::aarch64::custom_cpu_template::RegisterModifier;
+use vmm::cpu_config::templates::CustomCpuTemplate;
+
+use crate::template::baseline::{BaselineError, MergePolicy, ReportEntry, baseline_common};
+use crate::utils::aarch64::{RegModifierMap, RegModifierMapKey};
+
+fn reg_policy(key: &RegModifierMapKey) -> MergePolicy {
+    // The AArch64 feature ID registers are encoded with op0=3, op1=0, CRn=0 and CRm in 4..=7.
+    // As CRm and op2 are the lowest bits of the register IDs, they span the 32 IDs starting from
+    // ID_AA64PFR0_EL1 (CRm=4, op2=0).
+    match (ID_AA64PFR0_EL1..ID_AA64PFR0_EL1 + 32).contains(&key.0) {
+        true => MergePolicy::FeatureFields {
+            signed: signed_fields(key.0),
+        },
+        false => MergePolicy::Unmergeable,
+    }
+}
+
+/// Returns the first bits of the signed fields of a feature ID register, in which 0b1111 means
+/// "not implemented". The other fields are unsigned.
+fn signed_fields(reg: u64) -> &'static [u32] {
+    match reg {
+        // FP and AdvSIMD.
+        ID_AA64PFR0_EL1 => &[16, 20],
+        // DoubleLock.
+        ID_AA64DFR0_EL1 => &[36],
+        // TGran64 and TGran4.
+        ID_AA64MMFR0_EL1 => &[24, 28],
+        _ => &[],
+    }
+}
+
+pub fn baseline(
+    configs: Vec<CustomCpuTemplate>,
+) -> Result<(CustomCpuTemplate, Vec<ReportEntry>), BaselineError> {
+    // Convert `Vec<CustomCpuTemplate>` to `Vec<HashMap<_>>`.
+    let reg_modifiers_maps = configs
+        .into_iter()
+        .map(|config| RegModifierMap::from(config.reg_modifiers).0)
+        .collect::<Vec<_>>();
+
+    let mut report = Vec::new();
+    let reg_modifiers = baseline_common(&reg_modifiers_maps, reg_policy, &mut report)?;
+
+    let template = CustomCpuTemplate {
+        reg_modifiers: Vec::<RegisterModifier>::from(RegModifierMap(reg_modifiers)),
+        ..Default::default()
+    };
+
+    Ok((template, report))
+}
+
+#[cfg(test)]
+mod tests {
+    use vmm::arch::aarch64::regs::{ID_AA64ISAR0_EL1, MIDR_EL1};
+    use vmm::cpu_config::templates::RegisterValueFilter;
+
+    use super::*;
+    use crate::utils::aarch64::reg_modifier;
+
+    // Summary of the inputs:
+    // * The atomic instructions (bits 23:20) of ID_AA64ISAR0_EL1 are only on the first input.
+    // * The RNDR (bits 63:60) of ID_AA64ISAR0_EL1 is at a higher level on the first input.
+    // * The SVE (bits 35:32) of ID_AA64PFR0_EL1 is only on the second input.
+    // * The FP and AdvSIMD (bits 23:16) of ID_AA64PFR0_EL1 are only on the first input.
+    // * MIDR_EL1 differs.
+    #[rustfmt::skip]
+    fn build_input_configs() -> Vec<CustomCpuTemplate> {
+        vec![
+            CustomCpuTemplate {
+                reg_modifiers: vec![
+                    reg_modifier!(ID_AA64PFR0_EL1, 0x0000_0000_1100_0011),
+                    reg_modifier!(ID_AA64ISAR0_EL1, 0xf000_0000_0021_1120),
+                    reg_modifier!(MIDR_EL1, 0x410f_d0c1),
+                ],
+                ..Default::default()
+            },
+            CustomCpuTemplate {
+                reg_modifiers: vec![
+                    reg_modifier!(ID_AA64PFR0_EL1, 0x0000_0001_11ff_0011),
+                    reg_modifier!(ID_AA64ISAR0_EL1, 0x1000_0000_0001_1120),
+                    reg_modifier!(MIDR_EL1, 0x410f_d401),
+                ],
+                ..Default::default()
+            },
+        ]
+    }
+
+    #[test]
+    fn test_baseline() {
+        let (template, report) = baseline(build_input_configs()).unwrap();
+        assert_eq!(
+            template,
+            CustomCpuTemplate {
+                reg_modifiers: vec![
+                    reg_modifier!(ID_AA64PFR0_EL1, 0xff_0000, 0xf_00ff_0000),
+                    reg_modifier!(
+                        ID_AA64ISAR0_EL1,
+                        0x1000_0000_0000_0000,
+                        0xf000_0000_00f0_0000
+                    ),
+                ],
+                ..Default::default()
+            }
+        );
+        assert_eq!(report.len(), 3);
+        // MIDR_EL1 has the lowest ID.
+        assert!(matches!(report[0], ReportEntry::Unmerged { .. }));
+    }
+}
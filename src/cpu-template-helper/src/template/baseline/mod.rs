@@ -0,0 +1,285 @@
+// Copyright 2025 Amazon.com, Inc. or its affiliates. All Rights Reserved.
+// SPDX-License-Identifier: Apache-2.0
+
+use std::collections::HashMap;
+use std::fmt::Debug;
+use std::fs::read_to_string;
+use std::path::PathBuf;
+
+use vmm::cpu_config::templates::{CustomCpuTemplate, Numeric, RegisterValueFilter};
+
+use crate::fingerprint::Fingerprint;
+use crate::utils::ModifierMapKey;
+
+#[cfg(target_arch = "aarch64")]
+mod aarch64;
+#[cfg(target_arch = "aarch64")]
+pub use aarch64::baseline;
+
+#[cfg(target_arch = "x86_64")]
+mod x86_64;
+#[cfg(target_arch = "x86_64")]
+pub use x86_64::baseline;
+
+#[derive(Debug, thiserror::Error, displaydoc::Display)]
+pub enum BaselineError {
+    /// The number of inputs should be two or more.
+    NumberOfInputs,
+    /// Failed to read input file: {0}
+    FileIo(#[from] std::io::Error),
+    /// Input is neither a fingerprint nor a CPU template: {0}
+    Serde(#[from] serde_json::Error),
+}
+
+/// Loads the guest CPU configuration of a fingerprint file or a CPU template file.
+pub fn load_input(path: &PathBuf) -> Result<CustomCpuTemplate, BaselineError> {
+    let json = read_to_string(path)?;
+    match serde_json::from_str::<Fingerprint>(&json) {
+        Ok(fingerprint) => Ok(fingerprint.guest_cpu_config),
+        Err(_) => Ok(serde_json::from_str(&json)?),
+    }
+}
+
+/// How the values of a register are merged across the inputs.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MergePolicy {
+    /// Each bit advertises a feature, which is kept only if available on all the inputs.
+    FeatureBits,
+    /// The value bounds what the guest can query (e.g. the maximum CPUID leaf), and is lowered
+    /// to the smallest value of the inputs.
+    Limit,
+    /// Each 4-bit field advertises the level of a feature (e.g. the aarch64 ID registers), and is
+    /// lowered to the lowest level of the inputs. The fields starting at the bits in `signed` are
+    /// signed, 0b1111 being their lowest level ("not implemented"), while it is the highest level
+    /// of the unsigned fields.
+    FeatureFields { signed: &'static [u32] },
+    /// There is no known safe way of merging the values.
+    Unmergeable,
+}
+
+/// Entry of the report of how the inputs were merged.
+#[derive(Debug, PartialEq, Eq, displaydoc::Display)]
+pub enum ReportEntry {
+    /// {key}: masked bits {bits}, not set on all the inputs
+    Masked { key: String, bits: String },
+    /// {key}: lowered to {value} (filter: {filter})
+    Lowered {
+        key: String,
+        value: String,
+        filter: String,
+    },
+    /// {key}: differs across the inputs, left unmodified
+    Unmerged { key: String },
+}
+
+fn baseline_common<K, V>(
+    maps: &[HashMap<K, RegisterValueFilter<V>>],
+    policy: impl Fn(&K) -> MergePolicy,
+    report: &mut Vec<ReportEntry>,
+) -> Result<HashMap<K, RegisterValueFilter<V>>, BaselineError>
+where
+    K: ModifierMapKey + Debug,
+    V: Numeric + Debug + Ord,
+{
+    if maps.len() < 2 {
+        return Err(BaselineError::NumberOfInputs);
+    }
+
+    // Gather the keys of all the `maps`, in a stable order for the report.
+    let mut keys: Vec<&K> = Vec::new();
+    for key in maps.iter().flat_map(|map| map.keys()) {
+        if !keys.contains(&key) {
+            keys.push(key);
+        }
+    }
+    keys.sort_by_key(|key| key.to_string());
+
+    let mut baseline = HashMap::new();
+    for key in keys {
+        let values: Vec<V> = maps
+            .iter()
+            .filter_map(|map| map.get(key))
+            .map(|vf| vf.value & vf.filter)
+            .collect();
+        let in_all_maps = values.len() == maps.len();
+        let differs = !in_all_maps || values.iter().any(|value| *value != values[0]);
+        if !differs {
+            continue;
+        }
+
+        let merged = match policy(key) {
+            MergePolicy::FeatureBits => {
+                // A feature missing from one of the inputs is not available there.
+                let any = values.iter().fold(V::zero(), |acc, value| acc | *value);
+                let all = match in_all_maps {
+                    true => values.iter().fold(!V::zero(), |acc, value| acc & *value),
+                    false => V::zero(),
+                };
+                let masked = any & !all;
+                if masked == V::zero() {
+                    continue;
+                }
+                report.push(ReportEntry::Masked {
+                    key: key.to_string(),
+                    bits: format!("{masked:#b}"),
+                });
+                Some(RegisterValueFilter {
+                    filter: masked,
+                    value: V::zero(),
+                })
+            }
+            MergePolicy::Limit if in_all_maps => Some(RegisterValueFilter {
+                filter: !V::zero(),
+                value: *values.iter().min().unwrap(),
+            }),
+            MergePolicy::FeatureFields { signed } if in_all_maps => {
+                let mut merged = RegisterValueFilter {
+                    filter: V::zero(),
+                    value: V::zero(),
+                };
+                for field in 0..V::BITS / 4 {
+                    let mask = (0..4).fold(V::zero(), |mask, bit| {
+                        mask | (V::one() << (field * 4 + bit))
+                    });
+                    let levels: Vec<V> = values.iter().map(|value| *value & mask).collect();
+                    if levels.iter().all(|level| *level == levels[0]) {
+                        continue;
+                    }
+                    // The negative levels of a signed field are below the others, and ordered
+                    // like the unsigned ones.
+                    let sign = V::one() << (field * 4 + 3);
+                    let negative = levels.iter().filter(|level| **level & sign != V::zero());
+                    merged.filter |= mask;
+                    merged.value |= match signed.contains(&(field * 4)) {
+                        true => negative.min().or(levels.iter().min()),
+                        false => levels.iter().min(),
+                    }
+                    .copied()
+                    .unwrap();
+                }
+                Some(merged)
+            }
+            MergePolicy::Limit | MergePolicy::FeatureFields { .. } | MergePolicy::Unmergeable => {
+                None
+            }
+        };
+
+        match merged {
+            Some(merged) => {
+                if !matches!(policy(key), MergePolicy::FeatureBits) {
+                    report.push(ReportEntry::Lowered {
+                        key: key.to_string(),
+                        value: format!("{:#b}", merged.value),
+                        filter: format!("{:#b}", merged.filter),
+                    });
+                }
+                baseline.insert(key.clone(), merged);
+            }
+            None => report.push(ReportEntry::Unmerged {
+                key: key.to_string(),
+            }),
+        }
+    }
+
+    Ok(baseline)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::tests::{MockModifierMapKey, mock_modifier};
+
+    fn policy(key: &MockModifierMapKey) -> MergePolicy {
+        match key.0 {
+            0x0 => MergePolicy::FeatureBits,
+            0x1 => MergePolicy::Limit,
+            0x2 => MergePolicy::FeatureFields { signed: &[0] },
+            0x6 => MergePolicy::FeatureFields { signed: &[] },
+            _ => MergePolicy::Unmergeable,
+        }
+    }
+
+    #[test]
+    fn test_baseline_common_with_single_input() {
+        let input = vec![HashMap::from([mock_modifier!(0x0, 0b0000_0000)])];
+
+        match baseline_common(&input, policy, &mut Vec::new()) {
+            Err(BaselineError::NumberOfInputs) => (),
+            _ => panic!("Should fail with `Error::NumberOfInputs`."),
+        }
+    }
+
+    #[test]
+    fn test_baseline_common() {
+        let input = vec![
+            HashMap::from([
+                mock_modifier!(0x0, 0b1111_0011),
+                mock_modifier!(0x1, 0x10),
+                mock_modifier!(0x2, 0x23),
+                mock_modifier!(0x3, 0x1),
+                mock_modifier!(0x4, 0x1),
+                mock_modifier!(0x5, 0b0000_0001),
+                mock_modifier!(0x6, 0x0f),
+            ]),
+            HashMap::from([
+                mock_modifier!(0x0, 0b1010_1111),
+                mock_modifier!(0x1, 0x0d),
+                mock_modifier!(0x2, 0x1f),
+                mock_modifier!(0x3, 0x2),
+                mock_modifier!(0x4, 0x1),
+                mock_modifier!(0x6, 0x01),
+            ]),
+        ];
+        // The last key stands for a feature register missing from the second input.
+        let policy_with_missing = |key: &MockModifierMapKey| match key.0 {
+            0x5 => MergePolicy::FeatureBits,
+            _ => policy(key),
+        };
+        let expected = HashMap::from([
+            // Features missing from one input are masked.
+            mock_modifier!(0x0, 0b0000_0000, 0b0101_1100),
+            // Limits are lowered.
+            mock_modifier!(0x1, 0x0d),
+            // Feature levels are lowered, "not implemented" being the lowest of signed fields.
+            mock_modifier!(0x2, 0x1f, 0xff),
+            mock_modifier!(0x5, 0b0000_0000, 0b0000_0001),
+            // 0b1111 is the highest level of unsigned fields.
+            mock_modifier!(0x6, 0x01, 0x0f),
+        ]);
+
+        let mut report = Vec::new();
+        let baseline = baseline_common(&input, policy_with_missing, &mut report).unwrap();
+        assert_eq!(baseline, expected);
+        assert_eq!(
+            report,
+            vec![
+                ReportEntry::Masked {
+                    key: "ID=0x0".to_string(),
+                    bits: "0b1011100".to_string(),
+                },
+                ReportEntry::Lowered {
+                    key: "ID=0x1".to_string(),
+                    value: "0b1101".to_string(),
+                    filter: "0b11111111".to_string(),
+                },
+                ReportEntry::Lowered {
+                    key: "ID=0x2".to_string(),
+                    value: "0b11111".to_string(),
+                    filter: "0b11111111".to_string(),
+                },
+                ReportEntry::Unmerged {
+                    key: "ID=0x3".to_string(),
+                },
+                ReportEntry::Masked {
+                    key: "ID=0x5".to_string(),
+                    bits: "0b1".to_string(),
+                },
+                ReportEntry::Lowered {
+                    key: "ID=0x6".to_string(),
+                    value: "0b1".to_string(),
+                    filter: "0b1111".to_string(),
+                },
+            ]
+        );
+    }
+}
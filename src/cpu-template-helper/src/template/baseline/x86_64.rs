@@ -0,0 +1,177 @@
+// Copyright 2025 Amazon.com, Inc. or its affiliates. All Rights Reserved.
+// SPDX-License-Identifier: Apache-2.0
+
+use vmm::cpu_config::templates::CustomCpuTemplate;
+use vmm::cpu_config::x86_64::custom_cpu_template::{
+    CpuidLeafModifier, CpuidRegister, RegisterModifier,
+};
+
+use crate::template::baseline::{BaselineError, MergePolicy, ReportEntry, baseline_common};
+use crate::utils::x86_64::{
+    CpuidModifierMap, CpuidModifierMapKey, MsrModifierMap, MsrModifierMapKey,
+};
+
+/// IA32_ARCH_CAPABILITIES, whose bits advertise the absence of CPU vulnerabilities.
+const MSR_IA32_ARCH_CAPABILITIES: u32 = 0x10a;
+
+fn cpuid_policy(key: &CpuidModifierMapKey) -> MergePolicy {
+    use CpuidRegister::*;
+
+    match (key.leaf, key.subleaf, &key.register) {
+        // Maximum basic and extended CPUID leaves.
+        (0x0, _, Eax) | (0x8000_0000, _, Eax) => MergePolicy::Limit,
+        // Feature information, structured extended feature flags, XSAVE features and extended
+        // feature flags.
+        (0x1, _, Ecx | Edx)
+        | (0x7, 0x0, Ebx | Ecx | Edx)
+        | (0x7, 0x1, Eax)
+        | (0xd, 0x0, Eax | Edx)
+        | (0xd, 0x1, Eax)
+        | (0x8000_0001, _, Ecx | Edx)
+        | (0x8000_0008, _, Ebx) => MergePolicy::FeatureBits,
+        _ => MergePolicy::Unmergeable,
+    }
+}
+
+fn msr_policy(key: &MsrModifierMapKey) -> MergePolicy {
+    match key.0 {
+        MSR_IA32_ARCH_CAPABILITIES => MergePolicy::FeatureBits,
+        _ => MergePolicy::Unmergeable,
+    }
+}
+
+pub fn baseline(
+    configs: Vec<CustomCpuTemplate>,
+) -> Result<(CustomCpuTemplate, Vec<ReportEntry>), BaselineError> {
+    // Convert `Vec<CustomCpuTemplate>` to two `Vec<HashMap<_>>` of modifiers.
+    let (cpuid_modifiers_maps, msr_modifiers_maps): (Vec<_>, Vec<_>) = configs
+        .into_iter()
+        .map(|config| {
+            (
+                CpuidModifierMap::from(config.cpuid_modifiers).0,
+                MsrModifierMap::from(config.msr_modifiers).0,
+            )
+        })
+        .unzip();
+
+    let mut report = Vec::new();
+    let cpuid_modifiers = baseline_common(&cpuid_modifiers_maps, cpuid_policy, &mut report)?;
+    let msr_modifiers = baseline_common(&msr_modifiers_maps, msr_policy, &mut report)?;
+
+    let template = CustomCpuTemplate {
+        cpuid_modifiers: Vec::<CpuidLeafModifier>::from(CpuidModifierMap(cpuid_modifiers)),
+        msr_modifiers: Vec::<RegisterModifier>::from(MsrModifierMap(msr_modifiers)),
+        ..Default::default()
+    };
+
+    Ok((template, report))
+}
+
+#[cfg(test)]
+mod tests {
+    use vmm::cpu_config::templates::RegisterValueFilter;
+    use vmm::cpu_config::x86_64::cpuid::KvmCpuidFlags;
+    use vmm::cpu_config::x86_64::custom_cpu_template::CpuidRegister::*;
+    use vmm::cpu_config::x86_64::custom_cpu_template::CpuidRegisterModifier;
+
+    use super::*;
+    use crate::utils::x86_64::{cpuid_leaf_modifier, cpuid_reg_modifier, msr_modifier};
+
+    // Summary of the inputs:
+    // * The maximum basic CPUID leaf differs.
+    // * The feature bits of CPUID leaf 0x7 / subleaf 0x0 / EBX differ.
+    // * The family, model and stepping of CPUID leaf 0x1 / EAX differ.
+    // * IA32_ARCH_CAPABILITIES only exists on the first input.
+    // * The remaining modifiers are identical.
+    #[rustfmt::skip]
+    fn build_input_configs() -> Vec<CustomCpuTemplate> {
+        vec![
+            CustomCpuTemplate {
+                cpuid_modifiers: vec![
+                    cpuid_leaf_modifier!(0x0, 0x0, KvmCpuidFlags::EMPTY, vec![
+                        cpuid_reg_modifier!(Eax, 0x1f),
+                        cpuid_reg_modifier!(Ebx, 0x756e_6547),
+                    ]),
+                    cpuid_leaf_modifier!(0x1, 0x0, KvmCpuidFlags::EMPTY, vec![
+                        cpuid_reg_modifier!(Eax, 0x0005_0657),
+                    ]),
+                    cpuid_leaf_modifier!(0x7, 0x0, KvmCpuidFlags::SIGNIFICANT_INDEX, vec![
+                        cpuid_reg_modifier!(Ebx, 0b1111),
+                    ]),
+                ],
+                msr_modifiers: vec![
+                    msr_modifier!(0x10a, 0b1011),
+                    msr_modifier!(0x4b56_4d00, 0x1),
+                ],
+                ..Default::default()
+            },
+            CustomCpuTemplate {
+                cpuid_modifiers: vec![
+                    cpuid_leaf_modifier!(0x0, 0x0, KvmCpuidFlags::EMPTY, vec![
+                        cpuid_reg_modifier!(Eax, 0x1b),
+                        cpuid_reg_modifier!(Ebx, 0x756e_6547),
+                    ]),
+                    cpuid_leaf_modifier!(0x1, 0x0, KvmCpuidFlags::EMPTY, vec![
+                        cpuid_reg_modifier!(Eax, 0x0005_0654),
+                    ]),
+                    cpuid_leaf_modifier!(0x7, 0x0, KvmCpuidFlags::SIGNIFICANT_INDEX, vec![
+                        cpuid_reg_modifier!(Ebx, 0b0110),
+                    ]),
+                ],
+                msr_modifiers: vec![
+                    msr_modifier!(0x4b56_4d00, 0x1),
+                ],
+                ..Default::default()
+            },
+        ]
+    }
+
+    #[rustfmt::skip]
+    fn build_expected_template() -> CustomCpuTemplate {
+        CustomCpuTemplate {
+            cpuid_modifiers: vec![
+                cpuid_leaf_modifier!(0x0, 0x0, KvmCpuidFlags::EMPTY, vec![
+                    cpuid_reg_modifier!(Eax, 0x1b),
+                ]),
+                cpuid_leaf_modifier!(0x7, 0x0, KvmCpuidFlags::SIGNIFICANT_INDEX, vec![
+                    cpuid_reg_modifier!(Ebx, 0x0, 0b1001),
+                ]),
+            ],
+            msr_modifiers: vec![
+                msr_modifier!(0x10a, 0x0, 0b1011),
+            ],
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_baseline() {
+        let (template, report) = baseline(build_input_configs()).unwrap();
+        assert_eq!(template, build_expected_template());
+        assert_eq!(
+            report
+                .iter()
+                .map(|entry| entry.to_string())
+                .collect::<Vec<_>>(),
+            vec![
+                "leaf=0x0, subleaf=0x0, flags=0b0, register=eax: lowered to 0b11011 (filter: \
+                 0b11111111111111111111111111111111)",
+                "leaf=0x1, subleaf=0x0, flags=0b0, register=eax: differs across the inputs, left \
+                 unmodified",
+                "leaf=0x7, subleaf=0x0, flags=0b1, register=ebx: masked bits 0b1001, not set on \
+                 all the inputs",
+                "index=0x10a: masked bits 0b1011, not set on all the inputs",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_baseline_with_single_input() {
+        let mut configs = build_input_configs();
+        configs.pop();
+        match baseline(configs) {
+            Err(BaselineError::NumberOfInputs) => (),
+            _ => panic!("Should fail with `Error::NumberOfInputs`."),
+        }
+    }
+}
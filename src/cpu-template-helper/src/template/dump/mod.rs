@@ -14,6 +14,8 @@ use vmm::{DumpCpuConfigError, Vmm};
 #[cfg(target_arch = "aarch64")]
 use crate::template::dump::aarch64::config_to_template;
 #[cfg(target_arch = "x86_64")]
+pub use crate::template::dump::x86_64::SymbolicCpuTemplate;
+#[cfg(target_arch = "x86_64")]
 use crate::template::dump::x86_64::config_to_template;
 
 #[derive(Debug, thiserror::Error, displaydoc::Display)]
@@ -3,6 +3,7 @@
 
 use std::collections::BTreeMap;
 
+use serde::Serialize;
 use vmm::MSR_RANGE;
 use vmm::arch::x86_64::generated::msr_index::*;
 use vmm::arch::x86_64::msr::MsrRange;
@@ -24,6 +25,25 @@ pub fn config_to_template(cpu_config: &CpuConfiguration) -> CustomCpuTemplate {
     }
 }
 
+/// Custom CPU template in the symbolic form, where the CPUID bits of the known CPU features are
+/// listed by name in `cpuid_features` rather than in `cpuid_modifiers`.
+#[derive(Debug, Serialize)]
+pub struct SymbolicCpuTemplate {
+    #[serde(flatten)]
+    template: CustomCpuTemplate,
+    cpuid_features: BTreeMap<String, bool>,
+}
+
+impl From<CustomCpuTemplate> for SymbolicCpuTemplate {
+    fn from(mut template: CustomCpuTemplate) -> Self {
+        let cpuid_features = template.take_cpuid_features();
+        Self {
+            template,
+            cpuid_features,
+        }
+    }
+}
+
 fn cpuid_to_modifiers(cpuid: &Cpuid) -> Vec<CpuidLeafModifier> {
     cpuid
         .inner()
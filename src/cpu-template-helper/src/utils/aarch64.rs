@@ -4,8 +4,10 @@
 use std::collections::HashMap;
 use std::fmt::Display;
 
+use serde::Serialize;
+use vmm::arch::aarch64::regs::{ID_REGISTERS, id_register_name};
 use vmm::cpu_config::aarch64::custom_cpu_template::RegisterModifier;
-use vmm::cpu_config::templates::RegisterValueFilter;
+use vmm::cpu_config::templates::{CustomCpuTemplate, RegisterValueFilter};
 
 use super::ModifierMapKey;
 
@@ -15,7 +17,10 @@ pub struct RegModifierMapKey(pub u64);
 impl ModifierMapKey for RegModifierMapKey {}
 impl Display for RegModifierMapKey {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "ID={:#x}", self.0)
+        match id_register_name(self.0) {
+            Some(name) => write!(f, "ID={:#x} ({name})", self.0),
+            None => write!(f, "ID={:#x}", self.0),
+        }
     }
 }
 
@@ -70,8 +75,54 @@ macro_rules! reg_modifier {
 
 pub(crate) use reg_modifier;
 
+/// Difference in a 4-bit field of an ID register between two CPU configurations.
+#[derive(Debug, Eq, PartialEq, Serialize)]
+pub struct IdRegFieldDiff {
+    pub register: &'static str,
+    pub bits: String,
+    pub prev: String,
+    pub curr: String,
+}
+
+/// Lists the fields of the ID registers that differ between two dumped CPU configurations.
+/// The fields of the ID registers are 4 bits wide.
+pub fn id_reg_field_diffs(
+    prev: &CustomCpuTemplate,
+    curr: &CustomCpuTemplate,
+) -> Vec<IdRegFieldDiff> {
+    let value = |template: &CustomCpuTemplate, id: u64| {
+        template
+            .reg_modifiers
+            .iter()
+            .find(|modifier| modifier.addr == id)
+            .map(|modifier| modifier.bitmap.value)
+    };
+
+    let mut diffs = Vec::new();
+    for (name, id) in ID_REGISTERS {
+        let (Some(prev_value), Some(curr_value)) = (value(prev, id), value(curr, id)) else {
+            continue;
+        };
+        for shift in (0..64).step_by(4) {
+            let prev_field = (prev_value >> shift) & 0xf;
+            let curr_field = (curr_value >> shift) & 0xf;
+            if prev_field != curr_field {
+                diffs.push(IdRegFieldDiff {
+                    register: name,
+                    bits: format!("[{}:{}]", shift + 3, shift),
+                    prev: format!("{prev_field:#06b}"),
+                    curr: format!("{curr_field:#06b}"),
+                });
+            }
+        }
+    }
+    diffs
+}
+
 #[cfg(test)]
 mod tests {
+    use vmm::arch::aarch64::regs::{ID_AA64ISAR0_EL1, ID_AA64PFR0_EL1};
+
     use super::*;
 
     macro_rules! reg_modifier_map {
@@ -90,6 +141,46 @@ mod tests {
     fn test_format_reg_modifier_map_key() {
         let key = RegModifierMapKey(0x1234);
         assert_eq!(key.to_string(), "ID=0x1234");
+
+        let key = RegModifierMapKey(ID_AA64PFR0_EL1);
+        assert_eq!(key.to_string(), "ID=0x603000000013c020 (ID_AA64PFR0_EL1)");
+    }
+
+    #[test]
+    fn test_id_reg_field_diffs() {
+        let prev = CustomCpuTemplate {
+            reg_modifiers: vec![
+                reg_modifier!(ID_AA64PFR0_EL1, 0x1100_0011),
+                reg_modifier!(ID_AA64ISAR0_EL1, 0x2000),
+                reg_modifier!(0x1, 0x1),
+            ],
+            ..Default::default()
+        };
+        let curr = CustomCpuTemplate {
+            reg_modifiers: vec![
+                reg_modifier!(ID_AA64PFR0_EL1, 0x1100_0011),
+                reg_modifier!(ID_AA64ISAR0_EL1, 0x1001),
+                reg_modifier!(0x1, 0x2),
+            ],
+            ..Default::default()
+        };
+        assert_eq!(
+            id_reg_field_diffs(&prev, &curr),
+            vec![
+                IdRegFieldDiff {
+                    register: "ID_AA64ISAR0_EL1",
+                    bits: "[3:0]".to_string(),
+                    prev: "0b0000".to_string(),
+                    curr: "0b0001".to_string(),
+                },
+                IdRegFieldDiff {
+                    register: "ID_AA64ISAR0_EL1",
+                    bits: "[15:12]".to_string(),
+                    prev: "0b0010".to_string(),
+                    curr: "0b0001".to_string(),
+                },
+            ]
+        );
     }
 
     fn build_sample_reg_modifier_vec() -> Vec<RegisterModifier> {
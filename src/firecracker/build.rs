@@ -40,6 +40,11 @@ fn main() {
             target
         );
         format!("{}/unimplemented.json", JSON_DIR)
+    } else if std::env::var_os("CARGO_FEATURE_GDB").is_some() {
+        // The GDB stub needs more syscalls from the VMM thread, the gdb policy extends the default
+        // one with them.
+        println!("cargo:rerun-if-changed={}", seccomp_json_path);
+        format!("{}/gdb/{}.json", JSON_DIR, target)
     } else {
         seccomp_json_path
     };
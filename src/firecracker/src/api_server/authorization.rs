@@ -0,0 +1,327 @@
+// Copyright 2025 Amazon.com, Inc. or its affiliates. All Rights Reserved.
+// SPDX-License-Identifier: Apache-2.0
+
+//! Authorization of API requests based on the credentials of the connected peer.
+
+use std::fs::File;
+use std::io::BufReader;
+use std::os::unix::io::RawFd;
+use std::path::Path;
+
+use micro_http::Method;
+use serde::Deserialize;
+
+/// Credentials of the process on the other end of an API connection, as reported by
+/// `SO_PEERCRED`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PeerCredentials {
+    /// Process ID of the peer.
+    pub pid: i32,
+    /// User ID of the peer.
+    pub uid: u32,
+    /// Group ID of the peer.
+    pub gid: u32,
+}
+
+impl PeerCredentials {
+    /// Retrieves the credentials of the peer connected to the unix socket `fd`.
+    pub fn from_fd(fd: RawFd) -> std::io::Result<Self> {
+        let mut ucred = libc::ucred {
+            pid: 0,
+            uid: 0,
+            gid: 0,
+        };
+        // Safe to unwrap since the size of `ucred` is a few bytes.
+        let mut len = libc::socklen_t::try_from(std::mem::size_of::<libc::ucred>()).unwrap();
+        // SAFETY: `ucred` and `len` are valid for writes and `len` holds the size of `ucred`.
+        let ret = unsafe {
+            libc::getsockopt(
+                fd,
+                libc::SOL_SOCKET,
+                libc::SO_PEERCRED,
+                (&mut ucred as *mut libc::ucred).cast(),
+                &mut len,
+            )
+        };
+        if ret != 0 {
+            return Err(std::io::Error::last_os_error());
+        }
+        Ok(PeerCredentials {
+            pid: ucred.pid,
+            uid: ucred.uid,
+            gid: ucred.gid,
+        })
+    }
+}
+
+/// Errors associated with loading the API authorization policy.
+#[derive(Debug, thiserror::Error, displaydoc::Display)]
+pub enum AuthorizationPolicyError {
+    /// Cannot open the authorization policy file: {0}
+    Open(std::io::Error),
+    /// Cannot parse the authorization policy file: {0}
+    Parse(serde_json::Error),
+    /// Authorization rule {0} must match on `uid` and/or `gid`.
+    MissingPrincipal(usize),
+    /// Invalid path pattern in authorization rule {0}: {1}
+    InvalidPath(usize, String),
+}
+
+/// HTTP method that a rule of the authorization policy allows.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+enum AllowedMethod {
+    #[serde(rename = "GET")]
+    Get,
+    #[serde(rename = "PUT")]
+    Put,
+    #[serde(rename = "PATCH")]
+    Patch,
+    #[serde(rename = "*")]
+    Any,
+}
+
+impl AllowedMethod {
+    fn matches(self, method: Method) -> bool {
+        matches!(
+            (self, method),
+            (AllowedMethod::Any, _)
+                | (AllowedMethod::Get, Method::Get)
+                | (AllowedMethod::Put, Method::Put)
+                | (AllowedMethod::Patch, Method::Patch)
+        )
+    }
+}
+
+/// A `(method, path)` pair allowed by a rule of the authorization policy.
+///
+/// In `path`, a `*` segment matches any single path segment and a trailing `**` segment matches
+/// any number of remaining segments, e.g. `/drives/*` or `/snapshot/**`.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct AllowedRequest {
+    method: AllowedMethod,
+    path: String,
+}
+
+impl AllowedRequest {
+    fn matches(&self, method: Method, path: &str) -> bool {
+        if !self.method.matches(method) {
+            return false;
+        }
+
+        let mut patterns = path_segments(&self.path);
+        let mut segments = path_segments(path);
+        loop {
+            match (patterns.next(), segments.next()) {
+                (Some("**"), _) => return true,
+                (Some("*"), Some(_)) => (),
+                (Some(pattern), Some(segment)) if pattern == segment => (),
+                (None, None) => return true,
+                _ => return false,
+            }
+        }
+    }
+}
+
+fn path_segments(path: &str) -> impl DoubleEndedIterator<Item = &str> {
+    path.split('/').filter(|segment| !segment.is_empty())
+}
+
+/// Rule of the authorization policy, granting requests to the peers that match all of the
+/// specified principals.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct AuthorizationRule {
+    uid: Option<u32>,
+    gid: Option<u32>,
+    allow: Vec<AllowedRequest>,
+}
+
+impl AuthorizationRule {
+    fn applies_to(&self, peer: &PeerCredentials) -> bool {
+        self.uid.is_none_or(|uid| uid == peer.uid) && self.gid.is_none_or(|gid| gid == peer.gid)
+    }
+}
+
+/// Policy mapping API clients, identified by their uid and/or gid, to the requests that they
+/// are allowed to issue. Requests that no rule allows are denied.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct AuthorizationPolicy {
+    rules: Vec<AuthorizationRule>,
+}
+
+impl AuthorizationPolicy {
+    /// Loads the authorization policy from the JSON file at `path`.
+    pub fn from_file(path: &Path) -> Result<Self, AuthorizationPolicyError> {
+        let file = File::open(path).map_err(AuthorizationPolicyError::Open)?;
+        let policy: AuthorizationPolicy = serde_json::from_reader(BufReader::new(file))
+            .map_err(AuthorizationPolicyError::Parse)?;
+        policy.validate()?;
+        Ok(policy)
+    }
+
+    fn validate(&self) -> Result<(), AuthorizationPolicyError> {
+        for (idx, rule) in self.rules.iter().enumerate() {
+            if rule.uid.is_none() && rule.gid.is_none() {
+                return Err(AuthorizationPolicyError::MissingPrincipal(idx));
+            }
+            // Paths must be absolute and `**` is only supported as the last segment.
+            if let Some(allowed) = rule.allow.iter().find(|allowed| {
+                !allowed.path.starts_with('/')
+                    || path_segments(&allowed.path)
+                        .rev()
+                        .skip(1)
+                        .any(|segment| segment == "**")
+            }) {
+                return Err(AuthorizationPolicyError::InvalidPath(
+                    idx,
+                    allowed.path.clone(),
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    /// Whether `peer` is allowed to issue a request with `method` on `path`.
+    pub fn is_allowed(&self, peer: &PeerCredentials, method: Method, path: &str) -> bool {
+        self.rules
+            .iter()
+            .filter(|rule| rule.applies_to(peer))
+            .flat_map(|rule| rule.allow.iter())
+            .any(|allowed| allowed.matches(method, path))
+    }
+}
+
+/// Authorization of the client which issued an API request.
+#[derive(Debug, Clone, Copy)]
+pub enum Authorization<'a> {
+    /// No authorization policy is configured, all requests are allowed.
+    Unrestricted,
+    /// Requests are allowed by `policy` based on the credentials of the client. Requests from
+    /// clients with unknown credentials are denied.
+    Policy(&'a AuthorizationPolicy, Option<PeerCredentials>),
+}
+
+impl Authorization<'_> {
+    /// Whether the client is allowed to issue a request with `method` on `path`.
+    pub fn allows(&self, method: Method, path: &str) -> bool {
+        match self {
+            Authorization::Unrestricted => true,
+            Authorization::Policy(policy, peer_credentials) => {
+                peer_credentials.is_some_and(|peer| policy.is_allowed(&peer, method, path))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Write;
+    use std::os::unix::io::AsRawFd;
+    use std::os::unix::net::UnixStream;
+
+    use vmm_sys_util::tempfile::TempFile;
+
+    use super::*;
+
+    fn policy_from_str(json: &str) -> Result<AuthorizationPolicy, AuthorizationPolicyError> {
+        let file = TempFile::new().unwrap();
+        file.as_file().write_all(json.as_bytes()).unwrap();
+        AuthorizationPolicy::from_file(file.as_path())
+    }
+
+    #[test]
+    fn test_peer_credentials() {
+        let (stream, _peer) = UnixStream::pair().unwrap();
+        let credentials = PeerCredentials::from_fd(stream.as_raw_fd()).unwrap();
+        // SAFETY: getuid and getgid are always successful.
+        let (uid, gid) = unsafe { (libc::getuid(), libc::getgid()) };
+        assert_eq!(credentials.uid, uid);
+        assert_eq!(credentials.gid, gid);
+        assert_eq!(credentials.pid, i32::try_from(std::process::id()).unwrap());
+
+        PeerCredentials::from_fd(-1).unwrap_err();
+    }
+
+    #[test]
+    fn test_load_policy() {
+        assert!(matches!(
+            AuthorizationPolicy::from_file(Path::new("/invalid/path")),
+            Err(AuthorizationPolicyError::Open(_))
+        ));
+        assert!(matches!(
+            policy_from_str(
+                r#"{"rules": [{"uid": 0, "allow": [{"method": "POST", "path": "/"}]}]}"#
+            ),
+            Err(AuthorizationPolicyError::Parse(_))
+        ));
+        assert!(matches!(
+            policy_from_str(r#"{"rules": [{"allow": []}]}"#),
+            Err(AuthorizationPolicyError::MissingPrincipal(0))
+        ));
+        assert!(matches!(
+            policy_from_str(
+                r#"{"rules": [{"gid": 0, "allow": [{"method": "GET", "path": "vm"}]}]}"#
+            ),
+            Err(AuthorizationPolicyError::InvalidPath(0, _))
+        ));
+        assert!(matches!(
+            policy_from_str(
+                r#"{"rules": [{"gid": 0, "allow": [{"method": "GET", "path": "/**/config"}]}]}"#
+            ),
+            Err(AuthorizationPolicyError::InvalidPath(0, _))
+        ));
+    }
+
+    #[test]
+    fn test_is_allowed() {
+        let policy = policy_from_str(
+            r#"{
+                "rules": [
+                    { "uid": 0, "allow": [{ "method": "*", "path": "/**" }] },
+                    {
+                        "uid": 1000,
+                        "gid": 1000,
+                        "allow": [
+                            { "method": "GET", "path": "/" },
+                            { "method": "GET", "path": "/balloon/statistics" },
+                            { "method": "PATCH", "path": "/drives/*" }
+                        ]
+                    },
+                    { "gid": 2000, "allow": [{ "method": "GET", "path": "/vm/config" }] }
+                ]
+            }"#,
+        )
+        .unwrap();
+
+        let root = PeerCredentials {
+            pid: 1,
+            uid: 0,
+            gid: 0,
+        };
+        assert!(policy.is_allowed(&root, Method::Put, "/actions"));
+        assert!(policy.is_allowed(&root, Method::Patch, "/drives/rootfs"));
+
+        let agent = PeerCredentials {
+            pid: 1,
+            uid: 1000,
+            gid: 1000,
+        };
+        assert!(policy.is_allowed(&agent, Method::Get, "/"));
+        assert!(policy.is_allowed(&agent, Method::Get, "/balloon/statistics/"));
+        assert!(policy.is_allowed(&agent, Method::Patch, "/drives/rootfs"));
+        assert!(!policy.is_allowed(&agent, Method::Put, "/drives/rootfs"));
+        assert!(!policy.is_allowed(&agent, Method::Patch, "/drives"));
+        assert!(!policy.is_allowed(&agent, Method::Get, "/balloon"));
+        assert!(!policy.is_allowed(&agent, Method::Get, "/vm/config"));
+
+        let monitor = PeerCredentials {
+            pid: 1,
+            uid: 1000,
+            gid: 2000,
+        };
+        assert!(policy.is_allowed(&monitor, Method::Get, "/vm/config"));
+        assert!(!policy.is_allowed(&monitor, Method::Get, "/"));
+    }
+}
@@ -0,0 +1,431 @@
+// Copyright 2025 Amazon.com, Inc. or its affiliates. All Rights Reserved.
+// SPDX-License-Identifier: Apache-2.0
+
+//! Listener of the API socket, used when an authorization policy is configured. It accepts the
+//! API clients, retrieving their credentials as they connect, and reads their requests with
+//! `micro_http`.
+
+use std::collections::HashMap;
+use std::fmt;
+use std::io::{ErrorKind, Write};
+use std::os::unix::io::AsRawFd;
+use std::os::unix::net::{UnixListener, UnixStream};
+use std::path::Path;
+
+use micro_http::{Body, ConnectionError, HttpConnection, Request, Response, StatusCode, Version};
+use vmm::logger::{debug, error, warn};
+use vmm_sys_util::epoll::{ControlOperation, Epoll, EpollEvent, EventSet};
+use vmm_sys_util::eventfd::EventFd;
+
+use super::authorization::PeerCredentials;
+
+/// Maximum number of clients connected at the same time, as with the `micro_http` server.
+const MAX_CONNECTIONS: usize = 10;
+/// Epoll token of the API socket.
+const LISTENER_TOKEN: u64 = 0;
+/// Epoll token of the kill switch.
+const KILL_SWITCH_TOKEN: u64 = 1;
+
+/// Errors associated with the API socket.
+#[derive(Debug, thiserror::Error, displaydoc::Display)]
+pub enum ApiListenerError {
+    /// Cannot bind the API socket: {0}
+    Bind(std::io::Error),
+    /// Epoll error on the API socket: {0}
+    Epoll(std::io::Error),
+    /// The kill switch of the API socket was triggered.
+    Shutdown,
+}
+
+/// A request read from an API client.
+#[derive(Debug)]
+pub struct ClientRequest {
+    /// The request.
+    pub request: Request,
+    /// Credentials of the client, retrieved when it connected. `None` if they could not be
+    /// retrieved.
+    pub peer_credentials: Option<PeerCredentials>,
+    // Token of the connection to answer on.
+    token: u64,
+}
+
+struct ClientConnection {
+    http: HttpConnection<UnixStream>,
+    // The stream of `http`, to write the responses.
+    stream: UnixStream,
+    peer_credentials: Option<PeerCredentials>,
+    // Responses the client didn't read yet.
+    pending_output: Vec<u8>,
+}
+
+impl fmt::Debug for ClientConnection {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ClientConnection")
+            .field("peer_credentials", &self.peer_credentials)
+            .field("pending_output", &self.pending_output.len())
+            .finish_non_exhaustive()
+    }
+}
+
+/// Accepts the clients of the API socket and reads their requests.
+///
+/// The connections are identified by a token that is never reused, so that a response can't
+/// reach a client that connected after the one which issued the request.
+#[derive(Debug)]
+pub struct ApiListener {
+    listener: UnixListener,
+    epoll: Epoll,
+    kill_switch: Option<EventFd>,
+    connections: HashMap<u64, ClientConnection>,
+    next_token: u64,
+    payload_max_size: usize,
+}
+
+impl ApiListener {
+    /// Binds the API socket at `path`.
+    pub fn new(path: &Path) -> Result<Self, ApiListenerError> {
+        let listener = UnixListener::bind(path).map_err(ApiListenerError::Bind)?;
+        let epoll = Epoll::new().map_err(ApiListenerError::Epoll)?;
+        epoll
+            .ctl(
+                ControlOperation::Add,
+                listener.as_raw_fd(),
+                EpollEvent::new(EventSet::IN, LISTENER_TOKEN),
+            )
+            .map_err(ApiListenerError::Epoll)?;
+
+        Ok(ApiListener {
+            listener,
+            epoll,
+            kill_switch: None,
+            connections: HashMap::new(),
+            next_token: KILL_SWITCH_TOKEN + 1,
+            payload_max_size: vmm::HTTP_MAX_PAYLOAD_SIZE,
+        })
+    }
+
+    /// Adds `kill_switch`, an event which makes `requests` return [`ApiListenerError::Shutdown`].
+    pub fn add_kill_switch(&mut self, kill_switch: EventFd) -> Result<(), ApiListenerError> {
+        self.epoll
+            .ctl(
+                ControlOperation::Add,
+                kill_switch.as_raw_fd(),
+                EpollEvent::new(EventSet::IN, KILL_SWITCH_TOKEN),
+            )
+            .map_err(ApiListenerError::Epoll)?;
+        self.kill_switch = Some(kill_switch);
+        Ok(())
+    }
+
+    /// Sets the maximum size of the request bodies.
+    pub fn set_payload_max_size(&mut self, payload_max_size: usize) {
+        self.payload_max_size = payload_max_size;
+    }
+
+    /// Waits for events on the API socket and returns the requests read from the clients.
+    pub fn requests(&mut self) -> Result<Vec<ClientRequest>, ApiListenerError> {
+        let mut events = vec![EpollEvent::default(); MAX_CONNECTIONS + 2];
+        let event_count = match self.epoll.wait(-1, &mut events) {
+            Ok(event_count) => event_count,
+            Err(err) if err.kind() == ErrorKind::Interrupted => 0,
+            Err(err) => return Err(ApiListenerError::Epoll(err)),
+        };
+
+        let mut requests = Vec::new();
+        for event in &events[..event_count] {
+            match event.data() {
+                LISTENER_TOKEN => self.accept(),
+                KILL_SWITCH_TOKEN => return Err(ApiListenerError::Shutdown),
+                token => self.handle_connection_event(token, event.event_set(), &mut requests),
+            }
+        }
+        Ok(requests)
+    }
+
+    /// Sends `response` to the client which issued `request`.
+    pub fn respond(&mut self, request: &ClientRequest, response: Response) {
+        let Some(connection) = self.connections.get_mut(&request.token) else {
+            debug!("Dropping the response to a disconnected API client.");
+            return;
+        };
+        if let Err(err) = response.write_all(&mut connection.pending_output) {
+            error!("Failed to serialize the API response: {:?}", err);
+            return;
+        }
+        self.write_pending_output(request.token);
+    }
+
+    /// Writes the responses that the clients didn't read yet, as far as they can take them.
+    pub fn flush_outgoing_writes(&mut self) {
+        let tokens = self.connections.keys().copied().collect::<Vec<_>>();
+        for token in tokens {
+            self.write_pending_output(token);
+        }
+    }
+
+    fn accept(&mut self) {
+        let mut stream = match self.listener.accept() {
+            Ok((stream, _)) => stream,
+            Err(err) => {
+                error!("Failed to accept API client: {}", err);
+                return;
+            }
+        };
+        if self.connections.len() >= MAX_CONNECTIONS {
+            // Like the `micro_http` server, tell the client why it is disconnected.
+            warn!("Too many API clients, refusing the new connection.");
+            let mut response = Response::new(Version::Http11, StatusCode::ServiceUnavailable);
+            response.set_body(Body::new("{ \"error\": \"Too many open connections\" }"));
+            if let Err(err) = response.write_all(&mut stream) {
+                debug!("Failed to answer the refused API client: {:?}", err);
+            }
+            return;
+        }
+
+        // The credentials are retrieved right away, they are the ones of the process that
+        // connected.
+        let peer_credentials = PeerCredentials::from_fd(stream.as_raw_fd())
+            .map_err(|err| error!("Failed to get API client credentials: {}", err))
+            .ok();
+        // The responses are written as far as the client reads them, the rest is kept until
+        // the client can take it.
+        let write_stream = match stream
+            .set_nonblocking(true)
+            .and_then(|_| stream.try_clone())
+        {
+            Ok(write_stream) => write_stream,
+            Err(err) => {
+                error!("Failed to set up API client connection: {}", err);
+                return;
+            }
+        };
+
+        // The connection is registered through the write stream, whose events are updated while
+        // responses are pending.
+        let token = self.next_token;
+        if let Err(err) = self.epoll.ctl(
+            ControlOperation::Add,
+            write_stream.as_raw_fd(),
+            EpollEvent::new(EventSet::IN, token),
+        ) {
+            error!("Failed to register API client connection: {}", err);
+            return;
+        }
+        self.next_token += 1;
+
+        let mut http = HttpConnection::new(stream);
+        http.set_payload_max_size(self.payload_max_size);
+        self.connections.insert(
+            token,
+            ClientConnection {
+                http,
+                stream: write_stream,
+                peer_credentials,
+                pending_output: Vec::new(),
+            },
+        );
+    }
+
+    fn handle_connection_event(
+        &mut self,
+        token: u64,
+        event_set: EventSet,
+        requests: &mut Vec<ClientRequest>,
+    ) {
+        let Some(connection) = self.connections.get_mut(&token) else {
+            return;
+        };
+
+        if event_set.contains(EventSet::IN) {
+            match connection.http.try_read() {
+                Ok(()) => {
+                    while let Some(request) = connection.http.pop_parsed_request() {
+                        requests.push(ClientRequest {
+                            request,
+                            peer_credentials: connection.peer_credentials,
+                            token,
+                        });
+                    }
+                }
+                Err(ConnectionError::ParseError(err)) => {
+                    // The requests parsed before the faulty one are dropped along with it.
+                    while connection.http.pop_parsed_request().is_some() {}
+                    let mut response = Response::new(Version::Http11, StatusCode::BadRequest);
+                    response.set_body(Body::new(format!(
+                        "{{ \"error\": \"{}\nAll previous unanswered requests will be dropped.\" \
+                         }}",
+                        err
+                    )));
+                    if let Err(err) = response.write_all(&mut connection.pending_output) {
+                        error!("Failed to serialize the API response: {:?}", err);
+                    }
+                }
+                Err(err) => {
+                    debug!("Closing API client connection: {:?}", err);
+                    self.close(token);
+                    return;
+                }
+            }
+        } else if event_set.intersects(EventSet::HANG_UP | EventSet::ERROR) {
+            self.close(token);
+            return;
+        }
+        self.write_pending_output(token);
+    }
+
+    fn write_pending_output(&mut self, token: u64) {
+        let Some(connection) = self.connections.get_mut(&token) else {
+            return;
+        };
+
+        let mut written = 0;
+        let result = loop {
+            if written == connection.pending_output.len() {
+                break Ok(());
+            }
+            match connection
+                .stream
+                .write(&connection.pending_output[written..])
+            {
+                Ok(0) => break Err(ErrorKind::WriteZero.into()),
+                Ok(count) => written += count,
+                Err(err) if err.kind() == ErrorKind::Interrupted => (),
+                Err(err) => break Err(err),
+            }
+        };
+        connection.pending_output.drain(..written);
+
+        let event_set = match result {
+            Ok(()) => EventSet::IN,
+            // Wait for the client to read the responses before writing the rest.
+            Err(err) if err.kind() == ErrorKind::WouldBlock => EventSet::IN | EventSet::OUT,
+            Err(err) => {
+                debug!("Closing API client connection: {}", err);
+                self.close(token);
+                return;
+            }
+        };
+        if let Err(err) = self.epoll.ctl(
+            ControlOperation::Modify,
+            connection.stream.as_raw_fd(),
+            EpollEvent::new(event_set, token),
+        ) {
+            error!("Failed to update API client connection events: {}", err);
+            self.close(token);
+        }
+    }
+
+    fn close(&mut self, token: u64) {
+        if let Some(connection) = self.connections.remove(&token) {
+            // Both streams share the file description, which stays in the epoll set until the
+            // last of them is closed.
+            let _ = self.epoll.ctl(
+                ControlOperation::Delete,
+                connection.stream.as_raw_fd(),
+                EpollEvent::default(),
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Read;
+
+    use vmm_sys_util::tempdir::TempDir;
+
+    use super::*;
+
+    fn listener() -> (TempDir, std::path::PathBuf, ApiListener) {
+        let tmp_dir = TempDir::new().unwrap();
+        let path = tmp_dir.as_path().join("api.sock");
+        let listener = ApiListener::new(&path).unwrap();
+        (tmp_dir, path, listener)
+    }
+
+    #[test]
+    fn test_requests() {
+        let (_tmp_dir, path, mut listener) = listener();
+        assert!(matches!(
+            ApiListener::new(&path),
+            Err(ApiListenerError::Bind(_))
+        ));
+
+        let mut client = UnixStream::connect(&path).unwrap();
+        assert!(listener.requests().unwrap().is_empty());
+        assert_eq!(listener.connections.len(), 1);
+
+        client.write_all(b"GET / HTTP/1.1\r\n\r\n").unwrap();
+        let requests = listener.requests().unwrap();
+        assert_eq!(requests.len(), 1);
+        assert_eq!(requests[0].request.uri().get_abs_path(), "/");
+        // The credentials are the ones of the process which connected.
+        // SAFETY: getuid is always successful.
+        let uid = unsafe { libc::getuid() };
+        assert_eq!(requests[0].peer_credentials.unwrap().uid, uid);
+
+        listener.respond(&requests[0], Response::new(Version::Http11, StatusCode::OK));
+        let mut buf = [0; 17];
+        client.read_exact(&mut buf).unwrap();
+        assert_eq!(&buf, b"HTTP/1.1 200 \r\nSe");
+
+        // Disconnected clients are dropped.
+        drop(client);
+        assert!(listener.requests().unwrap().is_empty());
+        assert!(listener.connections.is_empty());
+        // The responses to disconnected clients are dropped.
+        listener.respond(&requests[0], Response::new(Version::Http11, StatusCode::OK));
+    }
+
+    #[test]
+    fn test_parse_error() {
+        let (_tmp_dir, path, mut listener) = listener();
+        listener.set_payload_max_size(10);
+
+        let mut client = UnixStream::connect(&path).unwrap();
+        listener.requests().unwrap();
+        client
+            .write_all(b"PUT /mmds HTTP/1.1\r\nContent-Length: 50\r\n\r\n{}")
+            .unwrap();
+        assert!(listener.requests().unwrap().is_empty());
+
+        let mut buf = [0; 15];
+        client.read_exact(&mut buf).unwrap();
+        assert_eq!(&buf, b"HTTP/1.1 400 \r\n");
+    }
+
+    #[test]
+    fn test_too_many_connections() {
+        let (_tmp_dir, path, mut listener) = listener();
+
+        let _clients = (0..MAX_CONNECTIONS)
+            .map(|_| UnixStream::connect(&path).unwrap())
+            .collect::<Vec<_>>();
+        while listener.connections.len() < MAX_CONNECTIONS {
+            listener.requests().unwrap();
+        }
+
+        // The new client is told that the server is full and disconnected.
+        let mut client = UnixStream::connect(&path).unwrap();
+        listener.requests().unwrap();
+        assert_eq!(listener.connections.len(), MAX_CONNECTIONS);
+        let mut response = Vec::new();
+        client.read_to_end(&mut response).unwrap();
+        assert!(response.starts_with(b"HTTP/1.1 503 \r\n"));
+    }
+
+    #[test]
+    fn test_kill_switch() {
+        let (_tmp_dir, _path, mut listener) = listener();
+        let kill_switch = EventFd::new(libc::EFD_NONBLOCK).unwrap();
+        listener
+            .add_kill_switch(kill_switch.try_clone().unwrap())
+            .unwrap();
+
+        kill_switch.write(1).unwrap();
+        assert!(matches!(
+            listener.requests(),
+            Err(ApiListenerError::Shutdown)
+        ));
+    }
+}
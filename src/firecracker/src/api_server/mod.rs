@@ -6,12 +6,16 @@
 //! It is constructed on top of an HTTP Server that uses Unix Domain Sockets and `EPOLL` to
 //! handle multiple connections on the same thread.
 
+pub mod authorization;
+pub mod listener;
 pub mod parsed_request;
 pub mod request;
 
-use std::fmt::Debug;
-use std::sync::mpsc;
+use std::fmt::{self, Debug};
+use std::sync::{Arc, mpsc};
 
+use authorization::{Authorization, AuthorizationPolicy, PeerCredentials};
+use listener::{ApiListener, ApiListenerError};
 pub use micro_http::{Body, HttpServer, Request, Response, ServerError, StatusCode, Version};
 use parsed_request::{ParsedRequest, RequestAction};
 use serde_json::json;
@@ -19,11 +23,33 @@ use utils::time::{ClockType, get_time_us};
 use vmm::logger::{
     METRICS, ProcessTimeReporter, debug, error, info, update_metric_with_elapsed_time, warn,
 };
-use vmm::rpc_interface::{ApiRequest, ApiResponse, VmmAction};
-use vmm::seccomp::BpfProgramRef;
+use vmm::rpc_interface::{ApiRequest, ApiResponse, VmmAction, VmmActionError};
+use vmm::seccomp::{BpfProgram, BpfProgramRef};
+use vmm::vmm_config::seccomp::SeccompFilterError;
 use vmm::vmm_config::snapshot::SnapshotType;
 use vmm_sys_util::eventfd::EventFd;
 
+/// Server of the API socket.
+pub enum ApiSocket {
+    /// The `micro_http` server, used when there is no authorization policy.
+    Http(HttpServer),
+    /// A listener retrieving the credentials of the clients, which the authorization policy
+    /// needs.
+    Authorizing(ApiListener),
+}
+
+impl Debug for ApiSocket {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ApiSocket::Http(_) => f.write_str("ApiSocket::Http"),
+            ApiSocket::Authorizing(listener) => f
+                .debug_tuple("ApiSocket::Authorizing")
+                .field(listener)
+                .finish(),
+        }
+    }
+}
+
 /// Structure associated with the API server implementation.
 #[derive(Debug)]
 pub struct ApiServer {
@@ -31,9 +57,13 @@ pub struct ApiServer {
     api_request_sender: mpsc::Sender<ApiRequest>,
     /// Receiver which collects messages from the VMM.
     vmm_response_receiver: mpsc::Receiver<ApiResponse>,
+    /// Receiver of the additional seccomp filters that the API thread has to install.
+    api_filter_receiver: mpsc::Receiver<Arc<BpfProgram>>,
     /// FD on which we notify the VMM that we have sent at least one
     /// `VmmRequest`.
     to_vmm_fd: EventFd,
+    /// Policy restricting the requests that each client is allowed to issue, if any.
+    authorization_policy: Option<AuthorizationPolicy>,
 }
 
 impl ApiServer {
@@ -43,12 +73,16 @@ impl ApiServer {
     pub fn new(
         api_request_sender: mpsc::Sender<ApiRequest>,
         vmm_response_receiver: mpsc::Receiver<ApiResponse>,
+        api_filter_receiver: mpsc::Receiver<Arc<BpfProgram>>,
         to_vmm_fd: EventFd,
+        authorization_policy: Option<AuthorizationPolicy>,
     ) -> Self {
         ApiServer {
             api_request_sender,
             vmm_response_receiver,
+            api_filter_receiver,
             to_vmm_fd,
+            authorization_policy,
         }
     }
 
@@ -56,19 +90,22 @@ impl ApiServer {
     ///
     /// # Arguments
     ///
-    /// * `path` - the socket path on which the server will wait for requests.
+    /// * `socket` - the server of the socket on which the server will wait for requests.
     /// * `start_time_us` - the timestamp for when the process was started in us.
     /// * `start_time_cpu_us` - the timestamp for when the process was started in CPU us.
     /// * `seccomp_filter` - the seccomp filter to apply.
     pub fn run(
         &mut self,
-        mut server: HttpServer,
+        mut socket: ApiSocket,
         process_time_reporter: ProcessTimeReporter,
         seccomp_filter: BpfProgramRef,
         api_payload_limit: usize,
     ) {
         // Set the api payload size limit.
-        server.set_payload_max_size(api_payload_limit);
+        match &mut socket {
+            ApiSocket::Http(server) => server.set_payload_max_size(api_payload_limit),
+            ApiSocket::Authorizing(listener) => listener.set_payload_max_size(api_payload_limit),
+        }
 
         // Load seccomp filters on the API thread.
         // Execution panics if filters cannot be loaded, use --no-seccomp if skipping filters
@@ -80,7 +117,9 @@ impl ApiServer {
             );
         }
 
-        server.start_server().expect("Cannot start HTTP server");
+        if let ApiSocket::Http(server) = &mut socket {
+            server.start_server().expect("Cannot start HTTP server");
+        }
         info!("API server started.");
 
         // Store process start time metric.
@@ -88,6 +127,13 @@ impl ApiServer {
         // Store process CPU start time metric.
         process_time_reporter.report_cpu_start_time();
 
+        match socket {
+            ApiSocket::Http(server) => self.serve_http(server),
+            ApiSocket::Authorizing(listener) => self.serve_authorizing(listener),
+        }
+    }
+
+    fn serve_http(&mut self, mut server: HttpServer) {
         loop {
             let request_vec = match server.requests() {
                 Ok(vec) => vec,
@@ -105,8 +151,9 @@ impl ApiServer {
             for server_request in request_vec {
                 let request_processing_start_us = get_time_us(ClockType::Monotonic);
                 // Use `self.handle_request()` as the processing callback.
-                let response = server_request
-                    .process(|request| self.handle_request(request, request_processing_start_us));
+                let response = server_request.process(|request| {
+                    self.handle_request(request, None, request_processing_start_us)
+                });
                 if let Err(err) = server.respond(response) {
                     error!("API Server encountered an error on response: {}", err);
                 };
@@ -117,13 +164,49 @@ impl ApiServer {
         }
     }
 
-    /// Handles an API request received through the associated socket.
+    fn serve_authorizing(&mut self, mut listener: ApiListener) {
+        loop {
+            let request_vec = match listener.requests() {
+                Ok(vec) => vec,
+                Err(ApiListenerError::Shutdown) => {
+                    listener.flush_outgoing_writes();
+                    debug!("shutdown request received, API server thread ending.");
+                    return;
+                }
+                Err(err) => {
+                    // print request error, but keep server running
+                    error!("API Server error on retrieving incoming request: {}", err);
+                    continue;
+                }
+            };
+            for client_request in request_vec {
+                let request_processing_start_us = get_time_us(ClockType::Monotonic);
+                let response = self.handle_request(
+                    &client_request.request,
+                    client_request.peer_credentials,
+                    request_processing_start_us,
+                );
+                listener.respond(&client_request, response);
+
+                let delta_us = get_time_us(ClockType::Monotonic) - request_processing_start_us;
+                debug!("Total previous API call duration: {} us.", delta_us);
+            }
+        }
+    }
+
+    /// Handles an API request received through the associated socket, from the client with
+    /// `peer_credentials`.
     pub fn handle_request(
         &mut self,
         request: &Request,
+        peer_credentials: Option<PeerCredentials>,
         request_processing_start_us: u64,
     ) -> Response {
-        match ParsedRequest::try_from(request).map(|r| r.into_parts()) {
+        let authorization = match &self.authorization_policy {
+            Some(policy) => Authorization::Policy(policy, peer_credentials),
+            None => Authorization::Unrestricted,
+        };
+        match ParsedRequest::try_from(request, authorization).map(|r| r.into_parts()) {
             Ok((req_action, mut parsing_info)) => {
                 let mut response = match req_action {
                     RequestAction::Sync(vmm_action) => {
@@ -171,7 +254,16 @@ impl ApiServer {
             .send(vmm_action)
             .expect("Failed to send VMM message");
         self.to_vmm_fd.write(1).expect("Cannot update send VMM fd");
-        let vmm_outcome = *(self.vmm_response_receiver.recv().expect("VMM disconnected"));
+        let mut vmm_outcome = *(self.vmm_response_receiver.recv().expect("VMM disconnected"));
+        // The VMM sends the filter of the API thread once the vCPU and VMM threads installed
+        // theirs, so the API thread installs it before confirming the request.
+        if let Ok(filter) = self.api_filter_receiver.try_recv() {
+            if let Err(err) = vmm::seccomp::apply_filter(&filter) {
+                vmm_outcome = Err(VmmActionError::SeccompFilter(
+                    SeccompFilterError::InstallApi(err),
+                ));
+            }
+        }
         let response = ParsedRequest::convert_to_response(&vmm_outcome);
 
         if vmm_outcome.is_ok() {
@@ -211,6 +303,7 @@ mod tests {
     use vmm::rpc_interface::{VmmActionError, VmmData};
     use vmm::seccomp::get_empty_filters;
     use vmm::vmm_config::instance_info::InstanceInfo;
+    use vmm::vmm_config::seccomp::SeccompFilterConfig;
     use vmm::vmm_config::snapshot::CreateSnapshotParams;
     use vmm_sys_util::tempfile::TempFile;
 
@@ -244,8 +337,15 @@ mod tests {
         let to_vmm_fd = EventFd::new(libc::EFD_NONBLOCK).unwrap();
         let (api_request_sender, _from_api) = channel();
         let (to_api, vmm_response_receiver) = channel();
+        let (to_api_filter, api_filter_receiver) = channel();
 
-        let mut api_server = ApiServer::new(api_request_sender, vmm_response_receiver, to_vmm_fd);
+        let mut api_server = ApiServer::new(
+            api_request_sender,
+            vmm_response_receiver,
+            api_filter_receiver,
+            to_vmm_fd,
+            None,
+        );
         to_api
             .send(Box::new(Err(VmmActionError::StartMicrovm(
                 StartMicrovmError::MissingKernelConfig,
@@ -295,6 +395,18 @@ mod tests {
         assert_eq!(response.status(), StatusCode::NoContent);
         assert_ne!(METRICS.latencies_us.diff_create_snapshot.fetch(), 0);
         assert_eq!(METRICS.latencies_us.full_create_snapshot.fetch(), 0);
+
+        // The API thread installs the filter that the VMM sends before its response, and reports
+        // a failure to do so.
+        to_api_filter.send(Arc::new(vec![0xFF; 1])).unwrap();
+        to_api.send(Box::new(Ok(VmmData::Empty))).unwrap();
+        let response = api_server.serve_vmm_action_request(
+            Box::new(VmmAction::LoadSeccompFilter(SeccompFilterConfig {
+                filter_path: PathBuf::new(),
+            })),
+            0,
+        );
+        assert_eq!(response.status(), StatusCode::BadRequest);
     }
 
     #[test]
@@ -302,8 +414,15 @@ mod tests {
         let to_vmm_fd = EventFd::new(libc::EFD_NONBLOCK).unwrap();
         let (api_request_sender, _from_api) = channel();
         let (to_api, vmm_response_receiver) = channel();
+        let (_, api_filter_receiver) = channel();
 
-        let mut api_server = ApiServer::new(api_request_sender, vmm_response_receiver, to_vmm_fd);
+        let mut api_server = ApiServer::new(
+            api_request_sender,
+            vmm_response_receiver,
+            api_filter_receiver,
+            to_vmm_fd,
+            None,
+        );
 
         // Test an Actions request.
         let (mut sender, receiver) = UnixStream::pair().unwrap();
@@ -320,7 +439,7 @@ mod tests {
             .unwrap();
         connection.try_read().unwrap();
         let req = connection.pop_parsed_request().unwrap();
-        let response = api_server.handle_request(&req, 0);
+        let response = api_server.handle_request(&req, None, 0);
         assert_eq!(response.status(), StatusCode::BadRequest);
 
         // Test a Get Info request.
@@ -332,7 +451,7 @@ mod tests {
         sender.write_all(b"GET / HTTP/1.1\r\n\r\n").unwrap();
         connection.try_read().unwrap();
         let req = connection.pop_parsed_request().unwrap();
-        let response = api_server.handle_request(&req, 0);
+        let response = api_server.handle_request(&req, None, 0);
         assert_eq!(response.status(), StatusCode::OK);
 
         // Test erroneous request.
@@ -345,7 +464,7 @@ mod tests {
             .unwrap();
         connection.try_read().unwrap();
         let req = connection.pop_parsed_request().unwrap();
-        let response = api_server.handle_request(&req, 0);
+        let response = api_server.handle_request(&req, None, 0);
         assert_eq!(response.status(), StatusCode::BadRequest);
     }
 
@@ -382,13 +501,21 @@ mod tests {
         let to_vmm_fd = EventFd::new(libc::EFD_NONBLOCK).unwrap();
         let (api_request_sender, _from_api) = channel();
         let (to_api, vmm_response_receiver) = channel();
+        let (_, api_filter_receiver) = channel();
         let seccomp_filters = get_empty_filters();
         let server = HttpServer::new(PathBuf::from(api_thread_path_to_socket)).unwrap();
         thread::Builder::new()
             .name("fc_api_test".to_owned())
             .spawn(move || {
-                ApiServer::new(api_request_sender, vmm_response_receiver, to_vmm_fd).run(
-                    server,
+                ApiServer::new(
+                    api_request_sender,
+                    vmm_response_receiver,
+                    api_filter_receiver,
+                    to_vmm_fd,
+                    None,
+                )
+                .run(
+                    ApiSocket::Http(server),
                     ProcessTimeReporter::new(Some(1), Some(1), Some(1)),
                     seccomp_filters.get("api").unwrap(),
                     vmm::HTTP_MAX_PAYLOAD_SIZE,
@@ -424,14 +551,22 @@ mod tests {
         let to_vmm_fd = EventFd::new(libc::EFD_NONBLOCK).unwrap();
         let (api_request_sender, _from_api) = channel();
         let (_to_api, vmm_response_receiver) = channel();
+        let (_, api_filter_receiver) = channel();
         let seccomp_filters = get_empty_filters();
 
         let server = HttpServer::new(PathBuf::from(api_thread_path_to_socket)).unwrap();
         thread::Builder::new()
             .name("fc_api_test".to_owned())
             .spawn(move || {
-                ApiServer::new(api_request_sender, vmm_response_receiver, to_vmm_fd).run(
-                    server,
+                ApiServer::new(
+                    api_request_sender,
+                    vmm_response_receiver,
+                    api_filter_receiver,
+                    to_vmm_fd,
+                    None,
+                )
+                .run(
+                    ApiSocket::Http(server),
                     ProcessTimeReporter::new(Some(1), Some(1), Some(1)),
                     seccomp_filters.get("api").unwrap(),
                     50,
@@ -460,6 +595,54 @@ mod tests {
         assert_eq!(&buf[..], &error_message[..]);
     }
 
+    #[test]
+    fn test_bind_and_run_with_policy() {
+        let mut tmp_socket = TempFile::new().unwrap();
+        tmp_socket.remove().unwrap();
+        let path_to_socket = tmp_socket.as_path().to_str().unwrap().to_owned();
+        let api_thread_path_to_socket = path_to_socket.clone();
+
+        let to_vmm_fd = EventFd::new(libc::EFD_NONBLOCK).unwrap();
+        let (api_request_sender, _from_api) = channel();
+        let (_to_api, vmm_response_receiver) = channel();
+        let (_, api_filter_receiver) = channel();
+        let seccomp_filters = get_empty_filters();
+        // A policy which only applies to another user, so the requests of this one are denied.
+        // SAFETY: getuid is always successful.
+        let uid = unsafe { libc::getuid() };
+        let policy: AuthorizationPolicy = serde_json::from_str(&format!(
+            r#"{{"rules": [{{"uid": {}, "allow": [{{"method": "GET", "path": "/"}}]}}]}}"#,
+            uid.wrapping_add(1)
+        ))
+        .unwrap();
+
+        let listener = ApiListener::new(&PathBuf::from(api_thread_path_to_socket)).unwrap();
+        thread::Builder::new()
+            .name("fc_api_test".to_owned())
+            .spawn(move || {
+                ApiServer::new(
+                    api_request_sender,
+                    vmm_response_receiver,
+                    api_filter_receiver,
+                    to_vmm_fd,
+                    Some(policy),
+                )
+                .run(
+                    ApiSocket::Authorizing(listener),
+                    ProcessTimeReporter::new(Some(1), Some(1), Some(1)),
+                    seccomp_filters.get("api").unwrap(),
+                    vmm::HTTP_MAX_PAYLOAD_SIZE,
+                )
+            })
+            .unwrap();
+
+        let mut sock = UnixStream::connect(PathBuf::from(path_to_socket)).unwrap();
+        sock.write_all(b"GET / HTTP/1.1\r\n\r\n").unwrap();
+        let mut buf: [u8; 15] = [0; 15];
+        sock.read_exact(&mut buf[..]).unwrap();
+        assert_eq!(&buf, b"HTTP/1.1 403 \r\n");
+    }
+
     #[test]
     fn test_kill_switch() {
         let mut tmp_socket = TempFile::new().unwrap();
@@ -469,6 +652,7 @@ mod tests {
         let to_vmm_fd = EventFd::new(libc::EFD_NONBLOCK).unwrap();
         let (api_request_sender, _from_api) = channel();
         let (_to_api, vmm_response_receiver) = channel();
+        let (_, api_filter_receiver) = channel();
         let seccomp_filters = get_empty_filters();
 
         let api_kill_switch = EventFd::new(libc::EFD_NONBLOCK).unwrap();
@@ -480,8 +664,15 @@ mod tests {
         let api_thread = thread::Builder::new()
             .name("fc_api_test".to_owned())
             .spawn(move || {
-                ApiServer::new(api_request_sender, vmm_response_receiver, to_vmm_fd).run(
-                    server,
+                ApiServer::new(
+                    api_request_sender,
+                    vmm_response_receiver,
+                    api_filter_receiver,
+                    to_vmm_fd,
+                    None,
+                )
+                .run(
+                    ApiSocket::Http(server),
                     ProcessTimeReporter::new(Some(1), Some(1), Some(1)),
                     seccomp_filters.get("api").unwrap(),
                     vmm::HTTP_MAX_PAYLOAD_SIZE,
@@ -6,16 +6,20 @@ use std::fmt::Debug;
 use micro_http::{Body, Method, Request, Response, StatusCode, Version};
 use serde::ser::Serialize;
 use serde_json::Value;
-use vmm::logger::{Level, error, info, log_enabled};
+use vmm::logger::{IncMetric, Level, METRICS, error, info, log_enabled};
 use vmm::rpc_interface::{VmmAction, VmmActionError, VmmData};
 
 use super::ApiServer;
+use super::authorization::Authorization;
 use super::request::actions::parse_put_actions;
 use super::request::balloon::{parse_get_balloon, parse_patch_balloon, parse_put_balloon};
 use super::request::boot_source::parse_put_boot_source;
+use super::request::console::parse_put_console_port;
+use super::request::coredump::parse_put_vm_coredump;
 use super::request::cpu_configuration::parse_put_cpu_config;
 use super::request::drive::{parse_patch_drive, parse_put_drive};
 use super::request::entropy::parse_put_entropy;
+use super::request::fs::parse_put_fs;
 use super::request::instance_info::parse_get_instance_info;
 use super::request::logger::parse_put_logger;
 use super::request::machine_configuration::{
@@ -24,9 +28,14 @@ use super::request::machine_configuration::{
 use super::request::metrics::parse_put_metrics;
 use super::request::mmds::{parse_get_mmds, parse_patch_mmds, parse_put_mmds};
 use super::request::net::{parse_patch_net, parse_put_net};
+use super::request::pmem::parse_put_pmem;
+use super::request::pvpanic::parse_put_pvpanic;
+use super::request::seccomp::parse_put_seccomp;
 use super::request::snapshot::{parse_patch_vm_state, parse_put_snapshot};
 use super::request::version::parse_get_version;
+use super::request::vm_config::parse_put_vm_config;
 use super::request::vsock::parse_put_vsock;
+use super::request::watchdog::parse_put_watchdog;
 
 #[derive(Debug)]
 pub(crate) enum RequestAction {
@@ -57,10 +66,17 @@ pub(crate) struct ParsedRequest {
     parsing_info: ParsingInfo,
 }
 
-impl TryFrom<&Request> for ParsedRequest {
-    type Error = RequestError;
-    fn try_from(request: &Request) -> Result<Self, Self::Error> {
+impl ParsedRequest {
+    /// Parses `request`, after checking that `authorization` allows it.
+    pub(crate) fn try_from(
+        request: &Request,
+        authorization: Authorization<'_>,
+    ) -> Result<Self, RequestError> {
         let request_uri = request.uri().get_abs_path().to_string();
+        if !authorization.allows(request.method(), &request_uri) {
+            METRICS.api_server.denied_request_count.inc();
+            return Err(RequestError::Forbidden(request_uri, request.method()));
+        }
         let description = describe(
             request.method(),
             request_uri.as_str(),
@@ -87,16 +103,34 @@ impl TryFrom<&Request> for ParsedRequest {
             (Method::Put, "actions", Some(body)) => parse_put_actions(body),
             (Method::Put, "balloon", Some(body)) => parse_put_balloon(body),
             (Method::Put, "boot-source", Some(body)) => parse_put_boot_source(body),
+            (Method::Put, "console-ports", Some(body)) => {
+                parse_put_console_port(body, path_tokens.next())
+            }
             (Method::Put, "cpu-config", Some(body)) => parse_put_cpu_config(body),
             (Method::Put, "drives", Some(body)) => parse_put_drive(body, path_tokens.next()),
+            (Method::Put, "fs", Some(body)) => parse_put_fs(body, path_tokens.next()),
             (Method::Put, "logger", Some(body)) => parse_put_logger(body),
-            (Method::Put, "machine-config", Some(body)) => parse_put_machine_config(body),
+            (Method::Put, "machine-config", Some(body)) => {
+                parse_put_machine_config(body, &request.files)
+            }
             (Method::Put, "metrics", Some(body)) => parse_put_metrics(body),
             (Method::Put, "mmds", Some(body)) => parse_put_mmds(body, path_tokens.next()),
             (Method::Put, "network-interfaces", Some(body)) => {
                 parse_put_net(body, path_tokens.next())
             }
+            (Method::Put, "pmem", Some(body)) => parse_put_pmem(body, path_tokens.next()),
+            (Method::Put, "pvpanic", Some(body)) => parse_put_pvpanic(body),
+            (Method::Put, "watchdog", Some(body)) => parse_put_watchdog(body),
+            (Method::Put, "seccomp", Some(body)) => parse_put_seccomp(body),
             (Method::Put, "snapshot", Some(body)) => parse_put_snapshot(body, path_tokens.next()),
+            (Method::Put, "vm", Some(body)) => match path_tokens.next() {
+                Some("config") => parse_put_vm_config(body),
+                Some("coredump") => parse_put_vm_coredump(body),
+                _ => Err(RequestError::InvalidPathMethod(
+                    path.to_string(),
+                    Method::Put,
+                )),
+            },
             (Method::Put, "vsock", Some(body)) => parse_put_vsock(body),
             (Method::Put, "entropy", Some(body)) => parse_put_entropy(body),
             (Method::Put, _, None) => method_to_error(Method::Put),
@@ -115,9 +149,7 @@ impl TryFrom<&Request> for ParsedRequest {
             )),
         }
     }
-}
 
-impl ParsedRequest {
     pub(crate) fn new(action: RequestAction) -> Self {
         Self {
             action,
@@ -278,6 +310,9 @@ pub(crate) enum RequestError {
     // The HTTP method & request path combination is not valid.
     #[error("Invalid request method and/or path: {} {}.", .1.to_str(), .0)]
     InvalidPathMethod(String, Method),
+    // The client is not allowed to issue the request by the authorization policy.
+    #[error("Request not allowed by the API authorization policy: {} {}.", .1.to_str(), .0)]
+    Forbidden(String, Method),
     // An error occurred when deserializing the json body of a request.
     #[error("An error occurred when deserializing the json body of a request: {0}.")]
     SerdeJson(#[from] serde_json::Error),
@@ -289,6 +324,7 @@ impl From<RequestError> for Response {
         let msg = ApiServer::json_fault_message(format!("{}", err));
         match err {
             RequestError::Generic(status, _) => ApiServer::json_response(status, msg),
+            RequestError::Forbidden(_, _) => ApiServer::json_response(StatusCode::Forbidden, msg),
             RequestError::EmptyID
             | RequestError::InvalidID
             | RequestError::InvalidPathMethod(_, _)
@@ -328,6 +364,7 @@ pub mod tests {
     use vmm::vmm_config::machine_config::MachineConfig;
 
     use super::*;
+    use crate::api_server::authorization::{AuthorizationPolicy, PeerCredentials};
 
     impl PartialEq for ParsedRequest {
         fn eq(&self, other: &ParsedRequest) -> bool {
@@ -406,7 +443,7 @@ pub mod tests {
         connection.try_read().unwrap();
 
         let req = connection.pop_parsed_request().unwrap();
-        ParsedRequest::try_from(&req).unwrap_err();
+        ParsedRequest::try_from(&req, Authorization::Unrestricted).unwrap_err();
     }
 
     #[test]
@@ -433,7 +470,7 @@ pub mod tests {
             .unwrap();
         connection.try_read().unwrap();
         let req = connection.pop_parsed_request().unwrap();
-        let parsed_request = ParsedRequest::try_from(&req);
+        let parsed_request = ParsedRequest::try_from(&req, Authorization::Unrestricted);
         assert!(matches!(
             &parsed_request,
             Err(RequestError::Generic(StatusCode::BadRequest, s)) if s == "GET request cannot have a body.",
@@ -449,7 +486,7 @@ pub mod tests {
             .unwrap();
         connection.try_read().unwrap();
         let req = connection.pop_parsed_request().unwrap();
-        let parsed_request = ParsedRequest::try_from(&req);
+        let parsed_request = ParsedRequest::try_from(&req, Authorization::Unrestricted);
         assert!(matches!(
             &parsed_request,
             Err(RequestError::Generic(StatusCode::BadRequest, s)) if s == "Empty PUT request.",
@@ -465,13 +502,61 @@ pub mod tests {
             .unwrap();
         connection.try_read().unwrap();
         let req = connection.pop_parsed_request().unwrap();
-        let parsed_request = ParsedRequest::try_from(&req);
+        let parsed_request = ParsedRequest::try_from(&req, Authorization::Unrestricted);
         assert!(matches!(
             &parsed_request,
             Err(RequestError::Generic(StatusCode::BadRequest, s)) if s == "Empty PATCH request.",
         ));
     }
 
+    #[test]
+    fn test_try_from_forbidden() {
+        let policy: AuthorizationPolicy = serde_json::from_str(
+            r#"{"rules": [{"uid": 1000, "allow": [{"method": "GET", "path": "/"}]}]}"#,
+        )
+        .unwrap();
+        let peer = PeerCredentials {
+            pid: 1,
+            uid: 1000,
+            gid: 1000,
+        };
+        let other_peer = PeerCredentials { uid: 0, ..peer };
+
+        let (mut sender, receiver) = UnixStream::pair().unwrap();
+        let mut connection = HttpConnection::new(receiver);
+        sender
+            .write_all(http_request("GET", "/", None).as_bytes())
+            .unwrap();
+        connection.try_read().unwrap();
+        let req = connection.pop_parsed_request().unwrap();
+        ParsedRequest::try_from(&req, Authorization::Policy(&policy, Some(peer))).unwrap();
+
+        let denied_count = METRICS.api_server.denied_request_count.count();
+        assert!(matches!(
+            ParsedRequest::try_from(&req, Authorization::Policy(&policy, Some(other_peer))),
+            Err(RequestError::Forbidden(path, Method::Get)) if path == "/"
+        ));
+        assert!(matches!(
+            ParsedRequest::try_from(&req, Authorization::Policy(&policy, None)),
+            Err(RequestError::Forbidden(_, _))
+        ));
+        assert_eq!(
+            METRICS.api_server.denied_request_count.count(),
+            denied_count + 2
+        );
+
+        sender
+            .write_all(http_request("GET", "/vm/config", None).as_bytes())
+            .unwrap();
+        connection.try_read().unwrap();
+        let req = connection.pop_parsed_request().unwrap();
+        let response: Response =
+            ParsedRequest::try_from(&req, Authorization::Policy(&policy, Some(peer)))
+                .unwrap_err()
+                .into();
+        assert_eq!(response.status(), StatusCode::Forbidden);
+    }
+
     #[test]
     fn test_error_into_response() {
         // Generic error.
@@ -611,7 +696,7 @@ pub mod tests {
             .unwrap();
         connection.try_read().unwrap();
         let req = connection.pop_parsed_request().unwrap();
-        ParsedRequest::try_from(&req).unwrap();
+        ParsedRequest::try_from(&req, Authorization::Unrestricted).unwrap();
     }
 
     #[test]
@@ -623,7 +708,7 @@ pub mod tests {
             .unwrap();
         connection.try_read().unwrap();
         let req = connection.pop_parsed_request().unwrap();
-        ParsedRequest::try_from(&req).unwrap();
+        ParsedRequest::try_from(&req, Authorization::Unrestricted).unwrap();
     }
 
     #[test]
@@ -635,7 +720,7 @@ pub mod tests {
             .unwrap();
         connection.try_read().unwrap();
         let req = connection.pop_parsed_request().unwrap();
-        ParsedRequest::try_from(&req).unwrap();
+        ParsedRequest::try_from(&req, Authorization::Unrestricted).unwrap();
     }
 
     #[test]
@@ -647,7 +732,7 @@ pub mod tests {
             .unwrap();
         connection.try_read().unwrap();
         let req = connection.pop_parsed_request().unwrap();
-        ParsedRequest::try_from(&req).unwrap();
+        ParsedRequest::try_from(&req, Authorization::Unrestricted).unwrap();
     }
 
     #[test]
@@ -659,7 +744,7 @@ pub mod tests {
             .unwrap();
         connection.try_read().unwrap();
         let req = connection.pop_parsed_request().unwrap();
-        ParsedRequest::try_from(&req).unwrap();
+        ParsedRequest::try_from(&req, Authorization::Unrestricted).unwrap();
     }
 
     #[test]
@@ -671,7 +756,7 @@ pub mod tests {
             .unwrap();
         connection.try_read().unwrap();
         let req = connection.pop_parsed_request().unwrap();
-        ParsedRequest::try_from(&req).unwrap();
+        ParsedRequest::try_from(&req, Authorization::Unrestricted).unwrap();
     }
 
     #[test]
@@ -684,7 +769,7 @@ pub mod tests {
             .unwrap();
         connection.try_read().unwrap();
         let req = connection.pop_parsed_request().unwrap();
-        ParsedRequest::try_from(&req).unwrap();
+        ParsedRequest::try_from(&req, Authorization::Unrestricted).unwrap();
     }
 
     #[test]
@@ -698,7 +783,7 @@ pub mod tests {
             .unwrap();
         connection.try_read().unwrap();
         let req = connection.pop_parsed_request().unwrap();
-        ParsedRequest::try_from(&req).unwrap();
+        ParsedRequest::try_from(&req, Authorization::Unrestricted).unwrap();
     }
 
     #[test]
@@ -713,7 +798,72 @@ pub mod tests {
             .unwrap();
         connection.try_read().unwrap();
         let req = connection.pop_parsed_request().unwrap();
-        ParsedRequest::try_from(&req).unwrap();
+        ParsedRequest::try_from(&req, Authorization::Unrestricted).unwrap();
+    }
+
+    #[test]
+    fn test_try_from_put_fs() {
+        let (mut sender, receiver) = UnixStream::pair().unwrap();
+        let mut connection = HttpConnection::new(receiver);
+        let body = "{ \"fs_id\": \"string\", \"socket\": \"string\", \"tag\": \"string\" }";
+        sender
+            .write_all(http_request("PUT", "/fs/string", Some(body)).as_bytes())
+            .unwrap();
+        connection.try_read().unwrap();
+        let req = connection.pop_parsed_request().unwrap();
+        ParsedRequest::try_from(&req, Authorization::Unrestricted).unwrap();
+    }
+
+    #[test]
+    fn test_try_from_put_console_port() {
+        let (mut sender, receiver) = UnixStream::pair().unwrap();
+        let mut connection = HttpConnection::new(receiver);
+        let body = "{ \"port_id\": \"string\", \"name\": \"string\", \"file_path\": \"string\" }";
+        sender
+            .write_all(http_request("PUT", "/console-ports/string", Some(body)).as_bytes())
+            .unwrap();
+        connection.try_read().unwrap();
+        let req = connection.pop_parsed_request().unwrap();
+        ParsedRequest::try_from(&req, Authorization::Unrestricted).unwrap();
+    }
+
+    #[test]
+    fn test_try_from_put_pmem() {
+        let (mut sender, receiver) = UnixStream::pair().unwrap();
+        let mut connection = HttpConnection::new(receiver);
+        let body = "{ \"id\": \"string\", \"path_on_host\": \"string\" }";
+        sender
+            .write_all(http_request("PUT", "/pmem/string", Some(body)).as_bytes())
+            .unwrap();
+        connection.try_read().unwrap();
+        let req = connection.pop_parsed_request().unwrap();
+        ParsedRequest::try_from(&req, Authorization::Unrestricted).unwrap();
+    }
+
+    #[test]
+    fn test_try_from_put_pvpanic() {
+        let (mut sender, receiver) = UnixStream::pair().unwrap();
+        let mut connection = HttpConnection::new(receiver);
+        let body = "{ \"on_panic\": \"Exit\" }";
+        sender
+            .write_all(http_request("PUT", "/pvpanic", Some(body)).as_bytes())
+            .unwrap();
+        connection.try_read().unwrap();
+        let req = connection.pop_parsed_request().unwrap();
+        ParsedRequest::try_from(&req, Authorization::Unrestricted).unwrap();
+    }
+
+    #[test]
+    fn test_try_from_put_watchdog() {
+        let (mut sender, receiver) = UnixStream::pair().unwrap();
+        let mut connection = HttpConnection::new(receiver);
+        let body = "{ \"action\": \"Pause\" }";
+        sender
+            .write_all(http_request("PUT", "/watchdog", Some(body)).as_bytes())
+            .unwrap();
+        connection.try_read().unwrap();
+        let req = connection.pop_parsed_request().unwrap();
+        ParsedRequest::try_from(&req, Authorization::Unrestricted).unwrap();
     }
 
     #[test]
@@ -726,7 +876,7 @@ pub mod tests {
             .unwrap();
         connection.try_read().unwrap();
         let req = connection.pop_parsed_request().unwrap();
-        ParsedRequest::try_from(&req).unwrap();
+        ParsedRequest::try_from(&req, Authorization::Unrestricted).unwrap();
     }
 
     #[test]
@@ -743,7 +893,7 @@ pub mod tests {
             .unwrap();
         connection.try_read().unwrap();
         let req = connection.pop_parsed_request().unwrap();
-        ParsedRequest::try_from(&req).unwrap();
+        ParsedRequest::try_from(&req, Authorization::Unrestricted).unwrap();
     }
 
     #[test]
@@ -757,7 +907,7 @@ pub mod tests {
             .unwrap();
         connection.try_read().unwrap();
         let req = connection.pop_parsed_request().unwrap();
-        ParsedRequest::try_from(&req).unwrap();
+        ParsedRequest::try_from(&req, Authorization::Unrestricted).unwrap();
     }
 
     #[test]
@@ -770,7 +920,7 @@ pub mod tests {
             .unwrap();
         connection.try_read().unwrap();
         let req = connection.pop_parsed_request().unwrap();
-        ParsedRequest::try_from(&req).unwrap();
+        ParsedRequest::try_from(&req, Authorization::Unrestricted).unwrap();
     }
 
     #[test]
@@ -783,7 +933,7 @@ pub mod tests {
             .unwrap();
         connection.try_read().unwrap();
         let req = connection.pop_parsed_request().unwrap();
-        ParsedRequest::try_from(&req).unwrap();
+        ParsedRequest::try_from(&req, Authorization::Unrestricted).unwrap();
     }
 
     #[test]
@@ -797,7 +947,7 @@ pub mod tests {
             .unwrap();
         connection.try_read().unwrap();
         let req = connection.pop_parsed_request().unwrap();
-        ParsedRequest::try_from(&req).unwrap();
+        ParsedRequest::try_from(&req, Authorization::Unrestricted).unwrap();
 
         let body = "{\"foo\":\"bar\"}";
         sender
@@ -805,7 +955,7 @@ pub mod tests {
             .unwrap();
         connection.try_read().unwrap();
         let req = connection.pop_parsed_request().unwrap();
-        ParsedRequest::try_from(&req).unwrap();
+        ParsedRequest::try_from(&req, Authorization::Unrestricted).unwrap();
 
         // `/mmds/config`
         let body = "{ \"ipv4_address\": \"169.254.170.2\", \"network_interfaces\": [\"iface0\"] }";
@@ -814,7 +964,7 @@ pub mod tests {
             .unwrap();
         connection.try_read().unwrap();
         let req = connection.pop_parsed_request().unwrap();
-        ParsedRequest::try_from(&req).unwrap();
+        ParsedRequest::try_from(&req, Authorization::Unrestricted).unwrap();
     }
 
     #[test]
@@ -833,7 +983,7 @@ pub mod tests {
             .unwrap();
         connection.try_read().unwrap();
         let req = connection.pop_parsed_request().unwrap();
-        ParsedRequest::try_from(&req).unwrap();
+        ParsedRequest::try_from(&req, Authorization::Unrestricted).unwrap();
     }
 
     #[test]
@@ -846,7 +996,7 @@ pub mod tests {
             .unwrap();
         connection.try_read().unwrap();
         let req = connection.pop_parsed_request().unwrap();
-        ParsedRequest::try_from(&req).unwrap();
+        ParsedRequest::try_from(&req, Authorization::Unrestricted).unwrap();
 
         let body = "{ \"snapshot_path\": \"foo\", \"mem_backend\": { \"backend_type\": \"File\", \
                     \"backend_path\": \"bar\" }, \"enable_diff_snapshots\": true }";
@@ -856,7 +1006,7 @@ pub mod tests {
 
         connection.try_read().unwrap();
         let req = connection.pop_parsed_request().unwrap();
-        ParsedRequest::try_from(&req).unwrap();
+        ParsedRequest::try_from(&req, Authorization::Unrestricted).unwrap();
 
         let body =
             "{ \"snapshot_path\": \"foo\", \"mem_file_path\": \"bar\", \"resume_vm\": true }";
@@ -866,7 +1016,7 @@ pub mod tests {
 
         connection.try_read().unwrap();
         let req = connection.pop_parsed_request().unwrap();
-        ParsedRequest::try_from(&req).unwrap();
+        ParsedRequest::try_from(&req, Authorization::Unrestricted).unwrap();
     }
 
     #[test]
@@ -879,7 +1029,7 @@ pub mod tests {
             .unwrap();
         connection.try_read().unwrap();
         let req = connection.pop_parsed_request().unwrap();
-        ParsedRequest::try_from(&req).unwrap();
+        ParsedRequest::try_from(&req, Authorization::Unrestricted).unwrap();
     }
 
     #[test]
@@ -892,7 +1042,41 @@ pub mod tests {
             .unwrap();
         connection.try_read().unwrap();
         let req = connection.pop_parsed_request().unwrap();
-        ParsedRequest::try_from(&req).unwrap();
+        ParsedRequest::try_from(&req, Authorization::Unrestricted).unwrap();
+    }
+
+    #[test]
+    fn test_try_from_put_vm_config() {
+        let (mut sender, receiver) = UnixStream::pair().unwrap();
+        let mut connection = HttpConnection::new(receiver);
+        let body = "{ \"boot-source\": { \"kernel_image_path\": \"string\" }, \"drives\": [] }";
+        sender
+            .write_all(http_request("PUT", "/vm/config", Some(body)).as_bytes())
+            .unwrap();
+        connection.try_read().unwrap();
+        let req = connection.pop_parsed_request().unwrap();
+        ParsedRequest::try_from(&req, Authorization::Unrestricted).unwrap();
+
+        // Only the `config` and `coredump` sub-resources can be PUT.
+        sender
+            .write_all(http_request("PUT", "/vm", Some(body)).as_bytes())
+            .unwrap();
+        connection.try_read().unwrap();
+        let req = connection.pop_parsed_request().unwrap();
+        ParsedRequest::try_from(&req, Authorization::Unrestricted).unwrap_err();
+    }
+
+    #[test]
+    fn test_try_from_put_vm_coredump() {
+        let (mut sender, receiver) = UnixStream::pair().unwrap();
+        let mut connection = HttpConnection::new(receiver);
+        let body = "{ \"dump_path\": \"string\" }";
+        sender
+            .write_all(http_request("PUT", "/vm/coredump", Some(body)).as_bytes())
+            .unwrap();
+        connection.try_read().unwrap();
+        let req = connection.pop_parsed_request().unwrap();
+        ParsedRequest::try_from(&req, Authorization::Unrestricted).unwrap();
     }
 
     #[test]
@@ -905,14 +1089,14 @@ pub mod tests {
             .unwrap();
         connection.try_read().unwrap();
         let req = connection.pop_parsed_request().unwrap();
-        ParsedRequest::try_from(&req).unwrap();
+        ParsedRequest::try_from(&req, Authorization::Unrestricted).unwrap();
         let body = "{ \"stats_polling_interval_s\": 1 }";
         sender
             .write_all(http_request("PATCH", "/balloon/statistics", Some(body)).as_bytes())
             .unwrap();
         connection.try_read().unwrap();
         let req = connection.pop_parsed_request().unwrap();
-        ParsedRequest::try_from(&req).unwrap();
+        ParsedRequest::try_from(&req, Authorization::Unrestricted).unwrap();
     }
 
     #[test]
@@ -925,7 +1109,7 @@ pub mod tests {
             .unwrap();
         connection.try_read().unwrap();
         let req = connection.pop_parsed_request().unwrap();
-        ParsedRequest::try_from(&req).unwrap();
+        ParsedRequest::try_from(&req, Authorization::Unrestricted).unwrap();
     }
 
     #[test]
@@ -938,7 +1122,7 @@ pub mod tests {
             .unwrap();
         connection.try_read().unwrap();
         let req = connection.pop_parsed_request().unwrap();
-        ParsedRequest::try_from(&req).unwrap();
+        ParsedRequest::try_from(&req, Authorization::Unrestricted).unwrap();
         let body =
             "{ \"vcpu_count\": 1, \"mem_size_mib\": 1, \"smt\": false, \"cpu_template\": \"C3\" }";
         sender
@@ -947,9 +1131,9 @@ pub mod tests {
         connection.try_read().unwrap();
         let req = connection.pop_parsed_request().unwrap();
         #[cfg(target_arch = "x86_64")]
-        ParsedRequest::try_from(&req).unwrap();
+        ParsedRequest::try_from(&req, Authorization::Unrestricted).unwrap();
         #[cfg(target_arch = "aarch64")]
-        ParsedRequest::try_from(&req).unwrap_err();
+        ParsedRequest::try_from(&req, Authorization::Unrestricted).unwrap_err();
     }
 
     #[test]
@@ -969,7 +1153,7 @@ pub mod tests {
         result.unwrap();
         connection.try_read().unwrap();
         let req = connection.pop_parsed_request().unwrap();
-        let request_result = ParsedRequest::try_from(&req);
+        let request_result = ParsedRequest::try_from(&req, Authorization::Unrestricted);
         assert!(request_result.is_ok(), "{}", request_result.err().unwrap());
     }
 
@@ -982,7 +1166,7 @@ pub mod tests {
             .unwrap();
         connection.try_read().unwrap();
         let req = connection.pop_parsed_request().unwrap();
-        ParsedRequest::try_from(&req).unwrap();
+        ParsedRequest::try_from(&req, Authorization::Unrestricted).unwrap();
     }
 
     #[test]
@@ -995,6 +1179,6 @@ pub mod tests {
             .unwrap();
         connection.try_read().unwrap();
         let req = connection.pop_parsed_request().unwrap();
-        ParsedRequest::try_from(&req).unwrap();
+        ParsedRequest::try_from(&req, Authorization::Unrestricted).unwrap();
     }
 }
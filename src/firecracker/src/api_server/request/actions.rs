@@ -6,15 +6,14 @@ use vmm::logger::{IncMetric, METRICS};
 use vmm::rpc_interface::VmmAction;
 
 use super::super::parsed_request::{ParsedRequest, RequestError};
-use super::Body;
-#[cfg(target_arch = "aarch64")]
-use super::StatusCode;
+use super::{Body, StatusCode};
 
 // The names of the members from this enum must precisely correspond (as a string) to the possible
 // values of "action_type" from the json request body. This is useful to get a strongly typed
 // struct from the Serde deserialization process.
 #[derive(Debug, Deserialize, Serialize)]
 enum ActionType {
+    AttachDebugger,
     FlushMetrics,
     InstanceStart,
     SendCtrlAltDel,
@@ -26,6 +25,7 @@ enum ActionType {
 #[serde(deny_unknown_fields)]
 struct ActionBody {
     action_type: ActionType,
+    gdb_socket_path: Option<String>,
 }
 
 pub(crate) fn parse_put_actions(body: &Body) -> Result<ParsedRequest, RequestError> {
@@ -34,7 +34,42 @@ pub(crate) fn parse_put_actions(body: &Body) -> Result<ParsedRequest, RequestErr
         METRICS.put_api_requests.actions_fails.inc();
     })?;
 
+    if action_body.gdb_socket_path.is_some()
+        && !matches!(action_body.action_type, ActionType::AttachDebugger)
+    {
+        METRICS.put_api_requests.actions_fails.inc();
+        return Err(RequestError::Generic(
+            StatusCode::BadRequest,
+            "gdb_socket_path is only allowed with the AttachDebugger action.".to_string(),
+        ));
+    }
+
     match action_body.action_type {
+        ActionType::AttachDebugger => {
+            let Some(gdb_socket_path) = action_body.gdb_socket_path else {
+                METRICS.put_api_requests.actions_fails.inc();
+                return Err(RequestError::Generic(
+                    StatusCode::BadRequest,
+                    "AttachDebugger requires a gdb_socket_path.".to_string(),
+                ));
+            };
+
+            // Debugging is only built in with the gdb feature.
+            #[cfg(not(feature = "gdb"))]
+            {
+                let _ = gdb_socket_path;
+                METRICS.put_api_requests.actions_fails.inc();
+                return Err(RequestError::Generic(
+                    StatusCode::BadRequest,
+                    "AttachDebugger is only supported by builds with the gdb feature.".to_string(),
+                ));
+            }
+
+            #[cfg(feature = "gdb")]
+            Ok(ParsedRequest::new_sync(VmmAction::AttachDebugger(
+                gdb_socket_path,
+            )))
+        }
         ActionType::FlushMetrics => Ok(ParsedRequest::new_sync(VmmAction::FlushMetrics)),
         ActionType::InstanceStart => Ok(ParsedRequest::new_sync(VmmAction::StartMicroVm)),
         ActionType::SendCtrlAltDel => {
@@ -99,5 +134,41 @@ mod tests {
             let result = parse_put_actions(&Body::new(json));
             assert_eq!(result.unwrap(), req);
         }
+
+        {
+            let json = r#"{
+                "action_type": "AttachDebugger",
+                "gdb_socket_path": "/tmp/gdb.socket"
+            }"#;
+
+            #[cfg(feature = "gdb")]
+            assert_eq!(
+                parse_put_actions(&Body::new(json)).unwrap(),
+                ParsedRequest::new_sync(VmmAction::AttachDebugger("/tmp/gdb.socket".to_string()))
+            );
+            #[cfg(not(feature = "gdb"))]
+            {
+                let fails = METRICS.put_api_requests.actions_fails.count();
+                parse_put_actions(&Body::new(json)).unwrap_err();
+                assert!(METRICS.put_api_requests.actions_fails.count() > fails);
+            }
+        }
+
+        {
+            let json = r#"{
+                "action_type": "AttachDebugger"
+            }"#;
+
+            parse_put_actions(&Body::new(json)).unwrap_err();
+        }
+
+        {
+            let json = r#"{
+                "action_type": "FlushMetrics",
+                "gdb_socket_path": "/tmp/gdb.socket"
+            }"#;
+
+            parse_put_actions(&Body::new(json)).unwrap_err();
+        }
     }
 }
@@ -34,6 +34,8 @@ mod tests {
             kernel_image_path: String::from("/foo/bar"),
             initrd_path: Some(String::from("/bar/foo")),
             boot_args: Some(String::from("foobar")),
+            firmware_path: None,
+            firmware_vars_path: None,
         };
         let parsed_req = parse_put_boot_source(&Body::new(body)).unwrap();
 
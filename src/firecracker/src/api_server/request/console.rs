@@ -0,0 +1,68 @@
+// Copyright 2025 Amazon.com, Inc. or its affiliates. All Rights Reserved.
+// SPDX-License-Identifier: Apache-2.0
+
+use vmm::rpc_interface::VmmAction;
+use vmm::vmm_config::console::ConsolePortConfig;
+
+use super::super::parsed_request::{ParsedRequest, RequestError, checked_id};
+use super::{Body, StatusCode};
+
+pub(crate) fn parse_put_console_port(
+    body: &Body,
+    id_from_path: Option<&str>,
+) -> Result<ParsedRequest, RequestError> {
+    let id = if let Some(id) = id_from_path {
+        checked_id(id)?
+    } else {
+        return Err(RequestError::EmptyID);
+    };
+
+    let port_cfg = serde_json::from_slice::<ConsolePortConfig>(body.raw())?;
+    if id != port_cfg.port_id.as_str() {
+        return Err(RequestError::Generic(
+            StatusCode::BadRequest,
+            format!(
+                "The id from the path [{}] does not match the id from the body [{}]!",
+                id,
+                port_cfg.port_id.as_str()
+            ),
+        ));
+    }
+    Ok(ParsedRequest::new_sync(VmmAction::InsertConsolePort(
+        port_cfg,
+    )))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api_server::parsed_request::tests::vmm_action_from_request;
+
+    #[test]
+    fn test_parse_put_console_port_request() {
+        let body = r#"{
+            "port_id": "foo",
+            "name": "org.example.agent",
+            "socket_path": "/tmp/agent.sock"
+        }"#;
+        // 1. Exercise infamous "The id from the path does not match id from the body!".
+        parse_put_console_port(&Body::new(body), Some("bar")).unwrap_err();
+        // 2. The `id_from_path` cannot be None.
+        parse_put_console_port(&Body::new(body), None).unwrap_err();
+
+        // 3. Success case.
+        let expected_config = serde_json::from_str::<ConsolePortConfig>(body).unwrap();
+        assert_eq!(
+            vmm_action_from_request(parse_put_console_port(&Body::new(body), Some("foo")).unwrap()),
+            VmmAction::InsertConsolePort(expected_config)
+        );
+
+        // 4. Serde error for unknown field.
+        let body = r#"{
+            "port_id": "foo",
+            "name": "org.example.agent",
+            "socket": "/tmp/agent.sock"
+        }"#;
+        parse_put_console_port(&Body::new(body), Some("foo")).unwrap_err();
+    }
+}
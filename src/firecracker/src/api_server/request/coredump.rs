@@ -0,0 +1,42 @@
+// Copyright 2025 Amazon.com, Inc. or its affiliates. All Rights Reserved.
+// SPDX-License-Identifier: Apache-2.0
+
+use vmm::rpc_interface::VmmAction;
+use vmm::vmm_config::coredump::CreateCoredumpParams;
+
+use super::super::parsed_request::{ParsedRequest, RequestError};
+use super::Body;
+
+pub(crate) fn parse_put_vm_coredump(body: &Body) -> Result<ParsedRequest, RequestError> {
+    let params = serde_json::from_slice::<CreateCoredumpParams>(body.raw())?;
+    Ok(ParsedRequest::new_sync(VmmAction::CreateCoredump(params)))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use super::*;
+
+    #[test]
+    fn test_parse_put_vm_coredump_request() {
+        parse_put_vm_coredump(&Body::new("invalid_payload")).unwrap_err();
+        parse_put_vm_coredump(&Body::new("{}")).unwrap_err();
+
+        let body = r#"{
+            "dump_path": "/tmp/vmcore",
+            "format": "kdump"
+        }"#;
+        parse_put_vm_coredump(&Body::new(body)).unwrap_err();
+
+        let body = r#"{
+            "dump_path": "/tmp/vmcore"
+        }"#;
+        assert_eq!(
+            parse_put_vm_coredump(&Body::new(body)).unwrap(),
+            ParsedRequest::new_sync(VmmAction::CreateCoredump(CreateCoredumpParams {
+                dump_path: PathBuf::from("/tmp/vmcore"),
+            }))
+        );
+    }
+}
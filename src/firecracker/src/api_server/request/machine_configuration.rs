@@ -1,24 +1,54 @@
 // Copyright 2018 Amazon.com, Inc. or its affiliates. All Rights Reserved.
 // SPDX-License-Identifier: Apache-2.0
 
+use std::fs::File;
+
 use vmm::logger::{IncMetric, METRICS};
 use vmm::rpc_interface::VmmAction;
-use vmm::vmm_config::machine_config::{MachineConfig, MachineConfigUpdate};
+use vmm::vmm_config::machine_config::{
+    GuestMemfd, GuestMemoryBackend, MachineConfig, MachineConfigUpdate,
+};
 
 use super::super::parsed_request::{ParsedRequest, RequestError, method_to_error};
-use super::{Body, Method};
+use super::{Body, Method, StatusCode};
 
 pub(crate) fn parse_get_machine_config() -> Result<ParsedRequest, RequestError> {
     METRICS.get_api_requests.machine_cfg_count.inc();
     Ok(ParsedRequest::new_sync(VmmAction::GetVmMachineConfig))
 }
 
-pub(crate) fn parse_put_machine_config(body: &Body) -> Result<ParsedRequest, RequestError> {
+pub(crate) fn parse_put_machine_config(
+    body: &Body,
+    files: &[File],
+) -> Result<ParsedRequest, RequestError> {
     METRICS.put_api_requests.machine_cfg_count.inc();
-    let config = serde_json::from_slice::<MachineConfig>(body.raw()).inspect_err(|_| {
+    let mut config = serde_json::from_slice::<MachineConfig>(body.raw()).inspect_err(|_| {
         METRICS.put_api_requests.machine_cfg_fails.inc();
     })?;
 
+    // The memfd backing the guest memory is passed along the request, over the API socket.
+    match (&mut config.memory_backend, files) {
+        (GuestMemoryBackend::Memfd { memfd }, [file]) => {
+            let file = file.try_clone().map_err(|err| {
+                METRICS.put_api_requests.machine_cfg_fails.inc();
+                RequestError::Generic(
+                    StatusCode::InternalServerError,
+                    format!("Cannot duplicate the memfd passed along the request: {err}"),
+                )
+            })?;
+            *memfd = Some(GuestMemfd::new(file));
+        }
+        (_, []) => (),
+        _ => {
+            METRICS.put_api_requests.machine_cfg_fails.inc();
+            return Err(RequestError::Generic(
+                StatusCode::BadRequest,
+                "Only the memfd of the Memfd memory backend can be passed along the request."
+                    .to_string(),
+            ));
+        }
+    }
+
     // Check for the presence of deprecated `cpu_template` field.
     let mut deprecation_message = None;
     if config.cpu_template.is_some() {
@@ -74,7 +104,8 @@ pub(crate) fn parse_patch_machine_config(body: &Body) -> Result<ParsedRequest, R
 #[cfg(test)]
 mod tests {
     use vmm::cpu_config::templates::StaticCpuTemplate;
-    use vmm::vmm_config::machine_config::HugePageConfig;
+    use vmm::vmm_config::machine_config::{HugePageConfig, NumaConfig, VirtioTransport};
+    use vmm_sys_util::tempfile::TempFile;
 
     use super::*;
     use crate::api_server::parsed_request::tests::{depr_action_from_req, vmm_action_from_request};
@@ -88,23 +119,25 @@ mod tests {
     #[test]
     fn test_parse_put_machine_config_request() {
         // 1. Test case for invalid payload.
-        parse_put_machine_config(&Body::new("invalid_payload")).unwrap_err();
+        parse_put_machine_config(&Body::new("invalid_payload"), &[]).unwrap_err();
         assert!(METRICS.put_api_requests.machine_cfg_fails.count() > 0);
 
         // 2. Test case for mandatory fields.
         let body = r#"{
             "mem_size_mib": 1024
         }"#;
-        parse_put_machine_config(&Body::new(body)).unwrap_err();
+        parse_put_machine_config(&Body::new(body), &[]).unwrap_err();
 
         let body = r#"{
             "vcpu_count": 8
         }"#;
-        parse_put_machine_config(&Body::new(body)).unwrap_err();
+        parse_put_machine_config(&Body::new(body), &[]).unwrap_err();
 
         let huge_pages_cases = [
             ("None", HugePageConfig::None),
             ("2M", HugePageConfig::Hugetlbfs2M),
+            ("1G", HugePageConfig::Hugetlbfs1G),
+            ("THP", HugePageConfig::Transparent),
         ];
 
         for (huge_page, expected) in huge_pages_cases {
@@ -120,14 +153,20 @@ mod tests {
                 vcpu_count: Some(8),
                 mem_size_mib: Some(1024),
                 smt: Some(false),
+                sockets: None,
+                cores_per_socket: None,
+                threads_per_core: None,
                 cpu_template: None,
                 track_dirty_pages: Some(false),
                 huge_pages: Some(expected),
+                virtio_transport: Some(VirtioTransport::Mmio),
+                memory_backend: Some(GuestMemoryBackend::Anonymous),
+                numa: Some(NumaConfig::default()),
                 #[cfg(feature = "gdb")]
                 gdb_socket_path: None,
             };
             assert_eq!(
-                vmm_action_from_request(parse_put_machine_config(&Body::new(body)).unwrap()),
+                vmm_action_from_request(parse_put_machine_config(&Body::new(body), &[]).unwrap()),
                 VmmAction::UpdateMachineConfiguration(expected_config)
             );
         }
@@ -141,14 +180,20 @@ mod tests {
             vcpu_count: Some(8),
             mem_size_mib: Some(1024),
             smt: Some(false),
+            sockets: None,
+            cores_per_socket: None,
+            threads_per_core: None,
             cpu_template: Some(StaticCpuTemplate::None),
             track_dirty_pages: Some(false),
             huge_pages: Some(HugePageConfig::None),
+            virtio_transport: Some(VirtioTransport::Mmio),
+            memory_backend: Some(GuestMemoryBackend::Anonymous),
+            numa: Some(NumaConfig::default()),
             #[cfg(feature = "gdb")]
             gdb_socket_path: None,
         };
         assert_eq!(
-            vmm_action_from_request(parse_put_machine_config(&Body::new(body)).unwrap()),
+            vmm_action_from_request(parse_put_machine_config(&Body::new(body), &[]).unwrap()),
             VmmAction::UpdateMachineConfiguration(expected_config)
         );
 
@@ -162,14 +207,20 @@ mod tests {
             vcpu_count: Some(8),
             mem_size_mib: Some(1024),
             smt: Some(false),
+            sockets: None,
+            cores_per_socket: None,
+            threads_per_core: None,
             cpu_template: None,
             track_dirty_pages: Some(true),
             huge_pages: Some(HugePageConfig::None),
+            virtio_transport: Some(VirtioTransport::Mmio),
+            memory_backend: Some(GuestMemoryBackend::Anonymous),
+            numa: Some(NumaConfig::default()),
             #[cfg(feature = "gdb")]
             gdb_socket_path: None,
         };
         assert_eq!(
-            vmm_action_from_request(parse_put_machine_config(&Body::new(body)).unwrap()),
+            vmm_action_from_request(parse_put_machine_config(&Body::new(body), &[]).unwrap()),
             VmmAction::UpdateMachineConfiguration(expected_config)
         );
 
@@ -187,20 +238,26 @@ mod tests {
                 vcpu_count: Some(8),
                 mem_size_mib: Some(1024),
                 smt: Some(false),
+                sockets: None,
+                cores_per_socket: None,
+                threads_per_core: None,
                 cpu_template: Some(StaticCpuTemplate::T2),
                 track_dirty_pages: Some(true),
                 huge_pages: Some(HugePageConfig::None),
+                virtio_transport: Some(VirtioTransport::Mmio),
+                memory_backend: Some(GuestMemoryBackend::Anonymous),
+                numa: Some(NumaConfig::default()),
                 #[cfg(feature = "gdb")]
                 gdb_socket_path: None,
             };
             assert_eq!(
-                vmm_action_from_request(parse_put_machine_config(&Body::new(body)).unwrap()),
+                vmm_action_from_request(parse_put_machine_config(&Body::new(body), &[]).unwrap()),
                 VmmAction::UpdateMachineConfiguration(expected_config)
             );
         }
         #[cfg(target_arch = "aarch64")]
         {
-            parse_put_machine_config(&Body::new(body)).unwrap_err();
+            parse_put_machine_config(&Body::new(body), &[]).unwrap_err();
         }
 
         // 5. Test that setting `smt: true` is successful
@@ -214,24 +271,63 @@ mod tests {
             vcpu_count: Some(8),
             mem_size_mib: Some(1024),
             smt: Some(true),
+            sockets: None,
+            cores_per_socket: None,
+            threads_per_core: None,
             cpu_template: None,
             track_dirty_pages: Some(true),
             huge_pages: Some(HugePageConfig::None),
+            virtio_transport: Some(VirtioTransport::Mmio),
+            memory_backend: Some(GuestMemoryBackend::Anonymous),
+            numa: Some(NumaConfig::default()),
             #[cfg(feature = "gdb")]
             gdb_socket_path: None,
         };
         assert_eq!(
-            vmm_action_from_request(parse_put_machine_config(&Body::new(body)).unwrap()),
+            vmm_action_from_request(parse_put_machine_config(&Body::new(body), &[]).unwrap()),
             VmmAction::UpdateMachineConfiguration(expected_config)
         );
 
-        // 6. Test nonsense values for huge page size
+        // 6. Test that the memfd backing the guest memory is taken from the request.
+        let body = r#"{
+            "vcpu_count": 8,
+            "mem_size_mib": 1024,
+            "memory_backend": {
+                "backend_type": "Memfd"
+            }
+        }"#;
+        let memfd = TempFile::new().unwrap().into_file();
+        match vmm_action_from_request(parse_put_machine_config(&Body::new(body), &[memfd]).unwrap())
+        {
+            VmmAction::UpdateMachineConfiguration(MachineConfigUpdate {
+                memory_backend: Some(GuestMemoryBackend::Memfd { memfd: Some(_) }),
+                ..
+            }) => (),
+            action => panic!("Unexpected action: {action:?}"),
+        }
+        // Without the memfd, the request is left to be rejected by the VMM.
+        match vmm_action_from_request(parse_put_machine_config(&Body::new(body), &[]).unwrap()) {
+            VmmAction::UpdateMachineConfiguration(MachineConfigUpdate {
+                memory_backend: Some(GuestMemoryBackend::Memfd { memfd: None }),
+                ..
+            }) => (),
+            action => panic!("Unexpected action: {action:?}"),
+        }
+        // Files can't be passed along other memory backends.
+        let body = r#"{
+            "vcpu_count": 8,
+            "mem_size_mib": 1024
+        }"#;
+        let file = TempFile::new().unwrap().into_file();
+        parse_put_machine_config(&Body::new(body), &[file]).unwrap_err();
+
+        // 7. Test nonsense values for huge page size
         let body = r#"{
             "vcpu_count": 8,
             "mem_size_mib": 1024,
             "huge_pages": "7M"
         }"#;
-        parse_put_machine_config(&Body::new(body)).unwrap_err();
+        parse_put_machine_config(&Body::new(body), &[]).unwrap_err();
     }
 
     #[test]
@@ -282,7 +378,7 @@ mod tests {
             "cpu_template": "None"
         }"#;
         depr_action_from_req(
-            parse_put_machine_config(&Body::new(body)).unwrap(),
+            parse_put_machine_config(&Body::new(body), &[]).unwrap(),
             Some("PUT /machine-config: cpu_template field is deprecated.".to_string()),
         );
 
@@ -291,7 +387,7 @@ mod tests {
             "vcpu_count": 8,
             "mem_size_mib": 1024
         }"#;
-        let (_, mut parsing_info) = parse_put_machine_config(&Body::new(body))
+        let (_, mut parsing_info) = parse_put_machine_config(&Body::new(body), &[])
             .unwrap()
             .into_parts();
         assert!(parsing_info.take_deprecation_message().is_none());
@@ -4,16 +4,24 @@
 pub mod actions;
 pub mod balloon;
 pub mod boot_source;
+pub mod console;
+pub mod coredump;
 pub mod cpu_configuration;
 pub mod drive;
 pub mod entropy;
+pub mod fs;
 pub mod instance_info;
 pub mod logger;
 pub mod machine_configuration;
 pub mod metrics;
 pub mod mmds;
 pub mod net;
+pub mod pmem;
+pub mod pvpanic;
+pub mod seccomp;
 pub mod snapshot;
 pub mod version;
+pub mod vm_config;
 pub mod vsock;
+pub mod watchdog;
 pub use micro_http::{Body, Method, StatusCode};
@@ -106,6 +106,7 @@ fn parse_put_snapshot_load(body: &Body) -> Result<ParsedRequest, RequestError> {
         enable_diff_snapshots: snapshot_config.enable_diff_snapshots,
         resume_vm: snapshot_config.resume_vm,
         network_overrides: snapshot_config.network_overrides,
+        check_host_compatibility: snapshot_config.check_host_compatibility,
     };
 
     // Construct the `ParsedRequest` object.
@@ -183,6 +184,7 @@ mod tests {
             enable_diff_snapshots: false,
             resume_vm: false,
             network_overrides: vec![],
+            check_host_compatibility: false,
         };
         let mut parsed_request = parse_put_snapshot(&Body::new(body), Some("load")).unwrap();
         assert!(
@@ -213,6 +215,7 @@ mod tests {
             enable_diff_snapshots: true,
             resume_vm: false,
             network_overrides: vec![],
+            check_host_compatibility: false,
         };
         let mut parsed_request = parse_put_snapshot(&Body::new(body), Some("load")).unwrap();
         assert!(
@@ -243,6 +246,7 @@ mod tests {
             enable_diff_snapshots: false,
             resume_vm: true,
             network_overrides: vec![],
+            check_host_compatibility: false,
         };
         let mut parsed_request = parse_put_snapshot(&Body::new(body), Some("load")).unwrap();
         assert!(
@@ -268,7 +272,8 @@ mod tests {
                     "iface_id": "eth0",
                     "host_dev_name": "vmtap2"
                 }
-            ]
+            ],
+            "check_host_compatibility": true
         }"#;
         let expected_config = LoadSnapshotParams {
             snapshot_path: PathBuf::from("foo"),
@@ -282,6 +287,7 @@ mod tests {
                 iface_id: String::from("eth0"),
                 host_dev_name: String::from("vmtap2"),
             }],
+            check_host_compatibility: true,
         };
         let mut parsed_request = parse_put_snapshot(&Body::new(body), Some("load")).unwrap();
         assert!(
@@ -295,6 +301,29 @@ mod tests {
             VmmAction::LoadSnapshot(expected_config)
         );
 
+        let body = r#"{
+            "snapshot_path": "foo",
+            "mem_backend": {
+                "backend_path": "bar",
+                "backend_type": "SharedFile"
+            }
+        }"#;
+        let expected_config = LoadSnapshotParams {
+            snapshot_path: PathBuf::from("foo"),
+            mem_backend: MemBackendConfig {
+                backend_path: PathBuf::from("bar"),
+                backend_type: MemBackendType::SharedFile,
+            },
+            enable_diff_snapshots: false,
+            resume_vm: false,
+            network_overrides: vec![],
+            check_host_compatibility: false,
+        };
+        assert_eq!(
+            vmm_action_from_request(parse_put_snapshot(&Body::new(body), Some("load")).unwrap()),
+            VmmAction::LoadSnapshot(expected_config)
+        );
+
         let body = r#"{
             "snapshot_path": "foo",
             "mem_file_path": "bar",
@@ -309,6 +338,7 @@ mod tests {
             enable_diff_snapshots: false,
             resume_vm: true,
             network_overrides: vec![],
+            check_host_compatibility: false,
         };
         let parsed_request = parse_put_snapshot(&Body::new(body), Some("load")).unwrap();
         assert_eq!(
@@ -0,0 +1,42 @@
+// Copyright 2025 Amazon.com, Inc. or its affiliates. All Rights Reserved.
+// SPDX-License-Identifier: Apache-2.0
+
+use vmm::resources::VmmConfig;
+use vmm::rpc_interface::VmmAction;
+
+use super::super::parsed_request::{ParsedRequest, RequestError};
+use super::Body;
+
+pub(crate) fn parse_put_vm_config(body: &Body) -> Result<ParsedRequest, RequestError> {
+    let cfg = serde_json::from_slice::<VmmConfig>(body.raw())?;
+    Ok(ParsedRequest::new_sync(VmmAction::SetVmConfig(cfg)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_put_vm_config_request() {
+        parse_put_vm_config(&Body::new("invalid_payload")).unwrap_err();
+
+        // PUT without the mandatory sections.
+        let body = r#"{
+            "machine-config": { "vcpu_count": 2, "mem_size_mib": 1024 }
+        }"#;
+        parse_put_vm_config(&Body::new(body)).unwrap_err();
+
+        // PUT with valid fields.
+        let body = r#"{
+            "boot-source": { "kernel_image_path": "/path/to/kernel" },
+            "drives": [],
+            "machine-config": { "vcpu_count": 2, "mem_size_mib": 1024 }
+        }"#;
+        assert_eq!(
+            parse_put_vm_config(&Body::new(body)).unwrap(),
+            ParsedRequest::new_sync(VmmAction::SetVmConfig(
+                serde_json::from_str::<VmmConfig>(body).unwrap()
+            ))
+        );
+    }
+}
@@ -0,0 +1,49 @@
+// Copyright 2025 Amazon.com, Inc. or its affiliates. All Rights Reserved.
+// SPDX-License-Identifier: Apache-2.0
+
+use vmm::rpc_interface::VmmAction;
+use vmm::vmm_config::watchdog::WatchdogConfig;
+
+use super::super::parsed_request::{ParsedRequest, RequestError};
+use super::Body;
+
+pub(crate) fn parse_put_watchdog(body: &Body) -> Result<ParsedRequest, RequestError> {
+    let cfg = serde_json::from_slice::<WatchdogConfig>(body.raw())?;
+    Ok(ParsedRequest::new_sync(VmmAction::SetWatchdogDevice(cfg)))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use vmm::vmm_config::watchdog::WatchdogAction;
+
+    use super::*;
+    use crate::api_server::parsed_request::tests::vmm_action_from_request;
+
+    #[test]
+    fn test_parse_put_watchdog_request() {
+        parse_put_watchdog(&Body::new("invalid_payload")).unwrap_err();
+
+        // PUT with invalid fields.
+        let body = r#"{
+            "action": "Reboot"
+        }"#;
+        parse_put_watchdog(&Body::new(body)).unwrap_err();
+
+        // PUT with valid fields.
+        let body = r#"{
+            "action": "SnapshotThenExit",
+            "snapshot_path": "/snapshots/vmstate",
+            "mem_file_path": "/snapshots/memory"
+        }"#;
+        assert_eq!(
+            vmm_action_from_request(parse_put_watchdog(&Body::new(body)).unwrap()),
+            VmmAction::SetWatchdogDevice(WatchdogConfig {
+                action: WatchdogAction::SnapshotThenExit,
+                snapshot_path: Some(PathBuf::from("/snapshots/vmstate")),
+                mem_file_path: Some(PathBuf::from("/snapshots/memory")),
+            })
+        );
+    }
+}
@@ -9,18 +9,21 @@ use std::thread;
 
 use event_manager::{EventOps, Events, MutEventSubscriber, SubscriberOps};
 use vmm::logger::{ProcessTimeReporter, error, info, warn};
+use vmm::notifications::NotificationListener;
 use vmm::resources::VmResources;
 use vmm::rpc_interface::{
     ApiRequest, ApiResponse, BuildMicrovmFromRequestsError, PrebootApiController,
     RuntimeApiController, VmmAction,
 };
-use vmm::seccomp::BpfThreadMap;
+use vmm::seccomp::{BpfProgram, BpfThreadMap};
 use vmm::vmm_config::instance_info::InstanceInfo;
 use vmm::{EventManager, FcExitCode, Vmm};
 use vmm_sys_util::epoll::EventSet;
 use vmm_sys_util::eventfd::EventFd;
 
-use super::api_server::{ApiServer, HttpServer, ServerError};
+use super::api_server::authorization::AuthorizationPolicy;
+use super::api_server::listener::{ApiListener, ApiListenerError};
+use super::api_server::{ApiServer, ApiSocket, HttpServer, ServerError};
 
 #[derive(Debug, thiserror::Error, displaydoc::Display)]
 pub enum ApiServerError {
@@ -32,6 +35,8 @@ pub enum ApiServerError {
     FailedToBindSocket(String),
     /// Failed to bind and run the HTTP server: {0}
     FailedToBindAndRunHttpServer(ServerError),
+    /// Failed to listen on the API socket: {0}
+    FailedToListen(ApiListenerError),
     /// Failed to build MicroVM from Json: {0}
     BuildFromJson(crate::BuildFromJsonError),
 }
@@ -41,6 +46,7 @@ struct ApiServerAdapter {
     api_event_fd: EventFd,
     from_api: Receiver<ApiRequest>,
     to_api: Sender<ApiResponse>,
+    to_api_filter: Sender<Arc<BpfProgram>>,
     controller: RuntimeApiController,
 }
 
@@ -51,6 +57,7 @@ impl ApiServerAdapter {
         api_event_fd: EventFd,
         from_api: Receiver<ApiRequest>,
         to_api: Sender<ApiResponse>,
+        to_api_filter: Sender<Arc<BpfProgram>>,
         vm_resources: VmResources,
         vmm: Arc<Mutex<Vmm>>,
         event_manager: &mut EventManager,
@@ -59,6 +66,7 @@ impl ApiServerAdapter {
             api_event_fd,
             from_api,
             to_api,
+            to_api_filter,
             controller: RuntimeApiController::new(vm_resources, vmm.clone()),
         }));
         event_manager.add_subscriber(api_adapter);
@@ -78,6 +86,13 @@ impl ApiServerAdapter {
 
     fn handle_request(&mut self, req_action: VmmAction) {
         let response = self.controller.handle_request(req_action);
+        // The API thread installs its additional seccomp filter before reading the result.
+        if let Some(filter) = self.controller.take_api_seccomp_filter() {
+            self.to_api_filter
+                .send(filter)
+                .map_err(|_| ())
+                .expect("API seccomp filter channel closed");
+        }
         // Send back the result.
         self.to_api
             .send(Box::new(response))
@@ -146,6 +161,8 @@ pub(crate) fn run_with_api(
     api_payload_limit: usize,
     mmds_size_limit: usize,
     metadata_json: Option<&str>,
+    notification_listener: Option<NotificationListener>,
+    authorization_policy: Option<AuthorizationPolicy>,
 ) -> Result<(), ApiServerError> {
     // FD to notify of API events. This is a blocking eventfd by design.
     // It is used in the config/pre-boot loop which is a simple blocking loop
@@ -157,6 +174,8 @@ pub(crate) fn run_with_api(
     // Channels for both directions between Vmm and Api threads.
     let (to_vmm, from_api) = channel();
     let (to_api, from_vmm) = channel();
+    // Channel through which the VMM hands the API thread its additional seccomp filters.
+    let (to_api_filter, api_filter_receiver) = channel();
 
     let to_vmm_event_fd = api_event_fd
         .try_clone()
@@ -165,14 +184,30 @@ pub(crate) fn run_with_api(
         .remove("api")
         .expect("Missing seccomp filter for API thread.");
 
-    let mut server = match HttpServer::new(&bind_path) {
-        Ok(s) => s,
-        Err(ServerError::IOError(inner)) if inner.kind() == std::io::ErrorKind::AddrInUse => {
-            let sock_path = bind_path.display().to_string();
-            return Err(ApiServerError::FailedToBindSocket(sock_path));
+    // The authorization policy needs the credentials of the clients, which only the API
+    // listener retrieves.
+    let addr_in_use = |err: &std::io::Error| err.kind() == std::io::ErrorKind::AddrInUse;
+    let mut api_socket = if authorization_policy.is_none() {
+        match HttpServer::new(&bind_path) {
+            Ok(s) => ApiSocket::Http(s),
+            Err(ServerError::IOError(inner)) if addr_in_use(&inner) => {
+                let sock_path = bind_path.display().to_string();
+                return Err(ApiServerError::FailedToBindSocket(sock_path));
+            }
+            Err(err) => {
+                return Err(ApiServerError::FailedToBindAndRunHttpServer(err));
+            }
         }
-        Err(err) => {
-            return Err(ApiServerError::FailedToBindAndRunHttpServer(err));
+    } else {
+        match ApiListener::new(&bind_path) {
+            Ok(listener) => ApiSocket::Authorizing(listener),
+            Err(ApiListenerError::Bind(inner)) if addr_in_use(&inner) => {
+                let sock_path = bind_path.display().to_string();
+                return Err(ApiServerError::FailedToBindSocket(sock_path));
+            }
+            Err(err) => {
+                return Err(ApiServerError::FailedToListen(err));
+            }
         }
     };
     info!("Listening on API socket ({bind_path:?}).");
@@ -181,16 +216,28 @@ pub(crate) fn run_with_api(
         .try_clone()
         .expect("Failed to clone API kill switch");
 
-    server
-        .add_kill_switch(api_kill_switch_clone)
-        .expect("Cannot add HTTP server kill switch");
+    match &mut api_socket {
+        ApiSocket::Http(server) => server
+            .add_kill_switch(api_kill_switch_clone)
+            .expect("Cannot add HTTP server kill switch"),
+        ApiSocket::Authorizing(listener) => listener
+            .add_kill_switch(api_kill_switch_clone)
+            .expect("Cannot add API listener kill switch"),
+    }
 
     // Start the separate API thread.
     let api_thread = thread::Builder::new()
         .name("fc_api".to_owned())
         .spawn(move || {
-            ApiServer::new(to_vmm, from_vmm, to_vmm_event_fd).run(
-                server,
+            ApiServer::new(
+                to_vmm,
+                from_vmm,
+                api_filter_receiver,
+                to_vmm_event_fd,
+                authorization_policy,
+            )
+            .run(
+                api_socket,
                 process_time_reporter,
                 &api_seccomp_filter,
                 api_payload_limit,
@@ -204,6 +251,10 @@ pub(crate) fn run_with_api(
     let firecracker_metrics = Arc::new(Mutex::new(super::metrics::PeriodicMetrics::new()));
     event_manager.add_subscriber(firecracker_metrics.clone());
 
+    if let Some(listener) = notification_listener {
+        event_manager.add_subscriber(Arc::new(Mutex::new(listener)));
+    }
+
     // Configure, build and start the microVM.
     let build_result = match config_json {
         Some(json) => super::build_microvm_from_json(
@@ -240,6 +291,7 @@ pub(crate) fn run_with_api(
             api_event_fd,
             from_api,
             to_api,
+            to_api_filter,
             vm_resources,
             vmm,
             &mut event_manager,
@@ -8,12 +8,13 @@ mod metrics;
 mod seccomp;
 
 use std::fs::{self, File};
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::process::ExitCode;
 use std::str::FromStr;
 use std::sync::{Arc, Mutex};
 use std::{io, panic};
 
+use api_server::authorization::{AuthorizationPolicy, AuthorizationPolicyError};
 use api_server_adapter::ApiServerError;
 use event_manager::SubscriberOps;
 use seccomp::FilterError;
@@ -24,6 +25,7 @@ use vmm::builder::StartMicrovmError;
 use vmm::logger::{
     LOGGER, LoggerConfig, METRICS, ProcessTimeReporter, StoreMetric, debug, error, info,
 };
+use vmm::notifications::{NotificationListener, NotificationsError};
 use vmm::persist::SNAPSHOT_VERSION;
 use vmm::resources::VmResources;
 use vmm::seccomp::BpfThreadMap;
@@ -59,6 +61,10 @@ enum MainError {
     LoggerInitialization(vmm::logger::LoggerUpdateError),
     /// Could not initialize metrics: {0}
     MetricsInitialization(MetricsConfigError),
+    /// Could not initialize the notifications socket: {0}
+    NotificationsInitialization(NotificationsError),
+    /// Could not load the API authorization policy: {0}
+    ApiAuthorizationPolicy(AuthorizationPolicyError),
     /// Seccomp error: {0}
     SeccompFilter(FilterError),
     /// Failed to resize fd table: {0}
@@ -149,6 +155,15 @@ fn main_exec() -> Result<(), MainError> {
                     .default_value(DEFAULT_API_SOCK_PATH)
                     .help("Path to unix domain socket used by the API."),
             )
+            .arg(
+                Argument::new("api-auth-policy")
+                    .takes_value(true)
+                    .forbids(vec!["no-api"])
+                    .help(
+                        "Optional parameter which allows specifying the path to a JSON policy \
+                         restricting the API requests that each client uid/gid can issue.",
+                    ),
+            )
             .arg(
                 Argument::new("id")
                     .takes_value(true)
@@ -231,6 +246,10 @@ fn main_exec() -> Result<(), MainError> {
                     .takes_value(true)
                     .help("Path to a fifo or a file used for configuring the metrics on startup."),
             )
+            .arg(Argument::new("events-sock").takes_value(true).help(
+                "Path to a unix domain socket on which JSON notifications about the microVM are \
+                 streamed to the connected clients.",
+            ))
             .arg(Argument::new("boot-timer").takes_value(false).help(
                 "Whether or not to load boot timer device for logging elapsed time since \
                  InstanceStart command.",
@@ -351,6 +370,12 @@ fn main_exec() -> Result<(), MainError> {
         init_metrics(metrics_config).map_err(MainError::MetricsInitialization)?;
     }
 
+    let notification_listener = arguments
+        .single_value("events-sock")
+        .map(|path| NotificationListener::new(Path::new(path)))
+        .transpose()
+        .map_err(MainError::NotificationsInitialization)?;
+
     let mut seccomp_filters: BpfThreadMap = SeccompConfig::from_args(
         arguments.flag_present("no-seccomp"),
         arguments.single_value("seccomp-filter"),
@@ -415,6 +440,12 @@ fn main_exec() -> Result<(), MainError> {
         let process_time_reporter =
             ProcessTimeReporter::new(start_time_us, start_time_cpu_us, parent_cpu_time_us);
 
+        let authorization_policy = arguments
+            .single_value("api-auth-policy")
+            .map(|path| AuthorizationPolicy::from_file(Path::new(path)))
+            .transpose()
+            .map_err(MainError::ApiAuthorizationPolicy)?;
+
         api_server_adapter::run_with_api(
             &mut seccomp_filters,
             vmm_config_json,
@@ -425,6 +456,8 @@ fn main_exec() -> Result<(), MainError> {
             api_payload_limit,
             mmds_size_limit,
             metadata_json.as_deref(),
+            notification_listener,
+            authorization_policy,
         )
         .map_err(MainError::RunWithApi)
     } else {
@@ -439,6 +472,7 @@ fn main_exec() -> Result<(), MainError> {
             boot_timer_enabled,
             mmds_size_limit,
             metadata_json.as_deref(),
+            notification_listener,
         )
         .map_err(MainError::RunWithoutApiError)
     }
@@ -595,6 +629,7 @@ fn run_without_api(
     bool_timer_enabled: bool,
     mmds_size_limit: usize,
     metadata_json: Option<&str>,
+    notification_listener: Option<NotificationListener>,
 ) -> Result<(), RunWithoutApiError> {
     let mut event_manager = EventManager::new().expect("Unable to create EventManager");
 
@@ -602,6 +637,10 @@ fn run_without_api(
     let firecracker_metrics = Arc::new(Mutex::new(metrics::PeriodicMetrics::new()));
     event_manager.add_subscriber(firecracker_metrics.clone());
 
+    if let Some(listener) = notification_listener {
+        event_manager.add_subscriber(Arc::new(Mutex::new(listener)));
+    }
+
     // Build the microVm. We can ignore VmResources since it's not used without api.
     let (_, vmm) = build_microvm_from_json(
         seccomp_filters,
@@ -1,22 +1,38 @@
 // Copyright 2024 Amazon.com, Inc. or its affiliates. All Rights Reserved.
 // SPDX-License-Identifier: Apache-2.0
 
-use clap::Parser;
-use seccompiler::{CompilationError, compile_bpf};
+use clap::error::ErrorKind;
+use clap::{Arg, ArgMatches, Args, Command, FromArgMatches, Parser};
+use seccompiler::{CompilationError, compile_bpf, diff_bpf};
 
 const DEFAULT_OUTPUT_FILENAME: &str = "seccomp_binary_filter.out";
 
 #[derive(Debug, Parser)]
 #[command(version = format!("v{}", env!("CARGO_PKG_VERSION")))]
 struct Cli {
+    #[command(flatten)]
+    mode: Mode,
+}
+
+// Arguments of the compilation of a JSON file.
+#[derive(Debug, Args)]
+struct CompileArgs {
     #[arg(
         short,
         long,
+        required = false,
+        required_unless_present = "diff",
         help = "The computer architecture where the BPF program runs. Supported architectures: \
                 x86_64, aarch64."
     )]
     target_arch: String,
-    #[arg(short, long, help = "File path of the JSON input.")]
+    #[arg(
+        short,
+        long,
+        required = false,
+        required_unless_present = "diff",
+        help = "File path of the JSON input."
+    )]
     input_file: String,
     #[arg(short, long, help = "Optional path of the output file.", default_value = DEFAULT_OUTPUT_FILENAME)]
     output_file: String,
@@ -29,12 +45,70 @@ struct Cli {
     basic: bool,
 }
 
+/// What seccompiler-bin does: compile a JSON file, or print the differences between two of them.
+#[derive(Debug)]
+enum Mode {
+    Compile(CompileArgs),
+    Diff {
+        base_file: String,
+        other_file: String,
+    },
+}
+
+impl FromArgMatches for Mode {
+    fn from_arg_matches(matches: &ArgMatches) -> Result<Self, clap::Error> {
+        let Some(mut files) = matches.get_many::<String>("diff") else {
+            return CompileArgs::from_arg_matches(matches).map(Mode::Compile);
+        };
+        match (files.next(), files.next()) {
+            (Some(base_file), Some(other_file)) => Ok(Mode::Diff {
+                base_file: base_file.clone(),
+                other_file: other_file.clone(),
+            }),
+            _ => Err(clap::Error::new(ErrorKind::WrongNumberOfValues)),
+        }
+    }
+
+    fn update_from_arg_matches(&mut self, matches: &ArgMatches) -> Result<(), clap::Error> {
+        *self = Self::from_arg_matches(matches)?;
+        Ok(())
+    }
+}
+
+impl Args for Mode {
+    fn augment_args(cmd: Command) -> Command {
+        CompileArgs::augment_args(cmd).arg(
+            Arg::new("diff")
+                .long("diff")
+                .num_args(2)
+                .value_names(["BASE_FILE", "OTHER_FILE"])
+                .conflicts_with_all(["target_arch", "input_file", "basic"])
+                .help(
+                    "Print the effective differences between the filters of two JSON files, after \
+                     resolving includes and extended filters.",
+                ),
+        )
+    }
+
+    fn augment_args_for_update(cmd: Command) -> Command {
+        Self::augment_args(cmd)
+    }
+}
+
 fn main() -> Result<(), CompilationError> {
-    let cli = Cli::parse();
-    compile_bpf(
-        &cli.input_file,
-        &cli.target_arch,
-        &cli.output_file,
-        cli.basic,
-    )
+    match Cli::parse().mode {
+        Mode::Compile(args) => compile_bpf(
+            &args.input_file,
+            &args.target_arch,
+            &args.output_file,
+            args.basic,
+        ),
+        Mode::Diff {
+            base_file,
+            other_file,
+        } => {
+            println!("{}", diff_bpf(&base_file, &other_file)?);
+            Ok(())
+        }
+    }
 }
@@ -1,11 +1,12 @@
 // Copyright 2024 Amazon.com, Inc. or its affiliates. All Rights Reserved.
 // SPDX-License-Identifier: Apache-2.0
 
-use std::collections::HashMap;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
 use std::fs::File;
 use std::io::{Read, Seek};
 use std::os::fd::{AsRawFd, FromRawFd};
 use std::os::unix::fs::MetadataExt;
+use std::path::{Path, PathBuf};
 use std::str::FromStr;
 
 use bincode::config;
@@ -42,6 +43,18 @@ pub enum CompilationError {
     JsonDeserialize(serde_json::Error),
     /// Cannot parse arch: {0}
     ArchParse(String),
+    /// Cyclic include of filter file: {0}
+    IncludeCycle(String),
+    /// Cyclic extends chain for filter: {0}
+    ExtendsCycle(String),
+    /// Filter {0} extends unknown filter {1}
+    UnknownBaseFilter(String, String),
+    /// Filter {0} does not define {1} and does not extend another filter
+    MissingAction(String, &'static str),
+    /// Filter {0} removes syscall {1} which is not present in its base filter
+    RemoveMissingSyscall(String, String),
+    /// Filter {0} contains syscall {1} which does not exist on {2}
+    UnknownSyscall(String, String, TargetArch),
     /// Cannot create libseccomp context
     LibSeccompContext,
     /// Cannot add libseccomp arch
@@ -64,21 +77,156 @@ pub enum CompilationError {
     BincodeSerialize(BincodeError),
 }
 
+/// Reads the JSON filter file at `path`, following its `include` list and resolving the
+/// `extends` references of its filters.
+pub fn resolve_filters(path: &Path) -> Result<BTreeMap<String, ResolvedFilter>, CompilationError> {
+    resolve_file(path, &mut Vec::new())
+}
+
+fn resolve_file(
+    path: &Path,
+    include_stack: &mut Vec<PathBuf>,
+) -> Result<BTreeMap<String, ResolvedFilter>, CompilationError> {
+    let canonical_path = path.canonicalize().map_err(CompilationError::IntputOpen)?;
+    if include_stack.contains(&canonical_path) {
+        return Err(CompilationError::IncludeCycle(path.display().to_string()));
+    }
+
+    let mut file_content = String::new();
+    File::open(path)
+        .map_err(CompilationError::IntputOpen)?
+        .read_to_string(&mut file_content)
+        .map_err(CompilationError::InputRead)?;
+    let bpf_json: BpfJson =
+        serde_json::from_str(&file_content).map_err(CompilationError::JsonDeserialize)?;
+
+    // Includes are relative to the file containing them. Filters from later includes
+    // replace the ones with the same name from earlier includes.
+    let parent = path.parent().unwrap_or(Path::new(""));
+    let mut included = BTreeMap::new();
+    include_stack.push(canonical_path);
+    for include in bpf_json.include.iter() {
+        included.extend(resolve_file(&parent.join(include), include_stack)?);
+    }
+    include_stack.pop();
+
+    let mut resolved = BTreeMap::new();
+    for name in bpf_json.filters.keys() {
+        resolve_filter(
+            name,
+            &bpf_json.filters,
+            &included,
+            &mut resolved,
+            &mut Vec::new(),
+        )?;
+    }
+    // Included filters that are not redefined in this file are kept as they are.
+    for (name, filter) in included {
+        resolved.entry(name).or_insert(filter);
+    }
+    Ok(resolved)
+}
+
+fn resolve_filter(
+    name: &str,
+    filters: &BTreeMap<String, Filter>,
+    included: &BTreeMap<String, ResolvedFilter>,
+    resolved: &mut BTreeMap<String, ResolvedFilter>,
+    extends_stack: &mut Vec<String>,
+) -> Result<ResolvedFilter, CompilationError> {
+    if let Some(filter) = resolved.get(name) {
+        return Ok(filter.clone());
+    }
+    if extends_stack.iter().any(|n| n == name) {
+        return Err(CompilationError::ExtendsCycle(name.to_string()));
+    }
+
+    let filter = &filters[name];
+    let base = match &filter.extends {
+        // A filter extending its own name refers to the included filter it redefines.
+        Some(base_name) if base_name != name && filters.contains_key(base_name) => {
+            extends_stack.push(name.to_string());
+            let base = resolve_filter(base_name, filters, included, resolved, extends_stack)?;
+            extends_stack.pop();
+            Some(base)
+        }
+        Some(base_name) => Some(included.get(base_name).cloned().ok_or_else(|| {
+            CompilationError::UnknownBaseFilter(name.to_string(), base_name.clone())
+        })?),
+        None => None,
+    };
+
+    let mut rules = base
+        .as_ref()
+        .map(|base| base.filter.clone())
+        .unwrap_or_default();
+    for syscall in filter.remove.iter() {
+        let len = rules.len();
+        rules.retain(|rule| &rule.syscall != syscall);
+        if rules.len() == len {
+            return Err(CompilationError::RemoveMissingSyscall(
+                name.to_string(),
+                syscall.to_string_lossy().into_owned(),
+            ));
+        }
+    }
+    rules.extend(filter.filter.iter().cloned());
+
+    let default_action = filter
+        .default_action
+        .clone()
+        .or_else(|| base.as_ref().map(|base| base.default_action.clone()))
+        .ok_or_else(|| CompilationError::MissingAction(name.to_string(), "default_action"))?;
+    let filter_action = filter
+        .filter_action
+        .clone()
+        .or_else(|| base.as_ref().map(|base| base.filter_action.clone()))
+        .ok_or_else(|| CompilationError::MissingAction(name.to_string(), "filter_action"))?;
+
+    let resolved_filter = ResolvedFilter {
+        default_action,
+        filter_action,
+        filter: rules,
+    };
+    resolved.insert(name.to_string(), resolved_filter.clone());
+    Ok(resolved_filter)
+}
+
+/// Checks that every syscall used by the filters exists on the target architecture.
+fn validate_syscalls(
+    filters: &BTreeMap<String, ResolvedFilter>,
+    arch: TargetArch,
+) -> Result<(), CompilationError> {
+    for (name, filter) in filters.iter() {
+        for rule in filter.filter.iter() {
+            // SAFETY: Safe as all args are correct.
+            let syscall = unsafe {
+                seccomp_syscall_resolve_name_arch(arch.to_scmp_type(), rule.syscall.as_ptr())
+            };
+            // Syscalls which are known to libseccomp but do not exist on the given
+            // architecture resolve to negative pseudo syscall numbers.
+            if syscall < 0 {
+                return Err(CompilationError::UnknownSyscall(
+                    name.clone(),
+                    rule.syscall.to_string_lossy().into_owned(),
+                    arch,
+                ));
+            }
+        }
+    }
+    Ok(())
+}
+
 pub fn compile_bpf(
     input_path: &str,
     arch: &str,
     out_path: &str,
     basic: bool,
 ) -> Result<(), CompilationError> {
-    let mut file_content = String::new();
-    File::open(input_path)
-        .map_err(CompilationError::IntputOpen)?
-        .read_to_string(&mut file_content)
-        .map_err(CompilationError::InputRead)?;
-    let bpf_map_json: BpfJson =
-        serde_json::from_str(&file_content).map_err(CompilationError::JsonDeserialize)?;
+    let filters = resolve_filters(Path::new(input_path))?;
 
     let arch = TargetArch::from_str(arch).map_err(CompilationError::ArchParse)?;
+    validate_syscalls(&filters, arch)?;
 
     // SAFETY: Safe because the parameters are valid.
     let memfd_fd = unsafe { libc::memfd_create(c"bpf".as_ptr().cast(), 0) };
@@ -92,7 +240,7 @@ pub fn compile_bpf(
     let mut memfd = unsafe { File::from_raw_fd(memfd_fd) };
 
     let mut bpf_map: HashMap<String, Vec<u64>> = HashMap::new();
-    for (name, filter) in bpf_map_json.0.iter() {
+    for (name, filter) in filters.iter() {
         let default_action = filter.default_action.to_scmp_type();
         let filter_action = filter.filter_action.to_scmp_type();
 
@@ -194,3 +342,353 @@ pub fn compile_bpf(
         .map_err(CompilationError::BincodeSerialize)?;
     Ok(())
 }
+
+/// Groups the rules of a filter by syscall name.
+fn rules_by_syscall(filter: &ResolvedFilter) -> BTreeMap<String, Vec<&SyscallRule>> {
+    let mut rules: BTreeMap<String, Vec<&SyscallRule>> = BTreeMap::new();
+    for rule in filter.filter.iter() {
+        rules
+            .entry(rule.syscall.to_string_lossy().into_owned())
+            .or_default()
+            .push(rule);
+    }
+    rules
+}
+
+/// Returns a human readable description of the effective differences between the
+/// filters defined in `base_path` and the ones defined in `other_path`, after resolving
+/// includes and `extends` references in both files.
+pub fn diff_bpf(base_path: &str, other_path: &str) -> Result<String, CompilationError> {
+    let base_filters = resolve_filters(Path::new(base_path))?;
+    let other_filters = resolve_filters(Path::new(other_path))?;
+
+    let mut lines = vec![format!("--- {base_path}"), format!("+++ {other_path}")];
+    let names = base_filters
+        .keys()
+        .chain(other_filters.keys())
+        .collect::<BTreeSet<_>>();
+    for name in names {
+        let (base, other) = match (base_filters.get(name), other_filters.get(name)) {
+            (Some(base), Some(other)) => (base, other),
+            (Some(_), None) => {
+                lines.push(format!("- filter {name}"));
+                continue;
+            }
+            (None, Some(_)) => {
+                lines.push(format!("+ filter {name}"));
+                continue;
+            }
+            (None, None) => unreachable!(),
+        };
+        if base == other {
+            continue;
+        }
+
+        lines.push(format!("~ filter {name}"));
+        if base.default_action != other.default_action {
+            lines.push(format!(
+                "    default_action: {:?} -> {:?}",
+                base.default_action, other.default_action
+            ));
+        }
+        if base.filter_action != other.filter_action {
+            lines.push(format!(
+                "    filter_action: {:?} -> {:?}",
+                base.filter_action, other.filter_action
+            ));
+        }
+
+        let base_rules = rules_by_syscall(base);
+        let other_rules = rules_by_syscall(other);
+        let syscalls = base_rules
+            .keys()
+            .chain(other_rules.keys())
+            .collect::<BTreeSet<_>>();
+        for syscall in syscalls {
+            match (base_rules.get(syscall), other_rules.get(syscall)) {
+                (Some(_), None) => lines.push(format!("    - {syscall}")),
+                (None, Some(_)) => lines.push(format!("    + {syscall}")),
+                (Some(base), Some(other)) if base != other => {
+                    lines.push(format!("    ~ {syscall} (argument rules differ)"))
+                }
+                _ => (),
+            }
+        }
+    }
+    Ok(lines.join("\n"))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::ffi::CString;
+    use std::fs;
+
+    use vmm_sys_util::tempdir::TempDir;
+
+    use super::*;
+
+    fn write_file(dir: &TempDir, name: &str, content: &str) -> PathBuf {
+        let path = dir.as_path().join(name);
+        fs::write(&path, content).unwrap();
+        path
+    }
+
+    fn syscalls(filter: &ResolvedFilter) -> Vec<String> {
+        filter
+            .filter
+            .iter()
+            .map(|rule| rule.syscall.to_string_lossy().into_owned())
+            .collect()
+    }
+
+    #[test]
+    fn test_resolve_include() {
+        let dir = TempDir::new().unwrap();
+        write_file(
+            &dir,
+            "base.json",
+            r#"{
+                "vmm": {
+                    "default_action": "trap",
+                    "filter_action": "allow",
+                    "filter": [{"syscall": "read"}]
+                },
+                "api": {
+                    "default_action": "trap",
+                    "filter_action": "allow",
+                    "filter": [{"syscall": "write"}]
+                }
+            }"#,
+        );
+        let path = write_file(
+            &dir,
+            "custom.json",
+            r#"{
+                "include": ["base.json"],
+                "api": {
+                    "default_action": "kill_process",
+                    "filter_action": "allow",
+                    "filter": [{"syscall": "close"}]
+                }
+            }"#,
+        );
+
+        let filters = resolve_filters(&path).unwrap();
+        assert_eq!(filters.len(), 2);
+        // Included filters are kept as they are, unless redefined.
+        assert_eq!(syscalls(&filters["vmm"]), ["read"]);
+        assert_eq!(filters["vmm"].default_action, SeccompAction::Trap);
+        assert_eq!(syscalls(&filters["api"]), ["close"]);
+        assert_eq!(filters["api"].default_action, SeccompAction::KillProcess);
+    }
+
+    #[test]
+    fn test_resolve_extends() {
+        let dir = TempDir::new().unwrap();
+        write_file(
+            &dir,
+            "base.json",
+            r#"{
+                "vmm": {
+                    "default_action": "trap",
+                    "filter_action": "allow",
+                    "filter": [{"syscall": "read"}, {"syscall": "write"}]
+                }
+            }"#,
+        );
+        let path = write_file(
+            &dir,
+            "custom.json",
+            r#"{
+                "include": ["base.json"],
+                "vmm": {
+                    "extends": "vmm",
+                    "filter": [{"syscall": "close"}],
+                    "remove": ["write"]
+                },
+                "api": {
+                    "extends": "vmm",
+                    "default_action": "kill_process",
+                    "filter": [{"syscall": "openat"}]
+                }
+            }"#,
+        );
+
+        let filters = resolve_filters(&path).unwrap();
+        // A filter extending its own name extends the included filter.
+        assert_eq!(syscalls(&filters["vmm"]), ["read", "close"]);
+        assert_eq!(filters["vmm"].default_action, SeccompAction::Trap);
+        assert_eq!(filters["vmm"].filter_action, SeccompAction::Allow);
+        // A filter extending another filter of the file extends its resolved version.
+        assert_eq!(syscalls(&filters["api"]), ["read", "close", "openat"]);
+        assert_eq!(filters["api"].default_action, SeccompAction::KillProcess);
+        assert_eq!(filters["api"].filter_action, SeccompAction::Allow);
+    }
+
+    #[test]
+    fn test_resolve_errors() {
+        let dir = TempDir::new().unwrap();
+
+        write_file(&dir, "a.json", r#"{"include": ["b.json"]}"#);
+        let path = write_file(&dir, "b.json", r#"{"include": ["a.json"]}"#);
+        assert!(matches!(
+            resolve_filters(&path),
+            Err(CompilationError::IncludeCycle(_))
+        ));
+
+        let path = write_file(
+            &dir,
+            "extends_cycle.json",
+            r#"{
+                "vmm": {"extends": "api"},
+                "api": {"extends": "vmm"}
+            }"#,
+        );
+        assert!(matches!(
+            resolve_filters(&path),
+            Err(CompilationError::ExtendsCycle(name)) if name == "vmm" || name == "api"
+        ));
+
+        let path = write_file(&dir, "unknown_base.json", r#"{"vmm": {"extends": "api"}}"#);
+        assert!(matches!(
+            resolve_filters(&path),
+            Err(CompilationError::UnknownBaseFilter(name, base)) if name == "vmm" && base == "api"
+        ));
+
+        let path = write_file(
+            &dir,
+            "missing_action.json",
+            r#"{"vmm": {"filter_action": "allow"}}"#,
+        );
+        assert!(matches!(
+            resolve_filters(&path),
+            Err(CompilationError::MissingAction(_, "default_action"))
+        ));
+
+        let path = write_file(
+            &dir,
+            "remove_missing.json",
+            r#"{
+                "vmm": {
+                    "default_action": "trap",
+                    "filter_action": "allow",
+                    "filter": [{"syscall": "read"}]
+                },
+                "api": {"extends": "vmm", "remove": ["write"]}
+            }"#,
+        );
+        assert!(matches!(
+            resolve_filters(&path),
+            Err(CompilationError::RemoveMissingSyscall(name, syscall))
+                if name == "api" && syscall == "write"
+        ));
+    }
+
+    #[test]
+    fn test_validate_syscalls() {
+        let filter = |syscall: &str| {
+            BTreeMap::from([(
+                "vmm".to_string(),
+                ResolvedFilter {
+                    default_action: SeccompAction::Trap,
+                    filter_action: SeccompAction::Allow,
+                    filter: vec![SyscallRule {
+                        syscall: CString::new(syscall).unwrap(),
+                        args: None,
+                    }],
+                },
+            )])
+        };
+
+        validate_syscalls(&filter("read"), TargetArch::X86_64).unwrap();
+        validate_syscalls(&filter("read"), TargetArch::Aarch64).unwrap();
+        // `open` only exists on x86_64.
+        validate_syscalls(&filter("open"), TargetArch::X86_64).unwrap();
+        assert!(matches!(
+            validate_syscalls(&filter("open"), TargetArch::Aarch64),
+            Err(CompilationError::UnknownSyscall(name, syscall, TargetArch::Aarch64))
+                if name == "vmm" && syscall == "open"
+        ));
+        assert!(matches!(
+            validate_syscalls(&filter("not_a_syscall"), TargetArch::X86_64),
+            Err(CompilationError::UnknownSyscall(..))
+        ));
+    }
+
+    #[test]
+    fn test_diff_bpf() {
+        let dir = TempDir::new().unwrap();
+        let base = write_file(
+            &dir,
+            "base.json",
+            r#"{
+                "vmm": {
+                    "default_action": "trap",
+                    "filter_action": "allow",
+                    "filter": [
+                        {"syscall": "read"},
+                        {"syscall": "write"},
+                        {"syscall": "ioctl", "args": [
+                            {"index": 1, "type": "dword", "op": "eq", "val": 1}
+                        ]}
+                    ]
+                },
+                "api": {
+                    "default_action": "trap",
+                    "filter_action": "allow",
+                    "filter": [{"syscall": "read"}]
+                }
+            }"#,
+        );
+        let other = write_file(
+            &dir,
+            "other.json",
+            r#"{
+                "include": ["base.json"],
+                "vmm": {
+                    "extends": "vmm",
+                    "default_action": "kill_process",
+                    "filter": [
+                        {"syscall": "openat"},
+                        {"syscall": "ioctl", "args": [
+                            {"index": 1, "type": "dword", "op": "eq", "val": 2}
+                        ]}
+                    ],
+                    "remove": ["write"]
+                },
+                "new": {
+                    "default_action": "trap",
+                    "filter_action": "allow"
+                }
+            }"#,
+        );
+
+        let base = base.to_str().unwrap();
+        let other = other.to_str().unwrap();
+        let diff = diff_bpf(base, other).unwrap();
+        assert_eq!(
+            diff,
+            [
+                format!("--- {base}"),
+                format!("+++ {other}"),
+                "+ filter new".to_string(),
+                "~ filter vmm".to_string(),
+                "    default_action: Trap -> KillProcess".to_string(),
+                "    ~ ioctl (argument rules differ)".to_string(),
+                "    + openat".to_string(),
+                "    - write".to_string(),
+            ]
+            .join("\n")
+        );
+        // The other file includes all the filters of the base file, the removals only show up
+        // in the reverse diff.
+        let diff = diff_bpf(other, base).unwrap();
+        assert!(diff.contains("- filter new"));
+        assert!(diff.contains("    default_action: KillProcess -> Trap"));
+        assert!(diff.contains("    - openat"));
+        assert!(diff.contains("    + write"));
+
+        let empty_diff = diff_bpf(base, base).unwrap();
+        assert_eq!(empty_diff, format!("--- {base}\n+++ {base}"));
+    }
+}
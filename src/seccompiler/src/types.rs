@@ -11,7 +11,7 @@ use serde::*;
 use crate::bindings::*;
 
 /// Comparison to perform when matching a condition.
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Deserialize)]
 #[serde(rename_all = "snake_case")]
 pub enum SeccompCmpOp {
     Eq,
@@ -34,7 +34,7 @@ pub enum SeccompCmpArgLen {
 }
 
 /// Condition that syscall must match in order to satisfy a rule.
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Deserialize)]
 pub struct SeccompCondition {
     pub index: u8,
     pub op: SeccompCmpOp,
@@ -115,7 +115,7 @@ impl SeccompCondition {
 }
 
 /// Actions that `seccomp` can apply to process calling a syscall.
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Deserialize)]
 #[serde(rename_all = "snake_case")]
 pub enum SeccompAction {
     Allow,
@@ -146,26 +146,51 @@ impl SeccompAction {
 /// If all conditions match then rule gets matched.
 /// The action of the first rule that matches will be applied to the calling process.
 /// If no rule matches the default action is applied.
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Deserialize)]
 pub struct SyscallRule {
     pub syscall: CString,
     pub args: Option<Vec<SeccompCondition>>,
 }
 
 /// Filter containing rules assigned to syscall numbers.
+///
+/// A filter can extend another filter, in which case `default_action` and `filter_action`
+/// are inherited unless overridden, the rules in `filter` are appended to the ones of the
+/// base filter and the syscalls listed in `remove` are dropped from it.
 #[derive(Debug, Deserialize)]
 pub struct Filter {
+    pub extends: Option<String>,
+    pub default_action: Option<SeccompAction>,
+    pub filter_action: Option<SeccompAction>,
+    #[serde(default)]
+    pub filter: Vec<SyscallRule>,
+    #[serde(default)]
+    pub remove: Vec<CString>,
+}
+
+/// Filter with all `extends` references resolved.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ResolvedFilter {
     pub default_action: SeccompAction,
     pub filter_action: SeccompAction,
     pub filter: Vec<SyscallRule>,
 }
 
 /// Deserializable object that represents the Json filter file.
+///
+/// Filters from the files listed in `include` (relative to the including file) are
+/// added to the ones defined in this file, unless a filter with the same name is
+/// defined here as well.
 #[derive(Debug, Deserialize)]
-pub struct BpfJson(pub BTreeMap<String, Filter>);
+pub struct BpfJson {
+    #[serde(default)]
+    pub include: Vec<String>,
+    #[serde(flatten)]
+    pub filters: BTreeMap<String, Filter>,
+}
 
 /// Supported target architectures.
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy)]
 pub enum TargetArch {
     X86_64,
     Aarch64,
@@ -180,6 +205,15 @@ impl TargetArch {
     }
 }
 
+impl std::fmt::Display for TargetArch {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            TargetArch::X86_64 => write!(f, "x86_64"),
+            TargetArch::Aarch64 => write!(f, "aarch64"),
+        }
+    }
+}
+
 impl FromStr for TargetArch {
     type Err = String;
     fn from_str(s: &str) -> Result<Self, Self::Err> {
@@ -5,7 +5,7 @@ use std::path::PathBuf;
 
 use clap::Subcommand;
 use semver::Version;
-use vmm::persist::MicrovmState;
+use vmm::persist::{HostCompatibilityError, MicrovmState, host_incompatibilities};
 
 use crate::utils::*;
 
@@ -13,6 +13,10 @@ use crate::utils::*;
 pub enum InfoVmStateError {
     /// {0}
     Utils(#[from] UtilsError),
+    /// Failed to check the host compatibility: {0}
+    HostCompatibility(#[from] HostCompatibilityError),
+    /// The snapshot is not compatible with the host: {0} incompatible feature(s).
+    Incompatible(usize),
 }
 
 #[derive(Debug, Subcommand)]
@@ -35,6 +39,12 @@ pub enum InfoVmStateSubCommand {
         #[arg(short, long)]
         vmstate_path: PathBuf,
     },
+    /// Check that the host supports the CPU features of the snapshot.
+    CheckHost {
+        /// Path to the vmstate file.
+        #[arg(short, long)]
+        vmstate_path: PathBuf,
+    },
 }
 
 pub fn info_vmstate_command(command: InfoVmStateSubCommand) -> Result<(), InfoVmStateError> {
@@ -44,6 +54,7 @@ pub fn info_vmstate_command(command: InfoVmStateSubCommand) -> Result<(), InfoVm
             info(&vmstate_path, info_vcpu_states)?
         }
         InfoVmStateSubCommand::VmState { vmstate_path } => info(&vmstate_path, info_vmstate)?,
+        InfoVmStateSubCommand::CheckHost { vmstate_path } => info(&vmstate_path, info_check_host)?,
     }
     Ok(())
 }
@@ -74,3 +85,15 @@ fn info_vmstate(vmstate: &MicrovmState, _version: Version) -> Result<(), InfoVmS
     println!("{vmstate:#?}");
     Ok(())
 }
+
+fn info_check_host(vmstate: &MicrovmState, _: Version) -> Result<(), InfoVmStateError> {
+    let incompatibilities = host_incompatibilities(vmstate)?;
+    if incompatibilities.is_empty() {
+        println!("The snapshot is compatible with the host.");
+        return Ok(());
+    }
+    for incompatibility in incompatibilities.iter() {
+        println!("{incompatibility}");
+    }
+    Err(InfoVmStateError::Incompatible(incompatibilities.len()))
+}
@@ -2,18 +2,28 @@
 // SPDX-License-Identifier: Apache-2.0
 
 use acpi_tables::fadt::{FADT_F_HW_REDUCED_ACPI, FADT_F_PWR_BUTTON, FADT_F_SLP_BUTTON};
-use acpi_tables::{Aml, Dsdt, Fadt, Madt, Rsdp, Sdt, Xsdt, aml};
+use acpi_tables::srat::{MemoryAffinity, ProcessorLocalApicAffinity};
+use acpi_tables::{Aml, Dsdt, Fadt, Madt, Mcfg, Rsdp, Sdt, Slit, Srat, Wdat, Xsdt, aml};
 use log::{debug, error};
 use vm_allocator::AllocPolicy;
+use zerocopy::IntoBytes;
 
 use crate::Vcpu;
 use crate::acpi::x86_64::{
     apic_addr, rsdp_addr, setup_arch_dsdt, setup_arch_fadt, setup_interrupt_controllers,
+    setup_watchdog_instructions,
 };
 use crate::device_manager::acpi::ACPIDeviceManager;
+use crate::device_manager::legacy::PortIODeviceManager;
 use crate::device_manager::mmio::MMIODeviceManager;
+use crate::device_manager::pci::PciDeviceManager;
 use crate::device_manager::resources::ResourceAllocator;
+use crate::devices::legacy::watchdog::{
+    WATCHDOG_MAX_COUNT, WATCHDOG_MIN_COUNT, WATCHDOG_TIMER_PERIOD_MS,
+};
+use crate::vmm_config::machine_config::NumaConfig;
 use crate::vstate::memory::{GuestAddress, GuestMemoryMmap};
+use crate::vstate::numa::node_memory_ranges;
 
 mod x86_64;
 
@@ -30,6 +40,10 @@ const OEM_REVISION: u32 = 0;
 // guest know that it runs within a Firecracker microVM.
 const HYPERVISOR_VENDOR_ID: [u8; 8] = *b"FIRECKVM";
 
+// Relative distances between the NUMA nodes, as normalized by the ACPI specification.
+const LOCAL_NODE_DISTANCE: u8 = 10;
+const REMOTE_NODE_DISTANCE: u8 = 20;
+
 #[derive(Debug, thiserror::Error, displaydoc::Display)]
 /// Error type for ACPI related operations
 pub enum AcpiError {
@@ -81,6 +95,8 @@ impl AcpiTableWriter<'_> {
         &mut self,
         mmio_device_manager: &MMIODeviceManager,
         acpi_device_manager: &ACPIDeviceManager,
+        pio_device_manager: &PortIODeviceManager,
+        pci_device_manager: Option<&PciDeviceManager>,
     ) -> Result<u64, AcpiError> {
         let mut dsdt_data = Vec::new();
 
@@ -90,8 +106,13 @@ impl AcpiTableWriter<'_> {
         // Add GED and VMGenID AML data.
         acpi_device_manager.append_aml_bytes(&mut dsdt_data)?;
 
+        // PCI host bridge AML data.
+        if let Some(pci_device_manager) = pci_device_manager {
+            pci_device_manager.append_aml_bytes(&mut dsdt_data)?;
+        }
+
         // Architecture specific DSDT data
-        setup_arch_dsdt(&mut dsdt_data)?;
+        setup_arch_dsdt(&mut dsdt_data, pio_device_manager)?;
 
         let mut dsdt = Dsdt::new(OEM_ID, *b"FCVMDSDT", OEM_REVISION, dsdt_data);
         self.write_acpi_table(&mut dsdt)
@@ -100,14 +121,14 @@ impl AcpiTableWriter<'_> {
     /// Build the FADT table for the guest
     ///
     /// This includes a pointer with the location of the DSDT in guest memory
-    fn build_fadt(&mut self, dsdt_addr: u64) -> Result<u64, AcpiError> {
+    fn build_fadt(&mut self, dsdt_addr: u64, msi_present: bool) -> Result<u64, AcpiError> {
         let mut fadt = Fadt::new(OEM_ID, *b"FCVMFADT", OEM_REVISION);
         fadt.set_hypervisor_vendor_id(HYPERVISOR_VENDOR_ID);
         fadt.set_x_dsdt(dsdt_addr);
         fadt.set_flags(
             (1 << FADT_F_HW_REDUCED_ACPI) | (1 << FADT_F_PWR_BUTTON) | (1 << FADT_F_SLP_BUTTON),
         );
-        setup_arch_fadt(&mut fadt);
+        setup_arch_fadt(&mut fadt, msi_present);
         self.write_acpi_table(&mut fadt)
     }
 
@@ -125,16 +146,100 @@ impl AcpiTableWriter<'_> {
         self.write_acpi_table(&mut madt)
     }
 
-    /// Build the XSDT table for the guest
+    /// Build the MCFG table for the guest
     ///
-    /// Currently, we pass to the guest just FADT and MADT tables.
-    fn build_xsdt(&mut self, fadt_addr: u64, madt_addr: u64) -> Result<u64, AcpiError> {
-        let mut xsdt = Xsdt::new(
+    /// This includes the location of the ECAM region of the PCI root complex
+    fn build_mcfg(&mut self, ecam_addr: u64) -> Result<u64, AcpiError> {
+        let mut mcfg = Mcfg::new(OEM_ID, *b"FCVMMCFG", OEM_REVISION, ecam_addr, 0, 0);
+        self.write_acpi_table(&mut mcfg)
+    }
+
+    /// Build the WDAT table for the guest
+    ///
+    /// This describes how the guest drives the watchdog device
+    fn build_wdat(&mut self) -> Result<u64, AcpiError> {
+        let mut wdat = Wdat::new(
             OEM_ID,
-            *b"FCMVXSDT",
+            *b"FCVMWDAT",
             OEM_REVISION,
-            vec![fadt_addr, madt_addr],
+            WATCHDOG_TIMER_PERIOD_MS,
+            WATCHDOG_MIN_COUNT,
+            WATCHDOG_MAX_COUNT,
+            setup_watchdog_instructions(),
         );
+        self.write_acpi_table(&mut wdat)
+    }
+
+    /// Build the SRAT table for the guest
+    ///
+    /// This assigns the vCPUs and the guest memory to the NUMA nodes of the microVM
+    fn build_srat(&mut self, numa: &NumaConfig, nr_vcpus: u8) -> Result<u64, AcpiError> {
+        let mut affinity_structures = Vec::new();
+        for vcpu in 0..nr_vcpus {
+            // All vCPUs belong to a node once the NUMA configuration is validated.
+            let node = numa.vcpu_node(vcpu).unwrap_or_default();
+            affinity_structures.extend_from_slice(
+                ProcessorLocalApicAffinity::new(node.try_into().unwrap(), vcpu).as_bytes(),
+            );
+        }
+        for range in node_memory_ranges(self.mem, numa) {
+            affinity_structures.extend_from_slice(
+                MemoryAffinity::new(
+                    range.node.try_into().unwrap(),
+                    range.start.raw_value(),
+                    range.len,
+                )
+                .as_bytes(),
+            );
+        }
+
+        let mut srat = Srat::new(OEM_ID, *b"FCVMSRAT", OEM_REVISION, affinity_structures);
+        self.write_acpi_table(&mut srat)
+    }
+
+    /// Build the SLIT table for the guest
+    ///
+    /// This describes the distances between the NUMA nodes of the microVM
+    fn build_slit(&mut self, numa: &NumaConfig) -> Result<u64, AcpiError> {
+        let nr_nodes = numa.nodes.len();
+        let distances = (0..nr_nodes * nr_nodes)
+            .map(|i| {
+                if i / nr_nodes == i % nr_nodes {
+                    LOCAL_NODE_DISTANCE
+                } else {
+                    REMOTE_NODE_DISTANCE
+                }
+            })
+            .collect();
+
+        let mut slit = Slit::new(
+            OEM_ID,
+            *b"FCVMSLIT",
+            OEM_REVISION,
+            nr_nodes.try_into().unwrap(),
+            distances,
+        );
+        self.write_acpi_table(&mut slit)
+    }
+
+    /// Build the XSDT table for the guest
+    ///
+    /// Currently, we pass to the guest the FADT and MADT tables, the MCFG table if there is a
+    /// PCI root complex, the WDAT table if there is a watchdog device, and the SRAT and SLIT
+    /// tables if the NUMA topology is exposed.
+    fn build_xsdt(
+        &mut self,
+        fadt_addr: u64,
+        madt_addr: u64,
+        mcfg_addr: Option<u64>,
+        wdat_addr: Option<u64>,
+        numa_addrs: Option<(u64, u64)>,
+    ) -> Result<u64, AcpiError> {
+        let mut tables = vec![fadt_addr, madt_addr];
+        tables.extend(mcfg_addr);
+        tables.extend(wdat_addr);
+        tables.extend(numa_addrs.into_iter().flat_map(|(srat, slit)| [srat, slit]));
+        let mut xsdt = Xsdt::new(OEM_ID, *b"FCMVXSDT", OEM_REVISION, tables);
         self.write_acpi_table(&mut xsdt)
     }
 
@@ -166,17 +271,40 @@ pub(crate) fn create_acpi_tables(
     resource_allocator: &mut ResourceAllocator,
     mmio_device_manager: &MMIODeviceManager,
     acpi_device_manager: &ACPIDeviceManager,
+    pio_device_manager: &PortIODeviceManager,
+    pci_device_manager: Option<&PciDeviceManager>,
     vcpus: &[Vcpu],
+    numa: &NumaConfig,
 ) -> Result<(), AcpiError> {
     let mut writer = AcpiTableWriter {
         mem,
         resource_allocator,
     };
 
-    let dsdt_addr = writer.build_dsdt(mmio_device_manager, acpi_device_manager)?;
-    let fadt_addr = writer.build_fadt(dsdt_addr)?;
-    let madt_addr = writer.build_madt(vcpus.len().try_into().unwrap())?;
-    let xsdt_addr = writer.build_xsdt(fadt_addr, madt_addr)?;
+    let dsdt_addr = writer.build_dsdt(
+        mmio_device_manager,
+        acpi_device_manager,
+        pio_device_manager,
+        pci_device_manager,
+    )?;
+    // MSI interrupts are only available to PCI devices.
+    let fadt_addr = writer.build_fadt(dsdt_addr, pci_device_manager.is_some())?;
+    let nr_vcpus = vcpus.len().try_into().unwrap();
+    let madt_addr = writer.build_madt(nr_vcpus)?;
+    let mcfg_addr = pci_device_manager
+        .map(|pci| writer.build_mcfg(pci.ecam_addr()))
+        .transpose()?;
+    let wdat_addr = pio_device_manager
+        .watchdog
+        .is_some()
+        .then(|| writer.build_wdat())
+        .transpose()?;
+    let numa_addrs = (numa.expose_topology && !numa.is_empty())
+        .then(|| -> Result<_, AcpiError> {
+            Ok((writer.build_srat(numa, nr_vcpus)?, writer.build_slit(numa)?))
+        })
+        .transpose()?;
+    let xsdt_addr = writer.build_xsdt(fadt_addr, madt_addr, mcfg_addr, wdat_addr, numa_addrs)?;
     writer.build_rsdp(xsdt_addr)
 }
 
@@ -190,6 +318,8 @@ mod tests {
     use crate::builder::tests::default_vmm;
     use crate::device_manager::resources::ResourceAllocator;
     use crate::utils::u64_to_usize;
+    use crate::vmm_config::machine_config::{NumaConfig, NumaNodeConfig};
+    use crate::vstate::memory::GuestAddress;
     use crate::vstate::vm::tests::setup_vm_with_memory;
 
     struct MockSdt(Vec<u8>);
@@ -257,6 +387,57 @@ mod tests {
         assert_eq!(addr, SYSTEM_MEM_START + 4115);
     }
 
+    #[test]
+    fn test_build_numa_tables() {
+        let mut vmm = default_vmm();
+        let mut writer = AcpiTableWriter {
+            mem: vmm.vm.guest_memory(),
+            resource_allocator: &mut vmm.resource_allocator,
+        };
+        let numa = NumaConfig {
+            nodes: vec![
+                NumaNodeConfig {
+                    mem_size_mib: 64,
+                    vcpus: vec![0],
+                    host_node: None,
+                },
+                NumaNodeConfig {
+                    mem_size_mib: 64,
+                    vcpus: vec![1],
+                    host_node: None,
+                },
+            ],
+            expose_topology: true,
+        };
+
+        let srat_addr = writer.build_srat(&numa, 2).unwrap();
+        let mut srat = [0u8; 48 + 2 * 16 + 2 * 40];
+        writer
+            .mem
+            .read_slice(&mut srat, GuestAddress(srat_addr))
+            .unwrap();
+        assert_eq!(&srat[0..4], b"SRAT");
+        assert_eq!(
+            &srat[4..8],
+            &u32::try_from(srat.len()).unwrap().to_le_bytes()
+        );
+        // The second vCPU belongs to the second node.
+        assert_eq!(&srat[64..68], &[0, 16, 1, 1]);
+        // As does the second half of the guest memory.
+        assert_eq!(&srat[122..126], &1u32.to_le_bytes());
+        assert_eq!(&srat[128..136], &(64u64 << 20).to_le_bytes());
+        assert_eq!(&srat[136..144], &(64u64 << 20).to_le_bytes());
+
+        let slit_addr = writer.build_slit(&numa).unwrap();
+        let mut slit = [0u8; 44 + 4];
+        writer
+            .mem
+            .read_slice(&mut slit, GuestAddress(slit_addr))
+            .unwrap();
+        assert_eq!(&slit[0..4], b"SLIT");
+        assert_eq!(&slit[44..], &[10, 20, 20, 10]);
+    }
+
     // If, for whatever weird reason, we end up with microVM that has less memory than the maximum
     // address we allocate for ACPI tables, we would be able to allocate the tables but we would
     // not be able to write them. This is practically impossible in our case. If we get such a
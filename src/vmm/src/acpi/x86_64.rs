@@ -8,12 +8,29 @@ use acpi_tables::fadt::{
     IAPC_BOOT_ARG_FLAGS_VGA_NOT_PRESENT,
 };
 use acpi_tables::madt::{IoAPIC, LocalAPIC};
-use acpi_tables::{Fadt, aml};
+use acpi_tables::wdat::{
+    WDAT_ACTION_QUERY_COUNTDOWN_PERIOD, WDAT_ACTION_QUERY_CURRENT_COUNTDOWN_PERIOD,
+    WDAT_ACTION_QUERY_RUNNING_STATE, WDAT_ACTION_QUERY_STOPPED_STATE, WDAT_ACTION_RESET,
+    WDAT_ACTION_SET_COUNTDOWN_PERIOD, WDAT_ACTION_SET_RUNNING_STATE, WDAT_ACTION_SET_STOPPED_STATE,
+    WDAT_INSTRUCTION_PRESERVE_REGISTER, WDAT_INSTRUCTION_READ_COUNTDOWN,
+    WDAT_INSTRUCTION_READ_VALUE, WDAT_INSTRUCTION_WRITE_COUNTDOWN, WDAT_INSTRUCTION_WRITE_VALUE,
+    WatchdogInstruction,
+};
+use acpi_tables::{Fadt, GenericAddressStructure, aml};
 use vm_memory::GuestAddress;
 use zerocopy::IntoBytes;
 
 use crate::arch::x86_64::layout;
 use crate::device_manager::legacy::PortIODeviceManager;
+use crate::devices::legacy::watchdog::{
+    WATCHDOG_CONTROL_RUNNING, WATCHDOG_PORT, WATCHDOG_REFRESH, WATCHDOG_REG_CONTROL,
+    WATCHDOG_REG_COUNTDOWN, WATCHDOG_REG_CURRENT_COUNTDOWN, WATCHDOG_REG_REFRESH,
+};
+
+// Address space of the registers accessed through I/O ports, in a Generic Address Structure.
+const ACPI_ADR_SPACE_SYSTEM_IO: u8 = 1;
+// Access size of the 32-bit registers, in a Generic Address Structure.
+const ACPI_ACCESS_SIZE_DWORD: u8 = 3;
 
 #[inline(always)]
 pub(crate) fn setup_interrupt_controllers(nr_vcpus: u8) -> Vec<u8> {
@@ -28,21 +45,102 @@ pub(crate) fn setup_interrupt_controllers(nr_vcpus: u8) -> Vec<u8> {
 }
 
 #[inline(always)]
-pub(crate) fn setup_arch_fadt(fadt: &mut Fadt) {
+pub(crate) fn setup_arch_fadt(fadt: &mut Fadt, msi_present: bool) {
     // Let the guest kernel know that there is not VGA hardware present
-    // neither do we support ASPM, or MSI type of interrupts.
+    // neither do we support ASPM. MSI type of interrupts are only supported
+    // with PCI devices.
     // More info here:
     // https://uefi.org/specs/ACPI/6.5/05_ACPI_Software_Programming_Model.html?highlight=0a06#ia-pc-boot-architecture-flags
-    fadt.setup_iapc_flags(
-        (1 << IAPC_BOOT_ARG_FLAGS_VGA_NOT_PRESENT)
-            | (1 << IAPC_BOOT_ARG_FLAGS_PCI_ASPM)
-            | (1 << IAPC_BOOT_ARG_FLAGS_MSI_NOT_PRESENT),
-    );
+    let mut flags =
+        (1 << IAPC_BOOT_ARG_FLAGS_VGA_NOT_PRESENT) | (1 << IAPC_BOOT_ARG_FLAGS_PCI_ASPM);
+    if !msi_present {
+        flags |= 1 << IAPC_BOOT_ARG_FLAGS_MSI_NOT_PRESENT;
+    }
+    fadt.setup_iapc_flags(flags);
 }
 
 #[inline(always)]
-pub(crate) fn setup_arch_dsdt(dsdt_data: &mut Vec<u8>) -> Result<(), aml::AmlError> {
-    PortIODeviceManager::append_aml_bytes(dsdt_data)
+pub(crate) fn setup_arch_dsdt(
+    dsdt_data: &mut Vec<u8>,
+    pio_device_manager: &PortIODeviceManager,
+) -> Result<(), aml::AmlError> {
+    pio_device_manager.append_aml_bytes(dsdt_data)
+}
+
+/// Returns the WDAT instructions performing the watchdog actions through the I/O port registers
+/// of the watchdog device.
+pub(crate) fn setup_watchdog_instructions() -> Vec<WatchdogInstruction> {
+    let register = |offset: u64| {
+        GenericAddressStructure::new(
+            ACPI_ADR_SPACE_SYSTEM_IO,
+            32,
+            0,
+            ACPI_ACCESS_SIZE_DWORD,
+            WATCHDOG_PORT + offset,
+        )
+    };
+    let control = register(WATCHDOG_REG_CONTROL);
+    let countdown = register(WATCHDOG_REG_COUNTDOWN);
+    let write_control = WDAT_INSTRUCTION_WRITE_VALUE | WDAT_INSTRUCTION_PRESERVE_REGISTER;
+
+    vec![
+        WatchdogInstruction::new(
+            WDAT_ACTION_RESET,
+            WDAT_INSTRUCTION_WRITE_VALUE,
+            register(WATCHDOG_REG_REFRESH),
+            WATCHDOG_REFRESH,
+            WATCHDOG_REFRESH,
+        ),
+        WatchdogInstruction::new(
+            WDAT_ACTION_QUERY_CURRENT_COUNTDOWN_PERIOD,
+            WDAT_INSTRUCTION_READ_COUNTDOWN,
+            register(WATCHDOG_REG_CURRENT_COUNTDOWN),
+            0,
+            u32::MAX,
+        ),
+        WatchdogInstruction::new(
+            WDAT_ACTION_QUERY_COUNTDOWN_PERIOD,
+            WDAT_INSTRUCTION_READ_COUNTDOWN,
+            countdown,
+            0,
+            u32::MAX,
+        ),
+        WatchdogInstruction::new(
+            WDAT_ACTION_SET_COUNTDOWN_PERIOD,
+            WDAT_INSTRUCTION_WRITE_COUNTDOWN,
+            countdown,
+            0,
+            u32::MAX,
+        ),
+        WatchdogInstruction::new(
+            WDAT_ACTION_QUERY_RUNNING_STATE,
+            WDAT_INSTRUCTION_READ_VALUE,
+            control,
+            WATCHDOG_CONTROL_RUNNING,
+            WATCHDOG_CONTROL_RUNNING,
+        ),
+        WatchdogInstruction::new(
+            WDAT_ACTION_SET_RUNNING_STATE,
+            write_control,
+            control,
+            WATCHDOG_CONTROL_RUNNING,
+            WATCHDOG_CONTROL_RUNNING,
+        ),
+        WatchdogInstruction::new(
+            WDAT_ACTION_QUERY_STOPPED_STATE,
+            WDAT_INSTRUCTION_READ_VALUE,
+            control,
+            0,
+            WATCHDOG_CONTROL_RUNNING,
+        ),
+        WatchdogInstruction::new(
+            WDAT_ACTION_SET_STOPPED_STATE,
+            write_control,
+            control,
+            0,
+            WATCHDOG_CONTROL_RUNNING,
+        ),
+    ]
 }
 
 pub(crate) const fn apic_addr() -> u32 {
@@ -18,12 +18,17 @@ use super::gic::GICDevice;
 use crate::device_manager::mmio::MMIODeviceInfo;
 use crate::devices::acpi::vmgenid::{VMGENID_MEM_SIZE, VmGenId};
 use crate::initrd::InitrdConfig;
+use crate::vmm_config::machine_config::{CpuTopology, NumaConfig};
 use crate::vstate::memory::{Address, GuestMemory, GuestMemoryMmap};
+use crate::vstate::numa::node_memory_ranges;
 
 // This is a value for uniquely identifying the FDT node declaring the interrupt controller.
 const GIC_PHANDLE: u32 = 1;
 // This is a value for uniquely identifying the FDT node containing the clock definition.
 const CLOCK_PHANDLE: u32 = 2;
+// This is a value for uniquely identifying the FDT node of the first cpu, the nodes of the other
+// cpus following in order.
+const FIRST_CPU_PHANDLE: u32 = 3;
 // You may be wondering why this big value?
 // This phandle is used to uniquely identify the FDT nodes containing cache information. Each cpu
 // can have a variable number of caches, some of these caches may be shared with other cpus.
@@ -56,6 +61,7 @@ pub enum FdtError {
 }
 
 /// Creates the flattened device tree for this aarch64 microVM.
+#[allow(clippy::too_many_arguments)]
 pub fn create_fdt(
     guest_mem: &GuestMemoryMmap,
     vcpu_mpidr: Vec<u64>,
@@ -64,7 +70,14 @@ pub fn create_fdt(
     gic_device: &GICDevice,
     vmgenid: &Option<VmGenId>,
     initrd: &Option<InitrdConfig>,
+    numa: &NumaConfig,
+    cpu_topology: &CpuTopology,
 ) -> Result<Vec<u8>, FdtError> {
+    // The NUMA nodes are only described to the guest when asked to.
+    let numa = (numa.expose_topology && !numa.is_empty()).then_some(numa);
+    // Without a cpu-map, the guest sees all the cpus in a single socket.
+    let cpu_topology = (cpu_topology.sockets > 1).then_some(cpu_topology);
+
     // Allocate stuff necessary for storing the blob.
     let mut fdt_writer = FdtWriter::new()?;
 
@@ -82,8 +95,9 @@ pub fn create_fdt(
     // This is not mandatory but we use it to point the root node to the node
     // containing description of the interrupt controller for this VM.
     fdt_writer.property_u32("interrupt-parent", GIC_PHANDLE)?;
-    create_cpu_nodes(&mut fdt_writer, &vcpu_mpidr)?;
-    create_memory_node(&mut fdt_writer, guest_mem)?;
+    create_cpu_nodes(&mut fdt_writer, &vcpu_mpidr, numa, cpu_topology)?;
+    create_memory_node(&mut fdt_writer, guest_mem, numa)?;
+    create_distance_map_node(&mut fdt_writer, numa)?;
     create_chosen_node(&mut fdt_writer, cmdline, initrd)?;
     create_gic_node(&mut fdt_writer, gic_device)?;
     create_timer_node(&mut fdt_writer)?;
@@ -101,7 +115,12 @@ pub fn create_fdt(
 }
 
 // Following are the auxiliary function for creating the different nodes that we append to our FDT.
-fn create_cpu_nodes(fdt: &mut FdtWriter, vcpu_mpidr: &[u64]) -> Result<(), FdtError> {
+fn create_cpu_nodes(
+    fdt: &mut FdtWriter,
+    vcpu_mpidr: &[u64],
+    numa: Option<&NumaConfig>,
+    cpu_topology: Option<&CpuTopology>,
+) -> Result<(), FdtError> {
     // Since the L1 caches are not shareable among CPUs and they are direct attributes of the
     // cpu in the device tree, we process the L1 and non-L1 caches separately.
     // We use sysfs for extracting the cache information.
@@ -127,6 +146,16 @@ fn create_cpu_nodes(fdt: &mut FdtWriter, vcpu_mpidr: &[u64]) -> Result<(), FdtEr
         // Set the field to first 24 bits of the MPIDR - Multiprocessor Affinity Register.
         // See http://infocenter.arm.com/help/index.jsp?topic=/com.arm.doc.ddi0488c/BABHBJCI.html.
         fdt.property_u64("reg", mpidr & 0x7FFFFF)?;
+        if cpu_topology.is_some() {
+            fdt.property_u32(
+                "phandle",
+                FIRST_CPU_PHANDLE + u32::try_from(cpu_index).unwrap(),
+            )?;
+        }
+        // See https://github.com/torvalds/linux/blob/master/Documentation/devicetree/bindings/numa.txt.
+        if let Some(node) = numa.and_then(|numa| numa.vcpu_node(u8::try_from(cpu_index).unwrap())) {
+            fdt.property_u32("numa-node-id", u32::try_from(node).unwrap())?;
+        }
 
         for cache in l1_caches.iter() {
             // Please check out
@@ -203,12 +232,42 @@ fn create_cpu_nodes(fdt: &mut FdtWriter, vcpu_mpidr: &[u64]) -> Result<(), FdtEr
 
         fdt.end_node(cpu)?;
     }
+    if let Some(cpu_topology) = cpu_topology {
+        create_cpu_map_node(fdt, cpu_topology)?;
+    }
     fdt.end_node(cpus)?;
 
     Ok(())
 }
 
-fn create_memory_node(fdt: &mut FdtWriter, guest_mem: &GuestMemoryMmap) -> Result<(), FdtError> {
+fn create_cpu_map_node(fdt: &mut FdtWriter, cpu_topology: &CpuTopology) -> Result<(), FdtError> {
+    // See https://github.com/torvalds/linux/blob/master/Documentation/devicetree/bindings/cpu/cpu-topology.txt.
+    // SMT is not supported on aarch64, so each core is a single cpu, and each socket holds a
+    // single cluster of cores.
+    let cpu_map = fdt.begin_node("cpu-map")?;
+    for socket in 0..cpu_topology.sockets {
+        let socket_node = fdt.begin_node(&format!("socket{socket}"))?;
+        let cluster_node = fdt.begin_node("cluster0")?;
+        for core in 0..cpu_topology.cores_per_socket {
+            let core_node = fdt.begin_node(&format!("core{core}"))?;
+            let cpu_index =
+                u32::from(socket) * u32::from(cpu_topology.cores_per_socket) + u32::from(core);
+            fdt.property_u32("cpu", FIRST_CPU_PHANDLE + cpu_index)?;
+            fdt.end_node(core_node)?;
+        }
+        fdt.end_node(cluster_node)?;
+        fdt.end_node(socket_node)?;
+    }
+    fdt.end_node(cpu_map)?;
+
+    Ok(())
+}
+
+fn create_memory_node(
+    fdt: &mut FdtWriter,
+    guest_mem: &GuestMemoryMmap,
+    numa: Option<&NumaConfig>,
+) -> Result<(), FdtError> {
     // See https://github.com/torvalds/linux/blob/master/Documentation/devicetree/booting-without-of.txt#L960
     // for an explanation of this.
 
@@ -220,14 +279,30 @@ fn create_memory_node(fdt: &mut FdtWriter, guest_mem: &GuestMemoryMmap) -> Resul
     // The reason we do this is that Linux does not allow remapping system memory. However, without
     // remap, kernel drivers cannot get virtual addresses to read data from device memory. Leaving
     // this memory region out allows Linux kernel modules to remap and thus read this region.
+    let mem_start = super::layout::DRAM_MEM_START + super::layout::SYSTEM_MEM_SIZE;
+
+    // With NUMA nodes, each of them gets its own memory nodes.
+    if let Some(numa) = numa {
+        for range in node_memory_ranges(guest_mem, numa) {
+            let start = range.start.raw_value().max(mem_start);
+            let end = range.start.raw_value() + range.len;
+            if start >= end {
+                continue;
+            }
+            let mem = fdt.begin_node(&format!("memory@{:x}", start))?;
+            fdt.property_string("device_type", "memory")?;
+            fdt.property_array_u64("reg", &[start, end - start])?;
+            fdt.property_u32("numa-node-id", u32::try_from(range.node).unwrap())?;
+            fdt.end_node(mem)?;
+        }
+        return Ok(());
+    }
+
     let mem_size = guest_mem.last_addr().raw_value()
         - super::layout::DRAM_MEM_START
         - super::layout::SYSTEM_MEM_SIZE
         + 1;
-    let mem_reg_prop = &[
-        super::layout::DRAM_MEM_START + super::layout::SYSTEM_MEM_SIZE,
-        mem_size,
-    ];
+    let mem_reg_prop = &[mem_start, mem_size];
     let mem = fdt.begin_node("memory@ram")?;
     fdt.property_string("device_type", "memory")?;
     fdt.property_array_u64("reg", mem_reg_prop)?;
@@ -236,6 +311,33 @@ fn create_memory_node(fdt: &mut FdtWriter, guest_mem: &GuestMemoryMmap) -> Resul
     Ok(())
 }
 
+fn create_distance_map_node(
+    fdt: &mut FdtWriter,
+    numa: Option<&NumaConfig>,
+) -> Result<(), FdtError> {
+    let Some(numa) = numa else {
+        return Ok(());
+    };
+
+    // Each entry of the matrix is a (node, node, distance) triplet, with the distances as
+    // normalized by the ACPI SLIT.
+    let nr_nodes = u32::try_from(numa.nodes.len()).unwrap();
+    let mut distance_matrix = Vec::new();
+    for from in 0..nr_nodes {
+        for to in 0..nr_nodes {
+            let distance = if from == to { 10 } else { 20 };
+            distance_matrix.extend([from, to, distance]);
+        }
+    }
+
+    let distance_map = fdt.begin_node("distance-map")?;
+    fdt.property_string("compatible", "numa-distance-map-v1")?;
+    fdt.property_array_u32("distance-matrix", &distance_matrix)?;
+    fdt.end_node(distance_map)?;
+
+    Ok(())
+}
+
 fn create_chosen_node(
     fdt: &mut FdtWriter,
     cmdline: CString,
@@ -409,6 +511,29 @@ fn create_rtc_node(fdt: &mut FdtWriter, dev_info: &MMIODeviceInfo) -> Result<(),
     Ok(())
 }
 
+fn create_pvpanic_node(fdt: &mut FdtWriter, dev_info: &MMIODeviceInfo) -> Result<(), FdtError> {
+    // Driver requirements:
+    // https://elixir.bootlin.com/linux/latest/source/Documentation/devicetree/bindings/misc/qemu,pvpanic-mmio.yaml
+    let pvpanic = fdt.begin_node(&format!("pvpanic@{:x}", dev_info.addr))?;
+    fdt.property_string("compatible", "qemu,pvpanic-mmio")?;
+    fdt.property_array_u64("reg", &[dev_info.addr, dev_info.len])?;
+    fdt.end_node(pvpanic)?;
+
+    Ok(())
+}
+
+fn create_flash_node(fdt: &mut FdtWriter, dev_info: &MMIODeviceInfo) -> Result<(), FdtError> {
+    // Driver requirements:
+    // https://elixir.bootlin.com/linux/latest/source/Documentation/devicetree/bindings/mtd/mtd-physmap.yaml
+    let flash = fdt.begin_node(&format!("flash@{:x}", dev_info.addr))?;
+    fdt.property_string("compatible", "cfi-flash")?;
+    fdt.property_array_u64("reg", &[dev_info.addr, dev_info.len])?;
+    fdt.property_u32("bank-width", 4)?;
+    fdt.end_node(flash)?;
+
+    Ok(())
+}
+
 fn create_devices_node(
     fdt: &mut FdtWriter,
     dev_info: &HashMap<(DeviceType, String), MMIODeviceInfo>,
@@ -419,7 +544,9 @@ fn create_devices_node(
     for ((device_type, _device_id), info) in dev_info {
         match device_type {
             DeviceType::BootTimer => (), // since it's not a real device
+            DeviceType::Flash => create_flash_node(fdt, info)?,
             DeviceType::Rtc => create_rtc_node(fdt, info)?,
+            DeviceType::PvPanic => create_pvpanic_node(fdt, info)?,
             DeviceType::Serial => create_serial_node(fdt, info)?,
             DeviceType::Virtio(_) => {
                 ordered_virtio_device.push(info);
@@ -448,6 +575,7 @@ mod tests {
     use crate::arch::aarch64::layout;
     use crate::device_manager::resources::ResourceAllocator;
     use crate::test_utils::arch_mem;
+    use crate::vmm_config::machine_config::NumaNodeConfig;
     use crate::vstate::memory::GuestAddress;
 
     const LEN: u64 = 4096;
@@ -505,6 +633,8 @@ mod tests {
             &gic,
             &None,
             &None,
+            &NumaConfig::default(),
+            &CpuTopology::default(),
         )
         .unwrap();
     }
@@ -525,8 +655,94 @@ mod tests {
             &gic,
             &Some(vmgenid),
             &None,
+            &NumaConfig::default(),
+            &CpuTopology::default(),
+        )
+        .unwrap();
+    }
+
+    #[test]
+    fn test_create_fdt_with_numa() {
+        let mem = arch_mem(4 << 20);
+        let kvm = Kvm::new().unwrap();
+        let vm = kvm.create_vm().unwrap();
+        let gic = create_gic(&vm, 2, None).unwrap();
+        let numa = NumaConfig {
+            nodes: vec![
+                NumaNodeConfig {
+                    mem_size_mib: 2,
+                    vcpus: vec![0],
+                    host_node: None,
+                },
+                NumaNodeConfig {
+                    mem_size_mib: 2,
+                    vcpus: vec![1],
+                    host_node: None,
+                },
+            ],
+            expose_topology: true,
+        };
+        let dtb = create_fdt(
+            &mem,
+            vec![0, 1],
+            CString::new("console=tty0").unwrap(),
+            &HashMap::<(DeviceType, std::string::String), MMIODeviceInfo>::new(),
+            &gic,
+            &None,
+            &None,
+            &numa,
+            &CpuTopology::default(),
         )
         .unwrap();
+
+        let contains = |needle: &[u8]| dtb.windows(needle.len()).any(|window| window == needle);
+        assert!(contains(b"numa-node-id"));
+        assert!(contains(b"distance-map"));
+        // The memory of the first node is all reserved for the system.
+        let second_node = format!("memory@{:x}", layout::DRAM_MEM_START + (2 << 20));
+        assert!(contains(second_node.as_bytes()));
+        assert!(!contains(b"memory@ram"));
+    }
+
+    #[test]
+    fn test_create_fdt_with_cpu_topology() {
+        let mem = arch_mem(layout::FDT_MAX_SIZE + 0x1000);
+        let kvm = Kvm::new().unwrap();
+        let vm = kvm.create_vm().unwrap();
+        let gic = create_gic(&vm, 4, None).unwrap();
+        let create = |cpu_topology: &CpuTopology| {
+            create_fdt(
+                &mem,
+                vec![0x0, 0x1, 0x100, 0x101],
+                CString::new("console=tty0").unwrap(),
+                &HashMap::<(DeviceType, std::string::String), MMIODeviceInfo>::new(),
+                &gic,
+                &None,
+                &None,
+                &NumaConfig::default(),
+                cpu_topology,
+            )
+            .unwrap()
+        };
+        let contains =
+            |dtb: &[u8], needle: &[u8]| dtb.windows(needle.len()).any(|window| window == needle);
+
+        let dtb = create(&CpuTopology {
+            sockets: 2,
+            cores_per_socket: 2,
+            threads_per_core: 1,
+        });
+        assert!(contains(&dtb, b"cpu-map"));
+        assert!(contains(&dtb, b"socket1"));
+        assert!(contains(&dtb, b"core1"));
+
+        // A single socket is left to the default topology.
+        let dtb = create(&CpuTopology {
+            sockets: 1,
+            cores_per_socket: 4,
+            threads_per_core: 1,
+        });
+        assert!(!contains(&dtb, b"cpu-map"));
     }
 
     #[test]
@@ -550,6 +766,8 @@ mod tests {
             &gic,
             &None,
             &None,
+            &NumaConfig::default(),
+            &CpuTopology::default(),
         )
         .unwrap();
 
@@ -612,6 +830,8 @@ mod tests {
             &gic,
             &None,
             &Some(initrd),
+            &NumaConfig::default(),
+            &CpuTopology::default(),
         )
         .unwrap();
 
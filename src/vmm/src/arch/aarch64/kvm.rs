@@ -5,16 +5,23 @@ use std::convert::Infallible;
 
 use kvm_ioctls::Kvm as KvmFd;
 
+use crate::arch::aarch64::vm::KVM_ARM_FEATURE_ID_RANGE;
 use crate::cpu_config::templates::KvmCapability;
 
 /// ['Kvm'] initialization can't fail for Aarch64
 pub type KvmArchError = Infallible;
 
+/// Capability reporting the register ranges supported by `KVM_ARM_GET_REG_WRITABLE_MASKS`.
+/// https://elixir.bootlin.com/linux/v6.8/source/include/uapi/linux/kvm.h#L1203
+pub const KVM_CAP_ARM_SUPPORTED_REG_MASK_RANGES: u32 = 230;
+
 /// Optional capabilities.
 #[derive(Debug, Default)]
 pub struct OptionalCapabilities {
     /// KVM_CAP_COUNTER_OFFSET
     pub counter_offset: bool,
+    /// KVM_CAP_ARM_SUPPORTED_REG_MASK_RANGES with the feature ID registers range
+    pub id_reg_writable_masks: bool,
 }
 
 /// Struct with kvm fd and kvm associated parameters.
@@ -55,6 +62,11 @@ impl Kvm {
                 .fd
                 .check_extension_raw(kvm_bindings::KVM_CAP_COUNTER_OFFSET.into())
                 != 0,
+            id_reg_writable_masks: self
+                .fd
+                .check_extension_raw(KVM_CAP_ARM_SUPPORTED_REG_MASK_RANGES.into())
+                & (1 << KVM_ARM_FEATURE_ID_RANGE)
+                != 0,
         }
     }
 }
@@ -82,3 +82,10 @@ pub const IRQ_BASE: u32 = 32;
 
 /// Below this address will reside the GIC, above this address will reside the MMIO devices.
 pub const MAPPED_IO_START: u64 = 1 << 30; // 1 GB
+
+/// Start of the flash region, where a firmware and its variable store are mapped.
+pub const FLASH_START: u64 = 0;
+/// Size of the flash region.
+pub const FLASH_SIZE: u64 = 0x0800_0000; // 128 MB
+/// Size of the erase blocks of the flash holding the variable store.
+pub const FLASH_BLOCK_SIZE: u64 = 0x4_0000; // 256 KB
@@ -49,6 +49,8 @@ pub enum ConfigurationError {
     VcpuConfig(#[from] CpuConfigurationError),
     /// Error configuring the vcpu: {0}
     VcpuConfigure(#[from] KvmVcpuError),
+    /// Error reading the writable bits of the ID registers: {0}
+    RegWritableMasks(#[from] vm::ArchVmError),
 }
 
 /// The start of the memory area reserved for MMIO devices.
@@ -89,6 +91,13 @@ pub fn arch_memory_regions(offset: usize, size: usize) -> Vec<(GuestAddress, usi
     )]
 }
 
+/// Returns a Vec of the valid memory addresses, like [`arch_memory_regions`]. DRAM starts at a
+/// multiple of all the supported page sizes, so the regions can be backed by pages of `page_size`
+/// bytes as long as `size` is a multiple of it.
+pub fn arch_memory_regions_aligned(size: usize, _page_size: usize) -> Vec<(GuestAddress, usize)> {
+    arch_memory_regions(0, size)
+}
+
 /// Configures the system for booting Linux.
 pub fn configure_system_for_boot(
     vmm: &mut Vmm,
@@ -99,19 +108,27 @@ pub fn configure_system_for_boot(
     initrd: &Option<InitrdConfig>,
     boot_cmdline: Cmdline,
 ) -> Result<(), ConfigurationError> {
+    let optional_capabilities = vmm.kvm.optional_capabilities();
+
     // Construct the base CpuConfiguration to apply CPU template onto.
-    let cpu_config = CpuConfiguration::new(cpu_template, vcpus)?;
+    let base_cpu_config = CpuConfiguration::new(cpu_template, vcpus)?;
 
     // Apply CPU template to the base CpuConfiguration.
-    let cpu_config = CpuConfiguration::apply_template(cpu_config, cpu_template);
+    let cpu_config = CpuConfiguration::apply_template(base_cpu_config.clone(), cpu_template);
+
+    // Reject templates changing ID register bits KVM would refuse, when KVM reports them.
+    if optional_capabilities.id_reg_writable_masks {
+        let masks = vmm.vm.get_reg_writable_masks()?;
+        cpu_config.check_writable(&base_cpu_config, &masks)?;
+    }
 
     let vcpu_config = VcpuConfig {
         vcpu_count: machine_config.vcpu_count,
         smt: machine_config.smt,
+        sockets: machine_config.cpu_topology().sockets,
         cpu_config,
     };
 
-    let optional_capabilities = vmm.kvm.optional_capabilities();
     // Configure vCPUs with normalizing and setting the generated CPU configuration.
     for vcpu in vcpus.iter_mut() {
         vcpu.kvm_vcpu.configure(
@@ -138,6 +155,8 @@ pub fn configure_system_for_boot(
         vmm.vm.get_irqchip(),
         &vmm.acpi_device_manager.vmgenid,
         initrd,
+        &machine_config.numa,
+        &machine_config.cpu_topology(),
     )?;
 
     let fdt_address = GuestAddress(get_fdt_addr(vmm.vm.guest_memory()));
@@ -153,6 +172,33 @@ pub fn get_kernel_start() -> u64 {
     layout::SYSTEM_MEM_START + layout::SYSTEM_MEM_SIZE
 }
 
+/// Returns the first address where device memory, such as the regions of virtio-pmem devices,
+/// can be mapped. It is past the guest memory.
+pub fn device_memory_start(guest_mem: &GuestMemoryMmap) -> u64 {
+    guest_mem.last_addr().raw_value() + 1
+}
+
+/// Returns the guest addresses of a firmware of `code_size` bytes and of its variable store of
+/// `vars_size` bytes, or `None` if they don't fit in the flash region. The firmware starts the
+/// flash region and the variable store is right after it.
+pub fn firmware_addresses(code_size: u64, vars_size: u64) -> Option<(GuestAddress, GuestAddress)> {
+    if code_size.checked_add(vars_size)? > layout::FLASH_SIZE {
+        return None;
+    }
+    Some((
+        GuestAddress(layout::FLASH_START),
+        GuestAddress(layout::FLASH_START + code_size),
+    ))
+}
+
+/// Returns the entry point of a firmware mapped at `code_addr`: its first instruction.
+pub fn firmware_entry_point(code_addr: GuestAddress) -> EntryPoint {
+    EntryPoint {
+        entry_addr: code_addr,
+        protocol: BootProtocol::Firmware,
+    }
+}
+
 /// Returns the memory address where the initrd could be loaded.
 pub fn initrd_load_addr(guest_mem: &GuestMemoryMmap, initrd_size: usize) -> Option<u64> {
     let rounded_size = align_up(
@@ -254,6 +300,16 @@ mod tests {
     use super::*;
     use crate::test_utils::arch_mem;
 
+    #[test]
+    fn test_firmware_addresses() {
+        let (code_addr, vars_addr) = firmware_addresses(0x20_0000, 0x8_0000).unwrap();
+        assert_eq!(code_addr, GuestAddress(layout::FLASH_START));
+        assert_eq!(vars_addr, GuestAddress(layout::FLASH_START + 0x20_0000));
+
+        assert!(firmware_addresses(layout::FLASH_SIZE, 0).is_some());
+        assert!(firmware_addresses(layout::FLASH_SIZE, 0x1000).is_none());
+    }
+
     #[test]
     fn test_regions_lt_1024gb() {
         let regions = arch_memory_regions(0, 1usize << 29);
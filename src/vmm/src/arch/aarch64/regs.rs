@@ -84,11 +84,67 @@ arm64_sys_reg!(MPIDR_EL1, 3, 0, 0, 0, 5);
 arm64_sys_reg!(MIDR_EL1, 3, 0, 0, 0, 0);
 
 // ID registers that represent cpu capabilities.
-// Needed for static cpu templates.
+// Needed for cpu templates.
+// https://elixir.bootlin.com/linux/v6.8/source/arch/arm64/tools/sysreg
 arm64_sys_reg!(ID_AA64PFR0_EL1, 3, 0, 0, 4, 0);
+arm64_sys_reg!(ID_AA64PFR1_EL1, 3, 0, 0, 4, 1);
+arm64_sys_reg!(ID_AA64PFR2_EL1, 3, 0, 0, 4, 2);
+arm64_sys_reg!(ID_AA64ZFR0_EL1, 3, 0, 0, 4, 4);
+arm64_sys_reg!(ID_AA64SMFR0_EL1, 3, 0, 0, 4, 5);
+arm64_sys_reg!(ID_AA64FPFR0_EL1, 3, 0, 0, 4, 7);
+arm64_sys_reg!(ID_AA64DFR0_EL1, 3, 0, 0, 5, 0);
+arm64_sys_reg!(ID_AA64DFR1_EL1, 3, 0, 0, 5, 1);
+arm64_sys_reg!(ID_AA64AFR0_EL1, 3, 0, 0, 5, 4);
+arm64_sys_reg!(ID_AA64AFR1_EL1, 3, 0, 0, 5, 5);
 arm64_sys_reg!(ID_AA64ISAR0_EL1, 3, 0, 0, 6, 0);
 arm64_sys_reg!(ID_AA64ISAR1_EL1, 3, 0, 0, 6, 1);
+arm64_sys_reg!(ID_AA64ISAR2_EL1, 3, 0, 0, 6, 2);
+arm64_sys_reg!(ID_AA64ISAR3_EL1, 3, 0, 0, 6, 3);
+arm64_sys_reg!(ID_AA64MMFR0_EL1, 3, 0, 0, 7, 0);
+arm64_sys_reg!(ID_AA64MMFR1_EL1, 3, 0, 0, 7, 1);
 arm64_sys_reg!(ID_AA64MMFR2_EL1, 3, 0, 0, 7, 2);
+arm64_sys_reg!(ID_AA64MMFR3_EL1, 3, 0, 0, 7, 3);
+arm64_sys_reg!(ID_AA64MMFR4_EL1, 3, 0, 0, 7, 4);
+
+/// Names of the ID registers that can be modified by cpu templates.
+pub const ID_REGISTERS: [(&str, u64); 20] = [
+    ("MIDR_EL1", MIDR_EL1),
+    ("ID_AA64PFR0_EL1", ID_AA64PFR0_EL1),
+    ("ID_AA64PFR1_EL1", ID_AA64PFR1_EL1),
+    ("ID_AA64PFR2_EL1", ID_AA64PFR2_EL1),
+    ("ID_AA64ZFR0_EL1", ID_AA64ZFR0_EL1),
+    ("ID_AA64SMFR0_EL1", ID_AA64SMFR0_EL1),
+    ("ID_AA64FPFR0_EL1", ID_AA64FPFR0_EL1),
+    ("ID_AA64DFR0_EL1", ID_AA64DFR0_EL1),
+    ("ID_AA64DFR1_EL1", ID_AA64DFR1_EL1),
+    ("ID_AA64AFR0_EL1", ID_AA64AFR0_EL1),
+    ("ID_AA64AFR1_EL1", ID_AA64AFR1_EL1),
+    ("ID_AA64ISAR0_EL1", ID_AA64ISAR0_EL1),
+    ("ID_AA64ISAR1_EL1", ID_AA64ISAR1_EL1),
+    ("ID_AA64ISAR2_EL1", ID_AA64ISAR2_EL1),
+    ("ID_AA64ISAR3_EL1", ID_AA64ISAR3_EL1),
+    ("ID_AA64MMFR0_EL1", ID_AA64MMFR0_EL1),
+    ("ID_AA64MMFR1_EL1", ID_AA64MMFR1_EL1),
+    ("ID_AA64MMFR2_EL1", ID_AA64MMFR2_EL1),
+    ("ID_AA64MMFR3_EL1", ID_AA64MMFR3_EL1),
+    ("ID_AA64MMFR4_EL1", ID_AA64MMFR4_EL1),
+];
+
+/// Returns the id of the ID register with the given name.
+pub fn id_register_by_name(name: &str) -> Option<u64> {
+    ID_REGISTERS
+        .iter()
+        .find(|(reg_name, _)| *reg_name == name)
+        .map(|(_, id)| *id)
+}
+
+/// Returns the name of the ID register with the given id.
+pub fn id_register_name(id: u64) -> Option<&'static str> {
+    ID_REGISTERS
+        .iter()
+        .find(|(_, reg_id)| *reg_id == id)
+        .map(|(name, _)| *name)
+}
 
 // Counter-timer Virtual Timer CompareValue register.
 // https://developer.arm.com/documentation/ddi0595/2021-12/AArch64-Registers/CNTV-CVAL-EL0--Counter-timer-Virtual-Timer-CompareValue-register
@@ -106,15 +162,14 @@ arm64_sys_reg!(SYS_CNTPCT_EL0, 3, 3, 14, 0, 1);
 // https://elixir.bootlin.com/linux/v6.12.6/source/arch/arm64/include/uapi/asm/kvm.h#L259
 arm64_sys_reg!(KVM_REG_ARM_PTIMER_CNT, 3, 3, 14, 0, 1);
 
-// Translation Table Base Register
+// Translation Table Base Registers
+// https://developer.arm.com/documentation/ddi0595/2021-03/AArch64-Registers/TTBR0-EL1--Translation-Table-Base-Register-0--EL1-
+arm64_sys_reg!(TTBR0_EL1, 3, 0, 2, 0, 0);
 // https://developer.arm.com/documentation/ddi0595/2021-03/AArch64-Registers/TTBR1-EL1--Translation-Table-Base-Register-1--EL1-
 arm64_sys_reg!(TTBR1_EL1, 3, 0, 2, 0, 1);
 // Translation Control Register
 // https://developer.arm.com/documentation/ddi0601/2024-09/AArch64-Registers/TCR-EL1--Translation-Control-Register--EL1-
 arm64_sys_reg!(TCR_EL1, 3, 0, 2, 0, 2);
-// AArch64 Memory Model Feature Register
-// https://developer.arm.com/documentation/100798/0400/register-descriptions/aarch64-system-registers/id-aa64mmfr0-el1--aarch64-memory-model-feature-register-0--el1
-arm64_sys_reg!(ID_AA64MMFR0_EL1, 3, 0, 0, 7, 0);
 
 /// Vector lengths pseudo-register
 /// TODO: this can be removed after https://github.com/rust-vmm/kvm-bindings/pull/89
@@ -532,6 +587,17 @@ mod tests {
         assert_eq!(reg_size(ID_AA64PFR0_EL1), 8);
     }
 
+    #[test]
+    fn test_id_register_names() {
+        assert_eq!(
+            id_register_by_name("ID_AA64ISAR1_EL1"),
+            Some(ID_AA64ISAR1_EL1)
+        );
+        assert_eq!(id_register_by_name("ID_AA64ISAR4_EL1"), None);
+        assert_eq!(id_register_name(ID_AA64PFR1_EL1), Some("ID_AA64PFR1_EL1"));
+        assert_eq!(id_register_name(MPIDR_EL1), None);
+    }
+
     #[test]
     fn test_aarch64_register_vec_serde() {
         let mut v = Aarch64RegisterVec::default();
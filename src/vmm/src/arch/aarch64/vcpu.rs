@@ -62,6 +62,12 @@ pub fn get_manufacturer_id_from_host() -> Result<u32, VcpuArchError> {
     Ok(manufacturer_id >> 24)
 }
 
+/// Returns the MPIDR of the vCPU `index` in a socket of `cpus_per_socket` vCPUs.
+fn socket_mpidr(index: u8, cpus_per_socket: u8) -> u64 {
+    // Bit 31 is RES1, Aff1 (bits 15:8) is the socket and Aff0 (bits 7:0) the core.
+    (1 << 31) | (u64::from(index / cpus_per_socket) << 8) | u64::from(index % cpus_per_socket)
+}
+
 /// Saves states of registers into `state`.
 ///
 /// # Arguments
@@ -192,6 +198,21 @@ impl KvmVcpu {
             })?;
         }
 
+        // With multiple sockets, the sockets are laid out in the affinity level 1 of the MPIDRs,
+        // and their cores in the affinity level 0. Otherwise, the MPIDR set by KVM is kept.
+        if vcpu_config.sockets > 1 {
+            let mpidr = socket_mpidr(self.index, vcpu_config.vcpu_count / vcpu_config.sockets);
+            self.fd
+                .set_one_reg(MPIDR_EL1, &mpidr.to_le_bytes())
+                .map_err(|err| {
+                    KvmVcpuError::ConfigureRegisters(VcpuArchError::SetOneReg(
+                        MPIDR_EL1,
+                        format!("{mpidr:#x}"),
+                        err,
+                    ))
+                })?;
+        }
+
         self.setup_boot_regs(
             kernel_entry_point.entry_addr.raw_value(),
             guest_mem,
@@ -601,6 +622,7 @@ mod tests {
         let vcpu_config = VcpuConfig {
             vcpu_count: 1,
             smt: false,
+            sockets: 1,
             cpu_config: CpuConfiguration::default(),
         };
 
@@ -642,6 +664,33 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_configure_vcpu_sockets() {
+        let (kvm, vm, mut vcpu) = setup_vcpu(0x10000);
+
+        let vcpu_config = VcpuConfig {
+            vcpu_count: 4,
+            smt: false,
+            sockets: 2,
+            cpu_config: CpuConfiguration::default(),
+        };
+        vcpu.configure(
+            vm.guest_memory(),
+            EntryPoint {
+                entry_addr: GuestAddress(crate::arch::get_kernel_start()),
+                protocol: BootProtocol::LinuxBoot,
+            },
+            &vcpu_config,
+            &kvm.optional_capabilities(),
+        )
+        .unwrap();
+        assert_eq!(vcpu.get_mpidr().unwrap(), socket_mpidr(0, 2));
+
+        // The last vCPU is the second core of the second socket.
+        assert_eq!(socket_mpidr(3, 2), 0x8000_0101);
+        assert_eq!(socket_mpidr(2, 2), 0x8000_0100);
+    }
+
     #[test]
     fn test_init_vcpu() {
         let (_, mut vm) = setup_vm_with_memory(0x1000);
@@ -1,7 +1,15 @@
 // Copyright 2025 Amazon.com, Inc. or its affiliates. All Rights Reserved.
 // SPDX-License-Identifier: Apache-2.0
 
+use kvm_bindings::{
+    KVM_REG_ARM64_SYSREG_CRM_MASK, KVM_REG_ARM64_SYSREG_CRM_SHIFT, KVM_REG_ARM64_SYSREG_CRN_MASK,
+    KVM_REG_ARM64_SYSREG_CRN_SHIFT, KVM_REG_ARM64_SYSREG_OP0_MASK, KVM_REG_ARM64_SYSREG_OP0_SHIFT,
+    KVM_REG_ARM64_SYSREG_OP1_MASK, KVM_REG_ARM64_SYSREG_OP1_SHIFT, KVM_REG_ARM64_SYSREG_OP2_MASK,
+    KVM_REG_ARM64_SYSREG_OP2_SHIFT, KVMIO,
+};
 use serde::{Deserialize, Serialize};
+use vmm_sys_util::ioctl::ioctl_with_ref;
+use vmm_sys_util::{ioctl_ioc_nr, ioctl_ior_nr};
 
 use crate::Kvm;
 use crate::arch::aarch64::gic::GicState;
@@ -26,6 +34,67 @@ pub enum ArchVmError {
     SaveGic(crate::arch::aarch64::gic::GicError),
     /// Failed to restore the VM's GIC state: {0}
     RestoreGic(crate::arch::aarch64::gic::GicError),
+    /// Failed to get the writable bits of the ID registers: {0}
+    GetRegWritableMasks(vmm_sys_util::errno::Error),
+}
+
+/// Range of the feature ID registers in `KVM_ARM_GET_REG_WRITABLE_MASKS`.
+/// https://elixir.bootlin.com/linux/v6.8/source/arch/arm64/include/uapi/asm/kvm.h#L520
+pub const KVM_ARM_FEATURE_ID_RANGE: u32 = 0;
+/// Number of registers in the feature ID registers range.
+pub const KVM_ARM_FEATURE_ID_RANGE_SIZE: usize = 3 * 8 * 8;
+
+/// Argument of the `KVM_ARM_GET_REG_WRITABLE_MASKS` ioctl.
+/// https://elixir.bootlin.com/linux/v6.8/source/arch/arm64/include/uapi/asm/kvm.h#L532
+#[repr(C)]
+#[derive(Debug, Default)]
+struct RegMaskRange {
+    addr: u64,
+    range: u32,
+    reserved: [u32; 13],
+}
+
+ioctl_ior_nr!(KVM_ARM_GET_REG_WRITABLE_MASKS, KVMIO, 0xb6, RegMaskRange);
+
+/// Masks of the bits of the feature ID registers that can be changed from userspace.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct IdRegWritableMasks(pub [u64; KVM_ARM_FEATURE_ID_RANGE_SIZE]);
+
+impl IdRegWritableMasks {
+    /// Returns the writable bits of the register with the given id, or `None` if the
+    /// register is not a feature ID register.
+    pub fn mask(&self, reg_id: u64) -> Option<u64> {
+        let field = |mask: u32, shift: u32| (reg_id & u64::from(mask)) >> shift;
+        let op0 = field(
+            KVM_REG_ARM64_SYSREG_OP0_MASK,
+            KVM_REG_ARM64_SYSREG_OP0_SHIFT,
+        );
+        let op1 = field(
+            KVM_REG_ARM64_SYSREG_OP1_MASK,
+            KVM_REG_ARM64_SYSREG_OP1_SHIFT,
+        );
+        let crn = field(
+            KVM_REG_ARM64_SYSREG_CRN_MASK,
+            KVM_REG_ARM64_SYSREG_CRN_SHIFT,
+        );
+        let crm = field(
+            KVM_REG_ARM64_SYSREG_CRM_MASK,
+            KVM_REG_ARM64_SYSREG_CRM_SHIFT,
+        );
+        let op2 = field(
+            KVM_REG_ARM64_SYSREG_OP2_MASK,
+            KVM_REG_ARM64_SYSREG_OP2_SHIFT,
+        );
+
+        // The range covers op0 == 3, op1 == {0, 1, 3}, CRn == 0, CRm == {0-7}.
+        // Same as the KVM_ARM_FEATURE_ID_RANGE_IDX kernel macro.
+        if op0 != 3 || crn != 0 || crm > 7 || op1 == 2 || op1 > 3 {
+            return None;
+        }
+        let op1 = if op1 == 3 { 2 } else { op1 };
+        let index = (op1 << 6) | (crm << 3) | op2;
+        self.0.get(crate::utils::u64_to_usize(index)).copied()
+    }
 }
 
 impl ArchVm {
@@ -66,6 +135,26 @@ impl ArchVm {
         self.irqchip_handle.as_ref().expect("IRQ chip not set")
     }
 
+    /// Gets the masks of the bits of the feature ID registers that can be changed from
+    /// userspace. Requires `KVM_CAP_ARM_SUPPORTED_REG_MASK_RANGES`.
+    pub fn get_reg_writable_masks(&self) -> Result<IdRegWritableMasks, ArchVmError> {
+        let mut masks = IdRegWritableMasks([0; KVM_ARM_FEATURE_ID_RANGE_SIZE]);
+        let range = RegMaskRange {
+            addr: masks.0.as_mut_ptr() as u64,
+            range: KVM_ARM_FEATURE_ID_RANGE,
+            ..Default::default()
+        };
+        // SAFETY: Safe because we call this with a valid vm fd, `range` points to a mask
+        // array of the size expected by the kernel for the range, and we check the return.
+        let ret = unsafe { ioctl_with_ref(self.fd(), KVM_ARM_GET_REG_WRITABLE_MASKS(), &range) };
+        if ret < 0 {
+            return Err(ArchVmError::GetRegWritableMasks(
+                vmm_sys_util::errno::Error::last(),
+            ));
+        }
+        Ok(masks)
+    }
+
     /// Saves and returns the Kvm Vm state.
     pub fn save_state(&self, mpidrs: &[u64]) -> Result<VmState, ArchVmError> {
         Ok(VmState {
@@ -21,9 +21,10 @@ pub use aarch64::vm::{ArchVm, ArchVmError, VmState};
 #[cfg(target_arch = "aarch64")]
 pub use aarch64::{
     ConfigurationError, MMIO_MEM_SIZE, MMIO_MEM_START, arch_memory_regions,
-    configure_system_for_boot, get_kernel_start, initrd_load_addr, layout::CMDLINE_MAX_SIZE,
-    layout::IRQ_BASE, layout::IRQ_MAX, layout::SYSTEM_MEM_SIZE, layout::SYSTEM_MEM_START,
-    load_kernel,
+    arch_memory_regions_aligned, configure_system_for_boot, device_memory_start,
+    firmware_addresses, firmware_entry_point, get_kernel_start, initrd_load_addr,
+    layout::CMDLINE_MAX_SIZE, layout::IRQ_BASE, layout::IRQ_MAX, layout::SYSTEM_MEM_SIZE,
+    layout::SYSTEM_MEM_START, load_kernel,
 };
 
 /// Module for x86_64 related functionality.
@@ -40,9 +41,10 @@ pub use x86_64::vm::{ArchVm, ArchVmError, VmState};
 #[cfg(target_arch = "x86_64")]
 pub use crate::arch::x86_64::{
     ConfigurationError, MMIO_MEM_SIZE, MMIO_MEM_START, arch_memory_regions,
-    configure_system_for_boot, get_kernel_start, initrd_load_addr, layout::APIC_ADDR,
-    layout::CMDLINE_MAX_SIZE, layout::IOAPIC_ADDR, layout::IRQ_BASE, layout::IRQ_MAX,
-    layout::SYSTEM_MEM_SIZE, layout::SYSTEM_MEM_START, load_kernel,
+    arch_memory_regions_aligned, configure_system_for_boot, device_memory_start,
+    firmware_addresses, firmware_entry_point, get_kernel_start, initrd_load_addr,
+    layout::APIC_ADDR, layout::CMDLINE_MAX_SIZE, layout::IOAPIC_ADDR, layout::IRQ_BASE,
+    layout::IRQ_MAX, layout::SYSTEM_MEM_SIZE, layout::SYSTEM_MEM_START, load_kernel,
 };
 
 /// Types of devices that can get attached to this platform.
@@ -56,8 +58,13 @@ pub enum DeviceType {
     /// Device Type: RTC.
     #[cfg(target_arch = "aarch64")]
     Rtc,
+    /// Device Type: PvPanic.
+    #[cfg(target_arch = "aarch64")]
+    PvPanic,
     /// Device Type: BootTimer.
     BootTimer,
+    /// Device Type: Flash.
+    Flash,
 }
 
 /// Default page size for the guest OS.
@@ -92,6 +99,8 @@ pub enum BootProtocol {
     #[cfg(target_arch = "x86_64")]
     /// PVH boot protocol (x86/HVM direct boot ABI)
     PvhBoot,
+    /// Boot from a firmware mapped in the flash region
+    Firmware,
 }
 
 impl fmt::Display for BootProtocol {
@@ -100,6 +109,7 @@ impl fmt::Display for BootProtocol {
             BootProtocol::LinuxBoot => write!(f, "Linux 64-bit boot protocol"),
             #[cfg(target_arch = "x86_64")]
             BootProtocol::PvhBoot => write!(f, "PVH boot protocol"),
+            BootProtocol::Firmware => write!(f, "firmware boot"),
         }
     }
 }
@@ -113,4 +123,7 @@ pub struct EntryPoint {
     pub entry_addr: GuestAddress,
     /// Specifies which boot protocol to use
     pub protocol: BootProtocol,
+    #[cfg(target_arch = "x86_64")]
+    /// Setup header of the kernel image, when the kernel was loaded from a bzImage
+    pub setup_header: Option<linux_loader::loader::bootparam::setup_header>,
 }
@@ -24,8 +24,27 @@ pub const IRQ_BASE: u32 = 5;
 /// Last usable IRQ ID for virtio device interrupts on x86_64.
 pub const IRQ_MAX: u32 = 23;
 
-/// Address for the TSS setup.
-pub const KVM_TSS_ADDRESS: u64 = 0xfffb_d000;
+/// First GSI used for MSI interrupts on x86_64. Lower GSIs are wired to the IOAPIC pins.
+pub const MSI_GSI_BASE: u32 = 24;
+/// Last GSI used for MSI interrupts on x86_64.
+pub const MSI_GSI_MAX: u32 = 255;
+
+/// Address for the TSS setup, below the flash region.
+pub const KVM_TSS_ADDRESS: u64 = 0xfeff_d000;
+
+/// Address of the identity map page used by KVM to run vCPUs in real mode, right below the TSS.
+pub const KVM_IDENTITY_MAP_ADDRESS: u64 = 0xfeff_c000;
+
+/// Start of the flash region, where a firmware and its variable store are mapped. It spans the
+/// last 16 MiB of the 32-bit address space.
+pub const FLASH_START: u64 = 0xff00_0000;
+/// Size of the flash region.
+pub const FLASH_SIZE: u64 = 0x0100_0000;
+/// Size of the erase blocks of the flash holding the variable store.
+pub const FLASH_BLOCK_SIZE: u64 = 0x1000;
+
+/// Address of the first instruction run by a vCPU after a reset.
+pub const RESET_VECTOR: u64 = 0xffff_fff0;
 
 /// Address of the hvm_start_info struct used in PVH boot
 pub const PVH_INFO_START: u64 = 0x6000;
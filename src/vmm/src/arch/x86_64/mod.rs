@@ -32,12 +32,14 @@ pub mod xstate;
 pub mod generated;
 
 use std::fs::File;
+use std::io::{Read, Seek};
 
 use layout::CMDLINE_START;
 use linux_loader::configurator::linux::LinuxBootConfigurator;
 use linux_loader::configurator::pvh::PvhBootConfigurator;
 use linux_loader::configurator::{BootConfigurator, BootParams};
-use linux_loader::loader::bootparam::boot_params;
+use linux_loader::loader::bootparam::{boot_params, setup_header};
+use linux_loader::loader::bzimage::BzImage;
 use linux_loader::loader::elf::Elf as Loader;
 use linux_loader::loader::elf::start_info::{
     hvm_memmap_table_entry, hvm_modlist_entry, hvm_start_info,
@@ -86,6 +88,8 @@ pub enum ConfigurationError {
     KernelFile,
     /// Cannot load kernel due to invalid memory configuration or invalid kernel image: {0}
     KernelLoader(linux_loader::loader::Error),
+    /// The bzImage kernel doesn't have a 64-bit entry point.
+    BzImageNot64Bit,
     /// Cannot load command line string: {0}
     LoadCommandline(linux_loader::loader::Error),
     /// Failed to create guest config: {0}
@@ -140,11 +144,64 @@ pub fn arch_memory_regions(offset: usize, size: usize) -> Vec<(GuestAddress, usi
     }
 }
 
+/// Returns a Vec of the valid memory addresses, like [`arch_memory_regions`], such that all the
+/// regions can be backed by pages of `page_size` bytes, as long as `size` is a multiple of it.
+/// The memory below the 32-bit MMIO gap ends at the last multiple of `page_size` before the gap,
+/// which is the gap itself for pages up to 256MiB, and the rest of the memory is moved past it.
+pub fn arch_memory_regions_aligned(size: usize, page_size: usize) -> Vec<(GuestAddress, usize)> {
+    assert!(size > 0, "Attempt to allocate guest memory of length 0");
+
+    let low_mem_end = u64_to_usize(align_down(MMIO_MEM_START, usize_to_u64(page_size)));
+    match size.checked_sub(low_mem_end) {
+        None | Some(0) => vec![(GuestAddress(0), size)],
+        Some(remaining) => vec![
+            (GuestAddress(0), low_mem_end),
+            (GuestAddress(FIRST_ADDR_PAST_32BITS), remaining),
+        ],
+    }
+}
+
+// Returns the end of the guest memory below the 32-bit MMIO gap, which doesn't necessarily extend
+// up to the gap, e.g. when the guest memory is backed by 1GB pages.
+fn low_mem_end(guest_mem: &GuestMemoryMmap) -> GuestAddress {
+    guest_mem
+        .find_region(GuestAddress(layout::HIMEM_START))
+        .map_or(GuestAddress(MMIO_MEM_START), |region| {
+            region.last_addr().unchecked_add(1)
+        })
+}
+
 /// Returns the memory address where the kernel could be loaded.
 pub fn get_kernel_start() -> u64 {
     layout::HIMEM_START
 }
 
+/// Returns the first address where device memory, such as the regions of virtio-pmem devices,
+/// can be mapped. It is past both the guest memory and the 32-bit MMIO gap.
+pub fn device_memory_start(guest_mem: &GuestMemoryMmap) -> u64 {
+    FIRST_ADDR_PAST_32BITS.max(guest_mem.last_addr().raw_value() + 1)
+}
+
+/// Returns the guest addresses of a firmware of `code_size` bytes and of its variable store of
+/// `vars_size` bytes, or `None` if they don't fit in the flash region. The firmware ends at 4 GiB,
+/// so that it holds the reset vector, and the variable store is right below it.
+pub fn firmware_addresses(code_size: u64, vars_size: u64) -> Option<(GuestAddress, GuestAddress)> {
+    if code_size.checked_add(vars_size)? > layout::FLASH_SIZE {
+        return None;
+    }
+    let code_addr = FIRST_ADDR_PAST_32BITS - code_size;
+    Some((GuestAddress(code_addr), GuestAddress(code_addr - vars_size)))
+}
+
+/// Returns the entry point of a firmware: the reset vector, wherever the firmware starts.
+pub fn firmware_entry_point(_code_addr: GuestAddress) -> EntryPoint {
+    EntryPoint {
+        entry_addr: GuestAddress(layout::RESET_VECTOR),
+        protocol: BootProtocol::Firmware,
+        setup_header: None,
+    }
+}
+
 /// Returns the memory address where the initrd could be loaded.
 pub fn initrd_load_addr(guest_mem: &GuestMemoryMmap, initrd_size: usize) -> Option<u64> {
     let first_region = guest_mem.find_region(GuestAddress::new(0))?;
@@ -179,6 +236,7 @@ pub fn configure_system_for_boot(
     let vcpu_config = VcpuConfig {
         vcpu_count: machine_config.vcpu_count,
         smt: machine_config.smt,
+        sockets: machine_config.cpu_topology().sockets,
         cpu_config,
     };
 
@@ -212,7 +270,12 @@ pub fn configure_system_for_boot(
 
     match entry_point.protocol {
         BootProtocol::PvhBoot => {
-            configure_pvh(vmm.vm.guest_memory(), GuestAddress(CMDLINE_START), initrd)?;
+            configure_pvh(
+                vmm.vm.guest_memory(),
+                GuestAddress(CMDLINE_START),
+                initrd,
+                false,
+            )?;
         }
         BootProtocol::LinuxBoot => {
             configure_64bit_boot(
@@ -220,6 +283,16 @@ pub fn configure_system_for_boot(
                 GuestAddress(CMDLINE_START),
                 cmdline_size,
                 initrd,
+                entry_point.setup_header,
+            )?;
+        }
+        // The firmware finds the memory map in the PVH start info, at a fixed address.
+        BootProtocol::Firmware => {
+            configure_pvh(
+                vmm.vm.guest_memory(),
+                GuestAddress(CMDLINE_START),
+                &None,
+                true,
             )?;
         }
     }
@@ -231,7 +304,10 @@ pub fn configure_system_for_boot(
         &mut vmm.resource_allocator,
         &vmm.mmio_device_manager,
         &vmm.acpi_device_manager,
+        &vmm.pio_device_manager,
+        vmm.pci_device_manager.as_ref(),
         vcpus,
+        &machine_config.numa,
     )?;
     Ok(())
 }
@@ -240,6 +316,7 @@ fn configure_pvh(
     guest_mem: &GuestMemoryMmap,
     cmdline_addr: GuestAddress,
     initrd: &Option<InitrdConfig>,
+    reserve_flash: bool,
 ) -> Result<(), ConfigurationError> {
     const XEN_HVM_START_MAGIC_VALUE: u32 = 0x336e_c578;
     let first_addr_past_32bits = GuestAddress(FIRST_ADDR_PAST_32BITS);
@@ -286,7 +363,7 @@ fn configure_pvh(
     } else {
         memmap.push(hvm_memmap_table_entry {
             addr: himem_start.raw_value(),
-            size: end_32bit_gap_start.unchecked_offset_from(himem_start),
+            size: low_mem_end(guest_mem).unchecked_offset_from(himem_start),
             type_: MEMMAP_TYPE_RAM,
             ..Default::default()
         });
@@ -301,6 +378,17 @@ fn configure_pvh(
         }
     }
 
+    // Keep the guest from placing anything over the flash region of the firmware.
+    if reserve_flash {
+        memmap.push(hvm_memmap_table_entry {
+            addr: layout::FLASH_START,
+            size: layout::FLASH_SIZE,
+            type_: E820_RESERVED,
+            ..Default::default()
+        });
+        memmap.sort_by_key(|entry| entry.addr);
+    }
+
     // Construct the hvm_start_info structure and serialize it into
     // boot_params.  This will be stored at PVH_INFO_START address, and %rbx
     // will be initialized to contain PVH_INFO_START prior to starting the
@@ -340,6 +428,7 @@ fn configure_64bit_boot(
     cmdline_addr: GuestAddress,
     cmdline_size: usize,
     initrd: &Option<InitrdConfig>,
+    setup_header: Option<setup_header>,
 ) -> Result<(), ConfigurationError> {
     const KERNEL_BOOT_FLAG_MAGIC: u16 = 0xaa55;
     const KERNEL_HDR_MAGIC: u32 = 0x5372_6448;
@@ -356,12 +445,18 @@ fn configure_64bit_boot(
         ..Default::default()
     };
 
+    if let Some(hdr) = setup_header {
+        // Start from the setup header of the bzImage, which describes how the kernel has to be
+        // loaded, for example its alignment and the memory it needs to decompress itself.
+        params.hdr = hdr;
+    } else {
+        params.hdr.boot_flag = KERNEL_BOOT_FLAG_MAGIC;
+        params.hdr.header = KERNEL_HDR_MAGIC;
+        params.hdr.kernel_alignment = KERNEL_MIN_ALIGNMENT_BYTES;
+    }
     params.hdr.type_of_loader = KERNEL_LOADER_OTHER;
-    params.hdr.boot_flag = KERNEL_BOOT_FLAG_MAGIC;
-    params.hdr.header = KERNEL_HDR_MAGIC;
     params.hdr.cmd_line_ptr = u32::try_from(cmdline_addr.raw_value()).unwrap();
     params.hdr.cmdline_size = u32::try_from(cmdline_size).unwrap();
-    params.hdr.kernel_alignment = KERNEL_MIN_ALIGNMENT_BYTES;
     if let Some(initrd_config) = initrd {
         params.hdr.ramdisk_image = u32::try_from(initrd_config.address.raw_value()).unwrap();
         params.hdr.ramdisk_size = u32::try_from(initrd_config.size).unwrap();
@@ -393,8 +488,8 @@ fn configure_64bit_boot(
             &mut params,
             himem_start.raw_value(),
             // it's safe to use unchecked_offset_from because
-            // end_32bit_gap_start > himem_start
-            end_32bit_gap_start.unchecked_offset_from(himem_start),
+            // the end of the low memory > himem_start
+            low_mem_end(guest_mem).unchecked_offset_from(himem_start),
             E820_RAM,
         )?;
 
@@ -438,6 +533,9 @@ fn add_e820_entry(
 }
 
 /// Load linux kernel into guest memory.
+///
+/// The kernel image is either an uncompressed ELF `vmlinux` or a bzImage, with or without an EFI
+/// stub.
 pub fn load_kernel(
     kernel: &File,
     guest_memory: &GuestMemoryMmap,
@@ -448,6 +546,15 @@ pub fn load_kernel(
         .try_clone()
         .map_err(|_| ConfigurationError::KernelFile)?;
 
+    let mut magic = [0u8; 4];
+    kernel_file
+        .read_exact(&mut magic)
+        .and_then(|_| kernel_file.rewind())
+        .map_err(|_| ConfigurationError::KernelFile)?;
+    if magic != *b"\x7fELF" {
+        return load_bzimage(&mut kernel_file, guest_memory);
+    }
+
     let entry_addr = Loader::load(
         guest_memory,
         None,
@@ -469,6 +576,48 @@ pub fn load_kernel(
     Ok(EntryPoint {
         entry_addr: entry_point_addr,
         protocol: boot_prot,
+        setup_header: None,
+    })
+}
+
+/// Load a bzImage kernel into guest memory.
+///
+/// Only the protected mode part of the kernel is loaded. The guest starts at its 64-bit entry
+/// point, with the setup header of the image copied into the zero page.
+fn load_bzimage(
+    kernel_file: &mut File,
+    guest_memory: &GuestMemoryMmap,
+) -> Result<EntryPoint, ConfigurationError> {
+    // The kernel can be entered in 64-bit mode.
+    const XLF_KERNEL_64: u16 = 1 << 0;
+    // Offset of the 64-bit entry point from the start of the protected mode kernel.
+    const STARTUP_64_OFFSET: u64 = 0x200;
+
+    let kernel_start = GuestAddress(get_kernel_start());
+    let loader_result = BzImage::load(
+        guest_memory,
+        Some(kernel_start),
+        kernel_file,
+        Some(kernel_start),
+    )
+    .map_err(ConfigurationError::KernelLoader)?;
+
+    let setup_header = loader_result
+        .setup_header
+        .ok_or(ConfigurationError::BzImageNot64Bit)?;
+    if setup_header.xloadflags & XLF_KERNEL_64 == 0 {
+        return Err(ConfigurationError::BzImageNot64Bit);
+    }
+
+    debug!(
+        "Kernel loaded from bzImage using {}",
+        BootProtocol::LinuxBoot
+    );
+
+    Ok(EntryPoint {
+        entry_addr: loader_result.kernel_load.unchecked_add(STARTUP_64_OFFSET),
+        protocol: BootProtocol::LinuxBoot,
+        setup_header: Some(setup_header),
     })
 }
 
@@ -524,11 +673,41 @@ mod verification {
 
 #[cfg(test)]
 mod tests {
+    use std::io::Write;
+
     use linux_loader::loader::bootparam::boot_e820_entry;
+    use vmm_sys_util::tempfile::TempFile;
 
     use super::*;
     use crate::device_manager::resources::ResourceAllocator;
-    use crate::test_utils::{arch_mem, single_region_mem};
+    use crate::test_utils::{arch_mem, multi_region_mem, single_region_mem};
+    use crate::vmm_config::machine_config::HugePageConfig;
+    use crate::vstate::memory::Bytes;
+
+    /// Builds a bzImage with one setup sector, followed by `kernel` as the protected mode part.
+    fn make_bzimage(xloadflags: u16, kernel: &[u8]) -> TempFile {
+        let mut image = vec![0u8; 0x400];
+        // setup_sects
+        image[0x1f1] = 1;
+        // boot_flag
+        image[0x1fe..0x200].copy_from_slice(&0xaa55u16.to_le_bytes());
+        // header
+        image[0x202..0x206].copy_from_slice(b"HdrS");
+        // version
+        image[0x206..0x208].copy_from_slice(&0x020fu16.to_le_bytes());
+        // loadflags: LOADED_HIGH
+        image[0x211] = 1;
+        // code32_start
+        image[0x214..0x218].copy_from_slice(&0x0010_0000u32.to_le_bytes());
+        // kernel_alignment
+        image[0x230..0x234].copy_from_slice(&0x0020_0000u32.to_le_bytes());
+        image[0x236..0x238].copy_from_slice(&xloadflags.to_le_bytes());
+        image.extend_from_slice(kernel);
+
+        let file = TempFile::new().unwrap();
+        file.as_file().write_all(&image).unwrap();
+        file
+    }
 
     #[test]
     fn regions_lt_4gb() {
@@ -569,6 +748,38 @@ mod tests {
         )
     }
 
+    #[test]
+    fn regions_aligned() {
+        const GIB: usize = 1 << 30;
+
+        // Regions backed by pages up to 2MB snuggle up to the MMIO gap.
+        let page_size = HugePageConfig::Hugetlbfs2M.page_size();
+        assert_eq!(
+            arch_memory_regions_aligned(4 * GIB, page_size),
+            arch_memory_regions(0, 4 * GIB)
+        );
+
+        // With 1GB pages, the memory below the MMIO gap ends at the last 1GB boundary before it.
+        let page_size = HugePageConfig::Hugetlbfs1G.page_size();
+        assert_eq!(
+            arch_memory_regions_aligned(2 * GIB, page_size),
+            vec![(GuestAddress(0), 2 * GIB)]
+        );
+        assert_eq!(
+            arch_memory_regions_aligned(4 * GIB, page_size),
+            vec![
+                (GuestAddress(0), 3 * GIB),
+                (GuestAddress(FIRST_ADDR_PAST_32BITS), GIB)
+            ]
+        );
+
+        // The e820 map follows the end of the memory below the gap.
+        let gm = multi_region_mem(&arch_memory_regions_aligned(4 * GIB, page_size));
+        assert_eq!(low_mem_end(&gm), GuestAddress(3 * GIB as u64));
+        let gm = arch_mem(4 * GIB);
+        assert_eq!(low_mem_end(&gm), GuestAddress(MMIO_MEM_START));
+    }
+
     #[test]
     fn test_system_configuration() {
         let no_vcpus = 4;
@@ -585,24 +796,125 @@ mod tests {
         let gm = arch_mem(mem_size);
         let mut resource_allocator = ResourceAllocator::new().unwrap();
         mptable::setup_mptable(&gm, &mut resource_allocator, no_vcpus).unwrap();
-        configure_64bit_boot(&gm, GuestAddress(0), 0, &None).unwrap();
-        configure_pvh(&gm, GuestAddress(0), &None).unwrap();
+        configure_64bit_boot(&gm, GuestAddress(0), 0, &None, None).unwrap();
+        configure_pvh(&gm, GuestAddress(0), &None, false).unwrap();
 
         // Now assigning some memory that is equal to the start of the 32bit memory hole.
         let mem_size = mib_to_bytes(3328);
         let gm = arch_mem(mem_size);
         let mut resource_allocator = ResourceAllocator::new().unwrap();
         mptable::setup_mptable(&gm, &mut resource_allocator, no_vcpus).unwrap();
-        configure_64bit_boot(&gm, GuestAddress(0), 0, &None).unwrap();
-        configure_pvh(&gm, GuestAddress(0), &None).unwrap();
+        configure_64bit_boot(&gm, GuestAddress(0), 0, &None, None).unwrap();
+        configure_pvh(&gm, GuestAddress(0), &None, false).unwrap();
 
         // Now assigning some memory that falls after the 32bit memory hole.
         let mem_size = mib_to_bytes(3330);
         let gm = arch_mem(mem_size);
         let mut resource_allocator = ResourceAllocator::new().unwrap();
         mptable::setup_mptable(&gm, &mut resource_allocator, no_vcpus).unwrap();
-        configure_64bit_boot(&gm, GuestAddress(0), 0, &None).unwrap();
-        configure_pvh(&gm, GuestAddress(0), &None).unwrap();
+        configure_64bit_boot(&gm, GuestAddress(0), 0, &None, None).unwrap();
+        configure_pvh(&gm, GuestAddress(0), &None, false).unwrap();
+    }
+
+    #[test]
+    fn test_configure_pvh_reserve_flash() {
+        let gm = arch_mem(mib_to_bytes(128));
+        configure_pvh(&gm, GuestAddress(0), &None, true).unwrap();
+
+        let start_info: hvm_start_info = gm.read_obj(GuestAddress(layout::PVH_INFO_START)).unwrap();
+        assert_eq!(start_info.memmap_entries, 4);
+        let flash: hvm_memmap_table_entry = gm
+            .read_obj(GuestAddress(
+                layout::MEMMAP_START + 3 * std::mem::size_of::<hvm_memmap_table_entry>() as u64,
+            ))
+            .unwrap();
+        assert_eq!(flash.addr, layout::FLASH_START);
+        assert_eq!(flash.size, layout::FLASH_SIZE);
+        assert_eq!(flash.type_, E820_RESERVED);
+    }
+
+    #[test]
+    fn test_firmware_addresses() {
+        // The firmware ends at 4 GiB, with the variable store right below it.
+        let (code_addr, vars_addr) = firmware_addresses(0x20_0000, 0x8_0000).unwrap();
+        assert_eq!(code_addr, GuestAddress(0xffe0_0000));
+        assert_eq!(vars_addr, GuestAddress(0xffd8_0000));
+        assert!(vars_addr.0 >= layout::FLASH_START);
+        assert!(layout::RESET_VECTOR > code_addr.0);
+
+        assert!(firmware_addresses(layout::FLASH_SIZE, 0).is_some());
+        assert!(firmware_addresses(layout::FLASH_SIZE, 0x1000).is_none());
+        assert!(firmware_addresses(u64::MAX, 0x1000).is_none());
+    }
+
+    #[test]
+    fn test_load_bzimage() {
+        let gm = arch_mem(mib_to_bytes(16));
+        let kernel = [0xcc; 0x200];
+
+        let bzimage = make_bzimage(1, &kernel);
+        let entry_point = load_kernel(bzimage.as_file(), &gm).unwrap();
+        assert_eq!(
+            entry_point.entry_addr,
+            GuestAddress(get_kernel_start() + 0x200)
+        );
+        assert!(matches!(entry_point.protocol, BootProtocol::LinuxBoot));
+        let setup_header = entry_point.setup_header.unwrap();
+        assert_eq!({ setup_header.kernel_alignment }, 0x0020_0000);
+        let mut loaded = [0u8; 0x200];
+        gm.read_slice(&mut loaded, GuestAddress(get_kernel_start()))
+            .unwrap();
+        assert_eq!(loaded, kernel);
+
+        // Kernels without a 64-bit entry point can't be booted.
+        let bzimage = make_bzimage(0, &kernel);
+        assert!(matches!(
+            load_kernel(bzimage.as_file(), &gm),
+            Err(ConfigurationError::BzImageNot64Bit)
+        ));
+
+        // Neither an ELF nor a bzImage.
+        let file = TempFile::new().unwrap();
+        file.as_file().write_all(&[0u8; 0x400]).unwrap();
+        assert!(matches!(
+            load_kernel(file.as_file(), &gm),
+            Err(ConfigurationError::KernelLoader(_))
+        ));
+    }
+
+    #[test]
+    fn test_configure_64bit_boot_setup_header() {
+        let gm = arch_mem(mib_to_bytes(128));
+        let setup_header = setup_header {
+            boot_flag: 0xaa55,
+            header: 0x5372_6448,
+            version: 0x020f,
+            kernel_alignment: 0x0020_0000,
+            init_size: 0x0100_0000,
+            ..Default::default()
+        };
+
+        configure_64bit_boot(
+            &gm,
+            GuestAddress(CMDLINE_START),
+            10,
+            &None,
+            Some(setup_header),
+        )
+        .unwrap();
+
+        let params: boot_params = gm.read_obj(GuestAddress(layout::ZERO_PAGE_START)).unwrap();
+        // The fields describing the kernel come from the image.
+        assert_eq!({ params.hdr.version }, 0x020f);
+        assert_eq!({ params.hdr.kernel_alignment }, 0x0020_0000);
+        assert_eq!({ params.hdr.init_size }, 0x0100_0000);
+        // The fields describing the loader are set by Firecracker.
+        assert_eq!({ params.hdr.type_of_loader }, 0xff);
+        assert_eq!(
+            { params.hdr.cmd_line_ptr },
+            u32::try_from(CMDLINE_START).unwrap()
+        );
+        assert_eq!({ params.hdr.cmdline_size }, 10);
     }
 
     #[test]
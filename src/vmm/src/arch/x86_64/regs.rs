@@ -106,6 +106,13 @@ pub fn setup_regs(vcpu: &VcpuFd, entry_point: EntryPoint) -> Result<(), SetupReg
             rsi: super::layout::ZERO_PAGE_START,
             ..Default::default()
         },
+        BootProtocol::Firmware => kvm_regs {
+            // The firmware starts in real mode, at the reset vector, which is at the top of the
+            // code segment based 64 KiB below 4 GiB.
+            rflags: 0x0000_0000_0000_0002u64,
+            rip: 0xfff0,
+            ..Default::default()
+        },
     };
 
     vcpu.set_regs(&regs).map_err(SetupRegistersError)
@@ -144,13 +151,20 @@ pub fn setup_sregs(
     vcpu: &VcpuFd,
     boot_prot: BootProtocol,
 ) -> Result<(), SetupSpecialRegistersError> {
+    let boot_prot = match boot_prot {
+        BootProtocol::LinuxBoot => KernelBootProtocol::LinuxBoot,
+        BootProtocol::PvhBoot => KernelBootProtocol::PvhBoot,
+        // The firmware starts with the segments and special registers of the reset state.
+        BootProtocol::Firmware => return Ok(()),
+    };
+
     let mut sregs: kvm_sregs = vcpu
         .get_sregs()
         .map_err(SetupSpecialRegistersError::GetSpecialRegisters)?;
 
     configure_segments_and_sregs(mem, &mut sregs, boot_prot)
         .map_err(SetupSpecialRegistersError::ConfigureSegmentsAndSpecialRegisters)?;
-    if let BootProtocol::LinuxBoot = boot_prot {
+    if let KernelBootProtocol::LinuxBoot = boot_prot {
         setup_page_tables(mem, &mut sregs).map_err(SetupSpecialRegistersError::SetupPageTables)?;
         // TODO(dgreid) - Can this be done once per system instead?
     }
@@ -159,6 +173,14 @@ pub fn setup_sregs(
         .map_err(SetupSpecialRegistersError::SetSpecialRegisters)
 }
 
+/// The boot protocols starting the vCPU with the segments and special registers set up by
+/// Firecracker.
+#[derive(Debug, Clone, Copy)]
+enum KernelBootProtocol {
+    LinuxBoot,
+    PvhBoot,
+}
+
 const BOOT_GDT_OFFSET: u64 = 0x500;
 const BOOT_IDT_OFFSET: u64 = 0x520;
 
@@ -195,10 +217,10 @@ fn write_idt_value(val: u64, guest_mem: &GuestMemoryMmap) -> Result<(), RegsErro
 fn configure_segments_and_sregs(
     mem: &GuestMemoryMmap,
     sregs: &mut kvm_sregs,
-    boot_prot: BootProtocol,
+    boot_prot: KernelBootProtocol,
 ) -> Result<(), RegsError> {
     let gdt_table: [u64; BOOT_GDT_MAX] = match boot_prot {
-        BootProtocol::PvhBoot => {
+        KernelBootProtocol::PvhBoot => {
             // Configure GDT entries as specified by PVH boot protocol
             [
                 gdt_entry(0, 0, 0),                // NULL
@@ -207,7 +229,7 @@ fn configure_segments_and_sregs(
                 gdt_entry(0x008b, 0, 0x67),        // TSS
             ]
         }
-        BootProtocol::LinuxBoot => {
+        KernelBootProtocol::LinuxBoot => {
             // Configure GDT entries as specified by Linux 64bit boot protocol
             [
                 gdt_entry(0, 0, 0),            // NULL
@@ -240,11 +262,11 @@ fn configure_segments_and_sregs(
     sregs.tr = tss_seg;
 
     match boot_prot {
-        BootProtocol::PvhBoot => {
+        KernelBootProtocol::PvhBoot => {
             sregs.cr0 = X86_CR0_PE | X86_CR0_ET;
             sregs.cr4 = 0;
         }
-        BootProtocol::LinuxBoot => {
+        KernelBootProtocol::LinuxBoot => {
             // 64-bit protected mode
             sregs.cr0 |= X86_CR0_PE;
             sregs.efer |= EFER_LME | EFER_LMA;
@@ -388,6 +410,7 @@ mod tests {
         let entry_point: EntryPoint = EntryPoint {
             entry_addr: GuestAddress(expected_regs.rip),
             protocol: BootProtocol::LinuxBoot,
+            setup_header: None,
         };
 
         setup_regs(&vcpu, entry_point).unwrap();
@@ -421,6 +444,28 @@ mod tests {
             });
     }
 
+    #[test]
+    fn test_setup_firmware_regs() {
+        let kvm = Kvm::new().unwrap();
+        let vm = kvm.create_vm().unwrap();
+        let vcpu = vm.create_vcpu(0).unwrap();
+        let gm = single_region_mem(0x10000);
+
+        let entry_point = EntryPoint {
+            entry_addr: GuestAddress(super::super::layout::RESET_VECTOR),
+            protocol: BootProtocol::Firmware,
+            setup_header: None,
+        };
+        setup_regs(&vcpu, entry_point).unwrap();
+        setup_sregs(&gm, &vcpu, BootProtocol::Firmware).unwrap();
+
+        // The vCPU is in real mode, at the reset vector.
+        let regs = vcpu.get_regs().unwrap();
+        let sregs = vcpu.get_sregs().unwrap();
+        assert_eq!(sregs.cr0 & X86_CR0_PE, 0);
+        assert_eq!(sregs.cs.base + regs.rip, super::super::layout::RESET_VECTOR);
+    }
+
     #[test]
     fn test_write_gdt_table() {
         // Not enough memory for the gdt table to be written.
@@ -462,11 +507,11 @@ mod tests {
     fn test_configure_segments_and_sregs() {
         let mut sregs: kvm_sregs = Default::default();
         let gm = single_region_mem(0x10000);
-        configure_segments_and_sregs(&gm, &mut sregs, BootProtocol::LinuxBoot).unwrap();
+        configure_segments_and_sregs(&gm, &mut sregs, KernelBootProtocol::LinuxBoot).unwrap();
 
         validate_segments_and_sregs(&gm, &sregs, BootProtocol::LinuxBoot);
 
-        configure_segments_and_sregs(&gm, &mut sregs, BootProtocol::PvhBoot).unwrap();
+        configure_segments_and_sregs(&gm, &mut sregs, KernelBootProtocol::PvhBoot).unwrap();
 
         validate_segments_and_sregs(&gm, &sregs, BootProtocol::PvhBoot);
     }
@@ -211,6 +211,8 @@ impl KvmVcpu {
             vcpu_config.vcpu_count,
             // The number of bits needed to enumerate logical CPUs per core.
             u8::from(vcpu_config.vcpu_count > 1 && vcpu_config.smt),
+            // The number of sockets the logical CPUs are spread across.
+            vcpu_config.sockets,
         )?;
 
         // Set CPUID.
@@ -847,6 +849,7 @@ mod tests {
         Ok(VcpuConfig {
             vcpu_count: 1,
             smt: false,
+            sockets: 1,
             cpu_config,
         })
     }
@@ -862,6 +865,7 @@ mod tests {
                 EntryPoint {
                     entry_addr: GuestAddress(0),
                     protocol: BootProtocol::LinuxBoot,
+                    setup_header: None,
                 },
                 &vcpu_config,
             ),
@@ -879,6 +883,7 @@ mod tests {
                             EntryPoint {
                                 entry_addr: GuestAddress(crate::arch::get_kernel_start()),
                                 protocol: BootProtocol::LinuxBoot,
+                                setup_header: None,
                             },
                             &config,
                         )
@@ -999,6 +1004,7 @@ mod tests {
         let vcpu_config = VcpuConfig {
             vcpu_count: 1,
             smt: false,
+            sockets: 1,
             cpu_config: CpuConfiguration {
                 cpuid: Cpuid::try_from(kvm.supported_cpuid.clone()).unwrap(),
                 msrs: BTreeMap::new(),
@@ -1009,6 +1015,7 @@ mod tests {
             EntryPoint {
                 entry_addr: GuestAddress(0),
                 protocol: BootProtocol::LinuxBoot,
+                setup_header: None,
             },
             &vcpu_config,
         )
@@ -1068,6 +1075,7 @@ mod tests {
         let vcpu_config = VcpuConfig {
             vcpu_count: 1,
             smt: false,
+            sockets: 1,
             cpu_config: CpuConfiguration {
                 cpuid: Cpuid::try_from(kvm.supported_cpuid.clone()).unwrap(),
                 msrs: BTreeMap::new(),
@@ -1079,6 +1087,7 @@ mod tests {
             EntryPoint {
                 entry_addr: GuestAddress(0),
                 protocol: BootProtocol::LinuxBoot,
+                setup_header: None,
             },
             &vcpu_config,
         )
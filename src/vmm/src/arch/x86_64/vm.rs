@@ -44,6 +44,8 @@ pub enum ArchVmError {
     GetMsrsToSave(MsrError),
     /// Failed during KVM_SET_TSS_ADDRESS: {0}
     SetTssAddress(kvm_ioctls::Error),
+    /// Failed during KVM_SET_IDENTITY_MAP_ADDR: {0}
+    SetIdentityMapAddress(kvm_ioctls::Error),
 }
 
 /// Structure representing the current architecture's understand of what a "virtual machine" is.
@@ -89,6 +91,11 @@ impl ArchVm {
             .fd
             .set_tss_address(u64_to_usize(crate::arch::x86_64::layout::KVM_TSS_ADDRESS))
             .map_err(ArchVmError::SetTssAddress)?;
+        // The default address of the identity map page is in the flash region.
+        common
+            .fd
+            .set_identity_map_address(crate::arch::x86_64::layout::KVM_IDENTITY_MAP_ADDRESS)
+            .map_err(ArchVmError::SetIdentityMapAddress)?;
 
         Ok(ArchVm {
             common,
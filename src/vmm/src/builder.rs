@@ -31,6 +31,8 @@ use crate::device_manager::acpi::ACPIDeviceManager;
 #[cfg(target_arch = "x86_64")]
 use crate::device_manager::legacy::PortIODeviceManager;
 use crate::device_manager::mmio::{MMIODeviceManager, MmioError};
+#[cfg(target_arch = "x86_64")]
+use crate::device_manager::pci::PciDeviceManager;
 use crate::device_manager::persist::{
     ACPIDeviceManagerConstructorArgs, ACPIDeviceManagerRestoreError, MMIODevManagerConstructorArgs,
 };
@@ -40,28 +42,44 @@ use crate::devices::acpi::vmgenid::{VmGenId, VmGenIdError};
 #[cfg(target_arch = "aarch64")]
 use crate::devices::legacy::RTCDevice;
 use crate::devices::legacy::serial::SerialOut;
-use crate::devices::legacy::{EventFdTrigger, SerialEventsWrapper, SerialWrapper};
+#[cfg(target_arch = "x86_64")]
+use crate::devices::legacy::watchdog::{WatchdogDevice, WatchdogState};
+use crate::devices::legacy::{EventFdTrigger, PvPanicDevice, SerialEventsWrapper, SerialWrapper};
 use crate::devices::virtio::balloon::Balloon;
 use crate::devices::virtio::block::device::Block;
+use crate::devices::virtio::console::VirtioConsole;
 use crate::devices::virtio::device::VirtioDevice;
+use crate::devices::virtio::fs::VhostUserFs;
 use crate::devices::virtio::mmio::MmioTransport;
 use crate::devices::virtio::net::Net;
+use crate::devices::virtio::pmem::{PMEM_ALIGNMENT, Pmem};
 use crate::devices::virtio::rng::Entropy;
 use crate::devices::virtio::vsock::{Vsock, VsockUnixBackend};
+use crate::firmware::{Firmware, FirmwareError};
 #[cfg(feature = "gdb")]
 use crate::gdb;
 use crate::initrd::{InitrdConfig, InitrdError};
 use crate::logger::{debug, error};
+#[cfg(target_arch = "x86_64")]
+use crate::persist::VmInfo;
 use crate::persist::{MicrovmState, MicrovmStateError};
 use crate::resources::VmResources;
 use crate::seccomp::BpfThreadMap;
 use crate::snapshot::Persist;
+use crate::utils::align_up;
+use crate::vmm_config::boot_source::BootImage;
 use crate::vmm_config::instance_info::InstanceInfo;
 use crate::vmm_config::machine_config::MachineConfigError;
+#[cfg(target_arch = "x86_64")]
+use crate::vmm_config::machine_config::VirtioTransport;
+use crate::vmm_config::pvpanic::{PanicAction, PvPanicConfig};
+#[cfg(target_arch = "x86_64")]
+use crate::vmm_config::watchdog::WatchdogConfig;
 use crate::vstate::kvm::Kvm;
 use crate::vstate::memory::GuestRegionMmap;
+use crate::vstate::numa::place_numa_nodes;
 use crate::vstate::vcpu::{Vcpu, VcpuError};
-use crate::vstate::vm::Vm;
+use crate::vstate::vm::{Vm, VmError};
 use crate::{EventManager, Vmm, VmmError, device_manager};
 
 /// Errors associated with starting the instance.
@@ -89,6 +107,8 @@ pub enum StartMicrovmError {
     EnablePVTime(crate::arch::VcpuArchError),
     /// Invalid Memory Configuration: {0}
     GuestMemory(crate::vstate::memory::MemoryError),
+    /// Cannot map the firmware in the guest: {0}
+    Firmware(#[from] FirmwareError),
     /// Error with initrd initialization: {0}.
     Initrd(#[from] InitrdError),
     /// Internal error while starting microVM: {0}
@@ -105,18 +125,28 @@ pub enum StartMicrovmError {
     MissingMemSizeConfig,
     /// No seccomp filter for thread category: {0}
     MissingSeccompFilters(String),
+    /// Cannot place the NUMA nodes on the host: {0}
+    NumaPlacement(crate::vstate::numa::NumaError),
     /// The net device configuration is missing the tap device.
     NetDeviceNotConfigured,
     /// Cannot open the block device backing file: {0}
     OpenBlockDevice(io::Error),
+    /// Cannot map the memory of a pmem device in the guest: {0}
+    RegisterPmemMemory(crate::vstate::vm::VmError),
     /// Cannot initialize a MMIO Device or add a device to the MMIO Bus or cmdline: {0}
     RegisterMmioDevice(#[from] device_manager::mmio::MmioError),
+    /// Cannot initialize the PCI root complex or plug a device into it: {0}
+    #[cfg(target_arch = "x86_64")]
+    RegisterPciDevice(#[from] device_manager::pci::PciError),
     /// Cannot restore microvm state: {0}
     RestoreMicrovmState(MicrovmStateError),
     /// Cannot set vm resources: {0}
     SetVmResources(MachineConfigError),
     /// Cannot create the entropy device: {0}
     CreateEntropyDevice(crate::devices::virtio::rng::EntropyError),
+    /// Cannot create the watchdog device: {0}
+    #[cfg(target_arch = "x86_64")]
+    CreateWatchdogDevice(io::Error),
     /// Failed to allocate guest resource: {0}
     AllocateResources(#[from] vm_allocator::Error),
     /// Error starting GDB debug session
@@ -156,6 +186,8 @@ fn create_vmm_and_vcpus(
     let acpi_device_manager = ACPIDeviceManager::new();
 
     let (vcpus, vcpus_exit_evt) = vm.create_vcpus(vcpu_count)?;
+    let pvpanic_evt = EventFd::new(libc::EFD_NONBLOCK).map_err(VmmError::EventFd)?;
+    let watchdog_evt = EventFd::new(libc::EFD_NONBLOCK).map_err(VmmError::EventFd)?;
 
     #[cfg(target_arch = "x86_64")]
     let pio_device_manager = {
@@ -186,11 +218,20 @@ fn create_vmm_and_vcpus(
         uffd: None,
         vcpus_handles: Vec::new(),
         vcpus_exit_evt,
+        pvpanic_evt,
+        pvpanic_action: PanicAction::default(),
+        watchdog_evt,
+        watchdog: None,
         resource_allocator,
         mmio_device_manager,
         #[cfg(target_arch = "x86_64")]
         pio_device_manager,
         acpi_device_manager,
+        #[cfg(target_arch = "x86_64")]
+        pci_device_manager: None,
+        firmware: None,
+        #[cfg(feature = "gdb")]
+        debug_resources: None,
     };
 
     Ok((vmm, vcpus))
@@ -241,21 +282,43 @@ pub fn build_microvm_for_boot(
     vmm.vm
         .register_memory_regions(guest_memory)
         .map_err(VmmError::Vm)?;
+    place_numa_nodes(
+        vmm.vm.guest_memory(),
+        &mut vcpus,
+        &vm_resources.machine_config.numa,
+    )
+    .map_err(NumaPlacement)?;
+
+    #[cfg(target_arch = "x86_64")]
+    if vm_resources.machine_config.virtio_transport == VirtioTransport::Pci {
+        vmm.pci_device_manager = Some(PciDeviceManager::new(
+            &vmm.kvm,
+            vmm.vm.fd(),
+            &mut vmm.resource_allocator,
+            &mut vmm.mmio_device_manager,
+        )?);
+        boot_cmdline = enable_pci_in_cmdline(&boot_cmdline)?;
+    }
 
-    let entry_point = load_kernel(&boot_config.kernel_file, vmm.vm.guest_memory())?;
+    let entry_point = match &boot_config.image {
+        BootImage::Kernel(kernel_file) => load_kernel(kernel_file, vmm.vm.guest_memory())?,
+        BootImage::Firmware { code, vars } => {
+            let firmware = Firmware::new(
+                code,
+                vars.as_ref(),
+                &mut vmm.vm,
+                &mut vmm.resource_allocator,
+                &mut vmm.mmio_device_manager,
+            )?;
+            let entry_point = firmware.entry_point();
+            vmm.firmware = Some(firmware);
+            entry_point
+        }
+    };
     let initrd = InitrdConfig::from_config(boot_config, vmm.vm.guest_memory())?;
 
     #[cfg(feature = "gdb")]
-    let (gdb_tx, gdb_rx) = mpsc::channel();
-    #[cfg(feature = "gdb")]
-    vcpus
-        .iter_mut()
-        .for_each(|vcpu| vcpu.attach_debug_info(gdb_tx.clone()));
-    #[cfg(feature = "gdb")]
-    let vcpu_fds = vcpus
-        .iter()
-        .map(|vcpu| vcpu.copy_kvm_vcpu_fd(vmm.vm()))
-        .collect::<Result<Vec<_>, _>>()?;
+    let debug_resources = attach_debug_channel(&vmm, &mut vcpus)?;
 
     // The boot timer device needs to be the first device attached in order
     // to maintain the same MMIO address referenced in the documentation
@@ -265,6 +328,10 @@ pub fn build_microvm_for_boot(
     }
 
     if let Some(balloon) = vm_resources.balloon.get() {
+        balloon
+            .lock()
+            .expect("Poisoned lock")
+            .set_transparent_huge_pages(vm_resources.machine_config.huge_pages.is_transparent());
         attach_balloon_device(&mut vmm, &mut boot_cmdline, balloon, event_manager)?;
     }
 
@@ -289,9 +356,42 @@ pub fn build_microvm_for_boot(
         attach_entropy_device(&mut vmm, &mut boot_cmdline, entropy, event_manager)?;
     }
 
+    attach_fs_devices(
+        &mut vmm,
+        &mut boot_cmdline,
+        vm_resources.fs.iter(),
+        event_manager,
+    )?;
+
+    if let Some(console) = vm_resources.console.get() {
+        attach_console_device(&mut vmm, &mut boot_cmdline, console, event_manager)?;
+    }
+
+    attach_pmem_devices(
+        &mut vmm,
+        &mut boot_cmdline,
+        vm_resources.pmem.iter(),
+        event_manager,
+    )?;
+
     #[cfg(target_arch = "aarch64")]
     attach_legacy_devices_aarch64(event_manager, &mut vmm, &mut boot_cmdline)?;
 
+    if let Some(pvpanic) = &vm_resources.pvpanic {
+        attach_pvpanic_device(&mut vmm, pvpanic)?;
+    }
+
+    #[cfg(target_arch = "x86_64")]
+    if let Some(watchdog) = &vm_resources.watchdog {
+        attach_watchdog_device(
+            &mut vmm,
+            event_manager,
+            watchdog,
+            VmInfo::from(vm_resources),
+            None,
+        )?;
+    }
+
     attach_vmgenid_device(&mut vmm)?;
 
     #[cfg(target_arch = "aarch64")]
@@ -317,14 +417,14 @@ pub fn build_microvm_for_boot(
     if let Some(gdb_socket_path) = &vm_resources.machine_config.gdb_socket_path {
         gdb::gdb_thread(
             vmm.clone(),
-            vcpu_fds,
-            gdb_rx,
+            debug_resources,
             entry_point.entry_addr,
             gdb_socket_path,
         )
         .map_err(GdbServer)?;
     } else {
         debug!("No GDB socket provided not starting gdb server.");
+        vmm.lock().unwrap().debug_resources = Some(debug_resources);
     }
 
     // Move vcpus to their own threads and start their state machine in the 'Paused' state.
@@ -399,6 +499,8 @@ pub enum BuildMicrovmFromSnapshotError {
     VmUpdateConfig(#[from] MachineConfigError),
     /// Failed to restore MMIO device: {0}
     RestoreMmioDevice(#[from] MicrovmStateError),
+    /// Failed to map the memory of a pmem device in the guest: {0}
+    RegisterPmemMemory(crate::vstate::vm::VmError),
     /// Failed to emulate MMIO serial: {0}
     EmulateSerialInit(#[from] crate::EmulateSerialInitError),
     /// Failed to start vCPUs as no vCPU seccomp filter found.
@@ -446,6 +548,12 @@ pub fn build_microvm_from_snapshot(
         .map_err(VmmError::Vm)
         .map_err(StartMicrovmError::Internal)?;
     vmm.uffd = uffd;
+    place_numa_nodes(
+        vmm.vm.guest_memory(),
+        &mut vcpus,
+        &vm_resources.machine_config.numa,
+    )
+    .map_err(StartMicrovmError::NumaPlacement)?;
 
     #[cfg(target_arch = "x86_64")]
     {
@@ -492,6 +600,8 @@ pub fn build_microvm_from_snapshot(
 
     // Restore the boot source config paths.
     vm_resources.boot_source.config = microvm_state.vm_info.boot_source;
+    vm_resources.pvpanic = microvm_state.vm_info.pvpanic;
+    vm_resources.watchdog = microvm_state.vm_info.watchdog;
 
     // Restore devices states.
     let mmio_ctor_args = MMIODevManagerConstructorArgs {
@@ -502,11 +612,38 @@ pub fn build_microvm_from_snapshot(
         vm_resources,
         instance_id: &instance_info.id,
         restored_from_file: vmm.uffd.is_none(),
+        #[cfg(target_arch = "aarch64")]
+        pvpanic_evt: &vmm.pvpanic_evt,
     };
 
     vmm.mmio_device_manager =
         MMIODeviceManager::restore(mmio_ctor_args, &microvm_state.device_states)
             .map_err(MicrovmStateError::RestoreDevices)?;
+    if let Some(pvpanic) = &vm_resources.pvpanic {
+        #[cfg(target_arch = "x86_64")]
+        attach_pvpanic_device(&mut vmm, pvpanic)?;
+        // On aarch64, the device is restored along with the other MMIO legacy devices.
+        #[cfg(target_arch = "aarch64")]
+        {
+            vmm.pvpanic_action = pvpanic.on_panic;
+        }
+    }
+    #[cfg(target_arch = "x86_64")]
+    if let Some(watchdog) = &vm_resources.watchdog {
+        attach_watchdog_device(
+            &mut vmm,
+            event_manager,
+            watchdog,
+            VmInfo::from(&*vm_resources),
+            microvm_state.watchdog_state.as_ref(),
+        )?;
+    }
+    // The pmem mappings are not part of the guest memory, so they are mapped again at the
+    // addresses saved in the snapshot.
+    for pmem in vm_resources.pmem.iter() {
+        register_pmem_memory(&mut vmm.vm, &pmem.lock().expect("Poisoned lock"))
+            .map_err(BuildMicrovmFromSnapshotError::RegisterPmemMemory)?;
+    }
     vmm.emulate_serial_init()?;
 
     {
@@ -527,6 +664,14 @@ pub fn build_microvm_from_snapshot(
             .map_err(BuildMicrovmFromSnapshotError::VMGenIDUpdate)?;
     }
 
+    // Allow a debugger to be attached to the restored microVM.
+    #[cfg(feature = "gdb")]
+    {
+        vmm.debug_resources = Some(
+            attach_debug_channel(&vmm, &mut vcpus).map_err(StartMicrovmError::VcpuFdCloneError)?,
+        );
+    }
+
     // Move vcpus to their own threads and start their state machine in the 'Paused' state.
     vmm.start_vcpus(
         vcpus,
@@ -551,6 +696,28 @@ pub fn build_microvm_from_snapshot(
     Ok(vmm)
 }
 
+/// Connects the Vcpus to a new GDB event channel and returns what a GDB session needs to drive
+/// them.
+#[cfg(feature = "gdb")]
+fn attach_debug_channel(
+    vmm: &Vmm,
+    vcpus: &mut [Vcpu],
+) -> Result<gdb::DebugResources, crate::vstate::vcpu::CopyKvmFdError> {
+    let (gdb_tx, gdb_rx) = mpsc::channel();
+    vcpus
+        .iter_mut()
+        .for_each(|vcpu| vcpu.attach_debug_info(gdb_tx.clone()));
+    let vcpu_fds = vcpus
+        .iter()
+        .map(|vcpu| vcpu.copy_kvm_vcpu_fd(vmm.vm()))
+        .collect::<Result<Vec<_>, _>>()?;
+
+    Ok(gdb::DebugResources {
+        vcpu_fds,
+        gdb_event_receiver: gdb_rx,
+    })
+}
+
 /// Sets up the serial device.
 pub fn setup_serial_device(
     event_manager: &mut EventManager,
@@ -646,6 +813,30 @@ fn attach_legacy_devices_aarch64(
         .map_err(VmmError::RegisterMMIODevice)
 }
 
+/// Removes the `pci=off` parameter from the kernel command line, as it would prevent the guest
+/// from enumerating the virtio-pci devices.
+#[cfg(target_arch = "x86_64")]
+fn enable_pci_in_cmdline(
+    cmdline: &LoaderKernelCmdline,
+) -> Result<LoaderKernelCmdline, StartMicrovmError> {
+    let cmdline_cstring = cmdline.as_cstring()?;
+    let cmdline_str = cmdline_cstring
+        .to_str()
+        .map_err(|err| StartMicrovmError::KernelCmdline(err.to_string()))?;
+    if !cmdline_str.split_whitespace().any(|arg| arg == "pci=off") {
+        return Ok(cmdline.clone());
+    }
+
+    log::warn!("Removing `pci=off` from the kernel command line to use the virtio-pci transport");
+    let filtered = cmdline_str
+        .split_whitespace()
+        .filter(|arg| *arg != "pci=off")
+        .collect::<Vec<_>>()
+        .join(" ");
+    let cmdline = LoaderKernelCmdline::try_from(&filtered, crate::arch::CMDLINE_MAX_SIZE)?;
+    Ok(cmdline)
+}
+
 /// Attaches a VirtioDevice device to the device manager and event manager.
 fn attach_virtio_device<T: 'static + VirtioDevice + MutEventSubscriber + Debug>(
     event_manager: &mut EventManager,
@@ -654,20 +845,32 @@ fn attach_virtio_device<T: 'static + VirtioDevice + MutEventSubscriber + Debug>(
     device: Arc<Mutex<T>>,
     cmdline: &mut LoaderKernelCmdline,
     is_vhost_user: bool,
-) -> Result<(), MmioError> {
+) -> Result<(), StartMicrovmError> {
     event_manager.add_subscriber(device.clone());
 
-    // The device mutex mustn't be locked here otherwise it will deadlock.
-    let device = MmioTransport::new(vmm.vm.guest_memory().clone(), device, is_vhost_user);
-    vmm.mmio_device_manager
-        .register_mmio_virtio_for_boot(
+    #[cfg(target_arch = "x86_64")]
+    if let Some(pci_device_manager) = vmm.pci_device_manager.as_mut() {
+        pci_device_manager.register_virtio_device(
             vmm.vm.fd(),
+            vmm.vm.guest_memory().clone(),
             &mut vmm.resource_allocator,
+            &mut vmm.mmio_device_manager,
             id,
             device,
-            cmdline,
-        )
-        .map(|_| ())
+        )?;
+        return Ok(());
+    }
+
+    // The device mutex mustn't be locked here otherwise it will deadlock.
+    let device = MmioTransport::new(vmm.vm.guest_memory().clone(), device, is_vhost_user);
+    vmm.mmio_device_manager.register_mmio_virtio_for_boot(
+        vmm.vm.fd(),
+        &mut vmm.resource_allocator,
+        id,
+        device,
+        cmdline,
+    )?;
+    Ok(())
 }
 
 pub(crate) fn attach_boot_timer_device(
@@ -693,12 +896,54 @@ fn attach_vmgenid_device(vmm: &mut Vmm) -> Result<(), StartMicrovmError> {
     Ok(())
 }
 
+fn attach_pvpanic_device(vmm: &mut Vmm, config: &PvPanicConfig) -> Result<(), StartMicrovmError> {
+    vmm.pvpanic_action = config.on_panic;
+    let pvpanic = PvPanicDevice::new(vmm.pvpanic_evt.try_clone().map_err(VmmError::EventFd)?);
+
+    #[cfg(target_arch = "x86_64")]
+    vmm.pio_device_manager
+        .register_pvpanic(pvpanic)
+        .map_err(StartMicrovmError::CreateLegacyDevice)?;
+    #[cfg(target_arch = "aarch64")]
+    vmm.mmio_device_manager
+        .register_mmio_pvpanic(&mut vmm.resource_allocator, pvpanic, None)?;
+    Ok(())
+}
+
+/// Attaches the watchdog device, restoring it from `state` if given.
+///
+/// `vm_info` is saved in the snapshot taken when the watchdog expires, if the configured action
+/// takes one.
+#[cfg(target_arch = "x86_64")]
+fn attach_watchdog_device(
+    vmm: &mut Vmm,
+    event_manager: &mut EventManager,
+    config: &WatchdogConfig,
+    vm_info: VmInfo,
+    state: Option<&WatchdogState>,
+) -> Result<(), StartMicrovmError> {
+    let expiry_evt = vmm.watchdog_evt.try_clone().map_err(VmmError::EventFd)?;
+    let watchdog = match state {
+        Some(state) => WatchdogDevice::restore(expiry_evt, state),
+        None => WatchdogDevice::new(expiry_evt),
+    }
+    .map_err(StartMicrovmError::CreateWatchdogDevice)?;
+
+    let watchdog = Arc::new(Mutex::new(BusDevice::Watchdog(watchdog)));
+    vmm.pio_device_manager
+        .register_watchdog(watchdog.clone())
+        .map_err(StartMicrovmError::CreateLegacyDevice)?;
+    event_manager.add_subscriber(watchdog);
+    vmm.watchdog = Some((config.clone(), vm_info));
+    Ok(())
+}
+
 fn attach_entropy_device(
     vmm: &mut Vmm,
     cmdline: &mut LoaderKernelCmdline,
     entropy_device: &Arc<Mutex<Entropy>>,
     event_manager: &mut EventManager,
-) -> Result<(), MmioError> {
+) -> Result<(), StartMicrovmError> {
     let id = entropy_device
         .lock()
         .expect("Poisoned lock")
@@ -715,6 +960,55 @@ fn attach_entropy_device(
     )
 }
 
+fn attach_console_device(
+    vmm: &mut Vmm,
+    cmdline: &mut LoaderKernelCmdline,
+    console: &Arc<Mutex<VirtioConsole>>,
+    event_manager: &mut EventManager,
+) -> Result<(), StartMicrovmError> {
+    let id = console.lock().expect("Poisoned lock").id().to_string();
+    // The device mutex mustn't be locked here otherwise it will deadlock.
+    attach_virtio_device(event_manager, vmm, id, console.clone(), cmdline, false)
+}
+
+fn register_pmem_memory(vm: &mut Vm, pmem: &Pmem) -> Result<(), VmError> {
+    vm.register_device_memory(pmem.guest_address(), pmem.mapping(), pmem.read_only())
+}
+
+fn attach_pmem_devices<'a, I: Iterator<Item = &'a Arc<Mutex<Pmem>>> + Debug>(
+    vmm: &mut Vmm,
+    cmdline: &mut LoaderKernelCmdline,
+    pmem_devices: I,
+    event_manager: &mut EventManager,
+) -> Result<(), StartMicrovmError> {
+    // The pmem devices are mapped one after the other, right after the guest memory.
+    let mut next_address = align_up(
+        crate::arch::device_memory_start(vmm.vm.guest_memory()),
+        PMEM_ALIGNMENT,
+    );
+    for pmem in pmem_devices {
+        let id = {
+            let mut locked = pmem.lock().expect("Poisoned lock");
+            // The root device is always the first one, so the guest names it /dev/pmem0.
+            if locked.root_device() {
+                cmdline.insert_str("root=/dev/pmem0")?;
+                match locked.read_only() {
+                    true => cmdline.insert_str("ro")?,
+                    false => cmdline.insert_str("rw")?,
+                }
+            }
+            locked.set_guest_address(GuestAddress(next_address));
+            register_pmem_memory(&mut vmm.vm, &locked)
+                .map_err(StartMicrovmError::RegisterPmemMemory)?;
+            next_address += align_up(locked.size(), PMEM_ALIGNMENT);
+            locked.id().to_string()
+        };
+        // The device mutex mustn't be locked here otherwise it will deadlock.
+        attach_virtio_device(event_manager, vmm, id, pmem.clone(), cmdline, false)?;
+    }
+    Ok(())
+}
+
 fn attach_block_devices<'a, I: Iterator<Item = &'a Arc<Mutex<Block>>> + Debug>(
     vmm: &mut Vmm,
     cmdline: &mut LoaderKernelCmdline,
@@ -749,6 +1043,20 @@ fn attach_block_devices<'a, I: Iterator<Item = &'a Arc<Mutex<Block>>> + Debug>(
     Ok(())
 }
 
+fn attach_fs_devices<'a, I: Iterator<Item = &'a Arc<Mutex<VhostUserFs>>> + Debug>(
+    vmm: &mut Vmm,
+    cmdline: &mut LoaderKernelCmdline,
+    fs_devices: I,
+    event_manager: &mut EventManager,
+) -> Result<(), StartMicrovmError> {
+    for fs in fs_devices {
+        let id = fs.lock().expect("Poisoned lock").id().to_string();
+        // The device mutex mustn't be locked here otherwise it will deadlock.
+        attach_virtio_device(event_manager, vmm, id, fs.clone(), cmdline, true)?;
+    }
+    Ok(())
+}
+
 fn attach_net_devices<'a, I: Iterator<Item = &'a Arc<Mutex<Net>>> + Debug>(
     vmm: &mut Vmm,
     cmdline: &mut LoaderKernelCmdline,
@@ -768,7 +1076,7 @@ fn attach_unixsock_vsock_device(
     cmdline: &mut LoaderKernelCmdline,
     unix_vsock: &Arc<Mutex<Vsock<VsockUnixBackend>>>,
     event_manager: &mut EventManager,
-) -> Result<(), MmioError> {
+) -> Result<(), StartMicrovmError> {
     let id = String::from(unix_vsock.lock().expect("Poisoned lock").id());
     // The device mutex mustn't be locked here otherwise it will deadlock.
     attach_virtio_device(event_manager, vmm, id, unix_vsock.clone(), cmdline, false)
@@ -779,7 +1087,7 @@ fn attach_balloon_device(
     cmdline: &mut LoaderKernelCmdline,
     balloon: &Arc<Mutex<Balloon>>,
     event_manager: &mut EventManager,
-) -> Result<(), MmioError> {
+) -> Result<(), StartMicrovmError> {
     let id = String::from(balloon.lock().expect("Poisoned lock").id());
     // The device mutex mustn't be locked here otherwise it will deadlock.
     attach_virtio_device(event_manager, vmm, id, balloon.clone(), cmdline, false)
@@ -809,19 +1117,25 @@ pub(crate) mod tests {
     use crate::arch::DeviceType;
     use crate::device_manager::resources::ResourceAllocator;
     use crate::devices::virtio::block::CacheType;
+    use crate::devices::virtio::console::CONSOLE_DEV_ID;
+    use crate::devices::virtio::pmem::device::tests::default_pmem_config;
     use crate::devices::virtio::rng::device::ENTROPY_DEV_ID;
     use crate::devices::virtio::vsock::{TYPE_VSOCK, VSOCK_DEV_ID};
-    use crate::devices::virtio::{TYPE_BALLOON, TYPE_BLOCK, TYPE_RNG};
+    use crate::devices::virtio::{TYPE_BALLOON, TYPE_BLOCK, TYPE_CONSOLE, TYPE_PMEM, TYPE_RNG};
     use crate::mmds::data_store::{Mmds, MmdsVersion};
     use crate::mmds::ns::MmdsNetworkStack;
     use crate::utils::mib_to_bytes;
     use crate::vmm_config::balloon::{BALLOON_DEV_ID, BalloonBuilder, BalloonDeviceConfig};
     use crate::vmm_config::boot_source::DEFAULT_KERNEL_CMDLINE;
+    use crate::vmm_config::console::{ConsoleBuilder, ConsolePortConfig};
     use crate::vmm_config::drive::{BlockBuilder, BlockDeviceConfig};
     use crate::vmm_config::entropy::{EntropyDeviceBuilder, EntropyDeviceConfig};
     use crate::vmm_config::net::{NetBuilder, NetworkInterfaceConfig};
+    use crate::vmm_config::pmem::PmemBuilder;
     use crate::vmm_config::vsock::tests::default_config;
     use crate::vmm_config::vsock::{VsockBuilder, VsockDeviceConfig};
+    #[cfg(target_arch = "x86_64")]
+    use crate::vmm_config::watchdog::WatchdogAction;
     use crate::vstate::vm::tests::setup_vm_with_memory;
 
     #[derive(Debug)]
@@ -909,11 +1223,20 @@ pub(crate) mod tests {
             uffd: None,
             vcpus_handles: Vec::new(),
             vcpus_exit_evt,
+            pvpanic_evt: EventFd::new(libc::EFD_NONBLOCK).unwrap(),
+            pvpanic_action: PanicAction::default(),
+            watchdog_evt: EventFd::new(libc::EFD_NONBLOCK).unwrap(),
+            watchdog: None,
             resource_allocator: ResourceAllocator::new().unwrap(),
             mmio_device_manager,
             #[cfg(target_arch = "x86_64")]
             pio_device_manager,
             acpi_device_manager,
+            #[cfg(target_arch = "x86_64")]
+            pci_device_manager: None,
+            firmware: None,
+            #[cfg(feature = "gdb")]
+            debug_resources: None,
         }
     }
 
@@ -1323,6 +1646,126 @@ pub(crate) mod tests {
         ));
     }
 
+    #[test]
+    fn test_attach_console_device() {
+        let mut event_manager = EventManager::new().expect("Unable to create EventManager");
+        let mut vmm = default_vmm();
+        let port_file = TempFile::new().unwrap();
+
+        let mut builder = ConsoleBuilder::new();
+        builder
+            .insert(ConsolePortConfig {
+                port_id: String::from("port0"),
+                name: String::from("agent"),
+                socket_path: None,
+                file_path: Some(port_file.as_path().to_str().unwrap().to_string()),
+            })
+            .unwrap();
+
+        let mut cmdline = default_kernel_cmdline();
+        attach_console_device(
+            &mut vmm,
+            &mut cmdline,
+            builder.get().unwrap(),
+            &mut event_manager,
+        )
+        .unwrap();
+        assert!(
+            vmm.mmio_device_manager
+                .get_device(DeviceType::Virtio(TYPE_CONSOLE), CONSOLE_DEV_ID)
+                .is_some()
+        );
+        // Check if the virtio-console device is described in kernel_cmdline.
+        #[cfg(target_arch = "x86_64")]
+        assert!(cmdline_contains(
+            &cmdline,
+            "virtio_mmio.device=4K@0xd0000000:5"
+        ));
+    }
+
+    #[test]
+    fn test_attach_pvpanic_device() {
+        let mut vmm = default_vmm();
+        let config = PvPanicConfig {
+            on_panic: PanicAction::Pause,
+        };
+
+        attach_pvpanic_device(&mut vmm, &config).unwrap();
+        assert_eq!(vmm.pvpanic_action, PanicAction::Pause);
+        #[cfg(target_arch = "x86_64")]
+        assert!(vmm.pio_device_manager.pvpanic.is_some());
+        #[cfg(target_arch = "aarch64")]
+        assert!(
+            vmm.mmio_device_manager
+                .get_device(DeviceType::PvPanic, &DeviceType::PvPanic.to_string())
+                .is_some()
+        );
+    }
+
+    #[test]
+    #[cfg(target_arch = "x86_64")]
+    fn test_attach_watchdog_device() {
+        let mut event_manager = EventManager::new().expect("Unable to create EventManager");
+        let mut vmm = default_vmm();
+        let config = WatchdogConfig {
+            action: WatchdogAction::Pause,
+            ..Default::default()
+        };
+        let state = WatchdogState {
+            running: true,
+            countdown: 10,
+        };
+
+        attach_watchdog_device(
+            &mut vmm,
+            &mut event_manager,
+            &config,
+            VmInfo::default(),
+            Some(&state),
+        )
+        .unwrap();
+        assert_eq!(vmm.watchdog.as_ref().unwrap().0, config);
+        assert_eq!(vmm.pio_device_manager.save_watchdog(), Some(state));
+    }
+
+    #[test]
+    fn test_attach_pmem_devices() {
+        let mut event_manager = EventManager::new().expect("Unable to create EventManager");
+        let mut vmm = default_vmm();
+        let files = [TempFile::new().unwrap(), TempFile::new().unwrap()];
+
+        let mut builder = PmemBuilder::new();
+        builder
+            .build(default_pmem_config("data", &files[0], false))
+            .unwrap();
+        let mut root = default_pmem_config("root", &files[1], true);
+        root.root_device = true;
+        builder.build(root).unwrap();
+
+        let mut cmdline = default_kernel_cmdline();
+        attach_pmem_devices(&mut vmm, &mut cmdline, builder.iter(), &mut event_manager).unwrap();
+        assert!(cmdline_contains(&cmdline, "root=/dev/pmem0 ro"));
+
+        // The devices are mapped one after the other, past the guest memory.
+        let start = align_up(
+            crate::arch::device_memory_start(vmm.vm.guest_memory()),
+            PMEM_ALIGNMENT,
+        );
+        for (index, id) in ["root", "data"].into_iter().enumerate() {
+            assert!(
+                vmm.mmio_device_manager
+                    .get_device(DeviceType::Virtio(TYPE_PMEM), id)
+                    .is_some()
+            );
+            let pmem = builder.devices[index].lock().unwrap();
+            assert_eq!(pmem.id(), id);
+            assert_eq!(
+                pmem.guest_address(),
+                GuestAddress(start + index as u64 * PMEM_ALIGNMENT)
+            );
+        }
+    }
+
     #[test]
     fn test_attach_vsock_device() {
         let mut event_manager = EventManager::new().expect("Unable to create EventManager");
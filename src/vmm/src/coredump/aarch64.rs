@@ -0,0 +1,90 @@
+// Copyright 2025 Amazon.com, Inc. or its affiliates. All Rights Reserved.
+// SPDX-License-Identifier: Apache-2.0
+
+use std::mem::offset_of;
+
+use kvm_bindings::{KVM_REG_SIZE_U64, kvm_regs, user_pt_regs};
+
+use crate::arch::aarch64::regs::{PC, arm64_core_reg_id};
+use crate::vstate::vcpu::VcpuState;
+
+/// `EM_AARCH64`, the ELF machine of the core dump.
+pub const ELF_MACHINE: u16 = 183;
+
+/// Mask of the exception level and stack pointer selection bits of PSTATE.
+const PSR_MODE_MASK: u64 = 0xf;
+/// PSTATE mode of the guest kernel: EL1, using SP_EL1.
+const PSR_MODE_EL1H: u64 = 0x5;
+
+/// Returns the registers of the `NT_PRSTATUS` note of a vCPU, as laid out by the aarch64
+/// `struct user_pt_regs`.
+pub fn prstatus_regs(state: &VcpuState) -> Vec<u64> {
+    let value = |offset: usize| {
+        let id = arm64_core_reg_id!(KVM_REG_SIZE_U64, offset_of!(kvm_regs, regs) + offset);
+        reg_value(state, id)
+    };
+
+    let mut regs: Vec<u64> = (0..31)
+        .map(|index| value(offset_of!(user_pt_regs, regs) + index * 8))
+        .collect();
+    let pstate = value(offset_of!(user_pt_regs, pstate));
+    // `user_pt_regs.sp` is SP_EL0, while the guest kernel runs on SP_EL1.
+    let sp = if pstate & PSR_MODE_MASK == PSR_MODE_EL1H {
+        reg_value(
+            state,
+            arm64_core_reg_id!(KVM_REG_SIZE_U64, offset_of!(kvm_regs, sp_el1)),
+        )
+    } else {
+        value(offset_of!(user_pt_regs, sp))
+    };
+    regs.extend([sp, reg_value(state, PC), pstate]);
+    regs
+}
+
+/// Returns the value of the 64-bit register with the given id, 0 if it was not saved.
+fn reg_value(state: &VcpuState, id: u64) -> u64 {
+    state
+        .regs
+        .iter()
+        .find(|reg| reg.id == id)
+        .map(|reg| reg.value::<u64, 8>())
+        .unwrap_or(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::arch::aarch64::regs::Aarch64RegisterRef;
+
+    #[test]
+    fn test_prstatus_regs() {
+        let core_reg = |offset: usize| arm64_core_reg_id!(KVM_REG_SIZE_U64, offset);
+        let mut state = VcpuState::default();
+        let mut push = |id: u64, value: u64| {
+            state
+                .regs
+                .push(Aarch64RegisterRef::new(id, &value.to_le_bytes()));
+        };
+        push(core_reg(offset_of!(user_pt_regs, regs) + 8), 0x11);
+        push(core_reg(offset_of!(user_pt_regs, sp)), 0x22);
+        push(core_reg(offset_of!(kvm_regs, sp_el1)), 0x33);
+        push(PC, 0x44);
+        push(core_reg(offset_of!(user_pt_regs, pstate)), PSR_MODE_EL1H);
+
+        let regs = prstatus_regs(&state);
+        assert_eq!(regs.len(), 34);
+        assert_eq!(regs[0], 0);
+        assert_eq!(regs[1], 0x11);
+        assert_eq!(&regs[31..], &[0x33, 0x44, PSR_MODE_EL1H]);
+
+        // Outside of EL1h, the stack pointer is SP_EL0.
+        let pstate_id = core_reg(offset_of!(user_pt_regs, pstate));
+        state
+            .regs
+            .iter_mut()
+            .find(|reg| reg.id == pstate_id)
+            .unwrap()
+            .set_value(0u64);
+        assert_eq!(prstatus_regs(&state)[31], 0x22);
+    }
+}
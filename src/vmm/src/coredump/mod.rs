@@ -0,0 +1,247 @@
+// Copyright 2025 Amazon.com, Inc. or its affiliates. All Rights Reserved.
+// SPDX-License-Identifier: Apache-2.0
+
+#[cfg(target_arch = "aarch64")]
+mod aarch64;
+#[cfg(target_arch = "x86_64")]
+mod x86_64;
+
+use std::fs::OpenOptions;
+use std::io::Write;
+
+#[cfg(target_arch = "aarch64")]
+use self::aarch64::{ELF_MACHINE, prstatus_regs};
+#[cfg(target_arch = "x86_64")]
+use self::x86_64::{ELF_MACHINE, prstatus_regs};
+use crate::Vmm;
+use crate::persist::MicrovmStateError;
+use crate::utils::usize_to_u64;
+use crate::vmm_config::coredump::CreateCoredumpParams;
+use crate::vstate::memory::{GuestMemoryExtension, GuestMemoryState, MemoryError};
+use crate::vstate::vcpu::VcpuState;
+
+/// Size of the ELF64 file header.
+const ELF_HEADER_SIZE: u16 = 64;
+/// Size of an ELF64 program header.
+const PROGRAM_HEADER_SIZE: u16 = 56;
+/// Core file type.
+const ET_CORE: u16 = 4;
+/// Loadable segment type.
+const PT_LOAD: u32 = 1;
+/// Note segment type.
+const PT_NOTE: u32 = 4;
+/// The guest memory is readable, writable and executable.
+const PF_RWX: u32 = 0x7;
+/// Note holding the `struct elf_prstatus` of a thread.
+const NT_PRSTATUS: u32 = 1;
+/// Name of the notes describing the process, NUL terminated.
+const CORE_NOTE_NAME: &[u8] = b"CORE\0";
+/// Offset of `pr_pid` in `struct elf_prstatus`.
+const PRSTATUS_PID_OFFSET: usize = 32;
+/// Offset of `pr_reg` in `struct elf_prstatus`, after the signal and process information.
+const PRSTATUS_REGS_OFFSET: usize = 112;
+/// Size of `pr_fpvalid` and of the padding ending `struct elf_prstatus`.
+const PRSTATUS_TAIL_SIZE: usize = 8;
+
+/// Errors associated with writing a guest core dump.
+#[derive(Debug, thiserror::Error, displaydoc::Display)]
+pub enum CoredumpError {
+    /// Cannot save the vCPU states: {0}
+    SaveVcpuStates(#[from] MicrovmStateError),
+    /// Cannot write the core dump file: {0}
+    WriteFile(#[from] std::io::Error),
+    /// Cannot write the guest memory to the core dump file: {0}
+    WriteMemory(#[from] MemoryError),
+}
+
+/// Writes an ELF core dump of the paused microVM to the file at `params.dump_path`.
+///
+/// The dump starts with a `PT_NOTE` segment holding an `NT_PRSTATUS` note with the registers of
+/// each vCPU, followed by a `PT_LOAD` segment with the contents of each guest memory region.
+pub fn create_coredump(vmm: &mut Vmm, params: &CreateCoredumpParams) -> Result<(), CoredumpError> {
+    let vcpu_states = vmm.save_vcpu_states()?;
+    let notes = vcpu_notes(&vcpu_states);
+    let headers = elf_headers(&vmm.vm.guest_memory().describe(), notes.len());
+
+    let mut file = OpenOptions::new()
+        .create(true)
+        .write(true)
+        .truncate(true)
+        .open(&params.dump_path)?;
+    file.write_all(&headers)?;
+    file.write_all(&notes)?;
+    // The regions are dumped in the order `describe` lists them.
+    vmm.vm.guest_memory().dump(&mut file)?;
+    file.sync_all()?;
+
+    Ok(())
+}
+
+/// Builds the ELF file header followed by the program headers: the notes segment, of
+/// `notes_size` bytes, and one loadable segment per guest memory region.
+fn elf_headers(memory_state: &GuestMemoryState, notes_size: usize) -> Vec<u8> {
+    let phnum = u16::try_from(memory_state.regions.len() + 1)
+        .expect("The number of guest memory regions fits the ELF program header count");
+    let mut headers = Vec::new();
+
+    // e_ident: magic, 64-bit, little endian, current version, System V ABI.
+    headers.extend_from_slice(b"\x7fELF");
+    headers.extend_from_slice(&[2, 1, 1, 0]);
+    headers.extend_from_slice(&[0; 8]);
+    headers.extend_from_slice(&ET_CORE.to_le_bytes());
+    headers.extend_from_slice(&ELF_MACHINE.to_le_bytes());
+    // e_version
+    headers.extend_from_slice(&1u32.to_le_bytes());
+    // e_entry
+    headers.extend_from_slice(&0u64.to_le_bytes());
+    // e_phoff
+    headers.extend_from_slice(&u64::from(ELF_HEADER_SIZE).to_le_bytes());
+    // e_shoff
+    headers.extend_from_slice(&0u64.to_le_bytes());
+    // e_flags
+    headers.extend_from_slice(&0u32.to_le_bytes());
+    headers.extend_from_slice(&ELF_HEADER_SIZE.to_le_bytes());
+    headers.extend_from_slice(&PROGRAM_HEADER_SIZE.to_le_bytes());
+    headers.extend_from_slice(&phnum.to_le_bytes());
+    // e_shentsize, e_shnum, e_shstrndx: there are no sections.
+    headers.extend_from_slice(&[0; 6]);
+
+    let mut offset = u64::from(ELF_HEADER_SIZE) + u64::from(PROGRAM_HEADER_SIZE) * u64::from(phnum);
+    let notes_size = usize_to_u64(notes_size);
+    program_header(&mut headers, PT_NOTE, 0, offset, 0, notes_size);
+    offset += notes_size;
+
+    // As for dumps of guests without paging information, the segments only have a physical
+    // address.
+    for (base, size) in memory_state.regions() {
+        let size = usize_to_u64(size);
+        program_header(&mut headers, PT_LOAD, PF_RWX, offset, base.0, size);
+        offset += size;
+    }
+
+    headers
+}
+
+/// Appends an ELF64 program header to `headers`.
+fn program_header(
+    headers: &mut Vec<u8>,
+    p_type: u32,
+    flags: u32,
+    offset: u64,
+    paddr: u64,
+    size: u64,
+) {
+    headers.extend_from_slice(&p_type.to_le_bytes());
+    headers.extend_from_slice(&flags.to_le_bytes());
+    headers.extend_from_slice(&offset.to_le_bytes());
+    // p_vaddr
+    headers.extend_from_slice(&0u64.to_le_bytes());
+    headers.extend_from_slice(&paddr.to_le_bytes());
+    // p_filesz and p_memsz
+    headers.extend_from_slice(&size.to_le_bytes());
+    headers.extend_from_slice(&size.to_le_bytes());
+    // p_align
+    headers.extend_from_slice(&0u64.to_le_bytes());
+}
+
+/// Builds the contents of the notes segment: an `NT_PRSTATUS` note per vCPU, with the vCPU
+/// registers. Like for the dumps of other hypervisors, the pid of a vCPU is its index plus one.
+fn vcpu_notes(vcpu_states: &[VcpuState]) -> Vec<u8> {
+    let mut notes = Vec::new();
+    for (index, state) in vcpu_states.iter().enumerate() {
+        let regs = prstatus_regs(state);
+        let mut prstatus = vec![0u8; PRSTATUS_REGS_OFFSET + regs.len() * 8 + PRSTATUS_TAIL_SIZE];
+        let pid = u32::try_from(index + 1).expect("The vCPU count fits a pid");
+        prstatus[PRSTATUS_PID_OFFSET..PRSTATUS_PID_OFFSET + 4].copy_from_slice(&pid.to_le_bytes());
+        for (chunk, reg) in prstatus[PRSTATUS_REGS_OFFSET..]
+            .chunks_exact_mut(8)
+            .zip(regs)
+        {
+            chunk.copy_from_slice(&reg.to_le_bytes());
+        }
+        note(&mut notes, CORE_NOTE_NAME, NT_PRSTATUS, &prstatus);
+    }
+    notes
+}
+
+/// Appends an ELF note to `notes`. The name and the descriptor are padded to 4 bytes.
+fn note(notes: &mut Vec<u8>, name: &[u8], n_type: u32, desc: &[u8]) {
+    let namesz = u32::try_from(name.len()).expect("The note name is short");
+    let descsz = u32::try_from(desc.len()).expect("The note descriptor is short");
+    notes.extend_from_slice(&namesz.to_le_bytes());
+    notes.extend_from_slice(&descsz.to_le_bytes());
+    notes.extend_from_slice(&n_type.to_le_bytes());
+    for field in [name, desc] {
+        notes.extend_from_slice(field);
+        notes.resize(notes.len().next_multiple_of(4), 0);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::vstate::memory::GuestMemoryRegionState;
+
+    fn read_u16(buf: &[u8], offset: usize) -> u16 {
+        u16::from_le_bytes(buf[offset..offset + 2].try_into().unwrap())
+    }
+
+    fn read_u32(buf: &[u8], offset: usize) -> u32 {
+        u32::from_le_bytes(buf[offset..offset + 4].try_into().unwrap())
+    }
+
+    fn read_u64(buf: &[u8], offset: usize) -> u64 {
+        u64::from_le_bytes(buf[offset..offset + 8].try_into().unwrap())
+    }
+
+    #[test]
+    fn test_elf_headers() {
+        let memory_state = GuestMemoryState {
+            regions: vec![
+                GuestMemoryRegionState {
+                    base_address: 0,
+                    size: 0x1000,
+                },
+                GuestMemoryRegionState {
+                    base_address: 0x1_0000_0000,
+                    size: 0x2000,
+                },
+            ],
+        };
+        let headers = elf_headers(&memory_state, 0x150);
+
+        assert_eq!(headers.len(), 64 + 3 * 56);
+        assert_eq!(&headers[..4], b"\x7fELF");
+        assert_eq!(read_u16(&headers, 16), ET_CORE);
+        assert_eq!(read_u16(&headers, 18), ELF_MACHINE);
+        assert_eq!(read_u64(&headers, 32), 64);
+        assert_eq!(read_u16(&headers, 56), 3);
+
+        let phdr = |index: usize| &headers[64 + index * 56..64 + (index + 1) * 56];
+        // Notes, right after the headers.
+        assert_eq!(read_u32(phdr(0), 0), PT_NOTE);
+        assert_eq!(read_u64(phdr(0), 8), 232);
+        assert_eq!(read_u64(phdr(0), 32), 0x150);
+        // Guest memory, after the notes.
+        assert_eq!(read_u32(phdr(1), 0), PT_LOAD);
+        assert_eq!(read_u64(phdr(1), 8), 232 + 0x150);
+        assert_eq!(read_u64(phdr(1), 24), 0);
+        assert_eq!(read_u64(phdr(1), 32), 0x1000);
+        assert_eq!(read_u32(phdr(2), 0), PT_LOAD);
+        assert_eq!(read_u64(phdr(2), 8), 232 + 0x150 + 0x1000);
+        assert_eq!(read_u64(phdr(2), 24), 0x1_0000_0000);
+        assert_eq!(read_u64(phdr(2), 40), 0x2000);
+    }
+
+    #[test]
+    fn test_note() {
+        let mut notes = Vec::new();
+        note(&mut notes, CORE_NOTE_NAME, NT_PRSTATUS, &[1, 2, 3, 4, 5, 6]);
+
+        assert_eq!(read_u32(&notes, 0), 5);
+        assert_eq!(read_u32(&notes, 4), 6);
+        assert_eq!(read_u32(&notes, 8), NT_PRSTATUS);
+        assert_eq!(&notes[12..20], b"CORE\0\0\0\0");
+        assert_eq!(&notes[20..], &[1, 2, 3, 4, 5, 6, 0, 0]);
+    }
+}
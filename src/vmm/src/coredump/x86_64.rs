@@ -0,0 +1,76 @@
+// Copyright 2025 Amazon.com, Inc. or its affiliates. All Rights Reserved.
+// SPDX-License-Identifier: Apache-2.0
+
+use crate::vstate::vcpu::VcpuState;
+
+/// `EM_X86_64`, the ELF machine of the core dump.
+pub const ELF_MACHINE: u16 = 62;
+
+/// Returns the registers of the `NT_PRSTATUS` note of a vCPU, as laid out by the x86_64
+/// `struct user_regs_struct`.
+pub fn prstatus_regs(state: &VcpuState) -> Vec<u64> {
+    let regs = &state.regs;
+    let sregs = &state.sregs;
+    vec![
+        regs.r15,
+        regs.r14,
+        regs.r13,
+        regs.r12,
+        regs.rbp,
+        regs.rbx,
+        regs.r11,
+        regs.r10,
+        regs.r9,
+        regs.r8,
+        regs.rax,
+        regs.rcx,
+        regs.rdx,
+        regs.rsi,
+        regs.rdi,
+        // orig_rax, only meaningful for threads interrupted in a syscall.
+        u64::MAX,
+        regs.rip,
+        u64::from(sregs.cs.selector),
+        regs.rflags,
+        regs.rsp,
+        u64::from(sregs.ss.selector),
+        sregs.fs.base,
+        sregs.gs.base,
+        u64::from(sregs.ds.selector),
+        u64::from(sregs.es.selector),
+        u64::from(sregs.fs.selector),
+        u64::from(sregs.gs.selector),
+    ]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_prstatus_regs() {
+        let mut state = VcpuState::default();
+        state.regs.r15 = 0x15;
+        state.regs.rax = 0xa;
+        state.regs.rdi = 0xd;
+        state.regs.rip = 0x1000;
+        state.regs.rflags = 0x2;
+        state.regs.rsp = 0x2000;
+        state.sregs.cs.selector = 0x10;
+        state.sregs.ss.selector = 0x18;
+        state.sregs.fs.base = 0x3000;
+        state.sregs.gs.base = 0x4000;
+        state.sregs.gs.selector = 0x28;
+
+        let regs = prstatus_regs(&state);
+        assert_eq!(regs.len(), 27);
+        assert_eq!(regs[0], 0x15);
+        assert_eq!(regs[10], 0xa);
+        assert_eq!(regs[14], 0xd);
+        assert_eq!(
+            &regs[15..23],
+            &[u64::MAX, 0x1000, 0x10, 0x2, 0x2000, 0x18, 0x3000, 0x4000]
+        );
+        assert_eq!(regs[26], 0x28);
+    }
+}
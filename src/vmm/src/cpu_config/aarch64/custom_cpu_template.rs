@@ -5,11 +5,11 @@
 /// config templates.
 use std::borrow::Cow;
 
-use serde::de::Error;
-use serde::{Deserialize, Serialize};
+use serde::de::{Error, IntoDeserializer};
+use serde::{Deserialize, Deserializer, Serialize};
 
-use crate::arch::aarch64::regs::{RegSize, reg_size};
-use crate::cpu_config::aarch64::static_cpu_templates::v1n1;
+use crate::arch::aarch64::regs::{RegSize, id_register_by_name, reg_size};
+use crate::cpu_config::aarch64::static_cpu_templates::{v1n1, v2n1};
 use crate::cpu_config::templates::{
     CpuTemplateType, GetCpuTemplate, GetCpuTemplateError, KvmCapability, RegisterValueFilter,
     StaticCpuTemplate,
@@ -24,6 +24,8 @@ impl GetCpuTemplate for Option<CpuTemplateType> {
                 CpuTemplateType::Static(template) => match template {
                     // TODO: Check if the CPU model is Neoverse-V1.
                     StaticCpuTemplate::V1N1 => Ok(Cow::Owned(v1n1::v1n1())),
+                    // TODO: Check if the CPU model is Neoverse-V2.
+                    StaticCpuTemplate::V2N1 => Ok(Cow::Owned(v2n1::v2n1())),
                     other => Err(GetCpuTemplateError::InvalidStaticCpuTemplate(*other)),
                 },
             },
@@ -101,8 +103,9 @@ pub struct VcpuFeatures {
 #[derive(Debug, Clone, Copy, Eq, PartialEq, Serialize, Deserialize, Hash)]
 pub struct RegisterModifier {
     /// Pointer of the location to be bit mapped.
+    /// Can also be given as the name of an ID register, e.g. `ID_AA64ISAR1_EL1`.
     #[serde(
-        deserialize_with = "deserialize_from_str_u64",
+        deserialize_with = "deserialize_reg_addr",
         serialize_with = "serialize_to_hex_str"
     )]
     pub addr: u64,
@@ -111,6 +114,18 @@ pub struct RegisterModifier {
     pub bitmap: RegisterValueFilter<u128>,
 }
 
+/// Deserializes a register address from either a number or the name of an ID register.
+fn deserialize_reg_addr<'de, D>(deserializer: D) -> Result<u64, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let addr_str = String::deserialize(deserializer)?;
+    match id_register_by_name(&addr_str) {
+        Some(addr) => Ok(addr),
+        None => deserialize_from_str_u64(addr_str.as_str().into_deserializer()),
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use serde_json::Value;
@@ -140,6 +155,17 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_get_cpu_template_with_v2n1_static_template() {
+        // Test `get_cpu_template()` when V2N1 static CPU template is specified. The owned
+        // `CustomCpuTemplate` should be returned.
+        let cpu_template = Some(CpuTemplateType::Static(StaticCpuTemplate::V2N1));
+        assert_eq!(
+            cpu_template.get_cpu_template().unwrap(),
+            Cow::Owned(v2n1::v2n1())
+        );
+    }
+
     #[test]
     fn test_get_cpu_tempalte_with_none_static_template() {
         // Test `get_cpu_template()` when no static CPU template is provided.
@@ -181,6 +207,38 @@ mod tests {
         cpu_config_result.unwrap();
     }
 
+    #[test]
+    fn test_named_register_addr() {
+        let cpu_config = serde_json::from_str::<CustomCpuTemplate>(
+            r#"{
+                    "reg_modifiers":  [
+                        {
+                            "addr": "ID_AA64ISAR1_EL1",
+                            "bitmap": "0b0000xxxx"
+                        }
+                    ]
+                }"#,
+        )
+        .unwrap();
+        assert_eq!(
+            cpu_config.reg_modifiers[0].addr,
+            crate::arch::aarch64::regs::ID_AA64ISAR1_EL1
+        );
+
+        // Unknown register names are rejected.
+        serde_json::from_str::<CustomCpuTemplate>(
+            r#"{
+                    "reg_modifiers":  [
+                        {
+                            "addr": "ID_AA64ISAR9_EL1",
+                            "bitmap": "0b0000xxxx"
+                        }
+                    ]
+                }"#,
+        )
+        .unwrap_err();
+    }
+
     #[test]
     fn test_malformed_json() {
         // Malformed kvm capabilities
@@ -10,8 +10,9 @@ pub mod test_utils;
 
 use super::templates::CustomCpuTemplate;
 use crate::Vcpu;
-use crate::arch::aarch64::regs::{Aarch64RegisterVec, RegSize};
+use crate::arch::aarch64::regs::{Aarch64RegisterVec, RegSize, id_register_name};
 use crate::arch::aarch64::vcpu::{VcpuArchError, get_registers};
+use crate::arch::aarch64::vm::IdRegWritableMasks;
 use crate::vstate::vcpu::KvmVcpuError;
 
 /// Errors thrown while configuring templates.
@@ -21,6 +22,8 @@ pub enum CpuConfigurationError {
     VcpuInit(#[from] KvmVcpuError),
     /// Error reading vcpu registers: {0}
     VcpuGetRegs(#[from] VcpuArchError),
+    /// CPU template changes bits {1:#x} of register {0} which KVM does not allow to change
+    NonWritableBits(String, u64),
 }
 
 /// CPU configuration for aarch64
@@ -70,9 +73,79 @@ impl CpuConfiguration {
         self
     }
 
+    /// Checks that the changes from `base` to this configuration only touch the
+    /// bits of the feature ID registers that KVM allows to change.
+    pub fn check_writable(
+        &self,
+        base: &CpuConfiguration,
+        masks: &IdRegWritableMasks,
+    ) -> Result<(), CpuConfigurationError> {
+        for (reg, base_reg) in self.regs.iter().zip(base.regs.iter()) {
+            if !matches!(reg.size(), RegSize::U64) {
+                continue;
+            }
+            let Some(mask) = masks.mask(reg.id) else {
+                continue;
+            };
+            let changed = reg.value::<u64, 8>() ^ base_reg.value::<u64, 8>();
+            if changed & !mask != 0 {
+                let name = id_register_name(reg.id)
+                    .map(str::to_string)
+                    .unwrap_or_else(|| format!("{:#x}", reg.id));
+                return Err(CpuConfigurationError::NonWritableBits(
+                    name,
+                    changed & !mask,
+                ));
+            }
+        }
+        Ok(())
+    }
+
     /// Returns ids of registers that are changed
     /// by this template
     pub fn register_ids(&self) -> Vec<u64> {
         self.regs.iter().map(|reg| reg.id).collect()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::arch::aarch64::regs::{
+        Aarch64RegisterRef, ID_AA64ISAR0_EL1, ID_AA64ISAR1_EL1, MPIDR_EL1,
+    };
+    use crate::arch::aarch64::vm::KVM_ARM_FEATURE_ID_RANGE_SIZE;
+
+    fn config(regs: &[(u64, u64)]) -> CpuConfiguration {
+        let mut config = CpuConfiguration::default();
+        for (id, value) in regs {
+            config
+                .regs
+                .push(Aarch64RegisterRef::new(*id, &value.to_le_bytes()));
+        }
+        config
+    }
+
+    #[test]
+    fn test_check_writable() {
+        let mut masks = IdRegWritableMasks([0; KVM_ARM_FEATURE_ID_RANGE_SIZE]);
+        // ID_AA64ISAR0_EL1 is op1 == 0, CRm == 6, op2 == 0.
+        masks.0[48] = 0xf0;
+        assert_eq!(masks.mask(ID_AA64ISAR0_EL1), Some(0xf0));
+        assert_eq!(masks.mask(ID_AA64ISAR1_EL1), Some(0));
+        assert_eq!(masks.mask(MPIDR_EL1), Some(0));
+
+        let base = config(&[(ID_AA64ISAR0_EL1, 0x1111), (ID_AA64ISAR1_EL1, 0x1)]);
+
+        // Only writable bits changed.
+        let templated = config(&[(ID_AA64ISAR0_EL1, 0x1101), (ID_AA64ISAR1_EL1, 0x1)]);
+        templated.check_writable(&base, &masks).unwrap();
+
+        // Non writable bits changed.
+        let templated = config(&[(ID_AA64ISAR0_EL1, 0x1111), (ID_AA64ISAR1_EL1, 0x0)]);
+        assert_eq!(
+            templated.check_writable(&base, &masks).unwrap_err(),
+            CpuConfigurationError::NonWritableBits("ID_AA64ISAR1_EL1".to_string(), 0x1)
+        );
+    }
+}
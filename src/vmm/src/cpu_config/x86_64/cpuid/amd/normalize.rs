@@ -99,17 +99,19 @@ impl super::AmdCpuid {
         &mut self,
         // The index of the current logical CPU in the range [0..cpu_count].
         cpu_index: u8,
-        // The total number of logical CPUs.
+        // The number of logical CPUs per socket.
         cpu_count: u8,
         // The number of logical CPUs per core.
         cpus_per_core: u8,
+        // The number of sockets the logical CPUs are spread across.
+        socket_count: u8,
     ) -> Result<(), NormalizeCpuidError> {
         self.passthrough_cache_topology()?;
         self.update_structured_extended_entry()?;
         self.update_extended_feature_fn_entry()?;
-        self.update_amd_feature_entry(cpu_count)?;
+        self.update_amd_feature_entry(cpu_count, socket_count)?;
         self.update_extended_cache_topology_entry(cpu_count, cpus_per_core)?;
-        self.update_extended_apic_id_entry(cpu_index, cpus_per_core)?;
+        self.update_extended_apic_id_entry(cpu_index, cpu_count, cpus_per_core)?;
         self.update_brand_string_entry()?;
 
         Ok(())
@@ -210,12 +212,21 @@ impl super::AmdCpuid {
 
     /// Update AMD feature entry.
     #[allow(clippy::unwrap_used, clippy::unwrap_in_result)]
-    fn update_amd_feature_entry(&mut self, cpu_count: u8) -> Result<(), FeatureEntryError> {
+    fn update_amd_feature_entry(
+        &mut self,
+        cpu_count: u8,
+        socket_count: u8,
+    ) -> Result<(), FeatureEntryError> {
         /// This value allows at most 64 logical threads within a package.
         const THREAD_ID_MAX_SIZE: u32 = 7;
 
         // We don't support more then 128 threads right now.
-        // It's safe to put them all on the same processor.
+        // It's safe to put them all on the same processor, unless they are spread across
+        // multiple sockets, in which case each socket has a power of 2 threads.
+        let thread_id_size = match socket_count {
+            1 => THREAD_ID_MAX_SIZE,
+            _ => cpu_count.next_power_of_two().ilog2(),
+        };
         let leaf_80000008 = self
             .get_mut(&CpuidKey::leaf(0x80000008))
             .ok_or(FeatureEntryError::MissingLeaf0x80000008)?;
@@ -229,7 +240,7 @@ impl super::AmdCpuid {
         // Fn8000_0008_ECX[NC]. A value of zero indicates that legacy methods must be
         // used to determine the maximum number of logical processors, as indicated by
         // CPUID Fn8000_0008_ECX[NC].
-        set_range(&mut leaf_80000008.result.ecx, 12..=15, THREAD_ID_MAX_SIZE).unwrap();
+        set_range(&mut leaf_80000008.result.ecx, 12..=15, thread_id_size).unwrap();
 
         // CPUID Fn8000_0008_ECX[7:0] (Field Name: NC)
         // Number of physical threads - 1. The number of threads in the processor is NT+1
@@ -309,6 +320,8 @@ impl super::AmdCpuid {
     fn update_extended_apic_id_entry(
         &mut self,
         cpu_index: u8,
+        // The number of logical CPUs per socket.
+        cpu_count: u8,
         cpus_per_core: u8,
     ) -> Result<(), ExtendedApicIdError> {
         /// 1 node per processor.
@@ -322,8 +335,18 @@ impl super::AmdCpuid {
         // logical CPU 2 -> core id: 1
         // logical CPU 3 -> core id: 1
         //
-        // SAFETY: We know `cpus_per_core != 0` therefore this is always safe.
-        let core_id = u32::from(cpu_index.checked_div(cpus_per_core).unwrap());
+        // The core id is relative to the socket, the first logical CPUs of each socket being
+        // the first threads of core 0.
+        //
+        // SAFETY: We know `cpu_count != 0` and `cpus_per_core != 0` therefore this is always safe.
+        let socket_id = u32::from(cpu_index.checked_div(cpu_count).unwrap());
+        let core_id = u32::from(
+            cpu_index
+                .checked_rem(cpu_count)
+                .unwrap()
+                .checked_div(cpus_per_core)
+                .unwrap(),
+        );
 
         let leaf_8000001e = self
             .get_mut(&CpuidKey::leaf(0x8000001e))
@@ -370,8 +393,8 @@ impl super::AmdCpuid {
         // Specifies the ID of the node containing the current logical processor. NodeId
         // values are unique across the system.
         //
-        // Put all the cpus in the same node.
-        set_range(&mut leaf_8000001e.result.ecx, 0..=7, 0).unwrap();
+        // Put all the cpus of a socket in the same node.
+        set_range(&mut leaf_8000001e.result.ecx, 0..=7, socket_id).unwrap();
 
         Ok(())
     }
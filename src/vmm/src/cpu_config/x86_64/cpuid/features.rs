@@ -0,0 +1,163 @@
+// Copyright 2025 Amazon.com, Inc. or its affiliates. All Rights Reserved.
+// SPDX-License-Identifier: Apache-2.0
+
+//! Names of the CPU features advertised by single CPUID bits, as used in custom CPU templates.
+
+use super::KvmCpuidFlags;
+use crate::cpu_config::x86_64::custom_cpu_template::CpuidRegister;
+
+/// CPU feature advertised by a single CPUID bit.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CpuidFeature {
+    /// Name of the feature, as reported by Linux in `/proc/cpuinfo`.
+    pub name: &'static str,
+    /// CPUID leaf.
+    pub leaf: u32,
+    /// CPUID subleaf.
+    pub subleaf: u32,
+    /// CPUID register.
+    pub register: CpuidRegister,
+    /// Bit of the register.
+    pub bit: u8,
+}
+
+impl CpuidFeature {
+    /// KVM flags of the leaf of the feature.
+    #[must_use]
+    pub fn flags(&self) -> KvmCpuidFlags {
+        match self.leaf {
+            0x7 | 0xd => KvmCpuidFlags::SIGNIFICANT_INDEX,
+            _ => KvmCpuidFlags::EMPTY,
+        }
+    }
+}
+
+macro_rules! cpuid_features {
+    ($(
+        ($leaf:literal, $subleaf:literal, $register:ident) => {
+            $($name:literal: $bit:literal),* $(,)?
+        }
+    ),* $(,)?) => {
+        &[$($(CpuidFeature {
+            name: $name,
+            leaf: $leaf,
+            subleaf: $subleaf,
+            register: CpuidRegister::$register,
+            bit: $bit,
+        },)*)*]
+    };
+}
+
+/// Known CPU features, from the Intel SDM and the AMD APM.
+pub const CPUID_FEATURES: &[CpuidFeature] = cpuid_features! {
+    (0x1, 0x0, Ecx) => {
+        "pni": 0, "pclmulqdq": 1, "dtes64": 2, "monitor": 3, "ds_cpl": 4, "vmx": 5, "smx": 6,
+        "est": 7, "tm2": 8, "ssse3": 9, "cid": 10, "sdbg": 11, "fma": 12, "cx16": 13,
+        "xtpr": 14, "pdcm": 15, "pcid": 17, "dca": 18, "sse4_1": 19, "sse4_2": 20, "x2apic": 21,
+        "movbe": 22, "popcnt": 23, "tsc_deadline_timer": 24, "aes": 25, "xsave": 26,
+        "osxsave": 27, "avx": 28, "f16c": 29, "rdrand": 30, "hypervisor": 31,
+    },
+    (0x1, 0x0, Edx) => {
+        "fpu": 0, "vme": 1, "de": 2, "pse": 3, "tsc": 4, "msr": 5, "pae": 6, "mce": 7, "cx8": 8,
+        "apic": 9, "sep": 11, "mtrr": 12, "pge": 13, "mca": 14, "cmov": 15, "pat": 16,
+        "pse36": 17, "pn": 18, "clflush": 19, "dts": 21, "acpi": 22, "mmx": 23, "fxsr": 24,
+        "sse": 25, "sse2": 26, "ss": 27, "ht": 28, "tm": 29, "pbe": 31,
+    },
+    (0x7, 0x0, Ebx) => {
+        "fsgsbase": 0, "tsc_adjust": 1, "sgx": 2, "bmi1": 3, "hle": 4, "avx2": 5,
+        "fdp_excptn_only": 6, "smep": 7, "bmi2": 8, "erms": 9, "invpcid": 10, "rtm": 11,
+        "cqm": 12, "zero_fcs_fds": 13, "mpx": 14, "rdt_a": 15, "avx512f": 16, "avx512dq": 17,
+        "rdseed": 18, "adx": 19, "smap": 20, "avx512ifma": 21, "clflushopt": 23, "clwb": 24,
+        "intel_pt": 25, "avx512pf": 26, "avx512er": 27, "avx512cd": 28, "sha_ni": 29,
+        "avx512bw": 30, "avx512vl": 31,
+    },
+    (0x7, 0x0, Ecx) => {
+        "prefetchwt1": 0, "avx512vbmi": 1, "umip": 2, "pku": 3, "ospke": 4, "waitpkg": 5,
+        "avx512_vbmi2": 6, "shstk": 7, "gfni": 8, "vaes": 9, "vpclmulqdq": 10,
+        "avx512_vnni": 11, "avx512_bitalg": 12, "tme": 13, "avx512_vpopcntdq": 14, "la57": 16,
+        "rdpid": 22, "bus_lock_detect": 24, "cldemote": 25, "movdiri": 27, "movdir64b": 28,
+        "enqcmd": 29, "sgx_lc": 30, "pks": 31,
+    },
+    (0x7, 0x0, Edx) => {
+        "avx512_4vnniw": 2, "avx512_4fmaps": 3, "fsrm": 4, "uintr": 5,
+        "avx512_vp2intersect": 8, "srbds_ctrl": 9, "md_clear": 10, "rtm_always_abort": 11,
+        "tsx_force_abort": 13, "serialize": 14, "hybrid_cpu": 15, "tsxldtrk": 16, "pconfig": 18,
+        "arch_lbr": 19, "ibt": 20, "amx_bf16": 22, "avx512_fp16": 23, "amx_tile": 24,
+        "amx_int8": 25, "spec_ctrl": 26, "intel_stibp": 27, "flush_l1d": 28,
+        "arch_capabilities": 29, "core_capabilities": 30, "spec_ctrl_ssbd": 31,
+    },
+    (0x7, 0x1, Eax) => {
+        "avx_vnni": 4, "avx512_bf16": 5, "cmpccxadd": 7, "fzrm": 10, "fsrs": 11, "fsrc": 12,
+        "amx_fp16": 21, "avx_ifma": 23, "lam": 26,
+    },
+    (0xd, 0x1, Eax) => {
+        "xsaveopt": 0, "xsavec": 1, "xgetbv1": 2, "xsaves": 3, "xfd": 4,
+    },
+    (0x8000_0001, 0x0, Ecx) => {
+        "lahf_lm": 0, "cmp_legacy": 1, "svm": 2, "extapic": 3, "cr8_legacy": 4, "abm": 5,
+        "sse4a": 6, "misalignsse": 7, "3dnowprefetch": 8, "osvw": 9, "ibs": 10, "xop": 11,
+        "skinit": 12, "wdt": 13, "lwp": 15, "fma4": 16, "tce": 17, "nodeid_msr": 19, "tbm": 21,
+        "topoext": 22, "perfctr_core": 23, "perfctr_nb": 24, "bpext": 26, "perfctr_llc": 28,
+        "mwaitx": 29,
+    },
+    (0x8000_0001, 0x0, Edx) => {
+        "syscall": 11, "nx": 20, "mmxext": 22, "fxsr_opt": 25, "pdpe1gb": 26, "rdtscp": 27,
+        "lm": 29, "3dnowext": 30, "3dnow": 31,
+    },
+    (0x8000_0008, 0x0, Ebx) => {
+        "clzero": 0, "irperf": 1, "xsaveerptr": 2, "rdpru": 4, "wbnoinvd": 9, "amd_ibpb": 12,
+        "amd_ibrs": 14, "amd_stibp": 15, "amd_ssbd": 24, "virt_ssbd": 25, "amd_ssb_no": 26,
+    },
+};
+
+/// Finds the CPU feature named `name`.
+#[must_use]
+pub fn find_cpuid_feature(name: &str) -> Option<&'static CpuidFeature> {
+    CPUID_FEATURES.iter().find(|feature| feature.name == name)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+
+    use super::*;
+
+    #[test]
+    fn test_cpuid_features_are_unique() {
+        let mut names = HashSet::new();
+        let mut bits = HashSet::new();
+        for feature in CPUID_FEATURES {
+            assert!(names.insert(feature.name), "{}", feature.name);
+            assert!(
+                bits.insert((
+                    feature.leaf,
+                    feature.subleaf,
+                    &feature.register,
+                    feature.bit
+                )),
+                "{}",
+                feature.name
+            );
+            assert!(feature.bit < 32, "{}", feature.name);
+        }
+    }
+
+    #[test]
+    fn test_find_cpuid_feature() {
+        let la57 = find_cpuid_feature("la57").unwrap();
+        assert_eq!(
+            (la57.leaf, la57.subleaf, &la57.register, la57.bit),
+            (0x7, 0x0, &CpuidRegister::Ecx, 16)
+        );
+        assert_eq!(la57.flags(), KvmCpuidFlags::SIGNIFICANT_INDEX);
+        assert_eq!(
+            find_cpuid_feature("avx512f").unwrap().register,
+            CpuidRegister::Ebx
+        );
+        assert_eq!(
+            find_cpuid_feature("sse2").unwrap().flags(),
+            KvmCpuidFlags::EMPTY
+        );
+        assert!(find_cpuid_feature("unknown").is_none());
+    }
+}
@@ -64,7 +64,7 @@ impl super::IntelCpuid {
         &mut self,
         // The index of the current logical CPU in the range [0..cpu_count].
         _cpu_index: u8,
-        // The total number of logical CPUs.
+        // The number of logical CPUs per socket.
         cpu_count: u8,
         // The number of logical CPUs per core.
         cpus_per_core: u8,
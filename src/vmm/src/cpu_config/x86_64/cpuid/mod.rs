@@ -38,6 +38,9 @@ use std::mem::{size_of, transmute};
 /// cpuid utility functions.
 pub mod common;
 
+/// Names of the CPU features advertised by CPUID.
+pub mod features;
+
 /// AMD CPUID specification handling.
 pub mod amd;
 pub use amd::AmdCpuid;
@@ -12,6 +12,8 @@ use crate::vmm_config::machine_config::MAX_SUPPORTED_VCPUS;
 pub enum NormalizeCpuidError {
     /// Provided `cpu_bits` is >=8: {0}.
     CpuBits(u8),
+    /// Provided `socket_count` does not evenly divide the CPUs: {0}.
+    SocketCount(u8),
     /// Failed to apply modifications to Intel CPUID: {0}
     Intel(#[from] crate::cpu_config::x86_64::cpuid::intel::NormalizeCpuidError),
     /// Failed to apply modifications to AMD CPUID: {0}
@@ -166,23 +168,38 @@ impl super::Cpuid {
         cpu_count: u8,
         // The number of bits needed to enumerate logical CPUs per core.
         cpu_bits: u8,
+        // The number of sockets the logical CPUs are evenly spread across.
+        socket_count: u8,
     ) -> Result<(), NormalizeCpuidError> {
         let cpus_per_core = 1u8
             .checked_shl(u32::from(cpu_bits))
             .ok_or(NormalizeCpuidError::CpuBits(cpu_bits))?;
+        // From here on, the topology leaves describe the package (i.e. socket) of the CPU.
+        let cpus_per_socket = cpu_count
+            .checked_div(socket_count)
+            .filter(|_| cpu_count % socket_count == 0)
+            .ok_or(NormalizeCpuidError::SocketCount(socket_count))?;
         self.update_vendor_id()?;
-        self.update_feature_info_entry(cpu_index, cpu_count)?;
-        self.update_extended_topology_entry(cpu_index, cpu_count, cpu_bits, cpus_per_core)?;
+        self.update_feature_info_entry(cpu_index, cpus_per_socket)?;
+        self.update_extended_topology_entry(
+            cpu_index,
+            cpus_per_socket,
+            cpu_bits,
+            cpus_per_core,
+            socket_count,
+        )?;
         self.update_extended_cache_features()?;
 
         // Apply manufacturer specific modifications.
         match self {
             // Apply Intel specific modifications.
             Self::Intel(intel_cpuid) => {
-                intel_cpuid.normalize(cpu_index, cpu_count, cpus_per_core)?;
+                intel_cpuid.normalize(cpu_index, cpus_per_socket, cpus_per_core)?;
             }
             // Apply AMD specific modifications.
-            Self::Amd(amd_cpuid) => amd_cpuid.normalize(cpu_index, cpu_count, cpus_per_core)?,
+            Self::Amd(amd_cpuid) => {
+                amd_cpuid.normalize(cpu_index, cpus_per_socket, cpus_per_core, socket_count)?;
+            }
         }
 
         Ok(())
@@ -208,6 +225,7 @@ impl super::Cpuid {
     fn update_feature_info_entry(
         &mut self,
         cpu_index: u8,
+        // The number of logical CPUs per package.
         cpu_count: u8,
     ) -> Result<(), FeatureInformationError> {
         let leaf_1 = self
@@ -267,9 +285,11 @@ impl super::Cpuid {
     fn update_extended_topology_entry(
         &mut self,
         cpu_index: u8,
+        // The number of logical CPUs per package.
         cpu_count: u8,
         cpu_bits: u8,
         cpus_per_core: u8,
+        socket_count: u8,
     ) -> Result<(), ExtendedTopologyError> {
         // The following commit changed the behavior of KVM_GET_SUPPORTED_CPUID to no longer
         // include CPUID.(EAX=0BH,ECX=1).
@@ -349,17 +369,21 @@ impl super::Cpuid {
                     }
                     // Core domain
                     1 => {
-                        // Configure such that the next higher-scoped domain (i.e. socket) include
-                        // all logical processors.
+                        // With a single socket, configure such that the next higher-scoped domain
+                        // (i.e. socket) include all logical processors.
                         //
                         // The CPUID.(EAX=0BH,ECX=1).EAX[4:0] value must be an integer N such that
                         // 2^N is greater than or equal to the maximum number of vCPUs.
-                        set_range(
-                            &mut subleaf.result.eax,
-                            0..=4,
-                            MAX_SUPPORTED_VCPUS.next_power_of_two().ilog2(),
-                        )
-                        .map_err(|err| ExtendedTopologyError::RightShiftBits(index, err))?;
+                        //
+                        // With multiple sockets, the number of logical processors per socket is a
+                        // power of 2, so that the socket is addressed by the next bits of the
+                        // APIC ID.
+                        let shift = match socket_count {
+                            1 => MAX_SUPPORTED_VCPUS.next_power_of_two().ilog2(),
+                            _ => cpu_count.next_power_of_two().ilog2(),
+                        };
+                        set_range(&mut subleaf.result.eax, 0..=4, shift)
+                            .map_err(|err| ExtendedTopologyError::RightShiftBits(index, err))?;
                         set_range(&mut subleaf.result.ebx, 0..=15, u32::from(cpu_count))
                             .map_err(|err| ExtendedTopologyError::NumLogicalProcs(index, err))?;
 
@@ -529,6 +553,7 @@ mod tests {
             cpu_count,
             cpu_bits,
             cpus_per_core,
+            1,
         );
         result.unwrap();
         assert!(intel_cpuid.inner().contains_key(&CpuidKey {
@@ -552,12 +577,51 @@ mod tests {
                 },
             },
         )])));
-        let result =
-            amd_cpuid.update_extended_topology_entry(cpu_index, cpu_count, cpu_bits, cpus_per_core);
+        let result = amd_cpuid.update_extended_topology_entry(
+            cpu_index,
+            cpu_count,
+            cpu_bits,
+            cpus_per_core,
+            1,
+        );
         result.unwrap();
         assert!(amd_cpuid.inner().contains_key(&CpuidKey {
             leaf: 0xb,
             subleaf: 0x1
         }));
     }
+
+    #[test]
+    fn test_update_extended_topology_entry_sockets() {
+        // 2 sockets of 4 cores of 2 threads, the last vCPU being the second thread of the last
+        // core.
+        let mut cpuid = Cpuid::Intel(IntelCpuid(BTreeMap::from([(
+            CpuidKey::subleaf(0xb, 0x0),
+            CpuidEntry::default(),
+        )])));
+        cpuid
+            .update_extended_topology_entry(15, 8, 1, 2, 2)
+            .unwrap();
+
+        let smt_level = cpuid.get(&CpuidKey::subleaf(0xb, 0x0)).unwrap();
+        assert_eq!(get_range(smt_level.result.eax, 0..=4), 1);
+        assert_eq!(get_range(smt_level.result.ebx, 0..=15), 2);
+        let core_level = cpuid.get(&CpuidKey::subleaf(0xb, 0x1)).unwrap();
+        // The socket is addressed by the APIC ID bits above the 8 logical processors.
+        assert_eq!(get_range(core_level.result.eax, 0..=4), 3);
+        assert_eq!(get_range(core_level.result.ebx, 0..=15), 8);
+        assert_eq!(get_range(core_level.result.ecx, 8..=15), 2);
+        assert_eq!(core_level.result.edx, 15);
+
+        // The CPUs must be evenly spread across the sockets.
+        let mut cpuid = Cpuid::Intel(IntelCpuid(BTreeMap::new()));
+        assert_eq!(
+            cpuid.normalize(0, 6, 0, 4),
+            Err(NormalizeCpuidError::SocketCount(4))
+        );
+        assert_eq!(
+            cpuid.normalize(0, 6, 0, 0),
+            Err(NormalizeCpuidError::SocketCount(0))
+        );
+    }
 }
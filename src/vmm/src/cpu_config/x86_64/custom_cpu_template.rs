@@ -4,6 +4,7 @@
 /// Guest config sub-module specifically useful for
 /// config templates.
 use std::borrow::Cow;
+use std::collections::BTreeMap;
 
 use serde::de::Error as SerdeError;
 use serde::{Deserialize, Deserializer, Serialize, Serializer};
@@ -15,6 +16,7 @@ use crate::cpu_config::templates::{
 use crate::cpu_config::templates_serde::*;
 use crate::cpu_config::x86_64::cpuid::KvmCpuidFlags;
 use crate::cpu_config::x86_64::cpuid::common::get_vendor_id_from_host;
+use crate::cpu_config::x86_64::cpuid::features::{CPUID_FEATURES, find_cpuid_feature};
 use crate::cpu_config::x86_64::static_cpu_templates::{StaticCpuTemplate, c3, t2, t2a, t2cl, t2s};
 use crate::logger::warn;
 
@@ -116,18 +118,17 @@ pub struct CpuidLeafModifier {
 }
 
 /// Wrapper type to containing x86_64 CPU config modifiers.
-#[derive(Debug, Default, Clone, Eq, PartialEq, Serialize, Deserialize)]
-#[serde(deny_unknown_fields)]
+///
+/// On deserialization, the CPUID features listed by name in `cpuid_features` (e.g.
+/// `"avx512f": false`) are folded into `cpuid_modifiers`.
+#[derive(Debug, Default, Clone, Eq, PartialEq, Serialize)]
 pub struct CustomCpuTemplate {
     /// Additional kvm capabilities to check before
     /// configuring vcpus.
-    #[serde(default)]
     pub kvm_capabilities: Vec<KvmCapability>,
     /// Modifiers for CPUID configuration.
-    #[serde(default)]
     pub cpuid_modifiers: Vec<CpuidLeafModifier>,
     /// Modifiers for model specific registers.
-    #[serde(default)]
     pub msr_modifiers: Vec<RegisterModifier>,
 }
 
@@ -141,6 +142,133 @@ impl CustomCpuTemplate {
     pub fn validate(&self) -> Result<(), serde_json::Error> {
         Ok(())
     }
+
+    /// Sets the CPUID bits of the named CPU features, adding CPUID modifiers as needed.
+    pub fn set_cpuid_features(
+        &mut self,
+        features: &BTreeMap<String, bool>,
+    ) -> Result<(), CpuidFeatureError> {
+        for (name, enabled) in features {
+            let feature =
+                find_cpuid_feature(name).ok_or_else(|| CpuidFeatureError::Unknown(name.clone()))?;
+
+            let leaf_modifier = match self
+                .cpuid_modifiers
+                .iter()
+                .position(|m| m.leaf == feature.leaf && m.subleaf == feature.subleaf)
+            {
+                Some(index) => &mut self.cpuid_modifiers[index],
+                None => {
+                    self.cpuid_modifiers.push(CpuidLeafModifier {
+                        leaf: feature.leaf,
+                        subleaf: feature.subleaf,
+                        flags: feature.flags(),
+                        modifiers: Vec::new(),
+                    });
+                    self.cpuid_modifiers.last_mut().unwrap()
+                }
+            };
+            let reg_modifier = match leaf_modifier
+                .modifiers
+                .iter()
+                .position(|m| m.register == feature.register)
+            {
+                Some(index) => &mut leaf_modifier.modifiers[index],
+                None => {
+                    leaf_modifier.modifiers.push(CpuidRegisterModifier {
+                        register: feature.register.clone(),
+                        bitmap: RegisterValueFilter::default(),
+                    });
+                    leaf_modifier.modifiers.last_mut().unwrap()
+                }
+            };
+
+            let mask = 1u32 << feature.bit;
+            if reg_modifier.bitmap.filter & mask != 0 {
+                return Err(CpuidFeatureError::Conflict(name.clone()));
+            }
+            reg_modifier.bitmap.filter |= mask;
+            if *enabled {
+                reg_modifier.bitmap.value |= mask;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Moves the CPUID bits of the known CPU features out of the CPUID modifiers, returning them
+    /// by name. The CPUID modifiers left without any bit to modify are removed.
+    pub fn take_cpuid_features(&mut self) -> BTreeMap<String, bool> {
+        let mut features = BTreeMap::new();
+
+        for leaf_modifier in self.cpuid_modifiers.iter_mut() {
+            for reg_modifier in leaf_modifier.modifiers.iter_mut() {
+                for feature in CPUID_FEATURES.iter().filter(|feature| {
+                    feature.leaf == leaf_modifier.leaf
+                        && feature.subleaf == leaf_modifier.subleaf
+                        && feature.register == reg_modifier.register
+                }) {
+                    let mask = 1u32 << feature.bit;
+                    if reg_modifier.bitmap.filter & mask == 0 {
+                        continue;
+                    }
+                    features.insert(
+                        feature.name.to_string(),
+                        reg_modifier.bitmap.value & mask != 0,
+                    );
+                    reg_modifier.bitmap.filter &= !mask;
+                    reg_modifier.bitmap.value &= !mask;
+                }
+            }
+            leaf_modifier
+                .modifiers
+                .retain(|reg_modifier| reg_modifier.bitmap.filter != 0);
+        }
+        self.cpuid_modifiers
+            .retain(|leaf_modifier| !leaf_modifier.modifiers.is_empty());
+
+        features
+    }
+}
+
+/// Errors associated with the CPU features named in a custom CPU template.
+#[derive(Debug, PartialEq, Eq, thiserror::Error, displaydoc::Display)]
+pub enum CpuidFeatureError {
+    /// Unknown CPUID feature: {0}
+    Unknown(String),
+    /// CPUID feature {0} is also modified by a CPUID modifier.
+    Conflict(String),
+}
+
+impl<'de> Deserialize<'de> for CustomCpuTemplate {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        #[derive(Deserialize)]
+        #[serde(deny_unknown_fields)]
+        struct CustomCpuTemplateDef {
+            #[serde(default)]
+            kvm_capabilities: Vec<KvmCapability>,
+            #[serde(default)]
+            cpuid_modifiers: Vec<CpuidLeafModifier>,
+            #[serde(default)]
+            msr_modifiers: Vec<RegisterModifier>,
+            #[serde(default)]
+            cpuid_features: BTreeMap<String, bool>,
+        }
+
+        let def = CustomCpuTemplateDef::deserialize(deserializer)?;
+        let mut template = CustomCpuTemplate {
+            kvm_capabilities: def.kvm_capabilities,
+            cpuid_modifiers: def.cpuid_modifiers,
+            msr_modifiers: def.msr_modifiers,
+        };
+        template
+            .set_cpuid_features(&def.cpuid_features)
+            .map_err(D::Error::custom)?;
+        Ok(template)
+    }
 }
 
 /// Wrapper of a mask defined as a bitmap to apply
@@ -574,4 +702,112 @@ mod tests {
             "MSR bitmap width in a x86_64 template was not tested."
         );
     }
+
+    #[test]
+    fn test_cpuid_features() {
+        let template = serde_json::from_str::<CustomCpuTemplate>(
+            r#"{
+                "cpuid_modifiers": [
+                    {
+                        "leaf": "0x7",
+                        "subleaf": "0x0",
+                        "flags": 1,
+                        "modifiers": [
+                            {
+                                "register": "ebx",
+                                "bitmap": "0bxxxxxxxxxxxxxxxxxxxxxxxxxxxxxx01"
+                            }
+                        ]
+                    }
+                ],
+                "cpuid_features": {
+                    "avx512f": false,
+                    "la57": false,
+                    "sse2": true
+                }
+            }"#,
+        )
+        .unwrap();
+        assert_eq!(
+            template.cpuid_modifiers,
+            vec![
+                CpuidLeafModifier {
+                    leaf: 0x7,
+                    subleaf: 0x0,
+                    flags: KvmCpuidFlags::SIGNIFICANT_INDEX,
+                    modifiers: vec![
+                        CpuidRegisterModifier {
+                            register: CpuidRegister::Ebx,
+                            bitmap: RegisterValueFilter {
+                                filter: (1 << 16) | 0b11,
+                                value: 0b01,
+                            },
+                        },
+                        CpuidRegisterModifier {
+                            register: CpuidRegister::Ecx,
+                            bitmap: RegisterValueFilter {
+                                filter: 1 << 16,
+                                value: 0,
+                            },
+                        },
+                    ],
+                },
+                CpuidLeafModifier {
+                    leaf: 0x1,
+                    subleaf: 0x0,
+                    flags: KvmCpuidFlags::EMPTY,
+                    modifiers: vec![CpuidRegisterModifier {
+                        register: CpuidRegister::Edx,
+                        bitmap: RegisterValueFilter {
+                            filter: 1 << 26,
+                            value: 1 << 26,
+                        },
+                    }],
+                },
+            ]
+        );
+
+        // The named features are moved back out of the CPUID modifiers.
+        let mut symbolic = template.clone();
+        assert_eq!(
+            symbolic.take_cpuid_features(),
+            BTreeMap::from([
+                ("avx512f".to_string(), false),
+                ("fsgsbase".to_string(), true),
+                ("la57".to_string(), false),
+                ("sse2".to_string(), true),
+                ("tsc_adjust".to_string(), false),
+            ])
+        );
+        assert_eq!(symbolic.cpuid_modifiers, vec![]);
+    }
+
+    #[test]
+    fn test_cpuid_features_errors() {
+        let mut template = CustomCpuTemplate::default();
+        assert_eq!(
+            template.set_cpuid_features(&BTreeMap::from([("avx1024".to_string(), true)])),
+            Err(CpuidFeatureError::Unknown("avx1024".to_string()))
+        );
+
+        let mut template = CustomCpuTemplate {
+            cpuid_modifiers: vec![CpuidLeafModifier {
+                leaf: 0x7,
+                subleaf: 0x0,
+                flags: KvmCpuidFlags::SIGNIFICANT_INDEX,
+                modifiers: vec![CpuidRegisterModifier {
+                    register: CpuidRegister::Ecx,
+                    bitmap: RegisterValueFilter {
+                        filter: 1 << 16,
+                        value: 1 << 16,
+                    },
+                }],
+            }],
+            ..Default::default()
+        };
+        assert_eq!(
+            template.set_cpuid_features(&BTreeMap::from([("la57".to_string(), false)])),
+            Err(CpuidFeatureError::Conflict("la57".to_string()))
+        );
+    }
 }
@@ -17,8 +17,11 @@ use vm_superio::Serial;
 use vmm_sys_util::eventfd::EventFd;
 
 use crate::devices::bus::BusDevice;
+use crate::devices::legacy::pvpanic::PVPANIC_PORT;
 use crate::devices::legacy::serial::SerialOut;
-use crate::devices::legacy::{EventFdTrigger, SerialDevice, SerialEventsWrapper};
+use crate::devices::legacy::watchdog::{WATCHDOG_PORT, WATCHDOG_PORT_LEN, WatchdogState};
+use crate::devices::legacy::{EventFdTrigger, PvPanicDevice, SerialDevice, SerialEventsWrapper};
+use crate::snapshot::Persist;
 
 /// Errors corresponding to the `PortIODeviceManager`.
 #[derive(Debug, derive_more::From, thiserror::Error, displaydoc::Display)]
@@ -30,7 +33,7 @@ pub enum LegacyDeviceError {
 }
 
 /// The `PortIODeviceManager` is a wrapper that is used for registering legacy devices
-/// on an I/O Bus. It currently manages the uart and i8042 devices.
+/// on an I/O Bus. It currently manages the uart, i8042, pvpanic and watchdog devices.
 /// The `LegacyDeviceManger` should be initialized only by using the constructor.
 #[derive(Debug)]
 pub struct PortIODeviceManager {
@@ -39,6 +42,10 @@ pub struct PortIODeviceManager {
     pub stdio_serial: Arc<Mutex<BusDevice>>,
     // BusDevice::I8042Device
     pub i8042: Arc<Mutex<BusDevice>>,
+    // BusDevice::PvPanic, if configured.
+    pub pvpanic: Option<Arc<Mutex<BusDevice>>>,
+    // BusDevice::Watchdog, if configured.
+    pub watchdog: Option<Arc<Mutex<BusDevice>>>,
 
     // Communication event on ports 1 & 3.
     pub com_evt_1_3: EventFdTrigger,
@@ -97,6 +104,8 @@ impl PortIODeviceManager {
             io_bus,
             stdio_serial: serial,
             i8042,
+            pvpanic: None,
+            watchdog: None,
             com_evt_1_3,
             com_evt_2_4,
             kbd_evt,
@@ -170,7 +179,63 @@ impl PortIODeviceManager {
         Ok(())
     }
 
-    pub(crate) fn append_aml_bytes(bytes: &mut Vec<u8>) -> Result<(), AmlError> {
+    /// Register the pvpanic device.
+    pub fn register_pvpanic(&mut self, pvpanic: PvPanicDevice) -> Result<(), LegacyDeviceError> {
+        let pvpanic = Arc::new(Mutex::new(BusDevice::PvPanic(pvpanic)));
+        self.io_bus.insert(pvpanic.clone(), PVPANIC_PORT, 1)?;
+        self.pvpanic = Some(pvpanic);
+        Ok(())
+    }
+
+    /// Register the watchdog device.
+    pub fn register_watchdog(
+        &mut self,
+        watchdog: Arc<Mutex<BusDevice>>,
+    ) -> Result<(), LegacyDeviceError> {
+        debug_assert!(matches!(*watchdog.lock().unwrap(), BusDevice::Watchdog(_)));
+        self.io_bus
+            .insert(watchdog.clone(), WATCHDOG_PORT, WATCHDOG_PORT_LEN)?;
+        self.watchdog = Some(watchdog);
+        Ok(())
+    }
+
+    /// Stops the countdown of the watchdog, if any, while the microVM is paused.
+    pub fn pause_watchdog(&self) {
+        if let Some(watchdog) = &self.watchdog {
+            watchdog
+                .lock()
+                .expect("Poisoned lock")
+                .watchdog_mut()
+                .unwrap()
+                .pause();
+        }
+    }
+
+    /// Restarts the countdown of the watchdog, if any, when the microVM is resumed.
+    pub fn resume_watchdog(&self) {
+        if let Some(watchdog) = &self.watchdog {
+            watchdog
+                .lock()
+                .expect("Poisoned lock")
+                .watchdog_mut()
+                .unwrap()
+                .resume();
+        }
+    }
+
+    /// Returns the state of the watchdog, if any.
+    pub fn save_watchdog(&self) -> Option<WatchdogState> {
+        self.watchdog.as_ref().map(|watchdog| {
+            watchdog
+                .lock()
+                .expect("Poisoned lock")
+                .watchdog_ref()
+                .unwrap()
+                .save()
+        })
+    }
+
+    pub(crate) fn append_aml_bytes(&self, bytes: &mut Vec<u8>) -> Result<(), AmlError> {
         // Set up COM devices
         let gsi = [
             Self::COM_EVT_1_3_GSI,
@@ -237,13 +302,30 @@ impl PortIODeviceManager {
                 )?,
             ],
         )
-        .append_aml_bytes(bytes)
+        .append_aml_bytes(bytes)?;
+        // Setup pvpanic
+        if self.pvpanic.is_some() {
+            let port = u16::try_from(PVPANIC_PORT).unwrap();
+            aml::Device::new(
+                "_SB_.PEVT".try_into()?,
+                vec![
+                    &aml::Name::new("_HID".try_into()?, &"QEMU0001")?,
+                    &aml::Name::new(
+                        "_CRS".try_into()?,
+                        &aml::ResourceTemplate::new(vec![&aml::Io::new(port, port, 1u8, 1u8)]),
+                    )?,
+                ],
+            )
+            .append_aml_bytes(bytes)?;
+        }
+        Ok(())
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::devices::legacy::WatchdogDevice;
     use crate::vstate::vm::tests::setup_vm_with_memory;
 
     #[test]
@@ -265,5 +347,21 @@ mod tests {
         )
         .unwrap();
         ldm.register_devices(vm.fd()).unwrap();
+        ldm.register_pvpanic(PvPanicDevice::new(
+            EventFd::new(libc::EFD_NONBLOCK).unwrap(),
+        ))
+        .unwrap();
+        assert!(ldm.io_bus.get_device(PVPANIC_PORT).is_some());
+
+        let mut aml = Vec::new();
+        ldm.append_aml_bytes(&mut aml).unwrap();
+        assert!(aml.windows(8).any(|name| name == b"QEMU0001"));
+
+        assert!(ldm.save_watchdog().is_none());
+        let watchdog = WatchdogDevice::new(EventFd::new(libc::EFD_NONBLOCK).unwrap()).unwrap();
+        ldm.register_watchdog(Arc::new(Mutex::new(BusDevice::Watchdog(watchdog))))
+            .unwrap();
+        assert!(ldm.io_bus.get_device(WATCHDOG_PORT).is_some());
+        assert!(ldm.save_watchdog().is_some());
     }
 }
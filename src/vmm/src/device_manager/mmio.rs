@@ -24,8 +24,9 @@ use super::resources::ResourceAllocator;
 use crate::arch::DeviceType;
 use crate::arch::DeviceType::Virtio;
 use crate::devices::BusDevice;
+use crate::devices::legacy::CfiFlashDevice;
 #[cfg(target_arch = "aarch64")]
-use crate::devices::legacy::RTCDevice;
+use crate::devices::legacy::{PvPanicDevice, RTCDevice};
 use crate::devices::pseudo::BootTimer;
 use crate::devices::virtio::balloon::Balloon;
 use crate::devices::virtio::block::device::Block;
@@ -168,7 +169,7 @@ impl MMIODeviceManager {
     }
 
     /// Register a device at some MMIO address.
-    fn register_mmio_device(
+    pub(crate) fn register_mmio_device(
         &mut self,
         identifier: (DeviceType, String),
         device_info: MMIODeviceInfo,
@@ -205,8 +206,12 @@ impl MMIODeviceManager {
                 vm.register_ioevent(queue_evt, &io_addr, u32::try_from(i).unwrap())
                     .map_err(MmioError::RegisterIoEvent)?;
             }
-            vm.register_irqfd(&locked_device.interrupt_trigger().irq_evt, irq.get())
-                .map_err(MmioError::RegisterIrqFd)?;
+            // Both interrupt sources of the device are signaled on the same legacy IRQ line.
+            let interrupt = locked_device.interrupt_trigger();
+            for evt in [&interrupt.irq_evt, &interrupt.config_evt] {
+                vm.register_irqfd(evt, irq.get())
+                    .map_err(MmioError::RegisterIrqFd)?;
+            }
         }
 
         self.register_mmio_device(
@@ -341,6 +346,43 @@ impl MMIODeviceManager {
         )
     }
 
+    #[cfg(target_arch = "aarch64")]
+    /// Register a MMIO pvpanic device at the specified MMIO configuration if given as parameter,
+    /// otherwise allocate a new MMIO resources for it.
+    pub fn register_mmio_pvpanic(
+        &mut self,
+        resource_allocator: &mut ResourceAllocator,
+        pvpanic: PvPanicDevice,
+        device_info_opt: Option<MMIODeviceInfo>,
+    ) -> Result<(), MmioError> {
+        let device_info = if let Some(device_info) = device_info_opt {
+            device_info
+        } else {
+            self.allocate_mmio_resources(resource_allocator, 0)?
+        };
+
+        let identifier = (DeviceType::PvPanic, DeviceType::PvPanic.to_string());
+        self.register_mmio_device(
+            identifier,
+            device_info,
+            Arc::new(Mutex::new(BusDevice::PvPanic(pvpanic))),
+        )
+    }
+
+    /// Register a flash device at the given address. Its range is `device_info.len` bytes long.
+    pub fn register_mmio_flash(
+        &mut self,
+        flash: CfiFlashDevice,
+        device_info: MMIODeviceInfo,
+    ) -> Result<(), MmioError> {
+        let identifier = (DeviceType::Flash, DeviceType::Flash.to_string());
+        self.register_mmio_device(
+            identifier,
+            device_info,
+            Arc::new(Mutex::new(BusDevice::CfiFlash(flash))),
+        )
+    }
+
     /// Register a boot timer device.
     pub fn register_mmio_boot_timer(
         &mut self,
@@ -405,9 +447,8 @@ impl MMIODeviceManager {
                 let virtio_device = bus_device
                     .lock()
                     .expect("Poisoned lock")
-                    .mmio_transport_ref()
-                    .expect("Unexpected device type")
-                    .device();
+                    .virtio_device()
+                    .expect("Unexpected device type");
                 f(*virtio_type, device_id, device_info, virtio_device)?;
             }
             Ok(())
@@ -431,9 +472,8 @@ impl MMIODeviceManager {
             let virtio_device = busdev
                 .lock()
                 .expect("Poisoned lock")
-                .mmio_transport_ref()
-                .expect("Unexpected device type")
-                .device();
+                .virtio_device()
+                .expect("Unexpected device type");
             let mut dev = virtio_device.lock().expect("Poisoned lock");
             f(dev
                 .as_mut_any()
@@ -11,6 +11,9 @@ pub mod acpi;
 pub mod legacy;
 /// Memory Mapped I/O Manager.
 pub mod mmio;
+/// PCI device manager.
+#[cfg(target_arch = "x86_64")]
+pub mod pci;
 /// Device managers (de)serialization support.
 pub mod persist;
 /// Resource manager for devices.
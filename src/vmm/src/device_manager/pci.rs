@@ -0,0 +1,275 @@
+// Copyright 2025 Amazon.com, Inc. or its affiliates. All Rights Reserved.
+// SPDX-License-Identifier: Apache-2.0
+
+use std::os::unix::io::AsRawFd;
+use std::sync::{Arc, Mutex};
+
+use acpi_tables::{Aml, aml};
+use kvm_ioctls::{IoEventAddress, NoDatamatch, VmFd};
+use log::debug;
+use vm_allocator::AllocPolicy;
+
+use super::mmio::{MMIODeviceInfo, MMIODeviceManager, MmioError};
+use super::resources::ResourceAllocator;
+use crate::arch::DeviceType;
+use crate::devices::BusDevice;
+use crate::devices::pci::msix::MsiRouting;
+use crate::devices::pci::{PCI_ECAM_SIZE, PciRootComplex};
+use crate::devices::virtio::device::VirtioDevice;
+use crate::devices::virtio::pci::{
+    NOTIFY_CFG_OFFSET, NOTIFY_OFF_MULTIPLIER, VIRTIO_PCI_BAR_SIZE, VirtioPciTransport,
+};
+use crate::utils::usize_to_u64;
+use crate::vstate::kvm::Kvm;
+use crate::vstate::memory::GuestMemoryMmap;
+
+/// Errors for the PCI device manager.
+#[derive(Debug, thiserror::Error, displaydoc::Display)]
+pub enum PciError {
+    /// Failed to allocate requested resource: {0}
+    Allocator(#[from] vm_allocator::Error),
+    /// Failed to duplicate the VM file descriptor: {0}
+    CloneVmFd(std::io::Error),
+    /// Failed to create the VM file descriptor: {0}
+    CreateVmFd(kvm_ioctls::Error),
+    /// No free slot left on the PCI bus.
+    NoFreeSlot,
+    /// Failed to insert device on the bus: {0}
+    BusInsert(crate::devices::BusError),
+    /// Failed to register the device: {0}
+    Mmio(#[from] MmioError),
+    /// Failed to register IO event: {0}
+    RegisterIoEvent(kvm_ioctls::Error),
+}
+
+/// Manages the PCI root complex and the devices plugged into it.
+///
+/// The ECAM region of the root complex and the BARs of the devices are placed on the MMIO bus.
+/// Virtio devices are also tracked by the MMIO device manager, so that they can be looked up by
+/// their ID regardless of their transport.
+#[derive(Debug)]
+pub struct PciDeviceManager {
+    ecam_addr: u64,
+    root_complex: Arc<Mutex<BusDevice>>,
+    msi_routing: Arc<MsiRouting>,
+}
+
+impl PciDeviceManager {
+    /// Creates the PCI root complex and places its ECAM region on the MMIO bus.
+    pub fn new(
+        kvm: &Kvm,
+        vm_fd: &VmFd,
+        resource_allocator: &mut ResourceAllocator,
+        mmio_device_manager: &mut MMIODeviceManager,
+    ) -> Result<Self, PciError> {
+        // The interrupts of the devices are routed from the vCPU threads, which don't have access
+        // to the VM, so the routing table gets its own VM file descriptor.
+        // SAFETY: `dup` is safe to call on any file descriptor.
+        let fd = unsafe { libc::dup(vm_fd.as_raw_fd()) };
+        if fd < 0 {
+            return Err(PciError::CloneVmFd(std::io::Error::last_os_error()));
+        }
+        // SAFETY: `fd` is a valid VM file descriptor that nothing else owns.
+        let msi_vm_fd =
+            unsafe { kvm.fd.create_vmfd_from_rawfd(fd) }.map_err(PciError::CreateVmFd)?;
+
+        let ecam_addr = resource_allocator.allocate_mmio_memory(
+            PCI_ECAM_SIZE,
+            PCI_ECAM_SIZE,
+            AllocPolicy::LastMatch,
+        )?;
+        let root_complex = Arc::new(Mutex::new(BusDevice::PciRootComplex(PciRootComplex::new())));
+        mmio_device_manager
+            .bus
+            .insert(root_complex.clone(), ecam_addr, PCI_ECAM_SIZE)
+            .map_err(PciError::BusInsert)?;
+        debug!("pci: ECAM region at {:#x}", ecam_addr);
+
+        Ok(PciDeviceManager {
+            ecam_addr,
+            root_complex,
+            msi_routing: Arc::new(MsiRouting::new(msi_vm_fd)),
+        })
+    }
+
+    /// Address of the ECAM region.
+    pub fn ecam_addr(&self) -> u64 {
+        self.ecam_addr
+    }
+
+    /// Plugs a virtio device into the next free slot, using the virtio-pci transport.
+    pub fn register_virtio_device(
+        &mut self,
+        vm_fd: &VmFd,
+        mem: GuestMemoryMmap,
+        resource_allocator: &mut ResourceAllocator,
+        mmio_device_manager: &mut MMIODeviceManager,
+        device_id: String,
+        device: Arc<Mutex<dyn VirtioDevice>>,
+    ) -> Result<(), PciError> {
+        let mut locked_root_complex = self.root_complex.lock().expect("Poisoned lock");
+        let root_complex = locked_root_complex
+            .pci_root_complex_mut()
+            .expect("Unexpected device type");
+        let slot = root_complex.free_slot().ok_or(PciError::NoFreeSlot)?;
+
+        let bar_addr = resource_allocator.allocate_mmio_memory(
+            VIRTIO_PCI_BAR_SIZE,
+            VIRTIO_PCI_BAR_SIZE,
+            AllocPolicy::FirstMatch,
+        )?;
+        let gsis = resource_allocator.allocate_msi_gsi(2)?;
+
+        let device_type = {
+            let locked_device = device.lock().expect("Poisoned lock");
+            for (i, queue_evt) in locked_device.queue_events().iter().enumerate() {
+                let io_addr = IoEventAddress::Mmio(
+                    bar_addr
+                        + NOTIFY_CFG_OFFSET
+                        + u64::from(NOTIFY_OFF_MULTIPLIER) * usize_to_u64(i),
+                );
+                vm_fd
+                    .register_ioevent(queue_evt, &io_addr, NoDatamatch)
+                    .map_err(PciError::RegisterIoEvent)?;
+            }
+            locked_device.device_type()
+        };
+
+        let transport = VirtioPciTransport::new(
+            mem,
+            device,
+            // The allocator only hands out addresses in the 32-bit MMIO gap.
+            u32::try_from(bar_addr).unwrap(),
+            (gsis[0], gsis[1]),
+            self.msi_routing.clone(),
+        );
+        let bus_device = Arc::new(Mutex::new(BusDevice::VirtioPciTransport(transport)));
+        mmio_device_manager.register_mmio_device(
+            (DeviceType::Virtio(device_type), device_id),
+            MMIODeviceInfo {
+                addr: bar_addr,
+                len: VIRTIO_PCI_BAR_SIZE,
+                irq: None,
+            },
+            bus_device.clone(),
+        )?;
+        root_complex.add_device(slot, bus_device);
+        debug!(
+            "pci: virtio device of type {} in slot {} with BAR at {:#x}",
+            device_type, slot, bar_addr
+        );
+        Ok(())
+    }
+}
+
+impl Aml for PciDeviceManager {
+    fn append_aml_bytes(&self, v: &mut Vec<u8>) -> Result<(), aml::AmlError> {
+        // The host bridge of the PCI Express root complex. Its memory window covers the 32-bit
+        // MMIO gap, where the BARs are allocated.
+        aml::Device::new(
+            "_SB_.PCI0".try_into()?,
+            vec![
+                &aml::Name::new("_HID".try_into()?, &aml::EisaName::new("PNP0A08")?)?,
+                &aml::Name::new("_CID".try_into()?, &aml::EisaName::new("PNP0A03")?)?,
+                &aml::Name::new("_SEG".try_into()?, &aml::ZERO)?,
+                &aml::Name::new("_UID".try_into()?, &aml::ZERO)?,
+                &aml::Name::new("_BBN".try_into()?, &aml::ZERO)?,
+                &aml::Name::new(
+                    "_CRS".try_into()?,
+                    &aml::ResourceTemplate::new(vec![
+                        &aml::AddressSpace::new_bus_number(0u16, 0u16)?,
+                        &aml::AddressSpace::new_memory(
+                            aml::AddressSpaceCacheable::NotCacheable,
+                            true,
+                            u32::try_from(crate::arch::MMIO_MEM_START).unwrap(),
+                            crate::arch::IOAPIC_ADDR - 1,
+                        )?,
+                    ]),
+                )?,
+            ],
+        )
+        .append_aml_bytes(v)?;
+
+        // Reserve the ECAM region as a motherboard resource, as required by the PCI Firmware
+        // Specification for the regions described in the MCFG.
+        aml::Device::new(
+            "_SB_.MCFG".try_into()?,
+            vec![
+                &aml::Name::new("_HID".try_into()?, &aml::EisaName::new("PNP0C02")?)?,
+                &aml::Name::new("_UID".try_into()?, &aml::ONE)?,
+                &aml::Name::new(
+                    "_CRS".try_into()?,
+                    &aml::ResourceTemplate::new(vec![&aml::Memory32Fixed::new(
+                        false,
+                        u32::try_from(self.ecam_addr).unwrap(),
+                        u32::try_from(PCI_ECAM_SIZE).unwrap(),
+                    )]),
+                )?,
+            ],
+        )
+        .append_aml_bytes(v)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Vm;
+    use crate::arch::{MMIO_MEM_SIZE, MMIO_MEM_START};
+    use crate::devices::virtio::mmio::tests::DummyDevice;
+    use crate::test_utils::single_region_mem_raw;
+
+    #[test]
+    fn test_register_virtio_device() {
+        let kvm = Kvm::new(vec![]).unwrap();
+        let mut vm = Vm::new(&kvm).unwrap();
+        vm.register_memory_regions(single_region_mem_raw(0x10000))
+            .unwrap();
+        vm.setup_irqchip().unwrap();
+        let mut resource_allocator = ResourceAllocator::new().unwrap();
+        let mut mmio_device_manager = MMIODeviceManager::new();
+
+        let mut pci_device_manager = PciDeviceManager::new(
+            &kvm,
+            vm.fd(),
+            &mut resource_allocator,
+            &mut mmio_device_manager,
+        )
+        .unwrap();
+        // The ECAM region is at the end of the MMIO gap.
+        let ecam_addr = pci_device_manager.ecam_addr();
+        assert_eq!(ecam_addr, MMIO_MEM_START + MMIO_MEM_SIZE - PCI_ECAM_SIZE);
+
+        for id in ["dummy0", "dummy1"] {
+            pci_device_manager
+                .register_virtio_device(
+                    vm.fd(),
+                    vm.guest_memory().clone(),
+                    &mut resource_allocator,
+                    &mut mmio_device_manager,
+                    id.to_string(),
+                    Arc::new(Mutex::new(DummyDevice::new())),
+                )
+                .unwrap();
+        }
+
+        // The BARs are allocated from the start of the MMIO gap, and reported in the
+        // configuration space of the devices.
+        for (slot, id) in ["dummy0", "dummy1"].into_iter().enumerate() {
+            let bar_addr = MMIO_MEM_START + usize_to_u64(slot) * VIRTIO_PCI_BAR_SIZE;
+            let device_info =
+                &mmio_device_manager.get_device_info()[&(DeviceType::Virtio(123), id.to_string())];
+            assert_eq!(device_info.addr, bar_addr);
+            assert_eq!(device_info.len, VIRTIO_PCI_BAR_SIZE);
+            assert_eq!(device_info.irq, None);
+
+            let mut data = [0u8; 4];
+            assert!(
+                mmio_device_manager
+                    .bus
+                    .read(ecam_addr + (usize_to_u64(slot) << 15) + 0x10, &mut data)
+            );
+            assert_eq!(u64::from(u32::from_le_bytes(data)), bar_addr);
+        }
+    }
+}
@@ -11,6 +11,8 @@ use kvm_ioctls::VmFd;
 use log::{error, warn};
 use serde::{Deserialize, Serialize};
 use vm_allocator::AllocPolicy;
+#[cfg(target_arch = "aarch64")]
+use vmm_sys_util::eventfd::EventFd;
 
 use super::acpi::ACPIDeviceManager;
 use super::mmio::*;
@@ -24,6 +26,10 @@ use crate::devices::virtio::balloon::{Balloon, BalloonError};
 use crate::devices::virtio::block::BlockError;
 use crate::devices::virtio::block::device::Block;
 use crate::devices::virtio::block::persist::{BlockConstructorArgs, BlockState};
+use crate::devices::virtio::console::VirtioConsole;
+use crate::devices::virtio::console::persist::{
+    VirtioConsoleConstructorArgs, VirtioConsolePersistError as ConsoleError, VirtioConsoleState,
+};
 use crate::devices::virtio::device::VirtioDevice;
 use crate::devices::virtio::mmio::MmioTransport;
 use crate::devices::virtio::net::Net;
@@ -31,6 +37,10 @@ use crate::devices::virtio::net::persist::{
     NetConstructorArgs, NetPersistError as NetError, NetState,
 };
 use crate::devices::virtio::persist::{MmioTransportConstructorArgs, MmioTransportState};
+use crate::devices::virtio::pmem::Pmem;
+use crate::devices::virtio::pmem::persist::{
+    PmemConstructorArgs, PmemPersistError as PmemError, PmemState,
+};
 use crate::devices::virtio::rng::Entropy;
 use crate::devices::virtio::rng::persist::{
     EntropyConstructorArgs, EntropyPersistError as EntropyError, EntropyState,
@@ -41,7 +51,9 @@ use crate::devices::virtio::vsock::persist::{
 use crate::devices::virtio::vsock::{
     TYPE_VSOCK, Vsock, VsockError, VsockUnixBackend, VsockUnixBackendError,
 };
-use crate::devices::virtio::{TYPE_BALLOON, TYPE_BLOCK, TYPE_NET, TYPE_RNG};
+use crate::devices::virtio::{
+    TYPE_BALLOON, TYPE_BLOCK, TYPE_CONSOLE, TYPE_FS, TYPE_NET, TYPE_PMEM, TYPE_RNG,
+};
 use crate::mmds::data_store::MmdsVersion;
 use crate::resources::{ResourcesError, VmResources};
 use crate::snapshot::Persist;
@@ -72,6 +84,10 @@ pub enum DevicePersistError {
     MmdsConfig(#[from] MmdsConfigError),
     /// Entropy: {0}
     Entropy(#[from] EntropyError),
+    /// Console: {0}
+    Console(#[from] ConsoleError),
+    /// Pmem: {0}
+    Pmem(#[from] PmemError),
     /// Resource misconfiguration: {0}. Is the snapshot file corrupted?
     ResourcesError(#[from] ResourcesError),
 }
@@ -141,6 +157,32 @@ pub struct ConnectedEntropyState {
     pub device_info: MMIODeviceInfo,
 }
 
+/// Holds the state of a virtio-console device connected to the MMIO space.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ConnectedConsoleState {
+    /// Device identifier.
+    pub device_id: String,
+    /// Device state.
+    pub device_state: VirtioConsoleState,
+    /// Mmio transport state.
+    pub transport_state: MmioTransportState,
+    /// VmmResources.
+    pub device_info: MMIODeviceInfo,
+}
+
+/// Holds the state of a virtio-pmem device connected to the MMIO space.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ConnectedPmemState {
+    /// Device identifier.
+    pub device_id: String,
+    /// Device state.
+    pub device_state: PmemState,
+    /// Mmio transport state.
+    pub transport_state: MmioTransportState,
+    /// VmmResources.
+    pub device_info: MMIODeviceInfo,
+}
+
 /// Holds the state of a legacy device connected to the MMIO space.
 #[cfg(target_arch = "aarch64")]
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -194,6 +236,10 @@ pub struct DeviceStates {
     pub mmds_version: Option<MmdsVersionState>,
     /// Entropy device state.
     pub entropy_device: Option<ConnectedEntropyState>,
+    /// Virtio-console device state.
+    pub console_device: Option<ConnectedConsoleState>,
+    /// Virtio-pmem device states.
+    pub pmem_devices: Vec<ConnectedPmemState>,
 }
 
 /// A type used to extract the concrete `Arc<Mutex<T>>` for each of the device
@@ -205,6 +251,8 @@ pub enum SharedDeviceType {
     Balloon(Arc<Mutex<Balloon>>),
     Vsock(Arc<Mutex<Vsock<VsockUnixBackend>>>),
     Entropy(Arc<Mutex<Entropy>>),
+    Console(Arc<Mutex<VirtioConsole>>),
+    Pmem(Arc<Mutex<Pmem>>),
 }
 
 pub struct MMIODevManagerConstructorArgs<'a> {
@@ -215,6 +263,8 @@ pub struct MMIODevManagerConstructorArgs<'a> {
     pub vm_resources: &'a mut VmResources,
     pub instance_id: &'a str,
     pub restored_from_file: bool,
+    #[cfg(target_arch = "aarch64")]
+    pub pvpanic_evt: &'a EventFd,
 }
 impl fmt::Debug for MMIODevManagerConstructorArgs<'_> {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
@@ -290,10 +340,17 @@ impl<'a> Persist<'a> for MMIODeviceManager {
                 // No need to save BootTimer state.
                 return Ok(());
             }
+            if *devtype == crate::arch::DeviceType::Flash {
+                // MicroVMs booted from a firmware can't be snapshotted.
+                return Ok(());
+            }
 
             #[cfg(target_arch = "aarch64")]
             {
-                if *devtype == DeviceType::Serial || *devtype == DeviceType::Rtc {
+                if *devtype == DeviceType::Serial
+                    || *devtype == DeviceType::Rtc
+                    || *devtype == DeviceType::PvPanic
+                {
                     states.legacy_devices.push(ConnectedLegacyState {
                         type_: *devtype,
                         device_info: device_info.clone(),
@@ -388,6 +445,12 @@ impl<'a> Persist<'a> for MMIODeviceManager {
                         device_info: device_info.clone(),
                     });
                 }
+                TYPE_FS => {
+                    warn!(
+                        "Skipping vhost-user-fs device. VhostUserFs does not support snapshotting \
+                         yet"
+                    );
+                }
                 TYPE_RNG => {
                     let entropy = locked_device
                         .as_mut_any()
@@ -401,6 +464,29 @@ impl<'a> Persist<'a> for MMIODeviceManager {
                         device_info: device_info.clone(),
                     });
                 }
+                TYPE_CONSOLE => {
+                    let console = locked_device
+                        .as_mut_any()
+                        .downcast_mut::<VirtioConsole>()
+                        .unwrap();
+
+                    states.console_device = Some(ConnectedConsoleState {
+                        device_id: devid.clone(),
+                        device_state: console.save(),
+                        transport_state,
+                        device_info: device_info.clone(),
+                    });
+                }
+                TYPE_PMEM => {
+                    let pmem = locked_device.as_mut_any().downcast_mut::<Pmem>().unwrap();
+
+                    states.pmem_devices.push(ConnectedPmemState {
+                        device_id: devid.clone(),
+                        device_state: pmem.save(),
+                        transport_state,
+                        device_info: device_info.clone(),
+                    });
+                }
                 _ => unreachable!(),
             };
 
@@ -465,6 +551,28 @@ impl<'a> Persist<'a> for MMIODeviceManager {
                         Some(state.device_info.clone()),
                     )?;
                 }
+                if state.type_ == DeviceType::PvPanic {
+                    let pvpanic = crate::devices::legacy::PvPanicDevice::new(
+                        constructor_args.pvpanic_evt.try_clone().map_err(|err| {
+                            DevicePersistError::Legacy(crate::VmmError::EventFd(err))
+                        })?,
+                    );
+                    constructor_args
+                        .resource_allocator
+                        .allocate_mmio_memory(
+                            MMIO_LEN,
+                            MMIO_LEN,
+                            AllocPolicy::ExactMatch(state.device_info.addr),
+                        )
+                        .map_err(|e| {
+                            DevicePersistError::DeviceManager(super::mmio::MmioError::Allocator(e))
+                        })?;
+                    dev_manager.register_mmio_pvpanic(
+                        constructor_args.resource_allocator,
+                        pvpanic,
+                        Some(state.device_info.clone()),
+                    )?;
+                }
             }
         }
 
@@ -512,13 +620,23 @@ impl<'a> Persist<'a> for MMIODeviceManager {
         };
 
         if let Some(balloon_state) = &state.balloon_device {
-            let device = Arc::new(Mutex::new(Balloon::restore(
+            let mut balloon = Balloon::restore(
                 BalloonConstructorArgs {
                     mem: mem.clone(),
                     restored_from_file: constructor_args.restored_from_file,
                 },
                 &balloon_state.device_state,
-            )?));
+            )?;
+            // Memory restored from a file is not advised to use transparent huge pages.
+            balloon.set_transparent_huge_pages(
+                constructor_args
+                    .vm_resources
+                    .machine_config
+                    .huge_pages
+                    .is_transparent()
+                    && !constructor_args.restored_from_file,
+            );
+            let device = Arc::new(Mutex::new(balloon));
 
             constructor_args
                 .vm_resources
@@ -652,6 +770,52 @@ impl<'a> Persist<'a> for MMIODeviceManager {
             )?;
         }
 
+        if let Some(console_state) = &state.console_device {
+            let ctor_args = VirtioConsoleConstructorArgs::new(mem.clone());
+
+            let device = Arc::new(Mutex::new(VirtioConsole::restore(
+                ctor_args,
+                &console_state.device_state,
+            )?));
+
+            constructor_args
+                .vm_resources
+                .update_from_restored_device(SharedDeviceType::Console(device.clone()))?;
+
+            restore_helper(
+                device.clone(),
+                false,
+                device,
+                &console_state.device_id,
+                &console_state.transport_state,
+                &console_state.device_info,
+                constructor_args.event_manager,
+            )?;
+        }
+
+        for pmem_state in &state.pmem_devices {
+            let ctor_args = PmemConstructorArgs::new(mem.clone());
+
+            let device = Arc::new(Mutex::new(Pmem::restore(
+                ctor_args,
+                &pmem_state.device_state,
+            )?));
+
+            constructor_args
+                .vm_resources
+                .update_from_restored_device(SharedDeviceType::Pmem(device.clone()))?;
+
+            restore_helper(
+                device.clone(),
+                false,
+                device,
+                &pmem_state.device_id,
+                &pmem_state.transport_state,
+                &pmem_state.device_info,
+                constructor_args.event_manager,
+            )?;
+        }
+
         Ok(dev_manager)
     }
 }
@@ -812,6 +976,8 @@ mod tests {
             vm_resources,
             instance_id: "microvm-id",
             restored_from_file: true,
+            #[cfg(target_arch = "aarch64")]
+            pvpanic_evt: &vmm.pvpanic_evt,
         };
         let restored_dev_manager =
             MMIODeviceManager::restore(restore_args, &device_states).unwrap();
@@ -839,7 +1005,9 @@ mod tests {
   "boot-source": {{
     "kernel_image_path": "",
     "initrd_path": null,
-    "boot_args": null
+    "boot_args": null,
+    "firmware_path": null,
+    "firmware_vars_path": null
   }},
   "cpu-config": null,
   "logger": null,
@@ -12,11 +12,15 @@ use crate::arch;
 ///
 /// * GSIs for legacy x86_64 devices
 /// * GSIs for MMIO devicecs
+/// * GSIs for MSI interrupts of PCI devices (x86_64 only)
 /// * Memory allocations in the MMIO address space
 #[derive(Debug)]
 pub struct ResourceAllocator {
     // Allocator for device interrupt lines
     gsi_allocator: IdAllocator,
+    // Allocator for GSIs that are routed to MSI messages
+    #[cfg(target_arch = "x86_64")]
+    msi_gsi_allocator: IdAllocator,
     // Allocator for memory in the MMIO address space
     mmio_memory: AddressAllocator,
     // Memory allocator for system data
@@ -28,6 +32,11 @@ impl ResourceAllocator {
     pub fn new() -> Result<Self, vm_allocator::Error> {
         Ok(Self {
             gsi_allocator: IdAllocator::new(arch::IRQ_BASE, arch::IRQ_MAX)?,
+            #[cfg(target_arch = "x86_64")]
+            msi_gsi_allocator: IdAllocator::new(
+                arch::x86_64::layout::MSI_GSI_BASE,
+                arch::x86_64::layout::MSI_GSI_MAX,
+            )?,
             mmio_memory: AddressAllocator::new(arch::MMIO_MEM_START, arch::MMIO_MEM_SIZE)?,
             system_memory: AddressAllocator::new(arch::SYSTEM_MEM_START, arch::SYSTEM_MEM_SIZE)?,
         })
@@ -39,22 +48,17 @@ impl ResourceAllocator {
     ///
     /// * `gsi_count` - The number of GSIs to allocate
     pub fn allocate_gsi(&mut self, gsi_count: u32) -> Result<Vec<u32>, vm_allocator::Error> {
-        let mut gsis = Vec::with_capacity(gsi_count as usize);
-
-        for _ in 0..gsi_count {
-            match self.gsi_allocator.allocate_id() {
-                Ok(gsi) => gsis.push(gsi),
-                Err(err) => {
-                    // It is ok to unwrap here, we just allocated the GSI
-                    gsis.into_iter().for_each(|gsi| {
-                        self.gsi_allocator.free_id(gsi).unwrap();
-                    });
-                    return Err(err);
-                }
-            }
-        }
+        allocate_ids(&mut self.gsi_allocator, gsi_count)
+    }
 
-        Ok(gsis)
+    /// Allocate a number of GSIs for MSI interrupts
+    ///
+    /// # Arguments
+    ///
+    /// * `gsi_count` - The number of GSIs to allocate
+    #[cfg(target_arch = "x86_64")]
+    pub fn allocate_msi_gsi(&mut self, gsi_count: u32) -> Result<Vec<u32>, vm_allocator::Error> {
+        allocate_ids(&mut self.msi_gsi_allocator, gsi_count)
     }
 
     /// Allocate a memory range in MMIO address space
@@ -97,6 +101,26 @@ impl ResourceAllocator {
     }
 }
 
+// Allocates `count` IDs, or none of them if there are not enough free IDs.
+fn allocate_ids(allocator: &mut IdAllocator, count: u32) -> Result<Vec<u32>, vm_allocator::Error> {
+    let mut ids = Vec::with_capacity(count as usize);
+
+    for _ in 0..count {
+        match allocator.allocate_id() {
+            Ok(id) => ids.push(id),
+            Err(err) => {
+                // It is ok to unwrap here, we just allocated the ID
+                ids.into_iter().for_each(|id| {
+                    allocator.free_id(id).unwrap();
+                });
+                return Err(err);
+            }
+        }
+    }
+
+    Ok(ids)
+}
+
 #[cfg(test)]
 mod tests {
     use super::ResourceAllocator;
@@ -142,4 +166,23 @@ mod tests {
             assert_eq!(allocator.allocate_gsi(1), Ok(vec![i]));
         }
     }
+
+    #[cfg(target_arch = "x86_64")]
+    #[test]
+    fn test_allocate_msi_gsi() {
+        use crate::arch::x86_64::layout::{MSI_GSI_BASE, MSI_GSI_MAX};
+
+        let mut allocator = ResourceAllocator::new().unwrap();
+        // MSI GSIs don't overlap with the legacy ones.
+        assert_eq!(allocator.allocate_gsi(1), Ok(vec![arch::IRQ_BASE]));
+        assert_eq!(
+            allocator.allocate_msi_gsi(2),
+            Ok(vec![MSI_GSI_BASE, MSI_GSI_BASE + 1])
+        );
+        assert_eq!(
+            allocator.allocate_msi_gsi(MSI_GSI_MAX - MSI_GSI_BASE),
+            Err(vm_allocator::Error::ResourceNotAvailable)
+        );
+        assert_eq!(allocator.allocate_msi_gsi(1), Ok(vec![MSI_GSI_BASE + 2]));
+    }
 }
@@ -54,9 +54,16 @@ use event_manager::{EventOps, Events, MutEventSubscriber};
 
 #[cfg(target_arch = "aarch64")]
 use super::legacy::RTCDevice;
-use super::legacy::{I8042Device, SerialDevice};
+#[cfg(target_arch = "x86_64")]
+use super::legacy::WatchdogDevice;
+use super::legacy::{CfiFlashDevice, I8042Device, PvPanicDevice, SerialDevice};
+#[cfg(target_arch = "x86_64")]
+use super::pci::PciRootComplex;
 use super::pseudo::BootTimer;
+use super::virtio::device::VirtioDevice;
 use super::virtio::mmio::MmioTransport;
+#[cfg(target_arch = "x86_64")]
+use super::virtio::pci::VirtioPciTransport;
 
 #[derive(Debug)]
 pub enum BusDevice {
@@ -64,7 +71,15 @@ pub enum BusDevice {
     #[cfg(target_arch = "aarch64")]
     RTCDevice(RTCDevice),
     BootTimer(BootTimer),
+    CfiFlash(CfiFlashDevice),
     MmioTransport(MmioTransport),
+    PvPanic(PvPanicDevice),
+    #[cfg(target_arch = "x86_64")]
+    Watchdog(WatchdogDevice),
+    #[cfg(target_arch = "x86_64")]
+    PciRootComplex(PciRootComplex),
+    #[cfg(target_arch = "x86_64")]
+    VirtioPciTransport(VirtioPciTransport),
     Serial(SerialDevice<std::io::Stdin>),
     #[cfg(test)]
     Dummy(DummyDevice),
@@ -127,6 +142,20 @@ impl BusDevice {
             _ => None,
         }
     }
+    #[cfg(target_arch = "x86_64")]
+    pub fn virtio_pci_transport_ref(&self) -> Option<&VirtioPciTransport> {
+        match self {
+            Self::VirtioPciTransport(x) => Some(x),
+            _ => None,
+        }
+    }
+    #[cfg(target_arch = "x86_64")]
+    pub fn watchdog_ref(&self) -> Option<&WatchdogDevice> {
+        match self {
+            Self::Watchdog(x) => Some(x),
+            _ => None,
+        }
+    }
     pub fn serial_ref(&self) -> Option<&SerialDevice<std::io::Stdin>> {
         match self {
             Self::Serial(x) => Some(x),
@@ -159,6 +188,27 @@ impl BusDevice {
             _ => None,
         }
     }
+    #[cfg(target_arch = "x86_64")]
+    pub fn virtio_pci_transport_mut(&mut self) -> Option<&mut VirtioPciTransport> {
+        match self {
+            Self::VirtioPciTransport(x) => Some(x),
+            _ => None,
+        }
+    }
+    #[cfg(target_arch = "x86_64")]
+    pub fn pci_root_complex_mut(&mut self) -> Option<&mut PciRootComplex> {
+        match self {
+            Self::PciRootComplex(x) => Some(x),
+            _ => None,
+        }
+    }
+    #[cfg(target_arch = "x86_64")]
+    pub fn watchdog_mut(&mut self) -> Option<&mut WatchdogDevice> {
+        match self {
+            Self::Watchdog(x) => Some(x),
+            _ => None,
+        }
+    }
     pub fn serial_mut(&mut self) -> Option<&mut SerialDevice<std::io::Stdin>> {
         match self {
             Self::Serial(x) => Some(x),
@@ -166,13 +216,31 @@ impl BusDevice {
         }
     }
 
+    /// Returns the virtio device behind a virtio transport, whatever the transport is.
+    pub fn virtio_device(&self) -> Option<Arc<Mutex<dyn VirtioDevice>>> {
+        match self {
+            Self::MmioTransport(x) => Some(x.device()),
+            #[cfg(target_arch = "x86_64")]
+            Self::VirtioPciTransport(x) => Some(x.device()),
+            _ => None,
+        }
+    }
+
     pub fn read(&mut self, offset: u64, data: &mut [u8]) {
         match self {
             Self::I8042Device(x) => x.bus_read(offset, data),
             #[cfg(target_arch = "aarch64")]
             Self::RTCDevice(x) => x.bus_read(offset, data),
             Self::BootTimer(x) => x.bus_read(offset, data),
+            Self::CfiFlash(x) => x.bus_read(offset, data),
             Self::MmioTransport(x) => x.bus_read(offset, data),
+            Self::PvPanic(x) => x.bus_read(offset, data),
+            #[cfg(target_arch = "x86_64")]
+            Self::Watchdog(x) => x.bus_read(offset, data),
+            #[cfg(target_arch = "x86_64")]
+            Self::PciRootComplex(x) => x.bus_read(offset, data),
+            #[cfg(target_arch = "x86_64")]
+            Self::VirtioPciTransport(x) => x.bus_read(offset, data),
             Self::Serial(x) => x.bus_read(offset, data),
             #[cfg(test)]
             Self::Dummy(x) => x.bus_read(offset, data),
@@ -187,7 +255,15 @@ impl BusDevice {
             #[cfg(target_arch = "aarch64")]
             Self::RTCDevice(x) => x.bus_write(offset, data),
             Self::BootTimer(x) => x.bus_write(offset, data),
+            Self::CfiFlash(x) => x.bus_write(offset, data),
             Self::MmioTransport(x) => x.bus_write(offset, data),
+            Self::PvPanic(x) => x.bus_write(offset, data),
+            #[cfg(target_arch = "x86_64")]
+            Self::Watchdog(x) => x.bus_write(offset, data),
+            #[cfg(target_arch = "x86_64")]
+            Self::PciRootComplex(x) => x.bus_write(offset, data),
+            #[cfg(target_arch = "x86_64")]
+            Self::VirtioPciTransport(x) => x.bus_write(offset, data),
             Self::Serial(x) => x.bus_write(offset, data),
             #[cfg(test)]
             Self::Dummy(x) => x.bus_write(offset, data),
@@ -201,12 +277,16 @@ impl MutEventSubscriber for BusDevice {
     fn process(&mut self, event: Events, ops: &mut EventOps) {
         match self {
             Self::Serial(serial) => serial.process(event, ops),
+            #[cfg(target_arch = "x86_64")]
+            Self::Watchdog(watchdog) => watchdog.process(event, ops),
             _ => panic!(),
         }
     }
     fn init(&mut self, ops: &mut EventOps) {
         match self {
             Self::Serial(serial) => serial.init(ops),
+            #[cfg(target_arch = "x86_64")]
+            Self::Watchdog(watchdog) => watchdog.init(ops),
             _ => panic!(),
         }
     }
@@ -0,0 +1,491 @@
+// Copyright 2025 Amazon.com, Inc. or its affiliates. All Rights Reserved.
+// SPDX-License-Identifier: Apache-2.0
+
+//! Emulates a CFI NOR flash with the Intel command set, in which firmwares keep their variable
+//! store. See the "Common Flash Interface" and "Intel StrataFlash" specifications.
+
+use std::fs::File;
+use std::io;
+use std::os::unix::fs::FileExt;
+
+use crate::logger::{error, warn};
+use crate::utils::{u64_to_usize, usize_to_u64};
+
+/// Set in the status register when the device is ready.
+const STATUS_READY: u8 = 0x80;
+/// Set in the status register when an erase failed.
+const STATUS_ERASE_ERROR: u8 = 0x20;
+/// Set in the status register when a program failed.
+const STATUS_PROGRAM_ERROR: u8 = 0x10;
+
+const CMD_READ_ARRAY: u8 = 0xff;
+const CMD_PROGRAM: u8 = 0x10;
+const CMD_PROGRAM_ALT: u8 = 0x40;
+const CMD_BLOCK_ERASE: u8 = 0x20;
+const CMD_CLEAR_STATUS: u8 = 0x50;
+const CMD_LOCK_SETUP: u8 = 0x60;
+const CMD_READ_STATUS: u8 = 0x70;
+const CMD_READ_ID: u8 = 0x90;
+const CMD_READ_QUERY: u8 = 0x98;
+const CMD_WRITE_TO_BUFFER: u8 = 0xe8;
+const CMD_CONFIRM: u8 = 0xd0;
+const CMD_LOCK_BLOCK: u8 = 0x01;
+
+/// Manufacturer and device codes returned by the read identifier command.
+const MANUFACTURER_ID: u8 = 0x89;
+const DEVICE_ID: u8 = 0x18;
+
+/// Largest buffered program, as a power of two.
+const WRITE_BUFFER_SHIFT: u8 = 11;
+
+/// What reads from the device return.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ReadMode {
+    Array,
+    Status,
+    Identifier,
+    Query,
+}
+
+/// What the next write to the device is.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum WriteState {
+    /// A command.
+    Command,
+    /// The data of a program command.
+    Program,
+    /// The confirmation of a block erase.
+    Erase,
+    /// The confirmation of a block lock or unlock.
+    Lock,
+    /// The number of writes to the buffer, minus one.
+    BufferCount,
+    /// The data of a buffered program, with the given number of writes left.
+    BufferData(u16),
+    /// The confirmation of a buffered program.
+    BufferConfirm,
+}
+
+/// Errors associated with the CFI flash device.
+#[derive(Debug, thiserror::Error, displaydoc::Display)]
+pub enum CfiFlashError {
+    /// Cannot read the flash contents: {0}
+    Read(io::Error),
+    /// The size of the flash must be a non-zero multiple of the block size: {0}
+    InvalidSize(u64),
+}
+
+/// A CFI flash, backed by a file.
+///
+/// The contents are kept in memory, every program and erase is written through to the file.
+/// Blocks are never locked, and operations complete immediately.
+#[derive(Debug)]
+pub struct CfiFlashDevice {
+    file: File,
+    contents: Vec<u8>,
+    block_size: u64,
+    query_table: Vec<u8>,
+    read_mode: ReadMode,
+    write_state: WriteState,
+    status: u8,
+}
+
+impl CfiFlashDevice {
+    /// Constructs a flash device with the contents of `file`, erased by blocks of `block_size`.
+    pub fn new(file: File, block_size: u64) -> Result<Self, CfiFlashError> {
+        let size = file.metadata().map_err(CfiFlashError::Read)?.len();
+        if size == 0 || size % block_size != 0 {
+            return Err(CfiFlashError::InvalidSize(size));
+        }
+        let mut contents = vec![0; u64_to_usize(size)];
+        file.read_exact_at(&mut contents, 0)
+            .map_err(CfiFlashError::Read)?;
+
+        Ok(CfiFlashDevice {
+            file,
+            contents,
+            block_size,
+            query_table: query_table(size, block_size),
+            read_mode: ReadMode::Array,
+            write_state: WriteState::Command,
+            status: STATUS_READY,
+        })
+    }
+
+    /// Returns the size of the flash, in bytes.
+    pub fn size(&self) -> u64 {
+        usize_to_u64(self.contents.len())
+    }
+
+    pub fn bus_read(&mut self, offset: u64, data: &mut [u8]) {
+        // Outside of the array mode, every byte of the access returns the same value, as if the
+        // bank was made of byte-wide chips.
+        let width = usize_to_u64(data.len().max(1));
+        match self.read_mode {
+            ReadMode::Array => {
+                let start = u64_to_usize(offset);
+                match self.contents.get(start..start.saturating_add(data.len())) {
+                    Some(contents) => data.copy_from_slice(contents),
+                    None => data.fill(0xff),
+                }
+            }
+            ReadMode::Status => data.fill(self.status),
+            ReadMode::Identifier => {
+                let value = match (offset % self.block_size) / width {
+                    0 => MANUFACTURER_ID,
+                    1 => DEVICE_ID,
+                    // Neither the block nor the device is locked.
+                    _ => 0,
+                };
+                data.fill(value);
+            }
+            ReadMode::Query => {
+                let value = self
+                    .query_table
+                    .get(u64_to_usize(offset / width))
+                    .copied()
+                    .unwrap_or(0);
+                data.fill(value);
+            }
+        }
+    }
+
+    pub fn bus_write(&mut self, offset: u64, data: &[u8]) {
+        let Some(&value) = data.first() else {
+            return;
+        };
+
+        match self.write_state {
+            WriteState::Command => self.command(value),
+            WriteState::Program => {
+                self.program(offset, data);
+                self.write_state = WriteState::Command;
+            }
+            WriteState::Erase => {
+                if value == CMD_CONFIRM {
+                    self.erase(offset);
+                } else {
+                    self.invalid_sequence(value);
+                }
+                self.write_state = WriteState::Command;
+            }
+            WriteState::Lock => {
+                // There is nothing to lock or unlock.
+                if value != CMD_CONFIRM && value != CMD_LOCK_BLOCK {
+                    self.invalid_sequence(value);
+                }
+                self.write_state = WriteState::Command;
+            }
+            WriteState::BufferCount => {
+                self.write_state = WriteState::BufferData(u16::from(value) + 1);
+            }
+            WriteState::BufferData(left) => {
+                self.program(offset, data);
+                self.write_state = if left > 1 {
+                    WriteState::BufferData(left - 1)
+                } else {
+                    WriteState::BufferConfirm
+                };
+            }
+            WriteState::BufferConfirm => {
+                if value != CMD_CONFIRM {
+                    self.invalid_sequence(value);
+                }
+                self.write_state = WriteState::Command;
+            }
+        }
+    }
+
+    fn command(&mut self, command: u8) {
+        match command {
+            0x00 | 0xf0 | CMD_READ_ARRAY => self.read_mode = ReadMode::Array,
+            CMD_PROGRAM | CMD_PROGRAM_ALT => {
+                self.read_mode = ReadMode::Status;
+                self.write_state = WriteState::Program;
+            }
+            CMD_BLOCK_ERASE => {
+                self.read_mode = ReadMode::Status;
+                self.write_state = WriteState::Erase;
+            }
+            CMD_CLEAR_STATUS => {
+                self.status = 0;
+                self.read_mode = ReadMode::Array;
+            }
+            CMD_LOCK_SETUP => {
+                self.read_mode = ReadMode::Status;
+                self.write_state = WriteState::Lock;
+            }
+            CMD_READ_STATUS => self.read_mode = ReadMode::Status,
+            CMD_READ_ID => self.read_mode = ReadMode::Identifier,
+            CMD_READ_QUERY => self.read_mode = ReadMode::Query,
+            CMD_WRITE_TO_BUFFER => {
+                // The buffer is always available.
+                self.status |= STATUS_READY;
+                self.read_mode = ReadMode::Status;
+                self.write_state = WriteState::BufferCount;
+            }
+            _ => {
+                warn!("cfi_flash: unsupported command {command:#x}");
+                self.read_mode = ReadMode::Array;
+            }
+        }
+    }
+
+    fn program(&mut self, offset: u64, data: &[u8]) {
+        self.status |= STATUS_READY;
+        let start = u64_to_usize(offset);
+        let Some(contents) = self
+            .contents
+            .get_mut(start..start.saturating_add(data.len()))
+        else {
+            self.status |= STATUS_PROGRAM_ERROR;
+            return;
+        };
+        contents.copy_from_slice(data);
+        if let Err(err) = self.file.write_all_at(contents, offset) {
+            error!("cfi_flash: cannot write to the backing file: {err}");
+            self.status |= STATUS_PROGRAM_ERROR;
+        }
+    }
+
+    fn erase(&mut self, offset: u64) {
+        self.status |= STATUS_READY;
+        let start = offset - offset % self.block_size;
+        let range = u64_to_usize(start)..u64_to_usize(start + self.block_size);
+        let Some(block) = self.contents.get_mut(range) else {
+            self.status |= STATUS_ERASE_ERROR;
+            return;
+        };
+        block.fill(0xff);
+        if let Err(err) = self.file.write_all_at(block, start) {
+            error!("cfi_flash: cannot write to the backing file: {err}");
+            self.status |= STATUS_ERASE_ERROR;
+        }
+    }
+
+    fn invalid_sequence(&mut self, value: u8) {
+        warn!("cfi_flash: invalid command sequence {value:#x}");
+        self.status |= STATUS_ERASE_ERROR | STATUS_PROGRAM_ERROR;
+    }
+}
+
+/// Builds the table returned by the read query command, indexed by the word offset.
+fn query_table(size: u64, block_size: u64) -> Vec<u8> {
+    let mut table = vec![0; 0x40];
+    table[0x10..0x13].copy_from_slice(b"QRY");
+    // Intel command set, with its extended table at 0x31.
+    table[0x13] = 0x01;
+    table[0x15] = 0x31;
+    // Supply voltages.
+    table[0x1b] = 0x45;
+    table[0x1c] = 0x55;
+    // Typical and maximum timeouts of the program, buffered program and block erase.
+    table[0x1f] = 0x07;
+    table[0x20] = 0x07;
+    table[0x21] = 0x0a;
+    table[0x23] = 0x04;
+    table[0x24] = 0x04;
+    table[0x25] = 0x04;
+    #[allow(clippy::cast_possible_truncation)] // the log2 of a u64 fits in a u8
+    {
+        table[0x27] = size.next_power_of_two().trailing_zeros() as u8;
+    }
+    // x8 and x16 asynchronous interface.
+    table[0x28] = 0x02;
+    table[0x2a] = WRITE_BUFFER_SHIFT;
+    // A single region of uniform blocks.
+    table[0x2c] = 0x01;
+    let blocks = u16::try_from(size / block_size - 1).unwrap_or(u16::MAX);
+    table[0x2d..0x2f].copy_from_slice(&blocks.to_le_bytes());
+    let block_units = u16::try_from(block_size / 256).unwrap_or(u16::MAX);
+    table[0x2f..0x31].copy_from_slice(&block_units.to_le_bytes());
+    table[0x31..0x36].copy_from_slice(b"PRI10");
+    table
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Write;
+
+    use vmm_sys_util::tempfile::TempFile;
+
+    use super::*;
+
+    const BLOCK_SIZE: u64 = 0x1000;
+
+    fn flash(blocks: u64) -> (TempFile, CfiFlashDevice) {
+        let file = TempFile::new().unwrap();
+        file.as_file()
+            .write_all(&vec![0xaa; u64_to_usize(blocks * BLOCK_SIZE)])
+            .unwrap();
+        let device = CfiFlashDevice::new(file.as_file().try_clone().unwrap(), BLOCK_SIZE).unwrap();
+        (file, device)
+    }
+
+    fn read(device: &mut CfiFlashDevice, offset: u64) -> u8 {
+        let mut data = [0];
+        device.bus_read(offset, &mut data);
+        data[0]
+    }
+
+    fn read_file(file: &TempFile, offset: u64) -> u8 {
+        let mut data = [0];
+        file.as_file().read_exact_at(&mut data, offset).unwrap();
+        data[0]
+    }
+
+    #[test]
+    fn test_invalid_size() {
+        let file = TempFile::new().unwrap();
+        assert!(matches!(
+            CfiFlashDevice::new(file.as_file().try_clone().unwrap(), BLOCK_SIZE),
+            Err(CfiFlashError::InvalidSize(0))
+        ));
+
+        file.as_file().write_all(&[0; 0x1800]).unwrap();
+        assert!(matches!(
+            CfiFlashDevice::new(file.as_file().try_clone().unwrap(), BLOCK_SIZE),
+            Err(CfiFlashError::InvalidSize(0x1800))
+        ));
+    }
+
+    #[test]
+    fn test_read_array() {
+        let (_file, mut device) = flash(2);
+        assert_eq!(device.size(), 2 * BLOCK_SIZE);
+
+        let mut data = [0; 4];
+        device.bus_read(0x10, &mut data);
+        assert_eq!(data, [0xaa; 4]);
+        // Past the end of the flash.
+        device.bus_read(2 * BLOCK_SIZE, &mut data);
+        assert_eq!(data, [0xff; 4]);
+    }
+
+    #[test]
+    fn test_detection() {
+        // The sequence through which EDK2 tells a flash from plain memory.
+        let (file, mut device) = flash(1);
+        device.bus_write(0, &[CMD_CLEAR_STATUS]);
+        assert_eq!(read(&mut device, 0), 0xaa);
+        device.bus_write(0, &[CMD_READ_STATUS]);
+        assert_eq!(read(&mut device, 0), 0);
+        device.bus_write(0, &[CMD_PROGRAM]);
+        device.bus_write(0, &[0xaa]);
+        device.bus_write(0, &[CMD_READ_STATUS]);
+        assert_eq!(read(&mut device, 0), STATUS_READY);
+        device.bus_write(0, &[CMD_READ_ARRAY]);
+        assert_eq!(read(&mut device, 0), 0xaa);
+        assert_eq!(read_file(&file, 0), 0xaa);
+    }
+
+    #[test]
+    fn test_program_and_erase() {
+        let (file, mut device) = flash(2);
+
+        device.bus_write(BLOCK_SIZE + 1, &[CMD_PROGRAM_ALT]);
+        device.bus_write(BLOCK_SIZE + 1, &[0x0f, 0xf0]);
+        assert_eq!(read(&mut device, 0), STATUS_READY);
+        device.bus_write(0, &[CMD_READ_ARRAY]);
+        assert_eq!(read(&mut device, BLOCK_SIZE + 1), 0x0f);
+        assert_eq!(read(&mut device, BLOCK_SIZE + 2), 0xf0);
+        assert_eq!(read_file(&file, BLOCK_SIZE + 1), 0x0f);
+        assert_eq!(read_file(&file, BLOCK_SIZE + 2), 0xf0);
+
+        device.bus_write(BLOCK_SIZE + 0x10, &[CMD_BLOCK_ERASE]);
+        device.bus_write(BLOCK_SIZE + 0x10, &[CMD_CONFIRM]);
+        assert_eq!(read(&mut device, 0), STATUS_READY);
+        device.bus_write(0, &[CMD_READ_ARRAY]);
+        // Only the addressed block is erased.
+        assert_eq!(read(&mut device, BLOCK_SIZE - 1), 0xaa);
+        assert_eq!(read(&mut device, BLOCK_SIZE + 1), 0xff);
+        assert_eq!(read(&mut device, 2 * BLOCK_SIZE - 1), 0xff);
+        assert_eq!(read_file(&file, BLOCK_SIZE - 1), 0xaa);
+        assert_eq!(read_file(&file, BLOCK_SIZE + 1), 0xff);
+
+        // A program past the end of the flash fails.
+        device.bus_write(0, &[CMD_PROGRAM]);
+        device.bus_write(2 * BLOCK_SIZE, &[0]);
+        assert_eq!(read(&mut device, 0), STATUS_READY | STATUS_PROGRAM_ERROR);
+        device.bus_write(0, &[CMD_CLEAR_STATUS]);
+        device.bus_write(0, &[CMD_READ_STATUS]);
+        assert_eq!(read(&mut device, 0), 0);
+    }
+
+    #[test]
+    fn test_invalid_erase() {
+        let (file, mut device) = flash(1);
+        device.bus_write(0, &[CMD_BLOCK_ERASE]);
+        device.bus_write(0, &[CMD_READ_ARRAY]);
+        assert_eq!(
+            read(&mut device, 0),
+            STATUS_READY | STATUS_ERASE_ERROR | STATUS_PROGRAM_ERROR
+        );
+        assert_eq!(read_file(&file, 0), 0xaa);
+    }
+
+    #[test]
+    fn test_buffered_program() {
+        let (file, mut device) = flash(1);
+        device.bus_write(0, &[CMD_WRITE_TO_BUFFER]);
+        assert_eq!(read(&mut device, 0), STATUS_READY);
+        device.bus_write(0, &[1]);
+        device.bus_write(0x20, &[0x00, 0x00, 0x00, 0x00]);
+        device.bus_write(0x24, &[0x0f, 0x0f, 0x0f, 0x0f]);
+        device.bus_write(0, &[CMD_CONFIRM]);
+        assert_eq!(read(&mut device, 0), STATUS_READY);
+        device.bus_write(0, &[CMD_READ_ARRAY]);
+
+        let mut data = [0; 8];
+        device.bus_read(0x20, &mut data);
+        assert_eq!(data, [0, 0, 0, 0, 0x0f, 0x0f, 0x0f, 0x0f]);
+        file.as_file().read_exact_at(&mut data, 0x20).unwrap();
+        assert_eq!(data, [0, 0, 0, 0, 0x0f, 0x0f, 0x0f, 0x0f]);
+    }
+
+    #[test]
+    fn test_identifier_and_query() {
+        let (_file, mut device) = flash(4);
+
+        device.bus_write(0, &[CMD_READ_ID]);
+        let mut data = [0; 4];
+        device.bus_read(0, &mut data);
+        assert_eq!(data, [MANUFACTURER_ID; 4]);
+        device.bus_read(4, &mut data);
+        assert_eq!(data, [DEVICE_ID; 4]);
+        device.bus_read(8, &mut data);
+        assert_eq!(data, [0; 4]);
+
+        device.bus_write(0, &[CMD_READ_QUERY]);
+        let query: Vec<u8> = (0x10..0x31)
+            .map(|index| {
+                device.bus_read(index * 4, &mut data);
+                assert!(data.iter().all(|byte| *byte == data[0]));
+                data[0]
+            })
+            .collect();
+        assert_eq!(&query[..3], b"QRY");
+        // 16 KiB in 4 blocks of 4 KiB.
+        assert_eq!(query[0x27 - 0x10], 14);
+        assert_eq!(query[0x2d - 0x10..], [3, 0, 0x10, 0]);
+
+        device.bus_write(0, &[CMD_READ_ARRAY]);
+        assert_eq!(read(&mut device, 0), 0xaa);
+    }
+
+    #[test]
+    fn test_lock() {
+        let (_file, mut device) = flash(1);
+        device.bus_write(0, &[CMD_LOCK_SETUP]);
+        device.bus_write(0, &[CMD_LOCK_BLOCK]);
+        device.bus_write(0, &[CMD_LOCK_SETUP]);
+        device.bus_write(0, &[CMD_CONFIRM]);
+        assert_eq!(read(&mut device, 0), STATUS_READY);
+
+        // Blocks stay writable.
+        device.bus_write(0, &[CMD_PROGRAM]);
+        device.bus_write(0, &[0]);
+        device.bus_write(0, &[CMD_READ_ARRAY]);
+        assert_eq!(read(&mut device, 0), 0);
+    }
+}
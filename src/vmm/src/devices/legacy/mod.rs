@@ -6,10 +6,14 @@
 // found in the THIRD-PARTY file.
 
 //! Implements legacy devices (UART, RTC etc).
+pub mod cfi_flash;
 mod i8042;
+pub mod pvpanic;
 #[cfg(target_arch = "aarch64")]
 pub mod rtc_pl031;
 pub mod serial;
+#[cfg(target_arch = "x86_64")]
+pub mod watchdog;
 
 use std::io;
 use std::ops::Deref;
@@ -19,12 +23,16 @@ use serde::ser::SerializeMap;
 use vm_superio::Trigger;
 use vmm_sys_util::eventfd::EventFd;
 
+pub use self::cfi_flash::{CfiFlashDevice, CfiFlashError};
 pub use self::i8042::{I8042Device, I8042Error as I8042DeviceError};
+pub use self::pvpanic::PvPanicDevice;
 #[cfg(target_arch = "aarch64")]
 pub use self::rtc_pl031::RTCDevice;
 pub use self::serial::{
     IER_RDA_BIT, IER_RDA_OFFSET, SerialDevice, SerialEventsWrapper, SerialWrapper,
 };
+#[cfg(target_arch = "x86_64")]
+pub use self::watchdog::WatchdogDevice;
 
 /// Wrapper for implementing the trigger functionality for `EventFd`.
 ///
@@ -0,0 +1,100 @@
+// Copyright 2025 Amazon.com, Inc. or its affiliates. All Rights Reserved.
+// SPDX-License-Identifier: Apache-2.0
+
+//! Emulates the pvpanic device, through which the guest kernel reports its panics.
+//! See <https://www.qemu.org/docs/master/specs/pvpanic.html>.
+
+use vmm_sys_util::eventfd::EventFd;
+
+use crate::logger::{IncMetric, METRICS, error, info, warn};
+
+/// Event written by the guest when its kernel panicked.
+pub const PVPANIC_PANICKED: u8 = 1 << 0;
+/// Event written by the guest when its kernel panicked, and a crash kernel is going to
+/// take over.
+pub const PVPANIC_CRASH_LOADED: u8 = 1 << 1;
+
+/// I/O port of the pvpanic device.
+#[cfg(target_arch = "x86_64")]
+pub const PVPANIC_PORT: u64 = 0x505;
+
+/// A pvpanic device, which signals the given event when the guest kernel panics.
+#[derive(Debug)]
+pub struct PvPanicDevice {
+    /// Event through which the panics are reported to the Vmm.
+    panic_evt: EventFd,
+}
+
+impl PvPanicDevice {
+    /// Constructs a pvpanic device that signals `panic_evt` when the guest kernel panics.
+    pub fn new(panic_evt: EventFd) -> Self {
+        PvPanicDevice { panic_evt }
+    }
+
+    pub fn bus_read(&mut self, offset: u64, data: &mut [u8]) {
+        // The single register of the device advertises the events it supports.
+        if offset != 0 || data.is_empty() {
+            warn!(
+                "pvpanic: invalid read at offset {offset} of {} bytes",
+                data.len()
+            );
+            return;
+        }
+        data.fill(0);
+        data[0] = PVPANIC_PANICKED | PVPANIC_CRASH_LOADED;
+    }
+
+    pub fn bus_write(&mut self, offset: u64, data: &[u8]) {
+        if offset != 0 || data.is_empty() {
+            warn!(
+                "pvpanic: invalid write at offset {offset} of {} bytes",
+                data.len()
+            );
+            return;
+        }
+
+        let event = data[0];
+        if event & PVPANIC_CRASH_LOADED != 0 {
+            // The crash kernel handles the panic, so there is nothing to do on the host side.
+            info!("pvpanic: the guest kernel panicked, and a crash kernel was loaded");
+            METRICS.vmm.guest_panic_count.inc();
+        } else if event & PVPANIC_PANICKED != 0 {
+            info!("pvpanic: the guest kernel panicked");
+            METRICS.vmm.guest_panic_count.inc();
+            if let Err(err) = self.panic_evt.write(1) {
+                error!("pvpanic: failed to signal the guest panic: {err}");
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pvpanic() {
+        let panic_evt = EventFd::new(libc::EFD_NONBLOCK).unwrap();
+        let mut pvpanic = PvPanicDevice::new(panic_evt.try_clone().unwrap());
+
+        let mut data = [0xff_u8; 1];
+        pvpanic.bus_read(0, &mut data);
+        assert_eq!(data[0], PVPANIC_PANICKED | PVPANIC_CRASH_LOADED);
+        // Invalid accesses are ignored.
+        let mut data = [0xff_u8; 1];
+        pvpanic.bus_read(1, &mut data);
+        assert_eq!(data[0], 0xff);
+        pvpanic.bus_write(1, &[PVPANIC_PANICKED]);
+        panic_evt.read().unwrap_err();
+
+        let panics = METRICS.vmm.guest_panic_count.count();
+        // Panics handled by a crash kernel are only accounted.
+        pvpanic.bus_write(0, &[PVPANIC_CRASH_LOADED]);
+        panic_evt.read().unwrap_err();
+        assert_eq!(METRICS.vmm.guest_panic_count.count(), panics + 1);
+
+        pvpanic.bus_write(0, &[PVPANIC_PANICKED]);
+        assert_eq!(panic_evt.read().unwrap(), 1);
+        assert_eq!(METRICS.vmm.guest_panic_count.count(), panics + 2);
+    }
+}
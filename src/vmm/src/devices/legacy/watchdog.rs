@@ -0,0 +1,336 @@
+// Copyright 2025 Amazon.com, Inc. or its affiliates. All Rights Reserved.
+// SPDX-License-Identifier: Apache-2.0
+
+//! Emulates a watchdog timer on I/O ports, described to the guest by an ACPI WDAT table.
+//!
+//! The guest restarts the countdown of the watchdog periodically. When the countdown expires,
+//! the device signals an event to the Vmm, which applies the action configured on the host.
+
+use std::fmt::Debug;
+use std::os::unix::io::AsRawFd;
+use std::time::{Duration, Instant};
+
+use event_manager::{EventOps, Events, MutEventSubscriber};
+use serde::{Deserialize, Serialize};
+use timerfd::{ClockId, SetTimeFlags, TimerFd, TimerState};
+use vmm_sys_util::epoll::EventSet;
+use vmm_sys_util::eventfd::EventFd;
+
+use crate::logger::{IncMetric, METRICS, error, info, warn};
+use crate::snapshot::Persist;
+
+/// First I/O port of the watchdog registers.
+pub const WATCHDOG_PORT: u64 = 0x530;
+/// Number of I/O ports used by the watchdog registers.
+pub const WATCHDOG_PORT_LEN: u64 = 0x10;
+
+/// Control register. Bit 0 is set while the watchdog is running.
+pub const WATCHDOG_REG_CONTROL: u64 = 0x0;
+/// Countdown the watchdog is restarted with, in units of `WATCHDOG_TIMER_PERIOD_MS`.
+pub const WATCHDOG_REG_COUNTDOWN: u64 = 0x4;
+/// Remaining countdown of the watchdog, in units of `WATCHDOG_TIMER_PERIOD_MS`. Read-only.
+pub const WATCHDOG_REG_CURRENT_COUNTDOWN: u64 = 0x8;
+/// Writing bit 0 restarts the countdown. Write-only.
+pub const WATCHDOG_REG_REFRESH: u64 = 0xc;
+
+/// Bit of the control register set while the watchdog is running.
+pub const WATCHDOG_CONTROL_RUNNING: u32 = 1 << 0;
+/// Bit of the refresh register restarting the countdown.
+pub const WATCHDOG_REFRESH: u32 = 1 << 0;
+
+/// Duration of a countdown unit, in milliseconds.
+pub const WATCHDOG_TIMER_PERIOD_MS: u32 = 1000;
+/// Minimum countdown of the watchdog.
+pub const WATCHDOG_MIN_COUNT: u32 = 1;
+/// Maximum countdown of the watchdog.
+pub const WATCHDOG_MAX_COUNT: u32 = 3600;
+// Countdown used until the guest sets one.
+const WATCHDOG_DEFAULT_COUNT: u32 = 30;
+
+/// The state of the watchdog, saved in snapshots.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct WatchdogState {
+    /// Whether the watchdog is running.
+    pub running: bool,
+    /// Countdown the watchdog is restarted with.
+    pub countdown: u32,
+}
+
+/// A watchdog timer, which signals the given event when the guest stops refreshing it.
+pub struct WatchdogDevice {
+    running: bool,
+    countdown: u32,
+    // When the countdown expires, if it is running.
+    deadline: Option<Instant>,
+    timer_fd: TimerFd,
+    // Event through which the expiries are reported to the Vmm.
+    expiry_evt: EventFd,
+}
+
+impl Debug for WatchdogDevice {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("WatchdogDevice")
+            .field("running", &self.running)
+            .field("countdown", &self.countdown)
+            .field("deadline", &self.deadline)
+            .finish()
+    }
+}
+
+impl WatchdogDevice {
+    /// Constructs a stopped watchdog that signals `expiry_evt` when it expires.
+    pub fn new(expiry_evt: EventFd) -> Result<Self, std::io::Error> {
+        Ok(WatchdogDevice {
+            running: false,
+            countdown: WATCHDOG_DEFAULT_COUNT,
+            deadline: None,
+            timer_fd: TimerFd::new_custom(ClockId::Monotonic, true, true)?,
+            expiry_evt,
+        })
+    }
+
+    fn countdown_duration(&self) -> Duration {
+        Duration::from_millis(u64::from(self.countdown) * u64::from(WATCHDOG_TIMER_PERIOD_MS))
+    }
+
+    // Restarts the countdown.
+    fn arm(&mut self) {
+        let countdown = self.countdown_duration();
+        self.deadline = Some(Instant::now() + countdown);
+        self.timer_fd
+            .set_state(TimerState::Oneshot(countdown), SetTimeFlags::Default);
+    }
+
+    fn disarm(&mut self) {
+        self.deadline = None;
+        self.timer_fd
+            .set_state(TimerState::Disarmed, SetTimeFlags::Default);
+    }
+
+    /// Stops the countdown while the microVM is paused.
+    pub fn pause(&mut self) {
+        if self.running {
+            self.disarm();
+        }
+    }
+
+    /// Restarts the countdown when the microVM is resumed, giving the guest a full countdown to
+    /// refresh the watchdog.
+    pub fn resume(&mut self) {
+        if self.running {
+            self.arm();
+        }
+    }
+
+    fn current_countdown(&self) -> u32 {
+        let remaining = self
+            .deadline
+            .map(|deadline| deadline.saturating_duration_since(Instant::now()))
+            .unwrap_or_default();
+        u32::try_from(
+            remaining
+                .as_millis()
+                .div_ceil(u128::from(WATCHDOG_TIMER_PERIOD_MS)),
+        )
+        .unwrap_or(u32::MAX)
+    }
+
+    pub fn bus_read(&mut self, offset: u64, data: &mut [u8]) {
+        let value = match (offset, data.len()) {
+            (WATCHDOG_REG_CONTROL, 4) if self.running => WATCHDOG_CONTROL_RUNNING,
+            (WATCHDOG_REG_CONTROL, 4) => 0,
+            (WATCHDOG_REG_COUNTDOWN, 4) => self.countdown,
+            (WATCHDOG_REG_CURRENT_COUNTDOWN, 4) => self.current_countdown(),
+            (WATCHDOG_REG_REFRESH, 4) => 0,
+            _ => {
+                warn!(
+                    "watchdog: invalid read at offset {offset} of {} bytes",
+                    data.len()
+                );
+                return;
+            }
+        };
+        data.copy_from_slice(&value.to_le_bytes());
+    }
+
+    pub fn bus_write(&mut self, offset: u64, data: &[u8]) {
+        let Ok(value) = <[u8; 4]>::try_from(data).map(u32::from_le_bytes) else {
+            warn!(
+                "watchdog: invalid write at offset {offset} of {} bytes",
+                data.len()
+            );
+            return;
+        };
+
+        match offset {
+            WATCHDOG_REG_CONTROL => {
+                let running = value & WATCHDOG_CONTROL_RUNNING != 0;
+                if running && !self.running {
+                    info!(
+                        "watchdog: started with a countdown of {} ms",
+                        self.countdown_duration().as_millis()
+                    );
+                    self.arm();
+                } else if !running && self.running {
+                    info!("watchdog: stopped");
+                    self.disarm();
+                }
+                self.running = running;
+            }
+            // The new countdown is used from the next refresh.
+            WATCHDOG_REG_COUNTDOWN => {
+                self.countdown = value.clamp(WATCHDOG_MIN_COUNT, WATCHDOG_MAX_COUNT);
+            }
+            WATCHDOG_REG_REFRESH => {
+                if value & WATCHDOG_REFRESH != 0 && self.running {
+                    self.arm();
+                }
+            }
+            _ => warn!("watchdog: invalid write at offset {offset}"),
+        }
+    }
+}
+
+impl MutEventSubscriber for WatchdogDevice {
+    fn process(&mut self, event: Events, _: &mut EventOps) {
+        if event.fd() != self.timer_fd.as_raw_fd() || event.event_set() != EventSet::IN {
+            error!("watchdog: spurious event");
+            return;
+        }
+
+        // The countdown may have been restarted or stopped since the timer expired.
+        if self.timer_fd.read() == 0 {
+            return;
+        }
+        self.deadline = None;
+        METRICS.vmm.watchdog_expiry_count.inc();
+        error!(
+            "watchdog: the guest did not refresh the watchdog for {} ms",
+            self.countdown_duration().as_millis()
+        );
+        if let Err(err) = self.expiry_evt.write(1) {
+            error!("watchdog: failed to signal the expiry: {err}");
+        }
+    }
+
+    fn init(&mut self, ops: &mut EventOps) {
+        if let Err(err) = ops.add(Events::new(&self.timer_fd, EventSet::IN)) {
+            error!("Failed to register watchdog timer fd: {}", err);
+        }
+    }
+}
+
+impl Persist<'_> for WatchdogDevice {
+    type State = WatchdogState;
+    type ConstructorArgs = EventFd;
+    type Error = std::io::Error;
+
+    fn save(&self) -> Self::State {
+        WatchdogState {
+            running: self.running,
+            countdown: self.countdown,
+        }
+    }
+
+    fn restore(
+        expiry_evt: Self::ConstructorArgs,
+        state: &Self::State,
+    ) -> Result<Self, Self::Error> {
+        // The countdown is restarted when the microVM is resumed.
+        let mut watchdog = WatchdogDevice::new(expiry_evt)?;
+        watchdog.countdown = state.countdown;
+        watchdog.running = state.running;
+        Ok(watchdog)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn read_reg(watchdog: &mut WatchdogDevice, offset: u64) -> u32 {
+        let mut data = [0xff_u8; 4];
+        watchdog.bus_read(offset, &mut data);
+        u32::from_le_bytes(data)
+    }
+
+    #[test]
+    fn test_watchdog_registers() {
+        let expiry_evt = EventFd::new(libc::EFD_NONBLOCK).unwrap();
+        let mut watchdog = WatchdogDevice::new(expiry_evt).unwrap();
+
+        assert_eq!(read_reg(&mut watchdog, WATCHDOG_REG_CONTROL), 0);
+        assert_eq!(
+            read_reg(&mut watchdog, WATCHDOG_REG_COUNTDOWN),
+            WATCHDOG_DEFAULT_COUNT
+        );
+        assert_eq!(read_reg(&mut watchdog, WATCHDOG_REG_CURRENT_COUNTDOWN), 0);
+        // Invalid accesses are ignored.
+        let mut data = [0xff_u8; 2];
+        watchdog.bus_read(WATCHDOG_REG_CONTROL, &mut data);
+        assert_eq!(data, [0xff; 2]);
+        watchdog.bus_write(WATCHDOG_REG_CONTROL, &[1]);
+        assert!(!watchdog.running);
+
+        // The countdown is clamped to the supported range.
+        watchdog.bus_write(WATCHDOG_REG_COUNTDOWN, &0u32.to_le_bytes());
+        assert_eq!(
+            read_reg(&mut watchdog, WATCHDOG_REG_COUNTDOWN),
+            WATCHDOG_MIN_COUNT
+        );
+        watchdog.bus_write(WATCHDOG_REG_COUNTDOWN, &u32::MAX.to_le_bytes());
+        assert_eq!(
+            read_reg(&mut watchdog, WATCHDOG_REG_COUNTDOWN),
+            WATCHDOG_MAX_COUNT
+        );
+        watchdog.bus_write(WATCHDOG_REG_COUNTDOWN, &10u32.to_le_bytes());
+
+        // Refreshing a stopped watchdog does nothing.
+        watchdog.bus_write(WATCHDOG_REG_REFRESH, &WATCHDOG_REFRESH.to_le_bytes());
+        assert!(watchdog.deadline.is_none());
+
+        watchdog.bus_write(
+            WATCHDOG_REG_CONTROL,
+            &WATCHDOG_CONTROL_RUNNING.to_le_bytes(),
+        );
+        assert_eq!(
+            read_reg(&mut watchdog, WATCHDOG_REG_CONTROL),
+            WATCHDOG_CONTROL_RUNNING
+        );
+        assert_eq!(read_reg(&mut watchdog, WATCHDOG_REG_CURRENT_COUNTDOWN), 10);
+        watchdog.bus_write(WATCHDOG_REG_REFRESH, &WATCHDOG_REFRESH.to_le_bytes());
+        assert!(watchdog.deadline.is_some());
+
+        watchdog.bus_write(WATCHDOG_REG_CONTROL, &0u32.to_le_bytes());
+        assert_eq!(read_reg(&mut watchdog, WATCHDOG_REG_CONTROL), 0);
+        assert!(watchdog.deadline.is_none());
+    }
+
+    #[test]
+    fn test_watchdog_persistence() {
+        let mut watchdog = WatchdogDevice::new(EventFd::new(libc::EFD_NONBLOCK).unwrap()).unwrap();
+        watchdog.bus_write(WATCHDOG_REG_COUNTDOWN, &20u32.to_le_bytes());
+        watchdog.bus_write(
+            WATCHDOG_REG_CONTROL,
+            &WATCHDOG_CONTROL_RUNNING.to_le_bytes(),
+        );
+
+        let state = watchdog.save();
+        assert_eq!(
+            state,
+            WatchdogState {
+                running: true,
+                countdown: 20
+            }
+        );
+        let mut restored =
+            WatchdogDevice::restore(EventFd::new(libc::EFD_NONBLOCK).unwrap(), &state).unwrap();
+        assert!(restored.running);
+        assert_eq!(read_reg(&mut restored, WATCHDOG_REG_CURRENT_COUNTDOWN), 0);
+        restored.resume();
+        assert_eq!(read_reg(&mut restored, WATCHDOG_REG_CURRENT_COUNTDOWN), 20);
+        restored.pause();
+        assert_eq!(read_reg(&mut restored, WATCHDOG_REG_CURRENT_COUNTDOWN), 0);
+        assert!(restored.running);
+    }
+}
@@ -12,6 +12,8 @@ use std::io;
 pub mod acpi;
 pub mod bus;
 pub mod legacy;
+#[cfg(target_arch = "x86_64")]
+pub mod pci;
 pub mod pseudo;
 pub mod virtio;
 
@@ -22,6 +24,7 @@ use crate::devices::virtio::net::metrics::NetDeviceMetrics;
 use crate::devices::virtio::queue::QueueError;
 use crate::devices::virtio::vsock::VsockError;
 use crate::logger::IncMetric;
+use crate::notifications::{NOTIFIER, Notification};
 
 // Function used for reporting error in terms of logging
 // but also in terms of metrics of net event fails.
@@ -30,6 +33,10 @@ use crate::logger::IncMetric;
 pub(crate) fn report_net_event_fail(net_iface_metrics: &NetDeviceMetrics, err: DeviceError) {
     error!("{:?}", err);
     net_iface_metrics.event_fails.inc();
+    NOTIFIER.notify(Notification::DeviceError {
+        device: "net".to_string(),
+        error: err.to_string(),
+    });
 }
 
 #[derive(Debug, thiserror::Error, displaydoc::Display)]
@@ -0,0 +1,296 @@
+// Copyright 2025 Amazon.com, Inc. or its affiliates. All Rights Reserved.
+// SPDX-License-Identifier: Apache-2.0
+
+//! Configuration space of a PCI type 0 (endpoint) function.
+
+use crate::logger::warn;
+
+/// Size of the configuration space of a PCI Express function.
+pub const PCI_CONFIGURATION_SPACE_SIZE: usize = 0x1000;
+
+const VENDOR_ID_OFFSET: usize = 0x00;
+const DEVICE_ID_OFFSET: usize = 0x02;
+const COMMAND_OFFSET: usize = 0x04;
+const STATUS_OFFSET: usize = 0x06;
+const REVISION_ID_OFFSET: usize = 0x08;
+const CLASS_CODE_OFFSET: usize = 0x09;
+const BAR0_OFFSET: usize = 0x10;
+const SUBSYSTEM_VENDOR_ID_OFFSET: usize = 0x2c;
+const SUBSYSTEM_ID_OFFSET: usize = 0x2e;
+const CAPABILITIES_POINTER_OFFSET: usize = 0x34;
+const INTERRUPT_LINE_OFFSET: usize = 0x3c;
+
+/// Memory space decoding bit of the command register.
+pub const COMMAND_MEMORY_SPACE: u16 = 1 << 1;
+/// Bus master bit of the command register.
+pub const COMMAND_BUS_MASTER: u16 = 1 << 2;
+/// INTx emulation disable bit of the command register.
+pub const COMMAND_INTX_DISABLE: u16 = 1 << 10;
+
+// Set in the status register when the function implements a capability list.
+const STATUS_CAPABILITIES_LIST: u16 = 1 << 4;
+
+// Capabilities live after the 64 bytes of the standard header.
+const FIRST_CAPABILITY_OFFSET: usize = 0x40;
+// The legacy configuration space, which holds the capability list, is 256 bytes long.
+const LEGACY_CONFIGURATION_SPACE_SIZE: usize = 0x100;
+
+/// Identification of a PCI function, as exposed in its configuration space header.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PciHeader {
+    /// Vendor ID.
+    pub vendor_id: u16,
+    /// Device ID.
+    pub device_id: u16,
+    /// Revision ID.
+    pub revision_id: u8,
+    /// Class code, subclass and programming interface, from the most to the least significant
+    /// byte.
+    pub class_code: u32,
+    /// Subsystem vendor ID.
+    pub subsystem_vendor_id: u16,
+    /// Subsystem ID.
+    pub subsystem_id: u16,
+}
+
+/// Configuration space of a PCI function with a single 32-bit memory BAR.
+///
+/// Every register is read-only unless explicitly marked as writable. BAR 0 can be sized by the
+/// guest, but it can not be relocated: the address assigned by the VMM is restored on any other
+/// write.
+#[derive(Debug)]
+pub struct PciConfiguration {
+    registers: Vec<u8>,
+    writable_bits: Vec<u8>,
+    bar_addr: u32,
+    bar_size: u32,
+    bar_sizing: bool,
+    last_capability: Option<usize>,
+    next_capability: usize,
+}
+
+impl PciConfiguration {
+    /// Creates the configuration space of a function identified by `header`.
+    pub fn new(header: &PciHeader) -> Self {
+        let mut config = PciConfiguration {
+            registers: vec![0; PCI_CONFIGURATION_SPACE_SIZE],
+            writable_bits: vec![0; PCI_CONFIGURATION_SPACE_SIZE],
+            bar_addr: 0,
+            bar_size: 0,
+            bar_sizing: false,
+            last_capability: None,
+            next_capability: FIRST_CAPABILITY_OFFSET,
+        };
+        config.write_u16(VENDOR_ID_OFFSET, header.vendor_id);
+        config.write_u16(DEVICE_ID_OFFSET, header.device_id);
+        config.registers[REVISION_ID_OFFSET] = header.revision_id;
+        config.registers[CLASS_CODE_OFFSET..CLASS_CODE_OFFSET + 3]
+            .copy_from_slice(&header.class_code.to_le_bytes()[..3]);
+        config.write_u16(SUBSYSTEM_VENDOR_ID_OFFSET, header.subsystem_vendor_id);
+        config.write_u16(SUBSYSTEM_ID_OFFSET, header.subsystem_id);
+        config.set_writable_bits(
+            COMMAND_OFFSET,
+            &(COMMAND_MEMORY_SPACE | COMMAND_BUS_MASTER | COMMAND_INTX_DISABLE).to_le_bytes(),
+        );
+        // The interrupt line is a scratch register for the guest, we don't use INTx.
+        config.set_writable_bits(INTERRUPT_LINE_OFFSET, &[0xff]);
+        config
+    }
+
+    /// Sets the address and size of BAR 0, a 32-bit non-prefetchable memory BAR.
+    pub fn set_bar(&mut self, addr: u32, size: u32) {
+        assert!(size.is_power_of_two() && addr % size == 0);
+        self.bar_addr = addr;
+        self.bar_size = size;
+        self.write_u32(BAR0_OFFSET, addr);
+    }
+
+    /// Address of BAR 0.
+    pub fn bar_addr(&self) -> u32 {
+        self.bar_addr
+    }
+
+    /// Appends a capability with the given `id` and `body` to the capability list, returning
+    /// the offset of the capability in the configuration space.
+    ///
+    /// The body doesn't include the capability ID and next pointer bytes.
+    pub fn add_capability(&mut self, id: u8, body: &[u8]) -> usize {
+        // Capabilities are dword aligned.
+        let offset = self.next_capability;
+        let len = 2 + body.len();
+        assert!(offset + len <= LEGACY_CONFIGURATION_SPACE_SIZE);
+
+        self.registers[offset] = id;
+        self.registers[offset + 1] = 0;
+        self.registers[offset + 2..offset + len].copy_from_slice(body);
+        match self.last_capability {
+            Some(last) => self.registers[last + 1] = u8::try_from(offset).unwrap(),
+            None => {
+                self.registers[CAPABILITIES_POINTER_OFFSET] = u8::try_from(offset).unwrap();
+                let status = self.read_u16(STATUS_OFFSET) | STATUS_CAPABILITIES_LIST;
+                self.write_u16(STATUS_OFFSET, status);
+            }
+        }
+        self.last_capability = Some(offset);
+        self.next_capability = (offset + len).next_multiple_of(4);
+        offset
+    }
+
+    /// Marks the bits set in `mask` as writable by the guest, starting from `offset`.
+    pub fn set_writable_bits(&mut self, offset: usize, mask: &[u8]) {
+        self.writable_bits[offset..offset + mask.len()].copy_from_slice(mask);
+    }
+
+    /// Reads a 16-bit register.
+    pub fn read_u16(&self, offset: usize) -> u16 {
+        u16::from_le_bytes([self.registers[offset], self.registers[offset + 1]])
+    }
+
+    /// Reads a 32-bit register.
+    pub fn read_u32(&self, offset: usize) -> u32 {
+        let mut bytes = [0u8; 4];
+        bytes.copy_from_slice(&self.registers[offset..offset + 4]);
+        u32::from_le_bytes(bytes)
+    }
+
+    fn write_u16(&mut self, offset: usize, value: u16) {
+        self.registers[offset..offset + 2].copy_from_slice(&value.to_le_bytes());
+    }
+
+    fn write_u32(&mut self, offset: usize, value: u32) {
+        self.registers[offset..offset + 4].copy_from_slice(&value.to_le_bytes());
+    }
+
+    /// Value of the command register.
+    pub fn command(&self) -> u16 {
+        self.read_u16(COMMAND_OFFSET)
+    }
+
+    /// Handles a guest read of the configuration space.
+    pub fn read(&self, offset: usize, data: &mut [u8]) {
+        let Some(registers) = self.registers.get(offset..offset + data.len()) else {
+            data.fill(0xff);
+            return;
+        };
+        data.copy_from_slice(registers);
+
+        // While the guest sizes BAR 0, reads return the size mask instead of the address.
+        if self.bar_sizing {
+            let mask = (!(self.bar_size - 1)).to_le_bytes();
+            for (i, byte) in data.iter_mut().enumerate() {
+                if let Some(mask_byte) = (offset + i)
+                    .checked_sub(BAR0_OFFSET)
+                    .and_then(|idx| mask.get(idx))
+                {
+                    *byte = *mask_byte;
+                }
+            }
+        }
+    }
+
+    /// Handles a guest write to the configuration space.
+    pub fn write(&mut self, offset: usize, data: &[u8]) {
+        if offset + data.len() > PCI_CONFIGURATION_SPACE_SIZE {
+            warn!(
+                "Invalid PCI configuration space write: {:#x}:{:#x}",
+                offset,
+                data.len()
+            );
+            return;
+        }
+
+        if offset == BAR0_OFFSET && data.len() == 4 {
+            let value = u32::from_le_bytes([data[0], data[1], data[2], data[3]]);
+            self.bar_sizing = value == u32::MAX && self.bar_size != 0;
+            if !self.bar_sizing && value & !0xf != self.bar_addr {
+                warn!(
+                    "Ignoring PCI BAR relocation from {:#x} to {:#x}",
+                    self.bar_addr, value
+                );
+            }
+            return;
+        }
+
+        for (i, byte) in data.iter().enumerate() {
+            let mask = self.writable_bits[offset + i];
+            let register = &mut self.registers[offset + i];
+            *register = (*register & !mask) | (byte & mask);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn test_configuration() -> PciConfiguration {
+        PciConfiguration::new(&PciHeader {
+            vendor_id: 0x1af4,
+            device_id: 0x1041,
+            revision_id: 1,
+            class_code: 0x02_00_00,
+            subsystem_vendor_id: 0x1af4,
+            subsystem_id: 0x40,
+        })
+    }
+
+    #[test]
+    fn test_header() {
+        let mut config = test_configuration();
+        assert_eq!(config.read_u32(VENDOR_ID_OFFSET), 0x1041_1af4);
+        assert_eq!(config.read_u32(REVISION_ID_OFFSET), 0x0200_0001);
+        assert_eq!(config.read_u32(SUBSYSTEM_VENDOR_ID_OFFSET), 0x0040_1af4);
+
+        // Only the writable bits of the command register can be changed.
+        config.write(COMMAND_OFFSET, &[0xff, 0xff]);
+        assert_eq!(
+            config.command(),
+            COMMAND_MEMORY_SPACE | COMMAND_BUS_MASTER | COMMAND_INTX_DISABLE
+        );
+        // The vendor ID is read-only.
+        config.write(VENDOR_ID_OFFSET, &[0, 0]);
+        assert_eq!(config.read_u16(VENDOR_ID_OFFSET), 0x1af4);
+
+        // Out of bounds accesses are ignored.
+        let mut data = [0u8; 4];
+        config.read(PCI_CONFIGURATION_SPACE_SIZE - 2, &mut data);
+        assert_eq!(data, [0xff; 4]);
+        config.write(PCI_CONFIGURATION_SPACE_SIZE - 2, &data);
+    }
+
+    #[test]
+    fn test_bar_sizing() {
+        let mut config = test_configuration();
+        config.set_bar(0xd000_0000, 0x8000);
+
+        let mut data = [0u8; 4];
+        config.read(BAR0_OFFSET, &mut data);
+        assert_eq!(u32::from_le_bytes(data), 0xd000_0000);
+
+        config.write(BAR0_OFFSET, &u32::MAX.to_le_bytes());
+        config.read(BAR0_OFFSET, &mut data);
+        assert_eq!(u32::from_le_bytes(data), 0xffff_8000);
+
+        // Relocations are ignored.
+        config.write(BAR0_OFFSET, &0xe000_0000u32.to_le_bytes());
+        config.read(BAR0_OFFSET, &mut data);
+        assert_eq!(u32::from_le_bytes(data), 0xd000_0000);
+    }
+
+    #[test]
+    fn test_capabilities() {
+        let mut config = test_configuration();
+        assert_eq!(config.read_u16(STATUS_OFFSET) & STATUS_CAPABILITIES_LIST, 0);
+
+        assert_eq!(config.add_capability(0x09, &[1, 2, 3]), 0x40);
+        assert_eq!(config.add_capability(0x11, &[4, 5]), 0x48);
+        assert_ne!(config.read_u16(STATUS_OFFSET) & STATUS_CAPABILITIES_LIST, 0);
+        assert_eq!(config.registers[CAPABILITIES_POINTER_OFFSET], 0x40);
+        assert_eq!(config.registers[0x40..0x45], [0x09, 0x48, 1, 2, 3]);
+        assert_eq!(config.registers[0x48..0x4c], [0x11, 0, 4, 5]);
+
+        config.set_writable_bits(0x4a, &[0xf0]);
+        config.write(0x4a, &[0xff, 0xff]);
+        assert_eq!(config.registers[0x4a..0x4c], [0xf4, 5]);
+    }
+}
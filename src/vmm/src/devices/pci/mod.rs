@@ -0,0 +1,118 @@
+// Copyright 2025 Amazon.com, Inc. or its affiliates. All Rights Reserved.
+// SPDX-License-Identifier: Apache-2.0
+
+//! Emulates a minimal PCI Express root complex, with a single bus whose configuration space is
+//! accessed through ECAM.
+
+pub mod configuration;
+pub mod msix;
+
+use std::sync::{Arc, Mutex};
+
+use crate::devices::BusDevice;
+use crate::logger::warn;
+
+/// Number of device slots of a PCI bus.
+pub const PCI_SLOTS: usize = 32;
+/// Size of the ECAM region covering the configuration space of bus 0.
+pub const PCI_ECAM_SIZE: u64 = 1 << 20;
+
+// ECAM offsets are built as (bus << 20) | (device << 15) | (function << 12) | register.
+const ECAM_DEVICE_SHIFT: u64 = 15;
+const ECAM_FUNCTION_SHIFT: u64 = 12;
+const ECAM_REGISTER_MASK: u64 = 0xfff;
+
+/// Root complex of the single PCI bus of the VM.
+///
+/// It dispatches the configuration space accesses received through ECAM to the devices plugged
+/// in its slots. Devices only implement function 0.
+#[derive(Debug)]
+pub struct PciRootComplex {
+    slots: Vec<Option<Arc<Mutex<BusDevice>>>>,
+}
+
+impl Default for PciRootComplex {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl PciRootComplex {
+    /// Creates a root complex with no devices.
+    pub fn new() -> Self {
+        PciRootComplex {
+            slots: vec![None; PCI_SLOTS],
+        }
+    }
+
+    /// Returns the first free slot, if any.
+    pub fn free_slot(&self) -> Option<usize> {
+        self.slots.iter().position(Option::is_none)
+    }
+
+    /// Plugs `device` in `slot`.
+    pub fn add_device(&mut self, slot: usize, device: Arc<Mutex<BusDevice>>) {
+        assert!(self.slots[slot].is_none());
+        self.slots[slot] = Some(device);
+    }
+
+    // Returns the device and register offset targeted by an access at `offset` in ECAM.
+    fn decode(&self, offset: u64) -> Option<(&Arc<Mutex<BusDevice>>, usize)> {
+        // The ECAM region only covers bus 0.
+        let slot = usize::try_from(offset >> ECAM_DEVICE_SHIFT).ok()?;
+        let function = (offset >> ECAM_FUNCTION_SHIFT) & 0x7;
+        if function != 0 {
+            return None;
+        }
+        let register = usize::try_from(offset & ECAM_REGISTER_MASK).ok()?;
+        self.slots.get(slot)?.as_ref().map(|dev| (dev, register))
+    }
+
+    /// Handles a read in the ECAM region.
+    pub fn bus_read(&mut self, offset: u64, data: &mut [u8]) {
+        match self.decode(offset) {
+            Some((device, register)) => device
+                .lock()
+                .expect("Poisoned lock")
+                .virtio_pci_transport_ref()
+                .expect("Unexpected device type")
+                .read_config(register, data),
+            // Reads of functions that don't exist return all ones.
+            None => data.fill(0xff),
+        }
+    }
+
+    /// Handles a write in the ECAM region.
+    pub fn bus_write(&mut self, offset: u64, data: &[u8]) {
+        match self.decode(offset) {
+            Some((device, register)) => device
+                .lock()
+                .expect("Poisoned lock")
+                .virtio_pci_transport_mut()
+                .expect("Unexpected device type")
+                .write_config(register, data),
+            None => warn!(
+                "Ignoring PCI configuration write to missing function: {:#x}",
+                offset
+            ),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_empty_root_complex() {
+        let mut root = PciRootComplex::new();
+        assert_eq!(root.free_slot(), Some(0));
+
+        let mut data = [0u8; 4];
+        root.bus_read(0, &mut data);
+        assert_eq!(data, [0xff; 4]);
+        root.bus_read(1 << ECAM_FUNCTION_SHIFT, &mut data);
+        assert_eq!(data, [0xff; 4]);
+        root.bus_write(0x4, &[0x6, 0]);
+    }
+}
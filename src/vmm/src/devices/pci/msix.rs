@@ -0,0 +1,439 @@
+// Copyright 2025 Amazon.com, Inc. or its affiliates. All Rights Reserved.
+// SPDX-License-Identifier: Apache-2.0
+
+//! MSI-X capability of PCI functions and routing of the MSI-X interrupts through KVM.
+
+use std::collections::BTreeMap;
+use std::sync::Mutex;
+
+use kvm_bindings::{
+    KVM_IRQ_ROUTING_IRQCHIP, KVM_IRQ_ROUTING_MSI, KVM_IRQCHIP_IOAPIC, KVM_IRQCHIP_PIC_MASTER,
+    KVM_IRQCHIP_PIC_SLAVE, KvmIrqRouting, kvm_irq_routing_entry,
+};
+use kvm_ioctls::VmFd;
+use vmm_sys_util::eventfd::EventFd;
+
+use crate::logger::warn;
+use crate::utils::byte_order;
+
+/// Capability ID of MSI-X.
+pub const MSIX_CAPABILITY_ID: u8 = 0x11;
+/// Size of an entry of the MSI-X table.
+pub const MSIX_TABLE_ENTRY_SIZE: u64 = 16;
+
+const MSIX_ENABLE: u16 = 1 << 15;
+const MSIX_FUNCTION_MASK: u16 = 1 << 14;
+const MSIX_VECTOR_MASKED: u32 = 1;
+
+// Number of GSIs routed to the legacy interrupt controllers by default.
+const IOAPIC_PINS: u32 = 24;
+const PIC_PINS: u32 = 16;
+
+/// Errors associated with MSI-X interrupts.
+#[derive(Debug, thiserror::Error, displaydoc::Display)]
+pub enum MsixError {
+    /// Failed to build the GSI routing table: {0:?}
+    RoutingTable(vmm_sys_util::fam::Error),
+    /// Failed to set the GSI routing: {0}
+    SetGsiRouting(kvm_ioctls::Error),
+    /// Failed to register irqfd: {0}
+    RegisterIrqFd(kvm_ioctls::Error),
+    /// Failed to unregister irqfd: {0}
+    UnregisterIrqFd(kvm_ioctls::Error),
+}
+
+/// Message signaled by an MSI-X vector.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MsiMessage {
+    /// Address to which the message is written.
+    pub address: u64,
+    /// Data of the message.
+    pub data: u32,
+}
+
+/// Entry of the MSI-X table.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MsixTableEntry {
+    /// Lower 32 bits of the message address.
+    pub msg_addr_lo: u32,
+    /// Upper 32 bits of the message address.
+    pub msg_addr_hi: u32,
+    /// Message data.
+    pub msg_data: u32,
+    /// Vector control, holding the per-vector mask bit.
+    pub vector_ctl: u32,
+}
+
+impl Default for MsixTableEntry {
+    fn default() -> Self {
+        // Vectors are masked after reset.
+        MsixTableEntry {
+            msg_addr_lo: 0,
+            msg_addr_hi: 0,
+            msg_data: 0,
+            vector_ctl: MSIX_VECTOR_MASKED,
+        }
+    }
+}
+
+impl MsixTableEntry {
+    fn is_masked(&self) -> bool {
+        self.vector_ctl & MSIX_VECTOR_MASKED != 0
+    }
+
+    fn message(&self) -> MsiMessage {
+        MsiMessage {
+            address: (u64::from(self.msg_addr_hi) << 32) | u64::from(self.msg_addr_lo),
+            data: self.msg_data,
+        }
+    }
+}
+
+/// State of the MSI-X capability of a PCI function: its message control register and MSI-X
+/// table.
+///
+/// The pending bit array always reads as zero. Interrupts raised while their vector is masked
+/// are kept pending in their event fd instead, and delivered once the vector is unmasked.
+#[derive(Debug)]
+pub struct MsixConfig {
+    table: Vec<MsixTableEntry>,
+    enabled: bool,
+    function_masked: bool,
+}
+
+impl MsixConfig {
+    /// Creates the MSI-X state of a function with `num_vectors` vectors.
+    pub fn new(num_vectors: u16) -> Self {
+        assert!(num_vectors > 0);
+        MsixConfig {
+            table: vec![MsixTableEntry::default(); usize::from(num_vectors)],
+            enabled: false,
+            function_masked: false,
+        }
+    }
+
+    /// Body of the MSI-X capability, for a table and pending bit array placed in BAR 0 at the
+    /// given offsets.
+    pub fn capability_body(&self, table_offset: u32, pba_offset: u32) -> [u8; 10] {
+        let mut body = [0u8; 10];
+        // The table size is encoded as N - 1. Safe to unwrap since it was built from a u16.
+        let table_size = u16::try_from(self.table.len() - 1).unwrap();
+        body[0..2].copy_from_slice(&table_size.to_le_bytes());
+        // The lower 3 bits of the offsets hold the BAR index, which is 0.
+        body[2..6].copy_from_slice(&table_offset.to_le_bytes());
+        body[6..10].copy_from_slice(&pba_offset.to_le_bytes());
+        body
+    }
+
+    /// Bits of the message control register that are writable by the guest.
+    pub fn message_control_writable_bits() -> u16 {
+        MSIX_ENABLE | MSIX_FUNCTION_MASK
+    }
+
+    /// Updates the state from the value of the message control register.
+    pub fn set_message_control(&mut self, message_control: u16) {
+        self.enabled = message_control & MSIX_ENABLE != 0;
+        self.function_masked = message_control & MSIX_FUNCTION_MASK != 0;
+    }
+
+    /// Whether MSI-X is enabled.
+    pub fn enabled(&self) -> bool {
+        self.enabled
+    }
+
+    /// Number of vectors.
+    pub fn num_vectors(&self) -> usize {
+        self.table.len()
+    }
+
+    /// Returns the message that `vector` currently delivers, if it can deliver any.
+    pub fn message(&self, vector: u16) -> Option<MsiMessage> {
+        if !self.enabled || self.function_masked {
+            return None;
+        }
+        self.table
+            .get(usize::from(vector))
+            .filter(|entry| !entry.is_masked())
+            .map(MsixTableEntry::message)
+    }
+
+    /// Handles a guest read of the MSI-X table.
+    pub fn read_table(&self, offset: u64, data: &mut [u8]) {
+        let Some(entry) = self.entry_at(offset, data.len()) else {
+            data.fill(0);
+            return;
+        };
+        let entry = &self.table[entry];
+        let fields = [
+            entry.msg_addr_lo,
+            entry.msg_addr_hi,
+            entry.msg_data,
+            entry.vector_ctl,
+        ];
+        let field = usize::try_from((offset % MSIX_TABLE_ENTRY_SIZE) / 4).unwrap();
+        match data.len() {
+            4 => byte_order::write_le_u32(data, fields[field]),
+            8 => byte_order::write_le_u64(
+                data,
+                (u64::from(fields[field + 1]) << 32) | u64::from(fields[field]),
+            ),
+            _ => unreachable!(),
+        }
+    }
+
+    /// Handles a guest write to the MSI-X table.
+    pub fn write_table(&mut self, offset: u64, data: &[u8]) {
+        let Some(entry) = self.entry_at(offset, data.len()) else {
+            return;
+        };
+        let entry = &mut self.table[entry];
+        let mut fields = [
+            &mut entry.msg_addr_lo,
+            &mut entry.msg_addr_hi,
+            &mut entry.msg_data,
+            &mut entry.vector_ctl,
+        ];
+        let field = usize::try_from((offset % MSIX_TABLE_ENTRY_SIZE) / 4).unwrap();
+        match data.len() {
+            4 => *fields[field] = byte_order::read_le_u32(data),
+            8 => {
+                let value = byte_order::read_le_u64(data);
+                // Truncation is intended, the value is split in two dwords.
+                #[allow(clippy::cast_possible_truncation)]
+                {
+                    *fields[field] = value as u32;
+                    *fields[field + 1] = (value >> 32) as u32;
+                }
+            }
+            _ => unreachable!(),
+        }
+        // Only the mask bit of the vector control is writable.
+        entry.vector_ctl &= MSIX_VECTOR_MASKED;
+    }
+
+    /// Handles a guest read of the pending bit array.
+    pub fn read_pba(&self, _offset: u64, data: &mut [u8]) {
+        data.fill(0);
+    }
+
+    // Index of the table entry accessed by a `len` bytes access at `offset`, if valid.
+    fn entry_at(&self, offset: u64, len: usize) -> Option<usize> {
+        // Accesses are dword or qword aligned and can't cross an entry.
+        let aligned = match len {
+            4 => offset % 4 == 0,
+            8 => offset % 8 == 0,
+            _ => false,
+        };
+        let index = usize::try_from(offset / MSIX_TABLE_ENTRY_SIZE).ok()?;
+        if !aligned || index >= self.table.len() {
+            warn!("Invalid MSI-X table access: {:#x}:{:#x}", offset, len);
+            return None;
+        }
+        Some(index)
+    }
+}
+
+/// Interrupt of a device delivered through a KVM irqfd on a dedicated GSI, which is routed to
+/// the message of an MSI-X vector.
+#[derive(Debug)]
+pub struct MsiInterrupt {
+    gsi: u32,
+    message: Option<MsiMessage>,
+}
+
+impl MsiInterrupt {
+    /// Creates an interrupt that is signaled on `gsi`. It is not delivered until it gets a
+    /// message.
+    pub fn new(gsi: u32) -> Self {
+        MsiInterrupt { gsi, message: None }
+    }
+
+    /// GSI on which the interrupt is signaled.
+    pub fn gsi(&self) -> u32 {
+        self.gsi
+    }
+
+    /// Message with which the interrupt is delivered, if any.
+    pub fn message(&self) -> Option<MsiMessage> {
+        self.message
+    }
+}
+
+/// GSI routing table of the VM, shared by the MSI interrupts of all PCI functions.
+///
+/// KVM only allows replacing the whole routing table, so this keeps track of the routes of
+/// every MSI interrupt, next to the default routes of the legacy interrupt controllers.
+#[derive(Debug)]
+pub struct MsiRouting {
+    vm_fd: VmFd,
+    routes: Mutex<BTreeMap<u32, MsiMessage>>,
+}
+
+impl MsiRouting {
+    /// Creates an empty routing table for the MSI interrupts of the VM.
+    pub fn new(vm_fd: VmFd) -> Self {
+        MsiRouting {
+            vm_fd,
+            routes: Mutex::new(BTreeMap::new()),
+        }
+    }
+
+    /// Delivers the interrupt signaled on `evt` with `message`, or stops delivering it if
+    /// `message` is `None`.
+    ///
+    /// While an interrupt is not delivered, its irqfd is unregistered so that the guest doesn't
+    /// miss the interrupts raised in the meantime: KVM injects them as soon as the irqfd gets
+    /// registered again.
+    pub fn update(
+        &self,
+        evt: &EventFd,
+        interrupt: &mut MsiInterrupt,
+        message: Option<MsiMessage>,
+    ) -> Result<(), MsixError> {
+        match message {
+            Some(message) => {
+                if interrupt.message != Some(message) {
+                    let mut routes = self.routes.lock().expect("Poisoned lock");
+                    routes.insert(interrupt.gsi, message);
+                    self.set_gsi_routing(&routes)?;
+                }
+                if interrupt.message.is_none() {
+                    self.vm_fd
+                        .register_irqfd(evt, interrupt.gsi)
+                        .map_err(MsixError::RegisterIrqFd)?;
+                }
+                interrupt.message = Some(message);
+            }
+            None => {
+                if interrupt.message.take().is_some() {
+                    self.vm_fd
+                        .unregister_irqfd(evt, interrupt.gsi)
+                        .map_err(MsixError::UnregisterIrqFd)?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn set_gsi_routing(&self, routes: &BTreeMap<u32, MsiMessage>) -> Result<(), MsixError> {
+        let mut entries = legacy_routes();
+        entries.extend(routes.iter().map(|(gsi, message)| {
+            let mut entry = kvm_irq_routing_entry {
+                gsi: *gsi,
+                type_: KVM_IRQ_ROUTING_MSI,
+                ..Default::default()
+            };
+            // Truncation is intended, the address is split in two dwords.
+            #[allow(clippy::cast_possible_truncation)]
+            {
+                entry.u.msi.address_lo = message.address as u32;
+                entry.u.msi.address_hi = (message.address >> 32) as u32;
+            }
+            entry.u.msi.data = message.data;
+            entry
+        }));
+        let routing = KvmIrqRouting::from_entries(&entries).map_err(MsixError::RoutingTable)?;
+        self.vm_fd
+            .set_gsi_routing(&routing)
+            .map_err(MsixError::SetGsiRouting)
+    }
+}
+
+// Routes of the GSIs of the PIC and IOAPIC, as set up by KVM when creating the irqchip. Setting
+// the routing table replaces them, so they have to be part of it.
+fn legacy_routes() -> Vec<kvm_irq_routing_entry> {
+    let irqchip_route = |gsi: u32, irqchip: u32, pin: u32| {
+        let mut entry = kvm_irq_routing_entry {
+            gsi,
+            type_: KVM_IRQ_ROUTING_IRQCHIP,
+            ..Default::default()
+        };
+        entry.u.irqchip.irqchip = irqchip;
+        entry.u.irqchip.pin = pin;
+        entry
+    };
+
+    let mut entries = Vec::new();
+    for gsi in 0..IOAPIC_PINS {
+        if gsi < PIC_PINS / 2 {
+            entries.push(irqchip_route(gsi, KVM_IRQCHIP_PIC_MASTER, gsi));
+        } else if gsi < PIC_PINS {
+            entries.push(irqchip_route(
+                gsi,
+                KVM_IRQCHIP_PIC_SLAVE,
+                gsi - PIC_PINS / 2,
+            ));
+        }
+        entries.push(irqchip_route(gsi, KVM_IRQCHIP_IOAPIC, gsi));
+    }
+    entries
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_msix_table() {
+        let mut msix = MsixConfig::new(2);
+        assert_eq!(
+            msix.capability_body(0x4000, 0x5000),
+            [1, 0, 0, 0x40, 0, 0, 0, 0x50, 0, 0]
+        );
+
+        // Vectors are masked after reset.
+        let mut data = [0u8; 4];
+        msix.read_table(12, &mut data);
+        assert_eq!(u32::from_le_bytes(data), MSIX_VECTOR_MASKED);
+
+        msix.write_table(16, &0xfee0_0000u64.to_le_bytes());
+        msix.write_table(24, &0x41u32.to_le_bytes());
+        let mut data = [0u8; 8];
+        msix.read_table(16, &mut data);
+        assert_eq!(u64::from_le_bytes(data), 0xfee0_0000);
+
+        // Vectors only deliver messages when MSI-X is enabled and they are unmasked.
+        assert_eq!(msix.message(1), None);
+        msix.set_message_control(MSIX_ENABLE);
+        assert!(msix.enabled());
+        assert_eq!(msix.message(1), None);
+        msix.write_table(28, &u32::MAX.to_le_bytes());
+        assert_eq!(msix.message(1), None);
+        msix.write_table(28, &0u32.to_le_bytes());
+        let message = MsiMessage {
+            address: 0xfee0_0000,
+            data: 0x41,
+        };
+        assert_eq!(msix.message(1), Some(message));
+        msix.set_message_control(MSIX_ENABLE | MSIX_FUNCTION_MASK);
+        assert_eq!(msix.message(1), None);
+        msix.set_message_control(MSIX_ENABLE);
+        assert_eq!(msix.message(1), Some(message));
+
+        // Invalid accesses are ignored.
+        assert_eq!(msix.message(2), None);
+        msix.write_table(32, &0u32.to_le_bytes());
+        msix.write_table(17, &0u32.to_le_bytes());
+        msix.write_table(16, &[0u8; 2]);
+        assert_eq!(msix.message(1), Some(message));
+        let mut data = [0xffu8; 4];
+        msix.read_table(32, &mut data);
+        assert_eq!(data, [0; 4]);
+    }
+
+    #[test]
+    fn test_legacy_routes() {
+        let routes = legacy_routes();
+        assert_eq!(routes.len(), 40);
+        let pic = routes
+            .iter()
+            .filter(|entry| entry.type_ == KVM_IRQ_ROUTING_IRQCHIP)
+            .filter(|entry| {
+                // SAFETY: all entries are irqchip routes.
+                let irqchip = unsafe { entry.u.irqchip.irqchip };
+                irqchip != KVM_IRQCHIP_IOAPIC
+            })
+            .count();
+        assert_eq!(pic, 16);
+    }
+}
@@ -13,7 +13,7 @@ use super::super::device::{DeviceState, VirtioDevice};
 use super::super::queue::Queue;
 use super::super::{ActivateError, TYPE_BALLOON};
 use super::metrics::METRICS;
-use super::util::{compact_page_frame_numbers, remove_range};
+use super::util::{PartialHugePages, compact_page_frame_numbers, remove_range};
 use super::{
     BALLOON_DEV_ID, BALLOON_NUM_QUEUES, BALLOON_QUEUE_SIZES, DEFLATE_INDEX, INFLATE_INDEX,
     MAX_PAGE_COMPACT_BUFFER, MAX_PAGES_IN_DESC, MIB_TO_4K_PAGES, STATS_INDEX,
@@ -27,8 +27,11 @@ use crate::devices::virtio::balloon::BalloonError;
 use crate::devices::virtio::device::{IrqTrigger, IrqType};
 use crate::devices::virtio::generated::virtio_config::VIRTIO_F_VERSION_1;
 use crate::logger::IncMetric;
+use crate::notifications::{NOTIFIER, Notification};
 use crate::utils::u64_to_usize;
-use crate::vstate::memory::{Address, ByteValued, Bytes, GuestAddress, GuestMemoryMmap};
+use crate::vstate::memory::{
+    Address, ByteValued, Bytes, GuestAddress, GuestMemory, GuestMemoryMmap,
+};
 
 const SIZE_OF_U32: usize = std::mem::size_of::<u32>();
 const SIZE_OF_STAT: usize = std::mem::size_of::<BalloonStat>();
@@ -173,6 +176,8 @@ pub struct Balloon {
     pub(crate) latest_stats: BalloonStats,
     // A buffer used as pfn accumulator during descriptor processing.
     pub(crate) pfn_buffer: [u32; MAX_PAGE_COMPACT_BUFFER],
+    // The partially inflated huge pages, when guest memory is backed by transparent huge pages.
+    pub(crate) partial_huge_pages: Option<PartialHugePages>,
 }
 
 // TODO Use `#[derive(Debug)]` when a new release of
@@ -194,6 +199,7 @@ impl fmt::Debug for Balloon {
             .field("stats_desc_index", &self.stats_desc_index)
             .field("latest_stats", &self.latest_stats)
             .field("pfn_buffer", &self.pfn_buffer)
+            .field("partial_huge_pages", &self.partial_huge_pages)
             .finish()
     }
 }
@@ -251,9 +257,16 @@ impl Balloon {
             stats_desc_index: None,
             latest_stats: BalloonStats::default(),
             pfn_buffer: [0u32; MAX_PAGE_COMPACT_BUFFER],
+            partial_huge_pages: None,
         })
     }
 
+    /// Makes the device aware of guest memory being backed by transparent huge pages, in which
+    /// case huge pages are only given back to the host once the guest inflated them entirely.
+    pub fn set_transparent_huge_pages(&mut self, enabled: bool) {
+        self.partial_huge_pages = enabled.then(PartialHugePages::default);
+    }
+
     pub(crate) fn process_inflate_queue_event(&mut self) -> Result<(), BalloonError> {
         self.queue_evts[INFLATE_INDEX]
             .read()
@@ -351,12 +364,15 @@ impl Balloon {
             for (page_frame_number, range_len) in page_ranges {
                 let guest_addr =
                     GuestAddress(u64::from(page_frame_number) << VIRTIO_BALLOON_PFN_SHIFT);
+                let range = (guest_addr, u64::from(range_len) << VIRTIO_BALLOON_PFN_SHIFT);
 
-                if let Err(err) = remove_range(
-                    mem,
-                    (guest_addr, u64::from(range_len) << VIRTIO_BALLOON_PFN_SHIFT),
-                    self.restored_from_file,
-                ) {
+                let result = match self.partial_huge_pages.as_mut() {
+                    Some(partial_huge_pages) => {
+                        partial_huge_pages.remove_range(mem, range, self.restored_from_file)
+                    }
+                    None => remove_range(mem, range, self.restored_from_file),
+                };
+                if let Err(err) = result {
                     error!("Error removing memory range: {:?}", err);
                 }
             }
@@ -376,6 +392,38 @@ impl Balloon {
         let mut needs_interrupt = false;
 
         while let Some(head) = queue.pop() {
+            // Pages given back to the guest must not be removed when the rest of their huge page
+            // gets inflated.
+            if let Some(partial_huge_pages) = self.partial_huge_pages.as_mut() {
+                // This is safe since we checked in the event handler that the device is
+                // activated.
+                let mem = self.device_state.mem().unwrap();
+
+                let len = head.len as usize;
+                let max_len = MAX_PAGES_IN_DESC * SIZE_OF_U32;
+
+                // Like bogus inflate descriptors, malformed ones are skipped, but still
+                // acknowledged since the guest doesn't expect the deflate to fail.
+                if !head.is_write_only() && len % SIZE_OF_U32 == 0 && len <= max_len {
+                    for index in (0..len).step_by(SIZE_OF_U32) {
+                        let Some(page_frame_number) = head
+                            .addr
+                            .checked_add(index as u64)
+                            .and_then(|addr| mem.read_obj::<u32>(addr).ok())
+                        else {
+                            error!("Deflate descriptor has invalid page frame numbers, skipping.");
+                            break;
+                        };
+                        let guest_addr =
+                            GuestAddress(u64::from(page_frame_number) << VIRTIO_BALLOON_PFN_SHIFT);
+
+                        if let Ok(host_addr) = mem.get_host_address(guest_addr) {
+                            partial_huge_pages.deflate(host_addr as usize);
+                        }
+                    }
+                }
+            }
+
             queue.add_used(head.index, 0).map_err(BalloonError::Queue)?;
             needs_interrupt = true;
         }
@@ -587,6 +635,7 @@ impl VirtioDevice for Balloon {
     }
 
     fn write_config(&mut self, offset: u64, data: &[u8]) {
+        let previous_actual_pages = self.config_space.actual_pages;
         let config_space_bytes = self.config_space.as_mut_slice();
         let start = usize::try_from(offset).ok();
         let end = start.and_then(|s| s.checked_add(data.len()));
@@ -599,6 +648,14 @@ impl VirtioDevice for Balloon {
         };
 
         dst.copy_from_slice(data);
+
+        // The guest driver reports the current balloon size through `actual_pages`.
+        let target_reached = self.config_space.actual_pages == self.config_space.num_pages;
+        if target_reached && self.config_space.actual_pages != previous_actual_pages {
+            NOTIFIER.notify(Notification::BalloonTargetReached {
+                target_mib: pages_to_mib(self.config_space.num_pages),
+            });
+        }
     }
 
     fn activate(&mut self, mem: GuestMemoryMmap) -> Result<(), ActivateError> {
@@ -984,6 +1041,24 @@ pub(crate) mod tests {
             );
             check_request_completion(&defq, 1);
         }
+
+        // Malformed descriptors are acknowledged with transparent huge pages too.
+        balloon.set_transparent_huge_pages(true);
+        {
+            set_request(
+                &defq,
+                2,
+                0x10000 - 2,
+                SIZE_OF_U32.try_into().unwrap(),
+                VIRTQ_DESC_F_NEXT,
+            );
+            check_metric_after_block!(
+                METRICS.deflate_count,
+                1,
+                invoke_handler_for_queue_event(&mut balloon, DEFLATE_INDEX)
+            );
+            check_request_completion(&defq, 2);
+        }
     }
 
     #[test]
@@ -18,6 +18,7 @@ use super::queue::QueueError;
 use crate::devices::virtio::balloon::metrics::METRICS;
 use crate::devices::virtio::queue::FIRECRACKER_MAX_QUEUE_SIZE;
 use crate::logger::IncMetric;
+use crate::notifications::{NOTIFIER, Notification};
 
 /// Device ID used in MMIO device identification.
 /// Because Balloon is unique per-vm, this ID can be hardcoded.
@@ -42,6 +43,8 @@ pub const MAX_PAGES_IN_DESC: usize = 256;
 pub const MAX_PAGE_COMPACT_BUFFER: usize = 2048;
 /// The addresses given by the driver are divided by 4096.
 pub const VIRTIO_BALLOON_PFN_SHIFT: u32 = 12;
+/// The size of the transparent huge pages that may back guest memory.
+pub const THP_SIZE: usize = 2 << 20;
 /// The index of the deflate queue from Balloon device queues/queues_evts vector.
 pub const INFLATE_INDEX: usize = 0;
 /// The index of the deflate queue from Balloon device queues/queues_evts vector.
@@ -117,4 +120,8 @@ pub enum RemoveRegionError {
 pub(super) fn report_balloon_event_fail(err: BalloonError) {
     error!("{:?}", err);
     METRICS.event_fails.inc();
+    NOTIFIER.notify(Notification::DeviceError {
+        device: "balloon".to_string(),
+        error: err.to_string(),
+    });
 }
@@ -1,9 +1,10 @@
 // Copyright 2020 Amazon.com, Inc. or its affiliates. All Rights Reserved.
 // SPDX-License-Identifier: Apache-2.0
 
+use std::collections::HashMap;
 use std::io;
 
-use super::{MAX_PAGE_COMPACT_BUFFER, RemoveRegionError};
+use super::{MAX_PAGE_COMPACT_BUFFER, RemoveRegionError, THP_SIZE, VIRTIO_BALLOON_PFN_SHIFT};
 use crate::logger::error;
 use crate::utils::u64_to_usize;
 use crate::vstate::memory::{GuestAddress, GuestMemory, GuestMemoryMmap, GuestMemoryRegion};
@@ -70,49 +71,151 @@ pub(crate) fn remove_range(
     range: (GuestAddress, u64),
     restored_from_file: bool,
 ) -> Result<(), RemoveRegionError> {
+    let host_address = host_range_address(guest_memory, range)?;
+    remove_host_range(host_address, u64_to_usize(range.1), restored_from_file)
+}
+
+/// Checks that the guest memory `range` lies within a single memory region, and returns the host
+/// address at which it is mapped.
+fn host_range_address(
+    guest_memory: &GuestMemoryMmap,
+    range: (GuestAddress, u64),
+) -> Result<*mut u8, RemoveRegionError> {
     let (guest_address, range_len) = range;
 
     if let Some(region) = guest_memory.find_region(guest_address) {
         if guest_address.0 + range_len > region.start_addr().0 + region.len() {
             return Err(RemoveRegionError::MalformedRange);
         }
-        let phys_address = guest_memory
+        guest_memory
             .get_host_address(guest_address)
-            .map_err(|_| RemoveRegionError::AddressTranslation)?;
-
-        // Mmap a new anonymous region over the present one in order to create a hole.
-        // This workaround is (only) needed after resuming from a snapshot because the guest memory
-        // is mmaped from file as private and there is no `madvise` flag that works for this case.
-        if restored_from_file {
-            // SAFETY: The address and length are known to be valid.
-            let ret = unsafe {
-                libc::mmap(
-                    phys_address.cast(),
-                    u64_to_usize(range_len),
-                    libc::PROT_READ | libc::PROT_WRITE,
-                    libc::MAP_FIXED | libc::MAP_ANONYMOUS | libc::MAP_PRIVATE,
-                    -1,
-                    0,
-                )
-            };
-            if ret == libc::MAP_FAILED {
-                return Err(RemoveRegionError::MmapFail(io::Error::last_os_error()));
-            }
-        };
+            .map_err(|_| RemoveRegionError::AddressTranslation)
+    } else {
+        Err(RemoveRegionError::RegionNotFound)
+    }
+}
 
-        // Madvise the region in order to mark it as not used.
+/// Gives the `range_len` bytes of guest memory mapped at `phys_address` back to the host.
+fn remove_host_range(
+    phys_address: *mut u8,
+    range_len: usize,
+    restored_from_file: bool,
+) -> Result<(), RemoveRegionError> {
+    // Mmap a new anonymous region over the present one in order to create a hole.
+    // This workaround is (only) needed after resuming from a snapshot because the guest memory
+    // is mmaped from file as private and there is no `madvise` flag that works for this case.
+    if restored_from_file {
         // SAFETY: The address and length are known to be valid.
         let ret = unsafe {
-            let range_len = u64_to_usize(range_len);
-            libc::madvise(phys_address.cast(), range_len, libc::MADV_DONTNEED)
+            libc::mmap(
+                phys_address.cast(),
+                range_len,
+                libc::PROT_READ | libc::PROT_WRITE,
+                libc::MAP_FIXED | libc::MAP_ANONYMOUS | libc::MAP_PRIVATE,
+                -1,
+                0,
+            )
         };
-        if ret < 0 {
-            return Err(RemoveRegionError::MadviseFail(io::Error::last_os_error()));
+        if ret == libc::MAP_FAILED {
+            return Err(RemoveRegionError::MmapFail(io::Error::last_os_error()));
+        }
+    };
+
+    // Madvise the region in order to mark it as not used.
+    // SAFETY: The address and length are known to be valid.
+    let ret = unsafe { libc::madvise(phys_address.cast(), range_len, libc::MADV_DONTNEED) };
+    if ret < 0 {
+        return Err(RemoveRegionError::MadviseFail(io::Error::last_os_error()));
+    }
+
+    Ok(())
+}
+
+/// Number of u64 words in the bitmap of the 4K pages of a transparent huge page.
+const THP_BITMAP_WORDS: usize = THP_SIZE / (64 << VIRTIO_BALLOON_PFN_SHIFT);
+
+/// Keeps track of the inflated 4K pages of transparent huge pages which the guest has only
+/// partially inflated.
+///
+/// Removing part of a transparent huge page makes the host kernel split it into 4K pages, which
+/// are not collapsed back until khugepaged gets to them. Such pages are therefore only removed
+/// once the guest inflated the whole huge page they belong to.
+#[derive(Debug, Default)]
+pub(crate) struct PartialHugePages {
+    // Bitmaps of the inflated 4K pages, keyed by the host address of their huge page. Host
+    // addresses are used because the alignment of transparent huge pages is that of the host
+    // mapping, which isn't necessarily the one of the guest physical addresses.
+    pages: HashMap<usize, [u64; THP_BITMAP_WORDS]>,
+}
+
+impl PartialHugePages {
+    /// Records the inflation of the guest memory `range`, and removes the huge pages it completes.
+    pub(crate) fn remove_range(
+        &mut self,
+        guest_memory: &GuestMemoryMmap,
+        range: (GuestAddress, u64),
+        restored_from_file: bool,
+    ) -> Result<(), RemoveRegionError> {
+        let host_address = host_range_address(guest_memory, range)?;
+
+        for (address, range_len) in self.inflate(host_address as usize, u64_to_usize(range.1)) {
+            remove_host_range(address as *mut u8, range_len, restored_from_file)?;
         }
 
         Ok(())
-    } else {
-        Err(RemoveRegionError::RegionNotFound)
+    }
+
+    /// Records the inflation of the `range_len` bytes at host address `start`, and returns the
+    /// host address ranges that can be removed, i.e. the ones made of whole huge pages.
+    fn inflate(&mut self, start: usize, range_len: usize) -> Vec<(usize, usize)> {
+        let end = start + range_len;
+        let mut removable: Vec<(usize, usize)> = Vec::new();
+        let mut address = start;
+
+        while address < end {
+            let huge_page = address - address % THP_SIZE;
+            let chunk_end = end.min(huge_page + THP_SIZE);
+
+            let complete = if address == huge_page && chunk_end == huge_page + THP_SIZE {
+                self.pages.remove(&huge_page);
+                true
+            } else {
+                let bitmap = self.pages.entry(huge_page).or_default();
+                for page in (address - huge_page) >> VIRTIO_BALLOON_PFN_SHIFT
+                    ..(chunk_end - huge_page) >> VIRTIO_BALLOON_PFN_SHIFT
+                {
+                    bitmap[page / 64] |= 1u64 << (page % 64);
+                }
+                let complete = bitmap.iter().all(|&word| word == u64::MAX);
+                if complete {
+                    self.pages.remove(&huge_page);
+                }
+                complete
+            };
+
+            if complete {
+                match removable.last_mut() {
+                    Some((last, len)) if *last + *len == huge_page => *len += THP_SIZE,
+                    _ => removable.push((huge_page, THP_SIZE)),
+                }
+            }
+            address = chunk_end;
+        }
+
+        removable
+    }
+
+    /// Forgets about the 4K page at host address `address`, which was given back to the guest.
+    pub(crate) fn deflate(&mut self, address: usize) {
+        let huge_page = address - address % THP_SIZE;
+
+        if let Some(bitmap) = self.pages.get_mut(&huge_page) {
+            let page = (address - huge_page) >> VIRTIO_BALLOON_PFN_SHIFT;
+            bitmap[page / 64] &= !(1u64 << (page % 64));
+            if bitmap.iter().all(|&word| word == 0) {
+                self.pages.remove(&huge_page);
+            }
+        }
     }
 }
 
@@ -256,6 +359,47 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_partial_huge_pages() {
+        let page_size = 1 << VIRTIO_BALLOON_PFN_SHIFT;
+        let base = 0x4000_0000;
+        let mut partial_huge_pages = PartialHugePages::default();
+
+        // Whole huge pages are removed right away, even if the range is not aligned.
+        assert_eq!(
+            partial_huge_pages.inflate(base + page_size, 2 * THP_SIZE),
+            vec![(base + THP_SIZE, THP_SIZE)]
+        );
+        assert_eq!(partial_huge_pages.pages.len(), 2);
+
+        // Completing the first huge page makes it removable.
+        assert_eq!(
+            partial_huge_pages.inflate(base, page_size),
+            vec![(base, THP_SIZE)]
+        );
+        assert_eq!(partial_huge_pages.pages.len(), 1);
+
+        // Pages given back to the guest are not removed with the rest of their huge page.
+        partial_huge_pages.deflate(base + 2 * THP_SIZE);
+        assert!(partial_huge_pages.pages.is_empty());
+        assert!(
+            partial_huge_pages
+                .inflate(base + 2 * THP_SIZE + page_size, THP_SIZE - page_size)
+                .is_empty()
+        );
+        assert_eq!(
+            partial_huge_pages.inflate(base + 2 * THP_SIZE, page_size),
+            vec![(base + 2 * THP_SIZE, THP_SIZE)]
+        );
+        assert!(partial_huge_pages.pages.is_empty());
+
+        // Adjacent huge pages are removed as a single range.
+        assert_eq!(
+            partial_huge_pages.inflate(base, 3 * THP_SIZE),
+            vec![(base, 3 * THP_SIZE)]
+        );
+    }
+
     /// -------------------------------------
     /// BEGIN PROPERTY BASED TESTING
     use proptest::prelude::*;
@@ -0,0 +1,856 @@
+// Copyright 2025 Amazon.com, Inc. or its affiliates. All Rights Reserved.
+// SPDX-License-Identifier: Apache-2.0
+
+use std::collections::VecDeque;
+use std::io;
+use std::os::unix::net::UnixStream;
+use std::sync::Arc;
+use std::sync::atomic::AtomicU32;
+
+use vm_memory::GuestMemoryError;
+use vmm_sys_util::eventfd::EventFd;
+
+use super::metrics::METRICS;
+use super::port::ConsolePort;
+use super::{
+    CONSOLE_DEV_ID, CONTROL_RX_QUEUE, CONTROL_TX_QUEUE, MAX_PORTS, VIRTIO_CONSOLE_F_MULTIPORT,
+    VirtioConsoleError, control, num_queues, rx_queue_index,
+};
+use crate::devices::DeviceError;
+use crate::devices::virtio::device::{DeviceState, IrqTrigger, IrqType, VirtioDevice};
+use crate::devices::virtio::generated::virtio_config::VIRTIO_F_VERSION_1;
+use crate::devices::virtio::queue::{DescriptorChain, FIRECRACKER_MAX_QUEUE_SIZE, Queue};
+use crate::devices::virtio::{ActivateError, TYPE_CONSOLE};
+use crate::logger::{IncMetric, debug, error, warn};
+use crate::utils::u64_to_usize;
+use crate::vmm_config::console::ConsolePortConfig;
+use crate::vstate::memory::{Bytes, GuestMemoryMmap};
+
+/// Size of the header of a control message: port id (u32), event (u16) and value (u16).
+const CONTROL_MSG_LEN: usize = 8;
+
+/// Maximum number of bytes taken from a single descriptor chain sent by the driver.
+const MAX_CHAIN_LEN: usize = 64 * 1024;
+
+/// Reads the device-readable part of a descriptor chain, up to `MAX_CHAIN_LEN` bytes.
+fn read_chain(mem: &GuestMemoryMmap, head: DescriptorChain) -> Result<Vec<u8>, GuestMemoryError> {
+    let mut data = Vec::new();
+    for desc in head.into_iter().filter(|desc| !desc.is_write_only()) {
+        let start = data.len();
+        let len = u64_to_usize(u64::from(desc.len)).min(MAX_CHAIN_LEN - start);
+        data.resize(start + len, 0);
+        mem.read_slice(&mut data[start..], desc.addr)?;
+        if data.len() == MAX_CHAIN_LEN {
+            break;
+        }
+    }
+    Ok(data)
+}
+
+/// Writes `data` to the device-writable part of a descriptor chain. Returns the number of bytes
+/// written, which is less than the length of `data` if the chain is too small.
+fn write_chain(
+    mem: &GuestMemoryMmap,
+    head: DescriptorChain,
+    data: &[u8],
+) -> Result<usize, GuestMemoryError> {
+    let mut written = 0;
+    for desc in head.into_iter().filter(|desc| desc.is_write_only()) {
+        if written == data.len() {
+            break;
+        }
+        let len = u64_to_usize(u64::from(desc.len)).min(data.len() - written);
+        mem.write_slice(&data[written..written + len], desc.addr)?;
+        written += len;
+    }
+    Ok(written)
+}
+
+/// Virtio-console device exposing multiple ports to the guest.
+#[derive(Debug)]
+pub struct VirtioConsole {
+    // VirtIO fields
+    avail_features: u64,
+    acked_features: u64,
+    activate_event: EventFd,
+
+    // Transport fields
+    device_state: DeviceState,
+    pub(crate) queues: Vec<Queue>,
+    queue_events: Vec<EventFd>,
+    irq_trigger: IrqTrigger,
+
+    // Device specific fields
+    pub(crate) ports: Vec<ConsolePort>,
+    /// Control messages waiting for a buffer in the control receive queue.
+    pub(crate) control_pending: VecDeque<Vec<u8>>,
+    /// Connections of host peers which were dropped, and must be unregistered from the event
+    /// manager.
+    pub(crate) closed_streams: Vec<(usize, UnixStream)>,
+}
+
+impl VirtioConsole {
+    /// Creates a virtio-console device without ports.
+    pub fn new() -> Result<Self, VirtioConsoleError> {
+        let queues = vec![Queue::new(FIRECRACKER_MAX_QUEUE_SIZE); num_queues(0)];
+        Self::new_with_queues(queues, Vec::new())
+    }
+
+    pub(crate) fn new_with_queues(
+        queues: Vec<Queue>,
+        ports: Vec<ConsolePort>,
+    ) -> Result<Self, VirtioConsoleError> {
+        let activate_event =
+            EventFd::new(libc::EFD_NONBLOCK).map_err(VirtioConsoleError::EventFd)?;
+        let queue_events = (0..queues.len())
+            .map(|_| EventFd::new(libc::EFD_NONBLOCK))
+            .collect::<Result<Vec<EventFd>, io::Error>>()
+            .map_err(VirtioConsoleError::EventFd)?;
+        let irq_trigger = IrqTrigger::new().map_err(VirtioConsoleError::EventFd)?;
+
+        Ok(Self {
+            avail_features: (1 << VIRTIO_F_VERSION_1) | (1 << VIRTIO_CONSOLE_F_MULTIPORT),
+            acked_features: 0u64,
+            activate_event,
+            device_state: DeviceState::Inactive,
+            queues,
+            queue_events,
+            irq_trigger,
+            ports,
+            control_pending: VecDeque::new(),
+            closed_streams: Vec::new(),
+        })
+    }
+
+    pub fn id(&self) -> &str {
+        CONSOLE_DEV_ID
+    }
+
+    /// Adds a port to the device, or replaces the port with the same ID. Ports can only be added
+    /// before the device is activated.
+    pub fn add_port(&mut self, config: ConsolePortConfig) -> Result<(), VirtioConsoleError> {
+        let index = self
+            .ports
+            .iter()
+            .position(|port| port.config().port_id == config.port_id);
+
+        match index {
+            Some(index) => self.replace_port(index, config)?,
+            None if self.ports.len() == MAX_PORTS => return Err(VirtioConsoleError::TooManyPorts),
+            None => self.ports.push(ConsolePort::new(config)?),
+        }
+
+        self.resize_queues()
+    }
+
+    /// Replaces the port at `index` with a port built from `config`. The old port is left in
+    /// place if the new one can't be built.
+    fn replace_port(
+        &mut self,
+        index: usize,
+        config: ConsolePortConfig,
+    ) -> Result<(), VirtioConsoleError> {
+        let old_socket_path = self.ports[index].config().socket_path.clone();
+        // A socket path can't be bound twice, so the new port takes over the listener of the old
+        // one if it uses the same path.
+        let listener = match self.ports[index].listener() {
+            Some(listener) if old_socket_path == config.socket_path => Some(
+                listener
+                    .try_clone()
+                    .map_err(VirtioConsoleError::BindSocket)?,
+            ),
+            _ => None,
+        };
+        let port = ConsolePort::with_listener(config, listener)?;
+
+        let old_port = std::mem::replace(&mut self.ports[index], port);
+        drop(old_port);
+        if old_socket_path != self.ports[index].config().socket_path {
+            if let Some(path) = old_socket_path {
+                let _ = std::fs::remove_file(path);
+            }
+        }
+        Ok(())
+    }
+
+    /// Makes the number of queues match the number of ports.
+    fn resize_queues(&mut self) -> Result<(), VirtioConsoleError> {
+        let count = num_queues(self.ports.len());
+        self.queues
+            .resize(count, Queue::new(FIRECRACKER_MAX_QUEUE_SIZE));
+        self.queue_events.truncate(count);
+        while self.queue_events.len() < count {
+            self.queue_events
+                .push(EventFd::new(libc::EFD_NONBLOCK).map_err(VirtioConsoleError::EventFd)?);
+        }
+        Ok(())
+    }
+
+    /// Returns the configurations of the ports.
+    pub fn port_configs(&self) -> Vec<ConsolePortConfig> {
+        self.ports
+            .iter()
+            .map(|port| port.config().clone())
+            .collect()
+    }
+
+    fn config_space(&self) -> [u8; 12] {
+        let max_nr_ports = u32::try_from(self.ports.len().max(1)).unwrap();
+        let mut config_space = [0u8; 12];
+        // The `cols` and `rows` fields are left to 0, as the size feature isn't offered, and so
+        // is the `emerg_wr` field.
+        config_space[4..8].copy_from_slice(&max_nr_ports.to_le_bytes());
+        config_space
+    }
+
+    fn signal_used_queue(&self) -> Result<(), DeviceError> {
+        self.irq_trigger
+            .trigger_irq(IrqType::Vring)
+            .map_err(DeviceError::FailedSignalingIrq)
+    }
+
+    fn signal_used_queue_or_log(&self) {
+        self.signal_used_queue().unwrap_or_else(|err| {
+            error!("console: {err:?}");
+            METRICS.event_fails.inc()
+        });
+    }
+
+    fn add_used(&mut self, queue_index: usize, desc_index: u16, len: usize) {
+        let len = u32::try_from(len).unwrap();
+        if let Err(err) = self.queues[queue_index].add_used(desc_index, len) {
+            error!("console: Could not add used descriptor to queue: {err}");
+            METRICS.event_fails.inc();
+        }
+    }
+
+    pub(crate) fn queue_control_message(
+        &mut self,
+        port_index: usize,
+        event: u16,
+        value: u16,
+        data: &[u8],
+    ) {
+        let port_id = u32::try_from(port_index).unwrap();
+        let mut msg = Vec::with_capacity(CONTROL_MSG_LEN + data.len());
+        msg.extend_from_slice(&port_id.to_le_bytes());
+        msg.extend_from_slice(&event.to_le_bytes());
+        msg.extend_from_slice(&value.to_le_bytes());
+        msg.extend_from_slice(data);
+        self.control_pending.push_back(msg);
+    }
+
+    /// Sends the pending control messages to the driver, as long as it provided buffers.
+    pub(crate) fn process_control_rx(&mut self) {
+        // This is safe since we checked in the event handler that the device is activated.
+        let mem = self.device_state.mem().unwrap();
+        let mut used = Vec::new();
+
+        while let Some(msg) = self.control_pending.front() {
+            let Some(head) = self.queues[CONTROL_RX_QUEUE].pop() else {
+                break;
+            };
+            let len = match write_chain(mem, head, msg) {
+                Ok(len) if len < msg.len() => {
+                    error!("console: Control message truncated to {len} bytes");
+                    METRICS.event_fails.inc();
+                    len
+                }
+                Ok(len) => len,
+                Err(err) => {
+                    error!("console: Could not write control message: {err}");
+                    METRICS.event_fails.inc();
+                    0
+                }
+            };
+            self.control_pending.pop_front();
+            used.push((head.index, len));
+        }
+
+        if !used.is_empty() {
+            for (desc_index, len) in used {
+                self.add_used(CONTROL_RX_QUEUE, desc_index, len);
+            }
+            self.signal_used_queue_or_log();
+        }
+    }
+
+    /// Handles the control messages sent by the driver.
+    pub(crate) fn process_control_tx(&mut self) {
+        // This is safe since we checked in the event handler that the device is activated.
+        let mem = self.device_state.mem().unwrap();
+        let mut messages = Vec::new();
+        let mut used = Vec::new();
+
+        while let Some(head) = self.queues[CONTROL_TX_QUEUE].pop() {
+            match read_chain(mem, head) {
+                Ok(msg) => messages.push(msg),
+                Err(err) => {
+                    error!("console: Could not read control message: {err}");
+                    METRICS.event_fails.inc();
+                }
+            }
+            used.push(head.index);
+        }
+
+        if !used.is_empty() {
+            for desc_index in used {
+                self.add_used(CONTROL_TX_QUEUE, desc_index, 0);
+            }
+            self.signal_used_queue_or_log();
+        }
+
+        for msg in messages {
+            self.handle_control_message(&msg);
+        }
+        self.process_control_rx();
+    }
+
+    fn handle_control_message(&mut self, msg: &[u8]) {
+        if msg.len() < CONTROL_MSG_LEN {
+            error!("console: Control message too short: {} bytes", msg.len());
+            METRICS.event_fails.inc();
+            return;
+        }
+        let port_id = u32::from_le_bytes(msg[0..4].try_into().unwrap());
+        let event = u16::from_le_bytes(msg[4..6].try_into().unwrap());
+        let value = u16::from_le_bytes(msg[6..8].try_into().unwrap());
+
+        if event == control::DEVICE_READY {
+            if value != 1 {
+                error!("console: The driver failed to initialize");
+                return;
+            }
+            for port_index in 0..self.ports.len() {
+                self.queue_control_message(port_index, control::DEVICE_ADD, 0, &[]);
+            }
+            return;
+        }
+
+        let port_index = u64_to_usize(u64::from(port_id));
+        let Some(port) = self.ports.get_mut(port_index) else {
+            warn!("console: Control event {event} for unknown port {port_id}");
+            return;
+        };
+
+        match event {
+            control::PORT_READY => {
+                if value != 1 {
+                    error!("console: The driver failed to add port {port_id}");
+                    return;
+                }
+                port.guest_ready = true;
+                let name = port.config().name.clone();
+                let host_connected = port.is_host_connected();
+                self.queue_control_message(port_index, control::PORT_NAME, 1, name.as_bytes());
+                if host_connected {
+                    self.queue_control_message(port_index, control::PORT_OPEN, 1, &[]);
+                }
+            }
+            control::PORT_OPEN => {
+                port.guest_open = value == 1;
+                if port.guest_open {
+                    // Input from the host peer is only read while the port is open.
+                    self.process_port_input(port_index);
+                }
+            }
+            _ => debug!("console: Ignoring control event {event} for port {port_id}"),
+        }
+    }
+
+    /// Moves the input of the host peer to the receive queue of a port.
+    pub(crate) fn process_port_input(&mut self, port_index: usize) {
+        // This is safe since we checked in the event handler that the device is activated.
+        let mem = self.device_state.mem().unwrap();
+        let queue_index = rx_queue_index(port_index);
+        let port = &mut self.ports[port_index];
+        let mut used = Vec::new();
+        let mut disconnect = false;
+
+        while port.guest_open {
+            if port.rx_pending.is_empty() {
+                match port.read_host() {
+                    Ok(0) => {
+                        disconnect = true;
+                        break;
+                    }
+                    Ok(_) => (),
+                    Err(err) if err.kind() == io::ErrorKind::WouldBlock => break,
+                    Err(err) => {
+                        error!("console: Could not read from the host peer: {err}");
+                        METRICS.event_fails.inc();
+                        disconnect = true;
+                        break;
+                    }
+                }
+            }
+
+            // If the driver didn't provide buffers, the input stays pending until it does.
+            let Some(head) = self.queues[queue_index].pop() else {
+                break;
+            };
+            let len = match write_chain(mem, head, &port.rx_pending) {
+                Ok(len) => {
+                    port.rx_pending.drain(..len);
+                    METRICS.rx_bytes_count.add(len as u64);
+                    len
+                }
+                Err(err) => {
+                    error!("console: Could not write port input: {err}");
+                    METRICS.event_fails.inc();
+                    0
+                }
+            };
+            used.push((head.index, len));
+        }
+
+        if !used.is_empty() {
+            for (desc_index, len) in used {
+                self.add_used(queue_index, desc_index, len);
+            }
+            self.signal_used_queue_or_log();
+        }
+        if disconnect {
+            self.disconnect_port(port_index);
+        }
+    }
+
+    /// Moves the output of the guest from the transmit queue of a port to the host.
+    pub(crate) fn process_port_output(&mut self, port_index: usize) {
+        // This is safe since we checked in the event handler that the device is activated.
+        let mem = self.device_state.mem().unwrap();
+        let queue_index = rx_queue_index(port_index) + 1;
+        let port = &mut self.ports[port_index];
+        let mut used = Vec::new();
+
+        // Output which the host peer couldn't take before goes first. The transmit queue is left
+        // untouched until the host peer takes all of it.
+        let mut result = port.flush_tx();
+        while result.is_ok() && !port.has_tx_pending() {
+            let Some(head) = self.queues[queue_index].pop() else {
+                break;
+            };
+            match read_chain(mem, head) {
+                Ok(data) => {
+                    METRICS.tx_bytes_count.add(data.len() as u64);
+                    result = port.write_host(&data);
+                }
+                Err(err) => {
+                    error!("console: Could not read port output: {err}");
+                    METRICS.event_fails.inc();
+                }
+            }
+            used.push(head.index);
+        }
+
+        if !used.is_empty() {
+            for desc_index in used {
+                self.add_used(queue_index, desc_index, 0);
+            }
+            self.signal_used_queue_or_log();
+        }
+        if let Err(err) = result {
+            error!("console: Could not write to the host: {err}");
+            METRICS.event_fails.inc();
+            self.disconnect_port(port_index);
+        }
+    }
+
+    /// Handles a notification on one of the queues.
+    pub(crate) fn process_queue_event(&mut self, queue_index: usize) {
+        if let Err(err) = self.queue_events[queue_index].read() {
+            error!("console: Failed to read queue event: {err}");
+            METRICS.event_fails.inc();
+            return;
+        }
+
+        match queue_index {
+            CONTROL_RX_QUEUE => self.process_control_rx(),
+            CONTROL_TX_QUEUE => self.process_control_tx(),
+            _ => {
+                let port_index = if queue_index < CONTROL_RX_QUEUE {
+                    0
+                } else {
+                    queue_index / 2 - 1
+                };
+                if port_index >= self.ports.len() {
+                    warn!("console: Notification on the queue of unknown port {port_index}");
+                } else if queue_index % 2 == 0 {
+                    self.process_port_input(port_index);
+                } else {
+                    self.process_port_output(port_index);
+                }
+            }
+        }
+    }
+
+    /// Accepts the connection of a host peer on the socket of a port. Returns the connection if
+    /// it was kept, so that it can be registered with the event manager.
+    pub(crate) fn accept_connection(&mut self, port_index: usize) -> Option<&UnixStream> {
+        match self.ports[port_index].accept() {
+            Ok(true) => {
+                METRICS.host_connections.inc();
+                if self.ports[port_index].guest_ready {
+                    self.queue_control_message(port_index, control::PORT_OPEN, 1, &[]);
+                    self.process_control_rx();
+                }
+                self.ports[port_index].stream()
+            }
+            Ok(false) => None,
+            Err(err) => {
+                error!("console: Could not accept host connection: {err}");
+                METRICS.event_fails.inc();
+                None
+            }
+        }
+    }
+
+    /// Drops the connection of the host peer of a port, and lets the guest know about it.
+    pub(crate) fn disconnect_port(&mut self, port_index: usize) {
+        let port = &mut self.ports[port_index];
+        if let Some(stream) = port.disconnect() {
+            METRICS.host_disconnections.inc();
+            self.closed_streams.push((port_index, stream));
+            if self.ports[port_index].guest_ready {
+                self.queue_control_message(port_index, control::PORT_OPEN, 0, &[]);
+                self.process_control_rx();
+            }
+        }
+    }
+
+    pub(crate) fn is_multiport(&self) -> bool {
+        self.has_feature(u64::from(VIRTIO_CONSOLE_F_MULTIPORT))
+    }
+
+    pub(crate) fn set_avail_features(&mut self, features: u64) {
+        self.avail_features = features;
+    }
+
+    pub(crate) fn set_acked_features(&mut self, features: u64) {
+        self.acked_features = features;
+    }
+
+    pub(crate) fn set_irq_status(&mut self, status: u32) {
+        self.irq_trigger.irq_status = Arc::new(AtomicU32::new(status));
+    }
+
+    pub(crate) fn set_activated(&mut self, mem: GuestMemoryMmap) {
+        self.device_state = DeviceState::Activated(mem);
+    }
+
+    pub(crate) fn activate_event(&self) -> &EventFd {
+        &self.activate_event
+    }
+}
+
+impl VirtioDevice for VirtioConsole {
+    fn device_type(&self) -> u32 {
+        TYPE_CONSOLE
+    }
+
+    fn queues(&self) -> &[Queue] {
+        &self.queues
+    }
+
+    fn queues_mut(&mut self) -> &mut [Queue] {
+        &mut self.queues
+    }
+
+    fn queue_events(&self) -> &[EventFd] {
+        &self.queue_events
+    }
+
+    fn interrupt_trigger(&self) -> &IrqTrigger {
+        &self.irq_trigger
+    }
+
+    fn avail_features(&self) -> u64 {
+        self.avail_features
+    }
+
+    fn acked_features(&self) -> u64 {
+        self.acked_features
+    }
+
+    fn set_acked_features(&mut self, acked_features: u64) {
+        self.acked_features = acked_features;
+    }
+
+    fn read_config(&self, offset: u64, data: &mut [u8]) {
+        let config_space = self.config_space();
+        if let Some(config_space_bytes) = config_space.get(u64_to_usize(offset)..) {
+            let len = config_space_bytes.len().min(data.len());
+            data[..len].copy_from_slice(&config_space_bytes[..len]);
+        } else {
+            error!("console: Failed to read config space");
+            METRICS.cfg_fails.inc();
+        }
+    }
+
+    fn write_config(&mut self, _offset: u64, _data: &[u8]) {
+        // Emergency writes aren't offered, so the config space is read-only.
+    }
+
+    fn is_activated(&self) -> bool {
+        self.device_state.is_activated()
+    }
+
+    fn activate(&mut self, mem: GuestMemoryMmap) -> Result<(), ActivateError> {
+        for q in self.queues.iter_mut() {
+            q.initialize(&mem)
+                .map_err(ActivateError::QueueMemoryError)?;
+        }
+
+        // Without the control queues, the first port is the only one and it's always open.
+        if !self.is_multiport() {
+            if let Some(port) = self.ports.first_mut() {
+                port.guest_ready = true;
+                port.guest_open = true;
+            }
+        }
+
+        self.activate_event.write(1).map_err(|_| {
+            METRICS.activate_fails.inc();
+            ActivateError::EventFd
+        })?;
+        self.device_state = DeviceState::Activated(mem);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use std::io::{Read, Write};
+    use std::time::Duration;
+
+    use vmm_sys_util::tempfile::TempFile;
+
+    use super::*;
+    use crate::devices::virtio::queue::VIRTQ_DESC_F_WRITE;
+    use crate::devices::virtio::test_utils::test::{
+        VirtioTestDevice, VirtioTestHelper, create_virtio_mem,
+    };
+    use crate::vstate::memory::GuestAddress;
+
+    impl VirtioTestDevice for VirtioConsole {
+        fn set_queues(&mut self, queues: Vec<Queue>) {
+            self.queues = queues;
+        }
+
+        fn num_queues() -> usize {
+            num_queues(1)
+        }
+    }
+
+    pub(crate) fn socket_port(port_id: &str, name: &str) -> ConsolePortConfig {
+        let mut tmp = TempFile::new().unwrap();
+        tmp.remove().unwrap();
+        ConsolePortConfig {
+            port_id: port_id.to_string(),
+            name: name.to_string(),
+            socket_path: Some(tmp.as_path().to_str().unwrap().to_string()),
+            file_path: None,
+        }
+    }
+
+    fn control_message(port_id: u32, event: u16, value: u16, data: &[u8]) -> Vec<u8> {
+        let mut msg = Vec::new();
+        msg.extend_from_slice(&port_id.to_le_bytes());
+        msg.extend_from_slice(&event.to_le_bytes());
+        msg.extend_from_slice(&value.to_le_bytes());
+        msg.extend_from_slice(data);
+        msg
+    }
+
+    fn console_with_ports(configs: &[ConsolePortConfig]) -> VirtioConsole {
+        let mut console = VirtioConsole::new().unwrap();
+        for config in configs {
+            console.add_port(config.clone()).unwrap();
+        }
+        console
+    }
+
+    fn cleanup(configs: &[ConsolePortConfig]) {
+        for config in configs {
+            if let Some(path) = &config.socket_path {
+                std::fs::remove_file(path).unwrap();
+            }
+        }
+    }
+
+    #[test]
+    fn test_add_port() {
+        let configs = [socket_port("port0", "agent"), socket_port("port1", "log")];
+        let mut console = VirtioConsole::new().unwrap();
+        assert_eq!(console.queues().len(), 4);
+
+        console.add_port(configs[0].clone()).unwrap();
+        assert_eq!(console.queues().len(), 4);
+        assert_eq!(console.queue_events().len(), 4);
+        console.add_port(configs[1].clone()).unwrap();
+        assert_eq!(console.queues().len(), 6);
+        assert_eq!(console.queue_events().len(), 6);
+        assert_eq!(console.port_configs(), configs);
+
+        // Replacing a port can reuse its socket.
+        let mut config = configs[0].clone();
+        config.name = "agent2".to_string();
+        console.add_port(config.clone()).unwrap();
+        assert_eq!(console.port_configs(), vec![config, configs[1].clone()]);
+        assert_eq!(console.queues().len(), 6);
+
+        // The socket of another port can't be reused.
+        let mut config = configs[1].clone();
+        config.port_id = "port2".to_string();
+        assert!(matches!(
+            console.add_port(config),
+            Err(VirtioConsoleError::BindSocket(_))
+        ));
+        assert_eq!(console.queues().len(), 6);
+
+        // A port that can't be replaced is left in place.
+        let mut config = configs[1].clone();
+        config.socket_path = configs[0].socket_path.clone();
+        assert!(matches!(
+            console.add_port(config),
+            Err(VirtioConsoleError::BindSocket(_))
+        ));
+        assert_eq!(console.port_configs()[1], configs[1]);
+        UnixStream::connect(configs[1].socket_path.as_ref().unwrap()).unwrap();
+
+        // Ports need exactly one backend.
+        let mut config = configs[1].clone();
+        config.file_path = Some("/tmp/console.log".to_string());
+        assert!(matches!(
+            console.add_port(config),
+            Err(VirtioConsoleError::InvalidBackend)
+        ));
+        assert_eq!(console.port_configs()[1], configs[1]);
+
+        cleanup(&configs);
+    }
+
+    #[test]
+    fn test_config_space() {
+        let configs = [socket_port("port0", "agent"), socket_port("port1", "log")];
+        let console = console_with_ports(&configs);
+
+        let mut data = [0xffu8; 12];
+        console.read_config(0, &mut data);
+        assert_eq!(data, [0, 0, 0, 0, 2, 0, 0, 0, 0, 0, 0, 0]);
+
+        let mut data = [0u8; 4];
+        console.read_config(4, &mut data);
+        assert_eq!(u32::from_le_bytes(data), 2);
+
+        assert_eq!(console.device_type(), TYPE_CONSOLE);
+        assert_eq!(console.id(), CONSOLE_DEV_ID);
+        assert_eq!(
+            console.avail_features(),
+            (1 << VIRTIO_F_VERSION_1) | (1 << VIRTIO_CONSOLE_F_MULTIPORT)
+        );
+
+        cleanup(&configs);
+    }
+
+    #[test]
+    fn test_control_messages() {
+        let file = TempFile::new().unwrap();
+        let configs = [
+            socket_port("port0", "agent"),
+            ConsolePortConfig {
+                port_id: "port1".to_string(),
+                name: "log".to_string(),
+                socket_path: None,
+                file_path: Some(file.as_path().to_str().unwrap().to_string()),
+            },
+        ];
+        let mut console = console_with_ports(&configs);
+
+        // Both ports get added once the driver is ready.
+        console.handle_control_message(&control_message(0, control::DEVICE_READY, 1, &[]));
+        assert_eq!(
+            console.control_pending,
+            [
+                control_message(0, control::DEVICE_ADD, 0, &[]),
+                control_message(1, control::DEVICE_ADD, 0, &[]),
+            ]
+        );
+        console.control_pending.clear();
+
+        // Ports get named once ready. Only the file port has its host side open.
+        console.handle_control_message(&control_message(0, control::PORT_READY, 1, &[]));
+        console.handle_control_message(&control_message(1, control::PORT_READY, 1, &[]));
+        assert_eq!(
+            console.control_pending,
+            [
+                control_message(0, control::PORT_NAME, 1, b"agent"),
+                control_message(1, control::PORT_NAME, 1, b"log"),
+                control_message(1, control::PORT_OPEN, 1, &[]),
+            ]
+        );
+        assert!(console.ports[0].guest_ready);
+        assert!(console.ports[1].guest_ready);
+        console.control_pending.clear();
+
+        // Messages for unknown ports and short messages are ignored.
+        console.handle_control_message(&control_message(5, control::PORT_READY, 1, &[]));
+        console.handle_control_message(&[0, 0, 0]);
+        assert!(console.control_pending.is_empty());
+
+        cleanup(&configs);
+    }
+
+    #[test]
+    fn test_socket_port() {
+        let configs = [socket_port("port0", "agent")];
+        let mem = create_virtio_mem();
+        let mut th = VirtioTestHelper::<VirtioConsole>::new(&mem, console_with_ports(&configs));
+        let features = th.device().avail_features();
+        th.device().set_acked_features(features);
+        th.activate_device(&mem);
+
+        // Connect a host peer.
+        let mut peer = UnixStream::connect(configs[0].socket_path.as_ref().unwrap()).unwrap();
+        peer.set_read_timeout(Some(Duration::from_millis(100)))
+            .unwrap();
+        th.emulate_for_msec(100).unwrap();
+        assert!(th.device().ports[0].is_host_connected());
+
+        // The host input is only read once the guest opened the port.
+        th.device().ports[0].guest_ready = true;
+        peer.write_all(b"hello").unwrap();
+        th.add_desc_chain(0, 0, &[(0, 16, VIRTQ_DESC_F_WRITE)]);
+        th.emulate_for_msec(100).unwrap();
+        assert_eq!(th.device().queues[0].len(), 1);
+
+        th.device()
+            .handle_control_message(&control_message(0, control::PORT_OPEN, 1, &[]));
+        assert_eq!(th.device().queues[0].len(), 0);
+        let mut data = [0u8; 5];
+        mem.read_slice(&mut data, GuestAddress(th.data_address()))
+            .unwrap();
+        assert_eq!(&data, b"hello");
+
+        // The guest output goes to the host peer.
+        let addr = th.data_address() + 0x1000;
+        mem.write_slice(b"world", GuestAddress(addr)).unwrap();
+        th.add_desc_chain(1, 0x1000, &[(1, 5, 0)]);
+        th.emulate_for_msec(100).unwrap();
+        let mut data = [0u8; 5];
+        peer.read_exact(&mut data).unwrap();
+        assert_eq!(&data, b"world");
+
+        // The guest is told when the host peer goes away.
+        drop(peer);
+        th.emulate_for_msec(100).unwrap();
+        assert!(!th.device().ports[0].is_host_connected());
+        assert!(th.device().closed_streams.is_empty());
+        assert_eq!(
+            th.device().control_pending,
+            [control_message(0, control::PORT_OPEN, 0, &[])]
+        );
+
+        cleanup(&configs);
+    }
+}
@@ -0,0 +1,161 @@
+// Copyright 2025 Amazon.com, Inc. or its affiliates. All Rights Reserved.
+// SPDX-License-Identifier: Apache-2.0
+
+use event_manager::{EventOps, Events, MutEventSubscriber};
+use vmm_sys_util::epoll::EventSet;
+
+use super::VirtioConsole;
+use crate::devices::virtio::device::VirtioDevice;
+use crate::logger::{error, warn};
+
+impl VirtioConsole {
+    const PROCESS_ACTIVATE: u32 = 0;
+    // Queue notifications use the queue index, offset by this value.
+    const PROCESS_QUEUE: u32 = 1;
+    // Connections on the socket of a port use the port index, offset by this value.
+    const PROCESS_LISTENER: u32 = 100;
+    // Events on the connection of a host peer use the port index, offset by this value.
+    const PROCESS_STREAM: u32 = 200;
+
+    // Connections of host peers are edge triggered: the device stops reading input when the
+    // guest has no buffers, and resumes when it provides some.
+    const STREAM_EVENTS: EventSet = EventSet::IN
+        .union(EventSet::OUT)
+        .union(EventSet::EDGE_TRIGGERED);
+
+    fn data(offset: u32, index: usize) -> u32 {
+        offset + u32::try_from(index).unwrap()
+    }
+
+    fn register_runtime_events(&self, ops: &mut EventOps) {
+        for (index, queue_evt) in self.queue_events().iter().enumerate() {
+            if let Err(err) = ops.add(Events::with_data(
+                queue_evt,
+                Self::data(Self::PROCESS_QUEUE, index),
+                EventSet::IN,
+            )) {
+                error!("console: Failed to register queue event: {err}");
+            }
+        }
+        for (index, port) in self.ports.iter().enumerate() {
+            if let Some(listener) = port.listener() {
+                if let Err(err) = ops.add(Events::with_data(
+                    listener,
+                    Self::data(Self::PROCESS_LISTENER, index),
+                    EventSet::IN,
+                )) {
+                    error!("console: Failed to register port socket: {err}");
+                }
+            }
+        }
+    }
+
+    fn register_activate_event(&self, ops: &mut EventOps) {
+        if let Err(err) = ops.add(Events::with_data(
+            self.activate_event(),
+            Self::PROCESS_ACTIVATE,
+            EventSet::IN,
+        )) {
+            error!("console: Failed to register activate event: {err}");
+        }
+    }
+
+    fn process_activate_event(&self, ops: &mut EventOps) {
+        if let Err(err) = self.activate_event().read() {
+            error!("console: Failed to consume activate event: {err}");
+        }
+
+        // Register runtime events
+        self.register_runtime_events(ops);
+
+        // Remove activate event
+        if let Err(err) = ops.remove(Events::with_data(
+            self.activate_event(),
+            Self::PROCESS_ACTIVATE,
+            EventSet::IN,
+        )) {
+            error!("console: Failed to un-register activate event: {err}");
+        }
+    }
+
+    fn process_listener_event(&mut self, port_index: usize, ops: &mut EventOps) {
+        if let Some(stream) = self.accept_connection(port_index) {
+            if let Err(err) = ops.add(Events::with_data(
+                stream,
+                Self::data(Self::PROCESS_STREAM, port_index),
+                Self::STREAM_EVENTS,
+            )) {
+                error!("console: Failed to register host connection: {err}");
+            }
+        }
+    }
+
+    fn process_stream_event(&mut self, port_index: usize, event_set: EventSet) {
+        if event_set.contains(EventSet::OUT) {
+            self.process_port_output(port_index);
+        }
+        if event_set.contains(EventSet::IN) {
+            self.process_port_input(port_index);
+        }
+        if event_set.intersects(EventSet::HANG_UP | EventSet::ERROR) {
+            self.disconnect_port(port_index);
+        }
+    }
+
+    fn unregister_closed_streams(&mut self, ops: &mut EventOps) {
+        for (port_index, stream) in self.closed_streams.drain(..) {
+            if let Err(err) = ops.remove(Events::with_data(
+                &stream,
+                Self::data(Self::PROCESS_STREAM, port_index),
+                Self::STREAM_EVENTS,
+            )) {
+                error!("console: Failed to un-register host connection: {err}");
+            }
+        }
+    }
+}
+
+impl MutEventSubscriber for VirtioConsole {
+    fn init(&mut self, ops: &mut EventOps) {
+        // This function can be called during different points in the device lifetime:
+        //  - shortly after device creation,
+        //  - on device activation (is-activated already true at this point),
+        //  - on device restore from snapshot.
+        if self.is_activated() {
+            self.register_runtime_events(ops);
+            // Control messages may have been queued on restore.
+            if self.is_multiport() {
+                self.process_control_rx();
+            }
+        } else {
+            self.register_activate_event(ops);
+        }
+    }
+
+    fn process(&mut self, events: Events, ops: &mut EventOps) {
+        let event_set = events.event_set();
+        let source = events.data();
+
+        if !self.is_activated() {
+            warn!("console: The device is not activated yet. Spurious event received: {source}");
+            return;
+        }
+
+        let index = |offset: u32| usize::try_from(source - offset).unwrap();
+        match source {
+            Self::PROCESS_ACTIVATE => self.process_activate_event(ops),
+            _ if source >= Self::PROCESS_STREAM => {
+                self.process_stream_event(index(Self::PROCESS_STREAM), event_set)
+            }
+            _ if !event_set.contains(EventSet::IN) => {
+                warn!("console: Received unknown event: {event_set:?} from source {source}");
+            }
+            _ if source >= Self::PROCESS_LISTENER => {
+                self.process_listener_event(index(Self::PROCESS_LISTENER), ops)
+            }
+            _ => self.process_queue_event(index(Self::PROCESS_QUEUE)),
+        }
+
+        self.unregister_closed_streams(ops);
+    }
+}
@@ -0,0 +1,89 @@
+// Copyright 2025 Amazon.com, Inc. or its affiliates. All Rights Reserved.
+// SPDX-License-Identifier: Apache-2.0
+
+//! Defines the metrics system for the virtio-console device.
+//!
+//! # Metrics format
+//! The metrics are flushed in JSON when requested by vmm::logger::metrics::METRICS.write().
+//!
+//! ## JSON example with metrics:
+//! ```json
+//!  "console": {
+//!     "activate_fails": "SharedIncMetric",
+//!     "cfg_fails": "SharedIncMetric",
+//!     "event_fails": "SharedIncMetric",
+//!     ...
+//!  }
+//! }
+//! ```
+//! Each `console` field in the example above is a serializable `ConsoleDeviceMetrics` structure
+//! collecting metrics such as `activate_fails`, `event_fails` etc. for the virtio-console device.
+//! Since there is a single virtio-console device, whatever the number of ports, `console`
+//! represents the aggregate metrics of all the ports.
+
+use serde::ser::SerializeMap;
+use serde::{Serialize, Serializer};
+
+use crate::logger::SharedIncMetric;
+
+/// Stores aggregated virtio-console metrics
+pub(super) static METRICS: ConsoleDeviceMetrics = ConsoleDeviceMetrics::new();
+
+/// Called by METRICS.flush(), this function facilitates serialization of virtio-console device
+/// metrics.
+pub fn flush_metrics<S: Serializer>(serializer: S) -> Result<S::Ok, S::Error> {
+    let mut seq = serializer.serialize_map(Some(1))?;
+    seq.serialize_entry("console", &METRICS)?;
+    seq.end()
+}
+
+#[derive(Debug, Serialize)]
+pub(super) struct ConsoleDeviceMetrics {
+    /// Number of device activation failures
+    pub activate_fails: SharedIncMetric,
+    /// Number of times reading the config space failed
+    pub cfg_fails: SharedIncMetric,
+    /// Number of failures while handling queue or host connection events
+    pub event_fails: SharedIncMetric,
+    /// Number of bytes sent to the guest
+    pub rx_bytes_count: SharedIncMetric,
+    /// Number of bytes received from the guest
+    pub tx_bytes_count: SharedIncMetric,
+    /// Number of host peers which connected to a port
+    pub host_connections: SharedIncMetric,
+    /// Number of host peers which disconnected from a port
+    pub host_disconnections: SharedIncMetric,
+}
+impl ConsoleDeviceMetrics {
+    /// Const default construction.
+    const fn new() -> Self {
+        Self {
+            activate_fails: SharedIncMetric::new(),
+            cfg_fails: SharedIncMetric::new(),
+            event_fails: SharedIncMetric::new(),
+            rx_bytes_count: SharedIncMetric::new(),
+            tx_bytes_count: SharedIncMetric::new(),
+            host_connections: SharedIncMetric::new(),
+            host_disconnections: SharedIncMetric::new(),
+        }
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+    use crate::logger::IncMetric;
+
+    #[test]
+    fn test_console_dev_metrics() {
+        let console_metrics: ConsoleDeviceMetrics = ConsoleDeviceMetrics::new();
+        let console_metrics_local: String = serde_json::to_string(&console_metrics).unwrap();
+        // the 1st serialize flushes the metrics and resets values to 0 so that
+        // we can compare the values with local metrics.
+        serde_json::to_string(&METRICS).unwrap();
+        let console_metrics_global: String = serde_json::to_string(&METRICS).unwrap();
+        assert_eq!(console_metrics_local, console_metrics_global);
+        console_metrics.tx_bytes_count.inc();
+        assert_eq!(console_metrics.tx_bytes_count.count(), 1);
+    }
+}
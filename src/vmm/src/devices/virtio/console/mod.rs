@@ -0,0 +1,70 @@
+// Copyright 2025 Amazon.com, Inc. or its affiliates. All Rights Reserved.
+// SPDX-License-Identifier: Apache-2.0
+
+//! Implements a virtio-console device with multiport support. Each port is backed on the host
+//! either by a Unix socket, on which a single peer can connect, or by a file to which the guest
+//! output is appended. The guest exposes the ports as `/dev/virtio-ports/<name>`.
+
+pub mod device;
+mod event_handler;
+pub mod metrics;
+pub mod persist;
+mod port;
+
+pub use self::device::VirtioConsole;
+
+/// Device ID used in MMIO device identification.
+/// Because the virtio-console device is unique per-vm, this ID can be hardcoded.
+pub const CONSOLE_DEV_ID: &str = "console";
+
+/// Maximum number of ports of the virtio-console device.
+pub const MAX_PORTS: usize = 31;
+
+/// Index of the queue on which the device sends control messages to the driver.
+pub(crate) const CONTROL_RX_QUEUE: usize = 2;
+/// Index of the queue on which the driver sends control messages to the device.
+pub(crate) const CONTROL_TX_QUEUE: usize = 3;
+
+/// Feature bit enabling multiple ports and the control queues.
+pub(crate) const VIRTIO_CONSOLE_F_MULTIPORT: u32 = 1;
+
+/// Control events, as defined in `include/uapi/linux/virtio_console.h`.
+pub(crate) mod control {
+    /// Sent by the driver once it's ready to receive ports.
+    pub const DEVICE_READY: u16 = 0;
+    /// Sent by the device to add a port.
+    pub const DEVICE_ADD: u16 = 1;
+    /// Sent by the driver once a port is ready.
+    pub const PORT_READY: u16 = 3;
+    /// Sent by either side when it opens or closes a port.
+    pub const PORT_OPEN: u16 = 6;
+    /// Sent by the device to name a port. The name follows the control message.
+    pub const PORT_NAME: u16 = 7;
+}
+
+/// Returns the number of queues the device needs to serve `nr_ports` ports. There is a pair of
+/// queues per port, in addition to the pair of control queues.
+pub(crate) fn num_queues(nr_ports: usize) -> usize {
+    2 * (nr_ports.max(1) + 1)
+}
+
+/// Returns the index of the receive queue of a port. The transmit queue directly follows it.
+/// Port 0 uses the first two queues, which precede the control queues.
+pub(crate) fn rx_queue_index(port: usize) -> usize {
+    if port == 0 { 0 } else { 2 * port + 2 }
+}
+
+/// Virtio-console device error.
+#[derive(Debug, thiserror::Error, displaydoc::Display)]
+pub enum VirtioConsoleError {
+    /// Error while handling an Event file descriptor: {0}
+    EventFd(std::io::Error),
+    /// Could not bind the port socket: {0}
+    BindSocket(std::io::Error),
+    /// Could not open the port file: {0}
+    OpenFile(std::io::Error),
+    /// A port must have either a socket or a file backend.
+    InvalidBackend,
+    /// The virtio-console device supports at most {MAX_PORTS} ports.
+    TooManyPorts,
+}
@@ -0,0 +1,184 @@
+// Copyright 2025 Amazon.com, Inc. or its affiliates. All Rights Reserved.
+// SPDX-License-Identifier: Apache-2.0
+
+//! Defines the structures needed for saving/restoring the virtio-console device.
+
+use serde::{Deserialize, Serialize};
+
+use super::port::ConsolePort;
+use super::{VirtioConsole, VirtioConsoleError, control, num_queues};
+use crate::devices::virtio::TYPE_CONSOLE;
+use crate::devices::virtio::persist::{PersistError as VirtioStateError, VirtioDeviceState};
+use crate::devices::virtio::queue::FIRECRACKER_MAX_QUEUE_SIZE;
+use crate::snapshot::Persist;
+use crate::vmm_config::console::ConsolePortConfig;
+use crate::vstate::memory::GuestMemoryMmap;
+
+/// State of a virtio-console port.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ConsolePortState {
+    port_id: String,
+    name: String,
+    socket_path: Option<String>,
+    file_path: Option<String>,
+    guest_ready: bool,
+    guest_open: bool,
+    host_connected: bool,
+}
+
+/// State of the virtio-console device.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct VirtioConsoleState {
+    virtio_state: VirtioDeviceState,
+    ports: Vec<ConsolePortState>,
+    control_pending: Vec<Vec<u8>>,
+}
+
+#[derive(Debug)]
+pub struct VirtioConsoleConstructorArgs(GuestMemoryMmap);
+
+impl VirtioConsoleConstructorArgs {
+    pub fn new(mem: GuestMemoryMmap) -> Self {
+        Self(mem)
+    }
+}
+
+#[derive(Debug, thiserror::Error, displaydoc::Display)]
+pub enum VirtioConsolePersistError {
+    /// Create virtio-console: {0}
+    CreateConsole(#[from] VirtioConsoleError),
+    /// Virtio state: {0}
+    VirtioState(#[from] VirtioStateError),
+}
+
+impl Persist<'_> for VirtioConsole {
+    type State = VirtioConsoleState;
+    type ConstructorArgs = VirtioConsoleConstructorArgs;
+    type Error = VirtioConsolePersistError;
+
+    fn save(&self) -> Self::State {
+        VirtioConsoleState {
+            virtio_state: VirtioDeviceState::from_device(self),
+            ports: self
+                .ports
+                .iter()
+                .map(|port| ConsolePortState {
+                    port_id: port.config().port_id.clone(),
+                    name: port.config().name.clone(),
+                    socket_path: port.config().socket_path.clone(),
+                    file_path: port.config().file_path.clone(),
+                    guest_ready: port.guest_ready,
+                    guest_open: port.guest_open,
+                    host_connected: port.is_host_connected(),
+                })
+                .collect(),
+            control_pending: self.control_pending.iter().cloned().collect(),
+        }
+    }
+
+    fn restore(
+        constructor_args: Self::ConstructorArgs,
+        state: &Self::State,
+    ) -> Result<Self, Self::Error> {
+        let queues = state.virtio_state.build_queues_checked(
+            &constructor_args.0,
+            TYPE_CONSOLE,
+            num_queues(state.ports.len()),
+            FIRECRACKER_MAX_QUEUE_SIZE,
+        )?;
+
+        let ports = state
+            .ports
+            .iter()
+            .map(|port_state| {
+                let mut port = ConsolePort::new(ConsolePortConfig {
+                    port_id: port_state.port_id.clone(),
+                    name: port_state.name.clone(),
+                    socket_path: port_state.socket_path.clone(),
+                    file_path: port_state.file_path.clone(),
+                })?;
+                port.guest_ready = port_state.guest_ready;
+                port.guest_open = port_state.guest_open;
+                Ok(port)
+            })
+            .collect::<Result<Vec<_>, VirtioConsoleError>>()?;
+
+        let mut console = VirtioConsole::new_with_queues(queues, ports)?;
+        console.set_avail_features(state.virtio_state.avail_features);
+        console.set_acked_features(state.virtio_state.acked_features);
+        console.set_irq_status(state.virtio_state.interrupt_status);
+        console.control_pending = state.control_pending.iter().cloned().collect();
+
+        // Host peers don't survive the snapshot, so let the guest know that they are gone.
+        for (port_index, port_state) in state.ports.iter().enumerate() {
+            let port = &console.ports[port_index];
+            if port.guest_ready && port_state.host_connected && !port.is_host_connected() {
+                console.queue_control_message(port_index, control::PORT_OPEN, 0, &[]);
+            }
+        }
+
+        if state.virtio_state.activated {
+            console.set_activated(constructor_args.0);
+        }
+
+        Ok(console)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::Ordering;
+
+    use super::*;
+    use crate::devices::virtio::console::CONSOLE_DEV_ID;
+    use crate::devices::virtio::console::device::tests::socket_port;
+    use crate::devices::virtio::device::VirtioDevice;
+    use crate::devices::virtio::test_utils::test::create_virtio_mem;
+    use crate::snapshot::Snapshot;
+
+    #[test]
+    fn test_persistence() {
+        let configs = [socket_port("port0", "agent"), socket_port("port1", "log")];
+        let mut mem = vec![0u8; 4096];
+        let mut console = VirtioConsole::new().unwrap();
+        for config in &configs {
+            console.add_port(config.clone()).unwrap();
+        }
+        console.ports[1].guest_ready = true;
+        console.ports[1].guest_open = true;
+
+        Snapshot::serialize(&mut mem.as_mut_slice(), &console.save()).unwrap();
+
+        // The restored device binds the same sockets.
+        console.ports.clear();
+        for config in &configs {
+            std::fs::remove_file(config.socket_path.as_ref().unwrap()).unwrap();
+        }
+
+        let guest_mem = create_virtio_mem();
+        let restored = VirtioConsole::restore(
+            VirtioConsoleConstructorArgs(guest_mem),
+            &Snapshot::deserialize(&mut mem.as_slice()).unwrap(),
+        )
+        .unwrap();
+
+        assert_eq!(restored.device_type(), TYPE_CONSOLE);
+        assert_eq!(restored.id(), CONSOLE_DEV_ID);
+        assert_eq!(restored.port_configs(), configs);
+        assert!(!restored.ports[0].guest_ready);
+        assert!(restored.ports[1].guest_ready);
+        assert!(restored.ports[1].guest_open);
+        assert_eq!(restored.queues().len(), 6);
+        assert_eq!(restored.is_activated(), console.is_activated());
+        assert_eq!(restored.avail_features(), console.avail_features());
+        assert_eq!(restored.acked_features(), console.acked_features());
+        assert_eq!(
+            restored.interrupt_status().load(Ordering::Relaxed),
+            console.interrupt_status().load(Ordering::Relaxed)
+        );
+
+        for config in &configs {
+            std::fs::remove_file(config.socket_path.as_ref().unwrap()).unwrap();
+        }
+    }
+}
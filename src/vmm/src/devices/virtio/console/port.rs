@@ -0,0 +1,200 @@
+// Copyright 2025 Amazon.com, Inc. or its affiliates. All Rights Reserved.
+// SPDX-License-Identifier: Apache-2.0
+
+//! Host side of the virtio-console ports.
+
+use std::fs::{File, OpenOptions};
+use std::io::{self, Read, Write};
+use std::os::unix::net::{UnixListener, UnixStream};
+
+use super::VirtioConsoleError;
+use crate::logger::warn;
+use crate::vmm_config::console::ConsolePortConfig;
+
+#[derive(Debug)]
+enum PortBackend {
+    /// Unix socket on which a single host peer can connect.
+    Socket {
+        listener: UnixListener,
+        stream: Option<UnixStream>,
+    },
+    /// File to which the guest output is appended.
+    File(File),
+}
+
+#[derive(Debug)]
+pub(crate) struct ConsolePort {
+    config: ConsolePortConfig,
+    backend: PortBackend,
+    /// Whether the driver has set up the port.
+    pub(crate) guest_ready: bool,
+    /// Whether a guest application has the port open.
+    pub(crate) guest_open: bool,
+    /// Bytes read from the host peer which didn't fit in the guest buffers yet.
+    pub(crate) rx_pending: Vec<u8>,
+    /// Guest output which the host peer couldn't take yet.
+    tx_pending: Vec<u8>,
+}
+
+impl ConsolePort {
+    pub(crate) fn new(config: ConsolePortConfig) -> Result<Self, VirtioConsoleError> {
+        Self::with_listener(config, None)
+    }
+
+    /// Builds a port which accepts its host peer on `listener` if given, instead of binding its
+    /// socket path.
+    pub(crate) fn with_listener(
+        config: ConsolePortConfig,
+        listener: Option<UnixListener>,
+    ) -> Result<Self, VirtioConsoleError> {
+        // The configuration is validated by the builder, but the one of restored ports comes
+        // from the snapshot.
+        let backend = match (&config.socket_path, &config.file_path) {
+            (Some(socket_path), None) => {
+                let listener = match listener {
+                    Some(listener) => listener,
+                    None => UnixListener::bind(socket_path)
+                        .and_then(|sock| sock.set_nonblocking(true).map(|_| sock))
+                        .map_err(VirtioConsoleError::BindSocket)?,
+                };
+                PortBackend::Socket {
+                    listener,
+                    stream: None,
+                }
+            }
+            (None, Some(file_path)) => OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(file_path)
+                .map(PortBackend::File)
+                .map_err(VirtioConsoleError::OpenFile)?,
+            _ => return Err(VirtioConsoleError::InvalidBackend),
+        };
+
+        Ok(ConsolePort {
+            config,
+            backend,
+            guest_ready: false,
+            guest_open: false,
+            rx_pending: Vec::new(),
+            tx_pending: Vec::new(),
+        })
+    }
+
+    pub(crate) fn config(&self) -> &ConsolePortConfig {
+        &self.config
+    }
+
+    pub(crate) fn listener(&self) -> Option<&UnixListener> {
+        match &self.backend {
+            PortBackend::Socket { listener, .. } => Some(listener),
+            PortBackend::File(_) => None,
+        }
+    }
+
+    pub(crate) fn stream(&self) -> Option<&UnixStream> {
+        match &self.backend {
+            PortBackend::Socket { stream, .. } => stream.as_ref(),
+            PortBackend::File(_) => None,
+        }
+    }
+
+    /// Whether the guest output can be consumed on the host. A file is always ready to take it.
+    pub(crate) fn is_host_connected(&self) -> bool {
+        match &self.backend {
+            PortBackend::Socket { stream, .. } => stream.is_some(),
+            PortBackend::File(_) => true,
+        }
+    }
+
+    /// Accepts a connection from a host peer. Returns whether the connection was kept, which
+    /// isn't the case if a peer is already connected.
+    pub(crate) fn accept(&mut self) -> io::Result<bool> {
+        let PortBackend::Socket { listener, stream } = &mut self.backend else {
+            return Ok(false);
+        };
+
+        let (new_stream, _) = listener.accept()?;
+        if stream.is_some() {
+            warn!(
+                "console: Port {} already has a host peer, dropping the new connection",
+                self.config.port_id
+            );
+            return Ok(false);
+        }
+        new_stream.set_nonblocking(true)?;
+        *stream = Some(new_stream);
+        Ok(true)
+    }
+
+    /// Drops the connection of the host peer, along with the data in flight.
+    pub(crate) fn disconnect(&mut self) -> Option<UnixStream> {
+        self.rx_pending.clear();
+        self.tx_pending.clear();
+        match &mut self.backend {
+            PortBackend::Socket { stream, .. } => stream.take(),
+            PortBackend::File(_) => None,
+        }
+    }
+
+    /// Reads the input of the host peer into `rx_pending`. Returns the number of bytes read,
+    /// 0 meaning that the peer closed the connection.
+    pub(crate) fn read_host(&mut self) -> io::Result<usize> {
+        let PortBackend::Socket {
+            stream: Some(stream),
+            ..
+        } = &mut self.backend
+        else {
+            return Err(io::Error::from(io::ErrorKind::WouldBlock));
+        };
+
+        let mut buf = [0u8; 4096];
+        let count = stream.read(&mut buf)?;
+        self.rx_pending.extend_from_slice(&buf[..count]);
+        Ok(count)
+    }
+
+    /// Whether some guest output is still waiting for the host peer.
+    pub(crate) fn has_tx_pending(&self) -> bool {
+        !self.tx_pending.is_empty()
+    }
+
+    /// Writes the guest output to the host. Whatever the host peer can't take right away is kept
+    /// until it becomes writable again. Output is dropped if no peer is connected.
+    pub(crate) fn write_host(&mut self, data: &[u8]) -> io::Result<()> {
+        match &mut self.backend {
+            PortBackend::File(file) => file.write_all(data),
+            PortBackend::Socket { stream: None, .. } => Ok(()),
+            PortBackend::Socket {
+                stream: Some(_), ..
+            } => {
+                self.tx_pending.extend_from_slice(data);
+                self.flush_tx()
+            }
+        }
+    }
+
+    /// Writes as much of the pending guest output as the host peer can take.
+    pub(crate) fn flush_tx(&mut self) -> io::Result<()> {
+        let PortBackend::Socket {
+            stream: Some(stream),
+            ..
+        } = &mut self.backend
+        else {
+            self.tx_pending.clear();
+            return Ok(());
+        };
+
+        while !self.tx_pending.is_empty() {
+            match stream.write(&self.tx_pending) {
+                Ok(0) => return Err(io::Error::from(io::ErrorKind::WriteZero)),
+                Ok(count) => {
+                    self.tx_pending.drain(..count);
+                }
+                Err(err) if err.kind() == io::ErrorKind::WouldBlock => break,
+                Err(err) => return Err(err),
+            }
+        }
+        Ok(())
+    }
+}
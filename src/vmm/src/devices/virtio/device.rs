@@ -54,10 +54,15 @@ pub enum IrqType {
 }
 
 /// Helper struct that is responsible for triggering guest IRQs
+///
+/// Used vring and configuration change interrupts are signaled on separate event fds, so that
+/// transports with per-source interrupt vectors (e.g. MSI-X) can route them independently. The
+/// MMIO transport connects both of them to the same legacy interrupt line.
 #[derive(Debug)]
 pub struct IrqTrigger {
     pub(crate) irq_status: Arc<AtomicU32>,
     pub(crate) irq_evt: EventFd,
+    pub(crate) config_evt: EventFd,
 }
 
 impl IrqTrigger {
@@ -65,17 +70,18 @@ impl IrqTrigger {
         Ok(Self {
             irq_status: Arc::new(AtomicU32::new(0)),
             irq_evt: EventFd::new(libc::EFD_NONBLOCK)?,
+            config_evt: EventFd::new(libc::EFD_NONBLOCK)?,
         })
     }
 
     pub fn trigger_irq(&self, irq_type: IrqType) -> Result<(), std::io::Error> {
-        let irq = match irq_type {
-            IrqType::Config => VIRTIO_MMIO_INT_CONFIG,
-            IrqType::Vring => VIRTIO_MMIO_INT_VRING,
+        let (irq, evt) = match irq_type {
+            IrqType::Config => (VIRTIO_MMIO_INT_CONFIG, &self.config_evt),
+            IrqType::Vring => (VIRTIO_MMIO_INT_VRING, &self.irq_evt),
         };
         self.irq_status.fetch_or(irq, Ordering::SeqCst);
 
-        self.irq_evt.write(1).map_err(|err| {
+        evt.write(1).map_err(|err| {
             error!("Failed to send irq to the guest: {:?}", err);
             err
         })?;
@@ -202,7 +208,11 @@ pub(crate) mod tests {
 
     impl IrqTrigger {
         pub fn has_pending_irq(&self, irq_type: IrqType) -> bool {
-            if let Ok(num_irqs) = self.irq_evt.read() {
+            let evt = match irq_type {
+                IrqType::Config => &self.config_evt,
+                IrqType::Vring => &self.irq_evt,
+            };
+            if let Ok(num_irqs) = evt.read() {
                 if num_irqs == 0 {
                     return false;
                 }
@@ -235,10 +245,12 @@ pub(crate) mod tests {
         irq_trigger.trigger_irq(IrqType::Vring).unwrap();
         assert!(irq_trigger.has_pending_irq(IrqType::Vring));
 
-        // Check trigger_irq() failure case (irq_evt is full).
+        // Check trigger_irq() failure case (event fds are full).
         irq_trigger.irq_evt.write(u64::MAX - 1).unwrap();
-        irq_trigger.trigger_irq(IrqType::Config).unwrap_err();
+        irq_trigger.trigger_irq(IrqType::Config).unwrap();
         irq_trigger.trigger_irq(IrqType::Vring).unwrap_err();
+        irq_trigger.config_evt.write(u64::MAX - 2).unwrap();
+        irq_trigger.trigger_irq(IrqType::Config).unwrap_err();
     }
 
     #[derive(Debug)]
@@ -0,0 +1,463 @@
+// Copyright 2025 Amazon.com, Inc. or its affiliates. All Rights Reserved.
+// SPDX-License-Identifier: Apache-2.0
+
+use std::sync::Arc;
+
+use log::error;
+use utils::time::{ClockType, get_time_us};
+use vhost::vhost_user::Frontend;
+use vhost::vhost_user::message::*;
+use vmm_sys_util::eventfd::EventFd;
+
+use super::{MAX_REQUEST_QUEUES, MAX_TAG_LEN, NUM_HIPRIO_QUEUES, QUEUE_SIZE, VhostUserFsError};
+use crate::devices::virtio::device::{DeviceState, IrqTrigger, VirtioDevice};
+use crate::devices::virtio::generated::virtio_config::VIRTIO_F_VERSION_1;
+use crate::devices::virtio::generated::virtio_ring::VIRTIO_RING_F_EVENT_IDX;
+use crate::devices::virtio::queue::Queue;
+use crate::devices::virtio::vhost_user::{VhostUserHandleBackend, VhostUserHandleImpl};
+use crate::devices::virtio::vhost_user_metrics::{
+    VhostUserDeviceMetrics, VhostUserMetricsPerDevice,
+};
+use crate::devices::virtio::{ActivateError, TYPE_FS};
+use crate::logger::{IncMetric, StoreMetric, log_dev_preview_warning};
+use crate::utils::{u64_to_usize, usize_to_u64};
+use crate::vstate::memory::GuestMemoryMmap;
+
+/// Size in bytes of the fs device config space: the tag followed by the number of request queues.
+const FS_CONFIG_SPACE_SIZE: usize = MAX_TAG_LEN + 4;
+
+const AVAILABLE_FEATURES: u64 = (1 << VIRTIO_F_VERSION_1)
+    | (1 << VIRTIO_RING_F_EVENT_IDX)
+    // vhost-user specific bit. Not defined in standart virtio spec.
+    // Specifies ability of frontend to negotiate protocol features.
+    | VhostUserVirtioFeatures::PROTOCOL_FEATURES.bits();
+
+pub type VhostUserFs = VhostUserFsImpl<Frontend>;
+
+/// vhost-user fs device.
+pub struct VhostUserFsImpl<T: VhostUserHandleBackend> {
+    // Virtio fields.
+    pub avail_features: u64,
+    pub acked_features: u64,
+    pub config_space: Vec<u8>,
+    pub activate_evt: EventFd,
+
+    // Transport related fields.
+    pub queues: Vec<Queue>,
+    pub queue_evts: Vec<EventFd>,
+    pub device_state: DeviceState,
+    pub irq_trigger: IrqTrigger,
+
+    // Implementation specific fields.
+    pub id: String,
+    pub tag: String,
+
+    // Vhost user protocol handle
+    pub vu_handle: VhostUserHandleImpl<T>,
+    pub vu_acked_protocol_features: u64,
+    pub metrics: Arc<VhostUserDeviceMetrics>,
+}
+
+// Need custom implementation because otherwise `Debug` is required for `vhost::Master`
+impl<T: VhostUserHandleBackend> std::fmt::Debug for VhostUserFsImpl<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("VhostUserFsImpl")
+            .field("avail_features", &self.avail_features)
+            .field("acked_features", &self.acked_features)
+            .field("config_space", &self.config_space)
+            .field("activate_evt", &self.activate_evt)
+            .field("queues", &self.queues)
+            .field("queue_evts", &self.queue_evts)
+            .field("device_state", &self.device_state)
+            .field("irq_trigger", &self.irq_trigger)
+            .field("id", &self.id)
+            .field("tag", &self.tag)
+            .field("vu_handle", &self.vu_handle)
+            .field(
+                "vu_acked_protocol_features",
+                &self.vu_acked_protocol_features,
+            )
+            .field("metrics", &self.metrics)
+            .finish()
+    }
+}
+
+impl<T: VhostUserHandleBackend> VhostUserFsImpl<T> {
+    /// Connects to the backend listening on `socket` and creates a device exposing `num_queues`
+    /// request queues, that the guest mounts using `tag`.
+    pub fn new(
+        id: String,
+        socket: &str,
+        tag: String,
+        num_queues: usize,
+    ) -> Result<Self, VhostUserFsError> {
+        log_dev_preview_warning("vhost-user-fs device", Option::None);
+        let start_time = get_time_us(ClockType::Monotonic);
+
+        if tag.is_empty() || tag.len() > MAX_TAG_LEN {
+            return Err(VhostUserFsError::InvalidTag);
+        }
+        if num_queues == 0 || num_queues > MAX_REQUEST_QUEUES {
+            return Err(VhostUserFsError::InvalidNumQueues);
+        }
+        let total_queues = NUM_HIPRIO_QUEUES + num_queues;
+
+        let mut vu_handle = VhostUserHandleImpl::<T>::new(socket, usize_to_u64(total_queues))
+            .map_err(VhostUserFsError::VhostUser)?;
+        // The config space is generated by the frontend, so there are no protocol features that
+        // the device needs.
+        let (acked_features, acked_protocol_features) = vu_handle
+            .negotiate_features(AVAILABLE_FEATURES, VhostUserProtocolFeatures::empty())
+            .map_err(VhostUserFsError::VhostUser)?;
+
+        let mut config_space = vec![0u8; FS_CONFIG_SPACE_SIZE];
+        config_space[..tag.len()].copy_from_slice(tag.as_bytes());
+        // The number of request queues is bounded by `MAX_REQUEST_QUEUES`.
+        config_space[MAX_TAG_LEN..]
+            .copy_from_slice(&u32::try_from(num_queues).unwrap().to_le_bytes());
+
+        let activate_evt = EventFd::new(libc::EFD_NONBLOCK).map_err(VhostUserFsError::EventFd)?;
+
+        let queues = (0..total_queues)
+            .map(|_| Queue::new(QUEUE_SIZE))
+            .collect::<Vec<_>>();
+        let queue_evts = (0..total_queues)
+            .map(|_| EventFd::new(libc::EFD_NONBLOCK))
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(VhostUserFsError::EventFd)?;
+        let device_state = DeviceState::Inactive;
+        let irq_trigger = IrqTrigger::new().map_err(VhostUserFsError::IrqTrigger)?;
+
+        // We negotiated features with backend. Now these acked_features
+        // are available for guest driver to choose from.
+        let avail_features = acked_features;
+        let acked_features = acked_features & VhostUserVirtioFeatures::PROTOCOL_FEATURES.bits();
+        let metrics = VhostUserMetricsPerDevice::alloc(format!("fs_{}", id));
+        let delta_us = get_time_us(ClockType::Monotonic) - start_time;
+        metrics.init_time_us.store(delta_us);
+
+        Ok(Self {
+            avail_features,
+            acked_features,
+            config_space,
+            activate_evt,
+
+            queues,
+            queue_evts,
+            device_state,
+            irq_trigger,
+
+            id,
+            tag,
+
+            vu_handle,
+            vu_acked_protocol_features: acked_protocol_features,
+            metrics,
+        })
+    }
+
+    /// Identifier of the device.
+    pub fn id(&self) -> &str {
+        &self.id
+    }
+
+    /// Path of the socket of the vhost-user backend.
+    pub fn socket_path(&self) -> &str {
+        &self.vu_handle.socket_path
+    }
+
+    /// Tag used by the guest to mount the file system.
+    pub fn tag(&self) -> &str {
+        &self.tag
+    }
+
+    /// Number of request queues exposed to the guest.
+    pub fn num_request_queues(&self) -> usize {
+        self.queues.len() - NUM_HIPRIO_QUEUES
+    }
+}
+
+impl<T: VhostUserHandleBackend + Send + 'static> VirtioDevice for VhostUserFsImpl<T> {
+    fn avail_features(&self) -> u64 {
+        self.avail_features
+    }
+
+    fn acked_features(&self) -> u64 {
+        self.acked_features
+    }
+
+    fn set_acked_features(&mut self, acked_features: u64) {
+        self.acked_features = acked_features;
+    }
+
+    fn device_type(&self) -> u32 {
+        TYPE_FS
+    }
+
+    fn queues(&self) -> &[Queue] {
+        &self.queues
+    }
+
+    fn queues_mut(&mut self) -> &mut [Queue] {
+        &mut self.queues
+    }
+
+    fn queue_events(&self) -> &[EventFd] {
+        &self.queue_evts
+    }
+
+    fn interrupt_trigger(&self) -> &IrqTrigger {
+        &self.irq_trigger
+    }
+
+    fn read_config(&self, offset: u64, data: &mut [u8]) {
+        if let Some(config_space_bytes) = self.config_space.as_slice().get(u64_to_usize(offset)..) {
+            let len = config_space_bytes.len().min(data.len());
+            data[..len].copy_from_slice(&config_space_bytes[..len]);
+        } else {
+            error!("Failed to read config space");
+            self.metrics.cfg_fails.inc();
+        }
+    }
+
+    fn write_config(&mut self, _offset: u64, _data: &[u8]) {
+        // The fs config space is read-only.
+    }
+
+    fn activate(&mut self, mem: GuestMemoryMmap) -> Result<(), ActivateError> {
+        for q in self.queues.iter_mut() {
+            q.initialize(&mem)
+                .map_err(ActivateError::QueueMemoryError)?;
+        }
+
+        let start_time = get_time_us(ClockType::Monotonic);
+        let queues = self
+            .queues
+            .iter()
+            .zip(self.queue_evts.iter())
+            .enumerate()
+            .map(|(index, (queue, queue_evt))| (index, queue, queue_evt))
+            .collect::<Vec<_>>();
+        // Setting features again, because now we negotiated them
+        // with guest driver as well.
+        self.vu_handle
+            .set_features(self.acked_features)
+            .and_then(|()| {
+                self.vu_handle
+                    .setup_backend(&mem, &queues, &self.irq_trigger)
+            })
+            .map_err(|err| {
+                self.metrics.activate_fails.inc();
+                ActivateError::VhostUser(err)
+            })?;
+        self.device_state = DeviceState::Activated(mem);
+        if self.activate_evt.write(1).is_err() {
+            error!("vhost-user-fs: Cannot write to activate_evt");
+        }
+        let delta_us = get_time_us(ClockType::Monotonic) - start_time;
+        self.metrics.activate_time_us.store(delta_us);
+        Ok(())
+    }
+
+    fn is_activated(&self) -> bool {
+        self.device_state.is_activated()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #![allow(clippy::undocumented_unsafe_blocks)]
+
+    use std::os::unix::net::UnixStream;
+
+    use vhost::{VhostUserMemoryRegionInfo, VringConfigData};
+    use vmm_sys_util::tempfile::TempFile;
+
+    use super::*;
+    use crate::devices::virtio::vhost_user::tests::create_mem;
+    use crate::test_utils::create_tmp_socket;
+    use crate::vstate::memory::GuestAddress;
+
+    struct MockFrontend {
+        max_queue_num: u64,
+        features: u64,
+        features_are_set: std::cell::UnsafeCell<bool>,
+        memory_is_set: std::cell::UnsafeCell<bool>,
+        vrings_enabled: std::cell::UnsafeCell<usize>,
+    }
+
+    impl VhostUserHandleBackend for MockFrontend {
+        fn from_stream(_sock: UnixStream, max_queue_num: u64) -> Self {
+            Self {
+                max_queue_num,
+                features: AVAILABLE_FEATURES,
+                features_are_set: std::cell::UnsafeCell::new(false),
+                memory_is_set: std::cell::UnsafeCell::new(false),
+                vrings_enabled: std::cell::UnsafeCell::new(0),
+            }
+        }
+
+        fn set_owner(&self) -> Result<(), vhost::Error> {
+            Ok(())
+        }
+
+        fn set_hdr_flags(&self, _flags: VhostUserHeaderFlag) {}
+
+        fn get_features(&self) -> Result<u64, vhost::Error> {
+            Ok(self.features)
+        }
+
+        fn get_protocol_features(&mut self) -> Result<VhostUserProtocolFeatures, vhost::Error> {
+            Ok(VhostUserProtocolFeatures::all())
+        }
+
+        fn set_protocol_features(
+            &mut self,
+            _features: VhostUserProtocolFeatures,
+        ) -> Result<(), vhost::Error> {
+            Ok(())
+        }
+
+        fn set_features(&self, _features: u64) -> Result<(), vhost::Error> {
+            unsafe { (*self.features_are_set.get()) = true };
+            Ok(())
+        }
+
+        fn set_mem_table(
+            &self,
+            _regions: &[VhostUserMemoryRegionInfo],
+        ) -> Result<(), vhost::Error> {
+            unsafe { (*self.memory_is_set.get()) = true };
+            Ok(())
+        }
+
+        fn set_vring_num(&self, _queue_index: usize, _num: u16) -> Result<(), vhost::Error> {
+            Ok(())
+        }
+
+        fn set_vring_addr(
+            &self,
+            _queue_index: usize,
+            _config_data: &VringConfigData,
+        ) -> Result<(), vhost::Error> {
+            Ok(())
+        }
+
+        fn set_vring_base(&self, _queue_index: usize, _base: u16) -> Result<(), vhost::Error> {
+            Ok(())
+        }
+
+        fn set_vring_call(&self, _queue_index: usize, _fd: &EventFd) -> Result<(), vhost::Error> {
+            Ok(())
+        }
+
+        fn set_vring_kick(&self, _queue_index: usize, _fd: &EventFd) -> Result<(), vhost::Error> {
+            Ok(())
+        }
+
+        fn set_vring_enable(
+            &mut self,
+            _queue_index: usize,
+            _enable: bool,
+        ) -> Result<(), vhost::Error> {
+            unsafe { (*self.vrings_enabled.get()) += 1 };
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_new() {
+        let (_tmp_dir, tmp_socket_path) = create_tmp_socket();
+
+        let err = VhostUserFsImpl::<MockFrontend>::new(
+            "fs0".to_string(),
+            &tmp_socket_path,
+            String::new(),
+            1,
+        )
+        .unwrap_err();
+        assert!(matches!(err, VhostUserFsError::InvalidTag), "{err}");
+        let err = VhostUserFsImpl::<MockFrontend>::new(
+            "fs0".to_string(),
+            &tmp_socket_path,
+            "a".repeat(MAX_TAG_LEN + 1),
+            1,
+        )
+        .unwrap_err();
+        assert!(matches!(err, VhostUserFsError::InvalidTag), "{err}");
+        let err = VhostUserFsImpl::<MockFrontend>::new(
+            "fs0".to_string(),
+            &tmp_socket_path,
+            "src".to_string(),
+            0,
+        )
+        .unwrap_err();
+        assert!(matches!(err, VhostUserFsError::InvalidNumQueues), "{err}");
+
+        let mut fs = VhostUserFsImpl::<MockFrontend>::new(
+            "fs0".to_string(),
+            &tmp_socket_path,
+            "src".to_string(),
+            2,
+        )
+        .unwrap();
+        assert_eq!(fs.vu_handle.vu.max_queue_num, 3);
+        assert_eq!(fs.queues().len(), 3);
+        assert_eq!(fs.queue_events().len(), 3);
+        assert_eq!(fs.num_request_queues(), 2);
+        assert_eq!(fs.device_type(), TYPE_FS);
+        assert_eq!(fs.avail_features(), AVAILABLE_FEATURES);
+        assert_eq!(
+            fs.acked_features(),
+            VhostUserVirtioFeatures::PROTOCOL_FEATURES.bits()
+        );
+        assert_eq!(fs.vu_acked_protocol_features, 0);
+
+        // The config space holds the tag, padded with zeros, and the number of request queues.
+        let mut tag = [0xffu8; MAX_TAG_LEN];
+        fs.read_config(0, &mut tag);
+        assert_eq!(&tag[..3], b"src");
+        assert!(tag[3..].iter().all(|b| *b == 0));
+        let mut num_queues = [0u8; 4];
+        fs.read_config(usize_to_u64(MAX_TAG_LEN), &mut num_queues);
+        assert_eq!(u32::from_le_bytes(num_queues), 2);
+
+        // Invalid offset
+        let mut data = [0u8; 4];
+        fs.read_config(0x69, &mut data);
+        assert_eq!(data, [0; 4]);
+
+        // Writing to the config does nothing
+        fs.write_config(0, &[0]);
+        fs.read_config(0, &mut tag);
+        assert_eq!(&tag[..3], b"src");
+    }
+
+    #[test]
+    fn test_activate() {
+        let (_tmp_dir, tmp_socket_path) = create_tmp_socket();
+        let mut fs = VhostUserFsImpl::<MockFrontend>::new(
+            "fs0".to_string(),
+            &tmp_socket_path,
+            "src".to_string(),
+            2,
+        )
+        .unwrap();
+
+        // Memory creation
+        let region_size = 0x10000;
+        let file = TempFile::new().unwrap().into_file();
+        file.set_len(region_size as u64).unwrap();
+        let regions = vec![(GuestAddress(0x0), region_size)];
+        let guest_memory = create_mem(file, &regions);
+
+        // During activation of the device features, memory and all the queues should be set and
+        // enabled.
+        fs.activate(guest_memory).unwrap();
+        assert!(unsafe { *fs.vu_handle.vu.features_are_set.get() });
+        assert!(unsafe { *fs.vu_handle.vu.memory_is_set.get() });
+        assert_eq!(unsafe { *fs.vu_handle.vu.vrings_enabled.get() }, 3);
+        assert!(fs.is_activated());
+        assert_eq!(fs.activate_evt.read().unwrap(), 1);
+    }
+}
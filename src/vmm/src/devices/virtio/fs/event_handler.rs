@@ -0,0 +1,75 @@
+// Copyright 2025 Amazon.com, Inc. or its affiliates. All Rights Reserved.
+// SPDX-License-Identifier: Apache-2.0
+use event_manager::{EventOps, Events, MutEventSubscriber};
+use vmm_sys_util::epoll::EventSet;
+
+use super::VhostUserFs;
+use crate::devices::virtio::device::VirtioDevice;
+use crate::logger::{error, warn};
+
+impl VhostUserFs {
+    const PROCESS_ACTIVATE: u32 = 0;
+
+    fn register_activate_event(&self, ops: &mut EventOps) {
+        if let Err(err) = ops.add(Events::with_data(
+            &self.activate_evt,
+            Self::PROCESS_ACTIVATE,
+            EventSet::IN,
+        )) {
+            error!("Failed to register activate event: {}", err);
+        }
+    }
+
+    fn process_activate_event(&self, ops: &mut EventOps) {
+        if let Err(err) = self.activate_evt.read() {
+            error!("Failed to consume fs activate event: {:?}", err);
+        }
+        if let Err(err) = ops.remove(Events::with_data(
+            &self.activate_evt,
+            Self::PROCESS_ACTIVATE,
+            EventSet::IN,
+        )) {
+            error!("Failed to un-register activate event: {}", err);
+        }
+    }
+}
+
+impl MutEventSubscriber for VhostUserFs {
+    // The queues are processed by the backend, so the only event is the activation.
+    fn process(&mut self, event: Events, ops: &mut EventOps) {
+        let source = event.data();
+        let event_set = event.event_set();
+        let supported_events = EventSet::IN;
+
+        if !supported_events.contains(event_set) {
+            warn!(
+                "Received unknown event: {:?} from source: {:?}",
+                event_set, source
+            );
+            return;
+        }
+
+        if self.is_activated() {
+            if Self::PROCESS_ACTIVATE == source {
+                self.process_activate_event(ops)
+            } else {
+                warn!("FsVhost: Spurious event received: {:?}", source)
+            }
+        } else {
+            warn!(
+                "FsVhost: The device is not yet activated. Spurious event received: {:?}",
+                source
+            );
+        }
+    }
+
+    fn init(&mut self, ops: &mut EventOps) {
+        // The device can't be restored from a snapshot, so this is only called shortly after
+        // its creation.
+        if self.is_activated() {
+            warn!("Vhost-user fs: unexpected init event");
+        } else {
+            self.register_activate_event(ops);
+        }
+    }
+}
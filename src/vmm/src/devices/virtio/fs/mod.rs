@@ -0,0 +1,39 @@
+// Copyright 2025 Amazon.com, Inc. or its affiliates. All Rights Reserved.
+// SPDX-License-Identifier: Apache-2.0
+
+//! Implements a virtio-fs device whose requests are served by a vhost-user backend, such as
+//! virtiofsd.
+
+pub mod device;
+pub mod event_handler;
+
+pub use self::device::VhostUserFs;
+use crate::devices::virtio::vhost_user::VhostUserError;
+
+/// Number of high priority queues of the vhost-user-fs device.
+pub const NUM_HIPRIO_QUEUES: usize = 1;
+
+/// Maximum number of request queues of the vhost-user-fs device. There is no benefit in having
+/// more request queues than vCPUs.
+pub const MAX_REQUEST_QUEUES: usize = 32;
+
+/// Queue size for the vhost-user-fs device.
+pub const QUEUE_SIZE: u16 = 256;
+
+/// Maximum length in bytes of the tag used by the guest to mount the file system.
+pub const MAX_TAG_LEN: usize = 36;
+
+/// Vhost-user fs device error.
+#[derive(Debug, thiserror::Error, displaydoc::Display)]
+pub enum VhostUserFsError {
+    /// The tag must be between 1 and {MAX_TAG_LEN} bytes long.
+    InvalidTag,
+    /// The number of request queues must be between 1 and {MAX_REQUEST_QUEUES}.
+    InvalidNumQueues,
+    /// Vhost-user error: {0}
+    VhostUser(VhostUserError),
+    /// Error opening eventfd: {0}
+    EventFd(std::io::Error),
+    /// Error creating irqfd: {0}
+    IrqTrigger(std::io::Error),
+}
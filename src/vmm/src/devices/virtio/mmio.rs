@@ -885,7 +885,7 @@ pub(crate) mod tests {
         assert_eq!(
             d.locked_device()
                 .interrupt_trigger()
-                .irq_evt
+                .config_evt
                 .read()
                 .unwrap(),
             1
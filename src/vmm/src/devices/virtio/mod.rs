@@ -14,13 +14,18 @@ use crate::devices::virtio::net::TapError;
 
 pub mod balloon;
 pub mod block;
+pub mod console;
 pub mod device;
+pub mod fs;
 pub mod generated;
 mod iov_deque;
 pub mod iovec;
 pub mod mmio;
 pub mod net;
+#[cfg(target_arch = "x86_64")]
+pub mod pci;
 pub mod persist;
+pub mod pmem;
 pub mod queue;
 pub mod rng;
 pub mod test_utils;
@@ -52,10 +57,16 @@ mod device_status {
 pub const TYPE_NET: u32 = 1;
 /// Virtio block device ID.
 pub const TYPE_BLOCK: u32 = 2;
+/// Virtio console device ID.
+pub const TYPE_CONSOLE: u32 = 3;
 /// Virtio rng device ID.
 pub const TYPE_RNG: u32 = 4;
 /// Virtio balloon device ID.
 pub const TYPE_BALLOON: u32 = 5;
+/// Virtio fs device ID.
+pub const TYPE_FS: u32 = 26;
+/// Virtio pmem device ID.
+pub const TYPE_PMEM: u32 = 27;
 
 /// Offset from the base MMIO address of a virtio device used by the guest to notify the device of
 /// queue events.
@@ -0,0 +1,884 @@
+// Copyright 2025 Amazon.com, Inc. or its affiliates. All Rights Reserved.
+// SPDX-License-Identifier: Apache-2.0
+
+use std::fmt::Debug;
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::{Arc, Mutex, MutexGuard};
+
+use crate::devices::pci::configuration::{PciConfiguration, PciHeader};
+use crate::devices::pci::msix::{
+    MSIX_CAPABILITY_ID, MsiInterrupt, MsiMessage, MsiRouting, MsixConfig,
+};
+use crate::devices::virtio::device::{IrqType, VirtioDevice};
+use crate::devices::virtio::queue::Queue;
+use crate::devices::virtio::{TYPE_BLOCK, TYPE_NET, device_status};
+use crate::logger::{error, warn};
+use crate::vstate::memory::{GuestAddress, GuestMemoryMmap};
+
+const VIRTIO_PCI_VENDOR_ID: u16 = 0x1af4;
+// Modern virtio devices use 0x1040 + the virtio device type as PCI device ID.
+const VIRTIO_PCI_DEVICE_ID_BASE: u16 = 0x1040;
+const VIRTIO_PCI_REVISION_ID: u8 = 1;
+const VIRTIO_PCI_SUBSYSTEM_ID: u16 = 0x40;
+
+// Vendor specific capability, used for the virtio structures.
+const PCI_CAPABILITY_ID_VENDOR: u8 = 0x09;
+// Types of the virtio structures (`cfg_type` of `struct virtio_pci_cap`).
+const VIRTIO_PCI_CAP_COMMON_CFG: u8 = 1;
+const VIRTIO_PCI_CAP_NOTIFY_CFG: u8 = 2;
+const VIRTIO_PCI_CAP_ISR_CFG: u8 = 3;
+const VIRTIO_PCI_CAP_DEVICE_CFG: u8 = 4;
+
+/// Size of the BAR holding the virtio structures and the MSI-X table.
+pub const VIRTIO_PCI_BAR_SIZE: u64 = 0x8000;
+// Layout of the BAR. Every structure gets its own page.
+const COMMON_CFG_OFFSET: u64 = 0x0000;
+const ISR_CFG_OFFSET: u64 = 0x1000;
+const DEVICE_CFG_OFFSET: u64 = 0x2000;
+/// Offset in the BAR of the queue notification region.
+pub const NOTIFY_CFG_OFFSET: u64 = 0x3000;
+const MSIX_TABLE_OFFSET: u64 = 0x4000;
+const MSIX_PBA_OFFSET: u64 = 0x5000;
+const STRUCTURE_SIZE: u64 = 0x1000;
+/// Distance between the notification addresses of consecutive queues.
+pub const NOTIFY_OFF_MULTIPLIER: u32 = 4;
+
+/// Number of MSI-X vectors of a device: one for configuration changes and one for used buffers.
+pub const MSIX_VECTORS: u16 = 2;
+/// Value of an MSI-X vector register that doesn't map its source to any vector.
+pub const VIRTIO_MSI_NO_VECTOR: u16 = 0xffff;
+
+/// Implements the
+/// [PCI](https://docs.oasis-open.org/virtio/virtio/v1.2/csd01/virtio-v1.2-csd01.html#x1-1150001)
+/// transport for virtio devices, in its modern (virtio 1.x only) flavor.
+///
+/// The virtio structures and the MSI-X table live in a single 32-bit memory BAR, whose address is
+/// assigned by the VMM. The transport doesn't support INTx interrupts, so the guest driver has to
+/// use MSI-X. Two interrupts are available: one for configuration changes and one for used
+/// buffers, which is shared by all queues.
+///
+/// This requires 3 points of installation to work with a VM:
+///
+/// 1. Configuration space accesses must be sent to this device by the PCI root complex.
+/// 1. BAR reads and writes must be sent to this device at the address of the BAR.
+/// 1. The queue events of the device must be installed at `NOTIFY_CFG_OFFSET` +
+///    `NOTIFY_OFF_MULTIPLIER` * queue index from the address of the BAR.
+#[derive(Debug)]
+pub struct VirtioPciTransport {
+    device: Arc<Mutex<dyn VirtioDevice>>,
+    mem: GuestMemoryMmap,
+    config: PciConfiguration,
+    msix: MsixConfig,
+    msix_cap_offset: usize,
+    msi_routing: Arc<MsiRouting>,
+    config_interrupt: MsiInterrupt,
+    vring_interrupt: MsiInterrupt,
+    device_feature_select: u32,
+    driver_feature_select: u32,
+    queue_select: u16,
+    device_status: u32,
+    config_generation: u8,
+    config_vector: u16,
+    queue_vectors: Vec<u16>,
+    interrupt_status: Arc<AtomicU32>,
+}
+
+impl VirtioPciTransport {
+    /// Constructs a new PCI transport for the given virtio device.
+    ///
+    /// The BAR of the device is placed at `bar_addr`, and its configuration change and used
+    /// buffers interrupts are signaled on the `config_gsi` and `vring_gsi` GSIs.
+    pub fn new(
+        mem: GuestMemoryMmap,
+        device: Arc<Mutex<dyn VirtioDevice>>,
+        bar_addr: u32,
+        (config_gsi, vring_gsi): (u32, u32),
+        msi_routing: Arc<MsiRouting>,
+    ) -> Self {
+        let (device_type, num_queues, interrupt_status) = {
+            let locked_device = device.lock().expect("Poisoned lock");
+            (
+                locked_device.device_type(),
+                locked_device.queues().len(),
+                locked_device.interrupt_status(),
+            )
+        };
+
+        let mut config = PciConfiguration::new(&PciHeader {
+            vendor_id: VIRTIO_PCI_VENDOR_ID,
+            device_id: VIRTIO_PCI_DEVICE_ID_BASE + u16::try_from(device_type).unwrap(),
+            revision_id: VIRTIO_PCI_REVISION_ID,
+            class_code: match device_type {
+                TYPE_NET => 0x02_00_00,
+                TYPE_BLOCK => 0x01_80_00,
+                _ => 0xff_00_00,
+            },
+            subsystem_vendor_id: VIRTIO_PCI_VENDOR_ID,
+            subsystem_id: VIRTIO_PCI_SUBSYSTEM_ID,
+        });
+        config.set_bar(bar_addr, u32::try_from(VIRTIO_PCI_BAR_SIZE).unwrap());
+        for (cfg_type, offset) in [
+            (VIRTIO_PCI_CAP_COMMON_CFG, COMMON_CFG_OFFSET),
+            (VIRTIO_PCI_CAP_NOTIFY_CFG, NOTIFY_CFG_OFFSET),
+            (VIRTIO_PCI_CAP_ISR_CFG, ISR_CFG_OFFSET),
+            (VIRTIO_PCI_CAP_DEVICE_CFG, DEVICE_CFG_OFFSET),
+        ] {
+            config.add_capability(
+                PCI_CAPABILITY_ID_VENDOR,
+                &virtio_capability(cfg_type, offset),
+            );
+        }
+
+        let msix = MsixConfig::new(MSIX_VECTORS);
+        let msix_cap_offset = config.add_capability(
+            MSIX_CAPABILITY_ID,
+            &msix.capability_body(
+                u32::try_from(MSIX_TABLE_OFFSET).unwrap(),
+                u32::try_from(MSIX_PBA_OFFSET).unwrap(),
+            ),
+        );
+        config.set_writable_bits(
+            msix_cap_offset + 2,
+            &MsixConfig::message_control_writable_bits().to_le_bytes(),
+        );
+
+        VirtioPciTransport {
+            device,
+            mem,
+            config,
+            msix,
+            msix_cap_offset,
+            msi_routing,
+            config_interrupt: MsiInterrupt::new(config_gsi),
+            vring_interrupt: MsiInterrupt::new(vring_gsi),
+            device_feature_select: 0,
+            driver_feature_select: 0,
+            queue_select: 0,
+            device_status: device_status::INIT,
+            config_generation: 0,
+            config_vector: VIRTIO_MSI_NO_VECTOR,
+            queue_vectors: vec![VIRTIO_MSI_NO_VECTOR; num_queues],
+            interrupt_status,
+        }
+    }
+
+    /// Gets the encapsulated locked VirtioDevice.
+    pub fn locked_device(&self) -> MutexGuard<dyn VirtioDevice + 'static> {
+        self.device.lock().expect("Poisoned lock")
+    }
+
+    /// Gets the encapsulated VirtioDevice.
+    pub fn device(&self) -> Arc<Mutex<dyn VirtioDevice>> {
+        self.device.clone()
+    }
+
+    /// Handles a read of the PCI configuration space of the device.
+    pub fn read_config(&self, offset: usize, data: &mut [u8]) {
+        self.config.read(offset, data);
+    }
+
+    /// Handles a write to the PCI configuration space of the device.
+    pub fn write_config(&mut self, offset: usize, data: &[u8]) {
+        self.config.write(offset, data);
+
+        let message_control = self.msix_cap_offset + 2;
+        if offset < message_control + 2 && message_control < offset + data.len() {
+            self.msix
+                .set_message_control(self.config.read_u16(message_control));
+            self.update_interrupts();
+        }
+    }
+
+    fn check_device_status(&self, set: u32, clr: u32) -> bool {
+        self.device_status & (set | clr) == set
+    }
+
+    fn are_queues_valid(&self) -> bool {
+        self.locked_device()
+            .queues()
+            .iter()
+            .all(|q| q.is_valid(&self.mem))
+    }
+
+    fn with_queue<U, F>(&self, d: U, f: F) -> U
+    where
+        F: FnOnce(&Queue) -> U,
+        U: Debug,
+    {
+        match self
+            .locked_device()
+            .queues()
+            .get(usize::from(self.queue_select))
+        {
+            Some(queue) => f(queue),
+            None => d,
+        }
+    }
+
+    fn update_queue_field<F: FnOnce(&mut Queue)>(&mut self, f: F) {
+        if self.check_device_status(
+            device_status::FEATURES_OK,
+            device_status::DRIVER_OK | device_status::FAILED,
+        ) {
+            if let Some(queue) = self
+                .locked_device()
+                .queues_mut()
+                .get_mut(usize::from(self.queue_select))
+            {
+                f(queue);
+            }
+        } else {
+            warn!(
+                "update virtio queue in invalid state {:#x}",
+                self.device_status
+            );
+        }
+    }
+
+    // Vector of the used buffers interrupt. All queues share the same interrupt, so it uses the
+    // first vector that the driver assigned to a queue.
+    fn vring_vector(&self) -> u16 {
+        self.queue_vectors
+            .iter()
+            .copied()
+            .find(|vector| *vector != VIRTIO_MSI_NO_VECTOR)
+            .unwrap_or(VIRTIO_MSI_NO_VECTOR)
+    }
+
+    fn vector_message(&self, vector: u16) -> Option<MsiMessage> {
+        if vector == VIRTIO_MSI_NO_VECTOR {
+            return None;
+        }
+        self.msix.message(vector)
+    }
+
+    // Routes the interrupts of the device according to the MSI-X state and vector assignments.
+    fn update_interrupts(&mut self) {
+        let config_message = self.vector_message(self.config_vector);
+        let vring_message = self.vector_message(self.vring_vector());
+
+        let locked_device = self.device.lock().expect("Poisoned lock");
+        let interrupt = locked_device.interrupt_trigger();
+        if let Err(err) = self.msi_routing.update(
+            &interrupt.config_evt,
+            &mut self.config_interrupt,
+            config_message,
+        ) {
+            error!("Failed to update virtio-pci config interrupt: {}", err);
+        }
+        if let Err(err) =
+            self.msi_routing
+                .update(&interrupt.irq_evt, &mut self.vring_interrupt, vring_message)
+        {
+            error!("Failed to update virtio-pci vring interrupt: {}", err);
+        }
+    }
+
+    // Drivers read back the vector registers to check that the assignment succeeded.
+    fn checked_vector(&self, vector: u16) -> u16 {
+        if usize::from(vector) < self.msix.num_vectors() {
+            vector
+        } else {
+            VIRTIO_MSI_NO_VECTOR
+        }
+    }
+
+    fn reset(&mut self) {
+        if self.locked_device().is_activated() {
+            warn!("reset device while it's still in active state");
+        }
+        self.device_feature_select = 0;
+        self.driver_feature_select = 0;
+        self.queue_select = 0;
+        self.interrupt_status.store(0, Ordering::SeqCst);
+        self.device_status = device_status::INIT;
+        self.config_vector = VIRTIO_MSI_NO_VECTOR;
+        self.queue_vectors.fill(VIRTIO_MSI_NO_VECTOR);
+        self.update_interrupts();
+        for queue in self.locked_device().queues_mut() {
+            *queue = Queue::new(queue.get_max_size());
+        }
+    }
+
+    /// Update device status according to the state machine defined by VirtIO Spec 1.0.
+    /// Please refer to VirtIO Spec 1.0, section 2.1.1 and 3.1.1.
+    fn set_device_status(&mut self, status: u32) {
+        use device_status::*;
+        // match changed bits
+        match !self.device_status & status {
+            ACKNOWLEDGE if self.device_status == INIT => {
+                self.device_status = status;
+            }
+            DRIVER if self.device_status == ACKNOWLEDGE => {
+                self.device_status = status;
+            }
+            FEATURES_OK if self.device_status == (ACKNOWLEDGE | DRIVER) => {
+                self.device_status = status;
+            }
+            DRIVER_OK if self.device_status == (ACKNOWLEDGE | DRIVER | FEATURES_OK) => {
+                self.device_status = status;
+                let device_activated = self.locked_device().is_activated();
+                if !device_activated && self.are_queues_valid() {
+                    // temporary variable needed for borrow checker
+                    let activate_result = self.locked_device().activate(self.mem.clone());
+                    if let Err(err) = activate_result {
+                        self.device_status |= DEVICE_NEEDS_RESET;
+
+                        // Section 2.1.2 of the specification states that we need to send a device
+                        // configuration change interrupt
+                        let _ = self
+                            .locked_device()
+                            .interrupt_trigger()
+                            .trigger_irq(IrqType::Config);
+
+                        error!("Failed to activate virtio device: {}", err)
+                    }
+                }
+            }
+            _ if (status & FAILED) != 0 => {
+                self.device_status |= FAILED;
+            }
+            _ if status == 0 => {
+                let device_activated = self.locked_device().is_activated();
+                if device_activated && self.locked_device().reset().is_none() {
+                    self.device_status |= FAILED;
+                }
+
+                // If the backend device driver doesn't support reset,
+                // just leave the device marked as FAILED.
+                if self.device_status & FAILED == 0 {
+                    self.reset();
+                }
+            }
+            _ => {
+                warn!(
+                    "invalid virtio driver status transition: {:#x} -> {:#x}",
+                    self.device_status, status
+                );
+            }
+        }
+    }
+
+    fn read_common_config(&self, offset: u64, data: &mut [u8]) {
+        let value = match (offset, data.len()) {
+            (0x00, 4) => self.device_feature_select,
+            (0x04, 4) => {
+                let mut features = self
+                    .locked_device()
+                    .avail_features_by_page(self.device_feature_select);
+                if self.device_feature_select == 1 {
+                    features |= 0x1; // enable support of VirtIO Version 1
+                }
+                features
+            }
+            (0x08, 4) => self.driver_feature_select,
+            (0x0c, 4) => {
+                let acked_features = self.locked_device().acked_features();
+                match self.driver_feature_select {
+                    0 => (acked_features & 0xffff_ffff) as u32,
+                    1 => (acked_features >> 32) as u32,
+                    _ => 0,
+                }
+            }
+            (0x10, 2) => u32::from(self.config_vector),
+            (0x12, 2) => u32::try_from(self.queue_vectors.len()).unwrap(),
+            (0x14, 1) => self.device_status,
+            (0x15, 1) => u32::from(self.config_generation),
+            (0x16, 2) => u32::from(self.queue_select),
+            // The queue size reads as the maximum size until the driver sets it.
+            (0x18, 2) => self.with_queue(0, |q| match q.size {
+                0 => u32::from(q.get_max_size()),
+                size => u32::from(size),
+            }),
+            (0x1a, 2) => self
+                .queue_vectors
+                .get(usize::from(self.queue_select))
+                .map_or(u32::from(VIRTIO_MSI_NO_VECTOR), |vector| u32::from(*vector)),
+            (0x1c, 2) => self.with_queue(0, |q| u32::from(q.ready)),
+            // The notification address of a queue is given by its index.
+            (0x1e, 2) => u32::from(self.queue_select),
+            (0x20, 4) => self.with_queue(0, |q| lo(q.desc_table_address)),
+            (0x24, 4) => self.with_queue(0, |q| hi(q.desc_table_address)),
+            (0x28, 4) => self.with_queue(0, |q| lo(q.avail_ring_address)),
+            (0x2c, 4) => self.with_queue(0, |q| hi(q.avail_ring_address)),
+            (0x30, 4) => self.with_queue(0, |q| lo(q.used_ring_address)),
+            (0x34, 4) => self.with_queue(0, |q| hi(q.used_ring_address)),
+            _ => {
+                warn!(
+                    "unknown virtio pci common config read: {:#x}:{:#x}",
+                    offset,
+                    data.len()
+                );
+                0
+            }
+        };
+        let len = data.len().min(4);
+        data[..len].copy_from_slice(&value.to_le_bytes()[..len]);
+    }
+
+    fn write_common_config(&mut self, offset: u64, data: &[u8]) {
+        fn set_hi(v: &mut GuestAddress, x: u32) {
+            *v = (*v & 0xffff_ffff) | (u64::from(x) << 32)
+        }
+
+        fn set_lo(v: &mut GuestAddress, x: u32) {
+            *v = (*v & !0xffff_ffff) | u64::from(x)
+        }
+
+        let mut bytes = [0u8; 4];
+        let len = data.len().min(4);
+        bytes[..len].copy_from_slice(&data[..len]);
+        let v = u32::from_le_bytes(bytes);
+
+        match (offset, data.len()) {
+            (0x00, 4) => self.device_feature_select = v,
+            (0x08, 4) => self.driver_feature_select = v,
+            (0x0c, 4) => {
+                if self.check_device_status(
+                    device_status::DRIVER,
+                    device_status::FEATURES_OK
+                        | device_status::FAILED
+                        | device_status::DEVICE_NEEDS_RESET,
+                ) {
+                    self.locked_device()
+                        .ack_features_by_page(self.driver_feature_select, v);
+                } else {
+                    warn!(
+                        "ack virtio features in invalid state {:#x}",
+                        self.device_status
+                    );
+                }
+            }
+            (0x10, 2) => {
+                self.config_vector = self.checked_vector((v & 0xffff) as u16);
+                self.update_interrupts();
+            }
+            (0x14, 1) => self.set_device_status(v),
+            (0x16, 2) => self.queue_select = (v & 0xffff) as u16,
+            (0x18, 2) => self.update_queue_field(|q| q.size = (v & 0xffff) as u16),
+            (0x1a, 2) => {
+                let vector = self.checked_vector((v & 0xffff) as u16);
+                if let Some(queue_vector) =
+                    self.queue_vectors.get_mut(usize::from(self.queue_select))
+                {
+                    *queue_vector = vector;
+                    self.update_interrupts();
+                }
+            }
+            (0x1c, 2) => self.update_queue_field(|q| q.ready = v == 1),
+            (0x20, 4) => self.update_queue_field(|q| set_lo(&mut q.desc_table_address, v)),
+            (0x24, 4) => self.update_queue_field(|q| set_hi(&mut q.desc_table_address, v)),
+            (0x28, 4) => self.update_queue_field(|q| set_lo(&mut q.avail_ring_address, v)),
+            (0x2c, 4) => self.update_queue_field(|q| set_hi(&mut q.avail_ring_address, v)),
+            (0x30, 4) => self.update_queue_field(|q| set_lo(&mut q.used_ring_address, v)),
+            (0x34, 4) => self.update_queue_field(|q| set_hi(&mut q.used_ring_address, v)),
+            _ => {
+                warn!(
+                    "unknown virtio pci common config write: {:#x}:{:#x}",
+                    offset,
+                    data.len()
+                );
+            }
+        }
+    }
+}
+
+impl VirtioPciTransport {
+    pub fn bus_read(&mut self, offset: u64, data: &mut [u8]) {
+        match offset {
+            COMMON_CFG_OFFSET..ISR_CFG_OFFSET => {
+                self.read_common_config(offset - COMMON_CFG_OFFSET, data)
+            }
+            ISR_CFG_OFFSET => {
+                // Reading the ISR status acknowledges the interrupts.
+                let isr = self.interrupt_status.swap(0, Ordering::SeqCst);
+                data.fill(0);
+                if let Some(byte) = data.first_mut() {
+                    *byte = (isr & 0xff) as u8;
+                }
+            }
+            DEVICE_CFG_OFFSET..NOTIFY_CFG_OFFSET => self
+                .locked_device()
+                .read_config(offset - DEVICE_CFG_OFFSET, data),
+            MSIX_TABLE_OFFSET..MSIX_PBA_OFFSET => {
+                self.msix.read_table(offset - MSIX_TABLE_OFFSET, data)
+            }
+            MSIX_PBA_OFFSET..VIRTIO_PCI_BAR_SIZE => {
+                self.msix.read_pba(offset - MSIX_PBA_OFFSET, data)
+            }
+            _ => {
+                data.fill(0);
+                warn!("invalid virtio pci read: {:#x}:{:#x}", offset, data.len());
+            }
+        }
+    }
+
+    pub fn bus_write(&mut self, offset: u64, data: &[u8]) {
+        match offset {
+            COMMON_CFG_OFFSET..ISR_CFG_OFFSET => {
+                self.write_common_config(offset - COMMON_CFG_OFFSET, data)
+            }
+            DEVICE_CFG_OFFSET..NOTIFY_CFG_OFFSET => {
+                if self.check_device_status(
+                    device_status::DRIVER,
+                    device_status::FAILED | device_status::DEVICE_NEEDS_RESET,
+                ) {
+                    self.locked_device()
+                        .write_config(offset - DEVICE_CFG_OFFSET, data)
+                } else {
+                    warn!("can not write to device config data area before driver is ready");
+                }
+            }
+            NOTIFY_CFG_OFFSET..MSIX_TABLE_OFFSET => {
+                // Notifications are normally handled by KVM through the queue event fds. This
+                // only happens if KVM didn't match the access.
+                let index = (offset - NOTIFY_CFG_OFFSET) / u64::from(NOTIFY_OFF_MULTIPLIER);
+                let locked_device = self.locked_device();
+                match usize::try_from(index)
+                    .ok()
+                    .and_then(|index| locked_device.queue_events().get(index))
+                {
+                    Some(queue_evt) => {
+                        if let Err(err) = queue_evt.write(1) {
+                            error!("Failed to notify virtio queue {}: {}", index, err);
+                        }
+                    }
+                    None => warn!("invalid virtio queue notification: {}", index),
+                }
+            }
+            MSIX_TABLE_OFFSET..MSIX_PBA_OFFSET => {
+                self.msix.write_table(offset - MSIX_TABLE_OFFSET, data);
+                self.update_interrupts();
+            }
+            _ => {
+                warn!("invalid virtio pci write: {:#x}:{:#x}", offset, data.len());
+            }
+        }
+    }
+}
+
+fn lo(v: GuestAddress) -> u32 {
+    (v.0 & 0xffff_ffff) as u32
+}
+
+fn hi(v: GuestAddress) -> u32 {
+    (v.0 >> 32) as u32
+}
+
+// Body of a `struct virtio_pci_cap` describing the structure of type `cfg_type` placed at
+// `offset` in BAR 0. The notification structure also holds the notify offset multiplier.
+fn virtio_capability(cfg_type: u8, offset: u64) -> Vec<u8> {
+    let mut body = Vec::with_capacity(18);
+    let cap_len: u8 = if cfg_type == VIRTIO_PCI_CAP_NOTIFY_CFG {
+        20
+    } else {
+        16
+    };
+    // cap_len, cfg_type, bar, id and 2 bytes of padding.
+    body.extend_from_slice(&[cap_len, cfg_type, 0, 0, 0, 0]);
+    body.extend_from_slice(&u32::try_from(offset).unwrap().to_le_bytes());
+    body.extend_from_slice(&u32::try_from(STRUCTURE_SIZE).unwrap().to_le_bytes());
+    if cfg_type == VIRTIO_PCI_CAP_NOTIFY_CFG {
+        body.extend_from_slice(&NOTIFY_OFF_MULTIPLIER.to_le_bytes());
+    }
+    body
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::devices::pci::msix::MSIX_TABLE_ENTRY_SIZE;
+    use crate::devices::virtio::mmio::tests::DummyDevice;
+    use crate::test_utils::single_region_mem;
+
+    const BAR_ADDR: u32 = 0xc000_0000;
+    const CONFIG_GSI: u32 = 24;
+    const VRING_GSI: u32 = 25;
+
+    fn transport(kvm: &kvm_ioctls::Kvm) -> VirtioPciTransport {
+        let vm_fd = kvm.create_vm().unwrap();
+        vm_fd.create_irq_chip().unwrap();
+        VirtioPciTransport::new(
+            single_region_mem(0x10000),
+            Arc::new(Mutex::new(DummyDevice::new())),
+            BAR_ADDR,
+            (CONFIG_GSI, VRING_GSI),
+            Arc::new(MsiRouting::new(vm_fd)),
+        )
+    }
+
+    fn read_common(transport: &mut VirtioPciTransport, offset: u64, len: usize) -> u32 {
+        let mut data = [0u8; 4];
+        transport.bus_read(COMMON_CFG_OFFSET + offset, &mut data[..len]);
+        u32::from_le_bytes(data)
+    }
+
+    fn write_common(transport: &mut VirtioPciTransport, offset: u64, len: usize, value: u32) {
+        transport.bus_write(COMMON_CFG_OFFSET + offset, &value.to_le_bytes()[..len]);
+    }
+
+    fn set_device_status(transport: &mut VirtioPciTransport, status: u32) {
+        write_common(transport, 0x14, 1, status);
+    }
+
+    fn device_status(transport: &mut VirtioPciTransport) -> u32 {
+        read_common(transport, 0x14, 1)
+    }
+
+    // Sets up queue `index` with its rings at `addr` in guest memory.
+    fn setup_queue(transport: &mut VirtioPciTransport, index: u32, addr: u32) {
+        write_common(transport, 0x16, 2, index);
+        write_common(transport, 0x18, 2, 16);
+        write_common(transport, 0x20, 4, addr);
+        write_common(transport, 0x28, 4, addr + 0x100);
+        write_common(transport, 0x30, 4, addr + 0x200);
+        write_common(transport, 0x1c, 2, 1);
+    }
+
+    fn write_msix_entry(transport: &mut VirtioPciTransport, vector: u64, message: MsiMessage) {
+        let offset = MSIX_TABLE_OFFSET + vector * MSIX_TABLE_ENTRY_SIZE;
+        transport.bus_write(offset, &message.address.to_le_bytes());
+        transport.bus_write(offset + 8, &message.data.to_le_bytes());
+        // Unmask the vector.
+        transport.bus_write(offset + 12, &0u32.to_le_bytes());
+    }
+
+    fn enable_msix(transport: &mut VirtioPciTransport) {
+        let message_control = transport.msix_cap_offset + 2;
+        // MSI-X enable bit.
+        transport.write_config(message_control, &(1u16 << 15).to_le_bytes());
+    }
+
+    #[test]
+    fn test_virtio_capability() {
+        assert_eq!(
+            virtio_capability(VIRTIO_PCI_CAP_COMMON_CFG, COMMON_CFG_OFFSET),
+            [16, 1, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0x10, 0, 0]
+        );
+        assert_eq!(
+            virtio_capability(VIRTIO_PCI_CAP_NOTIFY_CFG, NOTIFY_CFG_OFFSET),
+            [20, 2, 0, 0, 0, 0, 0, 0x30, 0, 0, 0, 0x10, 0, 0, 4, 0, 0, 0]
+        );
+    }
+
+    #[test]
+    fn test_pci_config() {
+        let kvm = kvm_ioctls::Kvm::new().unwrap();
+        let transport = transport(&kvm);
+
+        let mut data = [0u8; 4];
+        transport.read_config(0, &mut data);
+        assert_eq!(u16::from_le_bytes([data[0], data[1]]), VIRTIO_PCI_VENDOR_ID);
+        assert_eq!(
+            u16::from_le_bytes([data[2], data[3]]),
+            VIRTIO_PCI_DEVICE_ID_BASE + 123
+        );
+        transport.read_config(0x10, &mut data);
+        assert_eq!(u32::from_le_bytes(data), BAR_ADDR);
+    }
+
+    #[test]
+    fn test_common_config() {
+        let kvm = kvm_ioctls::Kvm::new().unwrap();
+        let mut transport = transport(&kvm);
+
+        // The device always offers VIRTIO_F_VERSION_1.
+        write_common(&mut transport, 0x00, 4, 1);
+        assert_eq!(read_common(&mut transport, 0x00, 4), 1);
+        assert_eq!(read_common(&mut transport, 0x04, 4), 1);
+        write_common(&mut transport, 0x00, 4, 0);
+        assert_eq!(read_common(&mut transport, 0x04, 4), 0);
+
+        assert_eq!(read_common(&mut transport, 0x12, 2), 2);
+        // Queue sizes read as their maximum size until the driver sets them.
+        assert_eq!(read_common(&mut transport, 0x18, 2), 16);
+        write_common(&mut transport, 0x16, 2, 1);
+        assert_eq!(read_common(&mut transport, 0x16, 2), 1);
+        assert_eq!(read_common(&mut transport, 0x18, 2), 32);
+        assert_eq!(read_common(&mut transport, 0x1e, 2), 1);
+        // Queues that don't exist read as zero.
+        write_common(&mut transport, 0x16, 2, 2);
+        assert_eq!(read_common(&mut transport, 0x18, 2), 0);
+        assert_eq!(
+            read_common(&mut transport, 0x1a, 2),
+            u32::from(VIRTIO_MSI_NO_VECTOR)
+        );
+
+        // Queues can't be configured before the features are negotiated.
+        write_common(&mut transport, 0x16, 2, 0);
+        write_common(&mut transport, 0x18, 2, 8);
+        assert_eq!(read_common(&mut transport, 0x18, 2), 16);
+
+        set_device_status(&mut transport, device_status::ACKNOWLEDGE);
+        set_device_status(
+            &mut transport,
+            device_status::ACKNOWLEDGE | device_status::DRIVER,
+        );
+        write_common(&mut transport, 0x08, 4, 1);
+        assert_eq!(read_common(&mut transport, 0x08, 4), 1);
+        write_common(&mut transport, 0x0c, 4, 1);
+        set_device_status(
+            &mut transport,
+            device_status::ACKNOWLEDGE | device_status::DRIVER | device_status::FEATURES_OK,
+        );
+
+        write_common(&mut transport, 0x18, 2, 8);
+        assert_eq!(read_common(&mut transport, 0x18, 2), 8);
+        for (offset, value) in [
+            (0x20, 0x1000),
+            (0x24, 0x1),
+            (0x28, 0x2000),
+            (0x2c, 0x2),
+            (0x30, 0x3000),
+            (0x34, 0x3),
+        ] {
+            write_common(&mut transport, offset, 4, value);
+            assert_eq!(read_common(&mut transport, offset, 4), value);
+        }
+        assert_eq!(
+            transport.locked_device().queues()[0].desc_table_address,
+            GuestAddress(0x1_0000_1000)
+        );
+        write_common(&mut transport, 0x1c, 2, 1);
+        assert_eq!(read_common(&mut transport, 0x1c, 2), 1);
+
+        // Accesses with an unexpected size are ignored.
+        write_common(&mut transport, 0x18, 4, 4);
+        assert_eq!(read_common(&mut transport, 0x18, 2), 8);
+        assert_eq!(read_common(&mut transport, 0x18, 4), 0);
+    }
+
+    #[test]
+    fn test_device_status() {
+        let kvm = kvm_ioctls::Kvm::new().unwrap();
+        let mut transport = transport(&kvm);
+
+        // Invalid transitions are ignored.
+        set_device_status(&mut transport, device_status::DRIVER);
+        assert_eq!(device_status(&mut transport), device_status::INIT);
+
+        set_device_status(&mut transport, device_status::ACKNOWLEDGE);
+        set_device_status(
+            &mut transport,
+            device_status::ACKNOWLEDGE | device_status::DRIVER,
+        );
+        set_device_status(
+            &mut transport,
+            device_status::ACKNOWLEDGE | device_status::DRIVER | device_status::FEATURES_OK,
+        );
+        setup_queue(&mut transport, 0, 0x1000);
+
+        // The device is not activated while its queues are invalid.
+        let driver_ok = device_status::ACKNOWLEDGE
+            | device_status::DRIVER
+            | device_status::FEATURES_OK
+            | device_status::DRIVER_OK;
+        set_device_status(&mut transport, driver_ok);
+        assert_eq!(device_status(&mut transport), driver_ok);
+        assert!(!transport.locked_device().is_activated());
+
+        // Resetting the device restores the initial state.
+        write_common(&mut transport, 0x16, 2, 1);
+        set_device_status(&mut transport, 0);
+        assert_eq!(device_status(&mut transport), device_status::INIT);
+        assert_eq!(read_common(&mut transport, 0x16, 2), 0);
+        assert_eq!(read_common(&mut transport, 0x1c, 2), 0);
+
+        set_device_status(&mut transport, device_status::ACKNOWLEDGE);
+        set_device_status(
+            &mut transport,
+            device_status::ACKNOWLEDGE | device_status::DRIVER,
+        );
+        set_device_status(
+            &mut transport,
+            device_status::ACKNOWLEDGE | device_status::DRIVER | device_status::FEATURES_OK,
+        );
+        setup_queue(&mut transport, 0, 0x1000);
+        setup_queue(&mut transport, 1, 0x2000);
+        set_device_status(&mut transport, driver_ok);
+        assert!(transport.locked_device().is_activated());
+
+        // Queues can't be updated once the device is live.
+        write_common(&mut transport, 0x18, 2, 8);
+        assert_eq!(read_common(&mut transport, 0x18, 2), 16);
+
+        // The dummy device doesn't support reset, so it's left failed.
+        set_device_status(&mut transport, 0);
+        assert_eq!(
+            device_status(&mut transport),
+            driver_ok | device_status::FAILED
+        );
+    }
+
+    #[test]
+    fn test_msix() {
+        let kvm = kvm_ioctls::Kvm::new().unwrap();
+        let mut transport = transport(&kvm);
+        let config_message = MsiMessage {
+            address: 0xfee0_0000,
+            data: 0x41,
+        };
+        let vring_message = MsiMessage {
+            address: 0xfee0_1000,
+            data: 0x42,
+        };
+
+        // The MSI-X table is accessible through the BAR.
+        write_msix_entry(&mut transport, 0, config_message);
+        write_msix_entry(&mut transport, 1, vring_message);
+        let mut data = [0u8; 8];
+        transport.bus_read(MSIX_TABLE_OFFSET + MSIX_TABLE_ENTRY_SIZE, &mut data);
+        assert_eq!(u64::from_le_bytes(data), vring_message.address);
+
+        // Vectors out of the table are rejected.
+        write_common(&mut transport, 0x10, 2, u32::from(MSIX_VECTORS));
+        assert_eq!(
+            read_common(&mut transport, 0x10, 2),
+            u32::from(VIRTIO_MSI_NO_VECTOR)
+        );
+        write_common(&mut transport, 0x10, 2, 0);
+        assert_eq!(read_common(&mut transport, 0x10, 2), 0);
+        write_common(&mut transport, 0x16, 2, 1);
+        write_common(&mut transport, 0x1a, 2, 1);
+        assert_eq!(read_common(&mut transport, 0x1a, 2), 1);
+
+        // Interrupts are only routed once MSI-X is enabled.
+        assert_eq!(transport.config_interrupt.message(), None);
+        assert_eq!(transport.vring_interrupt.message(), None);
+        enable_msix(&mut transport);
+        assert_eq!(transport.config_interrupt.message(), Some(config_message));
+        assert_eq!(transport.vring_interrupt.message(), Some(vring_message));
+        assert_eq!(transport.config_interrupt.gsi(), CONFIG_GSI);
+        assert_eq!(transport.vring_interrupt.gsi(), VRING_GSI);
+
+        // Updating a table entry updates the route of its interrupt.
+        let new_message = MsiMessage {
+            address: 0xfee0_2000,
+            data: 0x43,
+        };
+        write_msix_entry(&mut transport, 1, new_message);
+        assert_eq!(transport.vring_interrupt.message(), Some(new_message));
+
+        // Masking a vector stops delivering its interrupt.
+        transport.bus_write(MSIX_TABLE_OFFSET + 12, &1u32.to_le_bytes());
+        assert_eq!(transport.config_interrupt.message(), None);
+
+        // Unassigning the vector of the queue stops delivering the used buffers interrupt.
+        write_common(&mut transport, 0x1a, 2, u32::from(VIRTIO_MSI_NO_VECTOR));
+        assert_eq!(transport.vring_interrupt.message(), None);
+    }
+
+    #[test]
+    fn test_isr() {
+        let kvm = kvm_ioctls::Kvm::new().unwrap();
+        let mut transport = transport(&kvm);
+
+        transport.interrupt_status.store(0x3, Ordering::SeqCst);
+        // Reading the ISR status acknowledges the interrupts.
+        let mut data = [0xffu8; 1];
+        transport.bus_read(ISR_CFG_OFFSET, &mut data);
+        assert_eq!(data, [0x3]);
+        transport.bus_read(ISR_CFG_OFFSET, &mut data);
+        assert_eq!(data, [0]);
+    }
+}
@@ -0,0 +1,457 @@
+// Copyright 2025 Amazon.com, Inc. or its affiliates. All Rights Reserved.
+// SPDX-License-Identifier: Apache-2.0
+
+use std::fs::{File, OpenOptions};
+use std::io;
+use std::sync::Arc;
+use std::sync::atomic::AtomicU32;
+
+use vm_memory::GuestMemoryError;
+use vm_memory::mmap::MmapRegionError;
+use vmm_sys_util::eventfd::EventFd;
+
+use super::metrics::METRICS;
+use super::{PMEM_ALIGNMENT, PMEM_NUM_QUEUES, PMEM_QUEUE, VIRTIO_PMEM_REQ_TYPE_FLUSH};
+use crate::devices::DeviceError;
+use crate::devices::virtio::device::{DeviceState, IrqTrigger, IrqType, VirtioDevice};
+use crate::devices::virtio::generated::virtio_config::VIRTIO_F_VERSION_1;
+use crate::devices::virtio::queue::{DescriptorChain, FIRECRACKER_MAX_QUEUE_SIZE, Queue};
+use crate::devices::virtio::{ActivateError, TYPE_PMEM};
+use crate::logger::{IncMetric, error, warn};
+use crate::utils::u64_to_usize;
+use crate::vmm_config::pmem::PmemConfig;
+use crate::vstate::memory::{
+    Address, Bytes, FileOffset, GuestAddress, GuestMemoryMmap, MmapRegion, MmapRegionBuilder,
+};
+
+/// Size of the status written back to the driver.
+const PMEM_RESP_LEN: u32 = 4;
+
+#[derive(Debug, thiserror::Error, displaydoc::Display)]
+pub enum PmemError {
+    /// Error while handling an Event file descriptor: {0}
+    EventFd(#[from] io::Error),
+    /// Cannot open the backing file: {0}
+    OpenFile(io::Error),
+    /// The size of the backing file must be a non-zero multiple of 2 MiB, it is {0} bytes
+    InvalidFileSize(u64),
+    /// Cannot map the backing file: {0}
+    Mmap(MmapRegionError),
+    /// Bad guest memory buffer: {0}
+    GuestMemory(#[from] GuestMemoryError),
+    /// Malformed request descriptor chain
+    MalformedRequest,
+}
+
+/// Virtio-pmem device, exposing a host file mapped in the guest physical address space.
+#[derive(Debug)]
+pub struct Pmem {
+    // VirtIO fields
+    avail_features: u64,
+    acked_features: u64,
+    activate_event: EventFd,
+
+    // Transport fields
+    device_state: DeviceState,
+    pub(crate) queues: Vec<Queue>,
+    queue_events: Vec<EventFd>,
+    irq_trigger: IrqTrigger,
+
+    // Device specific fields
+    config: PmemConfig,
+    file: Arc<File>,
+    mapping: MmapRegion,
+    /// Guest physical address the backing file is mapped at.
+    guest_address: GuestAddress,
+}
+
+impl Pmem {
+    pub fn new(config: PmemConfig) -> Result<Self, PmemError> {
+        let queues = vec![Queue::new(FIRECRACKER_MAX_QUEUE_SIZE); PMEM_NUM_QUEUES];
+        Self::new_with_queues(queues, config)
+    }
+
+    pub fn new_with_queues(queues: Vec<Queue>, config: PmemConfig) -> Result<Self, PmemError> {
+        let file = OpenOptions::new()
+            .read(true)
+            .write(!config.read_only)
+            .open(&config.path_on_host)
+            .map_err(PmemError::OpenFile)?;
+        let size = file.metadata().map_err(PmemError::OpenFile)?.len();
+        if size == 0 || size % PMEM_ALIGNMENT != 0 {
+            return Err(PmemError::InvalidFileSize(size));
+        }
+
+        // The mapping is shared, so that the guest accesses go through the host page cache, which
+        // is then shared with all the microVMs using the same file.
+        let file = Arc::new(file);
+        let prot = if config.read_only {
+            libc::PROT_READ
+        } else {
+            libc::PROT_READ | libc::PROT_WRITE
+        };
+        let mapping = MmapRegionBuilder::new(u64_to_usize(size))
+            .with_mmap_prot(prot)
+            .with_mmap_flags(libc::MAP_SHARED | libc::MAP_NORESERVE)
+            .with_file_offset(FileOffset::from_arc(Arc::clone(&file), 0))
+            .build()
+            .map_err(PmemError::Mmap)?;
+
+        let activate_event = EventFd::new(libc::EFD_NONBLOCK)?;
+        let queue_events = (0..PMEM_NUM_QUEUES)
+            .map(|_| EventFd::new(libc::EFD_NONBLOCK))
+            .collect::<Result<Vec<EventFd>, io::Error>>()?;
+        let irq_trigger = IrqTrigger::new()?;
+
+        Ok(Self {
+            avail_features: 1 << VIRTIO_F_VERSION_1,
+            acked_features: 0u64,
+            activate_event,
+            device_state: DeviceState::Inactive,
+            queues,
+            queue_events,
+            irq_trigger,
+            config,
+            file,
+            mapping,
+            guest_address: GuestAddress(0),
+        })
+    }
+
+    pub fn id(&self) -> &str {
+        &self.config.id
+    }
+
+    pub fn config(&self) -> &PmemConfig {
+        &self.config
+    }
+
+    pub fn root_device(&self) -> bool {
+        self.config.root_device
+    }
+
+    pub fn read_only(&self) -> bool {
+        self.config.read_only
+    }
+
+    /// Returns the host mapping of the backing file.
+    pub fn mapping(&self) -> &MmapRegion {
+        &self.mapping
+    }
+
+    /// Returns the size of the backing file.
+    pub fn size(&self) -> u64 {
+        self.mapping.size() as u64
+    }
+
+    pub fn guest_address(&self) -> GuestAddress {
+        self.guest_address
+    }
+
+    /// Sets the guest physical address the backing file is mapped at. This needs to happen before
+    /// the driver reads the config space.
+    pub fn set_guest_address(&mut self, guest_address: GuestAddress) {
+        self.guest_address = guest_address;
+    }
+
+    fn config_space(&self) -> [u8; 16] {
+        let mut config_space = [0u8; 16];
+        config_space[..8].copy_from_slice(&self.guest_address.raw_value().to_le_bytes());
+        config_space[8..].copy_from_slice(&self.size().to_le_bytes());
+        config_space
+    }
+
+    fn signal_used_queue(&self) -> Result<(), DeviceError> {
+        self.irq_trigger
+            .trigger_irq(IrqType::Vring)
+            .map_err(DeviceError::FailedSignalingIrq)
+    }
+
+    /// Persists the guest writes to the backing file. Returns the status of the request.
+    fn flush(&self) -> u32 {
+        METRICS.flush_count.inc();
+        if self.config.read_only {
+            return 0;
+        }
+        match self.file.sync_all() {
+            Ok(()) => 0,
+            Err(err) => {
+                error!("pmem: Failed to flush the backing file: {err}");
+                METRICS.flush_fails.inc();
+                1
+            }
+        }
+    }
+
+    fn handle_request(
+        &self,
+        mem: &GuestMemoryMmap,
+        head: DescriptorChain,
+    ) -> Result<(), PmemError> {
+        if head.is_write_only() || head.len < 4 {
+            return Err(PmemError::MalformedRequest);
+        }
+        let status_desc = head
+            .next_descriptor()
+            .filter(|desc| desc.is_write_only() && desc.len >= PMEM_RESP_LEN)
+            .ok_or(PmemError::MalformedRequest)?;
+
+        let status = match mem.read_obj::<u32>(head.addr)? {
+            VIRTIO_PMEM_REQ_TYPE_FLUSH => self.flush(),
+            req_type => {
+                warn!("pmem: Unsupported request type: {req_type}");
+                1
+            }
+        };
+        mem.write_obj(status, status_desc.addr)?;
+        Ok(())
+    }
+
+    pub(crate) fn process_queue(&mut self) {
+        // This is safe since we checked in the event handler that the device is activated.
+        let mem = self.device_state.mem().unwrap();
+        let mut used_any = false;
+
+        while let Some(head) = self.queues[PMEM_QUEUE].pop() {
+            let len = match self.handle_request(mem, head) {
+                Ok(()) => PMEM_RESP_LEN,
+                Err(err) => {
+                    error!("pmem: Failed to handle request: {err}");
+                    METRICS.event_fails.inc();
+                    0
+                }
+            };
+
+            if let Err(err) = self.queues[PMEM_QUEUE].add_used(head.index, len) {
+                error!("pmem: Could not add used descriptor to queue: {err}");
+                METRICS.event_fails.inc();
+                break;
+            }
+            used_any = true;
+        }
+
+        if used_any {
+            self.signal_used_queue().unwrap_or_else(|err| {
+                error!("pmem: {err:?}");
+                METRICS.event_fails.inc()
+            });
+        }
+    }
+
+    pub(crate) fn process_queue_event(&mut self) {
+        if let Err(err) = self.queue_events[PMEM_QUEUE].read() {
+            error!("pmem: Failed to read queue event: {err}");
+            METRICS.event_fails.inc();
+        } else {
+            self.process_queue();
+        }
+    }
+
+    pub fn process_virtio_queues(&mut self) {
+        self.process_queue();
+    }
+
+    pub(crate) fn set_avail_features(&mut self, features: u64) {
+        self.avail_features = features;
+    }
+
+    pub(crate) fn set_acked_features(&mut self, features: u64) {
+        self.acked_features = features;
+    }
+
+    pub(crate) fn set_irq_status(&mut self, status: u32) {
+        self.irq_trigger.irq_status = Arc::new(AtomicU32::new(status));
+    }
+
+    pub(crate) fn set_activated(&mut self, mem: GuestMemoryMmap) {
+        self.device_state = DeviceState::Activated(mem);
+    }
+
+    pub(crate) fn activate_event(&self) -> &EventFd {
+        &self.activate_event
+    }
+}
+
+impl VirtioDevice for Pmem {
+    fn device_type(&self) -> u32 {
+        TYPE_PMEM
+    }
+
+    fn queues(&self) -> &[Queue] {
+        &self.queues
+    }
+
+    fn queues_mut(&mut self) -> &mut [Queue] {
+        &mut self.queues
+    }
+
+    fn queue_events(&self) -> &[EventFd] {
+        &self.queue_events
+    }
+
+    fn interrupt_trigger(&self) -> &IrqTrigger {
+        &self.irq_trigger
+    }
+
+    fn avail_features(&self) -> u64 {
+        self.avail_features
+    }
+
+    fn acked_features(&self) -> u64 {
+        self.acked_features
+    }
+
+    fn set_acked_features(&mut self, acked_features: u64) {
+        self.acked_features = acked_features;
+    }
+
+    fn read_config(&self, offset: u64, data: &mut [u8]) {
+        let config_space = self.config_space();
+        if let Some(config_space_bytes) = config_space.get(u64_to_usize(offset)..) {
+            let len = config_space_bytes.len().min(data.len());
+            data[..len].copy_from_slice(&config_space_bytes[..len]);
+        } else {
+            error!("pmem: Failed to read config space");
+            METRICS.cfg_fails.inc();
+        }
+    }
+
+    fn write_config(&mut self, _offset: u64, _data: &[u8]) {}
+
+    fn is_activated(&self) -> bool {
+        self.device_state.is_activated()
+    }
+
+    fn activate(&mut self, mem: GuestMemoryMmap) -> Result<(), ActivateError> {
+        for q in self.queues.iter_mut() {
+            q.initialize(&mem)
+                .map_err(ActivateError::QueueMemoryError)?;
+        }
+
+        self.activate_event.write(1).map_err(|_| {
+            METRICS.activate_fails.inc();
+            ActivateError::EventFd
+        })?;
+        self.device_state = DeviceState::Activated(mem);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use vmm_sys_util::tempfile::TempFile;
+
+    use super::*;
+    use crate::check_metric_after_block;
+    use crate::devices::virtio::queue::{VIRTQ_DESC_F_NEXT, VIRTQ_DESC_F_WRITE};
+    use crate::devices::virtio::test_utils::{VirtQueue, default_mem};
+
+    pub(crate) fn default_pmem_config(id: &str, file: &TempFile, read_only: bool) -> PmemConfig {
+        file.as_file().set_len(PMEM_ALIGNMENT).unwrap();
+        PmemConfig {
+            id: id.to_string(),
+            path_on_host: file.as_path().to_str().unwrap().to_string(),
+            root_device: false,
+            read_only,
+        }
+    }
+
+    #[test]
+    fn test_new() {
+        let file = TempFile::new().unwrap();
+        let pmem = Pmem::new(default_pmem_config("pmem0", &file, true)).unwrap();
+        assert_eq!(pmem.id(), "pmem0");
+        assert_eq!(pmem.device_type(), TYPE_PMEM);
+        assert_eq!(pmem.avail_features(), 1 << VIRTIO_F_VERSION_1);
+        assert_eq!(pmem.size(), PMEM_ALIGNMENT);
+        assert!(!pmem.is_activated());
+
+        // The size of the file must be aligned.
+        file.as_file().set_len(PMEM_ALIGNMENT + 4096).unwrap();
+        let config = pmem.config().clone();
+        assert!(matches!(
+            Pmem::new(config.clone()),
+            Err(PmemError::InvalidFileSize(_))
+        ));
+        file.as_file().set_len(0).unwrap();
+        assert!(matches!(
+            Pmem::new(config),
+            Err(PmemError::InvalidFileSize(0))
+        ));
+    }
+
+    #[test]
+    fn test_config_space() {
+        let file = TempFile::new().unwrap();
+        let mut pmem = Pmem::new(default_pmem_config("pmem0", &file, false)).unwrap();
+        pmem.set_guest_address(GuestAddress(0x1_0000_0000));
+
+        let mut data = [0u8; 16];
+        pmem.read_config(0, &mut data);
+        assert_eq!(
+            u64::from_le_bytes(data[..8].try_into().unwrap()),
+            0x1_0000_0000
+        );
+        assert_eq!(
+            u64::from_le_bytes(data[8..].try_into().unwrap()),
+            PMEM_ALIGNMENT
+        );
+
+        let mut data = [0u8; 8];
+        pmem.read_config(8, &mut data);
+        assert_eq!(u64::from_le_bytes(data), PMEM_ALIGNMENT);
+    }
+
+    #[test]
+    fn test_mapping() {
+        let file = TempFile::new().unwrap();
+        let pmem = Pmem::new(default_pmem_config("pmem0", &file, false)).unwrap();
+
+        // Writes through the mapping land in the backing file.
+        // SAFETY: The mapping is valid and large enough.
+        unsafe { pmem.mapping().as_ptr().write_volatile(42) };
+        let mut data = [0u8; 1];
+        std::os::unix::fs::FileExt::read_exact_at(file.as_file(), &mut data, 0).unwrap();
+        assert_eq!(data, [42]);
+    }
+
+    #[test]
+    fn test_requests() {
+        let file = TempFile::new().unwrap();
+        let mem = default_mem();
+        let vq = VirtQueue::new(GuestAddress(0), &mem, 16);
+        let mut pmem = Pmem::new(default_pmem_config("pmem0", &file, false)).unwrap();
+        pmem.queues[PMEM_QUEUE] = vq.create_queue();
+        pmem.activate(mem.clone()).unwrap();
+
+        let req_addr = GuestAddress(0x1000);
+        let status_addr = GuestAddress(0x2000);
+        vq.dtable[0].set(req_addr.0, 4, VIRTQ_DESC_F_NEXT, 1);
+        vq.dtable[1].set(status_addr.0, 4, VIRTQ_DESC_F_WRITE, 0);
+        let push_request = |req_type: u32| {
+            mem.write_obj(req_type, req_addr).unwrap();
+            mem.write_obj(0xffu32, status_addr).unwrap();
+            let idx = vq.avail.idx.get();
+            vq.avail.ring[usize::from(idx)].set(0);
+            vq.avail.idx.set(idx + 1);
+        };
+
+        // Flush requests succeed.
+        push_request(VIRTIO_PMEM_REQ_TYPE_FLUSH);
+        check_metric_after_block!(METRICS.flush_count, 1, pmem.process_queue());
+        assert_eq!(mem.read_obj::<u32>(status_addr).unwrap(), 0);
+        vq.check_used_elem(0, 0, PMEM_RESP_LEN);
+
+        // Unknown requests fail.
+        push_request(7);
+        pmem.process_queue();
+        assert_eq!(mem.read_obj::<u32>(status_addr).unwrap(), 1);
+        vq.check_used_elem(1, 0, PMEM_RESP_LEN);
+
+        // Requests without a status descriptor are malformed.
+        vq.dtable[0].set(req_addr.0, 4, 0, 0);
+        push_request(VIRTIO_PMEM_REQ_TYPE_FLUSH);
+        check_metric_after_block!(METRICS.event_fails, 1, pmem.process_queue());
+        vq.check_used_elem(2, 0, 0);
+    }
+}
@@ -0,0 +1,20 @@
+// Copyright 2025 Amazon.com, Inc. or its affiliates. All Rights Reserved.
+// SPDX-License-Identifier: Apache-2.0
+
+pub mod device;
+mod event_handler;
+pub mod metrics;
+pub mod persist;
+
+pub use self::device::{Pmem, PmemError};
+
+pub(crate) const PMEM_NUM_QUEUES: usize = 1;
+
+pub(crate) const PMEM_QUEUE: usize = 0;
+
+/// Alignment of the size of the backing files, and of the guest physical addresses they are
+/// mapped at. Linux requires it to use the device with DAX.
+pub const PMEM_ALIGNMENT: u64 = 2 << 20;
+
+/// Request sent by the driver to persist the writes to the device.
+pub(crate) const VIRTIO_PMEM_REQ_TYPE_FLUSH: u32 = 0;
@@ -0,0 +1,141 @@
+// Copyright 2025 Amazon.com, Inc. or its affiliates. All Rights Reserved.
+// SPDX-License-Identifier: Apache-2.0
+
+//! Defines the structures needed for saving/restoring virtio-pmem devices.
+
+use serde::{Deserialize, Serialize};
+
+use super::{PMEM_NUM_QUEUES, Pmem, PmemError};
+use crate::devices::virtio::TYPE_PMEM;
+use crate::devices::virtio::persist::{PersistError as VirtioStateError, VirtioDeviceState};
+use crate::devices::virtio::queue::FIRECRACKER_MAX_QUEUE_SIZE;
+use crate::snapshot::Persist;
+use crate::vmm_config::pmem::PmemConfig;
+use crate::vstate::memory::{GuestAddress, GuestMemoryMmap};
+
+/// State of a virtio-pmem device. The content of the backing file isn't saved, only where it is
+/// mapped in the guest.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PmemState {
+    virtio_state: VirtioDeviceState,
+    id: String,
+    path_on_host: String,
+    root_device: bool,
+    read_only: bool,
+    guest_address: u64,
+    size: u64,
+}
+
+#[derive(Debug)]
+pub struct PmemConstructorArgs(GuestMemoryMmap);
+
+impl PmemConstructorArgs {
+    pub fn new(mem: GuestMemoryMmap) -> Self {
+        Self(mem)
+    }
+}
+
+#[derive(Debug, thiserror::Error, displaydoc::Display)]
+pub enum PmemPersistError {
+    /// Create pmem: {0}
+    CreatePmem(#[from] PmemError),
+    /// The size of the backing file changed from {0} to {1} bytes
+    FileSizeChanged(u64, u64),
+    /// Virtio state: {0}
+    VirtioState(#[from] VirtioStateError),
+}
+
+impl Persist<'_> for Pmem {
+    type State = PmemState;
+    type ConstructorArgs = PmemConstructorArgs;
+    type Error = PmemPersistError;
+
+    fn save(&self) -> Self::State {
+        PmemState {
+            virtio_state: VirtioDeviceState::from_device(self),
+            id: self.config().id.clone(),
+            path_on_host: self.config().path_on_host.clone(),
+            root_device: self.config().root_device,
+            read_only: self.config().read_only,
+            guest_address: self.guest_address().0,
+            size: self.size(),
+        }
+    }
+
+    fn restore(
+        constructor_args: Self::ConstructorArgs,
+        state: &Self::State,
+    ) -> Result<Self, Self::Error> {
+        let queues = state.virtio_state.build_queues_checked(
+            &constructor_args.0,
+            TYPE_PMEM,
+            PMEM_NUM_QUEUES,
+            FIRECRACKER_MAX_QUEUE_SIZE,
+        )?;
+
+        let mut pmem = Pmem::new_with_queues(
+            queues,
+            PmemConfig {
+                id: state.id.clone(),
+                path_on_host: state.path_on_host.clone(),
+                root_device: state.root_device,
+                read_only: state.read_only,
+            },
+        )?;
+        // The guest expects the mapping to have the same size as when the snapshot was taken.
+        if pmem.size() != state.size {
+            return Err(PmemPersistError::FileSizeChanged(state.size, pmem.size()));
+        }
+        pmem.set_avail_features(state.virtio_state.avail_features);
+        pmem.set_acked_features(state.virtio_state.acked_features);
+        pmem.set_irq_status(state.virtio_state.interrupt_status);
+        pmem.set_guest_address(GuestAddress(state.guest_address));
+        if state.virtio_state.activated {
+            pmem.set_activated(constructor_args.0);
+        }
+
+        Ok(pmem)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::Ordering;
+
+    use vmm_sys_util::tempfile::TempFile;
+
+    use super::*;
+    use crate::devices::virtio::device::VirtioDevice;
+    use crate::devices::virtio::pmem::device::tests::default_pmem_config;
+    use crate::devices::virtio::test_utils::test::create_virtio_mem;
+    use crate::snapshot::Snapshot;
+
+    #[test]
+    fn test_persistence() {
+        let file = TempFile::new().unwrap();
+        let mut mem = vec![0u8; 4096];
+        let mut pmem = Pmem::new(default_pmem_config("pmem0", &file, true)).unwrap();
+        pmem.set_guest_address(GuestAddress(0x1_0000_0000));
+
+        Snapshot::serialize(&mut mem.as_mut_slice(), &pmem.save()).unwrap();
+
+        let guest_mem = create_virtio_mem();
+        let restored = Pmem::restore(
+            PmemConstructorArgs(guest_mem),
+            &Snapshot::deserialize(&mut mem.as_slice()).unwrap(),
+        )
+        .unwrap();
+
+        assert_eq!(restored.device_type(), TYPE_PMEM);
+        assert_eq!(restored.config(), pmem.config());
+        assert_eq!(restored.guest_address(), pmem.guest_address());
+        assert_eq!(restored.size(), pmem.size());
+        assert_eq!(restored.is_activated(), pmem.is_activated());
+        assert_eq!(restored.avail_features(), pmem.avail_features());
+        assert_eq!(restored.acked_features(), pmem.acked_features());
+        assert_eq!(
+            restored.interrupt_status().load(Ordering::Relaxed),
+            pmem.interrupt_status().load(Ordering::Relaxed)
+        );
+    }
+}
@@ -463,6 +463,7 @@ pub(crate) mod tests {
     #![allow(clippy::undocumented_unsafe_blocks)]
 
     use std::fs::File;
+    use std::sync::Arc;
 
     use vmm_sys_util::tempfile::TempFile;
 
@@ -476,7 +477,7 @@ pub(crate) mod tests {
             memory::create(
                 regions.iter().copied(),
                 libc::MAP_PRIVATE,
-                Some(file),
+                Some(Arc::new(file)),
                 false,
             )
             .unwrap(),
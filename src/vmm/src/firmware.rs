@@ -0,0 +1,228 @@
+// Copyright 2025 Amazon.com, Inc. or its affiliates. All Rights Reserved.
+// SPDX-License-Identifier: Apache-2.0
+
+use std::fs::File;
+use std::io;
+use std::sync::Arc;
+
+use vm_memory::mmap::MmapRegionError;
+
+#[cfg(target_arch = "aarch64")]
+use crate::arch::aarch64::layout::FLASH_BLOCK_SIZE;
+#[cfg(target_arch = "x86_64")]
+use crate::arch::x86_64::layout::FLASH_BLOCK_SIZE;
+use crate::arch::{EntryPoint, GUEST_PAGE_SIZE, firmware_addresses, firmware_entry_point};
+use crate::device_manager::mmio::{MMIODeviceInfo, MMIODeviceManager, MmioError};
+use crate::device_manager::resources::ResourceAllocator;
+use crate::devices::legacy::{CfiFlashDevice, CfiFlashError};
+use crate::utils::{u64_to_usize, usize_to_u64};
+use crate::vstate::memory::{FileOffset, MmapRegion, MmapRegionBuilder};
+use crate::vstate::vm::{Vm, VmError};
+
+/// Errors associated with mapping a firmware in the guest.
+#[derive(Debug, thiserror::Error, displaydoc::Display)]
+pub enum FirmwareError {
+    /// Cannot get the size of the firmware files: {0}
+    Metadata(io::Error),
+    /// Cannot copy the firmware file fd: {0}
+    CloneFd(io::Error),
+    /// The size of the firmware files must be a non-zero multiple of the page size: {0}
+    InvalidFileSize(u64),
+    /// The firmware and its variable store don't fit in the flash region.
+    TooLarge,
+    /// Cannot reserve the flash region: {0}
+    ReserveFlash(#[from] vm_allocator::Error),
+    /// Cannot map the firmware files: {0}
+    Mmap(MmapRegionError),
+    /// Cannot map the firmware files in the guest: {0}
+    RegisterMemory(#[from] VmError),
+    /// Cannot create the flash device of the variable store: {0}
+    Flash(#[from] CfiFlashError),
+    /// Cannot register the flash device of the variable store: {0}
+    RegisterFlash(#[from] MmioError),
+}
+
+/// A firmware and its variable store, in the flash region of the guest.
+///
+/// The firmware is mapped read-only, guest writes to it exit to the VMM like MMIO accesses. The
+/// variable store is an emulated CFI flash device, which writes through to its backing file.
+#[derive(Debug)]
+pub struct Firmware {
+    // The mapping must outlive the VM.
+    code: MmapRegion,
+    entry_point: EntryPoint,
+}
+
+impl Firmware {
+    /// Maps the firmware in `code` in the flash region of the guest, and puts a flash device
+    /// holding the variable store in `vars`, if there is one, right below it.
+    pub fn new(
+        code: &File,
+        vars: Option<&File>,
+        vm: &mut Vm,
+        resource_allocator: &mut ResourceAllocator,
+        mmio_device_manager: &mut MMIODeviceManager,
+    ) -> Result<Self, FirmwareError> {
+        let code_size = file_size(code)?;
+        let vars_size = vars
+            .map(|vars| Ok(vars.metadata().map_err(FirmwareError::Metadata)?.len()))
+            .transpose()?
+            .unwrap_or(0);
+        let (code_addr, vars_addr) =
+            firmware_addresses(code_size, vars_size).ok_or(FirmwareError::TooLarge)?;
+
+        // On x86_64, the flash region is at the top of the 32-bit MMIO gap: keep the MMIO devices
+        // out of it.
+        #[cfg(target_arch = "x86_64")]
+        resource_allocator.allocate_mmio_memory(
+            crate::arch::x86_64::layout::FLASH_SIZE,
+            usize_to_u64(GUEST_PAGE_SIZE),
+            vm_allocator::AllocPolicy::ExactMatch(crate::arch::x86_64::layout::FLASH_START),
+        )?;
+        #[cfg(target_arch = "aarch64")]
+        let _ = resource_allocator;
+
+        let code_mapping = map_file(code, code_size)?;
+        vm.register_device_memory(code_addr, &code_mapping, true)?;
+        if let Some(vars) = vars {
+            let vars = vars.try_clone().map_err(FirmwareError::CloneFd)?;
+            let flash = CfiFlashDevice::new(vars, FLASH_BLOCK_SIZE)?;
+            mmio_device_manager.register_mmio_flash(
+                flash,
+                MMIODeviceInfo {
+                    addr: vars_addr.raw_value(),
+                    len: vars_size,
+                    irq: None,
+                },
+            )?;
+        }
+
+        Ok(Firmware {
+            code: code_mapping,
+            entry_point: firmware_entry_point(code_addr),
+        })
+    }
+
+    /// Returns the entry point of the firmware.
+    pub fn entry_point(&self) -> EntryPoint {
+        self.entry_point
+    }
+}
+
+fn file_size(file: &File) -> Result<u64, FirmwareError> {
+    let size = file.metadata().map_err(FirmwareError::Metadata)?.len();
+    if size == 0 || size % usize_to_u64(GUEST_PAGE_SIZE) != 0 {
+        return Err(FirmwareError::InvalidFileSize(size));
+    }
+    Ok(size)
+}
+
+/// Maps `size` bytes of `file`, read-only.
+fn map_file(file: &File, size: u64) -> Result<MmapRegion, FirmwareError> {
+    let file = file.try_clone().map_err(FirmwareError::CloneFd)?;
+    MmapRegionBuilder::new(u64_to_usize(size))
+        .with_mmap_prot(libc::PROT_READ)
+        .with_mmap_flags(libc::MAP_PRIVATE | libc::MAP_NORESERVE)
+        .with_file_offset(FileOffset::from_arc(Arc::new(file), 0))
+        .build()
+        .map_err(FirmwareError::Mmap)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Write;
+
+    use vmm_sys_util::tempfile::TempFile;
+
+    use super::*;
+    use crate::arch::{BootProtocol, DeviceType};
+    use crate::vstate::vm::tests::setup_vm;
+
+    fn firmware_file(size: usize) -> TempFile {
+        let file = TempFile::new().unwrap();
+        file.as_file().write_all(&vec![0xaa; size]).unwrap();
+        file
+    }
+
+    #[test]
+    fn test_firmware() {
+        let (_, mut vm) = setup_vm();
+        let mut resource_allocator = ResourceAllocator::new().unwrap();
+        let mut mmio_device_manager = MMIODeviceManager::new();
+        let code = firmware_file(0x4000);
+        let vars = firmware_file(u64_to_usize(2 * FLASH_BLOCK_SIZE));
+
+        let firmware = Firmware::new(
+            code.as_file(),
+            Some(vars.as_file()),
+            &mut vm,
+            &mut resource_allocator,
+            &mut mmio_device_manager,
+        )
+        .unwrap();
+        assert!(matches!(
+            firmware.entry_point().protocol,
+            BootProtocol::Firmware
+        ));
+        assert_eq!(firmware.code.size(), 0x4000);
+        // The firmware isn't part of the guest memory.
+        assert_eq!(vm.guest_memory().num_regions(), 0);
+
+        // The variable store is a flash device, right below the firmware.
+        let (_, vars_addr) = firmware_addresses(0x4000, 2 * FLASH_BLOCK_SIZE).unwrap();
+        let info = &mmio_device_manager.get_device_info()
+            [&(DeviceType::Flash, DeviceType::Flash.to_string())];
+        assert_eq!(info.addr, vars_addr.raw_value());
+        assert_eq!(info.len, 2 * FLASH_BLOCK_SIZE);
+        let (offset, device) = mmio_device_manager.bus.get_device(info.addr + 1).unwrap();
+        assert_eq!(offset, 1);
+        let mut data = [0; 2];
+        device.lock().unwrap().read(offset, &mut data);
+        assert_eq!(data, [0xaa; 2]);
+    }
+
+    #[test]
+    fn test_invalid_firmware() {
+        let (_, mut vm) = setup_vm();
+        let mut resource_allocator = ResourceAllocator::new().unwrap();
+        let mut mmio_device_manager = MMIODeviceManager::new();
+
+        let code = firmware_file(0);
+        assert!(matches!(
+            Firmware::new(
+                code.as_file(),
+                None,
+                &mut vm,
+                &mut resource_allocator,
+                &mut mmio_device_manager
+            ),
+            Err(FirmwareError::InvalidFileSize(0))
+        ));
+
+        let code = firmware_file(0x1234);
+        assert!(matches!(
+            Firmware::new(
+                code.as_file(),
+                None,
+                &mut vm,
+                &mut resource_allocator,
+                &mut mmio_device_manager
+            ),
+            Err(FirmwareError::InvalidFileSize(0x1234))
+        ));
+
+        // The variable store must be made of whole flash blocks.
+        let code = firmware_file(0x4000);
+        let vars = firmware_file(u64_to_usize(FLASH_BLOCK_SIZE) + 0x1000);
+        assert!(matches!(
+            Firmware::new(
+                code.as_file(),
+                Some(vars.as_file()),
+                &mut vm,
+                &mut resource_allocator,
+                &mut mmio_device_manager
+            ),
+            Err(FirmwareError::Flash(CfiFlashError::InvalidSize(_)))
+        ));
+    }
+}
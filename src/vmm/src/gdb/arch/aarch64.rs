@@ -3,20 +3,24 @@
 
 use std::mem::offset_of;
 
+use gdbstub::target::ext::breakpoints::WatchKind;
 use gdbstub_arch::aarch64::reg::AArch64CoreRegs as CoreRegs;
 use kvm_bindings::{
     KVM_GUESTDBG_ENABLE, KVM_GUESTDBG_SINGLESTEP, KVM_GUESTDBG_USE_HW, KVM_GUESTDBG_USE_SW_BP,
-    KVM_REG_ARM_CORE, KVM_REG_ARM64, KVM_REG_SIZE_U64, kvm_guest_debug, kvm_regs, user_pt_regs,
+    KVM_REG_ARM_CORE, KVM_REG_ARM64, KVM_REG_SIZE_U64, kvm_debug_exit_arch, kvm_guest_debug,
+    kvm_regs, user_pt_regs,
 };
 use kvm_ioctls::VcpuFd;
 use vm_memory::{Bytes, GuestAddress};
 
 use crate::Vmm;
 use crate::arch::aarch64::regs::{
-    Aarch64RegisterVec, ID_AA64MMFR0_EL1, TCR_EL1, TTBR1_EL1, arm64_core_reg_id,
+    Aarch64RegisterVec, ID_AA64MMFR0_EL1, TCR_EL1, TTBR0_EL1, TTBR1_EL1, arm64_core_reg_id,
 };
 use crate::arch::aarch64::vcpu::get_registers;
-use crate::gdb::target::GdbTargetError;
+use crate::gdb::target::{GdbTargetError, Watchpoint};
+use crate::utils::u64_to_usize;
+use crate::vstate::memory::GuestMemoryMmap;
 
 /// Configures the number of bytes required for a software breakpoint.
 ///
@@ -61,11 +65,9 @@ macro_rules! extract_bits_64 {
 const PTE_ADDRESS_MASK: u64 = !0b111u64;
 
 /// Read a u64 value from a guest memory address
-fn read_address(vmm: &Vmm, address: u64) -> Result<u64, GdbTargetError> {
+fn read_address(guest_memory: &GuestMemoryMmap, address: u64) -> Result<u64, GdbTargetError> {
     let mut buf = [0; 8];
-    vmm.vm
-        .guest_memory()
-        .read(&mut buf, GuestAddress(address))?;
+    guest_memory.read(&mut buf, GuestAddress(address))?;
 
     Ok(u64::from_le_bytes(buf))
 }
@@ -79,27 +81,56 @@ const GRAIN_SIZE: usize = 9;
 /// To simplify the implementation we've made some assumptions about the paging setup.
 /// Here we just assert firstly paging is setup and these assumptions are correct.
 pub fn translate_gva(vcpu_fd: &VcpuFd, gva: u64, vmm: &Vmm) -> Result<u64, GdbTargetError> {
-    // Check this virtual address is in kernel space
-    if extract_bits_64!(gva, 55, 1) == 0 {
-        return Err(GdbTargetError::GvaTranslateError);
-    }
+    walk_page_tables(|reg| get_sys_reg(reg, vcpu_fd), gva, vmm.vm.guest_memory())
+}
 
+/// Walks the guest page tables to translate `gva`, reading the translation registers with
+/// `get_reg`.
+fn walk_page_tables(
+    get_reg: impl Fn(u64) -> Result<u64, GdbTargetError>,
+    gva: u64,
+    guest_memory: &GuestMemoryMmap,
+) -> Result<u64, GdbTargetError> {
     // Translation control register
-    let tcr_el1: u64 = get_sys_reg(TCR_EL1, vcpu_fd)?;
+    let tcr_el1: u64 = get_reg(TCR_EL1)?;
 
     // If this is 0 then translation is not yet ready
     if extract_bits_64!(tcr_el1, 16, 6) == 0 {
         return Ok(gva);
     }
 
+    // Bit 55 of the address selects the upper (kernel) or lower (user) virtual address range,
+    // translated through TTBR1_EL1 or TTBR0_EL1, with the size (TxSZ) and granule (TGx) of the
+    // range taken from the translation control register.
+    let (ttbr, tsz, granule_4kb) = if extract_bits_64!(gva, 55, 1) == 1 {
+        (
+            TTBR1_EL1,
+            extract_bits_64!(tcr_el1, 16, 6),
+            extract_bits_64!(tcr_el1, 30, 2) == 2,
+        )
+    } else {
+        (
+            TTBR0_EL1,
+            extract_bits_64!(tcr_el1, 0, 6),
+            extract_bits_64!(tcr_el1, 14, 2) == 0,
+        )
+    };
+
     // Check 4KB pages are being used
-    if extract_bits_64!(tcr_el1, 30, 2) != 2 {
+    if !granule_4kb {
+        return Err(GdbTargetError::GvaTranslateError);
+    }
+
+    // The number of virtual address bits translated. With 4KB pages, each level of the page
+    // tables translates 9 bits, on top of the 12 bits of the page offset.
+    let va_bits = 64 - tsz;
+    if !(13..=48).contains(&va_bits) {
         return Err(GdbTargetError::GvaTranslateError);
     }
 
     // ID_AA64MMFR0_EL1 provides information about the implemented memory model and memory
     // management. Check this is a physical address size we support
-    let pa_size = match get_sys_reg(ID_AA64MMFR0_EL1, vcpu_fd)? & 0b1111 {
+    let pa_size = match get_reg(ID_AA64MMFR0_EL1)? & 0b1111 {
         0 => 32,
         1 => 36,
         2 => 40,
@@ -117,21 +148,27 @@ pub fn translate_gva(vcpu_fd: &VcpuFd, gva: u64, vmm: &Vmm) -> Result<u64, GdbTa
     // A mask for a physical address mask with the lower 12 bits cleared
     let desc_mask: u64 = pa_address_mask & !lower_mask;
 
+    // Only keep the translated bits, the upper ones select the address range.
+    let va = gva & (!0u64 >> (64 - va_bits));
     let page_indices = [
-        (gva >> (GRAIN_SIZE * 4)) & lower_mask,
-        (gva >> (GRAIN_SIZE * 3)) & lower_mask,
-        (gva >> (GRAIN_SIZE * 2)) & lower_mask,
-        (gva >> GRAIN_SIZE) & lower_mask,
+        (va >> (GRAIN_SIZE * 4)) & lower_mask,
+        (va >> (GRAIN_SIZE * 3)) & lower_mask,
+        (va >> (GRAIN_SIZE * 2)) & lower_mask,
+        (va >> GRAIN_SIZE) & lower_mask,
     ];
 
     // Transition table base register used for initial table lookup.
     // Take the bottom 48 bits from the register value.
-    let mut address: u64 = get_sys_reg(TTBR1_EL1, vcpu_fd)? & pa_address_mask;
-    let mut level = 0;
+    let mut address: u64 = get_reg(ttbr)? & pa_address_mask;
+    // The walk starts at the level translating the top bits of the virtual address.
+    let mut level = u64_to_usize(3 - (va_bits - 13) / 9);
 
     while level < 4 {
         // Clear the bottom 3 bits from this address
-        let pte = read_address(vmm, (address + page_indices[level]) & PTE_ADDRESS_MASK)?;
+        let pte = read_address(
+            guest_memory,
+            (address + page_indices[level]) & PTE_ADDRESS_MASK,
+        )?;
         address = pte & desc_mask;
 
         // If this is a valid table entry and we aren't at the end of the page tables
@@ -153,12 +190,49 @@ pub fn translate_gva(vcpu_fd: &VcpuFd, gva: u64, vmm: &Vmm) -> Result<u64, GdbTa
     Ok(address)
 }
 
-/// Configures the kvm guest debug regs to register the hardware breakpoints
-fn set_kvm_debug(
-    control: u32,
-    vcpu_fd: &VcpuFd,
-    addrs: &[GuestAddress],
-) -> Result<(), GdbTargetError> {
+/// Number of hardware breakpoints and of hardware watchpoints we configure.
+const HW_DEBUG_REG_COUNT: usize = 4;
+
+/// Exception class of a watchpoint hit from a lower exception level, see ESR_EL2.
+const ESR_ELX_EC_WATCHPT_LOW: u32 = 0x34;
+/// Offset of the exception class in ESR_EL2
+const ESR_ELX_EC_SHIFT: u32 = 26;
+
+/// Returns whether there is a free debug register for another hardware breakpoint or watchpoint.
+/// On aarch64 breakpoints and watchpoints have their own registers.
+pub fn has_free_debug_reg(breakpoints: usize, watchpoints: usize, watchpoint: bool) -> bool {
+    if watchpoint {
+        watchpoints < HW_DEBUG_REG_COUNT
+    } else {
+        breakpoints < HW_DEBUG_REG_COUNT
+    }
+}
+
+/// Returns whether a watchpoint can be set on `len` bytes at `addr`. The watched range must fit
+/// in the 8 bytes aligned double word selected by a watchpoint value register.
+pub fn watchpoint_supported(addr: u64, len: u64) -> bool {
+    (1..=8).contains(&len) && (addr & 0b111) + len <= 8
+}
+
+/// Returns the watchpoint which triggered the debug exit, if any. The exception syndrome tells
+/// whether a watchpoint was hit, and the fault address register which address was accessed.
+pub fn hit_watchpoint(
+    debug_exit: &kvm_debug_exit_arch,
+    _breakpoints: usize,
+    watchpoints: &[Watchpoint],
+) -> Option<Watchpoint> {
+    if debug_exit.hsr >> ESR_ELX_EC_SHIFT != ESR_ELX_EC_WATCHPT_LOW {
+        return None;
+    }
+
+    watchpoints
+        .iter()
+        .find(|watchpoint| watchpoint.addr & !0b111 == debug_exit.far & !0b111)
+        .copied()
+}
+
+/// Builds the kvm guest debug regs registering the hardware breakpoints and watchpoints
+fn kvm_debug(control: u32, addrs: &[GuestAddress], watchpoints: &[Watchpoint]) -> kvm_guest_debug {
     let mut dbg = kvm_guest_debug {
         control,
         ..Default::default()
@@ -176,7 +250,38 @@ fn set_kvm_debug(
         dbg.arch.dbg_bvr[i] = (!0u64 >> 11) & addr.0;
     }
 
-    vcpu_fd.set_guest_debug(&dbg)?;
+    for (i, watchpoint) in watchpoints.iter().enumerate() {
+        // LSC: 0b01 watches loads, 0b10 stores and 0b11 both
+        let lsc: u64 = match watchpoint.kind {
+            WatchKind::Read => 0b01,
+            WatchKind::Write => 0b10,
+            WatchKind::ReadWrite => 0b11,
+        };
+        // BAS: one bit for each byte of the double word that is watched
+        let bas: u64 = ((1 << watchpoint.len) - 1) << (watchpoint.addr & 0b111);
+        // DBGWCR_EL1 (Debug Watchpoint Control Registers, D13.3.11):
+        // bit 0: 1 (Enabled)
+        // bit 1~2: 0b11 (PAC = EL1/EL0)
+        // bit 3~4: LSC
+        // bit 5~12: BAS
+        // others: 0
+        dbg.arch.dbg_wcr[i] = 0b1 | (0b11 << 1) | (lsc << 3) | (bas << 5);
+        // DBGWVR_EL1 (Debug Watchpoint Value Registers, D13.3.12):
+        // bit 3~52: VA[3:52]
+        dbg.arch.dbg_wvr[i] = (!0u64 >> 11) & watchpoint.addr & !0b111;
+    }
+
+    dbg
+}
+
+/// Configures the kvm guest debug regs to register the hardware breakpoints and watchpoints
+fn set_kvm_debug(
+    control: u32,
+    vcpu_fd: &VcpuFd,
+    addrs: &[GuestAddress],
+    watchpoints: &[Watchpoint],
+) -> Result<(), GdbTargetError> {
+    vcpu_fd.set_guest_debug(&kvm_debug(control, addrs, watchpoints))?;
 
     Ok(())
 }
@@ -213,6 +318,7 @@ fn toggle_interrupts(vcpu_fd: &VcpuFd, enable: bool) -> Result<(), GdbTargetErro
 pub fn vcpu_set_debug(
     vcpu_fd: &VcpuFd,
     addrs: &[GuestAddress],
+    watchpoints: &[Watchpoint],
     step: bool,
 ) -> Result<(), GdbTargetError> {
     let mut control = KVM_GUESTDBG_ENABLE | KVM_GUESTDBG_USE_HW | KVM_GUESTDBG_USE_SW_BP;
@@ -221,13 +327,14 @@ pub fn vcpu_set_debug(
     }
 
     toggle_interrupts(vcpu_fd, step)?;
-    set_kvm_debug(control, vcpu_fd, addrs)
+    set_kvm_debug(control, vcpu_fd, addrs, watchpoints)
 }
 
 /// KVM does not support injecting breakpoints on aarch64 so this is a no-op
 pub fn vcpu_inject_bp(
     _vcpu_fd: &VcpuFd,
     _addrs: &[GuestAddress],
+    _watchpoints: &[Watchpoint],
     _step: bool,
 ) -> Result<(), GdbTargetError> {
     Ok(())
@@ -309,3 +416,178 @@ pub fn write_registers(vcpu_fd: &VcpuFd, regs: &CoreRegs) -> Result<(), GdbTarge
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::*;
+    use crate::test_utils::single_region_mem;
+
+    const fn watchpoint(addr: u64, len: u64, kind: WatchKind) -> Watchpoint {
+        Watchpoint { addr, len, kind }
+    }
+
+    #[test]
+    fn test_watchpoint_supported() {
+        assert!(watchpoint_supported(0x1000, 8));
+        assert!(watchpoint_supported(0x1003, 5));
+        assert!(watchpoint_supported(0x1007, 1));
+        // Ranges crossing a double word.
+        assert!(!watchpoint_supported(0x1004, 8));
+        assert!(!watchpoint_supported(0x1007, 2));
+        // Unsupported lengths.
+        assert!(!watchpoint_supported(0x1000, 0));
+        assert!(!watchpoint_supported(0x1000, 16));
+    }
+
+    #[test]
+    fn test_has_free_debug_reg() {
+        // Breakpoints and watchpoints have their own registers.
+        assert!(has_free_debug_reg(4, 3, true));
+        assert!(has_free_debug_reg(3, 4, false));
+        assert!(!has_free_debug_reg(0, 4, true));
+        assert!(!has_free_debug_reg(4, 0, false));
+    }
+
+    #[test]
+    fn test_hit_watchpoint() {
+        let watchpoints = [
+            watchpoint(0x1000, 4, WatchKind::Write),
+            watchpoint(0x2004, 4, WatchKind::ReadWrite),
+        ];
+        let debug_exit = |ec: u32, far| kvm_debug_exit_arch {
+            hsr: ec << ESR_ELX_EC_SHIFT,
+            far,
+            ..Default::default()
+        };
+
+        assert_eq!(
+            hit_watchpoint(&debug_exit(ESR_ELX_EC_WATCHPT_LOW, 0x1002), 0, &watchpoints),
+            Some(watchpoints[0])
+        );
+        // The fault address is in the watched double word.
+        assert_eq!(
+            hit_watchpoint(&debug_exit(ESR_ELX_EC_WATCHPT_LOW, 0x2000), 0, &watchpoints),
+            Some(watchpoints[1])
+        );
+        assert_eq!(
+            hit_watchpoint(&debug_exit(ESR_ELX_EC_WATCHPT_LOW, 0x3000), 0, &watchpoints),
+            None
+        );
+        // Breakpoint (0x30) and software step (0x32) exceptions.
+        assert_eq!(
+            hit_watchpoint(&debug_exit(0x30, 0x1000), 0, &watchpoints),
+            None
+        );
+        assert_eq!(
+            hit_watchpoint(&debug_exit(0x32, 0x1000), 0, &watchpoints),
+            None
+        );
+    }
+
+    #[test]
+    fn test_kvm_debug() {
+        let dbg = kvm_debug(
+            KVM_GUESTDBG_ENABLE,
+            &[GuestAddress(0xffff_8000_0000_1004)],
+            &[
+                watchpoint(0x2000, 1, WatchKind::Read),
+                watchpoint(0x3002, 4, WatchKind::Write),
+                watchpoint(0xffff_8000_0000_4000, 8, WatchKind::ReadWrite),
+            ],
+        );
+
+        assert_eq!(dbg.control, KVM_GUESTDBG_ENABLE);
+        assert_eq!(dbg.arch.dbg_bcr[0], 0b1_1110_0111);
+        assert_eq!(dbg.arch.dbg_bvr[0], 0x001f_8000_0000_1004);
+        assert_eq!(dbg.arch.dbg_bcr[1], 0);
+
+        // Enabled, EL1/EL0, LSC and BAS.
+        assert_eq!(
+            dbg.arch.dbg_wcr[0],
+            0b111 | (0b01 << 3) | (0b0000_0001 << 5)
+        );
+        assert_eq!(dbg.arch.dbg_wvr[0], 0x2000);
+        assert_eq!(
+            dbg.arch.dbg_wcr[1],
+            0b111 | (0b10 << 3) | (0b0011_1100 << 5)
+        );
+        assert_eq!(dbg.arch.dbg_wvr[1], 0x3000);
+        assert_eq!(
+            dbg.arch.dbg_wcr[2],
+            0b111 | (0b11 << 3) | (0b1111_1111 << 5)
+        );
+        assert_eq!(dbg.arch.dbg_wvr[2], 0x001f_8000_0000_4000);
+        assert_eq!(dbg.arch.dbg_wcr[3], 0);
+    }
+
+    /// Builds a register source for the page table walk.
+    fn regs(
+        tcr_el1: u64,
+        ttbr0_el1: u64,
+        ttbr1_el1: u64,
+    ) -> impl Fn(u64) -> Result<u64, GdbTargetError> {
+        let regs = HashMap::from([
+            (TCR_EL1, tcr_el1),
+            (TTBR0_EL1, ttbr0_el1),
+            (TTBR1_EL1, ttbr1_el1),
+            // 48 bits of physical address.
+            (ID_AA64MMFR0_EL1, 0b0101),
+        ]);
+        move |reg| Ok(regs[&reg])
+    }
+
+    /// Writes the page table entry `pte` at index `index` of the table at `table`.
+    fn write_pte(guest_memory: &GuestMemoryMmap, table: u64, index: u64, pte: u64) {
+        guest_memory
+            .write_obj(pte, GuestAddress(table + index * 8))
+            .unwrap();
+    }
+
+    #[test]
+    fn test_walk_page_tables() {
+        let guest_memory = single_region_mem(0x10000);
+        // TTBR0_EL1: 48 bits of virtual address (T0SZ = 16), 4KB granule (TG0 = 0b00).
+        // TTBR1_EL1: 39 bits of virtual address (T1SZ = 25), 4KB granule (TG1 = 0b10).
+        let tcr_el1 = 16 | (25 << 16) | (0b10 << 30);
+        let get_reg = regs(tcr_el1, 0x1000, 0x6000);
+
+        // Translation is not enabled yet.
+        assert_eq!(
+            walk_page_tables(regs(0, 0x1000, 0x6000), 0x1234, &guest_memory).unwrap(),
+            0x1234
+        );
+
+        // The lower range starts at level 0 and is mapped with 4KB pages.
+        let gva = (1 << 39) | (2 << 30) | (3 << 21) | (4 << 12) | 0xabc;
+        write_pte(&guest_memory, 0x1000, 1, 0x2000 | 0b11);
+        write_pte(&guest_memory, 0x2000, 2, 0x3000 | 0b11);
+        write_pte(&guest_memory, 0x3000, 3, 0x4000 | 0b11);
+        write_pte(&guest_memory, 0x4000, 4, 0x5000 | 0b11);
+        assert_eq!(
+            walk_page_tables(&get_reg, gva, &guest_memory).unwrap(),
+            0x5abc
+        );
+
+        // The upper range starts at level 1, and is mapped with a 2MB block.
+        let gva = 0xffff_ff80_0000_0000 | (2 << 30) | (5 << 21) | 0x1_2345;
+        write_pte(&guest_memory, 0x6000, 2, 0x7000 | 0b11);
+        write_pte(&guest_memory, 0x7000, 5, 0x20_0000 | 0b01);
+        assert_eq!(
+            walk_page_tables(&get_reg, gva, &guest_memory).unwrap(),
+            0x21_2345
+        );
+
+        // Only 4KB granules are supported.
+        let get_reg = regs(
+            16 | (25 << 16) | (0b01 << 14) | (0b10 << 30),
+            0x1000,
+            0x6000,
+        );
+        assert!(matches!(
+            walk_page_tables(&get_reg, 0x1000, &guest_memory),
+            Err(GdbTargetError::GvaTranslateError)
+        ));
+    }
+}
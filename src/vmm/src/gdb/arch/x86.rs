@@ -1,19 +1,27 @@
 // Copyright 2024 Amazon.com, Inc. or its affiliates. All Rights Reserved.
 // SPDX-License-Identifier: Apache-2.0
 
+use gdbstub::target::ext::breakpoints::WatchKind;
 use gdbstub_arch::x86::reg::X86_64CoreRegs as CoreRegs;
 use kvm_bindings::*;
 use kvm_ioctls::VcpuFd;
 use vm_memory::GuestAddress;
 
 use crate::Vmm;
-use crate::gdb::target::GdbTargetError;
+use crate::gdb::target::{GdbTargetError, Watchpoint};
 use crate::logger::error;
 
 /// Sets the 9th (Global Exact Breakpoint enable) and the 10th (always 1) bits for the DR7 debug
 /// control register
 const X86_GLOBAL_DEBUG_ENABLE: u64 = 0b11 << 9;
 
+/// Number of debug address registers (DR0-DR3), shared between hardware breakpoints and
+/// watchpoints
+const X86_DEBUG_REG_COUNT: usize = 4;
+
+/// Offset of the R/W and LEN fields of DR0 in DR7, the fields of DRn are 4 bits further each
+const X86_DR7_RW_LEN_SHIFT: usize = 16;
+
 /// Op code to trigger a software breakpoint in x86
 const X86_SW_BP_OP: u8 = 0xCC;
 
@@ -41,16 +49,37 @@ pub fn translate_gva(vcpu_fd: &VcpuFd, gva: u64, _vmm: &Vmm) -> Result<u64, GdbT
     Ok(tr.physical_address)
 }
 
-/// Configures the kvm guest debug regs to register the hardware breakpoints, the `arch.debugreg`
-/// attribute is used to store the location of the hardware breakpoints, with the 8th slot being
-/// used as a bitfield to track which registers are enabled and setting the
-/// `X86_GLOBAL_DEBUG_ENABLE` flags. Further reading on the DR7 register can be found here:
+/// Returns whether there is a free debug register for another hardware breakpoint or watchpoint.
+/// On x86 the 4 debug address registers are shared between them.
+pub fn has_free_debug_reg(breakpoints: usize, watchpoints: usize, _watchpoint: bool) -> bool {
+    breakpoints + watchpoints < X86_DEBUG_REG_COUNT
+}
+
+/// Returns whether a watchpoint can be set on `len` bytes at `addr`. The watched range must be
+/// 1, 2, 4 or 8 bytes long and aligned on its length.
+pub fn watchpoint_supported(addr: u64, len: u64) -> bool {
+    matches!(len, 1 | 2 | 4 | 8) && addr % len == 0
+}
+
+/// Returns the watchpoint which triggered the debug exit, if any. DR6 tells which of the debug
+/// address registers were hit, the watchpoints being stored after the breakpoints.
+pub fn hit_watchpoint(
+    debug_exit: &kvm_debug_exit_arch,
+    breakpoints: usize,
+    watchpoints: &[Watchpoint],
+) -> Option<Watchpoint> {
+    (breakpoints..X86_DEBUG_REG_COUNT)
+        .find(|&i| debug_exit.dr6 & (1 << i) != 0)
+        .and_then(|i| watchpoints.get(i - breakpoints).copied())
+}
+
+/// Builds the kvm guest debug regs registering the hardware breakpoints and watchpoints, the
+/// `arch.debugreg` attribute is used to store the location of the hardware breakpoints followed
+/// by the watchpoints, with the 8th slot being used as a bitfield to track which registers are
+/// enabled, what they watch and setting the `X86_GLOBAL_DEBUG_ENABLE` flags. Further reading on
+/// the DR7 register can be found here:
 /// https://en.wikipedia.org/wiki/X86_debug_register#DR7_-_Debug_control
-fn set_kvm_debug(
-    control: u32,
-    vcpu_fd: &VcpuFd,
-    addrs: &[GuestAddress],
-) -> Result<(), GdbTargetError> {
+fn kvm_debug(control: u32, addrs: &[GuestAddress], watchpoints: &[Watchpoint]) -> kvm_guest_debug {
     let mut dbg = kvm_guest_debug {
         control,
         ..Default::default()
@@ -64,15 +93,44 @@ fn set_kvm_debug(
         dbg.arch.debugreg[7] |= 2 << (i * 2);
     }
 
-    vcpu_fd.set_guest_debug(&dbg)?;
+    for (i, watchpoint) in (addrs.len()..X86_DEBUG_REG_COUNT).zip(watchpoints) {
+        // R/W: 0b01 breaks on writes, 0b11 on reads and writes. Reads alone can't be watched.
+        let rw: u64 = match watchpoint.kind {
+            WatchKind::Write => 0b01,
+            WatchKind::Read | WatchKind::ReadWrite => 0b11,
+        };
+        // LEN: 0b00 for 1 byte, 0b01 for 2 bytes, 0b11 for 4 bytes and 0b10 for 8 bytes
+        let len: u64 = match watchpoint.len {
+            1 => 0b00,
+            2 => 0b01,
+            4 => 0b11,
+            _ => 0b10,
+        };
+        dbg.arch.debugreg[i] = watchpoint.addr;
+        dbg.arch.debugreg[7] |= 2 << (i * 2);
+        dbg.arch.debugreg[7] |= (rw | (len << 2)) << (X86_DR7_RW_LEN_SHIFT + i * 4);
+    }
+
+    dbg
+}
+
+/// Configures the kvm guest debug regs to register the hardware breakpoints and watchpoints
+fn set_kvm_debug(
+    control: u32,
+    vcpu_fd: &VcpuFd,
+    addrs: &[GuestAddress],
+    watchpoints: &[Watchpoint],
+) -> Result<(), GdbTargetError> {
+    vcpu_fd.set_guest_debug(&kvm_debug(control, addrs, watchpoints))?;
 
     Ok(())
 }
 
-/// Configures the Vcpu for debugging and sets the hardware breakpoints on the Vcpu
+/// Configures the Vcpu for debugging and sets the hardware breakpoints and watchpoints on the Vcpu
 pub fn vcpu_set_debug(
     vcpu_fd: &VcpuFd,
     addrs: &[GuestAddress],
+    watchpoints: &[Watchpoint],
     step: bool,
 ) -> Result<(), GdbTargetError> {
     let mut control = KVM_GUESTDBG_ENABLE | KVM_GUESTDBG_USE_HW_BP | KVM_GUESTDBG_USE_SW_BP;
@@ -80,7 +138,7 @@ pub fn vcpu_set_debug(
         control |= KVM_GUESTDBG_SINGLESTEP;
     }
 
-    set_kvm_debug(control, vcpu_fd, addrs)
+    set_kvm_debug(control, vcpu_fd, addrs, watchpoints)
 }
 
 /// Injects a BP back into the guest kernel for it to handle, this is particularly useful for the
@@ -88,6 +146,7 @@ pub fn vcpu_set_debug(
 pub fn vcpu_inject_bp(
     vcpu_fd: &VcpuFd,
     addrs: &[GuestAddress],
+    watchpoints: &[Watchpoint],
     step: bool,
 ) -> Result<(), GdbTargetError> {
     let mut control = KVM_GUESTDBG_ENABLE
@@ -99,7 +158,7 @@ pub fn vcpu_inject_bp(
         control |= KVM_GUESTDBG_SINGLESTEP;
     }
 
-    set_kvm_debug(control, vcpu_fd, addrs)
+    set_kvm_debug(control, vcpu_fd, addrs, watchpoints)
 }
 
 /// Reads the registers for the Vcpu
@@ -159,3 +218,109 @@ pub fn write_registers(vcpu_fd: &VcpuFd, regs: &CoreRegs) -> Result<(), GdbTarge
 
     Ok(vcpu_fd.set_regs(&new_regs)?)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const fn watchpoint(addr: u64, len: u64, kind: WatchKind) -> Watchpoint {
+        Watchpoint { addr, len, kind }
+    }
+
+    #[test]
+    fn test_watchpoint_supported() {
+        assert!(watchpoint_supported(0x1001, 1));
+        assert!(watchpoint_supported(0x1002, 2));
+        assert!(watchpoint_supported(0x1004, 4));
+        assert!(watchpoint_supported(0x1008, 8));
+        // Unaligned ranges.
+        assert!(!watchpoint_supported(0x1001, 2));
+        assert!(!watchpoint_supported(0x1004, 8));
+        // Unsupported lengths.
+        assert!(!watchpoint_supported(0x1000, 3));
+        assert!(!watchpoint_supported(0x1000, 16));
+    }
+
+    #[test]
+    fn test_has_free_debug_reg() {
+        // The debug registers are shared between breakpoints and watchpoints.
+        assert!(has_free_debug_reg(0, 0, false));
+        assert!(has_free_debug_reg(2, 1, true));
+        assert!(has_free_debug_reg(1, 2, false));
+        assert!(!has_free_debug_reg(2, 2, true));
+        assert!(!has_free_debug_reg(4, 0, true));
+        assert!(!has_free_debug_reg(0, 4, false));
+    }
+
+    #[test]
+    fn test_hit_watchpoint() {
+        let watchpoints = [
+            watchpoint(0x1000, 4, WatchKind::Write),
+            watchpoint(0x2000, 8, WatchKind::ReadWrite),
+        ];
+        let debug_exit = |dr6| kvm_debug_exit_arch {
+            dr6,
+            ..Default::default()
+        };
+
+        // DR0 holds the only breakpoint, DR1 and DR2 the watchpoints.
+        assert_eq!(hit_watchpoint(&debug_exit(0b0001), 1, &watchpoints), None);
+        assert_eq!(
+            hit_watchpoint(&debug_exit(0b0010), 1, &watchpoints),
+            Some(watchpoints[0])
+        );
+        assert_eq!(
+            hit_watchpoint(&debug_exit(0b0100), 1, &watchpoints),
+            Some(watchpoints[1])
+        );
+        // DR3 is not used.
+        assert_eq!(hit_watchpoint(&debug_exit(0b1000), 1, &watchpoints), None);
+        // Single step, with the BS bit.
+        assert_eq!(hit_watchpoint(&debug_exit(1 << 14), 1, &watchpoints), None);
+    }
+
+    #[test]
+    fn test_kvm_debug() {
+        let dbg = kvm_debug(KVM_GUESTDBG_ENABLE, &[], &[]);
+        assert_eq!(dbg.control, KVM_GUESTDBG_ENABLE);
+        assert_eq!(
+            dbg.arch.debugreg,
+            [0, 0, 0, 0, 0, 0, 0, X86_GLOBAL_DEBUG_ENABLE]
+        );
+
+        let dbg = kvm_debug(
+            KVM_GUESTDBG_ENABLE,
+            &[GuestAddress(0x1000)],
+            &[
+                watchpoint(0x2000, 1, WatchKind::Write),
+                watchpoint(0x3000, 2, WatchKind::Read),
+                watchpoint(0x4000, 8, WatchKind::ReadWrite),
+            ],
+        );
+        assert_eq!(dbg.arch.debugreg[..4], [0x1000, 0x2000, 0x3000, 0x4000]);
+        assert_eq!(
+            dbg.arch.debugreg[7],
+            X86_GLOBAL_DEBUG_ENABLE
+                // G0 to G3.
+                | 0b1010_1010
+                // DR1: write, 1 byte.
+                | (0b0001 << 20)
+                // DR2: read or write, 2 bytes.
+                | (0b0111 << 24)
+                // DR3: read or write, 8 bytes.
+                | (0b1011 << 28)
+        );
+
+        // 4 bytes watchpoints, without breakpoints.
+        let dbg = kvm_debug(
+            KVM_GUESTDBG_ENABLE,
+            &[],
+            &[watchpoint(0x2000, 4, WatchKind::Write)],
+        );
+        assert_eq!(dbg.arch.debugreg[0], 0x2000);
+        assert_eq!(
+            dbg.arch.debugreg[7],
+            X86_GLOBAL_DEBUG_ENABLE | 0b10 | (0b1101 << 16)
+        );
+    }
+}
@@ -14,7 +14,8 @@ use gdbstub::target::Target;
 use kvm_ioctls::VcpuFd;
 use vm_memory::GuestAddress;
 
-use super::target::{FirecrackerTarget, GdbTargetError, vcpuid_to_tid};
+use super::DebugResources;
+use super::target::{FirecrackerTarget, GdbEvent, GdbTargetError, vcpuid_to_tid};
 use crate::Vmm;
 use crate::logger::{error, trace};
 
@@ -23,7 +24,7 @@ pub fn event_loop(
     connection: UnixStream,
     vmm: Arc<Mutex<Vmm>>,
     vcpu_fds: Vec<VcpuFd>,
-    gdb_event_receiver: Receiver<usize>,
+    gdb_event_receiver: Receiver<GdbEvent>,
     entry_addr: GuestAddress,
 ) {
     let target = FirecrackerTarget::new(vmm, vcpu_fds, gdb_event_receiver, entry_addr);
@@ -39,6 +40,41 @@ pub fn event_loop(
     gdb_event_loop_thread(debugger, target);
 }
 
+/// Starts the GDB event loop for a session attached to a running microVM. When GDB detaches the
+/// Vcpus resume, unless GDB asked to kill the microVM.
+pub fn runtime_event_loop(connection: UnixStream, vmm: Arc<Mutex<Vmm>>, resources: DebugResources) {
+    let mut target = match FirecrackerTarget::attach(
+        vmm.clone(),
+        resources.vcpu_fds,
+        resources.gdb_event_receiver,
+    ) {
+        Ok(target) => target,
+        Err(err) => {
+            // Without the Vcpu fds no other session can be attached, stop there.
+            error!("Error attaching GDB to the microVM: {err}");
+            return;
+        }
+    };
+    let connection: Box<dyn ConnectionExt<Error = std::io::Error>> = { Box::new(connection) };
+    let debugger = GdbStub::new(connection);
+
+    if let Some(DisconnectReason::Kill) = run_gdb_session(debugger, &mut target) {
+        target.shutdown_vmm();
+        return;
+    }
+
+    match target.detach() {
+        Ok((vcpu_fds, gdb_event_receiver)) => {
+            trace!("GDB detached, Vcpus resumed");
+            vmm.lock().expect("Poisoned lock").debug_resources = Some(DebugResources {
+                vcpu_fds,
+                gdb_event_receiver,
+            });
+        }
+        Err(err) => error!("Error detaching GDB from the microVM: {err}"),
+    }
+}
+
 struct GdbBlockingEventLoop {}
 
 impl run_blocking::BlockingEventLoop for GdbBlockingEventLoop {
@@ -60,16 +96,16 @@ impl run_blocking::BlockingEventLoop for GdbBlockingEventLoop {
     > {
         loop {
             match target.gdb_event.try_recv() {
-                Ok(cpu_id) => {
+                Ok(event) => {
                     // The Vcpu reports it's id from raw_id so we straight convert here
-                    let tid = Tid::new(cpu_id).expect("Error converting cpu id to Tid");
+                    let tid = Tid::new(event.raw_tid).expect("Error converting cpu id to Tid");
                     // If notify paused returns false this means we were already debugging a single
                     // core, the target will track this for us to pick up later
                     target.set_paused_vcpu(tid);
                     trace!("Vcpu: {tid:?} paused from debug exit");
 
                     let stop_reason = target
-                        .get_stop_reason(tid)
+                        .get_stop_reason(tid, &event.debug_exit)
                         .map_err(WaitForStopReasonError::Target)?;
 
                     let Some(stop_response) = stop_reason else {
@@ -131,19 +167,32 @@ fn gdb_event_loop_thread(
     debugger: GdbStub<FirecrackerTarget, Box<dyn ConnectionExt<Error = std::io::Error>>>,
     mut target: FirecrackerTarget,
 ) {
-    match debugger.run_blocking::<GdbBlockingEventLoop>(&mut target) {
-        Ok(disconnect_reason) => match disconnect_reason {
-            DisconnectReason::Disconnect => {
-                trace!("Client disconnected")
-            }
-            DisconnectReason::TargetExited(code) => {
-                trace!("Target exited with code {}", code)
-            }
-            DisconnectReason::TargetTerminated(sig) => {
-                trace!("Target terminated with signal {}", sig)
+    run_gdb_session(debugger, &mut target);
+    target.shutdown_vmm();
+}
+
+/// Runs while communication with GDB is in progress, returns why GDB disconnected or `None` if
+/// the session failed
+fn run_gdb_session(
+    debugger: GdbStub<FirecrackerTarget, Box<dyn ConnectionExt<Error = std::io::Error>>>,
+    target: &mut FirecrackerTarget,
+) -> Option<DisconnectReason> {
+    match debugger.run_blocking::<GdbBlockingEventLoop>(target) {
+        Ok(disconnect_reason) => {
+            match disconnect_reason {
+                DisconnectReason::Disconnect => {
+                    trace!("Client disconnected")
+                }
+                DisconnectReason::TargetExited(code) => {
+                    trace!("Target exited with code {}", code)
+                }
+                DisconnectReason::TargetTerminated(sig) => {
+                    trace!("Target terminated with signal {}", sig)
+                }
+                DisconnectReason::Kill => trace!("GDB sent a kill command"),
             }
-            DisconnectReason::Kill => trace!("GDB sent a kill command"),
-        },
+            Some(disconnect_reason)
+        }
         Err(e) => {
             if e.is_target_error() {
                 error!("target encountered a fatal error: {e:?}")
@@ -152,8 +201,7 @@ fn gdb_event_loop_thread(
             } else {
                 error!("gdbstub encountered a fatal error {e:?}")
             }
+            None
         }
     }
-
-    target.shutdown_vmm();
 }
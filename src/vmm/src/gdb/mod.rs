@@ -9,18 +9,28 @@ mod event_loop;
 pub mod target;
 
 use std::os::unix::net::UnixListener;
-use std::path::Path;
+use std::path::{Path, PathBuf};
 use std::sync::mpsc::Receiver;
 use std::sync::{Arc, Mutex};
 
 use arch::vcpu_set_debug;
-use event_loop::event_loop;
+use event_loop::{event_loop, runtime_event_loop};
 use kvm_ioctls::VcpuFd;
-use target::GdbTargetError;
+use target::{GdbEvent, GdbTargetError};
 use vm_memory::GuestAddress;
 
 use crate::Vmm;
-use crate::logger::trace;
+use crate::logger::{error, trace};
+
+/// What a GDB session needs to drive the Vcpus: a copy of their fds and the receiving end of the
+/// channel the Vcpus report their debug exits on.
+#[derive(Debug)]
+pub struct DebugResources {
+    /// Copies of the Vcpu fds, in Vcpu id order
+    pub vcpu_fds: Vec<VcpuFd>,
+    /// Receives the debug events sent by the Vcpus
+    pub gdb_event_receiver: Receiver<GdbEvent>,
+}
 
 /// Kickstarts the GDB debugging process, it takes in the VMM object, a slice of
 /// the paused Vcpu's, the GDB event queue which is used as a mechanism for the Vcpu's to notify
@@ -35,19 +45,23 @@ use crate::logger::trace;
 /// communcation to the GDB server
 pub fn gdb_thread(
     vmm: Arc<Mutex<Vmm>>,
-    vcpu_fds: Vec<VcpuFd>,
-    gdb_event_receiver: Receiver<usize>,
+    resources: DebugResources,
     entry_addr: GuestAddress,
     socket_addr: &str,
 ) -> Result<(), GdbTargetError> {
+    let DebugResources {
+        vcpu_fds,
+        gdb_event_receiver,
+    } = resources;
+
     // We register a hw breakpoint at the entry point as GDB expects the application
     // to be stopped as it connects. This also allows us to set breakpoints before kernel starts.
     // This entry adddress is automatically used as it is not tracked inside the target state, so
     // when resumed will be removed
-    vcpu_set_debug(&vcpu_fds[0], &[entry_addr], false)?;
+    vcpu_set_debug(&vcpu_fds[0], &[entry_addr], &[], false)?;
 
     for vcpu_fd in &vcpu_fds[1..] {
-        vcpu_set_debug(vcpu_fd, &[], false)?;
+        vcpu_set_debug(vcpu_fd, &[], &[], false)?;
     }
 
     let path = Path::new(socket_addr);
@@ -64,3 +78,45 @@ pub fn gdb_thread(
 
     Ok(())
 }
+
+/// Starts a GDB session on a running microVM.
+///
+/// The GDB socket is created straight away, and a new thread waits for GDB to connect to it. On
+/// connection all the Vcpus are paused and configured for debugging. When GDB detaches, the
+/// breakpoints are removed and the Vcpus resume, so another session can be attached later.
+///
+/// Fails if a debugger is already attached, or was attached at boot.
+pub fn attach_debugger(vmm: Arc<Mutex<Vmm>>, socket_addr: &str) -> Result<(), GdbTargetError> {
+    let resources = vmm
+        .lock()?
+        .debug_resources
+        .take()
+        .ok_or(GdbTargetError::DebuggerAttached)?;
+
+    let path = PathBuf::from(socket_addr);
+    let listener = match UnixListener::bind(&path) {
+        Ok(listener) => listener,
+        Err(_) => {
+            vmm.lock()?.debug_resources = Some(resources);
+            return Err(GdbTargetError::ServerSocketError);
+        }
+    };
+
+    std::thread::Builder::new()
+        .name("gdb".into())
+        .spawn(move || {
+            trace!("Waiting for GDB server connection on {}...", path.display());
+            match listener.accept() {
+                Ok((connection, _addr)) => runtime_event_loop(connection, vmm, resources),
+                Err(err) => {
+                    error!("Error accepting the GDB connection: {err}");
+                    vmm.lock().expect("Poisoned lock").debug_resources = Some(resources);
+                }
+            }
+            // The session is over, make room for the next one.
+            let _ = std::fs::remove_file(&path);
+        })
+        .map_err(|_| GdbTargetError::GdbThreadError)?;
+
+    Ok(())
+}
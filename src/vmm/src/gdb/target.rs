@@ -8,6 +8,7 @@ use std::sync::{Arc, Mutex, PoisonError};
 use arrayvec::ArrayVec;
 use gdbstub::arch::Arch;
 use gdbstub::common::{Signal, Tid};
+use gdbstub::outputln;
 use gdbstub::stub::{BaseStopReason, MultiThreadStopReason};
 use gdbstub::target::ext::base::BaseOps;
 use gdbstub::target::ext::base::multithread::{
@@ -15,8 +16,11 @@ use gdbstub::target::ext::base::multithread::{
     MultiThreadSingleStepOps,
 };
 use gdbstub::target::ext::breakpoints::{
-    Breakpoints, BreakpointsOps, HwBreakpoint, HwBreakpointOps, SwBreakpoint, SwBreakpointOps,
+    Breakpoints, BreakpointsOps, HwBreakpoint, HwBreakpointOps, HwWatchpoint, HwWatchpointOps,
+    SwBreakpoint, SwBreakpointOps, WatchKind,
 };
+use gdbstub::target::ext::memory_map::{MemoryMap, MemoryMapOps};
+use gdbstub::target::ext::monitor_cmd::{ConsoleOutput, MonitorCmd, MonitorCmdOps};
 use gdbstub::target::ext::thread_extra_info::{ThreadExtraInfo, ThreadExtraInfoOps};
 use gdbstub::target::{Target, TargetError, TargetResult};
 #[cfg(target_arch = "aarch64")]
@@ -27,8 +31,9 @@ use gdbstub_arch::aarch64::reg::AArch64CoreRegs as CoreRegs;
 use gdbstub_arch::x86::X86_64_SSE as GdbArch;
 #[cfg(target_arch = "x86_64")]
 use gdbstub_arch::x86::reg::X86_64CoreRegs as CoreRegs;
+use kvm_bindings::{kvm_debug_exit_arch, kvm_guest_debug};
 use kvm_ioctls::VcpuFd;
-use vm_memory::{Bytes, GuestAddress, GuestMemoryError};
+use vm_memory::{Bytes, GuestAddress, GuestMemory, GuestMemoryError, GuestMemoryRegion};
 
 use super::arch;
 use crate::arch::GUEST_PAGE_SIZE;
@@ -36,6 +41,7 @@ use crate::arch::GUEST_PAGE_SIZE;
 use crate::arch::aarch64::vcpu::VcpuArchError as AarchVcpuError;
 use crate::logger::{error, info};
 use crate::utils::u64_to_usize;
+use crate::vmm_config::instance_info::VmState;
 use crate::vstate::vcpu::VcpuSendEventError;
 use crate::{FcExitCode, VcpuEvent, VcpuResponse, Vmm};
 
@@ -63,16 +69,55 @@ impl VcpuState {
     }
 
     /// Updates the kvm debug flags set against the Vcpu with a check
-    fn update_kvm_debug(&self, hw_breakpoints: &[GuestAddress]) -> Result<(), GdbTargetError> {
+    fn update_kvm_debug(
+        &self,
+        hw_breakpoints: &[GuestAddress],
+        hw_watchpoints: &[Watchpoint],
+    ) -> Result<(), GdbTargetError> {
         if !self.paused {
             info!("Attempted to update kvm debug on a non paused Vcpu");
             return Ok(());
         }
 
-        arch::vcpu_set_debug(&self.vcpu_fd, hw_breakpoints, self.single_step)
+        arch::vcpu_set_debug(
+            &self.vcpu_fd,
+            hw_breakpoints,
+            hw_watchpoints,
+            self.single_step,
+        )
     }
 }
 
+/// A hardware watchpoint set by GDB
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Watchpoint {
+    /// Guest virtual address of the watched memory
+    pub addr: u64,
+    /// Number of bytes watched
+    pub len: u64,
+    /// The accesses to watch
+    pub kind: WatchKind,
+}
+
+/// Event sent by a Vcpu to the GDB thread when it exits on a debug event
+#[derive(Debug, Clone, Copy)]
+pub struct GdbEvent {
+    /// The 1 indexed id of the Vcpu, see [`get_raw_tid`]
+    pub raw_tid: usize,
+    /// The details KVM gives about the debug exit
+    pub debug_exit: kvm_debug_exit_arch,
+}
+
+/// Maximum number of bytes read by the `phys-read` monitor command
+const MONITOR_MAX_READ_LEN: u64 = 4096;
+/// Number of bytes read by the `phys-read` monitor command when no length is given
+const MONITOR_DEFAULT_READ_LEN: u64 = 64;
+/// Help of the monitor commands
+const MONITOR_HELP: &str = "\
+phys-read <gpa> [<len>]  Reads <len> (default 64, at most 4096) bytes of guest physical memory
+gva2gpa <gva>            Translates a guest virtual address with the paused Vcpu page tables
+help                     Prints this help";
+
 /// Errors from interactions between GDB and the VMM
 #[derive(Debug, thiserror::Error, displaydoc::Display)]
 pub enum GdbTargetError {
@@ -90,6 +135,8 @@ pub enum GdbTargetError {
     ServerSocketError,
     /// Error with creating GDB thread
     GdbThreadError,
+    /// A debugger is already attached to the microVM
+    DebuggerAttached,
     /// VMM locking error
     VmmLockError,
     /// Vcpu send event error
@@ -136,16 +183,19 @@ impl<E> From<PoisonError<E>> for GdbTargetError {
 pub struct FirecrackerTarget {
     /// A mutex around the VMM to allow communicataion to the Vcpus
     vmm: Arc<Mutex<Vmm>>,
-    /// Store the guest entry point
-    entry_addr: GuestAddress,
+    /// Store the guest entry point, when the session started at boot
+    entry_addr: Option<GuestAddress>,
 
     /// Listener for events sent from the Vcpu
-    pub gdb_event: Receiver<usize>,
+    pub gdb_event: Receiver<GdbEvent>,
 
     /// Used to track the currently configured hardware breakpoints.
     /// Limited to 4 in x86 see:
     /// https://elixir.bootlin.com/linux/v6.1/source/arch/x86/include/asm/kvm_host.h#L210
     hw_breakpoints: ArrayVec<GuestAddress, 4>,
+    /// Used to track the currently configured hardware watchpoints, which share the 4 debug
+    /// registers with the hardware breakpoints on x86.
+    hw_watchpoints: ArrayVec<Watchpoint, 4>,
     /// Used to track the currently configured software breakpoints and store the op-code
     /// which was swapped out
     sw_breakpoints: HashMap<<GdbArch as Arch>::Usize, [u8; arch::SW_BP_SIZE]>,
@@ -156,6 +206,10 @@ pub struct FirecrackerTarget {
     /// Stores the current paused thread id, GDB can inact commands without providing us a Tid to
     /// run on and expects us to use the last paused thread.
     paused_vcpu: Option<Tid>,
+
+    /// Whether the Vcpus are resumed when GDB detaches, i.e. whether the microVM was running when
+    /// GDB attached, rather than paused through the API.
+    resume_on_detach: bool,
 }
 
 /// Convert the 1 indexed Tid to the 0 indexed Vcpuid
@@ -182,7 +236,7 @@ impl FirecrackerTarget {
     pub fn new(
         vmm: Arc<Mutex<Vmm>>,
         vcpu_fds: Vec<VcpuFd>,
-        gdb_event: Receiver<usize>,
+        gdb_event: Receiver<GdbEvent>,
         entry_addr: GuestAddress,
     ) -> Self {
         let mut vcpu_state: Vec<_> = vcpu_fds.into_iter().map(VcpuState::from_vcpu_fd).collect();
@@ -191,17 +245,92 @@ impl FirecrackerTarget {
 
         Self {
             vmm,
-            entry_addr,
+            entry_addr: Some(entry_addr),
             gdb_event,
             // We only support 4 hw breakpoints on x86 this will need to be configurable on arm
             hw_breakpoints: Default::default(),
+            hw_watchpoints: Default::default(),
             sw_breakpoints: HashMap::new(),
             vcpu_state,
 
             paused_vcpu: Tid::new(1),
+            resume_on_detach: true,
         }
     }
 
+    /// Creates a new Target for a GDB session started on a running microVM. All the Vcpus are
+    /// paused and configured for debugging, GDB is told the first Vcpu stopped.
+    pub fn attach(
+        vmm: Arc<Mutex<Vmm>>,
+        vcpu_fds: Vec<VcpuFd>,
+        gdb_event: Receiver<GdbEvent>,
+    ) -> Result<Self, GdbTargetError> {
+        let resume_on_detach = vmm.lock()?.instance_info.state == VmState::Running;
+        let vcpu_state = vcpu_fds.into_iter().map(VcpuState::from_vcpu_fd).collect();
+        let mut target = Self {
+            vmm,
+            entry_addr: None,
+            gdb_event,
+            hw_breakpoints: Default::default(),
+            hw_watchpoints: Default::default(),
+            sw_breakpoints: HashMap::new(),
+            vcpu_state,
+
+            paused_vcpu: None,
+            resume_on_detach,
+        };
+
+        target.pause_all_vcpus()?;
+        target.vcpu_state.iter().try_for_each(|state| {
+            state.update_kvm_debug(&target.hw_breakpoints, &target.hw_watchpoints)
+        })?;
+        target.paused_vcpu = Tid::new(1);
+
+        Ok(target)
+    }
+
+    /// Ends a session started with [`FirecrackerTarget::attach`]: restores the instructions
+    /// replaced by software breakpoints, disables debugging on the Vcpus and resumes them, unless
+    /// the microVM was paused through the API when GDB attached. Returns the Vcpu fds and the
+    /// event channel so a later session can use them.
+    pub fn detach(mut self) -> Result<(Vec<VcpuFd>, Receiver<GdbEvent>), GdbTargetError> {
+        // The Vcpus which did not hit a breakpoint are still running.
+        self.pause_all_vcpus()?;
+
+        {
+            let vmm = self.vmm.lock()?;
+            for (gpa, original) in self.sw_breakpoints.drain() {
+                vmm.vm
+                    .guest_memory()
+                    .write_slice(&original, GuestAddress(gpa))?;
+            }
+        }
+        self.hw_breakpoints.clear();
+        self.hw_watchpoints.clear();
+
+        for state in self.vcpu_state.iter_mut() {
+            state.reset_vcpu_state();
+            state.vcpu_fd.set_guest_debug(&kvm_guest_debug::default())?;
+        }
+
+        // Debug exits which raced with the pause are of no interest anymore.
+        while self.gdb_event.try_recv().is_ok() {}
+
+        if self.resume_on_detach {
+            for cpu_id in 0..self.vcpu_state.len() {
+                self.resume_vcpu(vcpuid_to_tid(cpu_id)?)?;
+            }
+        }
+
+        let Self {
+            vcpu_state,
+            gdb_event,
+            ..
+        } = self;
+        let vcpu_fds = vcpu_state.into_iter().map(|state| state.vcpu_fd).collect();
+        Ok((vcpu_fds, gdb_event))
+    }
+
     /// Retrieves the currently paused Vcpu id returns an error if there is no currently paused Vcpu
     fn get_paused_vcpu_id(&self) -> Result<Tid, GdbTargetError> {
         self.paused_vcpu.ok_or(GdbTargetError::NoPausedVcpu)
@@ -224,9 +353,9 @@ impl FirecrackerTarget {
     /// Resumes execution of all paused Vcpus, update them with current kvm debug info
     /// and resumes
     fn resume_all_vcpus(&mut self) -> Result<(), GdbTargetError> {
-        self.vcpu_state
-            .iter()
-            .try_for_each(|state| state.update_kvm_debug(&self.hw_breakpoints))?;
+        self.vcpu_state.iter().try_for_each(|state| {
+            state.update_kvm_debug(&self.hw_breakpoints, &self.hw_watchpoints)
+        })?;
 
         for cpu_id in 0..self.vcpu_state.len() {
             let tid = vcpuid_to_tid(cpu_id)?;
@@ -238,6 +367,14 @@ impl FirecrackerTarget {
         Ok(())
     }
 
+    /// Pauses all the running Vcpus
+    fn pause_all_vcpus(&mut self) -> Result<(), GdbTargetError> {
+        for cpu_id in 0..self.vcpu_state.len() {
+            self.pause_vcpu(vcpuid_to_tid(cpu_id)?)?;
+        }
+        Ok(())
+    }
+
     /// Resets all Vcpus to their base state
     fn reset_all_vcpu_states(&mut self) {
         for value in self.vcpu_state.iter_mut() {
@@ -275,7 +412,12 @@ impl FirecrackerTarget {
     /// A helper function to allow the event loop to inject this breakpoint back into the Vcpu
     pub fn inject_bp_to_guest(&mut self, tid: Tid) -> Result<(), GdbTargetError> {
         let vcpu_state = &mut self.vcpu_state[tid_to_vcpuid(tid)];
-        arch::vcpu_inject_bp(&vcpu_state.vcpu_fd, &self.hw_breakpoints, false)
+        arch::vcpu_inject_bp(
+            &vcpu_state.vcpu_fd,
+            &self.hw_breakpoints,
+            &self.hw_watchpoints,
+            false,
+        )
     }
 
     /// Resumes the Vcpu, will return early if the Vcpu is already running
@@ -306,6 +448,7 @@ impl FirecrackerTarget {
     pub fn get_stop_reason(
         &self,
         tid: Tid,
+        debug_exit: &kvm_debug_exit_arch,
     ) -> Result<Option<BaseStopReason<Tid, u64>>, GdbTargetError> {
         let vcpu_state = &self.vcpu_state[tid_to_vcpuid(tid)];
         if vcpu_state.single_step {
@@ -315,6 +458,16 @@ impl FirecrackerTarget {
             }));
         }
 
+        if let Some(watchpoint) =
+            arch::hit_watchpoint(debug_exit, self.hw_breakpoints.len(), &self.hw_watchpoints)
+        {
+            return Ok(Some(MultiThreadStopReason::Watch {
+                tid,
+                kind: watchpoint.kind,
+                addr: watchpoint.addr,
+            }));
+        }
+
         let Ok(ip) = arch::get_instruction_pointer(&vcpu_state.vcpu_fd) else {
             // If we error here we return an arbitrary Software Breakpoint, GDB will handle
             // this gracefully
@@ -330,13 +483,111 @@ impl FirecrackerTarget {
             return Ok(Some(MultiThreadStopReason::HwBreak(tid)));
         }
 
-        if ip == self.entry_addr.0 {
+        if Some(GuestAddress(ip)) == self.entry_addr {
             return Ok(Some(MultiThreadStopReason::HwBreak(tid)));
         }
 
         // This is not a breakpoint we've set, likely one set by the guest
         Ok(None)
     }
+
+    /// Builds the GDB memory map of the guest RAM regions
+    fn memory_map(&self) -> Result<String, GdbTargetError> {
+        let vmm = self.vmm.lock()?;
+        let regions: String = vmm
+            .vm
+            .guest_memory()
+            .iter()
+            .map(|region| {
+                format!(
+                    "<memory type=\"ram\" start=\"{:#x}\" length=\"{:#x}\"/>",
+                    region.start_addr().0,
+                    region.len()
+                )
+            })
+            .collect();
+
+        Ok(format!(
+            "<?xml version=\"1.0\"?><!DOCTYPE memory-map PUBLIC \"+//IDN gnu.org//DTD GDB Memory \
+             Map V1.0//EN\" \"http://sourceware.org/gdb/gdb-memory-map.dtd\"><memory-map>{regions}\
+             </memory-map>"
+        ))
+    }
+
+    /// Handles the `phys-read` monitor command, printing a hex dump of guest physical memory
+    fn monitor_phys_read(
+        &self,
+        args: &[&str],
+        mut out: ConsoleOutput<'_>,
+    ) -> Result<(), GdbTargetError> {
+        let (gpa, len) = match args {
+            [gpa] => (parse_number(gpa), Some(MONITOR_DEFAULT_READ_LEN)),
+            [gpa, len] => (parse_number(gpa), parse_number(len)),
+            _ => (None, None),
+        };
+        let (Some(gpa), Some(len)) = (gpa, len) else {
+            outputln!(out, "Usage: phys-read <gpa> [<len>]");
+            return Ok(());
+        };
+
+        let mut data = vec![0; u64_to_usize(len.min(MONITOR_MAX_READ_LEN))];
+        if let Err(err) = self
+            .vmm
+            .lock()?
+            .vm
+            .guest_memory()
+            .read_slice(&mut data, GuestAddress(gpa))
+        {
+            outputln!(out, "Failed to read guest memory at {gpa:#x}: {err}");
+            return Ok(());
+        }
+
+        for (addr, line) in (gpa..).step_by(16).zip(data.chunks(16)) {
+            let bytes: Vec<_> = line.iter().map(|byte| format!("{byte:02x}")).collect();
+            outputln!(out, "{addr:#018x}: {}", bytes.join(" "));
+        }
+        Ok(())
+    }
+
+    /// Handles the `gva2gpa` monitor command, translating a guest virtual address
+    fn monitor_gva2gpa(
+        &self,
+        args: &[&str],
+        mut out: ConsoleOutput<'_>,
+    ) -> Result<(), GdbTargetError> {
+        let [gva] = args else {
+            outputln!(out, "Usage: gva2gpa <gva>");
+            return Ok(());
+        };
+        let Some(gva) = parse_number(gva) else {
+            outputln!(out, "Usage: gva2gpa <gva>");
+            return Ok(());
+        };
+
+        let vcpu_fd = &self.get_paused_vcpu()?.vcpu_fd;
+        match arch::translate_gva(vcpu_fd, gva, &self.vmm.lock()?) {
+            Ok(gpa) => outputln!(out, "{gva:#x} -> {gpa:#x}"),
+            Err(err) => outputln!(out, "Failed to translate {gva:#x}: {err}"),
+        }
+        Ok(())
+    }
+}
+
+/// Parses a number given to a monitor command, in hexadecimal if prefixed with `0x`
+fn parse_number(s: &str) -> Option<u64> {
+    match s.strip_prefix("0x") {
+        Some(hex) => u64::from_str_radix(hex, 16).ok(),
+        None => s.parse().ok(),
+    }
+}
+
+/// Copies the `length` bytes at `offset` of `data` to `buf`, as much as fits, returning the
+/// number of bytes copied
+fn copy_range_to_buf(data: &[u8], offset: u64, length: usize, buf: &mut [u8]) -> usize {
+    let start = u64_to_usize(offset).min(data.len());
+    let end = start.saturating_add(length.min(buf.len())).min(data.len());
+    buf[..end - start].copy_from_slice(&data[start..end]);
+    end - start
 }
 
 impl Target for FirecrackerTarget {
@@ -353,6 +604,16 @@ impl Target for FirecrackerTarget {
         Some(self)
     }
 
+    #[inline(always)]
+    fn support_memory_map(&mut self) -> Option<MemoryMapOps<'_, Self>> {
+        Some(self)
+    }
+
+    #[inline(always)]
+    fn support_monitor_cmd(&mut self) -> Option<MonitorCmdOps<'_, Self>> {
+        Some(self)
+    }
+
     /// We disable implicit sw breakpoints as we want to manage these internally so we can inject
     /// breakpoints back into the guest if we didn't create them
     #[inline(always)]
@@ -527,6 +788,11 @@ impl Breakpoints for FirecrackerTarget {
     fn support_sw_breakpoint(&mut self) -> Option<SwBreakpointOps<Self>> {
         Some(self)
     }
+
+    #[inline(always)]
+    fn support_hw_watchpoint(&mut self) -> Option<HwWatchpointOps<Self>> {
+        Some(self)
+    }
 }
 
 impl HwBreakpoint for FirecrackerTarget {
@@ -542,12 +808,14 @@ impl HwBreakpoint for FirecrackerTarget {
             return Ok(true);
         }
 
-        if self.hw_breakpoints.try_push(ga).is_err() {
+        if !arch::has_free_debug_reg(self.hw_breakpoints.len(), self.hw_watchpoints.len(), false)
+            || self.hw_breakpoints.try_push(ga).is_err()
+        {
             return Ok(false);
         }
 
         let state = self.get_paused_vcpu()?;
-        state.update_kvm_debug(&self.hw_breakpoints)?;
+        state.update_kvm_debug(&self.hw_breakpoints, &self.hw_watchpoints)?;
 
         Ok(true)
     }
@@ -564,7 +832,54 @@ impl HwBreakpoint for FirecrackerTarget {
         };
 
         let state = self.get_paused_vcpu()?;
-        state.update_kvm_debug(&self.hw_breakpoints)?;
+        state.update_kvm_debug(&self.hw_breakpoints, &self.hw_watchpoints)?;
+
+        Ok(true)
+    }
+}
+
+impl HwWatchpoint for FirecrackerTarget {
+    /// Adds a hardware watchpoint. The watchpoints are stored in state so we can track the reason
+    /// for an exit. Returning false lets GDB fall back to software watchpoints.
+    fn add_hw_watchpoint(
+        &mut self,
+        addr: <Self::Arch as Arch>::Usize,
+        len: <Self::Arch as Arch>::Usize,
+        kind: WatchKind,
+    ) -> TargetResult<bool, Self> {
+        let watchpoint = Watchpoint { addr, len, kind };
+        if self.hw_watchpoints.contains(&watchpoint) {
+            return Ok(true);
+        }
+
+        if !arch::watchpoint_supported(addr, len)
+            || !arch::has_free_debug_reg(self.hw_breakpoints.len(), self.hw_watchpoints.len(), true)
+            || self.hw_watchpoints.try_push(watchpoint).is_err()
+        {
+            return Ok(false);
+        }
+
+        let state = self.get_paused_vcpu()?;
+        state.update_kvm_debug(&self.hw_breakpoints, &self.hw_watchpoints)?;
+
+        Ok(true)
+    }
+
+    /// Removes a hardware watchpoint.
+    fn remove_hw_watchpoint(
+        &mut self,
+        addr: <Self::Arch as Arch>::Usize,
+        len: <Self::Arch as Arch>::Usize,
+        kind: WatchKind,
+    ) -> TargetResult<bool, Self> {
+        let watchpoint = Watchpoint { addr, len, kind };
+        match self.hw_watchpoints.iter().position(|w| *w == watchpoint) {
+            None => return Ok(false),
+            Some(pos) => self.hw_watchpoints.remove(pos),
+        };
+
+        let state = self.get_paused_vcpu()?;
+        state.update_kvm_debug(&self.hw_breakpoints, &self.hw_watchpoints)?;
 
         Ok(true)
     }
@@ -634,3 +949,145 @@ impl ThreadExtraInfo for FirecrackerTarget {
         Ok(size)
     }
 }
+
+impl MemoryMap for FirecrackerTarget {
+    /// Reports the guest RAM regions, by guest physical address
+    fn memory_map_xml(
+        &self,
+        offset: u64,
+        length: usize,
+        buf: &mut [u8],
+    ) -> TargetResult<usize, Self> {
+        let memory_map = self.memory_map()?;
+        Ok(copy_range_to_buf(
+            memory_map.as_bytes(),
+            offset,
+            length,
+            buf,
+        ))
+    }
+}
+
+impl MonitorCmd for FirecrackerTarget {
+    /// Handles the `monitor` commands sent by GDB
+    fn handle_monitor_cmd(
+        &mut self,
+        cmd: &[u8],
+        mut out: ConsoleOutput<'_>,
+    ) -> Result<(), Self::Error> {
+        let cmd = String::from_utf8_lossy(cmd);
+        let mut args = cmd.split_whitespace();
+        let command = args.next();
+        let args: Vec<_> = args.collect();
+
+        match command {
+            Some("phys-read") => self.monitor_phys_read(&args, out),
+            Some("gva2gpa") => self.monitor_gva2gpa(&args, out),
+            Some("help") | None => {
+                outputln!(out, "{MONITOR_HELP}");
+                Ok(())
+            }
+            Some(other) => {
+                outputln!(out, "Unknown command `{other}`, see `monitor help`");
+                Ok(())
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::default_vmm;
+
+    /// Tells whether a Vcpu is paused, as only paused Vcpus can save their state.
+    fn vcpu_paused(vmm: &Arc<Mutex<Vmm>>, cpu_id: usize) -> bool {
+        let vmm = vmm.lock().unwrap();
+        let handle = &vmm.vcpus_handles[cpu_id];
+        handle.send_event(VcpuEvent::SaveState).unwrap();
+        match handle.response_receiver().recv().unwrap() {
+            VcpuResponse::SavedState(_) => true,
+            VcpuResponse::NotAllowed(_) => false,
+            response => panic!("Unexpected Vcpu response: {response:?}"),
+        }
+    }
+
+    fn attach(vmm: &Arc<Mutex<Vmm>>) -> FirecrackerTarget {
+        let resources = vmm.lock().unwrap().debug_resources.take().unwrap();
+        FirecrackerTarget::attach(
+            vmm.clone(),
+            resources.vcpu_fds,
+            resources.gdb_event_receiver,
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn test_attach_detach() {
+        let (vmm, _) = default_vmm(None);
+        let vcpu_count = vmm.lock().unwrap().vcpus_handles.len();
+
+        let target = attach(&vmm);
+        assert_eq!(target.paused_vcpu, Tid::new(1));
+        assert_eq!(target.vcpu_state.len(), vcpu_count);
+        assert!(target.vcpu_state.iter().all(|state| state.paused));
+        assert!((0..vcpu_count).all(|cpu_id| vcpu_paused(&vmm, cpu_id)));
+
+        // The Vcpus resume, and another session can be attached.
+        let (vcpu_fds, _gdb_event) = target.detach().unwrap();
+        assert_eq!(vcpu_fds.len(), vcpu_count);
+        assert!((0..vcpu_count).all(|cpu_id| !vcpu_paused(&vmm, cpu_id)));
+
+        vmm.lock().unwrap().stop(FcExitCode::Ok);
+    }
+
+    #[test]
+    fn test_detach_paused_microvm() {
+        let (vmm, _) = default_vmm(None);
+        let vcpu_count = vmm.lock().unwrap().vcpus_handles.len();
+        vmm.lock().unwrap().pause_vm().unwrap();
+
+        // The Vcpus paused through the API stay paused.
+        attach(&vmm).detach().unwrap();
+        assert!((0..vcpu_count).all(|cpu_id| vcpu_paused(&vmm, cpu_id)));
+        assert_eq!(vmm.lock().unwrap().instance_info.state, VmState::Paused);
+
+        vmm.lock().unwrap().resume_vm().unwrap();
+        assert!((0..vcpu_count).all(|cpu_id| !vcpu_paused(&vmm, cpu_id)));
+
+        vmm.lock().unwrap().stop(FcExitCode::Ok);
+    }
+
+    #[test]
+    fn test_parse_number() {
+        assert_eq!(parse_number("0"), Some(0));
+        assert_eq!(parse_number("4096"), Some(4096));
+        assert_eq!(parse_number("0x1000"), Some(0x1000));
+        assert_eq!(parse_number("0xffffffffffffffff"), Some(u64::MAX));
+        assert_eq!(parse_number("0x"), None);
+        assert_eq!(parse_number("0x1g"), None);
+        assert_eq!(parse_number("1000h"), None);
+        assert_eq!(parse_number("-1"), None);
+        assert_eq!(parse_number(""), None);
+    }
+
+    #[test]
+    fn test_copy_range_to_buf() {
+        let data = b"0123456789";
+        let mut buf = [0; 4];
+
+        assert_eq!(copy_range_to_buf(data, 0, 4, &mut buf), 4);
+        assert_eq!(&buf, b"0123");
+        // The length is bounded by the buffer size.
+        assert_eq!(copy_range_to_buf(data, 2, 16, &mut buf), 4);
+        assert_eq!(&buf, b"2345");
+        // And by the end of the data.
+        assert_eq!(copy_range_to_buf(data, 8, 4, &mut buf), 2);
+        assert_eq!(&buf[..2], b"89");
+        assert_eq!(copy_range_to_buf(data, 10, 4, &mut buf), 0);
+        assert_eq!(copy_range_to_buf(data, u64::MAX, usize::MAX, &mut buf), 0);
+        // Partial reads.
+        assert_eq!(copy_range_to_buf(data, 5, 1, &mut buf), 1);
+        assert_eq!(&buf[..1], b"5");
+    }
+}
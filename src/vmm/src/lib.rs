@@ -75,6 +75,8 @@ pub mod rate_limiter;
 pub mod acpi;
 /// Handles setup and initialization a `Vmm` object.
 pub mod builder;
+/// ELF core dumps of the guest.
+pub mod coredump;
 /// Types for guest configuration.
 pub mod cpu_config;
 pub(crate) mod device_manager;
@@ -90,6 +92,8 @@ pub mod gdb;
 pub mod logger;
 /// microVM Metadata Service MMDS
 pub mod mmds;
+/// Notifications about the microVM, streamed to the connected clients.
+pub mod notifications;
 /// Save/restore utilities.
 pub mod persist;
 /// Resource store for configured microVM resources.
@@ -114,6 +118,9 @@ pub mod vstate;
 /// Module with initrd.
 pub mod initrd;
 
+/// Module with the firmware of microVMs booting from one.
+pub mod firmware;
+
 use std::collections::HashMap;
 use std::io;
 use std::os::unix::io::AsRawFd;
@@ -125,7 +132,8 @@ use device_manager::acpi::ACPIDeviceManager;
 use device_manager::resources::ResourceAllocator;
 use devices::acpi::vmgenid::VmGenIdError;
 use event_manager::{EventManager as BaseEventManager, EventOps, Events, MutEventSubscriber};
-use seccomp::BpfProgram;
+use notifications::{NOTIFIER, Notification};
+use seccomp::{BpfProgram, BpfProgramRef};
 use userfaultfd::Uffd;
 use vmm_sys_util::epoll::EventSet;
 use vmm_sys_util::eventfd::EventFd;
@@ -138,6 +146,8 @@ use crate::cpu_config::templates::CpuConfiguration;
 #[cfg(target_arch = "x86_64")]
 use crate::device_manager::legacy::PortIODeviceManager;
 use crate::device_manager::mmio::MMIODeviceManager;
+#[cfg(target_arch = "x86_64")]
+use crate::device_manager::pci::PciDeviceManager;
 use crate::devices::legacy::{IER_RDA_BIT, IER_RDA_OFFSET};
 use crate::devices::virtio::balloon::{
     BALLOON_DEV_ID, Balloon, BalloonConfig, BalloonError, BalloonStats,
@@ -150,6 +160,8 @@ use crate::persist::{MicrovmState, MicrovmStateError, VmInfo};
 use crate::rate_limiter::BucketUpdate;
 use crate::snapshot::Persist;
 use crate::vmm_config::instance_info::{InstanceInfo, VmState};
+use crate::vmm_config::pvpanic::PanicAction;
+use crate::vmm_config::watchdog::{WatchdogAction, WatchdogConfig};
 use crate::vstate::memory::{GuestMemory, GuestMemoryMmap, GuestMemoryRegion};
 use crate::vstate::vcpu::VcpuState;
 pub use crate::vstate::vcpu::{Vcpu, VcpuConfig, VcpuEvent, VcpuHandle, VcpuResponse};
@@ -190,6 +202,10 @@ pub enum FcExitCode {
     BadConfiguration = 152,
     /// Command line arguments parsing error.
     ArgParsing = 153,
+    /// The guest kernel reported a panic through the pvpanic device.
+    GuestPanic = 158,
+    /// The guest stopped refreshing the watchdog device.
+    WatchdogExpired = 159,
 }
 
 /// Timeout used in recv_timeout, when waiting for a vcpu response on
@@ -296,6 +312,21 @@ pub enum DumpCpuConfigError {
     NotAllowed(String),
 }
 
+/// Error type for [`Vmm::apply_seccomp_filters()`]
+#[derive(Debug, thiserror::Error, displaydoc::Display)]
+pub enum ApplySeccompFiltersError {
+    /// Failed to send event to vcpu thread: {0}
+    SendEvent(#[from] VcpuSendEventError),
+    /// Got unexpected response from vcpu thread.
+    UnexpectedResponse,
+    /// Failed to install seccomp filter on vcpu thread: {0}
+    Vcpu(#[from] vcpu::VcpuError),
+    /// A vcpu thread already exited with code {0:?} and can't install the seccomp filter.
+    VcpuExited(FcExitCode),
+    /// Failed to install seccomp filter on vmm thread: {0}
+    Vmm(#[from] seccomp::InstallationError),
+}
+
 /// Contains the state and associated methods required for the Firecracker VMM.
 #[derive(Debug)]
 pub struct Vmm {
@@ -313,6 +344,15 @@ pub struct Vmm {
     vcpus_handles: Vec<VcpuHandle>,
     // Used by Vcpus and devices to initiate teardown; Vmm should never write here.
     vcpus_exit_evt: EventFd,
+    // Used by the pvpanic device to report guest kernel panics.
+    pvpanic_evt: EventFd,
+    // What to do when the guest kernel panics.
+    pvpanic_action: PanicAction,
+    // Used by the watchdog device to report its expiries.
+    watchdog_evt: EventFd,
+    // The watchdog configuration, along with the microVM info saved in the snapshot taken on
+    // expiry, if the device is enabled.
+    watchdog: Option<(WatchdogConfig, VmInfo)>,
 
     // Allocator for guest resources
     resource_allocator: ResourceAllocator,
@@ -321,6 +361,13 @@ pub struct Vmm {
     #[cfg(target_arch = "x86_64")]
     pio_device_manager: PortIODeviceManager,
     acpi_device_manager: ACPIDeviceManager,
+    #[cfg(target_arch = "x86_64")]
+    pci_device_manager: Option<PciDeviceManager>,
+    // The firmware the microVM booted from, if any.
+    firmware: Option<firmware::Firmware>,
+    // What a GDB session attached at runtime needs, while no debugger is attached.
+    #[cfg(feature = "gdb")]
+    debug_resources: Option<gdb::DebugResources>,
 }
 
 impl Vmm {
@@ -415,7 +462,11 @@ impl Vmm {
             return Err(VmmError::VcpuMessage);
         }
 
+        #[cfg(target_arch = "x86_64")]
+        self.pio_device_manager.resume_watchdog();
+
         self.instance_info.state = VmState::Running;
+        NOTIFIER.notify(Notification::VmResumed);
         Ok(())
     }
 
@@ -437,7 +488,12 @@ impl Vmm {
             return Err(VmmError::VcpuMessage);
         }
 
+        // The watchdog doesn't count down while the guest can't refresh it.
+        #[cfg(target_arch = "x86_64")]
+        self.pio_device_manager.pause_watchdog();
+
         self.instance_info.state = VmState::Paused;
+        NOTIFIER.notify(Notification::VmPaused);
         Ok(())
     }
 
@@ -519,6 +575,8 @@ impl Vmm {
         let device_states = self.mmio_device_manager.save();
 
         let acpi_dev_state = self.acpi_device_manager.save();
+        #[cfg(target_arch = "x86_64")]
+        let watchdog_state = self.pio_device_manager.save_watchdog();
 
         Ok(MicrovmState {
             vm_info: vm_info.clone(),
@@ -527,6 +585,8 @@ impl Vmm {
             vcpu_states,
             device_states,
             acpi_dev_state,
+            #[cfg(target_arch = "x86_64")]
+            watchdog_state,
         })
     }
 
@@ -586,6 +646,42 @@ impl Vmm {
         Ok(cpu_configs)
     }
 
+    /// Stacks additional seccomp filters on top of the ones already installed on the vCPU
+    /// threads and on the calling (VMM) thread. Each vCPU installs its filter on the next
+    /// iteration of its loop, and this only returns once all of them confirmed it.
+    pub fn apply_seccomp_filters(
+        &mut self,
+        vcpu_filter: Arc<BpfProgram>,
+        vmm_filter: BpfProgramRef,
+    ) -> Result<(), ApplySeccompFiltersError> {
+        for handle in self.vcpus_handles.iter() {
+            handle.send_event(VcpuEvent::ApplySeccompFilter(vcpu_filter.clone()))?;
+        }
+
+        let vcpu_responses = self
+            .vcpus_handles
+            .iter()
+            .map(|handle| handle.response_receiver().recv_timeout(RECV_TIMEOUT_SEC))
+            .collect::<Result<Vec<VcpuResponse>, RecvTimeoutError>>()
+            .map_err(|_| ApplySeccompFiltersError::UnexpectedResponse)?;
+
+        for response in vcpu_responses {
+            match response {
+                VcpuResponse::SeccompFilterApplied => (),
+                VcpuResponse::Error(err) => return Err(ApplySeccompFiltersError::Vcpu(err)),
+                // An exited vcpu only answers that it exited, so waiting for its acknowledgement
+                // would be pointless.
+                VcpuResponse::Exited(code) => {
+                    return Err(ApplySeccompFiltersError::VcpuExited(code));
+                }
+                _ => return Err(ApplySeccompFiltersError::UnexpectedResponse),
+            }
+        }
+
+        seccomp::apply_filter(vmm_filter)?;
+        Ok(())
+    }
+
     /// Updates the path of the host file backing the emulated block device with id `drive_id`.
     /// We update the disk image on the device and its virtio configuration.
     pub fn update_block_device_path(
@@ -651,9 +747,8 @@ impl Vmm {
             let virtio_device = busdev
                 .lock()
                 .expect("Poisoned lock")
-                .mmio_transport_ref()
-                .expect("Unexpected device type")
-                .device();
+                .virtio_device()
+                .expect("Unexpected device type");
 
             let config = virtio_device
                 .lock()
@@ -676,9 +771,8 @@ impl Vmm {
             let virtio_device = busdev
                 .lock()
                 .expect("Poisoned lock")
-                .mmio_transport_ref()
-                .expect("Unexpected device type")
-                .device();
+                .virtio_device()
+                .expect("Unexpected device type");
 
             let latest_stats = virtio_device
                 .lock()
@@ -710,9 +804,8 @@ impl Vmm {
                 let virtio_device = busdev
                     .lock()
                     .expect("Poisoned lock")
-                    .mmio_transport_ref()
-                    .expect("Unexpected device type")
-                    .device();
+                    .virtio_device()
+                    .expect("Unexpected device type");
 
                 virtio_device
                     .lock()
@@ -740,9 +833,8 @@ impl Vmm {
                 let virtio_device = busdev
                     .lock()
                     .expect("Poisoned lock")
-                    .mmio_transport_ref()
-                    .expect("Unexpected device type")
-                    .device();
+                    .virtio_device()
+                    .expect("Unexpected device type");
 
                 virtio_device
                     .lock()
@@ -758,6 +850,58 @@ impl Vmm {
         }
     }
 
+    // Applies the action configured for the expiry of the watchdog.
+    fn handle_watchdog_expiry(&mut self) {
+        let Some((config, vm_info)) = self.watchdog.clone() else {
+            error!("Watchdog expired without a watchdog configuration");
+            return;
+        };
+
+        match config.action {
+            // Like for a reset requested by the guest, the microVM is stopped.
+            WatchdogAction::Reset => self.stop(FcExitCode::Ok),
+            WatchdogAction::Shutdown => self.stop(FcExitCode::WatchdogExpired),
+            WatchdogAction::Pause => match self.pause_vm() {
+                Ok(()) => info!("Paused the microVM after the watchdog expired"),
+                Err(err) => {
+                    error!(
+                        "Failed to pause the microVM after the watchdog expired: {}",
+                        err
+                    );
+                    self.stop(FcExitCode::WatchdogExpired);
+                }
+            },
+            WatchdogAction::SnapshotThenExit => {
+                // The configuration is validated to hold the snapshot paths for this action.
+                let params = config.snapshot_params().unwrap();
+                let result = self
+                    .pause_vm()
+                    .map_err(|err| err.to_string())
+                    .and_then(|()| {
+                        crate::persist::create_snapshot(self, &vm_info, &params)
+                            .map_err(|err| err.to_string())
+                    });
+                match result {
+                    Ok(()) => {
+                        info!(
+                            "Saved a snapshot of the microVM after the watchdog expired to {}",
+                            params.snapshot_path.display()
+                        );
+                        NOTIFIER.notify(Notification::SnapshotCreated {
+                            snapshot_type: params.snapshot_type,
+                            snapshot_path: params.snapshot_path,
+                            mem_file_path: params.mem_file_path,
+                        });
+                    }
+                    Err(err) => {
+                        error!("Failed to snapshot the microVM after the watchdog expired: {err}")
+                    }
+                }
+                self.stop(FcExitCode::WatchdogExpired);
+            }
+        }
+    }
+
     /// Signals Vmm to stop and exit.
     pub fn stop(&mut self, exit_code: FcExitCode) {
         // To avoid cycles, all teardown paths take the following route:
@@ -793,6 +937,10 @@ impl Vmm {
         // (Vmm's Drop will also check if this list is empty).
         self.vcpus_handles.clear();
 
+        NOTIFIER.notify(Notification::VmStopped {
+            exit_code: exit_code as i32,
+        });
+
         // Break the main event loop, propagating the Vmm exit-code.
         self.shutdown_exit_code = Some(exit_code);
     }
@@ -903,6 +1051,24 @@ impl MutEventSubscriber for Vmm {
                 FcExitCode::Ok
             };
             self.stop(exit_code);
+        } else if source == self.pvpanic_evt.as_raw_fd() && event_set == EventSet::IN {
+            let _ = self.pvpanic_evt.read();
+            NOTIFIER.notify(Notification::GuestPanic);
+
+            match self.pvpanic_action {
+                PanicAction::Exit => self.stop(FcExitCode::GuestPanic),
+                PanicAction::Pause => match self.pause_vm() {
+                    Ok(()) => info!("Paused the microVM after a guest kernel panic"),
+                    Err(err) => {
+                        error!("Failed to pause the microVM after a guest panic: {}", err);
+                        self.stop(FcExitCode::GuestPanic);
+                    }
+                },
+            }
+        } else if source == self.watchdog_evt.as_raw_fd() && event_set == EventSet::IN {
+            let _ = self.watchdog_evt.read();
+            NOTIFIER.notify(Notification::WatchdogExpired);
+            self.handle_watchdog_expiry();
         } else {
             error!("Spurious EventManager event for handler: Vmm");
         }
@@ -912,5 +1078,11 @@ impl MutEventSubscriber for Vmm {
         if let Err(err) = ops.add(Events::new(&self.vcpus_exit_evt, EventSet::IN)) {
             error!("Failed to register vmm exit event: {}", err);
         }
+        if let Err(err) = ops.add(Events::new(&self.pvpanic_evt, EventSet::IN)) {
+            error!("Failed to register vmm pvpanic event: {}", err);
+        }
+        if let Err(err) = ops.add(Events::new(&self.watchdog_evt, EventSet::IN)) {
+            error!("Failed to register vmm watchdog event: {}", err);
+        }
     }
 }
@@ -74,10 +74,13 @@ use super::FcLineWriter;
 use crate::devices::legacy;
 use crate::devices::virtio::balloon::metrics as balloon_metrics;
 use crate::devices::virtio::block::virtio::metrics as block_metrics;
+use crate::devices::virtio::console::metrics as console_metrics;
 use crate::devices::virtio::net::metrics as net_metrics;
+use crate::devices::virtio::pmem::metrics as pmem_metrics;
 use crate::devices::virtio::rng::metrics as entropy_metrics;
 use crate::devices::virtio::vhost_user_metrics;
 use crate::devices::virtio::vsock::metrics as vsock_metrics;
+use crate::vstate::memory_metrics;
 
 /// Static instance used for handling metrics.
 pub static METRICS: Metrics<FirecrackerMetrics, FcLineWriter> =
@@ -358,6 +361,8 @@ pub struct ApiServerMetrics {
     pub sync_response_fails: SharedIncMetric,
     /// Number of timeouts during communication with the VMM.
     pub sync_vmm_send_timeout_count: SharedIncMetric,
+    /// Number of API requests denied by the authorization policy.
+    pub denied_request_count: SharedIncMetric,
 }
 impl ApiServerMetrics {
     /// Const default construction.
@@ -367,6 +372,7 @@ impl ApiServerMetrics {
             process_startup_time_cpu_us: SharedStoreMetric::new(),
             sync_response_fails: SharedIncMetric::new(),
             sync_vmm_send_timeout_count: SharedIncMetric::new(),
+            denied_request_count: SharedIncMetric::new(),
         }
     }
 }
@@ -815,6 +821,10 @@ pub struct VmmMetrics {
     pub device_events: SharedIncMetric,
     /// Metric for signaling a panic has occurred.
     pub panic_count: SharedStoreMetric,
+    /// Number of guest kernel panics reported through the pvpanic device.
+    pub guest_panic_count: SharedIncMetric,
+    /// Number of expiries of the watchdog device.
+    pub watchdog_expiry_count: SharedIncMetric,
 }
 impl VmmMetrics {
     /// Const default construction.
@@ -822,6 +832,8 @@ impl VmmMetrics {
         Self {
             device_events: SharedIncMetric::new(),
             panic_count: SharedStoreMetric::new(),
+            guest_panic_count: SharedIncMetric::new(),
+            watchdog_expiry_count: SharedIncMetric::new(),
         }
     }
 }
@@ -867,8 +879,11 @@ create_serialize_proxy!(NetMetricsSerializeProxy, net_metrics);
 create_serialize_proxy!(VhostUserMetricsSerializeProxy, vhost_user_metrics);
 create_serialize_proxy!(BalloonMetricsSerializeProxy, balloon_metrics);
 create_serialize_proxy!(EntropyMetricsSerializeProxy, entropy_metrics);
+create_serialize_proxy!(ConsoleMetricsSerializeProxy, console_metrics);
+create_serialize_proxy!(PmemMetricsSerializeProxy, pmem_metrics);
 create_serialize_proxy!(VsockMetricsSerializeProxy, vsock_metrics);
 create_serialize_proxy!(LegacyDevMetricsSerializeProxy, legacy);
+create_serialize_proxy!(MemoryMetricsSerializeProxy, memory_metrics);
 
 /// Structure storing all metrics while enforcing serialization support on them.
 #[derive(Debug, Default, Serialize)]
@@ -893,6 +908,9 @@ pub struct FirecrackerMetrics {
     pub latencies_us: PerformanceMetrics,
     /// Logging related metrics.
     pub logger: LoggerSystemMetrics,
+    #[serde(flatten)]
+    /// Metrics related to guest memory.
+    pub memory_ser: MemoryMetricsSerializeProxy,
     /// Metrics specific to MMDS functionality.
     pub mmds: MmdsMetrics,
     #[serde(flatten)]
@@ -917,6 +935,12 @@ pub struct FirecrackerMetrics {
     /// Metrics related to virtio-rng entropy device.
     pub entropy_ser: EntropyMetricsSerializeProxy,
     #[serde(flatten)]
+    /// Metrics related to the virtio-console device.
+    pub console_ser: ConsoleMetricsSerializeProxy,
+    #[serde(flatten)]
+    /// Metrics related to the virtio-pmem devices.
+    pub pmem_ser: PmemMetricsSerializeProxy,
+    #[serde(flatten)]
     /// Vhost-user device related metrics.
     pub vhost_user_ser: VhostUserMetricsSerializeProxy,
 }
@@ -933,6 +957,7 @@ impl FirecrackerMetrics {
             legacy_dev_ser: LegacyDevMetricsSerializeProxy {},
             latencies_us: PerformanceMetrics::new(),
             logger: LoggerSystemMetrics::new(),
+            memory_ser: MemoryMetricsSerializeProxy {},
             mmds: MmdsMetrics::new(),
             net_ser: NetMetricsSerializeProxy {},
             patch_api_requests: PatchRequestsMetrics::new(),
@@ -943,6 +968,8 @@ impl FirecrackerMetrics {
             signals: SignalMetrics::new(),
             vsock_ser: VsockMetricsSerializeProxy {},
             entropy_ser: EntropyMetricsSerializeProxy {},
+            console_ser: ConsoleMetricsSerializeProxy {},
+            pmem_ser: PmemMetricsSerializeProxy {},
             vhost_user_ser: VhostUserMetricsSerializeProxy {},
         }
     }
@@ -0,0 +1,309 @@
+// Copyright 2025 Amazon.com, Inc. or its affiliates. All Rights Reserved.
+// SPDX-License-Identifier: Apache-2.0
+
+//! Stream of asynchronous notifications about the microVM, delivered as JSON lines to the
+//! clients connected to the notifications Unix domain socket.
+
+use std::io::Write;
+use std::os::unix::io::AsRawFd;
+use std::os::unix::net::{UnixListener, UnixStream};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use event_manager::{EventOps, Events, MutEventSubscriber};
+use serde::Serialize;
+use utils::time::{ClockType, get_time_us};
+use vmm_sys_util::epoll::EventSet;
+
+use crate::logger::{error, info, warn};
+use crate::vmm_config::snapshot::SnapshotType;
+
+/// Static instance used for sending notifications to the connected clients.
+pub static NOTIFIER: Notifier = Notifier::new();
+
+/// Events that are sent to the clients of the notifications socket.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum Notification {
+    /// The microVM vCPUs were paused.
+    VmPaused,
+    /// The microVM vCPUs were resumed.
+    VmResumed,
+    /// The microVM stopped with the given Firecracker exit code.
+    VmStopped {
+        /// Exit code with which the Firecracker process is going to exit.
+        exit_code: i32,
+    },
+    /// The guest kernel reported a panic through the pvpanic device.
+    GuestPanic,
+    /// The guest stopped refreshing the watchdog device.
+    WatchdogExpired,
+    /// A device failed to process an event.
+    DeviceError {
+        /// Type of the device that reported the error.
+        device: String,
+        /// Description of the error.
+        error: String,
+    },
+    /// The guest balloon driver reached the requested balloon size.
+    BalloonTargetReached {
+        /// Balloon size, in MiB.
+        target_mib: u32,
+    },
+    /// A snapshot of the microVM was created.
+    SnapshotCreated {
+        /// Type of the created snapshot.
+        snapshot_type: SnapshotType,
+        /// Path of the file holding the microVM state.
+        snapshot_path: PathBuf,
+        /// Path of the file holding the guest memory.
+        mem_file_path: PathBuf,
+    },
+}
+
+/// A notification line, as written on the notifications socket.
+#[derive(Debug, Serialize)]
+struct NotificationRecord<'a> {
+    /// Wall clock time at which the notification was sent, in microseconds.
+    timestamp_us: u64,
+    #[serde(flatten)]
+    notification: &'a Notification,
+}
+
+/// Errors associated with the notifications socket.
+#[derive(Debug, thiserror::Error, displaydoc::Display)]
+pub enum NotificationsError {
+    /// Cannot bind the notifications socket {0:?}: {1}
+    Bind(PathBuf, std::io::Error),
+    /// Cannot set the notifications socket as non-blocking: {0}
+    NonBlocking(std::io::Error),
+}
+
+/// Keeps track of the clients connected to the notifications socket.
+#[derive(Debug)]
+pub struct Notifier {
+    subscribers: Mutex<Vec<UnixStream>>,
+}
+
+impl Notifier {
+    /// Creates a notifier without any subscribers.
+    pub const fn new() -> Self {
+        Notifier {
+            subscribers: Mutex::new(Vec::new()),
+        }
+    }
+
+    /// Registers a new client which will receive the notifications sent from now on.
+    pub fn add_subscriber(&self, stream: UnixStream) {
+        self.subscribers.lock().expect("Poisoned lock").push(stream);
+    }
+
+    /// Returns the number of connected clients.
+    pub fn subscriber_count(&self) -> usize {
+        self.subscribers.lock().expect("Poisoned lock").len()
+    }
+
+    /// Sends `notification` to all connected clients.
+    ///
+    /// The client sockets are non-blocking, so that a slow reader cannot stall the thread that
+    /// emits the notification. Clients that cannot take the whole line are disconnected.
+    pub fn notify(&self, notification: Notification) {
+        let mut subscribers = self.subscribers.lock().expect("Poisoned lock");
+        if subscribers.is_empty() {
+            return;
+        }
+
+        let record = NotificationRecord {
+            timestamp_us: get_time_us(ClockType::Real),
+            notification: &notification,
+        };
+        let mut line = match serde_json::to_vec(&record) {
+            Ok(line) => line,
+            Err(err) => {
+                error!(
+                    "Failed to serialize notification {:?}: {}",
+                    notification, err
+                );
+                return;
+            }
+        };
+        line.push(b'\n');
+
+        subscribers.retain_mut(|stream| match stream.write_all(&line) {
+            Ok(()) => true,
+            Err(err) => {
+                warn!("Disconnecting notifications client: {}", err);
+                false
+            }
+        });
+    }
+}
+
+impl Default for Notifier {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Accepts the clients of the notifications socket from the event loop.
+#[derive(Debug)]
+pub struct NotificationListener {
+    listener: UnixListener,
+    notifier: &'static Notifier,
+}
+
+impl NotificationListener {
+    /// Binds the notifications socket at `path`.
+    pub fn new(path: &Path) -> Result<Self, NotificationsError> {
+        Self::with_notifier(path, &NOTIFIER)
+    }
+
+    /// Binds the notifications socket at `path`, the clients subscribing to `notifier`.
+    fn with_notifier(path: &Path, notifier: &'static Notifier) -> Result<Self, NotificationsError> {
+        let listener = UnixListener::bind(path)
+            .map_err(|err| NotificationsError::Bind(path.to_path_buf(), err))?;
+        listener
+            .set_nonblocking(true)
+            .map_err(NotificationsError::NonBlocking)?;
+        info!("Listening on notifications socket ({path:?}).");
+        Ok(NotificationListener { listener, notifier })
+    }
+
+    fn accept_pending(&self) {
+        loop {
+            match self.listener.accept() {
+                Ok((stream, _)) => {
+                    if let Err(err) = stream.set_nonblocking(true) {
+                        error!(
+                            "Failed to set notifications client as non-blocking: {}",
+                            err
+                        );
+                        continue;
+                    }
+                    self.notifier.add_subscriber(stream);
+                }
+                Err(err) if err.kind() == std::io::ErrorKind::WouldBlock => break,
+                Err(err) => {
+                    error!("Failed to accept notifications client: {}", err);
+                    break;
+                }
+            }
+        }
+    }
+}
+
+impl MutEventSubscriber for NotificationListener {
+    fn process(&mut self, event: Events, _: &mut EventOps) {
+        let source = event.fd();
+        let event_set = event.event_set();
+
+        let supported_events = EventSet::IN;
+        if !supported_events.contains(event_set) {
+            warn!(
+                "Received unknown event: {:?} from source: {:?}",
+                event_set, source
+            );
+            return;
+        }
+
+        if source == self.listener.as_raw_fd() {
+            self.accept_pending();
+        } else {
+            error!("Spurious notifications socket event!");
+        }
+    }
+
+    fn init(&mut self, ops: &mut EventOps) {
+        if let Err(err) = ops.add(Events::new(&self.listener, EventSet::IN)) {
+            error!("Failed to register notifications socket event: {}", err);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::{BufRead, BufReader};
+
+    use event_manager::{EventManager, SubscriberOps};
+    use vmm_sys_util::tempdir::TempDir;
+
+    use super::*;
+
+    #[test]
+    fn test_notification_format() {
+        let notification = Notification::VmStopped { exit_code: 1 };
+        let record = NotificationRecord {
+            timestamp_us: 42,
+            notification: &notification,
+        };
+        assert_eq!(
+            serde_json::to_string(&record).unwrap(),
+            r#"{"timestamp_us":42,"event":"vm_stopped","exit_code":1}"#
+        );
+
+        let notification = Notification::SnapshotCreated {
+            snapshot_type: SnapshotType::Diff,
+            snapshot_path: PathBuf::from("vmstate"),
+            mem_file_path: PathBuf::from("mem"),
+        };
+        let record = NotificationRecord {
+            timestamp_us: 42,
+            notification: &notification,
+        };
+        assert_eq!(
+            serde_json::to_string(&record).unwrap(),
+            r#"{"timestamp_us":42,"event":"snapshot_created","snapshot_type":"Diff","snapshot_path":"vmstate","mem_file_path":"mem"}"#
+        );
+    }
+
+    #[test]
+    fn test_notifier() {
+        let notifier = Notifier::new();
+        // Nothing to do without subscribers.
+        notifier.notify(Notification::VmPaused);
+
+        let (first, first_peer) = UnixStream::pair().unwrap();
+        let (second, second_peer) = UnixStream::pair().unwrap();
+        notifier.add_subscriber(first);
+        notifier.add_subscriber(second);
+        assert_eq!(notifier.subscriber_count(), 2);
+
+        // Disconnected clients are dropped on the next notification.
+        drop(second_peer);
+        notifier.notify(Notification::VmResumed);
+        assert_eq!(notifier.subscriber_count(), 1);
+
+        let mut line = String::new();
+        BufReader::new(first_peer).read_line(&mut line).unwrap();
+        let value: serde_json::Value = serde_json::from_str(&line).unwrap();
+        assert_eq!(value["event"], "vm_resumed");
+        assert!(value["timestamp_us"].is_u64());
+    }
+
+    #[test]
+    fn test_notification_listener() {
+        // Other tests send notifications through `NOTIFIER`, keep the clients apart.
+        static TEST_NOTIFIER: Notifier = Notifier::new();
+
+        let tmp_dir = TempDir::new().unwrap();
+        let path = tmp_dir.as_path().join("notifications.sock");
+        let listener = NotificationListener::with_notifier(&path, &TEST_NOTIFIER).unwrap();
+        assert!(matches!(
+            NotificationListener::new(&path),
+            Err(NotificationsError::Bind(_, _))
+        ));
+
+        let mut event_manager = EventManager::new().unwrap();
+        event_manager.add_subscriber(std::sync::Arc::new(Mutex::new(listener)));
+
+        let client = UnixStream::connect(&path).unwrap();
+        event_manager.run_with_timeout(100).unwrap();
+        assert_eq!(TEST_NOTIFIER.subscriber_count(), 1);
+
+        TEST_NOTIFIER.notify(Notification::GuestPanic);
+        let mut line = String::new();
+        BufReader::new(client).read_line(&mut line).unwrap();
+        let value: serde_json::Value = serde_json::from_str(&line).unwrap();
+        assert_eq!(value["event"], "guest_panic");
+    }
+}
@@ -17,15 +17,21 @@ use serde::{Deserialize, Serialize};
 use userfaultfd::{FeatureFlags, Uffd, UffdBuilder};
 use vmm_sys_util::sock_ctrl_msg::ScmSocket;
 
+use crate::arch::DeviceType;
 #[cfg(target_arch = "aarch64")]
 use crate::arch::aarch64::vcpu::get_manufacturer_id_from_host;
 use crate::builder::{self, BuildMicrovmFromSnapshotError};
 use crate::cpu_config::templates::StaticCpuTemplate;
 #[cfg(target_arch = "x86_64")]
-use crate::cpu_config::x86_64::cpuid::CpuidTrait;
-#[cfg(target_arch = "x86_64")]
 use crate::cpu_config::x86_64::cpuid::common::get_vendor_id_from_host;
+#[cfg(target_arch = "x86_64")]
+use crate::cpu_config::x86_64::cpuid::{CpuidKey, CpuidTrait};
+#[cfg(target_arch = "x86_64")]
+use crate::cpu_config::x86_64::custom_cpu_template::CpuidRegister;
 use crate::device_manager::persist::{ACPIDeviceManagerState, DevicePersistError, DeviceStates};
+#[cfg(target_arch = "x86_64")]
+use crate::devices::legacy::watchdog::WatchdogState;
+use crate::devices::virtio::TYPE_FS;
 use crate::logger::{info, warn};
 use crate::resources::VmResources;
 use crate::seccomp::BpfThreadMap;
@@ -33,9 +39,16 @@ use crate::snapshot::Snapshot;
 use crate::utils::u64_to_usize;
 use crate::vmm_config::boot_source::BootSourceConfig;
 use crate::vmm_config::instance_info::InstanceInfo;
-use crate::vmm_config::machine_config::{HugePageConfig, MachineConfigError, MachineConfigUpdate};
+use crate::vmm_config::machine_config::{
+    GuestMemoryBackend, HugePageConfig, MachineConfigError, MachineConfigUpdate, NumaConfig,
+    VirtioTransport,
+};
+use crate::vmm_config::pvpanic::PvPanicConfig;
 use crate::vmm_config::snapshot::{CreateSnapshotParams, LoadSnapshotParams, MemBackendType};
-use crate::vstate::kvm::KvmState;
+use crate::vmm_config::watchdog::WatchdogConfig;
+#[cfg(target_arch = "x86_64")]
+use crate::vstate::kvm::Kvm;
+use crate::vstate::kvm::{KvmError, KvmState};
 use crate::vstate::memory;
 use crate::vstate::memory::{GuestMemoryState, GuestRegionMmap, MemoryError};
 use crate::vstate::vcpu::{VcpuSendEventError, VcpuState};
@@ -49,12 +62,24 @@ pub struct VmInfo {
     pub mem_size_mib: u64,
     /// smt information
     pub smt: bool,
+    /// Number of guest CPU sockets, if configured.
+    pub sockets: Option<u8>,
+    /// Number of cores per guest CPU socket, if configured.
+    pub cores_per_socket: Option<u8>,
+    /// Number of threads per guest CPU core, if configured.
+    pub threads_per_core: Option<u8>,
     /// CPU template type
     pub cpu_template: StaticCpuTemplate,
     /// Boot source information.
     pub boot_source: BootSourceConfig,
     /// Huge page configuration
     pub huge_pages: HugePageConfig,
+    /// pvpanic device configuration, if the device is enabled.
+    pub pvpanic: Option<PvPanicConfig>,
+    /// Watchdog device configuration, if the device is enabled.
+    pub watchdog: Option<WatchdogConfig>,
+    /// NUMA nodes of the microVM.
+    pub numa: NumaConfig,
 }
 
 impl From<&VmResources> for VmInfo {
@@ -62,9 +87,15 @@ impl From<&VmResources> for VmInfo {
         Self {
             mem_size_mib: value.machine_config.mem_size_mib as u64,
             smt: value.machine_config.smt,
+            sockets: value.machine_config.sockets,
+            cores_per_socket: value.machine_config.cores_per_socket,
+            threads_per_core: value.machine_config.threads_per_core,
             cpu_template: StaticCpuTemplate::from(&value.machine_config.cpu_template),
             boot_source: value.boot_source.config.clone(),
             huge_pages: value.machine_config.huge_pages,
+            pvpanic: value.pvpanic.clone(),
+            watchdog: value.watchdog.clone(),
+            numa: value.machine_config.numa.clone(),
         }
     }
 }
@@ -84,6 +115,9 @@ pub struct MicrovmState {
     pub device_states: DeviceStates,
     /// ACPI devices state.
     pub acpi_dev_state: ACPIDeviceManagerState,
+    /// Watchdog device state, if the device is enabled.
+    #[cfg(target_arch = "x86_64")]
+    pub watchdog_state: Option<WatchdogState>,
 }
 
 /// This describes the mapping between Firecracker base virtual address and
@@ -145,10 +179,16 @@ pub enum CreateSnapshotError {
     SerializeMicrovmState(#[from] crate::snapshot::SnapshotError),
     /// Cannot perform {0} on the snapshot backing file: {1}
     SnapshotBackingFile(&'static str, io::Error),
+    /// Snapshots are not supported with the virtio-pci transport.
+    PciTransport,
+    /// Snapshots are not supported for microVMs booted from a firmware.
+    Firmware,
+    /// Snapshots are not supported while a vhost-user-fs device is attached.
+    VhostUserFs,
 }
 
 /// Snapshot version
-pub const SNAPSHOT_VERSION: Version = Version::new(7, 0, 0);
+pub const SNAPSHOT_VERSION: Version = Version::new(14, 0, 0);
 
 /// Creates a Microvm snapshot.
 pub fn create_snapshot(
@@ -156,6 +196,23 @@ pub fn create_snapshot(
     vm_info: &VmInfo,
     params: &CreateSnapshotParams,
 ) -> Result<(), CreateSnapshotError> {
+    #[cfg(target_arch = "x86_64")]
+    if vmm.pci_device_manager.is_some() {
+        return Err(CreateSnapshotError::PciTransport);
+    }
+    if vmm.firmware.is_some() {
+        return Err(CreateSnapshotError::Firmware);
+    }
+    // The state of the file systems lives in their vhost-user backends, which can't be saved.
+    if vmm
+        .mmio_device_manager
+        .get_device_info()
+        .keys()
+        .any(|(device_type, _)| *device_type == DeviceType::Virtio(TYPE_FS))
+    {
+        return Err(CreateSnapshotError::VhostUserFs);
+    }
+
     let microvm_state = vmm
         .save_state(vm_info)
         .map_err(CreateSnapshotError::MicrovmState)?;
@@ -276,6 +333,221 @@ pub fn validate_cpu_manufacturer_id(microvm_state: &MicrovmState) {
         }
     }
 }
+/// Feature of the snapshotted vCPUs which is not supported by the host.
+#[cfg(target_arch = "x86_64")]
+#[rustfmt::skip]
+#[derive(Debug, PartialEq, Eq, displaydoc::Display)]
+pub enum HostIncompatibility {
+    /// CPU vendor ID {snapshot} differs from the host one {host}
+    VendorId {
+        /// Vendor ID of the snapshotted vCPUs.
+        snapshot: String,
+        /// Vendor ID of the host.
+        host: String,
+    },
+    /// CPUID leaf {leaf:#x}, subleaf {subleaf:#x}, register {register:?}: bits {bits:#x} are not supported by the host
+    CpuidFeature {
+        /// CPUID leaf.
+        leaf: u32,
+        /// CPUID subleaf.
+        subleaf: u32,
+        /// CPUID register.
+        register: CpuidRegister,
+        /// Feature bits set in the snapshot, but not supported by the host.
+        bits: u32,
+    },
+    /// MSR {0:#x} is not supported by the host
+    Msr(u32),
+}
+
+/// Feature of the snapshotted vCPUs which is not supported by the host.
+#[cfg(target_arch = "aarch64")]
+#[derive(Debug, PartialEq, Eq, displaydoc::Display)]
+pub enum HostIncompatibility {
+    /// CPU manufacturer ID {snapshot:#x} differs from the host one {host:#x}
+    ManufacturerId {
+        /// Manufacturer ID of the snapshotted vCPUs.
+        snapshot: u32,
+        /// Manufacturer ID of the host.
+        host: u32,
+    },
+}
+
+/// Error type for [`host_incompatibilities`] and [`check_host_compatibility`].
+#[derive(Debug, thiserror::Error, displaydoc::Display)]
+pub enum HostCompatibilityError {
+    /// Failed to query the KVM capabilities of the host: {0}
+    Kvm(#[from] KvmError),
+    /// Failed to get the MSRs supported by KVM: {0}
+    MsrIndexList(kvm_ioctls::Error),
+    /// Failed to identify the host CPU: {0}
+    HostCpuId(String),
+    /// The snapshot is not compatible with the host: {0}
+    Incompatible(String),
+}
+
+/// CPUID registers advertising CPU features, along with the bits which are not checked against
+/// the host, as they are either set by Firecracker regardless of the host (e.g. hypervisor, TSC
+/// deadline) or updated by KVM at runtime (e.g. OSXSAVE, OSPKE).
+#[cfg(target_arch = "x86_64")]
+const CPUID_FEATURE_REGISTERS: [(u32, u32, CpuidRegister, u32); 12] = [
+    (
+        0x1,
+        0x0,
+        CpuidRegister::Ecx,
+        (1 << 24) | (1 << 27) | (1 << 31),
+    ),
+    (0x1, 0x0, CpuidRegister::Edx, 1 << 28),
+    (0x7, 0x0, CpuidRegister::Ebx, (1 << 6) | (1 << 13)),
+    (0x7, 0x0, CpuidRegister::Ecx, 1 << 4),
+    (0x7, 0x0, CpuidRegister::Edx, 0),
+    (0x7, 0x1, CpuidRegister::Eax, 0),
+    (0xd, 0x0, CpuidRegister::Eax, 0),
+    (0xd, 0x0, CpuidRegister::Edx, 0),
+    (0xd, 0x1, CpuidRegister::Eax, 0),
+    (0x8000_0001, 0x0, CpuidRegister::Ecx, 1 << 22),
+    (0x8000_0001, 0x0, CpuidRegister::Edx, 0),
+    (0x8000_0008, 0x0, CpuidRegister::Ebx, 0),
+];
+
+/// Lists the CPUID feature bits set in `snapshot_cpuid`, but not in `host_cpuid`.
+#[cfg(target_arch = "x86_64")]
+fn cpuid_incompatibilities(
+    snapshot_cpuid: &kvm_bindings::CpuId,
+    host_cpuid: &kvm_bindings::CpuId,
+) -> Vec<HostIncompatibility> {
+    let register_value = |cpuid: &kvm_bindings::CpuId, leaf, subleaf, register: &CpuidRegister| {
+        cpuid
+            .get(&CpuidKey::subleaf(leaf, subleaf))
+            .map_or(0, |entry| match register {
+                CpuidRegister::Eax => entry.result.eax,
+                CpuidRegister::Ebx => entry.result.ebx,
+                CpuidRegister::Ecx => entry.result.ecx,
+                CpuidRegister::Edx => entry.result.edx,
+            })
+    };
+
+    CPUID_FEATURE_REGISTERS
+        .iter()
+        .filter_map(|(leaf, subleaf, register, unchecked)| {
+            let snapshot = register_value(snapshot_cpuid, *leaf, *subleaf, register);
+            let host = register_value(host_cpuid, *leaf, *subleaf, register);
+            let bits = snapshot & !host & !unchecked;
+            (bits != 0).then(|| HostIncompatibility::CpuidFeature {
+                leaf: *leaf,
+                subleaf: *subleaf,
+                register: register.clone(),
+                bits,
+            })
+        })
+        .collect()
+}
+
+/// Lists the MSRs saved in `saved_msrs`, but not in `host_msrs`.
+#[cfg(target_arch = "x86_64")]
+fn msr_incompatibilities(
+    saved_msrs: &[kvm_bindings::Msrs],
+    host_msrs: &[u32],
+) -> Vec<HostIncompatibility> {
+    saved_msrs
+        .iter()
+        .flat_map(|msrs| msrs.as_slice())
+        .filter(|msr| !host_msrs.contains(&msr.index))
+        .map(|msr| HostIncompatibility::Msr(msr.index))
+        .collect()
+}
+
+/// Lists the features of the snapshotted vCPUs which are not supported by the host, comparing
+/// the saved CPUID and MSRs with the ones supported by KVM.
+#[cfg(target_arch = "x86_64")]
+pub fn host_incompatibilities(
+    microvm_state: &MicrovmState,
+) -> Result<Vec<HostIncompatibility>, HostCompatibilityError> {
+    let kvm = Kvm::new(microvm_state.kvm_state.kvm_cap_modifiers.clone())?;
+    let host_msrs = kvm
+        .fd
+        .get_msr_index_list()
+        .map_err(HostCompatibilityError::MsrIndexList)?;
+    let host_vendor_id = get_vendor_id_from_host()
+        .map_err(|err| HostCompatibilityError::HostCpuId(err.to_string()))?;
+
+    // The saved vCPUs may differ (e.g. with hybrid CPUs or a tampered snapshot), so all of them
+    // are checked, reporting each incompatibility once.
+    let mut incompatibilities = Vec::new();
+    for vcpu_state in &microvm_state.vcpu_states {
+        let mut vcpu_incompatibilities = Vec::new();
+        if let Some(snapshot_vendor_id) = vcpu_state.cpuid.vendor_id() {
+            if snapshot_vendor_id != host_vendor_id {
+                vcpu_incompatibilities.push(HostIncompatibility::VendorId {
+                    snapshot: String::from_utf8_lossy(&snapshot_vendor_id).into_owned(),
+                    host: String::from_utf8_lossy(&host_vendor_id).into_owned(),
+                });
+            }
+        }
+        vcpu_incompatibilities.extend(cpuid_incompatibilities(
+            &vcpu_state.cpuid,
+            &kvm.supported_cpuid,
+        ));
+        vcpu_incompatibilities.extend(msr_incompatibilities(
+            &vcpu_state.saved_msrs,
+            host_msrs.as_slice(),
+        ));
+
+        for incompatibility in vcpu_incompatibilities {
+            if !incompatibilities.contains(&incompatibility) {
+                incompatibilities.push(incompatibility);
+            }
+        }
+    }
+
+    Ok(incompatibilities)
+}
+
+/// Lists the features of the snapshotted vCPUs which are not supported by the host. There is no
+/// equivalent of `KVM_GET_SUPPORTED_CPUID` on aarch64, so only the CPU manufacturer is compared.
+#[cfg(target_arch = "aarch64")]
+pub fn host_incompatibilities(
+    microvm_state: &MicrovmState,
+) -> Result<Vec<HostIncompatibility>, HostCompatibilityError> {
+    let host = get_manufacturer_id_from_host()
+        .map_err(|err| HostCompatibilityError::HostCpuId(err.to_string()))?;
+
+    let mut incompatibilities = Vec::new();
+    for vcpu_state in &microvm_state.vcpu_states {
+        let incompatibility = vcpu_state
+            .regs
+            .manifacturer_id()
+            .filter(|snapshot| *snapshot != host)
+            .map(|snapshot| HostIncompatibility::ManufacturerId { snapshot, host });
+        if let Some(incompatibility) = incompatibility {
+            if !incompatibilities.contains(&incompatibility) {
+                incompatibilities.push(incompatibility);
+            }
+        }
+    }
+
+    Ok(incompatibilities)
+}
+
+/// Fails if any feature of the snapshotted vCPUs is not supported by the host, listing all of
+/// them.
+pub fn check_host_compatibility(
+    microvm_state: &MicrovmState,
+) -> Result<(), HostCompatibilityError> {
+    let incompatibilities = host_incompatibilities(microvm_state)?;
+    if incompatibilities.is_empty() {
+        return Ok(());
+    }
+
+    Err(HostCompatibilityError::Incompatible(
+        incompatibilities
+            .iter()
+            .map(ToString::to_string)
+            .collect::<Vec<_>>()
+            .join("; "),
+    ))
+}
+
 /// Error type for [`snapshot_state_sanity_check`].
 #[derive(Debug, thiserror::Error, displaydoc::Display, PartialEq, Eq)]
 pub enum SnapShotStateSanityCheckError {
@@ -309,6 +581,8 @@ pub enum RestoreFromSnapshotError {
     File(#[from] SnapshotStateFromFileError),
     /// Invalid snapshot state: {0}
     Invalid(#[from] SnapShotStateSanityCheckError),
+    /// Host compatibility check failed: {0}
+    HostCompatibility(#[from] HostCompatibilityError),
     /// Failed to load guest memory: {0}
     GuestMemory(#[from] RestoreFromSnapshotGuestMemoryError),
     /// Failed to build microVM from snapshot: {0}
@@ -361,9 +635,21 @@ pub fn restore_from_snapshot(
             vcpu_count: Some(vcpu_count),
             mem_size_mib: Some(u64_to_usize(microvm_state.vm_info.mem_size_mib)),
             smt: Some(microvm_state.vm_info.smt),
+            sockets: microvm_state.vm_info.sockets,
+            cores_per_socket: microvm_state.vm_info.cores_per_socket,
+            threads_per_core: microvm_state.vm_info.threads_per_core,
             cpu_template: Some(microvm_state.vm_info.cpu_template),
             track_dirty_pages: Some(track_dirty_pages),
             huge_pages: Some(microvm_state.vm_info.huge_pages),
+            // Snapshots of microVMs using the virtio-pci transport can't be created.
+            virtio_transport: Some(VirtioTransport::Mmio),
+            memory_backend: Some(match params.mem_backend.backend_type {
+                MemBackendType::SharedFile => GuestMemoryBackend::File {
+                    backend_path: params.mem_backend.backend_path.clone(),
+                },
+                MemBackendType::File | MemBackendType::Uffd => GuestMemoryBackend::Anonymous,
+            }),
+            numa: Some(microvm_state.vm_info.numa.clone()),
             #[cfg(feature = "gdb")]
             gdb_socket_path: None,
         })
@@ -371,6 +657,9 @@ pub fn restore_from_snapshot(
 
     // Some sanity checks before building the microvm.
     snapshot_state_sanity_check(&microvm_state)?;
+    if params.check_host_compatibility {
+        check_host_compatibility(&microvm_state)?;
+    }
 
     let mem_backend_path = &params.mem_backend.backend_path;
     let mem_state = &microvm_state.vm_state.memory;
@@ -396,6 +685,12 @@ pub fn restore_from_snapshot(
             vm_resources.machine_config.huge_pages,
         )
         .map_err(RestoreFromSnapshotGuestMemoryError::Uffd)?,
+        // The memory file can live on hugetlbfs here, as it is mapped shared.
+        MemBackendType::SharedFile => (
+            guest_memory_from_shared_file(mem_backend_path, mem_state, track_dirty_pages)
+                .map_err(RestoreFromSnapshotGuestMemoryError::File)?,
+            None,
+        ),
     };
     builder::build_microvm_from_snapshot(
         instance_info,
@@ -457,6 +752,20 @@ fn guest_memory_from_file(
     Ok(guest_mem)
 }
 
+fn guest_memory_from_shared_file(
+    mem_file_path: &Path,
+    mem_state: &GuestMemoryState,
+    track_dirty_pages: bool,
+) -> Result<Vec<GuestRegionMmap>, GuestMemoryFromFileError> {
+    let mem_file = OpenOptions::new()
+        .read(true)
+        .write(true)
+        .open(mem_file_path)?;
+    let regions = mem_state.regions().collect::<Vec<_>>();
+    let guest_mem = memory::shared_file(Arc::new(mem_file), &regions, track_dirty_pages)?;
+    Ok(guest_mem)
+}
+
 /// Error type for [`guest_memory_from_uffd`]
 #[derive(Debug, thiserror::Error, displaydoc::Display)]
 pub enum GuestMemoryFromUffdError {
@@ -685,6 +994,8 @@ mod tests {
             #[cfg(target_arch = "x86_64")]
             vm_state: vmm.vm.save_state().unwrap(),
             acpi_dev_state: vmm.acpi_device_manager.save(),
+            #[cfg(target_arch = "x86_64")]
+            watchdog_state: None,
         };
 
         let mut buf = vec![0; 10000];
@@ -759,4 +1070,90 @@ mod tests {
 
         assert_eq!(uffd_regions, deserialized);
     }
+
+    #[cfg(target_arch = "x86_64")]
+    #[test]
+    fn test_cpuid_incompatibilities() {
+        let cpuid = |entries: &[(u32, u32, u32, u32)]| {
+            let entries: Vec<_> = entries
+                .iter()
+                .map(
+                    |&(function, index, ebx, ecx)| kvm_bindings::kvm_cpuid_entry2 {
+                        function,
+                        index,
+                        ebx,
+                        ecx,
+                        ..Default::default()
+                    },
+                )
+                .collect();
+            kvm_bindings::CpuId::from_entries(&entries).unwrap()
+        };
+
+        let host_cpuid = cpuid(&[(0x1, 0x0, 0x0, 0b0011), (0x7, 0x0, 0b1111, 0x0)]);
+        // Identical CPUID.
+        assert_eq!(cpuid_incompatibilities(&host_cpuid, &host_cpuid), []);
+        // Bits set by Firecracker regardless of the host are not checked.
+        let snapshot_cpuid = cpuid(&[(0x1, 0x0, 0x0, 0b0011 | (1 << 31))]);
+        assert_eq!(cpuid_incompatibilities(&snapshot_cpuid, &host_cpuid), []);
+        // Feature bits not supported by the host, including a leaf missing on the host.
+        let snapshot_cpuid = cpuid(&[
+            (0x1, 0x0, 0x0, 0b0110),
+            (0x7, 0x0, 0b0101, 0x0),
+            (0x8000_0001, 0x0, 0x0, 0b1000),
+        ]);
+        assert_eq!(
+            cpuid_incompatibilities(&snapshot_cpuid, &host_cpuid),
+            [
+                HostIncompatibility::CpuidFeature {
+                    leaf: 0x1,
+                    subleaf: 0x0,
+                    register: CpuidRegister::Ecx,
+                    bits: 0b0100,
+                },
+                HostIncompatibility::CpuidFeature {
+                    leaf: 0x8000_0001,
+                    subleaf: 0x0,
+                    register: CpuidRegister::Ecx,
+                    bits: 0b1000,
+                },
+            ]
+        );
+        assert_eq!(
+            cpuid_incompatibilities(&snapshot_cpuid, &host_cpuid)[0].to_string(),
+            "CPUID leaf 0x1, subleaf 0x0, register Ecx: bits 0x4 are not supported by the host"
+        );
+    }
+
+    #[cfg(target_arch = "x86_64")]
+    #[test]
+    fn test_msr_incompatibilities() {
+        let saved_msrs = [
+            kvm_bindings::Msrs::from_entries(&[
+                kvm_bindings::kvm_msr_entry {
+                    index: 0x10,
+                    ..Default::default()
+                },
+                kvm_bindings::kvm_msr_entry {
+                    index: 0x3a,
+                    ..Default::default()
+                },
+            ])
+            .unwrap(),
+            kvm_bindings::Msrs::from_entries(&[kvm_bindings::kvm_msr_entry {
+                index: 0x10a,
+                ..Default::default()
+            }])
+            .unwrap(),
+        ];
+
+        assert_eq!(msr_incompatibilities(&saved_msrs, &[0x10, 0x3a, 0x10a]), []);
+        assert_eq!(
+            msr_incompatibilities(&saved_msrs, &[0x10]),
+            [
+                HostIncompatibility::Msr(0x3a),
+                HostIncompatibility::Msr(0x10a)
+            ]
+        );
+    }
 }
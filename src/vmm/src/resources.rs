@@ -2,14 +2,16 @@
 // SPDX-License-Identifier: Apache-2.0
 
 use std::convert::From;
+use std::fs::OpenOptions;
 use std::path::PathBuf;
 use std::sync::{Arc, Mutex, MutexGuard};
 
 use serde::{Deserialize, Serialize};
+use serde_json::Value;
 
 use crate::cpu_config::templates::CustomCpuTemplate;
 use crate::device_manager::persist::SharedDeviceType;
-use crate::logger::info;
+use crate::logger::{info, warn};
 use crate::mmds;
 use crate::mmds::data_store::{Mmds, MmdsVersion};
 use crate::mmds::ns::MmdsNetworkStack;
@@ -19,16 +21,21 @@ use crate::vmm_config::balloon::*;
 use crate::vmm_config::boot_source::{
     BootConfig, BootSource, BootSourceConfig, BootSourceConfigError,
 };
+use crate::vmm_config::console::*;
 use crate::vmm_config::drive::*;
 use crate::vmm_config::entropy::*;
+use crate::vmm_config::fs::*;
 use crate::vmm_config::instance_info::InstanceInfo;
 use crate::vmm_config::machine_config::{
-    HugePageConfig, MachineConfig, MachineConfigError, MachineConfigUpdate,
+    GuestMemoryBackend, MachineConfig, MachineConfigError, MachineConfigUpdate,
 };
 use crate::vmm_config::metrics::{MetricsConfig, MetricsConfigError, init_metrics};
 use crate::vmm_config::mmds::{MmdsConfig, MmdsConfigError};
 use crate::vmm_config::net::*;
+use crate::vmm_config::pmem::*;
+use crate::vmm_config::pvpanic::PvPanicConfig;
 use crate::vmm_config::vsock::*;
+use crate::vmm_config::watchdog::{WatchdogConfig, WatchdogConfigError};
 use crate::vstate::memory;
 use crate::vstate::memory::{GuestRegionMmap, MemoryError};
 
@@ -61,8 +68,28 @@ pub enum ResourcesError {
     VsockDevice(#[from] VsockConfigError),
     /// Entropy device error: {0}
     EntropyDevice(#[from] EntropyDeviceError),
+    /// Fs device error: {0}
+    FsDevice(#[from] FsDeviceError),
+    /// Console port error: {0}
+    ConsolePort(#[from] ConsoleConfigError),
+    /// Pmem device error: {0}
+    PmemDevice(#[from] PmemConfigError),
+    /// Watchdog device error: {0}
+    WatchdogDevice(#[from] WatchdogConfigError),
 }
 
+/// Errors of the sections of a microVM configuration that could not be applied, along with the
+/// names of these sections.
+#[derive(Debug, thiserror::Error)]
+#[error(
+    "Invalid microVM configuration: {}",
+    .0.iter()
+        .map(|(section, err)| format!("[{section}] {err}"))
+        .collect::<Vec<_>>()
+        .join("; ")
+)]
+pub struct VmConfigError(pub Vec<(&'static str, ResourcesError)>);
+
 #[derive(Serialize, Deserialize, PartialEq, Eq, Debug)]
 #[serde(untagged)]
 enum CustomCpuTemplateOrPath {
@@ -86,6 +113,16 @@ pub struct VmmConfig {
     network_interfaces: Vec<NetworkInterfaceConfig>,
     vsock: Option<VsockDeviceConfig>,
     entropy: Option<EntropyDeviceConfig>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    fs: Vec<FsDeviceConfig>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    console_ports: Vec<ConsolePortConfig>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pmem: Vec<PmemConfig>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pvpanic: Option<PvPanicConfig>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    watchdog: Option<WatchdogConfig>,
 }
 
 /// A data structure that encapsulates the device configurations
@@ -106,6 +143,16 @@ pub struct VmResources {
     pub net_builder: NetBuilder,
     /// The entropy device builder.
     pub entropy: EntropyDeviceBuilder,
+    /// The vhost-user-fs devices builder.
+    pub fs: FsBuilder,
+    /// The virtio-console device builder.
+    pub console: ConsoleBuilder,
+    /// The virtio-pmem devices builder.
+    pub pmem: PmemBuilder,
+    /// The pvpanic device configuration, if the device is enabled.
+    pub pvpanic: Option<PvPanicConfig>,
+    /// The watchdog device configuration, if the device is enabled.
+    pub watchdog: Option<WatchdogConfig>,
     /// The optional Mmds data store.
     // This is initialised on demand (if ever used), so that we don't allocate it unless it's
     // actually used.
@@ -117,81 +164,266 @@ pub struct VmResources {
 }
 
 impl VmResources {
-    /// Configures Vmm resources as described by the `config_json` param.
+    /// Configures Vmm resources as described by the `config_json` param. No section is applied
+    /// after the first invalid one, whose error is returned.
     pub fn from_json(
         config_json: &str,
         instance_info: &InstanceInfo,
         mmds_size_limit: usize,
         metadata_json: Option<&str>,
     ) -> Result<Self, ResourcesError> {
-        let vmm_config = serde_json::from_str::<VmmConfig>(config_json)?;
+        let mut vmm_config = serde_json::from_str::<VmmConfig>(config_json)?;
 
-        if let Some(logger_config) = vmm_config.logger {
+        if let Some(logger_config) = vmm_config.logger.take() {
             crate::logger::LOGGER.update(logger_config)?;
         }
 
-        if let Some(metrics) = vmm_config.metrics {
+        if let Some(metrics) = vmm_config.metrics.take() {
             init_metrics(metrics)?;
         }
 
+        let metadata = metadata_json.map(|data| {
+            serde_json::from_str(data).expect("MMDS error: metadata provided not valid json")
+        });
+
+        let mut resources: Self = Self {
+            mmds_size_limit,
+            ..Default::default()
+        };
+        match resources
+            .apply_vmm_config(vmm_config, &instance_info.id, metadata, true)
+            .into_iter()
+            .next()
+        {
+            Some((_, err)) => Err(err),
+            None => Ok(resources),
+        }
+    }
+
+    /// Configures Vmm resources as described by `vmm_config`, either applying all of its
+    /// sections or none of them. `metadata` holds the MMDS contents to carry over to the new
+    /// resources, if any.
+    ///
+    /// Unlike [`VmResources::from_json`], all sections are validated and the error of every
+    /// invalid section is returned. The logger and metrics sections, which cannot be rolled back,
+    /// are only applied once all other sections are valid.
+    pub fn from_vmm_config(
+        mut vmm_config: VmmConfig,
+        instance_info: &InstanceInfo,
+        mmds_size_limit: usize,
+        metadata: Option<Value>,
+    ) -> Result<Self, VmConfigError> {
+        let logger_config = vmm_config.logger.take();
+        let metrics_config = vmm_config.metrics.take();
+
         let mut resources: Self = Self {
             mmds_size_limit,
             ..Default::default()
         };
+        let mut errors = resources.apply_vmm_config(vmm_config, &instance_info.id, metadata, false);
+
+        if errors.is_empty() {
+            if let Some(metrics) = metrics_config {
+                if let Err(err) = init_metrics(metrics) {
+                    errors.push(("metrics", err.into()));
+                }
+            }
+        }
+        if errors.is_empty() {
+            if let Some(logger_config) = logger_config {
+                if let Err(err) = crate::logger::LOGGER.update(logger_config) {
+                    errors.push(("logger", err.into()));
+                }
+            }
+        }
+
+        if errors.is_empty() {
+            Ok(resources)
+        } else {
+            // Don't leave the socket of a rejected vsock device behind.
+            if let Err(err) = resources.vsock.remove() {
+                warn!(
+                    "Cannot remove the vsock socket of the rejected configuration: {}",
+                    err
+                );
+            }
+            Err(VmConfigError(errors))
+        }
+    }
+
+    /// Applies the machine and device sections of `vmm_config`, returning the errors of the
+    /// sections that failed along with their names. With `fail_fast`, no section is applied
+    /// after the first one that fails.
+    fn apply_vmm_config(
+        &mut self,
+        vmm_config: VmmConfig,
+        instance_id: &str,
+        metadata: Option<Value>,
+        fail_fast: bool,
+    ) -> Vec<(&'static str, ResourcesError)> {
+        let mut errors = Vec::new();
+        // Records the error of `section`, if any, and returns whether to stop applying sections.
+        let mut failed = |section: &'static str, result: Result<(), ResourcesError>| {
+            let Err(err) = result else {
+                return false;
+            };
+            errors.push((section, err));
+            fail_fast
+        };
+
         if let Some(machine_config) = vmm_config.machine_config {
             let machine_config = MachineConfigUpdate::from(machine_config);
-            resources.update_machine_config(&machine_config)?;
+            if failed(
+                "machine-config",
+                self.update_machine_config(&machine_config)
+                    .map_err(ResourcesError::from),
+            ) {
+                return errors;
+            }
         }
 
         if let Some(either) = vmm_config.cpu_config {
-            match either {
-                CustomCpuTemplateOrPath::Path(path) => {
-                    let cpu_config_json =
-                        std::fs::read_to_string(path).map_err(ResourcesError::File)?;
-                    let cpu_template = CustomCpuTemplate::try_from(cpu_config_json.as_str())?;
-                    resources.set_custom_cpu_template(cpu_template);
-                }
-                CustomCpuTemplateOrPath::Template(template) => {
-                    resources.set_custom_cpu_template(template)
-                }
+            let cpu_template = match either {
+                CustomCpuTemplateOrPath::Path(path) => std::fs::read_to_string(path)
+                    .map_err(ResourcesError::File)
+                    .and_then(|cpu_config_json| {
+                        CustomCpuTemplate::try_from(cpu_config_json.as_str())
+                            .map_err(ResourcesError::from)
+                    }),
+                CustomCpuTemplateOrPath::Template(template) => Ok(template),
+            };
+            if failed(
+                "cpu-config",
+                cpu_template.map(|template| self.set_custom_cpu_template(template)),
+            ) {
+                return errors;
             }
         }
 
-        resources.build_boot_source(vmm_config.boot_source)?;
+        if failed(
+            "boot-source",
+            self.build_boot_source(vmm_config.boot_source)
+                .map_err(ResourcesError::from),
+        ) {
+            return errors;
+        }
 
         for drive_config in vmm_config.drives.into_iter() {
-            resources.set_block_device(drive_config)?;
+            if failed(
+                "drives",
+                self.set_block_device(drive_config)
+                    .map_err(ResourcesError::from),
+            ) {
+                return errors;
+            }
         }
 
         for net_config in vmm_config.network_interfaces.into_iter() {
-            resources.build_net_device(net_config)?;
+            if failed(
+                "network-interfaces",
+                self.build_net_device(net_config)
+                    .map_err(ResourcesError::from),
+            ) {
+                return errors;
+            }
         }
 
         if let Some(vsock_config) = vmm_config.vsock {
-            resources.set_vsock_device(vsock_config)?;
+            if failed(
+                "vsock",
+                self.set_vsock_device(vsock_config)
+                    .map_err(ResourcesError::from),
+            ) {
+                return errors;
+            }
         }
 
         if let Some(balloon_config) = vmm_config.balloon {
-            resources.set_balloon_device(balloon_config)?;
+            if failed(
+                "balloon",
+                self.set_balloon_device(balloon_config)
+                    .map_err(ResourcesError::from),
+            ) {
+                return errors;
+            }
         }
 
-        // Init the data store from file, if present.
-        if let Some(data) = metadata_json {
-            resources.locked_mmds_or_default().put_data(
-                serde_json::from_str(data).expect("MMDS error: metadata provided not valid json"),
-            )?;
-            info!("Successfully added metadata to mmds from file");
+        // Init the data store with the provided contents, if present.
+        if let Some(data) = metadata {
+            let result = self.locked_mmds_or_default().put_data(data);
+            if result.is_ok() {
+                info!("Successfully added metadata to mmds");
+            }
+            if failed("mmds", result.map_err(ResourcesError::from)) {
+                return errors;
+            }
         }
 
         if let Some(mmds_config) = vmm_config.mmds_config {
-            resources.set_mmds_config(mmds_config, &instance_info.id)?;
+            if failed(
+                "mmds-config",
+                self.set_mmds_config(mmds_config, instance_id)
+                    .map_err(ResourcesError::from),
+            ) {
+                return errors;
+            }
         }
 
         if let Some(entropy_device_config) = vmm_config.entropy {
-            resources.build_entropy_device(entropy_device_config)?;
+            if failed(
+                "entropy",
+                self.build_entropy_device(entropy_device_config)
+                    .map_err(ResourcesError::from),
+            ) {
+                return errors;
+            }
+        }
+
+        for fs_config in vmm_config.fs.into_iter() {
+            if failed(
+                "fs",
+                self.build_fs_device(fs_config)
+                    .map_err(ResourcesError::from),
+            ) {
+                return errors;
+            }
         }
 
-        Ok(resources)
+        for port_config in vmm_config.console_ports.into_iter() {
+            if failed(
+                "console-ports",
+                self.build_console_port(port_config)
+                    .map_err(ResourcesError::from),
+            ) {
+                return errors;
+            }
+        }
+
+        for pmem_config in vmm_config.pmem.into_iter() {
+            if failed(
+                "pmem",
+                self.build_pmem_device(pmem_config)
+                    .map_err(ResourcesError::from),
+            ) {
+                return errors;
+            }
+        }
+
+        if let Some(pvpanic_config) = vmm_config.pvpanic {
+            self.set_pvpanic_device(pvpanic_config);
+        }
+
+        if let Some(watchdog_config) = vmm_config.watchdog {
+            if failed(
+                "watchdog",
+                self.set_watchdog_device(watchdog_config)
+                    .map_err(ResourcesError::from),
+            ) {
+                return errors;
+            }
+        }
+
+        errors
     }
 
     /// If not initialised, create the mmds data store with the default config.
@@ -226,7 +458,7 @@ impl VmResources {
             SharedDeviceType::Balloon(balloon) => {
                 self.balloon.set_device(balloon);
 
-                if self.machine_config.huge_pages != HugePageConfig::None {
+                if self.machine_config.huge_pages.is_hugetlbfs() {
                     return Err(ResourcesError::BalloonDevice(BalloonConfigError::HugePages));
                 }
             }
@@ -237,6 +469,12 @@ impl VmResources {
             SharedDeviceType::Entropy(entropy) => {
                 self.entropy.set_device(entropy);
             }
+            SharedDeviceType::Console(console) => {
+                self.console.set_device(console);
+            }
+            SharedDeviceType::Pmem(pmem) => {
+                self.pmem.add_device(pmem);
+            }
         }
 
         Ok(())
@@ -268,7 +506,7 @@ impl VmResources {
             return Err(MachineConfigError::IncompatibleBalloonSize);
         }
 
-        if self.balloon.get().is_some() && updated.huge_pages != HugePageConfig::None {
+        if self.balloon.get().is_some() && updated.huge_pages.is_hugetlbfs() {
             return Err(MachineConfigError::BalloonAndHugePages);
         }
         self.machine_config = updated;
@@ -325,7 +563,7 @@ impl VmResources {
             return Err(BalloonConfigError::TooManyPagesRequested);
         }
 
-        if self.machine_config.huge_pages != HugePageConfig::None {
+        if self.machine_config.huge_pages.is_hugetlbfs() {
             return Err(BalloonConfigError::HugePages);
         }
 
@@ -352,6 +590,9 @@ impl VmResources {
         &mut self,
         block_device_config: BlockDeviceConfig,
     ) -> Result<(), DriveError> {
+        if block_device_config.is_root_device && self.pmem.has_root_device() {
+            return Err(DriveError::RootBlockDeviceAlreadyAdded);
+        }
         self.block.insert(block_device_config)
     }
 
@@ -377,6 +618,47 @@ impl VmResources {
         self.entropy.insert(body)
     }
 
+    /// Builds a vhost-user-fs device to be attached when the VM starts.
+    pub fn build_fs_device(&mut self, body: FsDeviceConfig) -> Result<(), FsDeviceError> {
+        let _ = self.fs.build(body)?;
+        Ok(())
+    }
+
+    /// Adds a port to the virtio-console device attached when the VM starts.
+    pub fn build_console_port(
+        &mut self,
+        body: ConsolePortConfig,
+    ) -> Result<(), ConsoleConfigError> {
+        self.console.insert(body)
+    }
+
+    /// Sets the pvpanic device to be attached when the VM starts.
+    pub fn set_pvpanic_device(&mut self, config: PvPanicConfig) {
+        self.pvpanic = Some(config);
+    }
+
+    /// Sets the watchdog device to be attached when the VM starts.
+    pub fn set_watchdog_device(
+        &mut self,
+        config: WatchdogConfig,
+    ) -> Result<(), WatchdogConfigError> {
+        // The guest discovers the watchdog through ACPI, which is only available on x86_64.
+        if cfg!(target_arch = "aarch64") {
+            return Err(WatchdogConfigError::UnsupportedArch);
+        }
+        config.validate()?;
+        self.watchdog = Some(config);
+        Ok(())
+    }
+
+    /// Builds a virtio-pmem device to be attached when the VM starts.
+    pub fn build_pmem_device(&mut self, body: PmemConfig) -> Result<(), PmemConfigError> {
+        if body.root_device && self.block.has_root_device() {
+            return Err(PmemConfigError::RootDeviceAlreadyAdded);
+        }
+        self.pmem.build(body)
+    }
+
     /// Setter for mmds config.
     pub fn set_mmds_config(
         &mut self,
@@ -453,38 +735,61 @@ impl VmResources {
 
     /// Allocates guest memory in a configuration most appropriate for these [`VmResources`].
     ///
-    /// If vhost-user-blk devices are in use, allocates memfd-backed shared memory, otherwise
-    /// prefers anonymous memory for performance reasons.
+    /// If the user provided a memfd or a file to back guest memory, maps it shared. Otherwise, if
+    /// vhost-user devices are in use, allocates memfd-backed shared memory, otherwise prefers
+    /// anonymous memory for performance reasons.
     pub fn allocate_guest_memory(&self) -> Result<Vec<GuestRegionMmap>, MemoryError> {
         let vhost_user_device_used = self
             .block
             .devices
             .iter()
-            .any(|b| b.lock().expect("Poisoned lock").is_vhost_user());
+            .any(|b| b.lock().expect("Poisoned lock").is_vhost_user())
+            || self.fs.iter().next().is_some();
 
         // Page faults are more expensive for shared memory mapping, including  memfd.
         // For this reason, we only back guest memory with a memfd
-        // if a vhost-user-blk device is configured in the VM, otherwise we fall back to
-        // an anonymous private memory.
+        // if a vhost-user-blk or vhost-user-fs device is configured in the VM, otherwise we fall
+        // back to an anonymous private memory.
         //
         // The vhost-user-blk branch is not currently covered by integration tests in Rust,
         // because that would require running a backend process. If in the future we converge to
         // a single way of backing guest memory for vhost-user and non-vhost-user cases,
         // that would not be worth the effort.
-        let regions =
-            crate::arch::arch_memory_regions(0, mib_to_bytes(self.machine_config.mem_size_mib));
-        if vhost_user_device_used {
-            memory::memfd_backed(
+        let regions = crate::arch::arch_memory_regions_aligned(
+            mib_to_bytes(self.machine_config.mem_size_mib),
+            self.machine_config.huge_pages.page_size(),
+        );
+        match &self.machine_config.memory_backend {
+            GuestMemoryBackend::Memfd { memfd } => memory::shared_file(
+                // The memfd is checked to be present when configuring the memory backend.
+                Arc::clone(memfd.as_ref().unwrap().file()),
+                regions.as_ref(),
+                self.machine_config.track_dirty_pages,
+            ),
+            GuestMemoryBackend::File { backend_path } => {
+                let file = OpenOptions::new()
+                    .read(true)
+                    .write(true)
+                    .create(true)
+                    .truncate(false)
+                    .open(backend_path)
+                    .map_err(MemoryError::BackendFile)?;
+                memory::shared_file(
+                    Arc::new(file),
+                    regions.as_ref(),
+                    self.machine_config.track_dirty_pages,
+                )
+            }
+            GuestMemoryBackend::Anonymous if vhost_user_device_used => memory::memfd_backed(
                 regions.as_ref(),
                 self.machine_config.track_dirty_pages,
                 self.machine_config.huge_pages,
-            )
-        } else {
-            memory::anonymous(
+            ),
+            GuestMemoryBackend::Anonymous => memory::anonymous(
                 regions.into_iter(),
                 self.machine_config.track_dirty_pages,
                 self.machine_config.huge_pages,
-            )
+            ),
         }
     }
 }
@@ -503,6 +808,11 @@ impl From<&VmResources> for VmmConfig {
             network_interfaces: resources.net_builder.configs(),
             vsock: resources.vsock.config(),
             entropy: resources.entropy.config(),
+            fs: resources.fs.configs(),
+            console_ports: resources.console.configs(),
+            pmem: resources.pmem.configs(),
+            pvpanic: resources.pvpanic.clone(),
+            watchdog: resources.watchdog.clone(),
         }
     }
 }
@@ -529,12 +839,16 @@ mod tests {
     use crate::utils::net::mac::MacAddr;
     use crate::vmm_config::RateLimiterConfig;
     use crate::vmm_config::boot_source::{
-        BootConfig, BootSource, BootSourceConfig, DEFAULT_KERNEL_CMDLINE,
+        BootConfig, BootImage, BootSource, BootSourceConfig, DEFAULT_KERNEL_CMDLINE,
     };
     use crate::vmm_config::drive::{BlockBuilder, BlockDeviceConfig};
-    use crate::vmm_config::machine_config::{HugePageConfig, MachineConfig, MachineConfigError};
+    use crate::vmm_config::machine_config::{
+        HugePageConfig, MachineConfig, MachineConfigError, NumaConfig, VirtioTransport,
+    };
     use crate::vmm_config::net::{NetBuilder, NetworkInterfaceConfig};
     use crate::vmm_config::vsock::tests::default_config;
+    use crate::vmm_config::watchdog::WatchdogAction;
+    use crate::vstate::memory::GuestMemoryRegion;
 
     fn default_net_cfg() -> NetworkInterfaceConfig {
         NetworkInterfaceConfig {
@@ -595,7 +909,7 @@ mod tests {
             config: BootSourceConfig::default(),
             builder: Some(BootConfig {
                 cmdline: kernel_cmdline,
-                kernel_file: File::open(tmp_file.as_path()).unwrap(),
+                image: BootImage::Kernel(File::open(tmp_file.as_path()).unwrap()),
                 initrd_file: Some(File::open(tmp_file.as_path()).unwrap()),
             }),
         }
@@ -613,6 +927,11 @@ mod tests {
             boot_timer: false,
             mmds_size_limit: HTTP_MAX_PAYLOAD_SIZE,
             entropy: Default::default(),
+            fs: Default::default(),
+            console: Default::default(),
+            pmem: Default::default(),
+            pvpanic: None,
+            watchdog: None,
         }
     }
 
@@ -1028,6 +1347,38 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_from_json_stops_at_first_error() {
+        let mut tmp_sock_file = TempFile::new().unwrap();
+        tmp_sock_file.remove().unwrap();
+        let json = format!(
+            r#"{{
+                "boot-source": {{ "kernel_image_path": "/invalid/path" }},
+                "drives": [],
+                "vsock": {{ "guest_cid": 3, "uds_path": "{}" }}
+            }}"#,
+            tmp_sock_file.as_path().to_str().unwrap()
+        );
+
+        let error = VmResources::from_json(
+            json.as_str(),
+            &InstanceInfo::default(),
+            HTTP_MAX_PAYLOAD_SIZE,
+            None,
+        )
+        .unwrap_err();
+        assert!(
+            matches!(
+                error,
+                ResourcesError::BootSource(BootSourceConfigError::InvalidKernelPath(_))
+            ),
+            "{:?}",
+            error
+        );
+        // The vsock section, after the invalid one, was not applied.
+        assert!(!tmp_sock_file.as_path().exists());
+    }
+
     #[test]
     fn test_cpu_config_from_invalid_json() {
         // Invalid cpu config file path.
@@ -1355,12 +1706,18 @@ mod tests {
             vcpu_count: Some(32),
             mem_size_mib: Some(512),
             smt: Some(false),
+            sockets: None,
+            cores_per_socket: None,
+            threads_per_core: None,
             #[cfg(target_arch = "x86_64")]
             cpu_template: Some(StaticCpuTemplate::T2),
             #[cfg(target_arch = "aarch64")]
             cpu_template: Some(StaticCpuTemplate::V1N1),
             track_dirty_pages: Some(false),
             huge_pages: Some(HugePageConfig::None),
+            virtio_transport: Some(VirtioTransport::Mmio),
+            memory_backend: Some(GuestMemoryBackend::Anonymous),
+            numa: Some(NumaConfig::default()),
             #[cfg(feature = "gdb")]
             gdb_socket_path: None,
         };
@@ -1448,6 +1805,19 @@ mod tests {
         // trigger the "ballooning incompatible with huge pages" check.
         vm_resources.balloon = BalloonBuilder::new();
         vm_resources.update_machine_config(&aux_vm_config).unwrap();
+
+        // mem_size_mib incompatible with 1G huge pages
+        aux_vm_config.mem_size_mib = Some(1536);
+        aux_vm_config.huge_pages = Some(HugePageConfig::Hugetlbfs1G);
+        assert_eq!(
+            vm_resources
+                .update_machine_config(&aux_vm_config)
+                .unwrap_err(),
+            MachineConfigError::InvalidMemorySize
+        );
+
+        aux_vm_config.mem_size_mib = Some(2048);
+        vm_resources.update_machine_config(&aux_vm_config).unwrap();
     }
 
     #[test]
@@ -1508,6 +1878,42 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_balloon_device_with_transparent_huge_pages() {
+        let mut vm_resources = default_vm_resources();
+        vm_resources.balloon = BalloonBuilder::new();
+        vm_resources
+            .update_machine_config(&MachineConfigUpdate {
+                huge_pages: Some(HugePageConfig::Transparent),
+                ..Default::default()
+            })
+            .unwrap();
+
+        // Unlike hugetlbfs, transparent huge pages are compatible with ballooning.
+        vm_resources
+            .set_balloon_device(BalloonDeviceConfig {
+                amount_mib: 100,
+                deflate_on_oom: false,
+                stats_polling_interval_s: 0,
+            })
+            .unwrap();
+        vm_resources
+            .update_machine_config(&MachineConfigUpdate {
+                huge_pages: Some(HugePageConfig::Transparent),
+                ..Default::default()
+            })
+            .unwrap();
+        assert_eq!(
+            vm_resources
+                .update_machine_config(&MachineConfigUpdate {
+                    huge_pages: Some(HugePageConfig::Hugetlbfs2M),
+                    ..Default::default()
+                })
+                .unwrap_err(),
+            MachineConfigError::BalloonAndHugePages
+        );
+    }
+
     #[test]
     fn test_set_entropy_device() {
         let mut vm_resources = default_vm_resources();
@@ -1523,6 +1929,60 @@ mod tests {
         assert_eq!(actual_entropy_cfg, entropy_device_cfg);
     }
 
+    #[test]
+    fn test_set_watchdog_device() {
+        let mut vm_resources = default_vm_resources();
+        let config = WatchdogConfig {
+            action: WatchdogAction::Pause,
+            ..Default::default()
+        };
+
+        #[cfg(target_arch = "x86_64")]
+        {
+            vm_resources.set_watchdog_device(config.clone()).unwrap();
+            assert_eq!(vm_resources.watchdog, Some(config));
+
+            let config = WatchdogConfig {
+                action: WatchdogAction::SnapshotThenExit,
+                ..Default::default()
+            };
+            assert_eq!(
+                vm_resources.set_watchdog_device(config).unwrap_err(),
+                WatchdogConfigError::MissingSnapshotPaths
+            );
+        }
+        #[cfg(target_arch = "aarch64")]
+        {
+            assert_eq!(
+                vm_resources.set_watchdog_device(config).unwrap_err(),
+                WatchdogConfigError::UnsupportedArch
+            );
+            assert!(vm_resources.watchdog.is_none());
+        }
+    }
+
+    #[test]
+    fn test_allocate_guest_memory_from_file() {
+        let mut vm_resources = default_vm_resources();
+        let backend_file = TempFile::new().unwrap();
+        vm_resources
+            .update_machine_config(&MachineConfigUpdate {
+                mem_size_mib: Some(2),
+                memory_backend: Some(GuestMemoryBackend::File {
+                    backend_path: backend_file.as_path().to_path_buf(),
+                }),
+                ..Default::default()
+            })
+            .unwrap();
+
+        let regions = vm_resources.allocate_guest_memory().unwrap();
+        assert!(regions.iter().all(|region| region.file_offset().is_some()));
+        assert_eq!(
+            backend_file.as_file().metadata().unwrap().len(),
+            mib_to_bytes(2) as u64
+        );
+    }
+
     #[test]
     fn test_set_boot_source() {
         let tmp_file = TempFile::new().unwrap();
@@ -1531,6 +1991,8 @@ mod tests {
             kernel_image_path: String::from(tmp_file.as_path().to_str().unwrap()),
             initrd_path: Some(String::from(tmp_file.as_path().to_str().unwrap())),
             boot_args: Some(cmdline.to_string()),
+            firmware_path: None,
+            firmware_vars_path: None,
         };
 
         let mut vm_resources = default_vm_resources();
@@ -1545,10 +2007,10 @@ mod tests {
                 .as_bytes_with_nul(),
             [cmdline.as_bytes(), b"\0"].concat()
         );
-        assert_ne!(
-            boot_builder.kernel_file.metadata().unwrap().st_ino(),
-            tmp_ino
-        );
+        let BootImage::Kernel(kernel_file) = &boot_builder.image else {
+            panic!("The boot source is a kernel");
+        };
+        assert_ne!(kernel_file.metadata().unwrap().st_ino(), tmp_ino);
         assert_ne!(
             boot_builder
                 .initrd_file
@@ -1570,10 +2032,10 @@ mod tests {
                 .as_bytes_with_nul(),
             [cmdline.as_bytes(), b"\0"].concat()
         );
-        assert_eq!(
-            boot_source_builder.kernel_file.metadata().unwrap().st_ino(),
-            tmp_ino
-        );
+        let BootImage::Kernel(kernel_file) = &boot_source_builder.image else {
+            panic!("The boot source is a kernel");
+        };
+        assert_eq!(kernel_file.metadata().unwrap().st_ino(), tmp_ino);
         assert_eq!(
             boot_source_builder
                 .initrd_file
@@ -13,34 +13,47 @@ use super::resources::VmResources;
 use super::{Vmm, VmmError};
 use crate::EventManager;
 use crate::builder::StartMicrovmError;
+use crate::coredump::{CoredumpError, create_coredump};
 use crate::cpu_config::templates::{CustomCpuTemplate, GuestConfigError};
 use crate::logger::{LoggerConfig, info, warn, *};
 use crate::mmds::data_store::{self, Mmds};
+use crate::notifications::{NOTIFIER, Notification};
 use crate::persist::{CreateSnapshotError, RestoreFromSnapshotError, VmInfo};
-use crate::resources::VmmConfig;
-use crate::seccomp::BpfThreadMap;
+use crate::resources::{VmConfigError, VmmConfig};
+use crate::seccomp::{BpfProgram, BpfThreadMap};
 use crate::vmm_config::balloon::{
     BalloonConfigError, BalloonDeviceConfig, BalloonStats, BalloonUpdateConfig,
     BalloonUpdateStatsConfig,
 };
 use crate::vmm_config::boot_source::{BootSourceConfig, BootSourceConfigError};
+use crate::vmm_config::console::{ConsoleConfigError, ConsolePortConfig};
+use crate::vmm_config::coredump::CreateCoredumpParams;
 use crate::vmm_config::drive::{BlockDeviceConfig, BlockDeviceUpdateConfig, DriveError};
 use crate::vmm_config::entropy::{EntropyDeviceConfig, EntropyDeviceError};
-use crate::vmm_config::instance_info::InstanceInfo;
+use crate::vmm_config::fs::{FsDeviceConfig, FsDeviceError};
+use crate::vmm_config::instance_info::{InstanceInfo, VmState};
 use crate::vmm_config::machine_config::{MachineConfig, MachineConfigError, MachineConfigUpdate};
 use crate::vmm_config::metrics::{MetricsConfig, MetricsConfigError};
 use crate::vmm_config::mmds::{MmdsConfig, MmdsConfigError};
 use crate::vmm_config::net::{
     NetworkInterfaceConfig, NetworkInterfaceError, NetworkInterfaceUpdateConfig,
 };
+use crate::vmm_config::pmem::{PmemConfig, PmemConfigError};
+use crate::vmm_config::pvpanic::PvPanicConfig;
+use crate::vmm_config::seccomp::{SeccompFilterConfig, SeccompFilterError, load_seccomp_filters};
 use crate::vmm_config::snapshot::{CreateSnapshotParams, LoadSnapshotParams, SnapshotType};
 use crate::vmm_config::vsock::{VsockConfigError, VsockDeviceConfig};
+use crate::vmm_config::watchdog::{WatchdogConfig, WatchdogConfigError};
 use crate::vmm_config::{self, RateLimiterUpdate};
 
 /// This enum represents the public interface of the VMM. Each action contains various
 /// bits of information (ids, paths, etc.).
 #[derive(Debug, PartialEq, Eq)]
 pub enum VmmAction {
+    /// Create the GDB socket at the given path and attach the GDB connecting to it to the running
+    /// microVM. This action can only be called after the microVM has booted.
+    #[cfg(feature = "gdb")]
+    AttachDebugger(String),
     /// Configure the boot source of the microVM using as input the `ConfigureBootSource`. This
     /// action can only be called before the microVM has booted.
     ConfigureBootSource(BootSourceConfig),
@@ -50,6 +63,10 @@ pub enum VmmAction {
     /// Configure the metrics using as input the `MetricsConfig`. This action can only be called
     /// before the microVM has booted.
     ConfigureMetrics(MetricsConfig),
+    /// Write an ELF core dump of the guest using as input the `CreateCoredumpParams`. This action
+    /// can only be called after the microVM has booted, a running microVM is paused while the
+    /// core dump is written.
+    CreateCoredump(CreateCoredumpParams),
     /// Create a snapshot using as input the `CreateSnapshotParams`. This action can only be called
     /// after the microVM has booted and only when the microVM is in `Paused` state.
     CreateSnapshot(CreateSnapshotParams),
@@ -72,14 +89,27 @@ pub enum VmmAction {
     /// Add a new block device or update one that already exists using the `BlockDeviceConfig` as
     /// input. This action can only be called before the microVM has booted.
     InsertBlockDevice(BlockDeviceConfig),
+    /// Add a new port to the virtio-console device or update one that already exists using the
+    /// `ConsolePortConfig` as input. This action can only be called before the microVM has booted.
+    InsertConsolePort(ConsolePortConfig),
+    /// Add a new vhost-user-fs device or update one that already exists using the
+    /// `FsDeviceConfig` as input. This action can only be called before the microVM has booted.
+    InsertFsDevice(FsDeviceConfig),
     /// Add a new network interface config or update one that already exists using the
     /// `NetworkInterfaceConfig` as input. This action can only be called before the microVM has
     /// booted.
     InsertNetworkDevice(NetworkInterfaceConfig),
+    /// Add a new pmem device or update one that already exists using the `PmemConfig` as input.
+    /// This action can only be called before the microVM has booted.
+    InsertPmemDevice(PmemConfig),
     /// Load the microVM state using as input the `LoadSnapshotParams`. This action can only be
     /// called before the microVM has booted. If this action is successful, the loaded microVM will
     /// be in `Paused` state. Should change this state to `Resumed` for the microVM to run.
     LoadSnapshot(LoadSnapshotParams),
+    /// Load the compiled seccomp filters described by `SeccompFilterConfig` and stack them on
+    /// top of the ones installed on the vCPU, VMM and API threads. This action can only be
+    /// called after the microVM has booted.
+    LoadSeccompFilter(SeccompFilterConfig),
     /// Partial update of the MMDS contents.
     PatchMMDS(Value),
     /// Pause the guest, by pausing the microVM VCPUs.
@@ -96,6 +126,9 @@ pub enum VmmAction {
     SetBalloonDevice(BalloonDeviceConfig),
     /// Set the MMDS configuration.
     SetMmdsConfiguration(MmdsConfig),
+    /// Replace the whole microVM configuration using `VmmConfig` as input, either applying all of
+    /// its sections or none of them. This action can only be called before the microVM has booted.
+    SetVmConfig(VmmConfig),
     /// Set the vsock device or update the one that already exists using the
     /// `VsockDeviceConfig` as input. This action can only be called before the microVM has
     /// booted.
@@ -103,6 +136,12 @@ pub enum VmmAction {
     /// Set the entropy device using `EntropyDeviceConfig` as input. This action can only be called
     /// before the microVM has booted.
     SetEntropyDevice(EntropyDeviceConfig),
+    /// Set the pvpanic device using `PvPanicConfig` as input. This action can only be called
+    /// before the microVM has booted.
+    SetPvPanicDevice(PvPanicConfig),
+    /// Set the watchdog device using `WatchdogConfig` as input. This action can only be called
+    /// before the microVM has booted.
+    SetWatchdogDevice(WatchdogConfig),
     /// Launch the microVM. This action can only be called before the microVM has booted.
     StartMicroVm,
     /// Send CTRL+ALT+DEL to the microVM, using the i8042 keyboard function. If an AT-keyboard
@@ -130,14 +169,23 @@ pub enum VmmActionError {
     BalloonConfig(#[from] BalloonConfigError),
     /// Boot source error: {0}
     BootSource(#[from] BootSourceConfigError),
+    /// Create core dump error: {0}
+    CreateCoredump(#[from] CoredumpError),
     /// Create snapshot error: {0}
     CreateSnapshot(#[from] CreateSnapshotError),
+    /// Debugger error: {0}
+    #[cfg(feature = "gdb")]
+    Debugger(crate::gdb::target::GdbTargetError),
     /// Configure CPU error: {0}
     ConfigureCpu(#[from] GuestConfigError),
+    /// Console port error: {0}
+    ConsolePort(#[from] ConsoleConfigError),
     /// Drive config error: {0}
     DriveConfig(#[from] DriveError),
     /// Entropy device error: {0}
     EntropyDevice(#[from] EntropyDeviceError),
+    /// Fs device error: {0}
+    FsDevice(#[from] FsDeviceError),
     /// Internal VMM error: {0}
     InternalVmm(#[from] VmmError),
     /// Load snapshot error: {0}
@@ -158,6 +206,10 @@ pub enum VmmActionError {
     MmdsLimitExceeded(data_store::MmdsDatastoreError),
     /// Network config error: {0}
     NetworkConfig(#[from] NetworkInterfaceError),
+    /// Pmem device error: {0}
+    PmemDevice(#[from] PmemConfigError),
+    /// Seccomp filter error: {0}
+    SeccompFilter(#[from] SeccompFilterError),
     /// The requested operation is not supported: {0}
     NotSupported(String),
     /// The requested operation is not supported after starting the microVM.
@@ -166,8 +218,12 @@ pub enum VmmActionError {
     OperationNotSupportedPreBoot,
     /// Start microvm error: {0}
     StartMicrovm(#[from] StartMicrovmError),
+    /// {0}
+    VmConfig(#[from] VmConfigError),
     /// Vsock config error: {0}
     VsockConfig(#[from] VsockConfigError),
+    /// Watchdog device error: {0}
+    WatchdogDevice(#[from] WatchdogConfigError),
 }
 
 /// The enum represents the response sent by the VMM in case of success. The response is either
@@ -421,7 +477,10 @@ impl<'a> PrebootApiController<'a> {
             GetVmInstanceInfo => Ok(VmmData::InstanceInformation(self.instance_info.clone())),
             GetVmmVersion => Ok(VmmData::VmmVersion(self.instance_info.vmm_version.clone())),
             InsertBlockDevice(config) => self.insert_block_device(config),
+            InsertConsolePort(config) => self.insert_console_port(config),
+            InsertFsDevice(config) => self.insert_fs_device(config),
             InsertNetworkDevice(config) => self.insert_net_device(config),
+            InsertPmemDevice(config) => self.insert_pmem_device(config),
             LoadSnapshot(config) => self
                 .load_snapshot(&config)
                 .map_err(VmmActionError::LoadSnapshot),
@@ -433,21 +492,28 @@ impl<'a> PrebootApiController<'a> {
             SetBalloonDevice(config) => self.set_balloon_device(config),
             SetVsockDevice(config) => self.set_vsock_device(config),
             SetMmdsConfiguration(config) => self.set_mmds_config(config),
+            SetVmConfig(config) => self.set_vm_config(config),
             StartMicroVm => self.start_microvm(),
             UpdateMachineConfiguration(config) => self.update_machine_config(config),
             SetEntropyDevice(config) => self.set_entropy_device(config),
+            SetPvPanicDevice(config) => self.set_pvpanic_device(config),
+            SetWatchdogDevice(config) => self.set_watchdog_device(config),
             // Operations not allowed pre-boot.
-            CreateSnapshot(_)
+            CreateCoredump(_)
+            | CreateSnapshot(_)
             | FlushMetrics
             | Pause
             | Resume
             | GetBalloonStats
+            | LoadSeccompFilter(_)
             | UpdateBalloon(_)
             | UpdateBalloonStatistics(_)
             | UpdateBlockDevice(_)
             | UpdateNetworkInterface(_) => Err(VmmActionError::OperationNotSupportedPreBoot),
             #[cfg(target_arch = "x86_64")]
             SendCtrlAltDel => Err(VmmActionError::OperationNotSupportedPreBoot),
+            #[cfg(feature = "gdb")]
+            AttachDebugger(_) => Err(VmmActionError::OperationNotSupportedPreBoot),
         }
     }
 
@@ -478,6 +544,24 @@ impl<'a> PrebootApiController<'a> {
             .map_err(VmmActionError::NetworkConfig)
     }
 
+    fn insert_console_port(&mut self, cfg: ConsolePortConfig) -> Result<VmmData, VmmActionError> {
+        self.boot_path = true;
+        self.vm_resources.build_console_port(cfg)?;
+        Ok(VmmData::Empty)
+    }
+
+    fn insert_fs_device(&mut self, cfg: FsDeviceConfig) -> Result<VmmData, VmmActionError> {
+        self.boot_path = true;
+        self.vm_resources.build_fs_device(cfg)?;
+        Ok(VmmData::Empty)
+    }
+
+    fn insert_pmem_device(&mut self, cfg: PmemConfig) -> Result<VmmData, VmmActionError> {
+        self.boot_path = true;
+        self.vm_resources.build_pmem_device(cfg)?;
+        Ok(VmmData::Empty)
+    }
+
     fn set_balloon_device(&mut self, cfg: BalloonDeviceConfig) -> Result<VmmData, VmmActionError> {
         self.boot_path = true;
         self.vm_resources
@@ -502,6 +586,43 @@ impl<'a> PrebootApiController<'a> {
             .map_err(VmmActionError::MmdsConfig)
     }
 
+    fn set_vm_config(&mut self, cfg: VmmConfig) -> Result<VmmData, VmmActionError> {
+        self.boot_path = true;
+        // The MMDS contents are not part of the configuration, so carry them over.
+        let metadata = self
+            .vm_resources
+            .mmds
+            .as_ref()
+            .map(|mmds| mmds.lock().expect("Poisoned lock").data_store_value())
+            .filter(|data| !data.is_null());
+        // The vsock device is dropped so that the new configuration can bind its socket path
+        // again, and restored if the new configuration is rejected.
+        let vsock_config = self.vm_resources.vsock.config();
+        self.vm_resources
+            .vsock
+            .remove()
+            .map_err(VmmActionError::VsockConfig)?;
+        let mut vm_resources = match VmResources::from_vmm_config(
+            cfg,
+            &self.instance_info,
+            self.vm_resources.mmds_size_limit,
+            metadata,
+        ) {
+            Ok(vm_resources) => vm_resources,
+            Err(err) => {
+                if let Some(vsock_config) = vsock_config {
+                    if let Err(vsock_err) = self.vm_resources.set_vsock_device(vsock_config) {
+                        error!("Cannot restore the vsock device: {}", vsock_err);
+                    }
+                }
+                return Err(err.into());
+            }
+        };
+        vm_resources.boot_timer = self.vm_resources.boot_timer;
+        *self.vm_resources = vm_resources;
+        Ok(VmmData::Empty)
+    }
+
     fn update_machine_config(
         &mut self,
         cfg: MachineConfigUpdate,
@@ -535,6 +656,18 @@ impl<'a> PrebootApiController<'a> {
         Ok(VmmData::Empty)
     }
 
+    fn set_pvpanic_device(&mut self, cfg: PvPanicConfig) -> Result<VmmData, VmmActionError> {
+        self.boot_path = true;
+        self.vm_resources.set_pvpanic_device(cfg);
+        Ok(VmmData::Empty)
+    }
+
+    fn set_watchdog_device(&mut self, cfg: WatchdogConfig) -> Result<VmmData, VmmActionError> {
+        self.boot_path = true;
+        self.vm_resources.set_watchdog_device(cfg)?;
+        Ok(VmmData::Empty)
+    }
+
     // On success, this command will end the pre-boot stage and this controller
     // will be replaced by a runtime controller.
     fn start_microvm(&mut self) -> Result<VmmData, VmmActionError> {
@@ -604,6 +737,8 @@ impl<'a> PrebootApiController<'a> {
 pub struct RuntimeApiController {
     vmm: Arc<Mutex<Vmm>>,
     vm_resources: VmResources,
+    // Filter that the API thread has to stack after a `LoadSeccompFilter` action.
+    api_seccomp_filter: Option<Arc<BpfProgram>>,
 }
 
 impl MmdsRequestHandler for RuntimeApiController {
@@ -618,6 +753,9 @@ impl RuntimeApiController {
         use self::VmmAction::*;
         match request {
             // Supported operations allowed post-boot.
+            #[cfg(feature = "gdb")]
+            AttachDebugger(socket_path) => self.attach_debugger(&socket_path),
+            CreateCoredump(coredump_cfg) => self.create_coredump(&coredump_cfg),
             CreateSnapshot(snapshot_create_cfg) => self.create_snapshot(&snapshot_create_cfg),
             FlushMetrics => self.flush_metrics(),
             GetBalloonConfig => self
@@ -645,6 +783,7 @@ impl RuntimeApiController {
             GetVmmVersion => Ok(VmmData::VmmVersion(
                 self.vmm.lock().expect("Poisoned lock").version(),
             )),
+            LoadSeccompFilter(cfg) => self.load_seccomp_filter(&cfg),
             PatchMMDS(value) => self.patch_mmds(value),
             Pause => self.pause(),
             PutMMDS(value) => self.put_mmds(value),
@@ -673,13 +812,19 @@ impl RuntimeApiController {
             | ConfigureLogger(_)
             | ConfigureMetrics(_)
             | InsertBlockDevice(_)
+            | InsertConsolePort(_)
+            | InsertFsDevice(_)
             | InsertNetworkDevice(_)
+            | InsertPmemDevice(_)
             | LoadSnapshot(_)
             | PutCpuConfiguration(_)
             | SetBalloonDevice(_)
             | SetVsockDevice(_)
             | SetMmdsConfiguration(_)
             | SetEntropyDevice(_)
+            | SetPvPanicDevice(_)
+            | SetWatchdogDevice(_)
+            | SetVmConfig(_)
             | StartMicroVm
             | UpdateMachineConfiguration(_) => Err(VmmActionError::OperationNotSupportedPostBoot),
         }
@@ -687,7 +832,11 @@ impl RuntimeApiController {
 
     /// Creates a new `RuntimeApiController`.
     pub fn new(vm_resources: VmResources, vmm: Arc<Mutex<Vmm>>) -> Self {
-        Self { vmm, vm_resources }
+        Self {
+            vmm,
+            vm_resources,
+            api_seccomp_filter: None,
+        }
     }
 
     /// Pauses the microVM by pausing the vCPUs.
@@ -716,6 +865,35 @@ impl RuntimeApiController {
         Ok(VmmData::Empty)
     }
 
+    /// Stacks the seccomp filters described by `cfg` on the vCPU and VMM threads, and keeps the
+    /// filter that the API thread has to install for [`Self::take_api_seccomp_filter`].
+    fn load_seccomp_filter(
+        &mut self,
+        cfg: &SeccompFilterConfig,
+    ) -> Result<VmmData, VmmActionError> {
+        let mut filters = load_seccomp_filters(cfg)?;
+        // Safe to unwrap since `load_seccomp_filters` checks that all categories are present.
+        let vcpu_filter = filters.remove("vcpu").unwrap();
+        let vmm_filter = filters.remove("vmm").unwrap();
+        let api_filter = filters.remove("api").unwrap();
+
+        self.vmm
+            .lock()
+            .expect("Poisoned lock")
+            .apply_seccomp_filters(vcpu_filter, &vmm_filter)
+            .map_err(SeccompFilterError::Install)?;
+        info!("Installed additional seccomp filters on the vCPU and VMM threads.");
+        self.api_seccomp_filter = Some(api_filter);
+
+        Ok(VmmData::Empty)
+    }
+
+    /// Returns the additional seccomp filter that the API thread has to install, once the
+    /// `LoadSeccompFilter` action installed the ones of the vCPU and VMM threads.
+    pub fn take_api_seccomp_filter(&mut self) -> Option<Arc<BpfProgram>> {
+        self.api_seccomp_filter.take()
+    }
+
     /// Write the metrics on user demand (flush). We use the word `flush` here to highlight the fact
     /// that the metrics will be written immediately.
     /// Defer to inner Vmm. We'll move to a variant where the Vmm simply exposes functionality like
@@ -741,6 +919,47 @@ impl RuntimeApiController {
             .map_err(VmmActionError::InternalVmm)
     }
 
+    #[cfg(feature = "gdb")]
+    fn attach_debugger(&mut self, socket_path: &str) -> Result<VmmData, VmmActionError> {
+        crate::gdb::attach_debugger(self.vmm.clone(), socket_path)
+            .map(|()| VmmData::Empty)
+            .map_err(VmmActionError::Debugger)
+    }
+
+    fn create_coredump(
+        &mut self,
+        params: &CreateCoredumpParams,
+    ) -> Result<VmmData, VmmActionError> {
+        let mut locked_vmm = self.vmm.lock().expect("Poisoned lock");
+        let create_start_us = get_time_us(ClockType::Monotonic);
+
+        // The vCPU states can only be saved while they are paused.
+        let was_running = locked_vmm.instance_info.state == VmState::Running;
+        if was_running {
+            locked_vmm.pause_vm()?;
+        }
+        let result = create_coredump(&mut locked_vmm, params);
+        if was_running {
+            if let Err(err) = locked_vmm.resume_vm() {
+                // Don't hide why the core dump failed behind the resume error.
+                if let Err(coredump_err) = result {
+                    error!(
+                        "Failed to resume the microVM after the core dump failed: {}",
+                        err
+                    );
+                    return Err(coredump_err.into());
+                }
+                return Err(err.into());
+            }
+        }
+        result?;
+
+        let elapsed_time_us = get_time_us(ClockType::Monotonic).saturating_sub(create_start_us);
+        info!("'create coredump' VMM action took {} us.", elapsed_time_us);
+
+        Ok(VmmData::Empty)
+    }
+
     fn create_snapshot(
         &mut self,
         create_params: &CreateSnapshotParams,
@@ -784,6 +1003,11 @@ impl RuntimeApiController {
                 );
             }
         }
+        NOTIFIER.notify(Notification::SnapshotCreated {
+            snapshot_type: create_params.snapshot_type,
+            snapshot_path: create_params.snapshot_path.clone(),
+            mem_file_path: create_params.mem_file_path.clone(),
+        });
         Ok(VmmData::Empty)
     }
 
@@ -844,6 +1068,8 @@ impl RuntimeApiController {
 mod tests {
     use std::path::PathBuf;
 
+    use vmm_sys_util::tempfile::TempFile;
+
     use super::*;
     use crate::HTTP_MAX_PAYLOAD_SIZE;
     use crate::builder::tests::default_vmm;
@@ -1103,6 +1329,106 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_preboot_set_vm_config() {
+        let mmds = Arc::new(Mutex::new(Mmds::default()));
+        mmds.lock()
+            .unwrap()
+            .put_data(Value::String("string".to_string()))
+            .unwrap();
+        let mut vm_resources = VmResources {
+            mmds: Some(mmds),
+            mmds_size_limit: HTTP_MAX_PAYLOAD_SIZE,
+            boot_timer: true,
+            ..Default::default()
+        };
+        let mut evmgr = EventManager::new().unwrap();
+        let seccomp_filters = BpfThreadMap::new();
+        let kernel_image = TempFile::new().unwrap();
+        let mut vsock_sock = TempFile::new().unwrap();
+        vsock_sock.remove().unwrap();
+        let vsock_path = vsock_sock.as_path().to_str().unwrap().to_string();
+        let mut new_vsock_sock = TempFile::new().unwrap();
+        new_vsock_sock.remove().unwrap();
+        vm_resources
+            .set_vsock_device(VsockDeviceConfig {
+                vsock_id: None,
+                guest_cid: 3,
+                uds_path: vsock_path.clone(),
+            })
+            .unwrap();
+
+        {
+            let mut preboot = default_preboot(&mut vm_resources, &mut evmgr, &seccomp_filters);
+
+            // Every invalid section is reported and none of them is applied.
+            let config: VmmConfig = serde_json::from_str(
+                r#"{
+                    "boot-source": { "kernel_image_path": "/invalid/path" },
+                    "drives": [],
+                    "machine-config": { "vcpu_count": 0, "mem_size_mib": 128 }
+                }"#,
+            )
+            .unwrap();
+            match preboot.handle_preboot_request(VmmAction::SetVmConfig(config)) {
+                Err(VmmActionError::VmConfig(VmConfigError(errors))) => {
+                    let sections: Vec<_> = errors.iter().map(|(section, _)| *section).collect();
+                    assert_eq!(sections, ["machine-config", "boot-source"]);
+                }
+                res => panic!("Unexpected result: {:?}", res),
+            }
+
+            // The socket of a rejected vsock device is removed, and the current one is kept.
+            let config: VmmConfig = serde_json::from_str(&format!(
+                r#"{{
+                    "boot-source": {{ "kernel_image_path": "/invalid/path" }},
+                    "drives": [],
+                    "vsock": {{ "guest_cid": 3, "uds_path": "{}" }}
+                }}"#,
+                new_vsock_sock.as_path().to_str().unwrap()
+            ))
+            .unwrap();
+            preboot
+                .handle_preboot_request(VmmAction::SetVmConfig(config))
+                .unwrap_err();
+            assert!(!new_vsock_sock.as_path().exists());
+            assert!(vsock_sock.as_path().exists());
+
+            // A configuration can keep the socket path of the current vsock device.
+            let config: VmmConfig = serde_json::from_str(&format!(
+                r#"{{
+                    "boot-source": {{ "kernel_image_path": "{}" }},
+                    "drives": [],
+                    "vsock": {{ "guest_cid": 4, "uds_path": "{}" }}
+                }}"#,
+                kernel_image.as_path().to_str().unwrap(),
+                vsock_path
+            ))
+            .unwrap();
+            assert_eq!(
+                preboot
+                    .handle_preboot_request(VmmAction::SetVmConfig(config))
+                    .unwrap(),
+                VmmData::Empty
+            );
+        }
+
+        assert_eq!(
+            vm_resources.boot_source.config.kernel_image_path,
+            kernel_image.as_path().to_str().unwrap()
+        );
+        let vsock_config = vm_resources.vsock.config().unwrap();
+        assert_eq!(vsock_config.guest_cid, 4);
+        assert_eq!(vsock_config.uds_path, vsock_path);
+        assert!(vsock_sock.as_path().exists());
+        vm_resources.vsock.remove().unwrap();
+        assert!(vm_resources.boot_timer);
+        assert_eq!(
+            vm_resources.locked_mmds_or_default().data_store_value(),
+            Value::String("string".to_string())
+        );
+    }
+
     #[test]
     fn test_preboot_disallowed() {
         fn check_unsupported(res: Result<VmmData, VmmActionError>) {
@@ -1117,6 +1443,11 @@ mod tests {
         check_unsupported(preboot_request(VmmAction::Pause));
         check_unsupported(preboot_request(VmmAction::Resume));
         check_unsupported(preboot_request(VmmAction::GetBalloonStats));
+        check_unsupported(preboot_request(VmmAction::LoadSeccompFilter(
+            SeccompFilterConfig {
+                filter_path: PathBuf::new(),
+            },
+        )));
         check_unsupported(preboot_request(VmmAction::UpdateBalloon(
             BalloonUpdateConfig { amount_mib: 0 },
         )));
@@ -1135,6 +1466,11 @@ mod tests {
                 tx_rate_limiter: None,
             },
         )));
+        check_unsupported(preboot_request(VmmAction::CreateCoredump(
+            CreateCoredumpParams {
+                dump_path: PathBuf::new(),
+            },
+        )));
         check_unsupported(preboot_request(VmmAction::CreateSnapshot(
             CreateSnapshotParams {
                 snapshot_type: SnapshotType::Full,
@@ -1144,6 +1480,8 @@ mod tests {
         )));
         #[cfg(target_arch = "x86_64")]
         check_unsupported(preboot_request(VmmAction::SendCtrlAltDel));
+        #[cfg(feature = "gdb")]
+        check_unsupported(preboot_request(VmmAction::AttachDebugger(String::new())));
     }
 
     fn runtime_request(request: VmmAction) -> Result<VmmData, VmmActionError> {
@@ -1249,6 +1587,9 @@ mod tests {
         check_unsupported(runtime_request(VmmAction::UpdateMachineConfiguration(
             MachineConfigUpdate::from(MachineConfig::default()),
         )));
+        check_unsupported(runtime_request(
+            VmmAction::SetVmConfig(VmmConfig::default()),
+        ));
         check_unsupported(runtime_request(VmmAction::LoadSnapshot(
             LoadSnapshotParams {
                 snapshot_path: PathBuf::new(),
@@ -1259,10 +1600,34 @@ mod tests {
                 enable_diff_snapshots: false,
                 resume_vm: false,
                 network_overrides: vec![],
+                check_host_compatibility: false,
             },
         )));
         check_unsupported(runtime_request(VmmAction::SetEntropyDevice(
             EntropyDeviceConfig::default(),
         )));
+        check_unsupported(runtime_request(VmmAction::SetPvPanicDevice(
+            PvPanicConfig::default(),
+        )));
+        check_unsupported(runtime_request(VmmAction::SetWatchdogDevice(
+            WatchdogConfig::default(),
+        )));
+        check_unsupported(runtime_request(VmmAction::InsertFsDevice(FsDeviceConfig {
+            fs_id: String::new(),
+            socket: String::new(),
+            tag: String::new(),
+            num_queues: 1,
+        })));
+        check_unsupported(runtime_request(VmmAction::InsertConsolePort(
+            ConsolePortConfig {
+                port_id: String::new(),
+                name: String::new(),
+                socket_path: None,
+                file_path: None,
+            },
+        )));
+        check_unsupported(runtime_request(VmmAction::InsertPmemDevice(
+            PmemConfig::default(),
+        )));
     }
 }
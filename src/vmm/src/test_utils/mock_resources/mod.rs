@@ -45,6 +45,8 @@ impl MockBootSourceConfig {
             kernel_image_path: kernel_image_path(None),
             initrd_path: None,
             boot_args: None,
+            firmware_path: None,
+            firmware_vars_path: None,
         })
     }
 
@@ -1,7 +1,7 @@
 // Copyright 2018 Amazon.com, Inc. or its affiliates. All Rights Reserved.
 // SPDX-License-Identifier: Apache-2.0
 
-use std::fs::File;
+use std::fs::{File, OpenOptions};
 use std::io;
 
 use serde::{Deserialize, Serialize};
@@ -23,13 +23,18 @@ pub const DEFAULT_KERNEL_CMDLINE: &str =
 #[derive(Clone, Debug, Default, Deserialize, Eq, PartialEq, Serialize)]
 #[serde(deny_unknown_fields)]
 pub struct BootSourceConfig {
-    /// Path of the kernel image.
+    /// Path of the kernel image. Empty when booting from a firmware.
+    #[serde(default)]
     pub kernel_image_path: String,
     /// Path of the initrd, if there is one.
     pub initrd_path: Option<String>,
     /// The boot arguments to pass to the kernel. If this field is uninitialized,
     /// DEFAULT_KERNEL_CMDLINE is used.
     pub boot_args: Option<String>,
+    /// Path of the firmware image to boot from, instead of a kernel.
+    pub firmware_path: Option<String>,
+    /// Path of the file backing the variable store of the firmware, if there is one.
+    pub firmware_vars_path: Option<String>,
 }
 
 /// Errors associated with actions on `BootSourceConfig`.
@@ -41,6 +46,14 @@ pub enum BootSourceConfigError {
     InvalidInitrdPath(io::Error),
     /// The kernel command line is invalid: {0}
     InvalidKernelCommandLine(String),
+    /// The firmware file cannot be opened: {0}
+    InvalidFirmwarePath(io::Error),
+    /// The firmware variable store file cannot be opened: {0}
+    InvalidFirmwareVarsPath(io::Error),
+    /// A firmware cannot be booted along with a kernel, an initrd or boot arguments.
+    FirmwareWithKernel,
+    /// A firmware variable store requires a firmware.
+    FirmwareVarsWithoutFirmware,
 }
 
 /// Holds the kernel specification (both configuration as well as runtime details).
@@ -53,13 +66,28 @@ pub struct BootSource {
     pub builder: Option<BootConfig>,
 }
 
+/// The image the microVM boots from.
+#[derive(Debug)]
+pub enum BootImage {
+    /// The descriptor to the kernel file.
+    Kernel(File),
+    /// The descriptors to the firmware file and to the file backing its variable store, if there
+    /// is one.
+    Firmware {
+        /// The descriptor to the firmware file.
+        code: File,
+        /// The descriptor to the variable store file, if there is one.
+        vars: Option<File>,
+    },
+}
+
 /// Holds the kernel builder (created and validates based on BootSourceConfig).
 #[derive(Debug)]
 pub struct BootConfig {
     /// The commandline validated against correctness.
     pub cmdline: linux_loader::cmdline::Cmdline,
-    /// The descriptor to the kernel file.
-    pub kernel_file: File,
+    /// The image to boot from.
+    pub image: BootImage,
     /// The descriptor to the initrd file, if there is one.
     pub initrd_file: Option<File>,
 }
@@ -68,11 +96,39 @@ impl BootConfig {
     /// Creates the BootConfig based on a given configuration.
     pub fn new(cfg: &BootSourceConfig) -> Result<Self, BootSourceConfigError> {
         use self::BootSourceConfigError::{
-            InvalidInitrdPath, InvalidKernelCommandLine, InvalidKernelPath,
+            FirmwareVarsWithoutFirmware, FirmwareWithKernel, InvalidFirmwarePath,
+            InvalidFirmwareVarsPath, InvalidInitrdPath, InvalidKernelCommandLine,
+            InvalidKernelPath,
         };
 
         // Validate boot source config.
-        let kernel_file = File::open(&cfg.kernel_image_path).map_err(InvalidKernelPath)?;
+        let image = match &cfg.firmware_path {
+            // The firmware loads the operating system on its own, there is nothing to pass to it.
+            Some(_)
+                if !cfg.kernel_image_path.is_empty()
+                    || cfg.initrd_path.is_some()
+                    || cfg.boot_args.is_some() =>
+            {
+                return Err(FirmwareWithKernel);
+            }
+            Some(path) => BootImage::Firmware {
+                code: File::open(path).map_err(InvalidFirmwarePath)?,
+                vars: match &cfg.firmware_vars_path {
+                    Some(path) => Some(
+                        OpenOptions::new()
+                            .read(true)
+                            .write(true)
+                            .open(path)
+                            .map_err(InvalidFirmwareVarsPath)?,
+                    ),
+                    None => None,
+                },
+            },
+            None if cfg.firmware_vars_path.is_some() => return Err(FirmwareVarsWithoutFirmware),
+            None => {
+                BootImage::Kernel(File::open(&cfg.kernel_image_path).map_err(InvalidKernelPath)?)
+            }
+        };
         let initrd_file: Option<File> = match &cfg.initrd_path {
             Some(path) => Some(File::open(path).map_err(InvalidInitrdPath)?),
             None => None,
@@ -88,7 +144,7 @@ impl BootConfig {
 
         Ok(BootConfig {
             cmdline,
-            kernel_file,
+            image,
             initrd_file,
         })
     }
@@ -110,9 +166,12 @@ pub(crate) mod tests {
             boot_args: None,
             initrd_path: None,
             kernel_image_path: kernel_path,
+            firmware_path: None,
+            firmware_vars_path: None,
         };
 
         let boot_cfg = BootConfig::new(&boot_src_cfg).unwrap();
+        assert!(matches!(boot_cfg.image, BootImage::Kernel(_)));
         assert!(boot_cfg.initrd_file.is_none());
         assert_eq!(
             boot_cfg.cmdline.as_cstring().unwrap().as_bytes_with_nul(),
@@ -120,12 +179,78 @@ pub(crate) mod tests {
         );
     }
 
+    #[test]
+    fn test_firmware_boot_config() {
+        let firmware_file = TempFile::new().unwrap();
+        let firmware_path = firmware_file.as_path().to_str().unwrap().to_string();
+        let vars_file = TempFile::new().unwrap();
+        let vars_path = vars_file.as_path().to_str().unwrap().to_string();
+
+        let mut boot_src_cfg = BootSourceConfig {
+            firmware_path: Some(firmware_path.clone()),
+            firmware_vars_path: Some(vars_path.clone()),
+            ..Default::default()
+        };
+        let boot_cfg = BootConfig::new(&boot_src_cfg).unwrap();
+        assert!(matches!(
+            boot_cfg.image,
+            BootImage::Firmware { vars: Some(_), .. }
+        ));
+
+        boot_src_cfg.firmware_vars_path = None;
+        let boot_cfg = BootConfig::new(&boot_src_cfg).unwrap();
+        assert!(matches!(
+            boot_cfg.image,
+            BootImage::Firmware { vars: None, .. }
+        ));
+
+        // Nothing can be passed to the firmware.
+        for cfg in [
+            BootSourceConfig {
+                kernel_image_path: firmware_path.clone(),
+                ..boot_src_cfg.clone()
+            },
+            BootSourceConfig {
+                initrd_path: Some(firmware_path.clone()),
+                ..boot_src_cfg.clone()
+            },
+            BootSourceConfig {
+                boot_args: Some(DEFAULT_KERNEL_CMDLINE.to_string()),
+                ..boot_src_cfg.clone()
+            },
+        ] {
+            assert!(matches!(
+                BootConfig::new(&cfg).unwrap_err(),
+                BootSourceConfigError::FirmwareWithKernel
+            ));
+        }
+
+        boot_src_cfg.firmware_path = Some("/invalid/path".to_string());
+        assert!(matches!(
+            BootConfig::new(&boot_src_cfg).unwrap_err(),
+            BootSourceConfigError::InvalidFirmwarePath(_)
+        ));
+
+        // A variable store is only used by a firmware.
+        let boot_src_cfg = BootSourceConfig {
+            kernel_image_path: firmware_path,
+            firmware_vars_path: Some(vars_path),
+            ..Default::default()
+        };
+        assert!(matches!(
+            BootConfig::new(&boot_src_cfg).unwrap_err(),
+            BootSourceConfigError::FirmwareVarsWithoutFirmware
+        ));
+    }
+
     #[test]
     fn test_serde() {
         let boot_src_cfg = BootSourceConfig {
             boot_args: Some(DEFAULT_KERNEL_CMDLINE.to_string()),
             initrd_path: Some("/tmp/initrd".to_string()),
             kernel_image_path: "./vmlinux.bin".to_string(),
+            firmware_path: None,
+            firmware_vars_path: None,
         };
 
         let mut snapshot_data = vec![0u8; 1000];
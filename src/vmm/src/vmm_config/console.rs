@@ -0,0 +1,172 @@
+// Copyright 2025 Amazon.com, Inc. or its affiliates. All Rights Reserved.
+// SPDX-License-Identifier: Apache-2.0
+
+use std::sync::{Arc, Mutex};
+
+use serde::{Deserialize, Serialize};
+
+use crate::devices::virtio::console::{VirtioConsole, VirtioConsoleError};
+
+/// This struct represents the strongly typed equivalent of the json body from virtio-console
+/// port related requests.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct ConsolePortConfig {
+    /// ID of the port.
+    pub port_id: String,
+    /// Name of the port, exposed in the guest as `/dev/virtio-ports/<name>`.
+    pub name: String,
+    /// Path of the Unix socket on which Firecracker accepts a connection from a host peer.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub socket_path: Option<String>,
+    /// Path of the file to which the guest output is appended.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub file_path: Option<String>,
+}
+
+/// Errors associated with the operations allowed on virtio-console ports.
+#[derive(Debug, thiserror::Error, displaydoc::Display)]
+pub enum ConsoleConfigError {
+    /// Could not create the virtio-console device: {0}
+    CreateConsole(#[from] VirtioConsoleError),
+    /// Exactly one of `socket_path` and `file_path` must be specified.
+    InvalidBackend,
+    /// The port name is invalid: {0}
+    InvalidName(String),
+    /// The port name is already in use: {0}
+    NameInUse(String),
+}
+
+/// Builder for the virtio-console device, which holds all the configured ports.
+#[derive(Debug, Default)]
+pub struct ConsoleBuilder(Option<Arc<Mutex<VirtioConsole>>>);
+
+impl ConsoleBuilder {
+    /// Creates a builder with no port configured.
+    pub fn new() -> Self {
+        Self(None)
+    }
+
+    /// Adds a port to the virtio-console device, creating the device when this is the first port.
+    /// A port with the same ID is replaced.
+    pub fn insert(&mut self, config: ConsolePortConfig) -> Result<(), ConsoleConfigError> {
+        if config.socket_path.is_some() == config.file_path.is_some() {
+            return Err(ConsoleConfigError::InvalidBackend);
+        }
+        // The guest uses the name as a path component under `/dev/virtio-ports`.
+        if config.name.is_empty() || config.name.contains('/') {
+            return Err(ConsoleConfigError::InvalidName(config.name));
+        }
+        if self
+            .configs()
+            .iter()
+            .any(|port| port.name == config.name && port.port_id != config.port_id)
+        {
+            return Err(ConsoleConfigError::NameInUse(config.name));
+        }
+
+        let console = match &self.0 {
+            Some(console) => console.clone(),
+            None => {
+                let console = Arc::new(Mutex::new(VirtioConsole::new()?));
+                self.0 = Some(console.clone());
+                console
+            }
+        };
+        console.lock().expect("Poisoned lock").add_port(config)?;
+        Ok(())
+    }
+
+    /// Returns a reference to the virtio-console device, if any port was configured.
+    pub fn get(&self) -> Option<&Arc<Mutex<VirtioConsole>>> {
+        self.0.as_ref()
+    }
+
+    /// Returns a vec with the structures used to configure the ports.
+    pub fn configs(&self) -> Vec<ConsolePortConfig> {
+        self.0
+            .as_ref()
+            .map(|console| console.lock().expect("Poisoned lock").port_configs())
+            .unwrap_or_default()
+    }
+
+    /// Sets the virtio-console device from an already created object.
+    pub fn set_device(&mut self, device: Arc<Mutex<VirtioConsole>>) {
+        self.0 = Some(device);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use vmm_sys_util::tempfile::TempFile;
+
+    use super::*;
+
+    fn file_port(port_id: &str, name: &str, path: &str) -> ConsolePortConfig {
+        ConsolePortConfig {
+            port_id: port_id.to_string(),
+            name: name.to_string(),
+            socket_path: None,
+            file_path: Some(path.to_string()),
+        }
+    }
+
+    #[test]
+    fn test_insert_port() {
+        let file = TempFile::new().unwrap();
+        let path = file.as_path().to_str().unwrap();
+        let mut builder = ConsoleBuilder::new();
+        assert!(builder.get().is_none());
+
+        builder.insert(file_port("port0", "agent", path)).unwrap();
+        builder.insert(file_port("port1", "log", path)).unwrap();
+        assert!(builder.get().is_some());
+        assert_eq!(builder.configs().len(), 2);
+
+        // Replacing a port keeps its position.
+        builder.insert(file_port("port0", "agent2", path)).unwrap();
+        assert_eq!(
+            builder.configs(),
+            vec![
+                file_port("port0", "agent2", path),
+                file_port("port1", "log", path)
+            ]
+        );
+    }
+
+    #[test]
+    fn test_insert_port_errors() {
+        let file = TempFile::new().unwrap();
+        let path = file.as_path().to_str().unwrap();
+        let mut builder = ConsoleBuilder::new();
+
+        let mut config = file_port("port0", "agent", path);
+        config.socket_path = Some("/tmp/console.sock".to_string());
+        assert!(matches!(
+            builder.insert(config),
+            Err(ConsoleConfigError::InvalidBackend)
+        ));
+
+        let mut config = file_port("port0", "agent", path);
+        config.file_path = None;
+        assert!(matches!(
+            builder.insert(config),
+            Err(ConsoleConfigError::InvalidBackend)
+        ));
+
+        assert!(matches!(
+            builder.insert(file_port("port0", "", path)),
+            Err(ConsoleConfigError::InvalidName(_))
+        ));
+        assert!(matches!(
+            builder.insert(file_port("port0", "a/b", path)),
+            Err(ConsoleConfigError::InvalidName(_))
+        ));
+
+        builder.insert(file_port("port0", "agent", path)).unwrap();
+        assert!(matches!(
+            builder.insert(file_port("port1", "agent", path)),
+            Err(ConsoleConfigError::NameInUse(_))
+        ));
+    }
+}
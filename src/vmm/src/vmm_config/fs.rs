@@ -0,0 +1,149 @@
+// Copyright 2025 Amazon.com, Inc. or its affiliates. All Rights Reserved.
+// SPDX-License-Identifier: Apache-2.0
+
+use std::sync::{Arc, Mutex};
+
+use serde::{Deserialize, Serialize};
+
+use crate::devices::virtio::fs::{VhostUserFs, VhostUserFsError};
+
+fn default_num_queues() -> usize {
+    1
+}
+
+/// This struct represents the strongly typed equivalent of the json body from fs device
+/// related requests.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct FsDeviceConfig {
+    /// ID of the fs device.
+    pub fs_id: String,
+    /// Path of the socket the vhost-user backend listens on.
+    pub socket: String,
+    /// Tag used by the guest to mount the file system.
+    pub tag: String,
+    /// Number of request queues.
+    #[serde(default = "default_num_queues")]
+    pub num_queues: usize,
+}
+
+impl From<&VhostUserFs> for FsDeviceConfig {
+    fn from(fs: &VhostUserFs) -> Self {
+        FsDeviceConfig {
+            fs_id: fs.id().to_string(),
+            socket: fs.socket_path().to_string(),
+            tag: fs.tag().to_string(),
+            num_queues: fs.num_request_queues(),
+        }
+    }
+}
+
+/// Errors associated with the operations allowed on a fs device.
+#[derive(Debug, thiserror::Error, displaydoc::Display)]
+pub enum FsDeviceError {
+    /// Could not create the fs device: {0}
+    CreateFsDevice(#[from] VhostUserFsError),
+    /// The tag is already in use: {0}
+    TagInUse(String),
+}
+
+/// Builder for a list of fs devices.
+#[derive(Debug, Default)]
+pub struct FsBuilder {
+    fs_devices: Vec<Arc<Mutex<VhostUserFs>>>,
+}
+
+impl FsBuilder {
+    /// Creates an empty list of fs devices.
+    pub fn new() -> Self {
+        FsBuilder {
+            fs_devices: Vec::new(),
+        }
+    }
+
+    /// Returns a immutable iterator over the fs devices.
+    pub fn iter(&self) -> ::std::slice::Iter<Arc<Mutex<VhostUserFs>>> {
+        self.fs_devices.iter()
+    }
+
+    /// Builds a fs device based on a fs device config. Keeps a device reference in the builder's
+    /// internal list.
+    pub fn build(
+        &mut self,
+        config: FsDeviceConfig,
+    ) -> Result<Arc<Mutex<VhostUserFs>>, FsDeviceError> {
+        let tag_conflict = |fs: &Arc<Mutex<VhostUserFs>>| {
+            let fs = fs.lock().expect("Poisoned lock");
+            // Check if another fs dev has the same tag.
+            fs.tag() == config.tag && fs.id() != config.fs_id
+        };
+        if self.fs_devices.iter().any(tag_conflict) {
+            return Err(FsDeviceError::TagInUse(config.tag));
+        }
+
+        let index = self
+            .fs_devices
+            .iter()
+            .position(|fs| fs.lock().expect("Poisoned lock").id() == config.fs_id);
+        let fs = Arc::new(Mutex::new(VhostUserFs::new(
+            config.fs_id,
+            &config.socket,
+            config.tag,
+            config.num_queues,
+        )?));
+        // If this is an update, replace the old device only once the new one is connected to its
+        // backend, dropping the old one closes its connection.
+        match index {
+            Some(index) => self.fs_devices[index] = fs.clone(),
+            None => self.fs_devices.push(fs.clone()),
+        }
+
+        Ok(fs)
+    }
+
+    /// Returns a vec with the structures used to configure the fs devices.
+    pub fn configs(&self) -> Vec<FsDeviceConfig> {
+        self.fs_devices
+            .iter()
+            .map(|fs| FsDeviceConfig::from(&*fs.lock().expect("Poisoned lock")))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fs_config_defaults() {
+        let config: FsDeviceConfig =
+            serde_json::from_str(r#"{"fs_id": "fs0", "socket": "/tmp/fs.sock", "tag": "src"}"#)
+                .unwrap();
+        assert_eq!(config.num_queues, 1);
+
+        serde_json::from_str::<FsDeviceConfig>(
+            r#"{"fs_id": "fs0", "socket": "/tmp/fs.sock", "tag": "src", "foo": 1}"#,
+        )
+        .unwrap_err();
+    }
+
+    #[test]
+    fn test_build_without_backend() {
+        let mut builder = FsBuilder::new();
+        let config = FsDeviceConfig {
+            fs_id: "fs0".to_string(),
+            socket: "/invalid/socket".to_string(),
+            tag: "src".to_string(),
+            num_queues: 1,
+        };
+        let err = builder.build(config).unwrap_err();
+        assert!(
+            matches!(
+                err,
+                FsDeviceError::CreateFsDevice(VhostUserFsError::VhostUser(_))
+            ),
+            "{err}"
+        );
+        assert_eq!(builder.iter().count(), 0);
+    }
+}
@@ -1,6 +1,9 @@
 // Copyright 2018 Amazon.com, Inc. or its affiliates. All Rights Reserved.
 // SPDX-License-Identifier: Apache-2.0
 use std::fmt::Debug;
+use std::fs::File;
+use std::path::PathBuf;
+use std::sync::Arc;
 
 use serde::{Deserialize, Deserializer, Serialize, Serializer};
 
@@ -11,6 +14,8 @@ pub const DEFAULT_MEM_SIZE_MIB: usize = 128;
 /// Firecracker aims to support small scale workloads only, so limit the maximum
 /// vCPUs supported.
 pub const MAX_SUPPORTED_VCPUS: u8 = 32;
+/// The maximum number of host NUMA nodes Linux can be configured with.
+pub const MAX_HOST_NUMA_NODES: u32 = 1024;
 
 /// Errors associated with configuring the microVM.
 #[rustfmt::skip]
@@ -31,6 +36,19 @@ pub enum MachineConfigError {
     KernelVersion,
     /// Firecracker's huge pages support is incompatible with memory ballooning.
     BalloonAndHugePages,
+    /// The virtio-pci transport is not supported on aarch64.
+    #[cfg(target_arch = "aarch64")]
+    PciNotSupported,
+    /// The Memfd memory backend requires a memfd to be passed along the request.
+    MissingMemfd,
+    /// The memory sizes (MiB) of the NUMA nodes must be multiples of the configured page size adding up to the memory size of the microVM.
+    NumaMemorySize,
+    /// Each vCPU must belong to exactly one NUMA node.
+    NumaVcpus,
+    /// The host NUMA nodes must be lower than {MAX_HOST_NUMA_NODES:}.
+    NumaHostNode,
+    /// The CPU topology must add up to the number of vCPUs, have 2 threads per core if and only if SMT is enabled, and a power of 2 vCPUs per socket if there are multiple sockets.
+    InvalidCpuTopology,
 }
 
 /// Describes the possible (huge)page configurations for a microVM's memory.
@@ -42,6 +60,12 @@ pub enum HugePageConfig {
     /// Back guest memory by 2MB hugetlbfs pages
     #[serde(rename = "2M")]
     Hugetlbfs2M,
+    /// Back guest memory by 1GB hugetlbfs pages
+    #[serde(rename = "1G")]
+    Hugetlbfs1G,
+    /// Back guest memory by anonymous memory advised for 2MB transparent huge pages
+    #[serde(rename = "THP")]
+    Transparent,
 }
 
 impl HugePageConfig {
@@ -51,7 +75,9 @@ impl HugePageConfig {
         let divisor = match self {
             // Any integer memory size expressed in MiB will be a multiple of 4096KiB.
             HugePageConfig::None => 1,
-            HugePageConfig::Hugetlbfs2M => 2,
+            // Keep guest memory a whole number of transparent huge pages.
+            HugePageConfig::Hugetlbfs2M | HugePageConfig::Transparent => 2,
+            HugePageConfig::Hugetlbfs1G => 1024,
         };
 
         mem_size_mib % divisor == 0
@@ -61,21 +87,34 @@ impl HugePageConfig {
     /// create a mapping backed by huge pages as described by this [`HugePageConfig`].
     pub fn mmap_flags(&self) -> libc::c_int {
         match self {
-            HugePageConfig::None => 0,
+            HugePageConfig::None | HugePageConfig::Transparent => 0,
             HugePageConfig::Hugetlbfs2M => libc::MAP_HUGETLB | libc::MAP_HUGE_2MB,
+            HugePageConfig::Hugetlbfs1G => libc::MAP_HUGETLB | libc::MAP_HUGE_1GB,
         }
     }
 
     /// Returns `true` iff this [`HugePageConfig`] describes a hugetlbfs-based configuration.
     pub fn is_hugetlbfs(&self) -> bool {
-        matches!(self, HugePageConfig::Hugetlbfs2M)
+        matches!(
+            self,
+            HugePageConfig::Hugetlbfs2M | HugePageConfig::Hugetlbfs1G
+        )
+    }
+
+    /// Returns `true` iff this [`HugePageConfig`] describes transparent huge pages.
+    pub fn is_transparent(&self) -> bool {
+        matches!(self, HugePageConfig::Transparent)
     }
 
     /// Gets the page size in bytes of this [`HugePageConfig`].
+    ///
+    /// Transparent huge pages can be split by the kernel at any time, so memory backed by them
+    /// is still managed at 4K granularity.
     pub fn page_size(&self) -> usize {
         match self {
-            HugePageConfig::None => 4096,
+            HugePageConfig::None | HugePageConfig::Transparent => 4096,
             HugePageConfig::Hugetlbfs2M => 2 * 1024 * 1024,
+            HugePageConfig::Hugetlbfs1G => 1024 * 1024 * 1024,
         }
     }
 }
@@ -83,12 +122,234 @@ impl HugePageConfig {
 impl From<HugePageConfig> for Option<memfd::HugetlbSize> {
     fn from(value: HugePageConfig) -> Self {
         match value {
-            HugePageConfig::None => None,
+            HugePageConfig::None | HugePageConfig::Transparent => None,
             HugePageConfig::Hugetlbfs2M => Some(memfd::HugetlbSize::Huge2MB),
+            HugePageConfig::Hugetlbfs1G => Some(memfd::HugetlbSize::Huge1GB),
         }
     }
 }
 
+/// Describes the NUMA nodes of a microVM, and how they are placed on the host NUMA nodes.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct NumaConfig {
+    /// The NUMA nodes of the microVM. Guest memory is split among them, in order.
+    pub nodes: Vec<NumaNodeConfig>,
+    /// Describes the NUMA nodes to the guest, instead of a single memory node.
+    #[serde(default)]
+    pub expose_topology: bool,
+}
+
+/// Describes a NUMA node of a microVM.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct NumaNodeConfig {
+    /// The amount of guest memory of the node, in MiB.
+    pub mem_size_mib: usize,
+    /// The indexes of the vCPUs of the node.
+    #[serde(default)]
+    pub vcpus: Vec<u8>,
+    /// The host NUMA node the guest memory of the node is bound to, and its vCPUs are pinned to.
+    #[serde(default)]
+    pub host_node: Option<u32>,
+}
+
+impl NumaConfig {
+    /// Returns `true` iff no NUMA nodes are configured.
+    pub fn is_empty(&self) -> bool {
+        self.nodes.is_empty()
+    }
+
+    /// Returns the index of the NUMA node the vCPU with index `vcpu` belongs to.
+    pub fn vcpu_node(&self, vcpu: u8) -> Option<usize> {
+        self.nodes
+            .iter()
+            .position(|node| node.vcpus.contains(&vcpu))
+    }
+
+    fn validate(
+        &self,
+        vcpu_count: u8,
+        mem_size_mib: usize,
+        page_config: HugePageConfig,
+    ) -> Result<(), MachineConfigError> {
+        if self
+            .nodes
+            .iter()
+            .any(|node| node.mem_size_mib == 0 || !page_config.is_valid_mem_size(node.mem_size_mib))
+            || self
+                .nodes
+                .iter()
+                .map(|node| node.mem_size_mib)
+                .sum::<usize>()
+                != mem_size_mib
+        {
+            return Err(MachineConfigError::NumaMemorySize);
+        }
+
+        let mut vcpus: Vec<u8> = self
+            .nodes
+            .iter()
+            .flat_map(|node| node.vcpus.iter().copied())
+            .collect();
+        vcpus.sort_unstable();
+        if !vcpus.iter().copied().eq(0..vcpu_count) {
+            return Err(MachineConfigError::NumaVcpus);
+        }
+
+        if self.nodes.iter().any(|node| {
+            node.host_node
+                .is_some_and(|host| host >= MAX_HOST_NUMA_NODES)
+        }) {
+            return Err(MachineConfigError::NumaHostNode);
+        }
+
+        Ok(())
+    }
+}
+
+/// Guest-visible topology of the vCPUs. The vCPUs are laid out socket by socket, core by core.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct CpuTopology {
+    /// Number of sockets.
+    pub sockets: u8,
+    /// Number of cores per socket.
+    pub cores_per_socket: u8,
+    /// Number of threads per core.
+    pub threads_per_core: u8,
+}
+
+impl Default for CpuTopology {
+    fn default() -> Self {
+        Self {
+            sockets: 1,
+            cores_per_socket: 1,
+            threads_per_core: 1,
+        }
+    }
+}
+
+impl CpuTopology {
+    /// Builds the topology of `vcpu_count` vCPUs, defaulting to a single socket and to as many
+    /// threads per core as SMT allows.
+    fn new(
+        vcpu_count: u8,
+        smt: bool,
+        sockets: Option<u8>,
+        cores_per_socket: Option<u8>,
+        threads_per_core: Option<u8>,
+    ) -> Result<Self, MachineConfigError> {
+        let smt_threads = if smt && vcpu_count > 1 { 2 } else { 1 };
+        let threads_per_core = threads_per_core.unwrap_or(smt_threads);
+        if threads_per_core != smt_threads {
+            return Err(MachineConfigError::InvalidCpuTopology);
+        }
+
+        let sockets = match (sockets, cores_per_socket) {
+            (Some(sockets), _) => sockets,
+            (None, Some(cores)) => cores
+                .checked_mul(threads_per_core)
+                .and_then(|cpus| vcpu_count.checked_div(cpus))
+                .ok_or(MachineConfigError::InvalidCpuTopology)?,
+            (None, None) => 1,
+        };
+        let cores_per_socket = match cores_per_socket {
+            Some(cores) => cores,
+            None => sockets
+                .checked_mul(threads_per_core)
+                .and_then(|cpus| vcpu_count.checked_div(cpus))
+                .ok_or(MachineConfigError::InvalidCpuTopology)?,
+        };
+
+        let topology = CpuTopology {
+            sockets,
+            cores_per_socket,
+            threads_per_core,
+        };
+        let cpus_per_socket = u16::from(cores_per_socket) * u16::from(threads_per_core);
+        // The APIC IDs and the MPIDRs of the vCPUs are their indexes, so the socket of a vCPU can
+        // only be told apart from its index bits if the sockets are a power of 2 vCPUs apart.
+        if cpus_per_socket == 0
+            || cpus_per_socket * u16::from(sockets) != u16::from(vcpu_count)
+            || (sockets > 1 && !cpus_per_socket.is_power_of_two())
+        {
+            return Err(MachineConfigError::InvalidCpuTopology);
+        }
+
+        Ok(topology)
+    }
+
+    /// Number of vCPUs per socket.
+    pub fn cpus_per_socket(&self) -> u8 {
+        self.cores_per_socket * self.threads_per_core
+    }
+}
+
+/// Describes the transport through which virtio devices are exposed to the guest.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum VirtioTransport {
+    /// Virtio devices are discovered through the kernel command line and use the MMIO transport.
+    #[default]
+    Mmio,
+    /// Virtio devices are plugged into a PCI root complex and interrupt through MSI-X.
+    Pci,
+}
+
+/// A memfd received over the API socket, backing the guest memory.
+#[derive(Clone, Debug)]
+pub struct GuestMemfd(Arc<File>);
+
+impl GuestMemfd {
+    /// Wraps the memfd received along a `/machine-config` request.
+    pub fn new(memfd: File) -> Self {
+        Self(Arc::new(memfd))
+    }
+
+    /// Returns the memfd backing the guest memory.
+    pub fn file(&self) -> &Arc<File> {
+        &self.0
+    }
+}
+
+// Two memfds are only considered equal if they are the very same file description.
+impl PartialEq for GuestMemfd {
+    fn eq(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.0, &other.0)
+    }
+}
+
+impl Eq for GuestMemfd {}
+
+/// Describes the host memory backing the guest memory.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "backend_type", deny_unknown_fields)]
+pub enum GuestMemoryBackend {
+    /// Private anonymous memory, unless a vhost-user device requires Firecracker to share the
+    /// guest memory through a memfd it creates.
+    #[default]
+    Anonymous,
+    /// A memfd passed over the API socket along the `/machine-config` request, mapped shared.
+    Memfd {
+        /// The memfd itself, which is never (de)serialized.
+        #[serde(skip)]
+        memfd: Option<GuestMemfd>,
+    },
+    /// A file, typically on a tmpfs or hugetlbfs mount, mapped shared. The file is created if
+    /// it doesn't exist, and extended if it is smaller than the guest memory.
+    File {
+        /// Path of the file backing the guest memory.
+        backend_path: PathBuf,
+    },
+}
+
+impl GuestMemoryBackend {
+    /// Returns `true` iff guest memory is mapped shared from a file provided by the user.
+    pub fn is_shared(&self) -> bool {
+        !matches!(self, GuestMemoryBackend::Anonymous)
+    }
+}
+
 /// Struct used in PUT `/machine-config` API call.
 #[derive(Clone, Debug, PartialEq, Eq, Deserialize, Serialize)]
 #[serde(deny_unknown_fields)]
@@ -100,6 +361,15 @@ pub struct MachineConfig {
     /// Enables or disabled SMT.
     #[serde(default)]
     pub smt: bool,
+    /// Number of guest CPU sockets.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sockets: Option<u8>,
+    /// Number of cores per guest CPU socket.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cores_per_socket: Option<u8>,
+    /// Number of threads per guest CPU core.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub threads_per_core: Option<u8>,
     /// A CPU template that it is used to filter the CPU features exposed to the guest.
     // FIXME: once support for static CPU templates is removed, this field can be dropped altogether
     #[serde(
@@ -115,6 +385,15 @@ pub struct MachineConfig {
     /// Configures what page size Firecracker should use to back guest memory.
     #[serde(default)]
     pub huge_pages: HugePageConfig,
+    /// Transport used by the virtio devices.
+    #[serde(default)]
+    pub virtio_transport: VirtioTransport,
+    /// Host memory backing the guest memory.
+    #[serde(default)]
+    pub memory_backend: GuestMemoryBackend,
+    /// NUMA nodes of the microVM.
+    #[serde(default, skip_serializing_if = "NumaConfig::is_empty")]
+    pub numa: NumaConfig,
     /// GDB socket address.
     #[cfg(feature = "gdb")]
     #[serde(default, skip_serializing_if = "Option::is_none")]
@@ -154,9 +433,15 @@ impl Default for MachineConfig {
             vcpu_count: 1,
             mem_size_mib: DEFAULT_MEM_SIZE_MIB,
             smt: false,
+            sockets: None,
+            cores_per_socket: None,
+            threads_per_core: None,
             cpu_template: None,
             track_dirty_pages: false,
             huge_pages: HugePageConfig::None,
+            virtio_transport: VirtioTransport::Mmio,
+            memory_backend: GuestMemoryBackend::Anonymous,
+            numa: NumaConfig::default(),
             #[cfg(feature = "gdb")]
             gdb_socket_path: None,
         }
@@ -181,6 +466,15 @@ pub struct MachineConfigUpdate {
     /// Enables or disabled SMT.
     #[serde(default)]
     pub smt: Option<bool>,
+    /// Number of guest CPU sockets.
+    #[serde(default)]
+    pub sockets: Option<u8>,
+    /// Number of cores per guest CPU socket.
+    #[serde(default)]
+    pub cores_per_socket: Option<u8>,
+    /// Number of threads per guest CPU core.
+    #[serde(default)]
+    pub threads_per_core: Option<u8>,
     /// A CPU template that it is used to filter the CPU features exposed to the guest.
     #[serde(default)]
     pub cpu_template: Option<StaticCpuTemplate>,
@@ -190,6 +484,15 @@ pub struct MachineConfigUpdate {
     /// Configures what page size Firecracker should use to back guest memory.
     #[serde(default)]
     pub huge_pages: Option<HugePageConfig>,
+    /// Transport used by the virtio devices.
+    #[serde(default)]
+    pub virtio_transport: Option<VirtioTransport>,
+    /// Host memory backing the guest memory.
+    #[serde(default)]
+    pub memory_backend: Option<GuestMemoryBackend>,
+    /// NUMA nodes of the microVM.
+    #[serde(default)]
+    pub numa: Option<NumaConfig>,
     /// GDB socket address.
     #[cfg(feature = "gdb")]
     #[serde(default)]
@@ -211,9 +514,15 @@ impl From<MachineConfig> for MachineConfigUpdate {
             vcpu_count: Some(cfg.vcpu_count),
             mem_size_mib: Some(cfg.mem_size_mib),
             smt: Some(cfg.smt),
+            sockets: cfg.sockets,
+            cores_per_socket: cfg.cores_per_socket,
+            threads_per_core: cfg.threads_per_core,
             cpu_template: cfg.static_template(),
             track_dirty_pages: Some(cfg.track_dirty_pages),
             huge_pages: Some(cfg.huge_pages),
+            virtio_transport: Some(cfg.virtio_transport),
+            memory_backend: Some(cfg.memory_backend),
+            numa: Some(cfg.numa),
             #[cfg(feature = "gdb")]
             gdb_socket_path: cfg.gdb_socket_path,
         }
@@ -233,6 +542,23 @@ impl MachineConfig {
         }
     }
 
+    /// Returns the guest-visible topology of the vCPUs.
+    pub fn cpu_topology(&self) -> CpuTopology {
+        CpuTopology::new(
+            self.vcpu_count,
+            self.smt,
+            self.sockets,
+            self.cores_per_socket,
+            self.threads_per_core,
+        )
+        // A `MachineConfig` is only ever built through `update`, which validates the topology.
+        .unwrap_or(CpuTopology {
+            sockets: 1,
+            cores_per_socket: self.vcpu_count,
+            threads_per_core: 1,
+        })
+    }
+
     /// Updates [`MachineConfig`] with [`MachineConfigUpdate`].
     /// Mapping for cpu template update:
     /// StaticCpuTemplate::None -> None
@@ -261,6 +587,18 @@ impl MachineConfig {
             return Err(MachineConfigError::InvalidVcpuCount);
         }
 
+        let sockets = update.sockets.or(self.sockets);
+        let cores_per_socket = update.cores_per_socket.or(self.cores_per_socket);
+        let threads_per_core = update.threads_per_core.or(self.threads_per_core);
+        CpuTopology::new(vcpu_count, smt, sockets, cores_per_socket, threads_per_core)?;
+
+        let virtio_transport = update.virtio_transport.unwrap_or(self.virtio_transport);
+
+        #[cfg(target_arch = "aarch64")]
+        if virtio_transport == VirtioTransport::Pci {
+            return Err(MachineConfigError::PciNotSupported);
+        }
+
         let mem_size_mib = update.mem_size_mib.unwrap_or(self.mem_size_mib);
         let page_config = update.huge_pages.unwrap_or(self.huge_pages);
 
@@ -268,6 +606,20 @@ impl MachineConfig {
             return Err(MachineConfigError::InvalidMemorySize);
         }
 
+        let memory_backend = update
+            .memory_backend
+            .clone()
+            .unwrap_or_else(|| self.memory_backend.clone());
+
+        if matches!(memory_backend, GuestMemoryBackend::Memfd { memfd: None }) {
+            return Err(MachineConfigError::MissingMemfd);
+        }
+
+        let numa = update.numa.clone().unwrap_or_else(|| self.numa.clone());
+        if !numa.is_empty() {
+            numa.validate(vcpu_count, mem_size_mib, page_config)?;
+        }
+
         let cpu_template = match update.cpu_template {
             None => self.cpu_template.clone(),
             Some(StaticCpuTemplate::None) => None,
@@ -278,9 +630,15 @@ impl MachineConfig {
             vcpu_count,
             mem_size_mib,
             smt,
+            sockets,
+            cores_per_socket,
+            threads_per_core,
             cpu_template,
             track_dirty_pages: update.track_dirty_pages.unwrap_or(self.track_dirty_pages),
             huge_pages: page_config,
+            virtio_transport,
+            memory_backend,
+            numa,
             #[cfg(feature = "gdb")]
             gdb_socket_path: update.gdb_socket_path.clone(),
         })
@@ -290,7 +648,10 @@ impl MachineConfig {
 #[cfg(test)]
 mod tests {
     use crate::cpu_config::templates::{CpuTemplateType, CustomCpuTemplate, StaticCpuTemplate};
-    use crate::vmm_config::machine_config::MachineConfig;
+    use crate::vmm_config::machine_config::{
+        CpuTopology, GuestMemfd, GuestMemoryBackend, HugePageConfig, MAX_HOST_NUMA_NODES,
+        MachineConfig, MachineConfigError, MachineConfigUpdate, NumaConfig,
+    };
 
     // Ensure the special (de)serialization logic for the cpu_template field works:
     // only static cpu templates can be specified via the machine-config endpoint, but
@@ -337,4 +698,220 @@ mod tests {
 
         assert!(deserialized.cpu_template.is_none());
     }
+
+    #[test]
+    fn test_memory_backend() {
+        let backend: GuestMemoryBackend =
+            serde_json::from_str(r#"{"backend_type": "File", "backend_path": "/dev/shm/mem"}"#)
+                .unwrap();
+        assert_eq!(
+            backend,
+            GuestMemoryBackend::File {
+                backend_path: "/dev/shm/mem".into()
+            }
+        );
+        serde_json::from_str::<GuestMemoryBackend>(r#"{"backend_type": "File"}"#).unwrap_err();
+        serde_json::from_str::<GuestMemoryBackend>(
+            r#"{"backend_type": "Memfd", "backend_path": "/dev/shm/mem"}"#,
+        )
+        .unwrap_err();
+
+        // The memfd itself is never part of the JSON representation.
+        let backend: GuestMemoryBackend =
+            serde_json::from_str(r#"{"backend_type": "Memfd"}"#).unwrap();
+        assert_eq!(backend, GuestMemoryBackend::Memfd { memfd: None });
+        let memfd = GuestMemfd::new(vmm_sys_util::tempfile::TempFile::new().unwrap().into_file());
+        let backend = GuestMemoryBackend::Memfd { memfd: Some(memfd) };
+        assert_eq!(
+            serde_json::to_string(&backend).unwrap(),
+            r#"{"backend_type":"Memfd"}"#
+        );
+
+        // A memfd backend can't be configured without the memfd.
+        let config = MachineConfig::default();
+        let update = MachineConfigUpdate {
+            memory_backend: Some(GuestMemoryBackend::Memfd { memfd: None }),
+            ..Default::default()
+        };
+        assert_eq!(
+            config.update(&update),
+            Err(MachineConfigError::MissingMemfd)
+        );
+        let update = MachineConfigUpdate {
+            memory_backend: Some(backend.clone()),
+            ..Default::default()
+        };
+        let config = config.update(&update).unwrap();
+        assert_eq!(config.memory_backend, backend);
+        assert!(config.memory_backend.is_shared());
+
+        // Updating other fields keeps the memory backend.
+        let update = MachineConfigUpdate {
+            vcpu_count: Some(2),
+            ..Default::default()
+        };
+        assert_eq!(config.update(&update).unwrap().memory_backend, backend);
+    }
+
+    #[test]
+    fn test_numa() {
+        let numa: NumaConfig = serde_json::from_str(
+            r#"{
+                "nodes": [
+                    {"mem_size_mib": 1024, "vcpus": [0, 1], "host_node": 0},
+                    {"mem_size_mib": 1024, "vcpus": [2, 3]}
+                ],
+                "expose_topology": true
+            }"#,
+        )
+        .unwrap();
+        assert_eq!(numa.nodes[0].host_node, Some(0));
+        assert_eq!(numa.nodes[1].host_node, None);
+        assert_eq!(numa.vcpu_node(3), Some(1));
+        assert_eq!(numa.vcpu_node(4), None);
+
+        let config = MachineConfig::default();
+        let mut update = MachineConfigUpdate {
+            vcpu_count: Some(4),
+            mem_size_mib: Some(2048),
+            numa: Some(numa.clone()),
+            ..Default::default()
+        };
+        let updated = config.update(&update).unwrap();
+        assert_eq!(updated.numa, numa);
+
+        // The NUMA nodes must add up to the memory size.
+        update.mem_size_mib = Some(4096);
+        assert_eq!(
+            config.update(&update),
+            Err(MachineConfigError::NumaMemorySize)
+        );
+        // And their sizes must be multiples of the page size.
+        update.mem_size_mib = Some(2048);
+        update.huge_pages = Some(HugePageConfig::Hugetlbfs1G);
+        let mut invalid = numa.clone();
+        invalid.nodes[0].mem_size_mib = 1536;
+        invalid.nodes[1].mem_size_mib = 512;
+        update.numa = Some(invalid);
+        assert_eq!(
+            config.update(&update),
+            Err(MachineConfigError::NumaMemorySize)
+        );
+        update.huge_pages = None;
+
+        // Each vCPU must be in exactly one node.
+        let mut invalid = numa.clone();
+        invalid.nodes[1].vcpus = vec![1, 2, 3];
+        update.numa = Some(invalid);
+        assert_eq!(config.update(&update), Err(MachineConfigError::NumaVcpus));
+        update.numa = Some(numa.clone());
+        update.vcpu_count = Some(6);
+        assert_eq!(config.update(&update), Err(MachineConfigError::NumaVcpus));
+        update.vcpu_count = Some(4);
+
+        let mut invalid = numa;
+        invalid.nodes[1].host_node = Some(MAX_HOST_NUMA_NODES);
+        update.numa = Some(invalid);
+        assert_eq!(
+            config.update(&update),
+            Err(MachineConfigError::NumaHostNode)
+        );
+
+        // Updating other fields keeps the NUMA nodes.
+        let update = MachineConfigUpdate {
+            track_dirty_pages: Some(true),
+            ..Default::default()
+        };
+        assert_eq!(updated.update(&update).unwrap().numa, updated.numa);
+    }
+
+    #[test]
+    fn test_cpu_topology() {
+        let config = MachineConfig {
+            vcpu_count: 8,
+            ..Default::default()
+        };
+        assert_eq!(
+            config.cpu_topology(),
+            CpuTopology {
+                sockets: 1,
+                cores_per_socket: 8,
+                threads_per_core: 1,
+            }
+        );
+
+        let mut update = MachineConfigUpdate {
+            sockets: Some(2),
+            ..Default::default()
+        };
+        let updated = config.update(&update).unwrap();
+        assert_eq!(
+            updated.cpu_topology(),
+            CpuTopology {
+                sockets: 2,
+                cores_per_socket: 4,
+                threads_per_core: 1,
+            }
+        );
+        assert_eq!(updated.cpu_topology().cpus_per_socket(), 4);
+
+        // The number of sockets is derived from the number of cores per socket.
+        update.sockets = None;
+        update.cores_per_socket = Some(2);
+        assert_eq!(config.update(&update).unwrap().cpu_topology().sockets, 4);
+
+        // The topology must add up to the number of vCPUs.
+        update.sockets = Some(2);
+        assert_eq!(
+            config.update(&update),
+            Err(MachineConfigError::InvalidCpuTopology)
+        );
+        update.sockets = Some(3);
+        update.cores_per_socket = None;
+        assert_eq!(
+            config.update(&update),
+            Err(MachineConfigError::InvalidCpuTopology)
+        );
+
+        // Multiple sockets must have a power of 2 vCPUs each.
+        update.vcpu_count = Some(6);
+        update.sockets = Some(2);
+        assert_eq!(
+            config.update(&update),
+            Err(MachineConfigError::InvalidCpuTopology)
+        );
+        update.sockets = Some(1);
+        assert_eq!(
+            config
+                .update(&update)
+                .unwrap()
+                .cpu_topology()
+                .cores_per_socket,
+            6
+        );
+
+        // There are 2 threads per core if and only if SMT is enabled.
+        update.threads_per_core = Some(2);
+        assert_eq!(
+            config.update(&update),
+            Err(MachineConfigError::InvalidCpuTopology)
+        );
+        #[cfg(target_arch = "x86_64")]
+        {
+            update.smt = Some(true);
+            assert_eq!(
+                config
+                    .update(&update)
+                    .unwrap()
+                    .cpu_topology()
+                    .cores_per_socket,
+                3
+            );
+            update.threads_per_core = Some(1);
+            assert_eq!(
+                config.update(&update),
+                Err(MachineConfigError::InvalidCpuTopology)
+            );
+        }
+    }
 }
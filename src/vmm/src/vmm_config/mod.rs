@@ -16,10 +16,16 @@ use crate::rate_limiter::{BucketUpdate, RateLimiter, TokenBucket};
 pub mod balloon;
 /// Wrapper for configuring the microVM boot source.
 pub mod boot_source;
+/// Wrapper for configuring the ports of the virtio-console device.
+pub mod console;
+/// Wrapper for configuring guest core dumps.
+pub mod coredump;
 /// Wrapper for configuring the block devices.
 pub mod drive;
 /// Wrapper for configuring the entropy device attached to the microVM.
 pub mod entropy;
+/// Wrapper for configuring the vhost-user-fs devices attached to the microVM.
+pub mod fs;
 /// Wrapper over the microVM general information attached to the microVM.
 pub mod instance_info;
 /// Wrapper for configuring the memory and CPU of the microVM.
@@ -30,10 +36,18 @@ pub mod metrics;
 pub mod mmds;
 /// Wrapper for configuring the network devices attached to the microVM.
 pub mod net;
+/// Wrapper for configuring the virtio-pmem devices attached to the microVM.
+pub mod pmem;
+/// Wrapper for configuring the pvpanic device attached to the microVM.
+pub mod pvpanic;
+/// Wrapper for stacking additional seccomp filters at runtime.
+pub mod seccomp;
 /// Wrapper for configuring microVM snapshots and the microVM state.
 pub mod snapshot;
 /// Wrapper for configuring the vsock devices attached to the microVM.
 pub mod vsock;
+/// Wrapper for configuring the watchdog device attached to the microVM.
+pub mod watchdog;
 
 // TODO: Migrate the VMM public-facing code (i.e. interface) to use stateless structures,
 // for receiving data/args, such as the below `RateLimiterConfig` and `TokenBucketConfig`.
@@ -0,0 +1,159 @@
+// Copyright 2025 Amazon.com, Inc. or its affiliates. All Rights Reserved.
+// SPDX-License-Identifier: Apache-2.0
+
+use std::sync::{Arc, Mutex};
+
+use serde::{Deserialize, Serialize};
+
+use crate::devices::virtio::pmem::{Pmem, PmemError};
+
+/// This struct represents the strongly typed equivalent of the json body from pmem device
+/// related requests.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct PmemConfig {
+    /// ID of the pmem device.
+    pub id: String,
+    /// Path of the file mapped in the guest.
+    pub path_on_host: String,
+    /// If set to true, the device is used as the root device of the guest.
+    #[serde(default)]
+    pub root_device: bool,
+    /// If set to true, the guest can only read the device.
+    #[serde(default)]
+    pub read_only: bool,
+}
+
+/// Errors associated with the operations allowed on a pmem device.
+#[derive(Debug, thiserror::Error, displaydoc::Display)]
+pub enum PmemConfigError {
+    /// Could not create the pmem device: {0}
+    CreatePmemDevice(#[from] PmemError),
+    /// A root device already exists!
+    RootDeviceAlreadyAdded,
+}
+
+/// Builder for a list of pmem devices.
+#[derive(Debug, Default)]
+pub struct PmemBuilder {
+    /// The list of pmem devices.
+    /// There can be at most one root pmem device and it is the first in the list, so that the
+    /// guest names it /dev/pmem0.
+    pub devices: Vec<Arc<Mutex<Pmem>>>,
+}
+
+impl PmemBuilder {
+    /// Creates an empty list of pmem devices.
+    pub fn new() -> Self {
+        PmemBuilder {
+            devices: Vec::new(),
+        }
+    }
+
+    /// Returns a immutable iterator over the pmem devices.
+    pub fn iter(&self) -> ::std::slice::Iter<Arc<Mutex<Pmem>>> {
+        self.devices.iter()
+    }
+
+    /// Specifies whether there is a root pmem device already present in the list.
+    pub fn has_root_device(&self) -> bool {
+        self.devices
+            .first()
+            .is_some_and(|pmem| pmem.lock().expect("Poisoned lock").root_device())
+    }
+
+    /// Inserts an existing pmem device.
+    pub fn add_device(&mut self, pmem: Arc<Mutex<Pmem>>) {
+        if pmem.lock().expect("Poisoned lock").root_device() {
+            self.devices.insert(0, pmem);
+        } else {
+            self.devices.push(pmem);
+        }
+    }
+
+    /// Builds a pmem device based on a pmem device config. If a device with the same id already
+    /// exists, it is replaced. Inserting a second root device fails.
+    pub fn build(&mut self, config: PmemConfig) -> Result<(), PmemConfigError> {
+        let position = self
+            .devices
+            .iter()
+            .position(|pmem| pmem.lock().expect("Poisoned lock").id() == config.id);
+        if config.root_device && self.has_root_device() && position != Some(0) {
+            return Err(PmemConfigError::RootDeviceAlreadyAdded);
+        }
+
+        // Drop the old device before creating the new one, so that its mapping is released.
+        if let Some(index) = position {
+            self.devices.remove(index);
+        }
+        let pmem = Arc::new(Mutex::new(Pmem::new(config)?));
+        self.add_device(pmem);
+        Ok(())
+    }
+
+    /// Returns a vec with the structures used to configure the pmem devices.
+    pub fn configs(&self) -> Vec<PmemConfig> {
+        self.devices
+            .iter()
+            .map(|pmem| pmem.lock().expect("Poisoned lock").config().clone())
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use vmm_sys_util::tempfile::TempFile;
+
+    use super::*;
+    use crate::devices::virtio::pmem::device::tests::default_pmem_config;
+
+    #[test]
+    fn test_pmem_config_defaults() {
+        let config: PmemConfig =
+            serde_json::from_str(r#"{"id": "pmem0", "path_on_host": "/tmp/rootfs.ext4"}"#).unwrap();
+        assert!(!config.root_device);
+        assert!(!config.read_only);
+
+        serde_json::from_str::<PmemConfig>(
+            r#"{"id": "pmem0", "path_on_host": "/tmp/rootfs.ext4", "foo": 1}"#,
+        )
+        .unwrap_err();
+    }
+
+    #[test]
+    fn test_build() {
+        let files = [TempFile::new().unwrap(), TempFile::new().unwrap()];
+        let mut builder = PmemBuilder::new();
+
+        let data = default_pmem_config("data", &files[0], false);
+        builder.build(data.clone()).unwrap();
+        let mut root = default_pmem_config("root", &files[1], true);
+        root.root_device = true;
+        builder.build(root.clone()).unwrap();
+        // The root device comes first.
+        assert_eq!(builder.configs(), [root.clone(), data.clone()]);
+        assert!(builder.has_root_device());
+
+        // A second root device is rejected.
+        let mut config = data.clone();
+        config.root_device = true;
+        assert!(matches!(
+            builder.build(config),
+            Err(PmemConfigError::RootDeviceAlreadyAdded)
+        ));
+
+        // Devices can be replaced.
+        root.read_only = false;
+        builder.build(root.clone()).unwrap();
+        assert_eq!(builder.configs(), [root, data]);
+
+        // Invalid backing files are rejected.
+        let mut config = default_pmem_config("invalid", &files[0], false);
+        config.path_on_host = "/invalid/path".to_string();
+        assert!(matches!(
+            builder.build(config),
+            Err(PmemConfigError::CreatePmemDevice(PmemError::OpenFile(_)))
+        ));
+        assert_eq!(builder.iter().count(), 2);
+    }
+}
@@ -0,0 +1,40 @@
+// Copyright 2025 Amazon.com, Inc. or its affiliates. All Rights Reserved.
+// SPDX-License-Identifier: Apache-2.0
+
+use serde::{Deserialize, Serialize};
+
+/// What Firecracker does when the guest kernel reports a panic.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+pub enum PanicAction {
+    /// Stop the microVM, exiting with `FcExitCode::GuestPanic`.
+    #[default]
+    Exit,
+    /// Pause the microVM, so that it can be inspected or snapshotted.
+    Pause,
+}
+
+/// This struct represents the strongly typed equivalent of the json body from pvpanic device
+/// related requests.
+#[derive(Debug, Default, Clone, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct PvPanicConfig {
+    /// Action taken when the guest kernel panics.
+    #[serde(default)]
+    pub on_panic: PanicAction,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pvpanic_config() {
+        let config: PvPanicConfig = serde_json::from_str("{}").unwrap();
+        assert_eq!(config.on_panic, PanicAction::Exit);
+        let config: PvPanicConfig = serde_json::from_str(r#"{"on_panic": "Pause"}"#).unwrap();
+        assert_eq!(config.on_panic, PanicAction::Pause);
+
+        serde_json::from_str::<PvPanicConfig>(r#"{"on_panic": "Reboot"}"#).unwrap_err();
+        serde_json::from_str::<PvPanicConfig>(r#"{"action": "Pause"}"#).unwrap_err();
+    }
+}
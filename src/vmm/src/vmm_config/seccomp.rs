@@ -0,0 +1,121 @@
+// Copyright 2025 Amazon.com, Inc. or its affiliates. All Rights Reserved.
+// SPDX-License-Identifier: Apache-2.0
+
+//! Auxiliary module for stacking additional seccomp filters at runtime.
+use std::fs::File;
+use std::io::BufReader;
+use std::path::PathBuf;
+
+use serde::{Deserialize, Serialize};
+
+use crate::ApplySeccompFiltersError;
+use crate::seccomp::{BpfThreadMap, DeserializationError, InstallationError, deserialize_binary};
+
+/// Thread categories that an additional seccomp filter file must provide filters for.
+const THREAD_CATEGORIES: [&str; 3] = ["vmm", "api", "vcpu"];
+
+/// Strongly typed structure used to describe an additional, compiled seccomp filter file.
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct SeccompFilterConfig {
+    /// Path of the file holding the filters compiled with seccompiler-bin.
+    pub filter_path: PathBuf,
+}
+
+/// Errors associated with stacking additional seccomp filters.
+#[derive(Debug, thiserror::Error, displaydoc::Display)]
+pub enum SeccompFilterError {
+    /// Cannot open the seccomp filter file: {0}
+    Open(std::io::Error),
+    /// Cannot deserialize the seccomp filter file: {0}
+    Deserialize(DeserializationError),
+    /// Missing seccomp filter for thread category: {0}
+    MissingThreadCategory(String),
+    /// Cannot install the seccomp filters: {0}
+    Install(ApplySeccompFiltersError),
+    /// Cannot install the seccomp filter on the API thread: {0}
+    InstallApi(InstallationError),
+}
+
+/// Reads the compiled seccomp filters described by `cfg`.
+pub fn load_seccomp_filters(cfg: &SeccompFilterConfig) -> Result<BpfThreadMap, SeccompFilterError> {
+    let file = File::open(&cfg.filter_path).map_err(SeccompFilterError::Open)?;
+    let filters =
+        deserialize_binary(BufReader::new(file)).map_err(SeccompFilterError::Deserialize)?;
+
+    if let Some(category) = THREAD_CATEGORIES
+        .iter()
+        .find(|category| !filters.contains_key(**category))
+    {
+        return Err(SeccompFilterError::MissingThreadCategory(
+            category.to_string(),
+        ));
+    }
+    Ok(filters)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+    use std::io::Write;
+
+    use vmm_sys_util::tempfile::TempFile;
+
+    use super::*;
+    use crate::seccomp::BpfProgram;
+
+    fn write_filters(categories: &[&str]) -> TempFile {
+        let filters: HashMap<String, BpfProgram> = categories
+            .iter()
+            .map(|category| (category.to_string(), vec![]))
+            .collect();
+        let file = TempFile::new().unwrap();
+        let bytes = bincode::serde::encode_to_vec(
+            &filters,
+            bincode::config::standard()
+                .with_fixed_int_encoding()
+                .with_little_endian(),
+        )
+        .unwrap();
+        file.as_file().write_all(&bytes).unwrap();
+        file
+    }
+
+    #[test]
+    fn test_load_seccomp_filters() {
+        let cfg = SeccompFilterConfig {
+            filter_path: PathBuf::from("/invalid/path"),
+        };
+        assert!(matches!(
+            load_seccomp_filters(&cfg),
+            Err(SeccompFilterError::Open(_))
+        ));
+
+        let file = TempFile::new().unwrap();
+        file.as_file().write_all(b"invalid filters").unwrap();
+        let cfg = SeccompFilterConfig {
+            filter_path: file.as_path().to_path_buf(),
+        };
+        assert!(matches!(
+            load_seccomp_filters(&cfg),
+            Err(SeccompFilterError::Deserialize(_))
+        ));
+
+        let file = write_filters(&["vmm", "vcpu"]);
+        let cfg = SeccompFilterConfig {
+            filter_path: file.as_path().to_path_buf(),
+        };
+        match load_seccomp_filters(&cfg) {
+            Err(SeccompFilterError::MissingThreadCategory(category)) => {
+                assert_eq!(category, "api")
+            }
+            res => panic!("Unexpected result: {:?}", res),
+        }
+
+        let file = write_filters(&THREAD_CATEGORIES);
+        let cfg = SeccompFilterConfig {
+            filter_path: file.as_path().to_path_buf(),
+        };
+        assert_eq!(load_seccomp_filters(&cfg).unwrap().len(), 3);
+    }
+}
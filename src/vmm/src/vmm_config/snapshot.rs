@@ -24,13 +24,16 @@ pub enum SnapshotType {
 /// resuming from a snapshot:
 /// 1) A file that contains the guest memory to be loaded,
 /// 2) An UDS where a custom page-fault handler process is listening for the UFFD set up by
-///    Firecracker to handle its guest memory page faults.
+///    Firecracker to handle its guest memory page faults,
+/// 3) A file that contains the guest memory, and keeps backing it after the restore.
 #[derive(Debug, PartialEq, Eq, Deserialize)]
 pub enum MemBackendType {
     /// Guest memory contents will be loaded from a file.
     File,
     /// Guest memory will be served through UFFD by a separate process.
     Uffd,
+    /// Guest memory will be mapped shared from a file, so that guest writes land in the file.
+    SharedFile,
 }
 
 /// Stores the configuration that will be used for creating a snapshot.
@@ -72,6 +75,9 @@ pub struct LoadSnapshotParams {
     pub resume_vm: bool,
     /// The network devices to override on load.
     pub network_overrides: Vec<NetworkOverride>,
+    /// When set to true, the load fails if the snapshotted vCPUs use CPU features which are not
+    /// supported by the host.
+    pub check_host_compatibility: bool,
 }
 
 /// Stores the configuration for loading a snapshot that is provided by the user.
@@ -97,6 +103,9 @@ pub struct LoadSnapshotConfig {
     /// The network devices to override on load.
     #[serde(default)]
     pub network_overrides: Vec<NetworkOverride>,
+    /// Whether or not to check that the host supports the CPU features of the snapshot.
+    #[serde(default)]
+    pub check_host_compatibility: bool,
 }
 
 /// Stores the configuration used for managing snapshot memory.
@@ -0,0 +1,113 @@
+// Copyright 2025 Amazon.com, Inc. or its affiliates. All Rights Reserved.
+// SPDX-License-Identifier: Apache-2.0
+
+use std::path::PathBuf;
+
+use serde::{Deserialize, Serialize};
+
+use crate::vmm_config::snapshot::{CreateSnapshotParams, SnapshotType};
+
+/// What Firecracker does when the guest stops refreshing the watchdog.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+pub enum WatchdogAction {
+    /// Stop the microVM as on a reset requested by the guest, exiting with `FcExitCode::Ok`.
+    Reset,
+    /// Stop the microVM, exiting with `FcExitCode::WatchdogExpired`.
+    #[default]
+    Shutdown,
+    /// Pause the microVM, so that it can be inspected or snapshotted.
+    Pause,
+    /// Take a full snapshot of the microVM, then stop it, exiting with
+    /// `FcExitCode::WatchdogExpired`.
+    SnapshotThenExit,
+}
+
+/// Errors associated with the configuration of the watchdog device.
+#[derive(Debug, thiserror::Error, displaydoc::Display, PartialEq, Eq)]
+pub enum WatchdogConfigError {
+    /// The watchdog device is only supported on x86_64.
+    UnsupportedArch,
+    /// The SnapshotThenExit action requires both the snapshot_path and mem_file_path fields.
+    MissingSnapshotPaths,
+    /// The snapshot_path and mem_file_path fields are only used by the SnapshotThenExit action.
+    UnexpectedSnapshotPaths,
+}
+
+/// This struct represents the strongly typed equivalent of the json body from watchdog device
+/// related requests.
+#[derive(Debug, Default, Clone, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct WatchdogConfig {
+    /// Action taken when the watchdog expires.
+    #[serde(default)]
+    pub action: WatchdogAction,
+    /// Path of the file the microVM state is saved to by the `SnapshotThenExit` action.
+    #[serde(default)]
+    pub snapshot_path: Option<PathBuf>,
+    /// Path of the file the guest memory is saved to by the `SnapshotThenExit` action.
+    #[serde(default)]
+    pub mem_file_path: Option<PathBuf>,
+}
+
+impl WatchdogConfig {
+    /// Checks that the snapshot paths are provided if, and only if, they are used by the action.
+    pub fn validate(&self) -> Result<(), WatchdogConfigError> {
+        let has_paths = self.snapshot_path.is_some() || self.mem_file_path.is_some();
+        match self.action {
+            WatchdogAction::SnapshotThenExit if self.snapshot_params().is_none() => {
+                Err(WatchdogConfigError::MissingSnapshotPaths)
+            }
+            WatchdogAction::SnapshotThenExit => Ok(()),
+            _ if has_paths => Err(WatchdogConfigError::UnexpectedSnapshotPaths),
+            _ => Ok(()),
+        }
+    }
+
+    /// Returns the parameters of the full snapshot taken by the `SnapshotThenExit` action.
+    pub fn snapshot_params(&self) -> Option<CreateSnapshotParams> {
+        Some(CreateSnapshotParams {
+            snapshot_type: SnapshotType::Full,
+            snapshot_path: self.snapshot_path.clone()?,
+            mem_file_path: self.mem_file_path.clone()?,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_watchdog_config() {
+        let config: WatchdogConfig = serde_json::from_str("{}").unwrap();
+        assert_eq!(config.action, WatchdogAction::Shutdown);
+        config.validate().unwrap();
+        serde_json::from_str::<WatchdogConfig>(r#"{"action": "Reboot"}"#).unwrap_err();
+        serde_json::from_str::<WatchdogConfig>(r#"{"on_expiry": "Pause"}"#).unwrap_err();
+
+        let config: WatchdogConfig =
+            serde_json::from_str(r#"{"action": "Pause", "snapshot_path": "snap"}"#).unwrap();
+        assert_eq!(
+            config.validate(),
+            Err(WatchdogConfigError::UnexpectedSnapshotPaths)
+        );
+
+        let config: WatchdogConfig =
+            serde_json::from_str(r#"{"action": "SnapshotThenExit", "snapshot_path": "snap"}"#)
+                .unwrap();
+        assert_eq!(
+            config.validate(),
+            Err(WatchdogConfigError::MissingSnapshotPaths)
+        );
+
+        let config: WatchdogConfig = serde_json::from_str(
+            r#"{"action": "SnapshotThenExit", "snapshot_path": "snap", "mem_file_path": "mem"}"#,
+        )
+        .unwrap();
+        config.validate().unwrap();
+        let params = config.snapshot_params().unwrap();
+        assert_eq!(params.snapshot_type, SnapshotType::Full);
+        assert_eq!(params.snapshot_path, PathBuf::from("snap"));
+        assert_eq!(params.mem_file_path, PathBuf::from("mem"));
+    }
+}
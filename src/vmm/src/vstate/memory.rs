@@ -23,6 +23,7 @@ use vmm_sys_util::errno;
 use crate::DirtyBitmap;
 use crate::utils::{get_page_size, u64_to_usize};
 use crate::vmm_config::machine_config::HugePageConfig;
+use crate::vstate::memory_metrics;
 
 /// Type of GuestMemoryMmap.
 pub type GuestMemoryMmap = vm_memory::GuestMemoryMmap<Option<AtomicBitmap>>;
@@ -46,6 +47,10 @@ pub enum MemoryError {
     Memfd(memfd::Error),
     /// Cannot resize memfd file: {0}
     MemfdSetLen(std::io::Error),
+    /// Cannot size the file backing guest memory: {0}
+    BackendFile(std::io::Error),
+    /// Cannot advise guest memory to use transparent huge pages: {0}
+    Madvise(std::io::Error),
     /// Total sum of memory regions exceeds largest possible file offset
     OffsetTooLarge,
 }
@@ -54,11 +59,10 @@ pub enum MemoryError {
 pub fn create(
     regions: impl Iterator<Item = (GuestAddress, usize)>,
     mmap_flags: libc::c_int,
-    file: Option<File>,
+    file: Option<Arc<File>>,
     track_dirty_pages: bool,
 ) -> Result<Vec<GuestRegionMmap>, MemoryError> {
     let mut offset = 0;
-    let file = file.map(Arc::new);
     regions
         .map(|(start, size)| {
             let mut builder = MmapRegionBuilder::new_with_bitmap(
@@ -91,19 +95,52 @@ pub fn create(
         .collect::<Result<Vec<_>, _>>()
 }
 
-/// Creates a GuestMemoryMmap with `size` in MiB backed by a memfd.
+/// Creates a GuestMemoryMmap with `size` in MiB backed by memfds.
 pub fn memfd_backed(
     regions: &[(GuestAddress, usize)],
     track_dirty_pages: bool,
     huge_pages: HugePageConfig,
+) -> Result<Vec<GuestRegionMmap>, MemoryError> {
+    let mut guest_regions = Vec::with_capacity(regions.len());
+    // Each region is backed by its own memfd, mapped from offset 0. The size of hugetlbfs files
+    // and the offset of their mappings must be multiples of the huge page size, which regions
+    // don't necessarily are with 1GB pages (e.g. the region below the 32-bit MMIO gap on x86_64).
+    for &(start, size) in regions {
+        let memfd_size = size.next_multiple_of(huge_pages.page_size()) as u64;
+        let memfd_file = create_memfd(memfd_size, huge_pages.into())?.into_file();
+
+        guest_regions.extend(create(
+            std::iter::once((start, size)),
+            libc::MAP_SHARED | huge_pages.mmap_flags(),
+            Some(Arc::new(memfd_file)),
+            track_dirty_pages,
+        )?);
+    }
+
+    if huge_pages.is_transparent() {
+        advise_transparent_huge_pages(&guest_regions)?;
+    }
+
+    Ok(guest_regions)
+}
+
+/// Creates a GuestMemoryMmap mapping shared a `file` provided by the user, which is extended to
+/// the size of the guest memory if it is smaller.
+pub fn shared_file(
+    file: Arc<File>,
+    regions: &[(GuestAddress, usize)],
+    track_dirty_pages: bool,
 ) -> Result<Vec<GuestRegionMmap>, MemoryError> {
     let size = regions.iter().map(|&(_, size)| size as u64).sum();
-    let memfd_file = create_memfd(size, huge_pages.into())?.into_file();
+    let file_size = file.metadata().map_err(MemoryError::BackendFile)?.len();
+    if file_size < size {
+        file.set_len(size).map_err(MemoryError::BackendFile)?;
+    }
 
     create(
         regions.iter().copied(),
-        libc::MAP_SHARED | huge_pages.mmap_flags(),
-        Some(memfd_file),
+        libc::MAP_SHARED,
+        Some(file),
         track_dirty_pages,
     )
 }
@@ -114,12 +151,39 @@ pub fn anonymous(
     track_dirty_pages: bool,
     huge_pages: HugePageConfig,
 ) -> Result<Vec<GuestRegionMmap>, MemoryError> {
-    create(
+    let guest_regions = create(
         regions,
         libc::MAP_PRIVATE | libc::MAP_ANONYMOUS | huge_pages.mmap_flags(),
         None,
         track_dirty_pages,
-    )
+    )?;
+
+    if huge_pages.is_transparent() {
+        advise_transparent_huge_pages(&guest_regions)?;
+    }
+
+    Ok(guest_regions)
+}
+
+/// Advises the kernel to back the given guest memory regions by transparent huge pages, and
+/// starts reporting how much of them actually is in the metrics.
+fn advise_transparent_huge_pages(guest_regions: &[GuestRegionMmap]) -> Result<(), MemoryError> {
+    for region in guest_regions {
+        // SAFETY: The address and length describe the mapping owned by `region`.
+        let ret = unsafe {
+            libc::madvise(
+                region.as_ptr().cast(),
+                u64_to_usize(region.len()),
+                libc::MADV_HUGEPAGE,
+            )
+        };
+        if ret < 0 {
+            return Err(MemoryError::Madvise(std::io::Error::last_os_error()));
+        }
+    }
+
+    memory_metrics::track_transparent_huge_pages(guest_regions);
+    Ok(())
 }
 
 /// Creates a GuestMemoryMmap given a `file` containing the data
@@ -129,7 +193,12 @@ pub fn snapshot_file(
     regions: impl Iterator<Item = (GuestAddress, usize)>,
     track_dirty_pages: bool,
 ) -> Result<Vec<GuestRegionMmap>, MemoryError> {
-    create(regions, libc::MAP_PRIVATE, Some(file), track_dirty_pages)
+    create(
+        regions,
+        libc::MAP_PRIVATE,
+        Some(Arc::new(file)),
+        track_dirty_pages,
+    )
 }
 
 /// Defines the interface for snapshotting memory.
@@ -376,6 +445,18 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_anonymous_transparent_huge_pages() {
+        let regions = [
+            (GuestAddress(0), 0x40_0000),
+            (GuestAddress(0x100_0000), 0x40_0000),
+        ];
+
+        let guest_memory =
+            anonymous(regions.into_iter(), false, HugePageConfig::Transparent).unwrap();
+        assert_eq!(guest_memory.len(), 2);
+    }
+
     #[test]
     fn test_mark_dirty() {
         let page_size = get_page_size().unwrap();
@@ -709,6 +790,26 @@ mod tests {
         });
     }
 
+    #[test]
+    fn test_memfd_backed() {
+        let page_size = get_page_size().unwrap();
+        let regions = [
+            (GuestAddress(0), page_size),
+            (GuestAddress(page_size as u64 * 2), page_size * 3),
+        ];
+
+        // Each region is mapped from the start of its own memfd.
+        let guest_regions = memfd_backed(&regions, false, HugePageConfig::None).unwrap();
+        assert_eq!(guest_regions.len(), 2);
+        for (region, &(start, size)) in guest_regions.iter().zip(regions.iter()) {
+            assert_eq!(region.start_addr(), start);
+            assert_eq!(region.len(), size as u64);
+            let file_offset = region.file_offset().unwrap();
+            assert_eq!(file_offset.start(), 0);
+            assert_eq!(file_offset.file().metadata().unwrap().len(), size as u64);
+        }
+    }
+
     #[test]
     fn test_create_memfd() {
         let size_bytes = mib_to_bytes(1) as u64;
@@ -722,4 +823,37 @@ mod tests {
         seals.insert(memfd::FileSeal::SealGrow);
         memfd.add_seals(&seals).unwrap_err();
     }
+
+    #[test]
+    fn test_shared_file() {
+        let page_size = get_page_size().unwrap();
+        let regions = [
+            (GuestAddress(0), page_size),
+            (GuestAddress(page_size as u64 * 2), page_size),
+        ];
+        let file = Arc::new(TempFile::new().unwrap().into_file());
+
+        // The file is extended to the size of the guest memory.
+        let guest_memory =
+            GuestMemoryMmap::from_regions(shared_file(file.clone(), &regions, false).unwrap())
+                .unwrap();
+        assert_eq!(file.metadata().unwrap().len(), page_size as u64 * 2);
+
+        // Guest writes land in the file, right after the previous regions.
+        guest_memory
+            .write_obj(0xdead_beef_u32, GuestAddress(page_size as u64 * 2))
+            .unwrap();
+        let mut content = vec![0u8; page_size * 2];
+        (&*file).seek(SeekFrom::Start(0)).unwrap();
+        (&*file).read_exact(&mut content).unwrap();
+        assert_eq!(
+            content[page_size..page_size + 4],
+            0xdead_beef_u32.to_ne_bytes()
+        );
+
+        // Larger files are left untouched.
+        file.set_len(page_size as u64 * 4).unwrap();
+        shared_file(file.clone(), &regions, false).unwrap();
+        assert_eq!(file.metadata().unwrap().len(), page_size as u64 * 4);
+    }
 }
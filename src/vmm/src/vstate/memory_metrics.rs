@@ -0,0 +1,139 @@
+// Copyright 2025 Amazon.com, Inc. or its affiliates. All Rights Reserved.
+// SPDX-License-Identifier: Apache-2.0
+
+//! Defines the metrics system for guest memory.
+//!
+//! # Metrics format
+//! The metrics are flushed in JSON when requested by vmm::logger::metrics::METRICS.write().
+//!
+//! ## JSON example with metrics:
+//! ```json
+//!  "memory": {
+//!     "thp_backed_bytes": 2147483648
+//!  }
+//! ```
+//! `thp_backed_bytes` is the amount of guest memory currently backed by transparent huge pages,
+//! as accounted by the `AnonHugePages` fields of `/proc/self/smaps`. It is only non zero when
+//! guest memory is configured to use transparent huge pages.
+//!
+//! Unlike the other metrics, it is not accumulated over time but sampled at flush time.
+
+use std::sync::Mutex;
+
+use serde::ser::SerializeMap;
+use serde::{Serialize, Serializer};
+
+use crate::logger::warn;
+use crate::utils::u64_to_usize;
+use crate::vstate::memory::{GuestMemoryRegion, GuestRegionMmap};
+
+/// Host address ranges of the guest memory regions advised to use transparent huge pages.
+static THP_REGIONS: Mutex<Vec<(usize, usize)>> = Mutex::new(Vec::new());
+
+/// Starts reporting how much of `guest_regions` is backed by transparent huge pages.
+pub(crate) fn track_transparent_huge_pages(guest_regions: &[GuestRegionMmap]) {
+    let ranges = guest_regions
+        .iter()
+        .map(|region| (region.as_ptr() as usize, u64_to_usize(region.len())))
+        .collect();
+
+    *THP_REGIONS.lock().expect("Poisoned lock") = ranges;
+}
+
+/// Called by METRICS.flush(), this function facilitates serialization of guest memory metrics.
+pub fn flush_metrics<S: Serializer>(serializer: S) -> Result<S::Ok, S::Error> {
+    let mut seq = serializer.serialize_map(Some(1))?;
+    seq.serialize_entry(
+        "memory",
+        &GuestMemoryMetrics {
+            thp_backed_bytes: thp_backed_bytes(),
+        },
+    )?;
+    seq.end()
+}
+
+/// Guest memory associated metrics.
+#[derive(Debug, Default, Serialize)]
+struct GuestMemoryMetrics {
+    /// Amount of guest memory backed by transparent huge pages, in bytes.
+    thp_backed_bytes: u64,
+}
+
+fn thp_backed_bytes() -> u64 {
+    // Metrics can be written from the signal handlers, so never wait for the lock.
+    let ranges = match THP_REGIONS.try_lock() {
+        Ok(ranges) if !ranges.is_empty() => ranges.clone(),
+        _ => return 0,
+    };
+
+    match std::fs::read_to_string("/proc/self/smaps") {
+        Ok(smaps) => anon_huge_pages_bytes(&smaps, &ranges),
+        Err(err) => {
+            warn!("Cannot read /proc/self/smaps: {}", err);
+            0
+        }
+    }
+}
+
+/// Sums up the `AnonHugePages` of the mappings in `smaps` starting within one of `ranges`.
+fn anon_huge_pages_bytes(smaps: &str, ranges: &[(usize, usize)]) -> u64 {
+    let mut in_ranges = false;
+    let mut total = 0;
+
+    for line in smaps.lines() {
+        if let Some(size) = line.strip_prefix("AnonHugePages:") {
+            if in_ranges {
+                let kib = size.trim().trim_end_matches("kB").trim();
+                total += kib.parse::<u64>().unwrap_or(0) * 1024;
+            }
+        } else if let Some((start, _)) = line
+            .split_once(' ')
+            .and_then(|(addresses, _)| addresses.split_once('-'))
+        {
+            // The mapping headers start with their address range, e.g. `7f12c0000000-7f1300000000`.
+            if let Ok(start) = usize::from_str_radix(start, 16) {
+                in_ranges = ranges
+                    .iter()
+                    .any(|&(base, len)| base <= start && start < base + len);
+            }
+        }
+    }
+
+    total
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_anon_huge_pages_bytes() {
+        let smaps = "\
+7f0000000000-7f0000400000 rw-p 00000000 00:00 0
+Size:               4096 kB
+AnonHugePages:      2048 kB
+VmFlags: rd wr mr mw me ac sd hg
+7f0000400000-7f0000800000 rw-p 00000000 00:00 0
+Size:               4096 kB
+AnonHugePages:      4096 kB
+VmFlags: rd wr mr mw me ac sd hg
+7f1000000000-7f1000200000 rw-p 00000000 00:00 0
+Size:               2048 kB
+AnonHugePages:      2048 kB
+VmFlags: rd wr mr mw me ac sd
+";
+
+        // Mappings split within a guest memory region are all accounted.
+        assert_eq!(
+            anon_huge_pages_bytes(smaps, &[(0x7f00_0000_0000, 0x80_0000)]),
+            6 << 20
+        );
+        assert_eq!(
+            anon_huge_pages_bytes(smaps, &[(0x7f00_0040_0000, 0x40_0000)]),
+            4 << 20
+        );
+        // Mappings outside of guest memory are not.
+        assert_eq!(anon_huge_pages_bytes(smaps, &[(0x1000, 0x1000)]), 0);
+        assert_eq!(anon_huge_pages_bytes(smaps, &[]), 0);
+    }
+}
@@ -5,6 +5,10 @@
 pub mod kvm;
 /// Module with GuestMemory implementation.
 pub mod memory;
+/// Module with the guest memory metrics.
+pub mod memory_metrics;
+/// Module with the NUMA placement of the microVM.
+pub mod numa;
 /// Module with Vcpu implementation.
 pub mod vcpu;
 /// Module with Vm implementation.
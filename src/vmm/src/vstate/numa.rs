@@ -0,0 +1,301 @@
+// Copyright 2025 Amazon.com, Inc. or its affiliates. All Rights Reserved.
+// SPDX-License-Identifier: Apache-2.0
+
+//! Placement of the NUMA nodes of a microVM on the NUMA nodes of the host.
+
+use std::io;
+
+use crate::utils::{mib_to_bytes, u64_to_usize, usize_to_u64};
+use crate::vmm_config::machine_config::NumaConfig;
+use crate::vstate::memory::{
+    Address, GuestAddress, GuestMemory, GuestMemoryMmap, GuestMemoryRegion,
+};
+use crate::vstate::vcpu::Vcpu;
+
+/// Move the pages already faulted in to the node the memory is bound to.
+const MPOL_MF_MOVE: libc::c_ulong = 1 << 1;
+/// Number of CPUs a `cpu_set_t` can hold.
+const CPU_SETSIZE: usize = 8 * size_of::<libc::cpu_set_t>();
+
+/// Errors associated with the NUMA placement of a microVM.
+#[derive(Debug, thiserror::Error, displaydoc::Display)]
+pub enum NumaError {
+    /// Cannot bind the memory of NUMA node {0} to host NUMA node {1}: {2}
+    BindMemory(usize, u32, io::Error),
+    /// Cannot read the CPUs of host NUMA node {0}: {1}
+    HostNodeCpus(u32, io::Error),
+    /// Invalid CPU list of host NUMA node {0}: {1}
+    ParseCpuList(u32, String),
+    /// Host NUMA node {0} has no CPUs to pin the vCPUs to.
+    NoHostNodeCpus(u32),
+}
+
+/// A range of guest memory belonging to a NUMA node of the microVM.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct NodeMemoryRange {
+    /// Index of the NUMA node.
+    pub node: usize,
+    /// Guest physical address the range starts at.
+    pub start: GuestAddress,
+    /// Length of the range, in bytes.
+    pub len: u64,
+}
+
+/// Splits the guest memory among the NUMA nodes, in order. Since the guest memory regions can
+/// be discontiguous, a node can span multiple ranges.
+pub fn node_memory_ranges(
+    guest_memory: &GuestMemoryMmap,
+    numa: &NumaConfig,
+) -> Vec<NodeMemoryRange> {
+    let mut nodes = numa
+        .nodes
+        .iter()
+        .map(|node| usize_to_u64(mib_to_bytes(node.mem_size_mib)))
+        .enumerate();
+    let mut current = nodes.next();
+    let mut ranges = Vec::new();
+
+    for region in guest_memory.iter() {
+        let mut start = region.start_addr();
+        let mut region_left = region.len();
+
+        while region_left > 0 {
+            let Some((node, node_left)) = current.as_mut() else {
+                return ranges;
+            };
+            let len = region_left.min(*node_left);
+            ranges.push(NodeMemoryRange {
+                node: *node,
+                start,
+                len,
+            });
+
+            start = start.unchecked_add(len);
+            region_left -= len;
+            *node_left -= len;
+            if *node_left == 0 {
+                current = nodes.next();
+            }
+        }
+    }
+
+    ranges
+}
+
+/// Binds the guest memory of the NUMA nodes to their host NUMA nodes, and sets the host CPUs
+/// their vCPUs are to be pinned to.
+pub fn place_numa_nodes(
+    guest_memory: &GuestMemoryMmap,
+    vcpus: &mut [Vcpu],
+    numa: &NumaConfig,
+) -> Result<(), NumaError> {
+    for range in node_memory_ranges(guest_memory, numa) {
+        if let Some(host_node) = numa.nodes[range.node].host_node {
+            // The ranges are within the guest memory regions by construction.
+            let host_addr = guest_memory.get_host_address(range.start).unwrap();
+            bind_memory(host_addr, u64_to_usize(range.len), host_node)
+                .map_err(|err| NumaError::BindMemory(range.node, host_node, err))?;
+        }
+    }
+
+    for vcpu in vcpus.iter_mut() {
+        let host_node = numa
+            .vcpu_node(vcpu.kvm_vcpu.index)
+            .and_then(|node| numa.nodes[node].host_node);
+        if let Some(host_node) = host_node {
+            vcpu.set_host_cpus(host_node_cpus(host_node)?);
+        }
+    }
+
+    Ok(())
+}
+
+/// Binds the memory at `addr` to `host_node`, moving the pages already faulted in.
+fn bind_memory(addr: *mut u8, len: usize, host_node: u32) -> io::Result<()> {
+    let host_node = u64_to_usize(u64::from(host_node));
+    let mut nodemask = vec![0u64; host_node / 64 + 1];
+    nodemask[host_node / 64] |= 1u64 << (host_node % 64);
+
+    // SAFETY: The address and length describe guest memory mapped by us, and the node mask is
+    // valid for the number of nodes passed along.
+    let ret = unsafe {
+        libc::syscall(
+            libc::SYS_mbind,
+            addr,
+            len,
+            libc::c_ulong::from(libc::MPOL_BIND.unsigned_abs()),
+            nodemask.as_ptr(),
+            nodemask.len() * 64 + 1,
+            MPOL_MF_MOVE,
+        )
+    };
+    if ret < 0 {
+        return Err(io::Error::last_os_error());
+    }
+
+    Ok(())
+}
+
+/// Returns the CPUs of `host_node`.
+fn host_node_cpus(host_node: u32) -> Result<Vec<usize>, NumaError> {
+    let cpulist =
+        std::fs::read_to_string(format!("/sys/devices/system/node/node{host_node}/cpulist"))
+            .map_err(|err| NumaError::HostNodeCpus(host_node, err))?;
+    let cpus = parse_cpu_list(&cpulist)
+        .ok_or_else(|| NumaError::ParseCpuList(host_node, cpulist.trim().to_string()))?;
+    if cpus.is_empty() {
+        return Err(NumaError::NoHostNodeCpus(host_node));
+    }
+
+    Ok(cpus)
+}
+
+/// Parses a list of CPUs in the sysfs format, e.g. `0-3,8,10-11`.
+fn parse_cpu_list(cpulist: &str) -> Option<Vec<usize>> {
+    let mut cpus = Vec::new();
+
+    for item in cpulist.trim().split(',').filter(|item| !item.is_empty()) {
+        let (first, last) = match item.split_once('-') {
+            Some((first, last)) => (first.parse().ok()?, last.parse().ok()?),
+            None => {
+                let cpu = item.parse().ok()?;
+                (cpu, cpu)
+            }
+        };
+        if last < first {
+            return None;
+        }
+        cpus.extend(first..=last);
+    }
+
+    Some(cpus)
+}
+
+/// Pins `thread` to `cpus`.
+pub(crate) fn pin_thread(thread: libc::pthread_t, cpus: &[usize]) -> io::Result<()> {
+    // SAFETY: `cpu_set_t` is a bitmask, for which all zeroes is a valid value.
+    let mut cpu_set: libc::cpu_set_t = unsafe { std::mem::zeroed() };
+    for &cpu in cpus {
+        if cpu >= CPU_SETSIZE {
+            return Err(io::Error::from_raw_os_error(libc::EINVAL));
+        }
+        // SAFETY: The CPU was checked to fit in the set.
+        unsafe { libc::CPU_SET(cpu, &mut cpu_set) };
+    }
+
+    // SAFETY: The thread is alive, and the set is valid for its size.
+    let ret =
+        unsafe { libc::pthread_setaffinity_np(thread, size_of::<libc::cpu_set_t>(), &cpu_set) };
+    if ret != 0 {
+        return Err(io::Error::from_raw_os_error(ret));
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::multi_region_mem;
+    use crate::vmm_config::machine_config::NumaNodeConfig;
+
+    fn numa_config(sizes_mib: &[usize]) -> NumaConfig {
+        NumaConfig {
+            nodes: sizes_mib
+                .iter()
+                .map(|&mem_size_mib| NumaNodeConfig {
+                    mem_size_mib,
+                    ..Default::default()
+                })
+                .collect(),
+            expose_topology: false,
+        }
+    }
+
+    #[test]
+    fn test_node_memory_ranges() {
+        const MIB: u64 = 1 << 20;
+
+        // A single region shared by the nodes.
+        let guest_memory = multi_region_mem(&[(GuestAddress(0), 4 << 20)]);
+        assert_eq!(
+            node_memory_ranges(&guest_memory, &numa_config(&[1, 3])),
+            [
+                NodeMemoryRange {
+                    node: 0,
+                    start: GuestAddress(0),
+                    len: MIB,
+                },
+                NodeMemoryRange {
+                    node: 1,
+                    start: GuestAddress(MIB),
+                    len: 3 * MIB,
+                },
+            ]
+        );
+
+        // A node spanning discontiguous regions.
+        let guest_memory = multi_region_mem(&[
+            (GuestAddress(0), 2 << 20),
+            (GuestAddress(16 * MIB), 2 << 20),
+        ]);
+        assert_eq!(
+            node_memory_ranges(&guest_memory, &numa_config(&[1, 2, 1])),
+            [
+                NodeMemoryRange {
+                    node: 0,
+                    start: GuestAddress(0),
+                    len: MIB,
+                },
+                NodeMemoryRange {
+                    node: 1,
+                    start: GuestAddress(MIB),
+                    len: MIB,
+                },
+                NodeMemoryRange {
+                    node: 1,
+                    start: GuestAddress(16 * MIB),
+                    len: MIB,
+                },
+                NodeMemoryRange {
+                    node: 2,
+                    start: GuestAddress(17 * MIB),
+                    len: MIB,
+                },
+            ]
+        );
+
+        assert_eq!(node_memory_ranges(&guest_memory, &numa_config(&[])), []);
+    }
+
+    #[test]
+    fn test_parse_cpu_list() {
+        assert_eq!(parse_cpu_list("0-3\n"), Some(vec![0, 1, 2, 3]));
+        assert_eq!(parse_cpu_list("0-1,8,10-11"), Some(vec![0, 1, 8, 10, 11]));
+        // Memory only nodes have no CPUs.
+        assert_eq!(parse_cpu_list("\n"), Some(vec![]));
+        assert_eq!(parse_cpu_list("3-1"), None);
+        assert_eq!(parse_cpu_list("0-a"), None);
+    }
+
+    #[test]
+    fn test_pin_thread() {
+        // SAFETY: Always safe to call.
+        let thread = unsafe { libc::pthread_self() };
+        // SAFETY: `cpu_set_t` is a bitmask, for which all zeroes is a valid value.
+        let mut initial: libc::cpu_set_t = unsafe { std::mem::zeroed() };
+        // SAFETY: The set is valid for its size.
+        let ret = unsafe {
+            libc::pthread_getaffinity_np(thread, size_of::<libc::cpu_set_t>(), &mut initial)
+        };
+        assert_eq!(ret, 0);
+        let cpus: Vec<usize> = (0..CPU_SETSIZE)
+            // SAFETY: The CPU fits in the set.
+            .filter(|&cpu| unsafe { libc::CPU_ISSET(cpu, &initial) })
+            .collect();
+
+        pin_thread(thread, &cpus[..1]).unwrap();
+        pin_thread(thread, &cpus).unwrap();
+        pin_thread(thread, &[CPU_SETSIZE]).unwrap_err();
+    }
+}
@@ -8,6 +8,7 @@
 use std::cell::Cell;
 #[cfg(feature = "gdb")]
 use std::os::fd::AsRawFd;
+use std::os::unix::thread::JoinHandleExt;
 use std::sync::atomic::{Ordering, fence};
 use std::sync::mpsc::{Receiver, Sender, TryRecvError, channel};
 use std::sync::{Arc, Barrier};
@@ -26,11 +27,12 @@ use crate::FcExitCode;
 pub use crate::arch::{KvmVcpu, KvmVcpuConfigureError, KvmVcpuError, Peripherals, VcpuState};
 use crate::cpu_config::templates::{CpuConfiguration, GuestConfigError};
 #[cfg(feature = "gdb")]
-use crate::gdb::target::{GdbTargetError, get_raw_tid};
+use crate::gdb::target::{GdbEvent, GdbTargetError, get_raw_tid};
 use crate::logger::{IncMetric, METRICS};
 use crate::seccomp::{BpfProgram, BpfProgramRef};
 use crate::utils::signal::{Killable, register_signal_handler, sigrtmin};
 use crate::utils::sm::StateMachine;
+use crate::vstate::numa::pin_thread;
 use crate::vstate::vm::Vm;
 
 /// Signal number (SIGRTMIN) used to kick Vcpus.
@@ -55,6 +57,8 @@ pub enum VcpuError {
     VcpuTlsInit,
     /// Vcpu not present in TLS
     VcpuTlsNotPresent,
+    /// Cannot install seccomp filter: {0}
+    SeccompFilter(crate::seccomp::InstallationError),
     /// Error with gdb request sent
     #[cfg(feature = "gdb")]
     GdbRequest(GdbTargetError),
@@ -67,6 +71,8 @@ pub struct VcpuConfig {
     pub vcpu_count: u8,
     /// Enable simultaneous multithreading in the CPUID configuration.
     pub smt: bool,
+    /// Number of guest CPU sockets the vCPUs are evenly spread across.
+    pub sockets: u8,
     /// Configuration for vCPU
     pub cpu_config: CpuConfiguration,
 }
@@ -76,7 +82,7 @@ type VcpuCell = Cell<Option<*mut Vcpu>>;
 
 /// Error type for [`Vcpu::start_threaded`].
 #[derive(Debug, derive_more::From, thiserror::Error)]
-#[error("Failed to spawn vCPU thread: {0}")]
+#[error("Failed to spawn or pin vCPU thread: {0}")]
 pub struct StartThreadedError(std::io::Error);
 
 /// Error type for [`Vcpu::copy_kvm_vcpu_fd`].
@@ -100,7 +106,7 @@ pub struct Vcpu {
     exit_evt: EventFd,
     /// Debugger emitter for gdb events
     #[cfg(feature = "gdb")]
-    gdb_event: Option<Sender<usize>>,
+    gdb_event: Option<Sender<GdbEvent>>,
     /// The receiving end of events channel owned by the vcpu side.
     event_receiver: Receiver<VcpuEvent>,
     /// The transmitting end of the events channel which will be given to the handler.
@@ -109,6 +115,8 @@ pub struct Vcpu {
     response_receiver: Option<Receiver<VcpuResponse>>,
     /// The transmitting end of the responses channel owned by the vcpu side.
     response_sender: Sender<VcpuResponse>,
+    /// The host CPUs the vcpu thread is pinned to.
+    host_cpus: Option<Vec<usize>>,
 }
 
 impl Vcpu {
@@ -215,6 +223,7 @@ impl Vcpu {
             #[cfg(feature = "gdb")]
             gdb_event: None,
             kvm_vcpu,
+            host_cpus: None,
         })
     }
 
@@ -223,9 +232,14 @@ impl Vcpu {
         self.kvm_vcpu.peripherals.mmio_bus = Some(mmio_bus);
     }
 
+    /// Pins the vcpu thread to the given host CPUs once started.
+    pub fn set_host_cpus(&mut self, host_cpus: Vec<usize>) {
+        self.host_cpus = Some(host_cpus);
+    }
+
     /// Attaches the fields required for debugging
     #[cfg(feature = "gdb")]
-    pub fn attach_debug_info(&mut self, gdb_event: Sender<usize>) {
+    pub fn attach_debug_info(&mut self, gdb_event: Sender<GdbEvent>) {
         self.gdb_event = Some(gdb_event);
     }
 
@@ -250,6 +264,7 @@ impl Vcpu {
     ) -> Result<VcpuHandle, StartThreadedError> {
         let event_sender = self.event_sender.take().expect("vCPU already started");
         let response_receiver = self.response_receiver.take().unwrap();
+        let host_cpus = self.host_cpus.take();
         let vcpu_thread = thread::Builder::new()
             .name(format!("fc_vcpu {}", self.kvm_vcpu.index))
             .spawn(move || {
@@ -261,6 +276,10 @@ impl Vcpu {
                 self.run(filter);
             })?;
 
+        if let Some(host_cpus) = host_cpus {
+            pin_thread(vcpu_thread.as_pthread_t(), &host_cpus)?;
+        }
+
         Ok(VcpuHandle::new(
             event_sender,
             response_receiver,
@@ -369,6 +388,7 @@ impl Vcpu {
                     )))
                     .expect("vcpu channel unexpectedly closed");
             }
+            Ok(VcpuEvent::ApplySeccompFilter(filter)) => self.apply_seccomp_filter(&filter),
             Ok(VcpuEvent::Finish) => return StateMachine::finish(),
             // Unhandled exit of the other end.
             Err(TryRecvError::Disconnected) => {
@@ -440,6 +460,10 @@ impl Vcpu {
 
                 StateMachine::next(Self::paused)
             }
+            Ok(VcpuEvent::ApplySeccompFilter(filter)) => {
+                self.apply_seccomp_filter(&filter);
+                StateMachine::next(Self::paused)
+            }
             Ok(VcpuEvent::Finish) => StateMachine::finish(),
             // Unhandled exit of the other end.
             Err(_) => {
@@ -449,6 +473,17 @@ impl Vcpu {
         }
     }
 
+    // Stacks an additional seccomp filter on top of the ones already installed on this thread.
+    fn apply_seccomp_filter(&self, filter: BpfProgramRef) {
+        let response = match crate::seccomp::apply_filter(filter) {
+            Ok(()) => VcpuResponse::SeccompFilterApplied,
+            Err(err) => VcpuResponse::Error(VcpuError::SeccompFilter(err)),
+        };
+        self.response_sender
+            .send(response)
+            .expect("vcpu channel unexpectedly closed");
+    }
+
     // Transition to the exited state and finish on command.
     fn exit(&mut self, exit_code: FcExitCode) -> StateMachine<Self> {
         // To avoid cycles, all teardown paths take the following route:
@@ -503,10 +538,13 @@ impl Vcpu {
                 Ok(VcpuEmulation::Interrupted)
             }
             #[cfg(feature = "gdb")]
-            Ok(VcpuExit::Debug(_)) => {
+            Ok(VcpuExit::Debug(debug_exit)) => {
                 if let Some(gdb_event) = &self.gdb_event {
                     gdb_event
-                        .send(get_raw_tid(self.kvm_vcpu.index.into()))
+                        .send(GdbEvent {
+                            raw_tid: get_raw_tid(self.kvm_vcpu.index.into()),
+                            debug_exit,
+                        })
                         .expect("Unable to notify gdb event");
                 }
 
@@ -636,6 +674,8 @@ pub enum VcpuEvent {
     SaveState,
     /// Event to dump CPU configuration of a paused Vcpu.
     DumpCpuConfig,
+    /// Event to install an additional seccomp filter on the Vcpu thread.
+    ApplySeccompFilter(Arc<BpfProgram>),
 }
 
 /// List of responses that the Vcpu reports.
@@ -654,6 +694,8 @@ pub enum VcpuResponse {
     SavedState(Box<VcpuState>),
     /// Vcpu is in the state where CPU config is dumped.
     DumpedCpuConfig(Box<CpuConfiguration>),
+    /// Additional seccomp filter is installed on the Vcpu thread.
+    SeccompFilterApplied,
 }
 
 impl fmt::Debug for VcpuResponse {
@@ -667,6 +709,7 @@ impl fmt::Debug for VcpuResponse {
             Error(err) => write!(f, "VcpuResponse::Error({:?})", err),
             NotAllowed(reason) => write!(f, "VcpuResponse::NotAllowed({})", reason),
             DumpedCpuConfig(_) => write!(f, "VcpuResponse::DumpedCpuConfig"),
+            SeccompFilterApplied => write!(f, "VcpuResponse::SeccompFilterApplied"),
         }
     }
 }
@@ -900,11 +943,13 @@ pub(crate) mod tests {
             use crate::VcpuResponse::*;
             // Guard match with no wildcard to make sure we catch new enum variants.
             match self {
-                Paused | Resumed | Exited(_) => (),
+                Paused | Resumed | Exited(_) | SeccompFilterApplied => (),
                 Error(_) | NotAllowed(_) | SavedState(_) | DumpedCpuConfig(_) => (),
             };
             match (self, other) {
-                (Paused, Paused) | (Resumed, Resumed) => true,
+                (Paused, Paused)
+                | (Resumed, Resumed)
+                | (SeccompFilterApplied, SeccompFilterApplied) => true,
                 (Exited(code), Exited(other_code)) => code == other_code,
                 (NotAllowed(_), NotAllowed(_))
                 | (SavedState(_), SavedState(_))
@@ -970,6 +1015,8 @@ pub(crate) mod tests {
         let entry_point = EntryPoint {
             entry_addr: load_good_kernel(vm.guest_memory()),
             protocol: BootProtocol::LinuxBoot,
+            #[cfg(target_arch = "x86_64")]
+            setup_header: None,
         };
 
         #[cfg(target_arch = "x86_64")]
@@ -982,6 +1029,7 @@ pub(crate) mod tests {
                     &VcpuConfig {
                         vcpu_count: 1,
                         smt: false,
+                        sockets: 1,
                         cpu_config: CpuConfiguration {
                             cpuid: Cpuid::try_from(kvm.supported_cpuid.clone()).unwrap(),
                             msrs: BTreeMap::new(),
@@ -999,6 +1047,7 @@ pub(crate) mod tests {
                 &VcpuConfig {
                     vcpu_count: 1,
                     smt: false,
+                    sockets: 1,
                     cpu_config: crate::cpu_config::aarch64::CpuConfiguration::default(),
                 },
                 &kvm.optional_capabilities(),
@@ -1242,6 +1291,42 @@ pub(crate) mod tests {
         vcpu_handle.send_event(VcpuEvent::Finish).unwrap();
     }
 
+    #[test]
+    fn test_vcpu_apply_seccomp_filter() {
+        let (_vm, vcpu_handle, _) = vcpu_configured_for_boot();
+
+        // An empty filter is accepted while paused.
+        queue_event_expect_response(
+            &vcpu_handle,
+            VcpuEvent::ApplySeccompFilter(Arc::new(vec![])),
+            VcpuResponse::SeccompFilterApplied,
+        );
+
+        // And while running.
+        queue_event_expect_response(&vcpu_handle, VcpuEvent::Resume, VcpuResponse::Resumed);
+        queue_event_expect_response(
+            &vcpu_handle,
+            VcpuEvent::ApplySeccompFilter(Arc::new(vec![])),
+            VcpuResponse::SeccompFilterApplied,
+        );
+        queue_event_expect_response(&vcpu_handle, VcpuEvent::Pause, VcpuResponse::Paused);
+
+        // An invalid filter is reported back.
+        vcpu_handle
+            .send_event(VcpuEvent::ApplySeccompFilter(Arc::new(vec![0xFF; 1])))
+            .expect("Failed to send an event to vcpu.");
+        match vcpu_handle
+            .response_receiver()
+            .recv_timeout(RECV_TIMEOUT_SEC)
+            .expect("Could not receive a response from vcpu.")
+        {
+            VcpuResponse::Error(VcpuError::SeccompFilter(_)) => (),
+            _ => panic!("Got an unexpected response."),
+        }
+
+        vcpu_handle.send_event(VcpuEvent::Finish).unwrap();
+    }
+
     #[test]
     fn test_vcpu_rtsig_offset() {
         validate_signal_num(sigrtmin() + VCPU_RTSIG_OFFSET).unwrap();
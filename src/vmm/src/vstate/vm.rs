@@ -11,7 +11,7 @@ use std::io::Write;
 use std::path::Path;
 use std::sync::Arc;
 
-use kvm_bindings::{KVM_MEM_LOG_DIRTY_PAGES, kvm_userspace_memory_region};
+use kvm_bindings::{KVM_MEM_LOG_DIRTY_PAGES, KVM_MEM_READONLY, kvm_userspace_memory_region};
 use kvm_ioctls::VmFd;
 use vmm_sys_util::eventfd::EventFd;
 
@@ -21,7 +21,8 @@ use crate::persist::CreateSnapshotError;
 use crate::utils::u64_to_usize;
 use crate::vmm_config::snapshot::SnapshotType;
 use crate::vstate::memory::{
-    Address, GuestMemory, GuestMemoryExtension, GuestMemoryMmap, GuestMemoryRegion, GuestRegionMmap,
+    Address, GuestAddress, GuestMemory, GuestMemoryExtension, GuestMemoryMmap, GuestMemoryRegion,
+    GuestRegionMmap, MmapRegion,
 };
 use crate::vstate::vcpu::VcpuError;
 use crate::{DirtyBitmap, Vcpu, mem_size_mib};
@@ -32,6 +33,9 @@ pub struct VmCommon {
     /// The KVM file descriptor used to access this Vm.
     pub fd: VmFd,
     max_memslots: usize,
+    // Number of slots used by device memory. They are allocated from the last one downwards, as
+    // the slots of the guest memory regions are their index.
+    device_memslots: usize,
     /// The guest memory of this Vm.
     pub guest_memory: GuestMemoryMmap,
 }
@@ -100,6 +104,7 @@ impl Vm {
         Ok(VmCommon {
             fd,
             max_memslots: kvm.max_nr_memslots(),
+            device_memslots: 0,
             guest_memory: GuestMemoryMmap::default(),
         })
     }
@@ -143,7 +148,7 @@ impl Vm {
             .num_regions()
             .try_into()
             .map_err(|_| VmError::NotEnoughMemorySlots)?;
-        if next_slot as usize >= self.common.max_memslots {
+        if next_slot as usize + self.common.device_memslots >= self.common.max_memslots {
             return Err(VmError::NotEnoughMemorySlots);
         }
 
@@ -175,6 +180,42 @@ impl Vm {
         Ok(())
     }
 
+    /// Maps memory which isn't part of the guest memory, such as the backing file of a virtio-pmem
+    /// device, at `guest_addr` in the guest physical address space. The mapping must outlive the
+    /// VM.
+    pub fn register_device_memory(
+        &mut self,
+        guest_addr: GuestAddress,
+        mapping: &MmapRegion,
+        read_only: bool,
+    ) -> Result<(), VmError> {
+        let used_slots = self.guest_memory().num_regions() + self.common.device_memslots;
+        if used_slots >= self.common.max_memslots {
+            return Err(VmError::NotEnoughMemorySlots);
+        }
+        let slot = u32::try_from(self.common.max_memslots - 1 - self.common.device_memslots)
+            .map_err(|_| VmError::NotEnoughMemorySlots)?;
+
+        let memory_region = kvm_userspace_memory_region {
+            slot,
+            guest_phys_addr: guest_addr.raw_value(),
+            memory_size: mapping.size() as u64,
+            userspace_addr: mapping.as_ptr() as u64,
+            flags: if read_only { KVM_MEM_READONLY } else { 0 },
+        };
+
+        // SAFETY: Safe because the fd is a valid KVM file descriptor, and the caller keeps the
+        // mapping alive for as long as the VM.
+        unsafe {
+            self.fd()
+                .set_user_memory_region(memory_region)
+                .map_err(VmError::SetUserMemoryRegion)?;
+        }
+        self.common.device_memslots += 1;
+
+        Ok(())
+    }
+
     /// Gets a reference to the kvm file descriptor owned by this VM.
     pub fn fd(&self) -> &VmFd {
         &self.common.fd
@@ -329,6 +370,23 @@ pub(crate) mod tests {
         res.unwrap();
     }
 
+    #[test]
+    fn test_register_device_memory() {
+        let (_, mut vm) = setup_vm_with_memory(0x1000);
+        let mapping: MmapRegion = MmapRegionBuilder::new(0x1000).build().unwrap();
+
+        vm.register_device_memory(GuestAddress(0x10000), &mapping, true)
+            .unwrap();
+        // Device memory isn't part of the guest memory.
+        assert_eq!(vm.guest_memory().num_regions(), 1);
+        assert_eq!(vm.common.device_memslots, 1);
+
+        // Device memory can't overlap with the guest memory.
+        vm.register_device_memory(GuestAddress(0), &mapping, false)
+            .unwrap_err();
+        assert_eq!(vm.common.device_memslots, 1);
+    }
+
     #[test]
     fn test_too_many_regions() {
         let (kvm, mut vm) = setup_vm();
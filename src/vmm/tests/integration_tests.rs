@@ -2,6 +2,8 @@
 // SPDX-License-Identifier: Apache-2.0
 
 use std::io::{Seek, SeekFrom};
+use std::os::unix::fs::FileExt;
+use std::sync::{Arc, Mutex};
 use std::thread;
 use std::time::Duration;
 
@@ -18,6 +20,7 @@ use vmm::test_utils::mock_resources::{MockVmResources, NOISY_KERNEL_IMAGE};
 use vmm::test_utils::{create_vmm, default_vmm, default_vmm_no_boot};
 use vmm::vmm_config::balloon::BalloonDeviceConfig;
 use vmm::vmm_config::boot_source::BootSourceConfig;
+use vmm::vmm_config::coredump::CreateCoredumpParams;
 use vmm::vmm_config::drive::BlockDeviceConfig;
 use vmm::vmm_config::instance_info::{InstanceInfo, VmState};
 use vmm::vmm_config::machine_config::{MachineConfig, MachineConfigUpdate};
@@ -26,7 +29,8 @@ use vmm::vmm_config::snapshot::{
     CreateSnapshotParams, LoadSnapshotParams, MemBackendConfig, MemBackendType, SnapshotType,
 };
 use vmm::vmm_config::vsock::VsockDeviceConfig;
-use vmm::{DumpCpuConfigError, EventManager, FcExitCode};
+use vmm::vstate::memory::{Bytes, GuestMemory, GuestMemoryRegion};
+use vmm::{ApplySeccompFiltersError, DumpCpuConfigError, EventManager, FcExitCode, Vmm};
 use vmm_sys_util::tempfile::TempFile;
 
 #[test]
@@ -243,7 +247,12 @@ fn verify_create_snapshot(is_diff: bool) -> (TempFile, TempFile) {
     (snapshot_file, memory_file)
 }
 
-fn verify_load_snapshot(snapshot_file: TempFile, memory_file: TempFile) {
+fn load_snapshot(
+    snapshot_file: &TempFile,
+    memory_file: &TempFile,
+    backend_type: MemBackendType,
+    resume_vm: bool,
+) -> Arc<Mutex<Vmm>> {
     let mut event_manager = EventManager::new().unwrap();
     let empty_seccomp_filters = get_empty_filters();
     let mut vm_resources = VmResources::default();
@@ -260,15 +269,20 @@ fn verify_load_snapshot(snapshot_file: TempFile, memory_file: TempFile) {
             snapshot_path: snapshot_file.as_path().to_path_buf(),
             mem_backend: MemBackendConfig {
                 backend_path: memory_file.as_path().to_path_buf(),
-                backend_type: MemBackendType::File,
+                backend_type,
             },
             enable_diff_snapshots: false,
-            resume_vm: true,
+            resume_vm,
             network_overrides: vec![],
+            check_host_compatibility: false,
         }))
         .unwrap();
 
-    let vmm = preboot_api_controller.built_vmm.take().unwrap();
+    preboot_api_controller.built_vmm.take().unwrap()
+}
+
+fn verify_load_snapshot(snapshot_file: TempFile, memory_file: TempFile) {
+    let vmm = load_snapshot(&snapshot_file, &memory_file, MemBackendType::File, true);
 
     assert_eq!(vmm.lock().unwrap().instance_info.state, VmState::Running);
     vmm.lock().unwrap().stop(FcExitCode::Ok);
@@ -293,6 +307,93 @@ fn test_create_and_load_snapshot() {
     verify_load_snapshot(snapshot_file, memory_file);
 }
 
+#[test]
+fn test_load_snapshot_shared_file() {
+    // Writes to the guest memory land in the memory file with the `SharedFile` backend, but not
+    // with the `File` one.
+    for (backend_type, file_modified) in [
+        (MemBackendType::File, false),
+        (MemBackendType::SharedFile, true),
+    ] {
+        let (snapshot_file, memory_file) = verify_create_snapshot(false);
+        let vmm = load_snapshot(&snapshot_file, &memory_file, backend_type, false);
+        assert_eq!(vmm.lock().unwrap().instance_info.state, VmState::Paused);
+
+        let mut before = [0u8; 8];
+        memory_file.as_file().read_exact_at(&mut before, 0).unwrap();
+        let value = u64::from_le_bytes(before) ^ u64::MAX;
+        {
+            let vmm = vmm.lock().unwrap();
+            let guest_memory = vmm.vm.guest_memory();
+            let start = guest_memory.iter().next().unwrap().start_addr();
+            guest_memory.write_obj(value, start).unwrap();
+        }
+
+        let mut after = [0u8; 8];
+        memory_file.as_file().read_exact_at(&mut after, 0).unwrap();
+        assert_eq!(u64::from_le_bytes(after) == value, file_modified);
+        vmm.lock().unwrap().stop(FcExitCode::Ok);
+    }
+}
+
+// On x86_64, the vCPU of the test kernel exits once its workload completes.
+#[cfg(target_arch = "x86_64")]
+#[test]
+fn test_apply_seccomp_filters_exited_vcpu() {
+    // The event manager doesn't run, so the VMM doesn't handle the exit and the vCPU thread
+    // stays in its exited state.
+    let (vmm, _evmgr) = default_vmm(None);
+    thread::sleep(Duration::from_millis(500));
+
+    let filter = Arc::new(vec![]);
+    let err = vmm
+        .lock()
+        .unwrap()
+        .apply_seccomp_filters(filter.clone(), &filter)
+        .unwrap_err();
+    assert!(
+        matches!(err, ApplySeccompFiltersError::VcpuExited(_)),
+        "{err:?}"
+    );
+    vmm.lock().unwrap().stop(FcExitCode::Ok);
+}
+
+#[test]
+fn test_create_coredump() {
+    let dump_file = TempFile::new().unwrap();
+
+    let (vmm, _) = create_vmm(Some(NOISY_KERNEL_IMAGE), false, true);
+    let mut controller = RuntimeApiController::new(VmResources::default(), vmm.clone());
+
+    // Be sure that the microVM is running.
+    thread::sleep(Duration::from_millis(200));
+
+    controller
+        .handle_request(VmmAction::CreateCoredump(CreateCoredumpParams {
+            dump_path: dump_file.as_path().to_path_buf(),
+        }))
+        .unwrap();
+
+    // The microVM is paused while dumping, then resumed.
+    assert_eq!(vmm.lock().unwrap().instance_info.state, VmState::Running);
+    vmm.lock().unwrap().stop(FcExitCode::Ok);
+
+    let dump = std::fs::read(dump_file.as_path()).unwrap();
+    assert_eq!(&dump[..4], b"\x7fELF");
+    // ET_CORE
+    assert_eq!(u16::from_le_bytes([dump[16], dump[17]]), 4);
+    // The notes and at least one guest memory region.
+    let phnum = u16::from_le_bytes([dump[56], dump[57]]);
+    assert!(phnum >= 2);
+
+    // The last loadable segment ends the file.
+    let phdr = 64 + (usize::from(phnum) - 1) * 56;
+    let field = |offset: usize| {
+        u64::from_le_bytes(dump[phdr + offset..phdr + offset + 8].try_into().unwrap())
+    };
+    assert_eq!(field(8) + field(32), dump.len() as u64);
+}
+
 #[test]
 fn test_snapshot_load_sanity_checks() {
     use vmm::persist::SnapShotStateSanityCheckError;
@@ -349,6 +450,7 @@ fn verify_load_snap_disallowed_after_boot_resources(res: VmmAction, res_name: &s
         enable_diff_snapshots: false,
         resume_vm: false,
         network_overrides: vec![],
+        check_host_compatibility: false,
     });
     let err = preboot_api_controller.handle_preboot_request(req);
     assert!(
@@ -18,7 +18,7 @@ INTEL_TEMPLATES = ["C3", "T2", "T2CL", "T2S"]
 # All existing CPU templates available on AMD
 AMD_TEMPLATES = ["T2A"]
 # All existing CPU templates available on ARM
-ARM_TEMPLATES = ["V1N1"]
+ARM_TEMPLATES = ["V1N1", "V2N1"]
 
 
 def get_supported_cpu_templates():
@@ -34,7 +34,9 @@ def get_supported_cpu_templates():
         case CpuVendor.AMD, CpuModel.AMD_MILAN:
             return AMD_TEMPLATES
         case CpuVendor.ARM, CpuModel.ARM_NEOVERSE_V1 if host_linux >= (6, 1):
-            return ARM_TEMPLATES
+            return ["V1N1"]
+        case CpuVendor.ARM, CpuModel.ARM_NEOVERSE_V2 if host_linux >= (6, 1):
+            return ["V2N1"]
         case _:
             return []
 
@@ -66,6 +68,8 @@ def get_supported_custom_cpu_templates():
             return ["V1N1", "AARCH64_WITH_SVE_AND_PAC"]
         case CpuVendor.ARM, CpuModel.ARM_NEOVERSE_V1:
             return ["AARCH64_WITH_SVE_AND_PAC"]
+        case CpuVendor.ARM, CpuModel.ARM_NEOVERSE_V2 if host_linux >= (6, 1):
+            return ["V2N1", "AARCH64_WITH_SVE_AND_PAC"]
         case CpuVendor.ARM, CpuModel.ARM_NEOVERSE_V2:
             return ["AARCH64_WITH_SVE_AND_PAC"]
         case _:
@@ -134,6 +134,7 @@ def validate_fc_metrics(metrics):
             "process_startup_time_cpu_us",
             "sync_response_fails",
             "sync_vmm_send_timeout_count",
+            "denied_request_count",
         ],
         "balloon": [
             "activate_fails",
@@ -180,6 +181,9 @@ def validate_fc_metrics(metrics):
             "missed_log_count",
             "log_fails",
         ],
+        "memory": [
+            "thp_backed_bytes",
+        ],
         "mmds": [
             "rx_accepted",
             "rx_accepted_err",
@@ -244,6 +248,8 @@ def validate_fc_metrics(metrics):
         "vmm": [
             "device_events",
             "panic_count",
+            "guest_panic_count",
+            "watchdog_expiry_count",
         ],
         "uart": [
             "error_count",
@@ -293,6 +299,22 @@ def validate_fc_metrics(metrics):
             "entropy_rate_limiter_throttled",
             "rate_limiter_event_count",
         ],
+        "console": [
+            "activate_fails",
+            "cfg_fails",
+            "event_fails",
+            "rx_bytes_count",
+            "tx_bytes_count",
+            "host_connections",
+            "host_disconnections",
+        ],
+        "pmem": [
+            "activate_fails",
+            "cfg_fails",
+            "event_fails",
+            "flush_count",
+            "flush_fails",
+        ],
     }
 
     # validate timestamp before jsonschema validation which some more time
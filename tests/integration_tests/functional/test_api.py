@@ -1143,6 +1143,8 @@ def test_get_full_config_after_restoring_snapshot(microvm_factory, uvm_nano):
         "kernel_image_path": uvm_nano.get_jailed_resource(uvm_nano.kernel_file),
         "initrd_path": None,
         "boot_args": None,
+        "firmware_path": None,
+        "firmware_vars_path": None,
     }
 
     # no ipv4 specified during PUT /mmds/config so we expect the default
@@ -1184,6 +1186,8 @@ def test_get_full_config(uvm_plain):
         "boot_args": "",
         "kernel_image_path": f"/{test_microvm.kernel_file.name}",
         "initrd_path": None,
+        "firmware_path": None,
+        "firmware_vars_path": None,
     }
     expected_cfg["drives"] = [
         {
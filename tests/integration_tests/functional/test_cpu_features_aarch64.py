@@ -49,6 +49,8 @@ def test_guest_cpu_features(uvm_any):
             expected_cpu_features = G3_FEATS | G3_SVE_AND_PAC
         case CpuModel.ARM_NEOVERSE_V1, "None":
             expected_cpu_features = G3_FEATS
+        case CpuModel.ARM_NEOVERSE_V2, "V2N1":
+            expected_cpu_features = G2_FEATS
         case CpuModel.ARM_NEOVERSE_V2, "None":
             expected_cpu_features = G4_FEATS
         case CpuModel.ARM_NEOVERSE_V2, "AARCH64_WITH_SVE_AND_PAC":